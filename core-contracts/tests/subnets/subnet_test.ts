@@ -2902,3 +2902,346 @@ Clarinet.test({
     assertEquals(nft_amount, 1);
   },
 });
+
+Clarinet.test({
+  name: "Ensure that a batch of STX withdrawals can be settled with a multi-proof",
+  fn(
+    chain: Chain,
+    accounts: Map<string, Account>,
+    _contracts: Map<string, Contract>
+  ) {
+    // contract deployer
+    const deployer = accounts.get("deployer")!;
+    // valid miner
+    const alice = accounts.get("wallet_1")!;
+    // user
+    const charlie = accounts.get("wallet_3")!;
+
+    const recipient = "ST18F1AHKW194BWQ3CEFDPWVRARA79RBGFEWSDQR8";
+
+    // set alice as a miner
+    let block = chain.mineBlock([
+      Tx.contractCall(
+        config.subnet_contract,
+        "update-miner",
+        [types.principal(alice.address)],
+        deployer.address
+      ),
+    ]);
+    block.receipts[0].result.expectOk().expectBool(true);
+
+    // User should be able to deposit STX
+    block = chain.mineBlock([
+      Tx.contractCall(
+        config.subnet_contract,
+        "deposit-stx",
+        [types.uint(5), types.principal(charlie.address)],
+        charlie.address
+      ),
+    ]);
+    block.receipts[0].result.expectOk().expectBool(true);
+
+    // Same tree as in "Ensure that withdrawals work with a more complex Merkle tree"
+    const root_hash = fromHex(
+      "b02609e344ebb6525c83cd6c2bd3d2a1c73daa2c9344119f036d615b110aad15"
+    );
+    const ft_leaf_hash = fromHex(
+      "be7bcffde781f217150cfc63c88fc2e78bca424b318f5421abdfe96842321e79"
+    );
+    const level_two_sib_hash = fromHex(
+      "8bec7ac5a0ec8eed899374f25fa8c0aa67e852b0c5a99ff6595e589a8d123ea0"
+    );
+
+    const id_header_hash = chain
+      .callReadOnlyFn("test-helpers", "get-id-header-hash", [], alice.address)
+      .result.expectOk()
+      .toString();
+
+    block = chain.mineBlock([
+      Tx.contractCall(
+        config.subnet_contract,
+        "commit-block",
+        [
+          types.buff(new Uint8Array([0, 1, 1, 1, 1])),
+          types.uint(0),
+          id_header_hash,
+          types.uint(chain.blockHeight - 1),
+          types.buff(root_hash),
+        ],
+        alice.address
+      ),
+    ]);
+    block.receipts[0].result
+      .expectOk()
+      .expectBuff(new Uint8Array([0, 1, 1, 1, 1]));
+
+    const withdrawals = types.list([
+      types.tuple({
+        amount: types.uint(1),
+        recipient: types.principal(recipient),
+        "withdrawal-id": types.uint(0),
+        height: types.uint(0),
+      }),
+    ]);
+    const proof_hashes = types.list([
+      types.buff(ft_leaf_hash),
+      types.buff(level_two_sib_hash),
+    ]);
+    const proof_steps = types.list([
+      types.tuple({
+        "use-proof": types.bool(true),
+        "is-left-side": types.bool(false),
+      }),
+      types.tuple({
+        "use-proof": types.bool(true),
+        "is-left-side": types.bool(false),
+      }),
+    ]);
+
+    // A multi-proof with a missing step should fail with ERR_MERKLE_ROOT_DOES_NOT_MATCH
+    block = chain.mineBlock([
+      Tx.contractCall(
+        config.subnet_contract,
+        "withdraw-stx-batch",
+        [
+          withdrawals,
+          types.buff(root_hash),
+          proof_hashes,
+          types.list([
+            types.tuple({
+              "use-proof": types.bool(true),
+              "is-left-side": types.bool(false),
+            }),
+          ]),
+        ],
+        alice.address
+      ),
+    ]);
+    block.receipts[0].result.expectErr().expectInt(7);
+
+    // An empty batch should fail with ERR_EMPTY_BATCH
+    block = chain.mineBlock([
+      Tx.contractCall(
+        config.subnet_contract,
+        "withdraw-stx-batch",
+        [types.list([]), types.buff(root_hash), proof_hashes, proof_steps],
+        alice.address
+      ),
+    ]);
+    block.receipts[0].result.expectErr().expectInt(18);
+
+    block = chain.mineBlock([
+      Tx.contractCall(
+        config.subnet_contract,
+        "withdraw-stx-batch",
+        [withdrawals, types.buff(root_hash), proof_hashes, proof_steps],
+        alice.address
+      ),
+    ]);
+    block.receipts[0].result.expectOk().expectBool(true);
+
+    let stx_assets = chain.getAssetsMaps().assets["STX"];
+    assertEquals(stx_assets[recipient], 1);
+
+    // The same withdrawal can't be settled a second time
+    block = chain.mineBlock([
+      Tx.contractCall(
+        config.subnet_contract,
+        "withdraw-stx-batch",
+        [withdrawals, types.buff(root_hash), proof_hashes, proof_steps],
+        alice.address
+      ),
+    ]);
+    // should return (err ERR_WITHDRAWAL_ALREADY_PROCESSED)
+    block.receipts[0].result.expectErr().expectInt(9);
+  },
+});
+
+Clarinet.test({
+  name: "Ensure that only registered source subnets can deposit STX from another subnet",
+  fn(
+    chain: Chain,
+    accounts: Map<string, Account>,
+    _contracts: Map<string, Contract>
+  ) {
+    // contract deployer and default admin
+    const deployer = accounts.get("deployer")!;
+    const alice = accounts.get("wallet_1")!;
+    const bob = accounts.get("wallet_2")!;
+
+    // Only the admin can register a source subnet
+    let block = chain.mineBlock([
+      Tx.contractCall(
+        config.subnet_contract,
+        "register-source-subnet",
+        [types.principal(alice.address)],
+        bob.address
+      ),
+    ]);
+    // should return (err ERR_UNAUTHORIZED)
+    block.receipts[0].result.expectErr().expectInt(17);
+
+    // A caller that is not a registered source subnet can't credit STX
+    block = chain.mineBlock([
+      Tx.contractCall(
+        config.subnet_contract,
+        "deposit-stx-from-subnet",
+        [types.uint(5000), types.principal(bob.address)],
+        alice.address
+      ),
+    ]);
+    // should return (err ERR_UNAUTHORIZED)
+    block.receipts[0].result.expectErr().expectInt(17);
+
+    block = chain.mineBlock([
+      Tx.contractCall(
+        config.subnet_contract,
+        "register-source-subnet",
+        [types.principal(alice.address)],
+        deployer.address
+      ),
+    ]);
+    block.receipts[0].result.expectOk().expectBool(true);
+
+    // Once registered, the caller can credit STX on this subnet
+    block = chain.mineBlock([
+      Tx.contractCall(
+        config.subnet_contract,
+        "deposit-stx-from-subnet",
+        [types.uint(5000), types.principal(bob.address)],
+        alice.address
+      ),
+    ]);
+    block.receipts[0].result.expectOk().expectBool(true);
+  },
+});
+
+Clarinet.test({
+  name: "Ensure that the recipient of a subnet-to-subnet STX withdrawal can withdraw it on L1 instead",
+  fn(
+    chain: Chain,
+    accounts: Map<string, Account>,
+    _contracts: Map<string, Contract>
+  ) {
+    // contract deployer
+    const deployer = accounts.get("deployer")!;
+    // valid miner
+    const alice = accounts.get("wallet_1")!;
+    // user
+    const bob = accounts.get("wallet_2")!;
+    const charlie = accounts.get("wallet_3")!;
+
+    // a destination that never registered this contract as a source subnet
+    const destination = `${deployer.address}.unregistered-subnet`;
+
+    // set alice as a miner
+    let block = chain.mineBlock([
+      Tx.contractCall(
+        config.subnet_contract,
+        "update-miner",
+        [types.principal(alice.address)],
+        deployer.address
+      ),
+    ]);
+    block.receipts[0].result.expectOk().expectBool(true);
+
+    block = chain.mineBlock([
+      Tx.contractCall(
+        config.subnet_contract,
+        "deposit-stx",
+        [types.uint(5), types.principal(charlie.address)],
+        charlie.address
+      ),
+    ]);
+    block.receipts[0].result.expectOk().expectBool(true);
+
+    // the withdrawal is the only leaf of its tree
+    const leaf_hash = chain
+      .callReadOnlyFn(
+        config.subnet_contract,
+        "leaf-hash-withdraw-stx-to-subnet",
+        [
+          types.uint(1),
+          types.principal(charlie.address),
+          types.principal(destination),
+          types.uint(0),
+          types.uint(0),
+        ],
+        alice.address
+      )
+      .result.toString();
+
+    const id_header_hash = chain
+      .callReadOnlyFn("test-helpers", "get-id-header-hash", [], alice.address)
+      .result.expectOk()
+      .toString();
+
+    block = chain.mineBlock([
+      Tx.contractCall(
+        config.subnet_contract,
+        "commit-block",
+        [
+          types.buff(new Uint8Array([0, 1, 1, 1, 1])),
+          types.uint(0),
+          id_header_hash,
+          types.uint(chain.blockHeight - 1),
+          leaf_hash,
+        ],
+        alice.address
+      ),
+    ]);
+    block.receipts[0].result
+      .expectOk()
+      .expectBuff(new Uint8Array([0, 1, 1, 1, 1]));
+
+    const args = [
+      types.principal(destination),
+      types.uint(1),
+      types.principal(charlie.address),
+      types.uint(0),
+      types.uint(0),
+      leaf_hash,
+      leaf_hash,
+      types.list([]),
+    ];
+
+    // Only the recipient can withdraw it on L1
+    block = chain.mineBlock([
+      Tx.contractCall(
+        config.subnet_contract,
+        "withdraw-stx-to-subnet-fallback",
+        args,
+        bob.address
+      ),
+    ]);
+    // should return (err ERR_UNAUTHORIZED)
+    block.receipts[0].result.expectErr().expectInt(17);
+
+    block = chain.mineBlock([
+      Tx.contractCall(
+        config.subnet_contract,
+        "withdraw-stx-to-subnet-fallback",
+        args,
+        charlie.address
+      ),
+    ]);
+    block.receipts[0].result.expectOk().expectBool(true);
+    block.receipts[0].events.expectSTXTransferEvent(
+      1,
+      `${deployer.address}.${config.subnet_contract}`,
+      charlie.address
+    );
+
+    // The withdrawal can't be settled a second time
+    block = chain.mineBlock([
+      Tx.contractCall(
+        config.subnet_contract,
+        "withdraw-stx-to-subnet-fallback",
+        args,
+        charlie.address
+      ),
+    ]);
+    // should return (err ERR_WITHDRAWAL_ALREADY_PROCESSED)
+    block.receipts[0].result.expectErr().expectInt(9);
+  },
+});
@@ -21,6 +21,7 @@
 
 extern crate subnet_lib;
 
+use std::collections::HashMap;
 use std::convert::TryFrom;
 use std::io::prelude::*;
 use std::io::Read;
@@ -38,12 +39,14 @@ use subnet_lib::chainstate::stacks::{
     TransactionSpendingCondition, TransactionVersion, C32_ADDRESS_VERSION_MAINNET_SINGLESIG,
     C32_ADDRESS_VERSION_TESTNET_SINGLESIG,
 };
+use subnet_lib::clarity_vm::verify;
 use subnet_lib::codec::{Error as CodecError, StacksMessageCodec};
 use subnet_lib::core::{
     LAYER_1_CHAIN_ID_MAINNET, LAYER_1_CHAIN_ID_TESTNET, SUBNETS_CLARITY_VERSION,
 };
 use subnet_lib::net::Error as NetError;
-use subnet_lib::types::chainstate::StacksAddress;
+use subnet_lib::types::chainstate::{StacksAddress, TrieHash};
+use subnet_lib::util::hash::Sha512Trunc256Sum;
 use subnet_lib::util::{hash::hex_bytes, hash::to_hex, log, retry::LogReader};
 use subnet_lib::util_lib::strings::StacksString;
 use subnet_lib::vm::{
@@ -68,6 +71,8 @@ This CLI has these methods:
   decode-header      used to decode a hex-encoded Stacks header into a human-readable representation
   decode-block       used to decode a hex-encoded Stacks block into a human-readable representation
   decode-microblock  used to decode a hex-encoded Stacks microblock into a human-readable representation
+  verify-proof       used to verify a MARF proof of Clarity state against a block's state root
+  verify-withdrawal  used to verify a withdrawal's Merkle path against a block's withdrawal root
 
 For usage information on those methods, call `blockstack-cli [method] -h`
 
@@ -170,6 +175,49 @@ raw binary microblock will be read from stdin.
 N.B. Stacks microblocks are not stored as files in the Stacks chainstate -- they are stored in 
 block's sqlite database.";
 
+const VERIFY_PROOF_USAGE: &str = "blockstack-cli (options) verify-proof [proof-hex] [state-root] [key] [value] (--root-to-block [file.json])
+
+The verify-proof command checks a MARF proof, as returned by the node's RPC interface with
+`?proof=1`, that the Clarity database key [key] maps to [value] in the state trie whose root
+is [state-root].  The state root is the `state_index_root` of the block header the proof was
+generated against, which should be checked independently of the node that served the proof.
+
+[key] is the Clarity database key that the node looked up:
+
+   data var:   vm::<contract-id>::1::<var-name>
+               e.g. vm::ST2DS4MSWSGJ3W9FBC6BVT0Y92S345HY8N3T6AV7R.hello-world::1::bar
+   map entry:  vm::<contract-id>::0::<map-name>::<map-key>
+               where <map-key> is the hex serialization of the Clarity map key, without 0x
+
+[value] is the string the Clarity database stores for the key: for data vars and map entries,
+this is the `data` field of the node's response.
+
+Most proofs pass through the tries of ancestor blocks. To check them, supply a JSON file with
+--root-to-block that maps the `state_index_root` of each ancestor to its index block hash:
+
+   { \"<state_index_root>\": \"<index_block_hash>\", ... }
+
+The genesis block's header has no `state_index_root`. For it, use the root of its trie, which
+the node records as the `index_root` of the genesis block.
+
+If the proof is valid, this command outputs `valid` and exits with code 0. Otherwise, it
+exits with code 1.";
+
+const VERIFY_WITHDRAWAL_USAGE: &str = "blockstack-cli (options) verify-withdrawal [withdrawal-root] [withdrawal-leaf-hash] [sibling-hashes] (--withdrawal-key [key-hex])
+
+The verify-withdrawal command checks that a withdrawal's leaf hash and sibling hashes, as
+returned by the node's `/v2/withdrawal/...` endpoints, lead to [withdrawal-root].  The root
+should be the `withdrawal_merkle_root` of the block header the withdrawal was made in, checked
+independently of the node that served the proof.
+
+The root and leaf hash may be given either as 32-byte hex strings or as hex-serialized Clarity
+buffers, and the sibling hashes as the hex-serialized Clarity list in the node's response.  If
+--withdrawal-key is given, the leaf hash is also checked against the hex-serialized withdrawal
+key tuple.
+
+If the proof is valid, this command outputs `valid` and exits with code 0. Otherwise, it
+exits with code 1.";
+
 #[derive(Debug)]
 enum CliError {
     ClarityRuntimeError(RuntimeErrorType),
@@ -772,6 +820,113 @@ fn decode_microblock(args: &[String], _version: TransactionVersion) -> Result<St
     }
 }
 
+/// Remove the option `flag` and its value from `args`, returning the value
+fn take_option(
+    args: &mut Vec<String>,
+    flag: &str,
+    usage: &str,
+) -> Result<Option<String>, CliError> {
+    let idx = match args.iter().position(|x| x == flag) {
+        Some(idx) => idx,
+        None => return Ok(None),
+    };
+    if idx + 1 >= args.len() {
+        return Err(CliError::Message(format!("USAGE:\n {}", usage)));
+    }
+    let value = args.remove(idx + 1);
+    args.remove(idx);
+    Ok(Some(value))
+}
+
+/// Parse a 32-byte hash given either as hex, or as a hex-serialized Clarity `(buff 32)`
+fn parse_hash_arg(arg: &str, name: &str) -> Result<Sha512Trunc256Sum, CliError> {
+    let hex = arg.trim_start_matches("0x");
+    if let Ok(hash) = Sha512Trunc256Sum::from_hex(hex) {
+        return Ok(hash);
+    }
+    let value = Value::try_deserialize_hex_untyped(hex)?;
+    verify::hash_from_clarity_buffer(&value)
+        .ok_or_else(|| CliError::Message(format!("{} is not a 32-byte buffer", name)))
+}
+
+fn verify_proof(args_slice: &[String], _version: TransactionVersion) -> Result<String, CliError> {
+    if args_slice.len() >= 1 && args_slice[0] == "-h" {
+        return Err(CliError::Message(format!(
+            "USAGE:\n {}",
+            VERIFY_PROOF_USAGE
+        )));
+    }
+    let mut args = args_slice.to_vec();
+    let root_to_block_path = take_option(&mut args, "--root-to-block", VERIFY_PROOF_USAGE)?;
+    if args.len() != 4 {
+        return Err(CliError::Message(format!(
+            "USAGE:\n {}",
+            VERIFY_PROOF_USAGE
+        )));
+    }
+
+    let proof_bytes = hex_bytes(args[0].trim_start_matches("0x"))?;
+    let state_root = TrieHash::from_hex(args[1].trim_start_matches("0x"))?;
+    let key = &args[2];
+    let value = args[3].trim_start_matches("0x");
+
+    let root_to_block = match root_to_block_path {
+        Some(path) => verify::parse_root_to_block(&fs::read_to_string(&path)?)
+            .map_err(|e| CliError::Message(e))?,
+        None => HashMap::new(),
+    };
+
+    if verify::verify_state_proof(&proof_bytes, key, value, &state_root, &root_to_block)? {
+        Ok("valid".into())
+    } else {
+        Err(CliError::Message("Invalid proof".into()))
+    }
+}
+
+fn verify_withdrawal(
+    args_slice: &[String],
+    _version: TransactionVersion,
+) -> Result<String, CliError> {
+    if args_slice.len() >= 1 && args_slice[0] == "-h" {
+        return Err(CliError::Message(format!(
+            "USAGE:\n {}",
+            VERIFY_WITHDRAWAL_USAGE
+        )));
+    }
+    let mut args = args_slice.to_vec();
+    let withdrawal_key = take_option(&mut args, "--withdrawal-key", VERIFY_WITHDRAWAL_USAGE)?;
+    if args.len() != 3 {
+        return Err(CliError::Message(format!(
+            "USAGE:\n {}",
+            VERIFY_WITHDRAWAL_USAGE
+        )));
+    }
+
+    let withdrawal_root = parse_hash_arg(&args[0], "withdrawal-root")?;
+    let leaf_hash = parse_hash_arg(&args[1], "withdrawal-leaf-hash")?;
+    let sibling_hashes = Value::try_deserialize_hex_untyped(args[2].trim_start_matches("0x"))?;
+    let path = verify::clarity_list_to_merkle_path(&sibling_hashes)
+        .ok_or_else(|| CliError::Message("sibling-hashes is not a Merkle path".into()))?;
+
+    if let Some(key_hex) = withdrawal_key {
+        let key = Value::try_deserialize_hex_untyped(key_hex.trim_start_matches("0x"))?;
+        if verify::withdrawal_leaf_hash(&key) != leaf_hash {
+            return Err(CliError::Message(
+                "Withdrawal leaf hash does not match the withdrawal key".into(),
+            ));
+        }
+    }
+
+    if verify::verify_withdrawal_path(&leaf_hash, &path, &withdrawal_root) {
+        Ok("valid".into())
+    } else {
+        Err(CliError::Message(format!(
+            "Invalid proof: it leads to withdrawal root {}",
+            verify::compute_withdrawal_root(&leaf_hash, &path).to_hex()
+        )))
+    }
+}
+
 fn main() {
     let mut argv: Vec<String> = env::args().collect();
 
@@ -813,6 +968,8 @@ fn main_handler(mut argv: Vec<String>) -> Result<String, CliError> {
             "decode-header" => decode_header(args, tx_version),
             "decode-block" => decode_block(args, tx_version),
             "decode-microblock" => decode_microblock(args, tx_version),
+            "verify-proof" => verify_proof(args, tx_version),
+            "verify-withdrawal" => verify_withdrawal(args, tx_version),
             _ => Err(CliError::Usage),
         }
     } else {
@@ -1158,4 +1315,76 @@ mod test {
         let result = main_handler(to_string_vec(&header_args)).unwrap();
         eprintln!("result:\n{}", result);
     }
+
+    #[test]
+    fn simple_verify_withdrawal() {
+        use subnet_lib::clarity_vm::withdrawal::{buffer_from_hash, merkle_path_to_clarity_list};
+        use subnet_lib::util::hash::MerkleTree;
+
+        let keys: Vec<Value> = (0..3).map(|i| Value::UInt(i)).collect();
+        let leaves: Vec<Vec<u8>> = keys.iter().map(|key| key.serialize_to_vec()).collect();
+        let tree = MerkleTree::<Sha512Trunc256Sum>::new(&leaves);
+
+        let root = tree.root();
+        let leaf_hash = verify::withdrawal_leaf_hash(&keys[1]);
+        let path = tree.path(&leaves[1]).unwrap();
+        let sibling_hashes = merkle_path_to_clarity_list(path).unwrap();
+
+        // as served by the node, or with the root from the block header
+        for root_arg in [
+            format!("0x{}", buffer_from_hash(root.clone()).serialize_to_hex()),
+            root.to_hex(),
+        ] {
+            let args = [
+                "verify-withdrawal".to_string(),
+                root_arg,
+                format!(
+                    "0x{}",
+                    buffer_from_hash(leaf_hash.clone()).serialize_to_hex()
+                ),
+                format!("0x{}", sibling_hashes.serialize_to_hex()),
+                "--withdrawal-key".to_string(),
+                keys[1].serialize_to_hex(),
+            ];
+            assert_eq!(main_handler(args.to_vec()).unwrap(), "valid");
+        }
+
+        // wrong root
+        let args = [
+            "verify-withdrawal".to_string(),
+            Sha512Trunc256Sum([0u8; 32]).to_hex(),
+            leaf_hash.to_hex(),
+            sibling_hashes.serialize_to_hex(),
+        ];
+        assert!(format!("{}", main_handler(args.to_vec()).unwrap_err()).contains("Invalid proof"));
+
+        // key doesn't match the leaf
+        let args = [
+            "verify-withdrawal".to_string(),
+            root.to_hex(),
+            leaf_hash.to_hex(),
+            sibling_hashes.serialize_to_hex(),
+            "--withdrawal-key".to_string(),
+            keys[0].serialize_to_hex(),
+        ];
+        assert!(format!("{}", main_handler(args.to_vec()).unwrap_err()).contains("does not match"));
+    }
+
+    #[test]
+    fn simple_verify_proof() {
+        assert!(main_handler(to_string_vec(&["verify-proof", "-h"])).is_err());
+        assert!(main_handler(to_string_vec(&["verify-proof", "00"])).is_err());
+
+        // a proof that doesn't decode
+        let args = [
+            "verify-proof",
+            "ffffffffffff",
+            "0000000000000000000000000000000000000000000000000000000000000000",
+            "key",
+            "value",
+        ];
+        assert!(
+            format!("{}", main_handler(to_string_vec(&args)).unwrap_err()).contains("CodecError")
+        );
+    }
 }
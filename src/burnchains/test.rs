@@ -463,7 +463,6 @@ impl TestBurnchainBlock {
                 burnchain,
                 blockstack_txs,
                 None,
-                None,
                 0,
             )
             .unwrap();
@@ -472,11 +471,11 @@ impl TestBurnchainBlock {
         new_snapshot.0
     }
 
-    pub fn mine_pox<'a, T: BlockEventDispatcher, N: CoordinatorNotices, R: RewardSetProvider>(
+    pub fn mine_pox<'a, T: BlockEventDispatcher, N: CoordinatorNotices>(
         &self,
         db: &mut SortitionDB,
         burnchain: &Burnchain,
-        coord: &mut ChainsCoordinator<'a, T, N, R, (), ()>,
+        coord: &mut ChainsCoordinator<'a, T, N, (), ()>,
     ) -> BlockSnapshot {
         let block_hash = bhh_from_test_data(
             self.block_height,
@@ -585,16 +584,11 @@ impl TestBurnchainFork {
         snapshot
     }
 
-    pub fn mine_pending_blocks_pox<
-        'a,
-        T: BlockEventDispatcher,
-        N: CoordinatorNotices,
-        R: RewardSetProvider,
-    >(
+    pub fn mine_pending_blocks_pox<'a, T: BlockEventDispatcher, N: CoordinatorNotices>(
         &mut self,
         db: &mut SortitionDB,
         burnchain: &Burnchain,
-        coord: &mut ChainsCoordinator<'a, T, N, R, (), ()>,
+        coord: &mut ChainsCoordinator<'a, T, N, (), ()>,
     ) -> BlockSnapshot {
         let mut snapshot = {
             let ic = db.index_conn();
@@ -26,7 +26,6 @@ use crate::chainstate::burn::operations::{
     BlockstackOperationType, Error as OpError,
 };
 use crate::chainstate::burn::BlockSnapshot;
-use crate::chainstate::coordinator::RewardCycleInfo;
 use crate::chainstate::stacks::db::StacksChainState;
 use crate::chainstate::stacks::index::ClarityMarfTrieId;
 use crate::chainstate::stacks::index::{
@@ -166,7 +165,6 @@ impl<'a> SortitionHandleTx<'a> {
         parent_snapshot: &BlockSnapshot,
         block_header: &BurnchainBlockHeader,
         this_block_ops: &Vec<BlockstackOperationType>,
-        _next_pox_info: Option<RewardCycleInfo>,
         reward_info: Option<&RewardSetInfo>,
         initial_mining_bonus_ustx: u128,
     ) -> Result<(BlockSnapshot, BurnchainStateTransition), BurnchainError> {
@@ -294,7 +292,6 @@ impl<'a> SortitionHandleTx<'a> {
         parent_snapshot: &BlockSnapshot,
         block_header: &BurnchainBlockHeader,
         mut blockstack_txs: Vec<BlockstackOperationType>,
-        next_pox_info: Option<RewardCycleInfo>,
         reward_set_info: Option<&RewardSetInfo>,
         initial_mining_bonus_ustx: u128,
     ) -> Result<(BlockSnapshot, BurnchainStateTransition), BurnchainError> {
@@ -333,7 +330,6 @@ impl<'a> SortitionHandleTx<'a> {
                 parent_snapshot,
                 block_header,
                 &blockstack_txs,
-                next_pox_info,
                 reward_set_info,
                 initial_mining_bonus_ustx,
             )
@@ -357,7 +353,6 @@ impl<'a> SortitionHandleTx<'a> {
         this_block_header: &BurnchainBlockHeader,
         burnchain: &Burnchain,
         blockstack_txs: Vec<BlockstackOperationType>,
-        next_pox_info: Option<RewardCycleInfo>,
         reward_set_info: Option<&RewardSetInfo>,
         initial_mining_bonus_ustx: u128,
     ) -> Result<(BlockSnapshot, BurnchainStateTransition), BurnchainError> {
@@ -382,7 +377,6 @@ impl<'a> SortitionHandleTx<'a> {
             &parent_snapshot,
             &this_block_header,
             blockstack_txs,
-            next_pox_info,
             reward_set_info,
             initial_mining_bonus_ustx,
         )?;
@@ -458,7 +452,6 @@ mod tests {
                     BlockstackOperationType::DepositStx(deposit.clone()),
                 ],
                 None,
-                None,
                 0,
             )
             .unwrap()
@@ -486,7 +479,6 @@ mod tests {
                     BlockstackOperationType::LeaderBlockCommit(block_commit.clone()),
                 ],
                 None,
-                None,
                 0,
             )
             .unwrap()
@@ -536,7 +528,6 @@ mod tests {
                     &next_block_header,
                     vec![BlockstackOperationType::LeaderBlockCommit(block_commit)],
                     None,
-                    None,
                     0,
                 )
                 .unwrap();
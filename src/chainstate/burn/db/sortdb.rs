@@ -49,9 +49,7 @@ use crate::chainstate::burn::operations::{
 };
 use crate::chainstate::burn::Opcodes;
 use crate::chainstate::burn::{BlockSnapshot, ConsensusHash, OpsHash, SortitionHash};
-use crate::chainstate::coordinator::{
-    Error as CoordinatorError, PoxAnchorBlockStatus, RewardCycleInfo,
-};
+use crate::chainstate::coordinator::Error as CoordinatorError;
 use crate::chainstate::stacks::db::{StacksChainState, StacksHeaderInfo};
 use crate::chainstate::stacks::index::marf::MARFOpenOpts;
 use crate::chainstate::stacks::index::marf::MarfConnection;
@@ -1070,25 +1068,6 @@ impl<'a> SortitionHandleTx<'a> {
         )
     }
 
-    /// Get the expected PoX recipients (reward set) for the next sortition, either by querying information
-    ///  from the current reward cycle, or if `next_pox_info` is provided, by querying information
-    ///  for the next reward cycle.
-    ///
-    /// Returns None if:
-    ///   * The reward cycle had an anchor block, but it isn't known by this node.
-    ///   * The reward cycle did not have anchor block
-    ///   * The block is in the prepare phase of a reward cycle, in which case miners must burn
-    ///   * The Stacking recipient set is empty (either because this reward cycle has already exhausted the set of addresses or because no one ever Stacked).
-    fn pick_recipients(
-        &mut self,
-        _burnchain: &Burnchain,
-        _block_height: u64,
-        _reward_set_vrf_seed: &SortitionHash,
-        _next_pox_info: Option<&RewardCycleInfo>,
-    ) -> Result<Option<RewardSetInfo>, BurnchainError> {
-        Ok(None)
-    }
-
     fn get_reward_set_entry(&mut self, entry_ix: u16) -> Result<StacksAddress, db_error> {
         let chain_tip = self.context.chain_tip.clone();
         let entry_str = self
@@ -2205,21 +2184,6 @@ impl SortitionDB {
             .map_err(db_error::from)
     }
 
-    /// Is the given block an expected PoX anchor in this sortition history?
-    ///  if so, return the Stacks block hash
-    pub fn is_stacks_block_pox_anchor(
-        &mut self,
-        block: &BlockHeaderHash,
-        sortition_tip: &SortitionId,
-    ) -> Result<Option<BlockHeaderHash>, BurnchainError> {
-        let handle = self.index_handle(sortition_tip);
-        let expects_block_as_anchor = handle
-            .get_tip_indexed(&db_keys::pox_anchor_to_prepare_end(block))?
-            .map(|_| block.clone());
-
-        return Ok(expects_block_as_anchor);
-    }
-
     fn parse_last_anchor_block_hash(s: Option<String>) -> Option<BlockHeaderHash> {
         s.map(|s| {
             if s == "" {
@@ -2255,28 +2219,6 @@ impl SortitionDB {
         Ok(())
     }
 
-    /// Get the last sortition in the prepare phase that chose a particular Stacks block as the anchor,
-    ///   or if the anchor is not expected, return None
-    pub fn get_prepare_end_for(
-        &mut self,
-        sortition_tip: &SortitionId,
-        anchor: &BlockHeaderHash,
-    ) -> Result<Option<BlockSnapshot>, BurnchainError> {
-        let handle = self.index_handle(sortition_tip);
-        let prepare_end_sortid = match handle
-            .get_tip_indexed(&db_keys::pox_anchor_to_prepare_end(anchor))?
-        {
-            Some(s) => SortitionId::from_hex(&s).expect("CORRUPTION: DB stored bad sortition ID"),
-            None => return Ok(None),
-        };
-        let snapshot =
-            SortitionDB::get_block_snapshot(self.conn(), &prepare_end_sortid)?.expect(&format!(
-            "BUG: Sortition ID for prepare phase end is known, but no BlockSnapshot is stored: {}",
-            &prepare_end_sortid
-        ));
-        Ok(Some(snapshot))
-    }
-
     pub fn get_sortition_result(
         &self,
         id: &SortitionId,
@@ -2298,7 +2240,6 @@ impl SortitionDB {
     /// * `ops` - the parsed blockstack operations (will be validated in this function)
     /// * `burnchain` - a reference to the burnchain information struct
     /// * `from_tip` - tip of the "sortition chain" that is being built on
-    /// * `next_pox_info` - iff this sortition is the first block in a reward cycle, this should be Some
     /// * `announce_to` - a function that will be invoked with the calculated reward set before this method
     ///                   commits its results. This is used to post the calculated reward set to an event observer.
     pub fn evaluate_sortition<F: FnOnce(Option<RewardSetInfo>) -> ()>(
@@ -2307,7 +2248,6 @@ impl SortitionDB {
         ops: Vec<BlockstackOperationType>,
         burnchain: &Burnchain,
         from_tip: &SortitionId,
-        next_pox_info: Option<RewardCycleInfo>,
         announce_to: F,
     ) -> Result<(BlockSnapshot, BurnchainStateTransition), BurnchainError> {
         let is_parent_first_block = burn_header.block_height == self.first_block_height + 1;
@@ -2354,7 +2294,6 @@ impl SortitionDB {
             burn_header,
             burnchain,
             ops,
-            next_pox_info,
             reward_set_info.as_ref(),
             initial_mining_bonus,
         )?;
@@ -2374,10 +2313,9 @@ impl SortitionDB {
     pub fn test_get_next_block_recipients(
         &mut self,
         burnchain: &Burnchain,
-        next_pox_info: Option<&RewardCycleInfo>,
     ) -> Result<Option<RewardSetInfo>, BurnchainError> {
         let parent_snapshot = SortitionDB::get_canonical_burn_chain_tip(self.conn())?;
-        self.get_next_block_recipients(burnchain, &parent_snapshot, next_pox_info)
+        self.get_next_block_recipients(burnchain, &parent_snapshot)
     }
 
     /// There are never any block recipients. This comes from mainchain code.
@@ -2387,7 +2325,6 @@ impl SortitionDB {
         &mut self,
         burnchain: &Burnchain,
         parent_snapshot: &BlockSnapshot,
-        next_pox_info: Option<&RewardCycleInfo>,
     ) -> Result<Option<RewardSetInfo>, BurnchainError> {
         Ok(None)
     }
@@ -219,7 +219,7 @@ fn qc_serialize<S: serde::Serializer>(
     s.serialize_str(inst.as_str())
 }
 
-fn qc_deserialize<'de, D: serde::Deserializer<'de>>(
+pub(crate) fn qc_deserialize<'de, D: serde::Deserializer<'de>>(
     d: D,
 ) -> Result<QualifiedContractIdentifier, D::Error> {
     let inst_str = String::deserialize(d)?;
@@ -231,7 +231,9 @@ fn pd_serialize<S: serde::Serializer>(pd: &PrincipalData, s: S) -> Result<S::Ok,
     s.serialize_str(inst.as_str())
 }
 
-fn pd_deserialize<'de, D: serde::Deserializer<'de>>(d: D) -> Result<PrincipalData, D::Error> {
+pub(crate) fn pd_deserialize<'de, D: serde::Deserializer<'de>>(
+    d: D,
+) -> Result<PrincipalData, D::Error> {
     let inst_str = String::deserialize(d)?;
     PrincipalData::parse(&inst_str).map_err(serde::de::Error::custom)
 }
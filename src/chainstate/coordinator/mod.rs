@@ -19,7 +19,7 @@ use std::convert::{TryFrom, TryInto};
 use std::fs;
 use std::path::PathBuf;
 use std::sync::mpsc::SyncSender;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 
 use crate::burnchains::{
     db::{BurnchainBlockData, BurnchainDB},
@@ -44,6 +44,7 @@ use crate::chainstate::stacks::{
 use crate::core::StacksEpoch;
 use crate::monitoring::{
     increment_contract_calls_processed, increment_stx_blocks_processed_counter,
+    observe_burn_block_processing_time,
 };
 use crate::net::atlas::{AtlasConfig, AttachmentInstance};
 use crate::util_lib::db::Error as DBError;
@@ -54,9 +55,7 @@ use clarity::vm::{
 };
 
 use crate::cost_estimates::{CostEstimator, FeeEstimator, PessimisticEstimator};
-use crate::types::chainstate::{
-    BlockHeaderHash, BurnchainHeaderHash, SortitionId, StacksAddress, StacksBlockId,
-};
+use crate::types::chainstate::{BurnchainHeaderHash, SortitionId, StacksAddress, StacksBlockId};
 use clarity::vm::database::BurnStateDB;
 
 use crate::chainstate::stacks::index::marf::MARFOpenOpts;
@@ -67,53 +66,6 @@ pub mod comm;
 #[cfg(test)]
 pub mod tests;
 
-/// The 3 different states for the current
-///  reward cycle's relationship to its PoX anchor
-#[derive(Debug, PartialEq)]
-pub enum PoxAnchorBlockStatus {
-    SelectedAndKnown(BlockHeaderHash, Vec<StacksAddress>),
-    SelectedAndUnknown(BlockHeaderHash),
-    NotSelected,
-}
-
-#[derive(Debug, PartialEq)]
-pub struct RewardCycleInfo {
-    pub anchor_status: PoxAnchorBlockStatus,
-}
-
-impl RewardCycleInfo {
-    pub fn selected_anchor_block(&self) -> Option<&BlockHeaderHash> {
-        use self::PoxAnchorBlockStatus::*;
-        match self.anchor_status {
-            SelectedAndUnknown(ref block) | SelectedAndKnown(ref block, _) => Some(block),
-            NotSelected => None,
-        }
-    }
-    pub fn is_reward_info_known(&self) -> bool {
-        use self::PoxAnchorBlockStatus::*;
-        match self.anchor_status {
-            SelectedAndUnknown(_) => false,
-            SelectedAndKnown(_, _) | NotSelected => true,
-        }
-    }
-    pub fn known_selected_anchor_block(&self) -> Option<&Vec<StacksAddress>> {
-        use self::PoxAnchorBlockStatus::*;
-        match self.anchor_status {
-            SelectedAndUnknown(_) => None,
-            SelectedAndKnown(_, ref reward_set) => Some(reward_set),
-            NotSelected => None,
-        }
-    }
-    pub fn known_selected_anchor_block_owned(self) -> Option<Vec<StacksAddress>> {
-        use self::PoxAnchorBlockStatus::*;
-        match self.anchor_status {
-            SelectedAndUnknown(_) => None,
-            SelectedAndKnown(_, reward_set) => Some(reward_set),
-            NotSelected => None,
-        }
-    }
-}
-
 pub trait BlockEventDispatcher {
     fn announce_block(
         &self,
@@ -151,7 +103,6 @@ pub struct ChainsCoordinator<
     'a,
     T: BlockEventDispatcher,
     N: CoordinatorNotices,
-    R: RewardSetProvider,
     CE: CostEstimator + ?Sized,
     FE: FeeEstimator + ?Sized,
 > {
@@ -165,7 +116,6 @@ pub struct ChainsCoordinator<
     dispatcher: Option<&'a T>,
     cost_estimator: Option<&'a mut CE>,
     fee_estimator: Option<&'a mut FE>,
-    reward_set_provider: R,
     notifier: N,
     atlas_config: AtlasConfig,
 }
@@ -179,7 +129,6 @@ pub enum Error {
     NoSortitions,
     FailedToProcessSortition(BurnchainError),
     DBError(DBError),
-    NotPrepareEndBlock,
 }
 
 impl From<BurnchainError> for Error {
@@ -200,36 +149,8 @@ impl From<DBError> for Error {
     }
 }
 
-pub trait RewardSetProvider {
-    fn get_reward_set(
-        &self,
-        current_burn_height: u64,
-        chainstate: &mut StacksChainState,
-        burnchain: &Burnchain,
-        sortdb: &SortitionDB,
-        block_id: &StacksBlockId,
-    ) -> Result<Vec<StacksAddress>, Error>;
-}
-
-/// The reward set is always empty. This is a holdover from the mainchain reward provider.
-/// TODO: Delete this class once baseline subnet system is stable.
-pub struct OnChainRewardSetProvider();
-impl RewardSetProvider for OnChainRewardSetProvider {
-    #[allow(unused_variables)]
-    fn get_reward_set(
-        &self,
-        current_burn_height: u64,
-        chainstate: &mut StacksChainState,
-        burnchain: &Burnchain,
-        sortdb: &SortitionDB,
-        block_id: &StacksBlockId,
-    ) -> Result<Vec<StacksAddress>, Error> {
-        Ok(vec![])
-    }
-}
-
 impl<'a, T: BlockEventDispatcher, CE: CostEstimator + ?Sized, FE: FeeEstimator + ?Sized>
-    ChainsCoordinator<'a, T, ArcCounterCoordinatorNotices, OnChainRewardSetProvider, CE, FE>
+    ChainsCoordinator<'a, T, ArcCounterCoordinatorNotices, CE, FE>
 {
     pub fn run(
         chain_state_db: StacksChainState,
@@ -268,7 +189,6 @@ impl<'a, T: BlockEventDispatcher, CE: CostEstimator + ?Sized, FE: FeeEstimator +
             attachments_tx,
             dispatcher: Some(dispatcher),
             notifier: arc_notices,
-            reward_set_provider: OnChainRewardSetProvider(),
             cost_estimator,
             fee_estimator,
             atlas_config,
@@ -299,23 +219,15 @@ impl<'a, T: BlockEventDispatcher, CE: CostEstimator + ?Sized, FE: FeeEstimator +
     }
 }
 
-impl<'a, T: BlockEventDispatcher, U: RewardSetProvider> ChainsCoordinator<'a, T, (), U, (), ()> {
+impl<'a, T: BlockEventDispatcher> ChainsCoordinator<'a, T, (), (), ()> {
     #[cfg(test)]
     pub fn test_new(
         burnchain: &Burnchain,
         chain_id: u32,
         path: &str,
-        reward_set_provider: U,
         attachments_tx: SyncSender<HashSet<AttachmentInstance>>,
-    ) -> ChainsCoordinator<'a, T, (), U, (), ()> {
-        ChainsCoordinator::test_new_with_observer(
-            burnchain,
-            chain_id,
-            path,
-            reward_set_provider,
-            attachments_tx,
-            None,
-        )
+    ) -> ChainsCoordinator<'a, T, (), (), ()> {
+        ChainsCoordinator::test_new_with_observer(burnchain, chain_id, path, attachments_tx, None)
     }
 
     #[cfg(test)]
@@ -323,10 +235,9 @@ impl<'a, T: BlockEventDispatcher, U: RewardSetProvider> ChainsCoordinator<'a, T,
         burnchain: &Burnchain,
         chain_id: u32,
         path: &str,
-        reward_set_provider: U,
         attachments_tx: SyncSender<HashSet<AttachmentInstance>>,
         dispatcher: Option<&'a T>,
-    ) -> ChainsCoordinator<'a, T, (), U, (), ()> {
+    ) -> ChainsCoordinator<'a, T, (), (), ()> {
         let burnchain = burnchain.clone();
 
         let mut boot_data = ChainStateBootData::new(&burnchain, vec![], None);
@@ -355,51 +266,17 @@ impl<'a, T: BlockEventDispatcher, U: RewardSetProvider> ChainsCoordinator<'a, T,
             dispatcher,
             cost_estimator: None,
             fee_estimator: None,
-            reward_set_provider,
             notifier: (),
             attachments_tx,
             atlas_config: AtlasConfig::default(false),
         }
     }
-}
-
-pub fn get_next_recipients<U: RewardSetProvider>(
-    sortition_tip: &BlockSnapshot,
-    chain_state: &mut StacksChainState,
-    sort_db: &mut SortitionDB,
-    burnchain: &Burnchain,
-    provider: &U,
-) -> Result<Option<RewardSetInfo>, Error> {
-    let reward_cycle_info = get_reward_cycle_info(
-        sortition_tip.block_height + 1,
-        &sortition_tip.burn_header_hash,
-        &sortition_tip.sortition_id,
-        burnchain,
-        chain_state,
-        sort_db,
-        provider,
-    )?;
-    sort_db
-        .get_next_block_recipients(burnchain, sortition_tip, reward_cycle_info.as_ref())
-        .map_err(|e| Error::from(e))
-}
 
-/// returns None if this burnchain block is _not_ the start of a reward cycle
-///         otherwise, returns the required reward cycle info for this burnchain block
-///                     in our current sortition view:
-///           * PoX anchor block
-///           * Was PoX anchor block known?
-#[allow(unused_variables)]
-pub fn get_reward_cycle_info<U: RewardSetProvider>(
-    burn_height: u64,
-    parent_bhh: &BurnchainHeaderHash,
-    sortition_tip: &SortitionId,
-    burnchain: &Burnchain,
-    chain_state: &mut StacksChainState,
-    sort_db: &SortitionDB,
-    provider: &U,
-) -> Result<Option<RewardCycleInfo>, Error> {
-    Ok(None)
+    /// Record the receipts of the blocks this coordinator processes in the transaction index
+    #[cfg(test)]
+    pub fn set_txindex(&mut self, txindex: bool) {
+        self.chain_state_db.txindex = txindex;
+    }
 }
 
 struct PaidRewards {
@@ -439,21 +316,130 @@ fn dispatcher_announce_burn_ops<T: BlockEventDispatcher>(
     );
 }
 
+/// Announce an already-evaluated burnchain block to `dispatcher` again, with the same payload
+/// `handle_new_burnchain_block` announced when its sortition was first processed. Sortitions in
+/// a subnet never carry reward set information, so there are no recipients to report.
+pub fn dispatcher_replay_burn_block<T: BlockEventDispatcher>(
+    dispatcher: &T,
+    snapshot: &BlockSnapshot,
+) {
+    let paid_rewards = calculate_paid_rewards(&[]);
+    dispatcher.announce_burn_block(
+        &snapshot.burn_header_hash,
+        snapshot.block_height,
+        paid_rewards.pox,
+        paid_rewards.burns,
+        vec![],
+    );
+}
+
+/// Announce the canonical Stacks blocks at or above `from_height` to `dispatcher` again, and the
+/// burnchain blocks from the one that elected the first of them up to the burnchain tip, in the
+/// order the coordinator first announced them. Each Stacks block is re-executed to rebuild its
+/// receipts (see `StacksChainState::replay_block`), so nothing is written to the chain state.
+///
+/// Returns the number of Stacks blocks announced.
+pub fn replay_chain_events<T: BlockEventDispatcher>(
+    chainstate: &mut StacksChainState,
+    sortdb: &SortitionDB,
+    dispatcher: &T,
+    from_height: u64,
+) -> Result<u64, ChainstateError> {
+    // the boot block at height 0 was never appended, so there is nothing to replay for it
+    let from_height = from_height.max(1);
+
+    let (tip_consensus_hash, tip_block_hash) =
+        SortitionDB::get_canonical_stacks_chain_tip_hash(sortdb.conn())?;
+    let tip_id = StacksBlockId::new(&tip_consensus_hash, &tip_block_hash);
+    let headers = match StacksChainState::get_stacks_block_header_info_by_index_block_hash(
+        chainstate.db(),
+        &tip_id,
+    )? {
+        Some(tip_header) => {
+            let mut headers =
+                StacksChainState::get_ancestors_headers(chainstate.db(), tip_header, from_height)?;
+            headers.reverse();
+            headers
+        }
+        None => vec![],
+    };
+
+    let burn_tip = SortitionDB::get_canonical_burn_chain_tip(sortdb.conn())?;
+    let mut next_burn_height = match headers.first() {
+        Some(header) => header.burn_header_height as u64,
+        None => burn_tip.block_height + 1,
+    };
+
+    let mut replayed = 0;
+    for header in headers.into_iter() {
+        // announce the burnchain blocks up to the one that elected this block first
+        while next_burn_height <= header.burn_header_height as u64 {
+            replay_burn_block_at(dispatcher, sortdb, next_burn_height, &burn_tip.sortition_id)?;
+            next_burn_height += 1;
+        }
+
+        let index_block_hash = header.index_block_hash();
+        let (block, epoch_receipt) = chainstate
+            .replay_block(sortdb, &index_block_hash)?
+            .ok_or(ChainstateError::NoSuchBlockError)?;
+        let parent_id = StacksChainState::get_parent_block_id(chainstate.db(), &index_block_hash)?
+            .ok_or(ChainstateError::NoSuchBlockError)?;
+        let winner_txid =
+            SortitionDB::get_block_snapshot_consensus(sortdb.conn(), &header.consensus_hash)?
+                .ok_or(ChainstateError::NoSuchBlockError)?
+                .winning_block_txid;
+
+        dispatcher.announce_block(
+            &block,
+            &epoch_receipt.header,
+            &epoch_receipt.tx_receipts,
+            &parent_id,
+            winner_txid,
+            &epoch_receipt.matured_rewards,
+            epoch_receipt.matured_rewards_info.as_ref(),
+            epoch_receipt.parent_burn_block_hash,
+            epoch_receipt.parent_burn_block_height,
+            epoch_receipt.parent_burn_block_timestamp,
+            &epoch_receipt.anchored_block_cost,
+            &epoch_receipt.parent_microblocks_cost,
+        );
+
+        debug!("Replayed block events";
+               "stacks_height" => header.stacks_block_height,
+               "index_block_hash" => %index_block_hash);
+        replayed += 1;
+    }
+
+    while next_burn_height <= burn_tip.block_height {
+        replay_burn_block_at(dispatcher, sortdb, next_burn_height, &burn_tip.sortition_id)?;
+        next_burn_height += 1;
+    }
+
+    Ok(replayed)
+}
+
+fn replay_burn_block_at<T: BlockEventDispatcher>(
+    dispatcher: &T,
+    sortdb: &SortitionDB,
+    burn_height: u64,
+    burn_tip: &SortitionId,
+) -> Result<(), ChainstateError> {
+    let snapshot = SortitionDB::get_ancestor_snapshot(&sortdb.index_conn(), burn_height, burn_tip)?
+        .ok_or(ChainstateError::NoSuchBlockError)?;
+    dispatcher_replay_burn_block(dispatcher, &snapshot);
+    Ok(())
+}
+
 impl<
         'a,
         T: BlockEventDispatcher,
         N: CoordinatorNotices,
-        U: RewardSetProvider,
         CE: CostEstimator + ?Sized,
         FE: FeeEstimator + ?Sized,
-    > ChainsCoordinator<'a, T, N, U, CE, FE>
+    > ChainsCoordinator<'a, T, N, CE, FE>
 {
     pub fn handle_new_stacks_block(&mut self) -> Result<(), Error> {
-        if let Some(pox_anchor) = self.process_ready_blocks()? {
-            self.process_new_pox_anchor(pox_anchor)
-        } else {
-            Ok(())
-        }
+        self.process_ready_blocks()
     }
 
     pub fn handle_new_burnchain_block(&mut self) -> Result<(), Error> {
@@ -512,6 +498,7 @@ impl<
         );
 
         for unprocessed_block in sortitions_to_process.into_iter() {
+            let start = Instant::now();
             let BurnchainBlockData { header, ops } = unprocessed_block;
 
             // calculate paid rewards during this burnchain block if we announce
@@ -525,9 +512,8 @@ impl<
                 }
             };
 
-            // at this point, we need to figure out if the sortition we are
-            //  about to process is the first block in reward cycle.
-            let reward_cycle_info = self.get_reward_cycle_info(&header)?;
+            // a subnet's sortitions come from L1 block commits, so there is no
+            //  reward cycle (and no PoX anchor block) to compute here.
             // bind a reference here to avoid tripping up the borrow-checker
             let dispatcher_ref = &self.dispatcher;
             let (next_snapshot, _) = self
@@ -537,7 +523,6 @@ impl<
                     ops,
                     &self.burnchain,
                     &last_processed_ancestor,
-                    reward_cycle_info,
                     |reward_set_info| {
                         if let Some(dispatcher) = dispatcher_ref {
                             dispatcher_announce_burn_ops(
@@ -570,49 +555,18 @@ impl<
             //   has moved, so we should move our canonical sortition tip as well.
             self.canonical_sortition_tip = Some(sortition_id.clone());
             last_processed_ancestor = sortition_id;
+            observe_burn_block_processing_time(start.elapsed().as_secs_f64());
 
-            if let Some(pox_anchor) = self.process_ready_blocks()? {
-                return self.process_new_pox_anchor(pox_anchor);
-            }
+            self.process_ready_blocks()?;
         }
 
         Ok(())
     }
 
-    /// returns None if this burnchain block is _not_ the start of a reward cycle
-    ///         otherwise, returns the required reward cycle info for this burnchain block
-    ///                     in our current sortition view:
-    ///           * PoX anchor block
-    ///           * Was PoX anchor block known?
-    pub fn get_reward_cycle_info(
-        &mut self,
-        burn_header: &BurnchainBlockHeader,
-    ) -> Result<Option<RewardCycleInfo>, Error> {
-        let sortition_tip_id = self
-            .canonical_sortition_tip
-            .as_ref()
-            .expect("FATAL: Processing anchor block, but no known sortition tip");
-
-        get_reward_cycle_info(
-            burn_header.block_height,
-            &burn_header.parent_block_hash,
-            sortition_tip_id,
-            &self.burnchain,
-            &mut self.chain_state_db,
-            &self.sortition_db,
-            &self.reward_set_provider,
-        )
-    }
-
-    ///
-    /// Process any ready staging blocks until there are either:
-    ///   * there are no more to process
-    ///   * a PoX anchor block is processed which invalidates the current PoX fork
     ///
-    /// Returns Some(StacksBlockId) if such an anchor block is discovered,
-    ///   otherwise returns None
+    /// Process any ready staging blocks until there are no more to process
     ///
-    fn process_ready_blocks(&mut self) -> Result<Option<BlockHeaderHash>, Error> {
+    fn process_ready_blocks(&mut self) -> Result<(), Error> {
         let canonical_sortition_tip = self.canonical_sortition_tip.as_ref().expect(
             "FAIL: processing a new Stacks block, but don't have a canonical sortition tip",
         );
@@ -726,15 +680,15 @@ impl<
                         }
                     }
 
-                    // if, just after processing the block, we _know_ that this block is a pox anchor, that means
-                    //   that sortitions have already begun processing that didn't know about this pox anchor.
-                    //   we need to trigger an unwind
-                    if let Some(pox_anchor) = self
-                        .sortition_db
-                        .is_stacks_block_pox_anchor(&block_hash, canonical_sortition_tip)?
+                    let canonical_stacks_tip_height =
+                        new_canonical_block_snapshot.canonical_stacks_tip_height;
+                    if let Err(e) = self
+                        .chain_state_db
+                        .prune_block_data(canonical_stacks_tip_height)
                     {
-                        info!("Discovered an old anchor block: {}", &pox_anchor);
-                        return Ok(Some(pox_anchor));
+                        warn!("Failed to prune block data";
+                              "stacks_height" => canonical_stacks_tip_height,
+                              "error" => ?e);
                     }
                 }
             }
@@ -747,52 +701,7 @@ impl<
                     .process_blocks(sortdb_handle, 1, self.dispatcher)?;
         }
 
-        Ok(None)
-    }
-
-    fn process_new_pox_anchor(&mut self, block_id: BlockHeaderHash) -> Result<(), Error> {
-        // get the last sortition in the prepare phase that chose this anchor block
-        //   that sortition is now the current canonical sortition,
-        //   and now that we have process the anchor block for the corresponding reward phase,
-        //   update the canonical pox bitvector.
-        let sortition_id = self.canonical_sortition_tip.as_ref().expect(
-            "FAIL: processing a new anchor block, but don't have a canonical sortition tip",
-        );
-
-        let mut prep_end = self
-            .sortition_db
-            .get_prepare_end_for(sortition_id, &block_id)?
-            .expect(&format!(
-                "FAIL: expected to get a sortition for a chosen anchor block {}, but not found.",
-                &block_id
-            ));
-
-        // was this block a pox anchor for an even earlier reward cycle?
-        while let Some(older_prep_end) = self
-            .sortition_db
-            .get_prepare_end_for(&prep_end.sortition_id, &block_id)?
-        {
-            prep_end = older_prep_end;
-        }
-
-        info!(
-            "Reprocessing with anchor block information, starting at block height: {}",
-            prep_end.block_height
-        );
-
-        // invalidate all the sortitions > canonical_sortition_tip, in the same burnchain fork
-        self.sortition_db
-            .invalidate_descendants_of(&prep_end.burn_header_hash)?;
-
-        // roll back to the state as of prep_end
-        self.canonical_chain_tip = Some(StacksBlockId::new(
-            &prep_end.consensus_hash,
-            &prep_end.canonical_stacks_tip_hash,
-        ));
-        self.canonical_sortition_tip = Some(prep_end.sortition_id);
-
-        // Start processing from the beginning of the new PoX reward set
-        self.handle_new_burnchain_block()
+        Ok(())
     }
 }
 
@@ -20,8 +20,9 @@ use std::collections::VecDeque;
 use std::sync::{
     atomic::{AtomicBool, AtomicU64, Ordering},
     mpsc::sync_channel,
-    Arc, RwLock,
+    Arc, Mutex, RwLock,
 };
+use std::time::{Duration, Instant};
 
 use rusqlite::Connection;
 
@@ -32,14 +33,23 @@ use crate::chainstate::burn::operations::leader_block_commit::*;
 use crate::chainstate::burn::operations::*;
 use crate::chainstate::burn::*;
 use crate::chainstate::coordinator::{Error as CoordError, *};
+use crate::chainstate::stacks::db::fsck::{fsck, FsckReport};
+use crate::chainstate::stacks::db::replay::replay_block_report;
+use crate::chainstate::stacks::db::snapshot::{
+    export_snapshot, import_snapshot, SnapshotManifest, SNAPSHOT_MAGIC,
+};
 use crate::chainstate::stacks::db::{
     accounts::MinerReward, ClarityTx, StacksChainState, StacksHeaderInfo,
 };
+use crate::chainstate::stacks::index::marf::MarfConnection;
+use crate::chainstate::stacks::Error as ChainstateError;
 use crate::chainstate::stacks::*;
 use crate::clarity_vm::clarity::ClarityConnection;
 use crate::core;
 use crate::core::*;
-use crate::monitoring::increment_stx_blocks_processed_counter;
+use crate::monitoring::{
+    increment_stx_blocks_processed_counter, OBSERVED_DEPOSITS, OBSERVED_WITHDRAWAL_LEAVES,
+};
 use clarity::vm::{
     costs::{ExecutionCost, LimitedCostTracker},
     types::PrincipalData,
@@ -47,7 +57,7 @@ use clarity::vm::{
     Value,
 };
 use stacks_common::address;
-use stacks_common::util::hash::{to_hex, Hash160};
+use stacks_common::util::hash::{hex_bytes, to_hex, Hash160, Sha256Sum, Sha512Trunc256Sum};
 use stacks_common::util::vrf::*;
 
 use crate::chainstate::stacks::boot::COSTS_2_NAME;
@@ -274,43 +284,115 @@ impl BlockEventDispatcher for NullEventDispatcher {
     fn dispatch_boot_receipts(&mut self, _receipts: Vec<StacksTransactionReceipt>) {}
 }
 
-pub fn make_coordinator<'a>(
-    path: &str,
-    burnchain: Option<Burnchain>,
-) -> ChainsCoordinator<'a, NullEventDispatcher, (), OnChainRewardSetProvider, (), ()> {
-    let (tx, _) = sync_channel(100000);
-    let burnchain = burnchain.unwrap_or_else(|| get_burnchain(path, None));
-    ChainsCoordinator::test_new(&burnchain, 0x80000000, path, OnChainRewardSetProvider(), tx)
+/// Everything a `BlockEventDispatcher` was handed for one Stacks block
+#[derive(Debug, Clone, PartialEq)]
+struct AnnouncedBlock {
+    block: StacksBlock,
+    metadata: StacksHeaderInfo,
+    receipts: Vec<StacksTransactionReceipt>,
+    parent: StacksBlockId,
+    winner_txid: Txid,
+    matured_rewards: Vec<MinerReward>,
+    matured_rewards_info: Option<MinerRewardInfo>,
+    parent_burn_block_hash: BurnchainHeaderHash,
+    parent_burn_block_height: u32,
+    parent_burn_block_timestamp: u64,
+    anchored_block_cost: ExecutionCost,
+    confirmed_mblock_cost: ExecutionCost,
+}
+
+/// Everything a `BlockEventDispatcher` was handed for one burnchain block
+#[derive(Debug, Clone, PartialEq)]
+struct AnnouncedBurnBlock {
+    burn_block: BurnchainHeaderHash,
+    burn_block_height: u64,
+    rewards: Vec<(StacksAddress, u64)>,
+    burns: u64,
+    reward_recipients: Vec<StacksAddress>,
+}
+
+/// Records the blocks and burnchain blocks announced to it, in order
+struct RecordingEventDispatcher {
+    blocks: Mutex<Vec<AnnouncedBlock>>,
+    burn_blocks: Mutex<Vec<AnnouncedBurnBlock>>,
+}
+
+impl RecordingEventDispatcher {
+    fn new() -> RecordingEventDispatcher {
+        RecordingEventDispatcher {
+            blocks: Mutex::new(vec![]),
+            burn_blocks: Mutex::new(vec![]),
+        }
+    }
+
+    fn get_blocks(&self) -> Vec<AnnouncedBlock> {
+        self.blocks.lock().unwrap().clone()
+    }
+
+    fn get_burn_blocks(&self) -> Vec<AnnouncedBurnBlock> {
+        self.burn_blocks.lock().unwrap().clone()
+    }
 }
 
-struct StubbedRewardSetProvider(Vec<StacksAddress>);
+impl BlockEventDispatcher for RecordingEventDispatcher {
+    fn announce_block(
+        &self,
+        block: &StacksBlock,
+        metadata: &StacksHeaderInfo,
+        receipts: &Vec<StacksTransactionReceipt>,
+        parent: &StacksBlockId,
+        winner_txid: Txid,
+        matured_rewards: &Vec<MinerReward>,
+        matured_rewards_info: Option<&MinerRewardInfo>,
+        parent_burn_block_hash: BurnchainHeaderHash,
+        parent_burn_block_height: u32,
+        parent_burn_block_timestamp: u64,
+        anchored_block_cost: &ExecutionCost,
+        confirmed_mblock_cost: &ExecutionCost,
+    ) {
+        self.blocks.lock().unwrap().push(AnnouncedBlock {
+            block: block.clone(),
+            metadata: metadata.clone(),
+            receipts: receipts.clone(),
+            parent: parent.clone(),
+            winner_txid,
+            matured_rewards: matured_rewards.clone(),
+            matured_rewards_info: matured_rewards_info.cloned(),
+            parent_burn_block_hash,
+            parent_burn_block_height,
+            parent_burn_block_timestamp,
+            anchored_block_cost: anchored_block_cost.clone(),
+            confirmed_mblock_cost: confirmed_mblock_cost.clone(),
+        });
+    }
 
-impl RewardSetProvider for StubbedRewardSetProvider {
-    fn get_reward_set(
+    fn announce_burn_block(
         &self,
-        _current_burn_height: u64,
-        chainstate: &mut StacksChainState,
-        burnchain: &Burnchain,
-        sortdb: &SortitionDB,
-        block_id: &StacksBlockId,
-    ) -> Result<Vec<StacksAddress>, chainstate::coordinator::Error> {
-        Ok(self.0.clone())
+        burn_block: &BurnchainHeaderHash,
+        burn_block_height: u64,
+        rewards: Vec<(StacksAddress, u64)>,
+        burns: u64,
+        reward_recipients: Vec<StacksAddress>,
+    ) {
+        self.burn_blocks.lock().unwrap().push(AnnouncedBurnBlock {
+            burn_block: burn_block.clone(),
+            burn_block_height,
+            rewards,
+            burns,
+            reward_recipients,
+        });
     }
+
+    fn dispatch_boot_receipts(&mut self, _receipts: Vec<StacksTransactionReceipt>) {}
 }
 
-fn make_reward_set_coordinator<'a>(
+pub fn make_coordinator<'a>(
     path: &str,
-    addrs: Vec<StacksAddress>,
-    pox_consts: Option<PoxConstants>,
-) -> ChainsCoordinator<'a, NullEventDispatcher, (), StubbedRewardSetProvider, (), ()> {
+    burnchain: Option<Burnchain>,
+) -> ChainsCoordinator<'a, NullEventDispatcher, (), (), ()> {
     let (tx, _) = sync_channel(100000);
-    ChainsCoordinator::test_new(
-        &get_burnchain(path, pox_consts),
-        0x80000000,
-        path,
-        StubbedRewardSetProvider(addrs),
-        tx,
-    )
+    let burnchain = burnchain.unwrap_or_else(|| get_burnchain(path, None));
+    ChainsCoordinator::test_new(&burnchain, 0x80000000, path, tx)
 }
 
 pub fn get_burnchain(path: &str, pox_consts: Option<PoxConstants>) -> Burnchain {
@@ -755,6 +837,1447 @@ fn test_simple_setup() {
     }
 }
 
+#[test]
+fn test_replay_chain_events() {
+    let path = "/tmp/stacks-node-tests/unit-tests/stacks-blockchain-replay-chain-events";
+    let _r = std::fs::remove_dir_all(path);
+
+    let vrf_keys: Vec<_> = (0..10).map(|_| VRFPrivateKey::new()).collect();
+    let committers: Vec<_> = (0..10).map(|_| StacksPrivateKey::new()).collect();
+
+    setup_states(
+        &[path],
+        &vrf_keys,
+        &committers,
+        None,
+        None,
+        StacksEpochId::Epoch21,
+    );
+
+    let observer = RecordingEventDispatcher::new();
+    let (tx, _) = sync_channel(100000);
+    let mut coord = ChainsCoordinator::test_new_with_observer(
+        &get_burnchain(path, None),
+        0x80000000,
+        path,
+        tx,
+        Some(&observer),
+    );
+    coord.handle_new_burnchain_block().unwrap();
+
+    let sort_db = get_sortition_db(path, None);
+
+    let mut parent = BlockHeaderHash([0; 32]);
+    for (ix, (vrf_key, miner)) in vrf_keys.iter().zip(committers.iter()).enumerate() {
+        let mut burnchain = get_burnchain_db(path, None);
+        let mut chainstate = get_chainstate(path);
+        let b = get_burnchain(path, None);
+        let burnchain_tip = burnchain.get_canonical_chain_tip().unwrap();
+
+        let (op, block) = if ix == 0 {
+            make_genesis_block(
+                &sort_db,
+                &mut chainstate,
+                &parent,
+                miner,
+                10000,
+                vrf_key,
+                ix as u32,
+            )
+        } else {
+            make_stacks_block(
+                &sort_db,
+                &mut chainstate,
+                &b,
+                &parent,
+                burnchain_tip.block_height,
+                miner,
+                10000,
+                vrf_key,
+                ix as u32,
+            )
+        };
+
+        produce_burn_block(
+            &mut burnchain,
+            &burnchain_tip.block_hash,
+            vec![op],
+            std::iter::empty(),
+        );
+        coord.handle_new_burnchain_block().unwrap();
+
+        let tip = SortitionDB::get_canonical_burn_chain_tip(sort_db.conn()).unwrap();
+        preprocess_block(&mut chainstate, &sort_db, &tip, block.clone());
+        coord.handle_new_stacks_block().unwrap();
+
+        parent = block.block_hash();
+    }
+
+    let announced_blocks = observer.get_blocks();
+    assert_eq!(announced_blocks.len(), 10);
+
+    // replaying from the start announces every block again, with the same receipts, rewards
+    //  and costs, and every burnchain block from the one that elected the first Stacks block
+    let replay_observer = RecordingEventDispatcher::new();
+    let mut chainstate = get_chainstate(path);
+    let replayed = replay_chain_events(&mut chainstate, &sort_db, &replay_observer, 0).unwrap();
+    assert_eq!(replayed, 10);
+    assert_eq!(replay_observer.get_blocks(), announced_blocks);
+
+    let first_burn_height = announced_blocks[0].metadata.burn_header_height as u64;
+    let announced_burn_blocks: Vec<_> = observer
+        .get_burn_blocks()
+        .into_iter()
+        .filter(|burn_block| burn_block.burn_block_height >= first_burn_height)
+        .collect();
+    assert_eq!(announced_burn_blocks.len(), 10);
+    assert_eq!(replay_observer.get_burn_blocks(), announced_burn_blocks);
+
+    // the replay wrote nothing, so replaying again from a later height gives the same suffix
+    let replay_observer = RecordingEventDispatcher::new();
+    let replayed = replay_chain_events(&mut chainstate, &sort_db, &replay_observer, 6).unwrap();
+    assert_eq!(replayed, 5);
+    assert_eq!(replay_observer.get_blocks(), announced_blocks[5..].to_vec());
+    assert_eq!(
+        replay_observer.get_burn_blocks(),
+        announced_burn_blocks[5..].to_vec()
+    );
+}
+
+#[test]
+fn test_replay_block_report() {
+    let path = "/tmp/stacks-node-tests/unit-tests/stacks-blockchain-replay-block-report";
+    let _r = std::fs::remove_dir_all(path);
+
+    let vrf_keys: Vec<_> = (0..5).map(|_| VRFPrivateKey::new()).collect();
+    let committers: Vec<_> = (0..5).map(|_| StacksPrivateKey::new()).collect();
+
+    setup_states(
+        &[path],
+        &vrf_keys,
+        &committers,
+        None,
+        None,
+        StacksEpochId::Epoch21,
+    );
+
+    let mut coord = make_coordinator(path, None);
+    coord.set_txindex(true);
+    coord.handle_new_burnchain_block().unwrap();
+
+    let sort_db = get_sortition_db(path, None);
+
+    let mut parent = BlockHeaderHash([0; 32]);
+    let mut block_ids = vec![];
+    for (ix, (vrf_key, miner)) in vrf_keys.iter().zip(committers.iter()).enumerate() {
+        let mut burnchain = get_burnchain_db(path, None);
+        let mut chainstate = get_chainstate(path);
+        let b = get_burnchain(path, None);
+        let burnchain_tip = burnchain.get_canonical_chain_tip().unwrap();
+
+        let (op, block) = if ix == 0 {
+            make_genesis_block(
+                &sort_db,
+                &mut chainstate,
+                &parent,
+                miner,
+                10000,
+                vrf_key,
+                ix as u32,
+            )
+        } else {
+            make_stacks_block(
+                &sort_db,
+                &mut chainstate,
+                &b,
+                &parent,
+                burnchain_tip.block_height,
+                miner,
+                10000,
+                vrf_key,
+                ix as u32,
+            )
+        };
+
+        produce_burn_block(
+            &mut burnchain,
+            &burnchain_tip.block_hash,
+            vec![op],
+            std::iter::empty(),
+        );
+        coord.handle_new_burnchain_block().unwrap();
+
+        let tip = SortitionDB::get_canonical_burn_chain_tip(sort_db.conn()).unwrap();
+        preprocess_block(&mut chainstate, &sort_db, &tip, block.clone());
+        coord.handle_new_stacks_block().unwrap();
+
+        parent = block.block_hash();
+        block_ids.push(StacksBlockId::new(&tip.consensus_hash, &parent));
+    }
+
+    // no trailing slash, so the scratch copy goes next to the chainstate rather than inside it
+    let chainstate_path = format!("{}/chainstate", path);
+    let sortdb_path = get_burnchain(path, None).get_db_path();
+    for (ix, block_id) in block_ids.iter().enumerate() {
+        let report = replay_block_report(&chainstate_path, &sortdb_path, block_id).unwrap();
+        assert_eq!(report.index_block_hash, block_id.to_string());
+        assert_eq!(report.block_height, ix as u64 + 1);
+
+        // a replay of a block this node processed reaches the same roots...
+        assert!(report.state_root_matches);
+        assert_eq!(report.replayed_state_root, report.expected_state_root);
+        assert!(report.withdrawal_root_matches);
+        assert_eq!(
+            report.replayed_withdrawal_root,
+            report.expected_withdrawal_root
+        );
+
+        // ...and the same receipts as the ones in the transaction index
+        assert_eq!(report.transactions.len(), 1);
+        assert_eq!(report.transactions[0].origin, "stacks");
+        assert_eq!(report.receipt_diffs, Some(vec![]));
+    }
+
+    // the replays ran in a scratch copy, which is gone, and left the chain state as it was
+    assert!(!std::path::Path::new(&format!("{}.replay", chainstate_path)).exists());
+    assert_eq!(
+        eval_at_chain_tip(path, &sort_db, "block-height"),
+        Value::UInt(5)
+    );
+}
+
+/// Rewrite the file at `file_path` in the snapshot archive at `archive_path` with `tamper`, and
+/// update its size and hash in the manifest so that the archive itself still checks out
+fn tamper_snapshot_file<F: FnOnce(&mut Vec<u8>)>(archive_path: &str, file_path: &str, tamper: F) {
+    let archive = std::fs::read(archive_path).unwrap();
+    let mut len_bytes = [0u8; 4];
+    len_bytes.copy_from_slice(&archive[8..12]);
+    let manifest_len = u32::from_be_bytes(len_bytes) as usize;
+    let mut manifest: SnapshotManifest =
+        serde_json::from_slice(&archive[12..12 + manifest_len]).unwrap();
+
+    let mut offset = 12 + manifest_len;
+    let mut contents = vec![];
+    for file in manifest.files.iter_mut() {
+        let mut data = archive[offset..offset + file.size as usize].to_vec();
+        offset += file.size as usize;
+        if file.path == file_path {
+            tamper(&mut data);
+            file.size = data.len() as u64;
+            file.sha512_256 = Sha512Trunc256Sum::from_data(&data);
+        }
+        contents.push(data);
+    }
+    assert!(manifest.files.iter().any(|file| file.path == file_path));
+
+    let manifest_bytes = serde_json::to_vec(&manifest).unwrap();
+    let mut bytes = SNAPSHOT_MAGIC.to_vec();
+    bytes.extend_from_slice(&(manifest_bytes.len() as u32).to_be_bytes());
+    bytes.extend_from_slice(&manifest_bytes);
+    for data in contents.iter() {
+        bytes.extend_from_slice(data);
+    }
+    std::fs::write(archive_path, &bytes).unwrap();
+}
+
+#[test]
+fn test_import_snapshot() {
+    let path = "/tmp/stacks-node-tests/unit-tests/stacks-blockchain-import-snapshot";
+    let _r = std::fs::remove_dir_all(path);
+
+    let path_other = "/tmp/stacks-node-tests/unit-tests/stacks-blockchain-import-snapshot-other";
+    let _r = std::fs::remove_dir_all(path_other);
+
+    // the last key mines a block on top of the imported snapshot
+    let vrf_keys: Vec<_> = (0..6).map(|_| VRFPrivateKey::new()).collect();
+    let committers: Vec<_> = (0..6).map(|_| StacksPrivateKey::new()).collect();
+
+    setup_states(
+        &[path, path_other],
+        &vrf_keys,
+        &committers,
+        None,
+        None,
+        StacksEpochId::Epoch21,
+    );
+
+    let mut coord = make_coordinator(path, None);
+    coord.handle_new_burnchain_block().unwrap();
+
+    let sort_db = get_sortition_db(path, None);
+
+    let mut parent = BlockHeaderHash([0; 32]);
+    let mut last_block_id = None;
+    for (ix, (vrf_key, miner)) in vrf_keys.iter().zip(committers.iter()).enumerate().take(5) {
+        let mut burnchain = get_burnchain_db(path, None);
+        let mut chainstate = get_chainstate(path);
+        let b = get_burnchain(path, None);
+        let burnchain_tip = burnchain.get_canonical_chain_tip().unwrap();
+
+        let (op, block) = if ix == 0 {
+            make_genesis_block(
+                &sort_db,
+                &mut chainstate,
+                &parent,
+                miner,
+                10000,
+                vrf_key,
+                ix as u32,
+            )
+        } else {
+            make_stacks_block(
+                &sort_db,
+                &mut chainstate,
+                &b,
+                &parent,
+                burnchain_tip.block_height,
+                miner,
+                10000,
+                vrf_key,
+                ix as u32,
+            )
+        };
+
+        produce_burn_block(
+            &mut burnchain,
+            &burnchain_tip.block_hash,
+            vec![op],
+            std::iter::empty(),
+        );
+        coord.handle_new_burnchain_block().unwrap();
+
+        let tip = SortitionDB::get_canonical_burn_chain_tip(sort_db.conn()).unwrap();
+        preprocess_block(&mut chainstate, &sort_db, &tip, block.clone());
+        coord.handle_new_stacks_block().unwrap();
+
+        parent = block.block_hash();
+        last_block_id = Some(StacksBlockId::new(&tip.consensus_hash, &parent));
+    }
+    let last_block_id = last_block_id.unwrap();
+
+    let archive_path = format!("{}/snapshot", path);
+    let manifest = export_snapshot(&format!("{}/chainstate", path), None, &archive_path).unwrap();
+    assert_eq!(manifest.index_block_hash, last_block_id);
+    assert_eq!(manifest.block_height, 5);
+
+    let import_path = format!("{}/imported", path);
+    let assert_not_installed = || {
+        assert!(!std::path::Path::new(&import_path).exists());
+        assert!(!std::path::Path::new(&format!("{}.snapshot-import", &import_path)).exists());
+    };
+
+    // the other node's sortition DB has not seen the commit for the anchor
+    let other_sort_db = get_sortition_db(path_other, None);
+    match import_snapshot(
+        &archive_path,
+        &import_path,
+        &other_sort_db,
+        false,
+        0x80000000,
+        false,
+    ) {
+        Err(ChainstateError::InvalidSnapshot(msg)) => assert!(msg.contains("No block commit")),
+        x => panic!("Expected InvalidSnapshot, got {:?}", x),
+    }
+    assert_not_installed();
+
+    // the anchor's state root no longer matches the one in its header. The archive is
+    // consistent with its manifest, so only the check against the header catches it.
+    let tampered_path = format!("{}/snapshot-tampered", path);
+    std::fs::copy(&archive_path, &tampered_path).unwrap();
+    let clarity_blobs = format!(
+        "{}.blobs",
+        StacksChainState::vm_state_index_marf_path(std::path::PathBuf::new())
+            .to_str()
+            .unwrap()
+    );
+    tamper_snapshot_file(&tampered_path, &clarity_blobs, |data| {
+        let state_root = manifest.state_index_root.as_bytes().to_vec();
+        let mut found = 0;
+        for i in 0..(data.len() - state_root.len() + 1) {
+            if data[i..i + state_root.len()] == state_root[..] {
+                data[i + state_root.len() - 1] ^= 0xff;
+                found += 1;
+            }
+        }
+        assert!(found > 0);
+    });
+    match import_snapshot(
+        &tampered_path,
+        &import_path,
+        &sort_db,
+        false,
+        0x80000000,
+        false,
+    ) {
+        Err(ChainstateError::InvalidSnapshot(msg)) => assert!(msg.contains("State root")),
+        x => panic!("Expected InvalidSnapshot, got {:?}", x),
+    }
+    assert_not_installed();
+
+    // a leaf of the Clarity MARF is altered, but every stored root is left alone, so only
+    // rehashing the tries catches it
+    let value_hashes: Vec<String> = get_chainstate(path).with_clarity_marf(|marf| {
+        // the values written in the anchor's trie, by the hashes its leaves hold
+        let leaves = marf.verify_trie_at(&last_block_id).unwrap();
+        leaves
+            .iter()
+            .map(|(_, value)| value.to_hex())
+            .filter(|value_hash| {
+                let args: &[&dyn rusqlite::ToSql] = &[value_hash];
+                let count: i64 = marf
+                    .sqlite_conn()
+                    .query_row(
+                        "SELECT COUNT(*) FROM data_table WHERE key = ?1",
+                        args,
+                        |row| row.get(0),
+                    )
+                    .unwrap();
+                count > 0
+            })
+            .collect()
+    });
+    assert!(!value_hashes.is_empty());
+    std::fs::copy(&archive_path, &tampered_path).unwrap();
+    tamper_snapshot_file(&tampered_path, &clarity_blobs, |data| {
+        let mut found = 0;
+        for value_hash in value_hashes.iter() {
+            let leaf_value = hex_bytes(value_hash).unwrap();
+            for i in 0..(data.len() - leaf_value.len() + 1) {
+                if data[i..i + leaf_value.len()] == leaf_value[..] {
+                    data[i] ^= 0xff;
+                    found += 1;
+                }
+            }
+            if found > 0 {
+                break;
+            }
+        }
+        assert!(found > 0);
+    });
+    match import_snapshot(
+        &tampered_path,
+        &import_path,
+        &sort_db,
+        false,
+        0x80000000,
+        false,
+    ) {
+        Err(ChainstateError::InvalidSnapshot(msg)) => assert!(msg.contains("hashes to")),
+        x => panic!("Expected InvalidSnapshot, got {:?}", x),
+    }
+    assert_not_installed();
+
+    // a value the Clarity MARF commits to is altered, and its leaf is left alone
+    let clarity_marf_file = StacksChainState::vm_state_index_marf_path(std::path::PathBuf::new())
+        .to_str()
+        .unwrap()
+        .to_string();
+    std::fs::copy(&archive_path, &tampered_path).unwrap();
+    tamper_snapshot_file(&tampered_path, &clarity_marf_file, |data| {
+        let db_path = format!("{}/tampered-marf.sqlite", path);
+        std::fs::write(&db_path, &data).unwrap();
+        {
+            let conn = Connection::open(&db_path).unwrap();
+            let updated = conn
+                .execute(
+                    "UPDATE data_table SET value = value || ' ' WHERE key = ?1",
+                    &[&value_hashes[0]],
+                )
+                .unwrap();
+            assert_eq!(updated, 1);
+        }
+        *data = std::fs::read(&db_path).unwrap();
+    });
+    match import_snapshot(
+        &tampered_path,
+        &import_path,
+        &sort_db,
+        false,
+        0x80000000,
+        false,
+    ) {
+        Err(ChainstateError::InvalidSnapshot(msg)) => assert!(msg.contains("does not hash to")),
+        x => panic!("Expected InvalidSnapshot, got {:?}", x),
+    }
+    assert_not_installed();
+
+    // the untampered snapshot checks out against the node's own sortition DB
+    let imported = import_snapshot(
+        &archive_path,
+        &import_path,
+        &sort_db,
+        false,
+        0x80000000,
+        false,
+    )
+    .unwrap();
+    assert_eq!(imported, manifest);
+    assert!(
+        StacksChainState::header_index_root_path(std::path::PathBuf::from(&import_path)).exists()
+    );
+    std::fs::remove_dir_all(&import_path).unwrap();
+
+    // a node whose chainstate is restored from the snapshot goes on to process the next block
+    drop(coord);
+    let chainstate_path = format!("{}/chainstate", path);
+    std::fs::remove_dir_all(&chainstate_path).unwrap();
+    import_snapshot(
+        &archive_path,
+        &chainstate_path,
+        &sort_db,
+        false,
+        0x80000000,
+        false,
+    )
+    .unwrap();
+
+    let mut coord = make_coordinator(path, None);
+    let mut burnchain = get_burnchain_db(path, None);
+    let mut chainstate = get_chainstate(path);
+    let b = get_burnchain(path, None);
+    let burnchain_tip = burnchain.get_canonical_chain_tip().unwrap();
+    let (op, block) = make_stacks_block(
+        &sort_db,
+        &mut chainstate,
+        &b,
+        &parent,
+        burnchain_tip.block_height,
+        &committers[5],
+        10000,
+        &vrf_keys[5],
+        5,
+    );
+    produce_burn_block(
+        &mut burnchain,
+        &burnchain_tip.block_hash,
+        vec![op],
+        std::iter::empty(),
+    );
+    coord.handle_new_burnchain_block().unwrap();
+
+    let tip = SortitionDB::get_canonical_burn_chain_tip(sort_db.conn()).unwrap();
+    preprocess_block(&mut chainstate, &sort_db, &tip, block.clone());
+    coord.handle_new_stacks_block().unwrap();
+
+    let header = StacksChainState::get_anchored_block_header_info(
+        chainstate.db(),
+        &tip.consensus_hash,
+        &block.block_hash(),
+    )
+    .unwrap()
+    .expect("BUG: the block after the anchor was not processed");
+    assert_eq!(header.stacks_block_height, 6);
+    assert_eq!(
+        SortitionDB::get_canonical_stacks_chain_tip_hash(sort_db.conn()).unwrap(),
+        (tip.consensus_hash.clone(), block.block_hash())
+    );
+
+    // the block commit elected a block with a different withdrawal root
+    {
+        let rw_sort_db = get_rw_sortdb(path, None);
+        let args: &[&dyn rusqlite::ToSql] = &[&Sha512Trunc256Sum([0xff; 32])];
+        rw_sort_db
+            .conn()
+            .execute("UPDATE block_commits SET withdrawal_merkle_root = ?1", args)
+            .unwrap();
+    }
+    match import_snapshot(
+        &archive_path,
+        &import_path,
+        &sort_db,
+        false,
+        0x80000000,
+        false,
+    ) {
+        Err(ChainstateError::InvalidSnapshot(msg)) => {
+            assert!(msg.contains("does not match its block commit"))
+        }
+        x => panic!("Expected InvalidSnapshot, got {:?}", x),
+    }
+    assert_not_installed();
+}
+
+#[test]
+fn test_fsck() {
+    let path = "/tmp/stacks-node-tests/unit-tests/stacks-blockchain-fsck";
+    let _r = std::fs::remove_dir_all(path);
+
+    let vrf_keys: Vec<_> = (0..4).map(|_| VRFPrivateKey::new()).collect();
+    let committers: Vec<_> = (0..4).map(|_| StacksPrivateKey::new()).collect();
+
+    setup_states(
+        &[path],
+        &vrf_keys,
+        &committers,
+        None,
+        None,
+        StacksEpochId::Epoch21,
+    );
+
+    // without the transaction index, withdrawal roots can only be checked by replaying
+    let mut coord = make_coordinator(path, None);
+    coord.handle_new_burnchain_block().unwrap();
+
+    let sort_db = get_sortition_db(path, None);
+
+    let mut parent = BlockHeaderHash([0; 32]);
+    let mut blocks = vec![];
+    for (ix, (vrf_key, miner)) in vrf_keys.iter().zip(committers.iter()).enumerate() {
+        let mut burnchain = get_burnchain_db(path, None);
+        let mut chainstate = get_chainstate(path);
+        let b = get_burnchain(path, None);
+        let burnchain_tip = burnchain.get_canonical_chain_tip().unwrap();
+
+        let (op, block) = if ix == 0 {
+            make_genesis_block(
+                &sort_db,
+                &mut chainstate,
+                &parent,
+                miner,
+                10000,
+                vrf_key,
+                ix as u32,
+            )
+        } else {
+            make_stacks_block(
+                &sort_db,
+                &mut chainstate,
+                &b,
+                &parent,
+                burnchain_tip.block_height,
+                miner,
+                10000,
+                vrf_key,
+                ix as u32,
+            )
+        };
+
+        produce_burn_block(
+            &mut burnchain,
+            &burnchain_tip.block_hash,
+            vec![op],
+            std::iter::empty(),
+        );
+        coord.handle_new_burnchain_block().unwrap();
+
+        let tip = SortitionDB::get_canonical_burn_chain_tip(sort_db.conn()).unwrap();
+        preprocess_block(&mut chainstate, &sort_db, &tip, block.clone());
+        coord.handle_new_stacks_block().unwrap();
+
+        parent = block.block_hash();
+        blocks.push((
+            StacksBlockId::new(&tip.consensus_hash, &parent),
+            parent.clone(),
+        ));
+    }
+
+    let chainstate_path = format!("{}/chainstate", path);
+    let sortdb_path = get_burnchain(path, None).get_db_path();
+    let scratch_path = format!("{}.fsck", &chainstate_path);
+
+    // checking without repairing leaves the DBs as they were
+    let headers_db_path = StacksChainState::header_index_root_path(chainstate_path.clone().into());
+    let headers_db = std::fs::read(&headers_db_path).unwrap();
+    let report = fsck(&chainstate_path, &sortdb_path, false, false).unwrap();
+    assert_eq!(
+        report,
+        FsckReport {
+            checked_blocks: 5,
+            unchecked_withdrawal_roots: 4,
+            issues: vec![],
+        }
+    );
+    assert_eq!(std::fs::read(&headers_db_path).unwrap(), headers_db);
+
+    let report = fsck(&chainstate_path, &sortdb_path, true, false).unwrap();
+    assert_eq!(
+        report,
+        FsckReport {
+            checked_blocks: 5,
+            unchecked_withdrawal_roots: 0,
+            issues: vec![],
+        }
+    );
+    assert!(!std::path::Path::new(&scratch_path).exists());
+
+    // corrupt the state root of the second block, the withdrawal root of the third, and the
+    // block commit and a Clarity value of the fourth
+    {
+        let mut chainstate = get_chainstate(path);
+        let args: &[&dyn rusqlite::ToSql] = &[&TrieHash([0xff; 32]), &blocks[1].0];
+        chainstate
+            .db()
+            .execute(
+                "UPDATE block_headers SET state_index_root = ?1 WHERE index_block_hash = ?2",
+                args,
+            )
+            .unwrap();
+        let args: &[&dyn rusqlite::ToSql] = &[&Sha512Trunc256Sum([0xff; 32]), &blocks[2].0];
+        chainstate
+            .db()
+            .execute(
+                "UPDATE block_headers SET withdrawal_merkle_root = ?1 WHERE index_block_hash = ?2",
+                args,
+            )
+            .unwrap();
+
+        // a value that only the fourth block's Clarity trie commits to, so that only its
+        // trie fails to check out
+        chainstate.with_clarity_marf(|marf| {
+            let mut leaves: Vec<Vec<String>> = blocks
+                .iter()
+                .map(|(block_id, _)| {
+                    let leaves = marf.verify_trie_at(block_id).unwrap();
+                    leaves.iter().map(|(_, value)| value.to_hex()).collect()
+                })
+                .collect();
+            let last_leaves = leaves.pop().unwrap();
+            let value_hash = last_leaves
+                .iter()
+                .find(|value_hash| {
+                    !leaves
+                        .iter()
+                        .any(|block_leaves| block_leaves.contains(value_hash))
+                        && marf
+                            .sqlite_conn()
+                            .execute(
+                                "UPDATE data_table SET value = value || ' ' WHERE key = ?1",
+                                &[value_hash],
+                            )
+                            .unwrap()
+                            > 0
+                })
+                .cloned();
+            assert!(value_hash.is_some());
+        });
+
+        let rw_sort_db = get_rw_sortdb(path, None);
+        let args: &[&dyn rusqlite::ToSql] = &[&BlockHeaderHash([0xff; 32]), &blocks[3].1];
+        rw_sort_db
+            .conn()
+            .execute(
+                "UPDATE block_commits SET committed_block_hash = ?1 WHERE committed_block_hash = ?2",
+                args,
+            )
+            .unwrap();
+    }
+    let issues = |report: &FsckReport| -> Vec<(String, String)> {
+        report
+            .issues
+            .iter()
+            .map(|issue| (issue.check.clone(), issue.subject.clone()))
+            .collect()
+    };
+
+    // the withdrawal root goes unchecked without replaying
+    let report = fsck(&chainstate_path, &sortdb_path, false, false).unwrap();
+    assert_eq!(report.unchecked_withdrawal_roots, 4);
+    assert_eq!(
+        issues(&report),
+        vec![
+            ("state_root".to_string(), blocks[1].0.to_string()),
+            ("state_trie".to_string(), blocks[3].0.to_string()),
+            ("block_commit".to_string(), blocks[3].0.to_string()),
+        ]
+    );
+    assert!(report.issues[1].detail.contains("does not hash to"));
+    assert!(!report.is_consistent());
+
+    let report = fsck(&chainstate_path, &sortdb_path, true, false).unwrap();
+    assert_eq!(report.unchecked_withdrawal_roots, 0);
+    assert_eq!(
+        issues(&report),
+        vec![
+            ("state_root".to_string(), blocks[1].0.to_string()),
+            ("withdrawal_root".to_string(), blocks[2].0.to_string()),
+            ("state_trie".to_string(), blocks[3].0.to_string()),
+            ("block_commit".to_string(), blocks[3].0.to_string()),
+        ]
+    );
+    assert!(!report.is_consistent());
+    assert!(!std::path::Path::new(&scratch_path).exists());
+
+    // repair leaves these to the operator
+    let report = fsck(&chainstate_path, &sortdb_path, false, true).unwrap();
+    assert!(!report.is_consistent());
+}
+
+#[test]
+fn test_export_sql_replays_unindexed_blocks() {
+    let path = "/tmp/stacks-node-tests/unit-tests/stacks-blockchain-export-sql-replay";
+    let _r = std::fs::remove_dir_all(path);
+
+    let vrf_keys: Vec<_> = (0..4).map(|_| VRFPrivateKey::new()).collect();
+    let committers: Vec<_> = (0..4).map(|_| StacksPrivateKey::new()).collect();
+
+    setup_states(
+        &[path],
+        &vrf_keys,
+        &committers,
+        None,
+        None,
+        StacksEpochId::Epoch21,
+    );
+
+    let mut coord = make_coordinator(path, None);
+    coord.set_txindex(true);
+    coord.handle_new_burnchain_block().unwrap();
+
+    let sort_db = get_sortition_db(path, None);
+
+    let mut parent = BlockHeaderHash([0; 32]);
+    for (ix, (vrf_key, miner)) in vrf_keys.iter().zip(committers.iter()).enumerate() {
+        let mut burnchain = get_burnchain_db(path, None);
+        let mut chainstate = get_chainstate(path);
+        let b = get_burnchain(path, None);
+        let burnchain_tip = burnchain.get_canonical_chain_tip().unwrap();
+
+        let (op, block) = if ix == 0 {
+            make_genesis_block(
+                &sort_db,
+                &mut chainstate,
+                &parent,
+                miner,
+                10000,
+                vrf_key,
+                ix as u32,
+            )
+        } else {
+            make_stacks_block(
+                &sort_db,
+                &mut chainstate,
+                &b,
+                &parent,
+                burnchain_tip.block_height,
+                miner,
+                10000,
+                vrf_key,
+                ix as u32,
+            )
+        };
+
+        produce_burn_block(
+            &mut burnchain,
+            &burnchain_tip.block_hash,
+            vec![op],
+            std::iter::empty(),
+        );
+        coord.handle_new_burnchain_block().unwrap();
+
+        let tip = SortitionDB::get_canonical_burn_chain_tip(sort_db.conn()).unwrap();
+        preprocess_block(&mut chainstate, &sort_db, &tip, block.clone());
+        coord.handle_new_stacks_block().unwrap();
+
+        parent = block.block_hash();
+    }
+
+    let chainstate_path = format!("{}/chainstate", path);
+    let sortdb_path = get_burnchain(path, None).get_db_path();
+
+    let indexed_path = format!("{}/export-indexed.sqlite", path);
+    let summary = export_sql(
+        &chainstate_path,
+        &sortdb_path,
+        &indexed_path,
+        ExportFormat::Sqlite,
+    )
+    .unwrap();
+    assert_eq!(summary.exported_blocks, 5);
+    assert_eq!(summary.replayed_blocks, 0);
+
+    // as if the node had run without the transaction index
+    get_chainstate(path)
+        .db()
+        .execute("DELETE FROM transactions", rusqlite::NO_PARAMS)
+        .unwrap();
+
+    let replayed_path = format!("{}/export-replayed.sqlite", path);
+    let summary = export_sql(
+        &chainstate_path,
+        &sortdb_path,
+        &replayed_path,
+        ExportFormat::Sqlite,
+    )
+    .unwrap();
+    assert_eq!(summary.exported_blocks, 5);
+    assert_eq!(summary.replayed_blocks, 4);
+    assert!(!std::path::Path::new(&format!("{}.export", &chainstate_path)).exists());
+
+    let rows = |export_path: &str, sql: &str| -> Vec<Vec<rusqlite::types::Value>> {
+        let conn = Connection::open(export_path).unwrap();
+        let mut stmt = conn.prepare(sql).unwrap();
+        let columns = stmt.column_count();
+        let rows = stmt
+            .query_map(rusqlite::NO_PARAMS, |row| {
+                (0..columns).map(|i| row.get(i)).collect()
+            })
+            .unwrap();
+        rows.map(|row| row.unwrap()).collect()
+    };
+
+    // the replayed receipts are exported as the indexed ones were
+    let transactions = rows(
+        &indexed_path,
+        "SELECT * FROM transactions ORDER BY index_block_hash, position",
+    );
+    assert_eq!(transactions.len(), 4);
+    assert_eq!(
+        transactions,
+        rows(
+            &replayed_path,
+            "SELECT * FROM transactions ORDER BY index_block_hash, position"
+        )
+    );
+    for (table, order) in [
+        ("events", "index_block_hash, txid, event_index"),
+        ("deposits", "index_block_hash, txid"),
+        ("withdrawals", "index_block_hash, withdrawal_id"),
+        ("block_commits", "index_block_hash"),
+    ]
+    .iter()
+    {
+        let sql = format!("SELECT * FROM {} ORDER BY {}", table, order);
+        assert_eq!(rows(&indexed_path, &sql), rows(&replayed_path, &sql));
+    }
+    assert_eq!(
+        rows(
+            &replayed_path,
+            "SELECT block_height, indexed FROM blocks ORDER BY block_height"
+        ),
+        (0..5)
+            .map(|height| vec![
+                rusqlite::types::Value::Integer(height),
+                rusqlite::types::Value::Integer(0),
+            ])
+            .collect::<Vec<_>>()
+    );
+}
+
+#[test]
+fn test_indexed_transaction_in_forks() {
+    let path = "/tmp/stacks-node-tests/unit-tests/stacks-blockchain-indexed-tx-forks";
+    let _r = std::fs::remove_dir_all(path);
+
+    let vrf_keys: Vec<_> = (0..4).map(|_| VRFPrivateKey::new()).collect();
+    let committers: Vec<_> = (0..3).map(|_| StacksPrivateKey::new()).collect();
+
+    setup_states(
+        &[path],
+        &vrf_keys,
+        &committers,
+        None,
+        None,
+        StacksEpochId::Epoch21,
+    );
+
+    let mut coord = make_coordinator(path, None);
+    coord.set_txindex(true);
+    coord.handle_new_burnchain_block().unwrap();
+
+    let sort_db = get_sortition_db(path, None);
+
+    // a genesis block, and three forks off of it. The first two are mined by the same miner,
+    // so both confirm the same coinbase transaction; the third is mined by another miner.
+    let fork_miners = [0, 1, 1, 2];
+    let mut blocks: Vec<StacksBlock> = vec![];
+    let mut block_ids = vec![];
+    for (ix, (vrf_key, miner_ix)) in vrf_keys.iter().zip(fork_miners.iter()).enumerate() {
+        let miner = &committers[*miner_ix];
+        let mut burnchain = get_burnchain_db(path, None);
+        let mut chainstate = get_chainstate(path);
+        let b = get_burnchain(path, None);
+        let burnchain_tip = burnchain.get_canonical_chain_tip().unwrap();
+
+        let (op, block) = if ix == 0 {
+            make_genesis_block(
+                &sort_db,
+                &mut chainstate,
+                &BlockHeaderHash([0; 32]),
+                miner,
+                10000,
+                vrf_key,
+                ix as u32,
+            )
+        } else {
+            make_stacks_block(
+                &sort_db,
+                &mut chainstate,
+                &b,
+                &blocks[0].block_hash(),
+                burnchain_tip.block_height,
+                miner,
+                10000,
+                vrf_key,
+                ix as u32,
+            )
+        };
+
+        produce_burn_block(
+            &mut burnchain,
+            &burnchain_tip.block_hash,
+            vec![op],
+            std::iter::empty(),
+        );
+        coord.handle_new_burnchain_block().unwrap();
+
+        let tip = SortitionDB::get_canonical_burn_chain_tip(sort_db.conn()).unwrap();
+        preprocess_block(&mut chainstate, &sort_db, &tip, block.clone());
+        coord.handle_new_stacks_block().unwrap();
+
+        block_ids.push(StacksBlockId::new(&tip.consensus_hash, &block.block_hash()));
+        blocks.push(block);
+    }
+
+    let txid = blocks[1].txs[0].txid();
+    assert_eq!(blocks[2].txs[0].txid(), txid);
+    assert_ne!(blocks[1].block_hash(), blocks[2].block_hash());
+
+    let chainstate = get_chainstate(path);
+
+    // each fork's tip finds the block in its own fork
+    for fork_ix in [1, 2].iter() {
+        let (entry, header) = chainstate
+            .get_indexed_transaction(&txid, &block_ids[*fork_ix])
+            .unwrap()
+            .unwrap();
+        assert_eq!(entry.txid, txid);
+        assert_eq!(entry.index_block_hash, block_ids[*fork_ix]);
+        assert_eq!(header.index_block_hash(), block_ids[*fork_ix]);
+        assert_eq!(header.stacks_block_height, 2);
+    }
+
+    // a fork that never confirmed it does not find it
+    assert!(chainstate
+        .get_indexed_transaction(&txid, &block_ids[3])
+        .unwrap()
+        .is_none());
+}
+
+#[test]
+fn test_block_metrics_observed_once_per_block() {
+    let path = "/tmp/stacks-node-tests/unit-tests/stacks-blockchain-block-metrics-observed";
+    let _r = std::fs::remove_dir_all(path);
+
+    let vrf_keys: Vec<_> = (0..3).map(|_| VRFPrivateKey::new()).collect();
+    let committers: Vec<_> = (0..3).map(|_| StacksPrivateKey::new()).collect();
+
+    setup_states(
+        &[path],
+        &vrf_keys,
+        &committers,
+        None,
+        None,
+        StacksEpochId::Epoch21,
+    );
+
+    let mut coord = make_coordinator(path, None);
+    coord.handle_new_burnchain_block().unwrap();
+
+    let sort_db = get_sortition_db(path, None);
+    OBSERVED_WITHDRAWAL_LEAVES.with(|observed| observed.borrow_mut().clear());
+    OBSERVED_DEPOSITS.with(|observed| observed.borrow_mut().clear());
+
+    let mut parent = BlockHeaderHash([0; 32]);
+    let mut block_ids = vec![];
+    for (ix, (vrf_key, miner)) in vrf_keys.iter().zip(committers.iter()).enumerate() {
+        let mut burnchain = get_burnchain_db(path, None);
+        let mut chainstate = get_chainstate(path);
+        let b = get_burnchain(path, None);
+        let burnchain_tip = burnchain.get_canonical_chain_tip().unwrap();
+
+        // mining the block builds its withdrawal tree and processes its deposits, but is not
+        // counted
+        let (op, block) = if ix == 0 {
+            make_genesis_block(
+                &sort_db,
+                &mut chainstate,
+                &parent,
+                miner,
+                10000,
+                vrf_key,
+                ix as u32,
+            )
+        } else {
+            make_stacks_block(
+                &sort_db,
+                &mut chainstate,
+                &b,
+                &parent,
+                burnchain_tip.block_height,
+                miner,
+                10000,
+                vrf_key,
+                ix as u32,
+            )
+        };
+        assert_eq!(
+            OBSERVED_WITHDRAWAL_LEAVES.with(|observed| observed.borrow().len()),
+            ix
+        );
+        assert_eq!(
+            OBSERVED_DEPOSITS.with(|observed| observed.borrow().len()),
+            if ix > 1 { 1 } else { 0 }
+        );
+
+        // the second block processes a STX deposit made alongside the first block's commit
+        let mut ops = vec![op];
+        if ix == 0 {
+            ops.push(BlockstackOperationType::DepositStx(DepositStxOp {
+                txid: next_txid(),
+                burn_header_hash: BurnchainHeaderHash([0; 32]),
+                amount: 1000,
+                sender: PrincipalData::from(p2pkh_from(miner)),
+            }));
+        }
+        produce_burn_block(
+            &mut burnchain,
+            &burnchain_tip.block_hash,
+            ops,
+            std::iter::empty(),
+        );
+        coord.handle_new_burnchain_block().unwrap();
+
+        let tip = SortitionDB::get_canonical_burn_chain_tip(sort_db.conn()).unwrap();
+        preprocess_block(&mut chainstate, &sort_db, &tip, block.clone());
+        coord.handle_new_stacks_block().unwrap();
+
+        parent = block.block_hash();
+        block_ids.push(StacksBlockId::new(&tip.consensus_hash, &parent));
+    }
+
+    // each appended block was counted once, with no withdrawals, and the deposit once
+    assert_eq!(
+        OBSERVED_WITHDRAWAL_LEAVES.with(|observed| observed.borrow().clone()),
+        vec![0, 0, 0]
+    );
+    assert_eq!(
+        OBSERVED_DEPOSITS.with(|observed| observed.borrow().clone()),
+        vec![("stx".to_string(), "deposited".to_string())]
+    );
+
+    // replaying a block does not count it again
+    let mut chainstate = get_chainstate(path);
+    for block_id in block_ids.iter() {
+        assert!(chainstate
+            .replay_block(&sort_db, block_id)
+            .unwrap()
+            .is_some());
+    }
+    assert_eq!(
+        OBSERVED_WITHDRAWAL_LEAVES.with(|observed| observed.borrow().len()),
+        3
+    );
+    assert_eq!(
+        OBSERVED_DEPOSITS.with(|observed| observed.borrow().len()),
+        1
+    );
+}
+
+/// The sortitions and Stacks blocks the coordinator produced from the chain built by
+///  `process_coordinator_fixture`, hex-encoded so it can be checked in as JSON.
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+struct CoordinatorFixture {
+    /// (burn header hash, sortition ID, consensus hash, sortition index root) of each burn block
+    sortitions: Vec<(String, String, String, String)>,
+    /// (index block hash, state index root) of each processed Stacks block
+    stacks_blocks: Vec<(String, String)>,
+}
+
+/// Number of burn blocks (each with one block commit) in the coordinator fixture
+const COORDINATOR_FIXTURE_BLOCKS: u64 = 20;
+
+/// Set this to (re-)record `coordinator_fixture.json` instead of checking against it
+const COORDINATOR_FIXTURE_RECORD_ENV: &str = "STACKS_RECORD_COORDINATOR_FIXTURE";
+
+/// Deterministic stand-in for `next_burn_header_hash()`, `next_txid()` and `next_hash160()`.
+///  Those counters are shared by every test in this module, so what they hand out (and the
+///  state roots that depend on it) changes with the order the tests run in.
+fn fixture_hash(tag: u8, ix: u64) -> Sha256Sum {
+    let mut bytes = vec![tag];
+    bytes.extend_from_slice(&ix.to_le_bytes());
+    Sha256Sum::from_data(&bytes)
+}
+
+/// Store a burn block with a fixed header hash on top of `parent`
+fn produce_fixture_burn_block(
+    burnchain_db: &mut BurnchainDB,
+    parent: &BurnchainHeaderHash,
+    mut ops: Vec<BlockstackOperationType>,
+) -> BurnchainHeaderHash {
+    let BurnchainBlockData {
+        header: parent_header,
+        ..
+    } = burnchain_db.get_burnchain_block(parent).unwrap();
+    let block_height = parent_header.block_height + 1;
+    let block_hash = BurnchainHeaderHash(fixture_hash(0, block_height).0);
+    for op in ops.iter_mut() {
+        op.set_block_height(block_height);
+        op.set_burn_header_hash(block_hash.clone());
+    }
+    let header = BurnchainBlockHeader {
+        block_height,
+        timestamp: parent_header.timestamp + 1,
+        num_txs: ops.len() as u64,
+        block_hash: block_hash.clone(),
+        parent_block_hash: parent.clone(),
+    };
+    burnchain_db.raw_store_burnchain_block(header, ops).unwrap();
+    block_hash
+}
+
+/// Like `make_stacks_block_with_input`, but the microblock public key hash and the block
+///  commit txid are derived from `ix`, so the block is the same on every run.
+///  `parent_block` is None for the first block of the chain.
+fn make_fixture_stacks_block(
+    sort_db: &SortitionDB,
+    state: &mut StacksChainState,
+    parent_block: Option<&BlockHeaderHash>,
+    miner: &StacksPrivateKey,
+    vrf_key: &VRFPrivateKey,
+    ix: u64,
+) -> (BlockstackOperationType, StacksBlock) {
+    let tx_auth = TransactionAuth::from_p2pkh(miner).unwrap();
+
+    let mut tx = StacksTransaction::new(
+        TransactionVersion::Testnet,
+        tx_auth,
+        TransactionPayload::Coinbase(CoinbasePayload([0u8; 32])),
+    );
+    tx.chain_id = 0x80000000;
+    tx.anchor_mode = TransactionAnchorMode::OnChainOnly;
+    let mut tx_signer = StacksTransactionSigner::new(&tx);
+    tx_signer.sign_origin(miner).unwrap();
+
+    let coinbase_op = tx_signer.get_tx().unwrap();
+
+    let sortition_tip = SortitionDB::get_canonical_burn_chain_tip(sort_db.conn()).unwrap();
+    let (parent_stacks_header, total_burn) = match parent_block {
+        None => (StacksHeaderInfo::regtest_genesis(), 0),
+        Some(parent_block) => {
+            let parents_sortition = SortitionDB::get_block_snapshot_for_winning_stacks_block(
+                &sort_db.index_conn(),
+                &sortition_tip.sortition_id,
+                parent_block,
+            )
+            .unwrap()
+            .unwrap();
+            let parent_stacks_header = StacksChainState::get_anchored_block_header_info(
+                state.db(),
+                &parents_sortition.consensus_hash,
+                parent_block,
+            )
+            .unwrap()
+            .unwrap();
+            (parent_stacks_header, parents_sortition.total_burn)
+        }
+    };
+    let proof = VRF::prove(vrf_key, sortition_tip.sortition_hash.as_bytes());
+
+    let iconn = sort_db.index_conn();
+
+    let mut builder = StacksBlockBuilder::make_regtest_block_builder(
+        &parent_stacks_header,
+        proof.clone(),
+        total_burn,
+        Hash160::from_bytes(&fixture_hash(2, ix).as_bytes()[0..20]).unwrap(),
+    )
+    .unwrap();
+    let mut miner_epoch_info = builder.pre_epoch_begin(state, &iconn).unwrap();
+    let mut epoch_tx = builder
+        .epoch_begin(&iconn, &mut miner_epoch_info)
+        .unwrap()
+        .0;
+
+    builder.try_mine_tx(&mut epoch_tx, &coinbase_op).unwrap();
+
+    let block = builder.mine_anchored_block(&mut epoch_tx);
+    builder.epoch_finish(epoch_tx);
+
+    let commit_op = LeaderBlockCommitOp {
+        block_header_hash: block.block_hash(),
+        withdrawal_merkle_root: block.header.withdrawal_merkle_root,
+        txid: Txid(fixture_hash(1, ix).0),
+        burn_header_hash: BurnchainHeaderHash([0; 32]),
+    };
+
+    (BlockstackOperationType::LeaderBlockCommit(commit_op), block)
+}
+
+/// Build a chain of `COORDINATOR_FIXTURE_BLOCKS` burn blocks, each committing to a coinbase-only
+///  Stacks block, from fixed keys and hashes. Process it with a coordinator at `path`, and
+///  return what the coordinator produced along with the time it spent in
+///  `handle_new_burnchain_block()`.
+fn process_coordinator_fixture(path: &str) -> (CoordinatorFixture, Duration) {
+    let _r = std::fs::remove_dir_all(path);
+
+    let burnchain = get_burnchain(path, None);
+    let mut burnchain_db = {
+        let epochs = StacksEpoch::unit_test(StacksEpochId::Epoch21, burnchain.first_block_height);
+        let sortition_db = SortitionDB::connect(
+            &burnchain.get_db_path(),
+            burnchain.first_block_height,
+            &epochs,
+            true,
+        )
+        .unwrap();
+        let mut burnchain_db = BurnchainDB::connect(
+            &burnchain.get_burnchaindb_path(),
+            burnchain.first_block_height,
+            true,
+        )
+        .unwrap();
+        let first_sortition =
+            SortitionDB::get_canonical_burn_chain_tip(sortition_db.conn()).unwrap();
+        produce_fixture_burn_block(&mut burnchain_db, &first_sortition.burn_header_hash, vec![]);
+        burnchain_db
+    };
+
+    let mut boot_data = ChainStateBootData::new(&burnchain, vec![], None);
+    StacksChainState::open_and_exec(
+        false,
+        0x80000000,
+        &get_chainstate_path_str(path),
+        Some(&mut boot_data),
+        None,
+    )
+    .unwrap();
+
+    let mut coord = make_coordinator(path, None);
+    let mut burn_time = Duration::from_secs(0);
+
+    let start = Instant::now();
+    coord.handle_new_burnchain_block().unwrap();
+    burn_time += start.elapsed();
+
+    let sort_db = get_sortition_db(path, None);
+    let mut parent = None;
+    for ix in 0..COORDINATOR_FIXTURE_BLOCKS {
+        let vrf_key = VRFPrivateKey::from_bytes(fixture_hash(3, ix).as_bytes()).unwrap();
+        let miner = StacksPrivateKey::from_slice(fixture_hash(4, ix).as_bytes()).unwrap();
+        let mut chainstate = get_chainstate(path);
+        let burnchain_tip = burnchain_db.get_canonical_chain_tip().unwrap();
+
+        let (op, block) = make_fixture_stacks_block(
+            &sort_db,
+            &mut chainstate,
+            parent.as_ref(),
+            &miner,
+            &vrf_key,
+            ix,
+        );
+        produce_fixture_burn_block(&mut burnchain_db, &burnchain_tip.block_hash, vec![op]);
+
+        let start = Instant::now();
+        coord.handle_new_burnchain_block().unwrap();
+        burn_time += start.elapsed();
+
+        let tip = SortitionDB::get_canonical_burn_chain_tip(sort_db.conn()).unwrap();
+        assert_eq!(tip.winning_stacks_block_hash, block.block_hash());
+        preprocess_block(&mut chainstate, &sort_db, &tip, block.clone());
+        coord.handle_new_stacks_block().unwrap();
+
+        parent = Some(block.block_hash());
+    }
+
+    let chainstate = get_chainstate(path);
+    let mut fixture = CoordinatorFixture {
+        sortitions: vec![],
+        stacks_blocks: vec![],
+    };
+    for snapshot in sort_db.get_all_snapshots().unwrap() {
+        fixture.sortitions.push((
+            snapshot.burn_header_hash.to_hex(),
+            snapshot.sortition_id.to_hex(),
+            snapshot.consensus_hash.to_hex(),
+            snapshot.index_root.to_hex(),
+        ));
+        if !snapshot.sortition {
+            continue;
+        }
+        let header = StacksChainState::get_anchored_block_header_info(
+            chainstate.db(),
+            &snapshot.consensus_hash,
+            &snapshot.winning_stacks_block_hash,
+        )
+        .unwrap()
+        .expect("FATAL: sortition winner was not processed");
+        fixture.stacks_blocks.push((
+            header.index_block_hash().to_hex(),
+            header.anchored_header.state_index_root.to_hex(),
+        ));
+    }
+
+    (fixture, burn_time)
+}
+
+#[test]
+fn test_coordinator_fixture_is_deterministic() {
+    let path = "/tmp/stacks-node-tests/unit-tests/stacks-blockchain-coordinator-fixture";
+    let path_again =
+        "/tmp/stacks-node-tests/unit-tests/stacks-blockchain-coordinator-fixture.again";
+
+    let (fixture, _) = process_coordinator_fixture(path);
+    let (fixture_again, _) = process_coordinator_fixture(path_again);
+    assert_eq!(fixture, fixture_again);
+
+    // the first burn block, the one stored at setup, and one per Stacks block
+    assert_eq!(
+        fixture.sortitions.len() as u64,
+        COORDINATOR_FIXTURE_BLOCKS + 2
+    );
+    assert_eq!(
+        fixture.stacks_blocks.len() as u64,
+        COORDINATOR_FIXTURE_BLOCKS
+    );
+    // a subnet sortition is identified by the burn block it was evaluated in
+    for (burn_header_hash, sortition_id, ..) in fixture.sortitions.iter() {
+        assert_eq!(burn_header_hash, sortition_id);
+    }
+}
+
+/// Checks the coordinator against `fixtures/coordinator_fixture.json`: the sortition IDs,
+///  consensus hashes and state roots that the coordinator produced for the same chain
+///  before its reward cycle and PoX anchor handling was removed. To record the file, run
+///  this test with `STACKS_RECORD_COORDINATOR_FIXTURE=1` on the commit before that removal
+///  (this test and its helpers only use APIs that exist there). Running it also prints the
+///  time spent processing burn blocks, for comparing the two coordinators.
+#[test]
+// needs fixtures/coordinator_fixture.json, recorded from the coordinator with PoX handling
+#[ignore]
+fn test_coordinator_matches_baseline_fixture() {
+    let path = "/tmp/stacks-node-tests/unit-tests/stacks-blockchain-coordinator-baseline";
+    let fixture_path = format!(
+        "{}/src/chainstate/coordinator/fixtures/coordinator_fixture.json",
+        env!("CARGO_MANIFEST_DIR")
+    );
+
+    let (fixture, burn_time) = process_coordinator_fixture(path);
+    eprintln!(
+        "Processed {} burn blocks in {:?} ({:?} per block)",
+        fixture.sortitions.len() - 1,
+        burn_time,
+        burn_time / (fixture.sortitions.len() as u32 - 1)
+    );
+
+    if std::env::var(COORDINATOR_FIXTURE_RECORD_ENV).is_ok() {
+        let fixture_dir = std::path::Path::new(&fixture_path).parent().unwrap();
+        std::fs::create_dir_all(fixture_dir).unwrap();
+        std::fs::write(
+            &fixture_path,
+            serde_json::to_string_pretty(&fixture).unwrap(),
+        )
+        .unwrap();
+        return;
+    }
+
+    let recorded: CoordinatorFixture = serde_json::from_str(
+        &std::fs::read_to_string(&fixture_path).expect("FATAL: no recorded coordinator fixture"),
+    )
+    .unwrap();
+    assert_eq!(recorded, fixture);
+}
+
 fn eval_at_chain_tip(chainstate_path: &str, sort_db: &SortitionDB, eval: &str) -> Value {
     let stacks_tip = SortitionDB::get_canonical_stacks_chain_tip_hash(sort_db.conn()).unwrap();
     let mut chainstate = get_chainstate(chainstate_path);
@@ -778,10 +2301,10 @@ fn eval_at_chain_tip(chainstate_path: &str, sort_db: &SortitionDB, eval: &str) -
         .unwrap()
 }
 
-fn reveal_block<T: BlockEventDispatcher, N: CoordinatorNotices, U: RewardSetProvider>(
+fn reveal_block<T: BlockEventDispatcher, N: CoordinatorNotices>(
     chainstate_path: &str,
     sort_db: &SortitionDB,
-    coord: &mut ChainsCoordinator<T, N, U, (), ()>,
+    coord: &mut ChainsCoordinator<T, N, (), ()>,
     my_sortition: &SortitionId,
     block: &StacksBlock,
 ) {
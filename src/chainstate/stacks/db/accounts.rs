@@ -20,11 +20,14 @@ use rusqlite::types::ToSql;
 use rusqlite::Row;
 
 use crate::burnchains::Address;
+use crate::chainstate::burn::operations::qc_deserialize;
 use crate::chainstate::stacks::db::blocks::*;
 use crate::chainstate::stacks::db::*;
 use crate::chainstate::stacks::Error;
 use crate::chainstate::stacks::*;
-use crate::clarity_vm::clarity::{ClarityConnection, ClarityTransactionConnection};
+use crate::clarity_vm::clarity::{
+    ClarityConnection, ClarityTransactionConnection, Error as clarity_error,
+};
 use crate::util_lib::db::Error as db_error;
 use crate::util_lib::db::*;
 use clarity::vm::database::clarity_store::*;
@@ -32,6 +35,25 @@ use clarity::vm::database::*;
 use clarity::vm::types::*;
 
 use crate::types::chainstate::{StacksAddress, StacksBlockId};
+use crate::util_lib::boot::boot_code_id;
+
+/// Name of the data var in the subnet boot contract that holds the fee asset
+pub const FEE_ASSET_VAR_NAME: &str = "fee-asset";
+
+/// A fungible token in which all transaction fees of a subnet are paid, instead of STX.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct FeeAsset {
+    /// Subnet contract that defines the token
+    #[serde(deserialize_with = "qc_deserialize")]
+    pub contract_id: QualifiedContractIdentifier,
+    pub token_name: String,
+}
+
+impl std::fmt::Display for FeeAsset {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}::{}", &self.contract_id, &self.token_name)
+    }
+}
 
 #[derive(Debug, Clone, PartialEq)]
 pub struct MinerReward {
@@ -189,6 +211,146 @@ impl StacksChainState {
             .map_err(Error::ClarityError)
     }
 
+    /// Get the fungible token in which transaction fees are paid.
+    /// Returns None if fees are paid in STX.
+    pub fn get_fee_asset<T: ClarityConnection>(
+        clarity_tx: &mut T,
+        mainnet: bool,
+    ) -> Result<Option<FeeAsset>, Error> {
+        let fee_asset_value = match StacksChainState::get_data_var(
+            clarity_tx,
+            &boot_code_id("subnet", mainnet),
+            FEE_ASSET_VAR_NAME,
+        )? {
+            Some(value) => value,
+            None => return Ok(None),
+        };
+
+        let fee_asset = match fee_asset_value.expect_optional() {
+            Some(tuple) => tuple.expect_tuple(),
+            None => return Ok(None),
+        };
+        let contract_id = match fee_asset
+            .get("contract")
+            .cloned()
+            .map(|v| v.expect_principal())
+        {
+            Ok(PrincipalData::Contract(contract_id)) => contract_id,
+            _ => return Err(Error::InvalidChainstateDB),
+        };
+        let token_name = fee_asset
+            .get("token-name")
+            .cloned()
+            .map_err(|_| Error::InvalidChainstateDB)?
+            .expect_ascii();
+
+        Ok(Some(FeeAsset {
+            contract_id,
+            token_name,
+        }))
+    }
+
+    /// Get the subnet's fee asset, if one is configured.
+    /// The fee asset can only be set at genesis, so this is read from the genesis block's state.
+    pub fn get_subnet_fee_asset(&mut self) -> Result<Option<FeeAsset>, Error> {
+        let first_index_hash = StacksBlockHeader::make_index_block_hash(
+            &FIRST_BURNCHAIN_CONSENSUS_HASH,
+            &FIRST_STACKS_BLOCK_HASH,
+        );
+        if !StacksChainState::has_stacks_block(self.db(), &first_index_hash)? {
+            // not booted yet
+            return Ok(None);
+        }
+        let mainnet = self.mainnet;
+        let mut conn = self.begin_read_only_clarity_tx(&NULL_BURN_STATE_DB, &first_index_hash);
+        StacksChainState::get_fee_asset(&mut conn, mainnet)
+    }
+
+    /// Get a principal's balance of the fee asset
+    pub fn get_fee_asset_balance<T: ClarityConnection>(
+        clarity_tx: &mut T,
+        fee_asset: &FeeAsset,
+        principal: &PrincipalData,
+    ) -> Result<u128, Error> {
+        clarity_tx
+            .with_clarity_db_readonly(|ref mut db| {
+                db.get_ft_balance(
+                    &fee_asset.contract_id,
+                    &fee_asset.token_name,
+                    principal,
+                    None,
+                )
+            })
+            .map_err(|e| Error::ClarityError(clarity_error::Interpreter(e)))
+    }
+
+    /// Debit the fee asset from this principal to pay a tx fee.
+    /// DOES NOT UPDATE THE NONCE
+    pub fn account_debit_fee_asset(
+        clarity_tx: &mut ClarityTransactionConnection,
+        fee_asset: &FeeAsset,
+        principal: &PrincipalData,
+        amount: u128,
+    ) {
+        clarity_tx
+            .with_clarity_db(|ref mut db| {
+                let balance = db.get_ft_balance(
+                    &fee_asset.contract_id,
+                    &fee_asset.token_name,
+                    principal,
+                    None,
+                )?;
+
+                // last line of defense: if we don't have sufficient funds, panic.
+                // This should be checked by the block validation logic.
+                let new_balance = balance.checked_sub(amount).unwrap_or_else(|| {
+                    panic!(
+                        "Tried to debit {} {} from account {} (which only has {})",
+                        amount, fee_asset, principal, balance
+                    )
+                });
+                db.set_ft_balance(
+                    &fee_asset.contract_id,
+                    &fee_asset.token_name,
+                    principal,
+                    new_balance,
+                )?;
+                Ok(())
+            })
+            .expect("FATAL: failed to debit account")
+    }
+
+    /// Credit the fee asset to this principal, e.g. when paying out matured tx fees.
+    pub fn account_credit_fee_asset(
+        clarity_tx: &mut ClarityTransactionConnection,
+        fee_asset: &FeeAsset,
+        principal: &PrincipalData,
+        amount: u128,
+    ) {
+        clarity_tx
+            .with_clarity_db(|ref mut db| {
+                let balance = db.get_ft_balance(
+                    &fee_asset.contract_id,
+                    &fee_asset.token_name,
+                    principal,
+                    None,
+                )?;
+                let new_balance = balance
+                    .checked_add(amount)
+                    .expect("FATAL: fee asset balance overflow");
+                db.set_ft_balance(
+                    &fee_asset.contract_id,
+                    &fee_asset.token_name,
+                    principal,
+                    new_balance,
+                )?;
+
+                info!("{} credited: {} {}", principal, new_balance, fee_asset);
+                Ok(())
+            })
+            .expect("FATAL: failed to credit account")
+    }
+
     /// Called each time a transaction is invoked from this principal, to e.g.
     /// debit the STX-denominated tx fee or transfer/burn STX.
     /// Will consolidate unlocked STX.
@@ -23,6 +23,7 @@ use std::io;
 use std::io::prelude::*;
 use std::io::{Read, Seek, SeekFrom, Write};
 use std::path::{Path, PathBuf};
+use std::sync::mpsc::Receiver;
 
 use rand::thread_rng;
 use rand::Rng;
@@ -45,12 +46,14 @@ use crate::chainstate::stacks::{
     C32_ADDRESS_VERSION_MAINNET_MULTISIG, C32_ADDRESS_VERSION_MAINNET_SINGLESIG,
 };
 use crate::clarity_vm::clarity::{ClarityBlockConnection, ClarityConnection, ClarityInstance};
+use crate::clarity_vm::database::marf::MarfWriteLog;
 use crate::codec::MAX_MESSAGE_LEN;
 use crate::codec::{read_next, write_next};
 use crate::core::mempool::MemPoolDB;
 use crate::core::mempool::MAXIMUM_MEMPOOL_TX_CHAINING;
 use crate::core::*;
 use crate::cost_estimates::EstimatorError;
+use crate::net::event_stream::{EventStreamData, EventStreamMessage};
 use crate::net::BlocksInvData;
 use crate::net::Error as net_error;
 use crate::net::ExtendedStacksHeader;
@@ -83,8 +86,11 @@ use crate::chainstate::stacks::address::StacksAddressExtensions;
 use crate::chainstate::stacks::Error::NoSuchBlockError;
 use crate::chainstate::stacks::StacksBlockHeader;
 use crate::chainstate::stacks::StacksMicroblockHeader;
-use crate::clarity_vm::withdrawal::create_withdrawal_merkle_tree;
-use crate::monitoring::set_last_execution_cost_observed;
+use crate::clarity_vm::withdrawal::{create_withdrawal_merkle_tree, withdrawal_count};
+use crate::monitoring::{
+    increment_deposits_processed_counter, observe_withdrawal_leaves,
+    set_last_execution_cost_observed,
+};
 use crate::util_lib::boot::boot_code_id;
 use crate::{types, util};
 
@@ -154,6 +160,21 @@ pub enum MemPoolRejection {
     NoCoinbaseViaMempool,
     NoSuchChainTip(ConsensusHash, BlockHeaderHash),
     ConflictingNonceInMempool,
+    /// A replacement for a pending transaction didn't raise the fee enough (actual, expected)
+    ReplacementFeeTooLow(u64, u64),
+    /// The transaction is a cancellation, but there is no pending transaction to cancel
+    NoTransactionToCancel,
+    /// The transaction was cancelled by its sender, and can't be resubmitted for now
+    TransactionCancelled,
+    TooManyPendingTransactions {
+        principal: PrincipalData,
+        max_pending: u64,
+    },
+    /// The origin already cancelled a transaction less than `min_interval_secs` ago
+    TooManyCancellations {
+        principal: PrincipalData,
+        min_interval_secs: u64,
+    },
     TooMuchChaining {
         max_nonce: u64,
         actual_nonce: u64,
@@ -179,6 +200,36 @@ pub struct SetupBlockResult<'a, 'b> {
         Option<(MinerReward, Vec<MinerReward>, MinerReward, MinerRewardInfo)>,
     pub evaluated_epoch: StacksEpochId,
     pub applied_epoch_transition: bool,
+    /// The number of FT and NFT deposit ops whose deposit call errored, and so have no receipt
+    pub failed_deposits: Vec<(&'static str, usize)>,
+}
+
+/// The outcome of re-executing an already-processed block (see
+/// `StacksChainState::replay_processed_block`).
+pub struct ReplayedBlock {
+    pub block: StacksBlock,
+    pub epoch_receipt: StacksEpochReceipt,
+    /// The state root the replay reached
+    pub state_root: TrieHash,
+    /// The withdrawal root of the replayed receipts
+    pub withdrawal_root: Sha512Trunc256Sum,
+    /// The MARF writes of each transaction, if they were logged
+    pub write_log: Option<MarfWriteLog>,
+}
+
+/// The outcome of executing an anchored block on top of the state prepared by
+/// `StacksChainState::setup_block` (see `StacksChainState::execute_block`).
+struct ExecutedBlock {
+    tx_receipts: Vec<StacksTransactionReceipt>,
+    block_fees: u128,
+    block_burns: u128,
+    block_cost: ExecutionCost,
+    matured_rewards: Vec<MinerReward>,
+    matured_rewards_info: Option<MinerRewardInfo>,
+    /// The state root reached once the block's transactions were applied
+    state_root: TrieHash,
+    /// The withdrawal tree of the block's receipts
+    withdrawal_tree: MerkleTree<Sha512Trunc256Sum>,
 }
 
 pub struct DummyEventDispatcher;
@@ -297,6 +348,34 @@ impl MemPoolRejection {
                 Some(json!({"message": e.to_string()})),
             ),
             ConflictingNonceInMempool => ("ConflictingNonceInMempool", None),
+            ReplacementFeeTooLow(actual, expected) => (
+                "ReplacementFeeTooLow",
+                Some(json!({
+                    "expected": expected,
+                    "actual": actual})),
+            ),
+            NoTransactionToCancel => ("NoTransactionToCancel", None),
+            TransactionCancelled => ("TransactionCancelled", None),
+            TooManyPendingTransactions {
+                principal,
+                max_pending,
+            } => (
+                "TooManyPendingTransactions",
+                Some(json!({
+                    "message": "Principal has too many pending transactions in mempool",
+                    "principal": principal.to_string(),
+                    "max_pending": max_pending})),
+            ),
+            TooManyCancellations {
+                principal,
+                min_interval_secs,
+            } => (
+                "TooManyCancellations",
+                Some(json!({
+                    "message": "Principal cancelled a transaction too recently",
+                    "principal": principal.to_string(),
+                    "min_interval_secs": min_interval_secs})),
+            ),
             ContractAlreadyExists(id) => (
                 "ContractAlreadyExists",
                 Some(json!({ "contract_identifier": id.to_string() })),
@@ -494,6 +573,10 @@ impl MicroblockStreamData {
 }
 
 impl StreamCursor {
+    pub fn new_event_stream(receiver: Receiver<EventStreamMessage>) -> StreamCursor {
+        StreamCursor::Events(EventStreamData::new(receiver))
+    }
+
     pub fn new_block(index_block_hash: StacksBlockId) -> StreamCursor {
         StreamCursor::Block(BlockStreamData {
             index_block_hash: index_block_hash,
@@ -644,8 +727,8 @@ impl StreamCursor {
             StreamCursor::Block(ref stream) => stream.offset(),
             StreamCursor::Microblocks(ref stream) => stream.offset(),
             StreamCursor::Headers(ref stream) => stream.offset(),
-            // no-op for mempool txs
-            StreamCursor::MempoolTxs(..) => 0,
+            // no-op for mempool txs and events
+            StreamCursor::MempoolTxs(..) | StreamCursor::Events(..) => 0,
         }
     }
 
@@ -654,8 +737,8 @@ impl StreamCursor {
             StreamCursor::Block(ref mut stream) => stream.add_bytes(nw),
             StreamCursor::Microblocks(ref mut stream) => stream.add_bytes(nw),
             StreamCursor::Headers(ref mut stream) => stream.add_bytes(nw),
-            // no-op fo mempool txs
-            StreamCursor::MempoolTxs(..) => (),
+            // no-op fo mempool txs and events
+            StreamCursor::MempoolTxs(..) | StreamCursor::Events(..) => (),
         }
     }
 
@@ -708,6 +791,15 @@ impl StreamCursor {
                 Ok(num_written)
             }
             StreamCursor::Block(ref mut stream) => chainstate.stream_block(fd, stream, count),
+            StreamCursor::Events(ref mut stream) => stream.stream_to(fd, count),
+        }
+    }
+
+    /// Is this a live event stream, which can have nothing to send for now without being over?
+    pub fn is_open_ended(&self) -> bool {
+        match self {
+            StreamCursor::Events(ref stream) => !stream.is_closed(),
+            _ => false,
         }
     }
 }
@@ -4651,6 +4743,7 @@ impl StacksChainState {
                 let txid_buff = Value::buff_from(txid.as_bytes().to_vec())
                     .expect("BUG: failed to convert txid to buffer");
                 // call the register asset function in the subnet contract
+                clarity_tx.close_write_log_entry(None);
                 let result = clarity_tx.connection().as_transaction(|tx| {
                     tx.run_contract_call(
                         &boot_code_addr(mainnet).into(),
@@ -4666,6 +4759,7 @@ impl StacksChainState {
                         |_, _| false,
                     )
                 });
+                clarity_tx.close_write_log_entry(Some(txid.clone()));
                 let mut execution_cost = clarity_tx.cost_so_far();
                 execution_cost
                     .sub(&cost_so_far)
@@ -4695,6 +4789,34 @@ impl StacksChainState {
             .collect()
     }
 
+    /// Count the deposit ops processed by a block this node appended to its chain state, by
+    /// asset type and outcome. `failed_deposits` holds the number of ops per asset type that
+    /// produced no receipt. Blocks built by the miner or re-executed by a replay are not counted.
+    fn count_processed_deposits(
+        tx_receipts: &[StacksTransactionReceipt],
+        failed_deposits: &[(&'static str, usize)],
+    ) {
+        for receipt in tx_receipts.iter() {
+            let asset = match &receipt.transaction {
+                TransactionOrigin::Burn(BlockstackOperationType::DepositStx(_)) => "stx",
+                TransactionOrigin::Burn(BlockstackOperationType::DepositFt(_)) => "ft",
+                TransactionOrigin::Burn(BlockstackOperationType::DepositNft(_)) => "nft",
+                _ => continue,
+            };
+            // a deposit rejected by the subnet contract is refunded with a withdrawal
+            let outcome = match &receipt.result {
+                Value::Response(response) if !response.committed => "refunded",
+                _ => "deposited",
+            };
+            increment_deposits_processed_counter(asset, outcome);
+        }
+        for (asset, num_failed) in failed_deposits.iter() {
+            for _ in 0..*num_failed {
+                increment_deposits_processed_counter(asset, "failed");
+            }
+        }
+    }
+
     /// Process any deposit STX operations that haven't been processed in this
     /// subnet fork yet.
     pub fn process_deposit_stx_ops(
@@ -4707,12 +4829,13 @@ impl StacksChainState {
                     .into_iter()
                     .map(|deposit_stx_op| {
                         let DepositStxOp {
-                            txid: _,
+                            txid,
                             amount,
                             sender,
                             ..
                         } = deposit_stx_op.clone();
                         // call the corresponding deposit function in the subnet contract
+                        clarity_tx.close_write_log_entry(None);
                         let result = clarity_tx.connection().as_transaction(|tx| {
                             StacksChainState::account_credit(tx, &sender, amount as u64);
                             StacksTransactionEvent::STXEvent(STXEventType::STXMintEvent(
@@ -4724,6 +4847,7 @@ impl StacksChainState {
                         });
                         // deposits increment the STX liquidity in the layer 2
                         clarity_tx.increment_ustx_liquid_supply(amount);
+                        clarity_tx.close_write_log_entry(Some(txid.clone()));
 
                         StacksTransactionReceipt {
                             transaction: TransactionOrigin::Burn(deposit_stx_op.into()),
@@ -4764,6 +4888,7 @@ impl StacksChainState {
                     ..
                 } = deposit_ft_op.clone();
                 // call the corresponding deposit function in the subnet contract
+                clarity_tx.close_write_log_entry(None);
                 let result = clarity_tx.connection().as_transaction(|tx| {
                     tx.run_contract_call(
                         &boot_code_addr(mainnet).into(),
@@ -4774,6 +4899,7 @@ impl StacksChainState {
                         |_, _| false,
                     )
                 });
+                clarity_tx.close_write_log_entry(Some(txid.clone()));
                 let mut execution_cost = clarity_tx.cost_so_far();
                 execution_cost
                     .sub(&cost_so_far)
@@ -4800,7 +4926,7 @@ impl StacksChainState {
                                 Token::Ft { amount },
                                 mainnet,
                             ));
-                        };
+                        }
 
                         Some(StacksTransactionReceipt {
                             transaction: TransactionOrigin::Burn(deposit_ft_op.into()),
@@ -4846,6 +4972,7 @@ impl StacksChainState {
                     sender,
                     ..
                 } = deposit_nft_op.clone();
+                clarity_tx.close_write_log_entry(None);
                 let result = clarity_tx.connection().as_transaction(|tx| {
                     tx.run_contract_call(
                         &boot_code_addr(mainnet).into(),
@@ -4856,6 +4983,7 @@ impl StacksChainState {
                         |_, _| false,
                     )
                 });
+                clarity_tx.close_write_log_entry(Some(txid.clone()));
                 let mut execution_cost = clarity_tx.cost_so_far();
                 execution_cost
                     .sub(&cost_so_far)
@@ -4882,7 +5010,7 @@ impl StacksChainState {
                                 Token::Nft { id },
                                 mainnet,
                             ));
-                        };
+                        }
 
                         Some(StacksTransactionReceipt {
                             transaction: TransactionOrigin::Burn(deposit_nft_op.into()),
@@ -4935,12 +5063,33 @@ impl StacksChainState {
     }
 
     /// Process a single matured miner reward.
-    /// Grant it STX tokens.
+    /// Grant it STX tokens, and the tx fees in the subnet's fee asset if it has one.
     fn process_matured_miner_reward(
         clarity_tx: &mut ClarityTx,
         miner_reward: &MinerReward,
     ) -> Result<(), Error> {
-        let miner_reward_total = miner_reward.total();
+        let mainnet = clarity_tx.config.mainnet;
+        let fee_asset = StacksChainState::get_fee_asset(clarity_tx, mainnet)?;
+        let miner_reward_total = match fee_asset {
+            Some(ref fee_asset) => {
+                let tx_fees = miner_reward.total() - miner_reward.coinbase;
+                let miner_principal = PrincipalData::Standard(StandardPrincipalData::from(
+                    miner_reward.address.clone(),
+                ));
+                if tx_fees > 0 {
+                    clarity_tx.connection().as_transaction(|tx| {
+                        StacksChainState::account_credit_fee_asset(
+                            tx,
+                            fee_asset,
+                            &miner_principal,
+                            tx_fees,
+                        )
+                    });
+                }
+                miner_reward.coinbase
+            }
+            None => miner_reward.total(),
+        };
         clarity_tx
             .connection()
             .as_transaction(|x| {
@@ -5215,15 +5364,19 @@ impl StacksChainState {
             deposit_stx_ops,
         ));
 
-        // Process asset deposits
-        tx_receipts.extend(StacksChainState::process_deposit_ft_ops(
-            &mut clarity_tx,
-            deposit_ft_ops,
-        ));
-        tx_receipts.extend(StacksChainState::process_deposit_nft_ops(
-            &mut clarity_tx,
-            deposit_nft_ops,
-        ));
+        // Process asset deposits. Ops whose deposit call errored produce no receipt, so keep
+        // count of them for `append_block`'s metrics.
+        let num_deposit_ft_ops = deposit_ft_ops.len();
+        let ft_receipts = StacksChainState::process_deposit_ft_ops(&mut clarity_tx, deposit_ft_ops);
+        let num_deposit_nft_ops = deposit_nft_ops.len();
+        let nft_receipts =
+            StacksChainState::process_deposit_nft_ops(&mut clarity_tx, deposit_nft_ops);
+        let failed_deposits = vec![
+            ("ft", num_deposit_ft_ops - ft_receipts.len()),
+            ("nft", num_deposit_nft_ops - nft_receipts.len()),
+        ];
+        tx_receipts.extend(ft_receipts);
+        tx_receipts.extend(nft_receipts);
 
         Ok(SetupBlockResult {
             clarity_tx,
@@ -5235,6 +5388,7 @@ impl StacksChainState {
             matured_miner_rewards_opt,
             evaluated_epoch,
             applied_epoch_transition,
+            failed_deposits,
         })
     }
 
@@ -5290,6 +5444,133 @@ impl StacksChainState {
         Ok(vec![])
     }
 
+    /// Get the hash, height and timestamp of the burnchain block that elected the parent of
+    /// `block`, as reported in the block's epoch receipt.
+    fn get_parent_burn_block_stats(
+        sortdb_conn: &Connection,
+        block: &StacksBlock,
+        parent_consensus_hash: &ConsensusHash,
+        parent_block_hash: &BlockHeaderHash,
+    ) -> Result<(BurnchainHeaderHash, u32, u64), Error> {
+        if block.is_first_mined() {
+            return Ok((BurnchainHeaderHash([0; 32]), 0, 0));
+        }
+        match SortitionDB::get_block_snapshot_consensus(sortdb_conn, parent_consensus_hash)? {
+            Some(sn) => Ok((
+                sn.burn_header_hash,
+                sn.block_height as u32,
+                sn.burn_header_timestamp,
+            )),
+            None => {
+                // shouldn't happen
+                warn!(
+                    "CORRUPTION: block {}/{} does not correspond to a burn block",
+                    parent_consensus_hash, parent_block_hash
+                );
+                Ok((BurnchainHeaderHash([0; 32]), 0, 0))
+            }
+        }
+    }
+
+    /// Execute the transactions of an anchored block on top of the state that `setup_block`
+    /// prepared in `clarity_tx`, pay out the matured miner rewards, and seal the resulting
+    /// state. `tx_receipts` are the receipts `setup_block` already produced, and the receipts
+    /// of the confirmed parent microblocks are appended after the anchored block's.
+    ///
+    /// This is the block execution shared by `append_block` and `replay_processed_block`.
+    /// Neither the state root nor the withdrawal root is checked against the block header, and
+    /// on error the caller must roll back `clarity_tx`.
+    fn execute_block(
+        clarity_tx: &mut ClarityTx,
+        block: &StacksBlock,
+        mut tx_receipts: Vec<StacksTransactionReceipt>,
+        microblock_txs_receipts: Vec<StacksTransactionReceipt>,
+        matured_miner_rewards_opt: Option<(
+            MinerReward,
+            Vec<MinerReward>,
+            MinerReward,
+            MinerRewardInfo,
+        )>,
+    ) -> Result<ExecutedBlock, Error> {
+        // process anchored block
+        let (block_fees, block_burns, txs_receipts) =
+            match StacksChainState::process_block_transactions(
+                clarity_tx,
+                block,
+                microblock_txs_receipts.len() as u32,
+            ) {
+                Err(e) => {
+                    let msg = format!("Invalid Stacks block {}: {:?}", block.block_hash(), &e);
+                    warn!("{}", &msg);
+                    return Err(Error::InvalidStacksBlock(msg));
+                }
+                Ok((block_fees, block_burns, txs_receipts)) => {
+                    (block_fees, block_burns, txs_receipts)
+                }
+            };
+
+        tx_receipts.extend(txs_receipts.into_iter());
+
+        let block_cost = clarity_tx.cost_so_far();
+
+        // obtain reward info for receipt
+        let (matured_rewards, matured_rewards_info, miner_payouts_opt) =
+            if let Some((miner_reward, mut user_rewards, parent_reward, reward_ptr)) =
+                matured_miner_rewards_opt
+            {
+                let mut ret = vec![];
+                ret.push(miner_reward.clone());
+                ret.append(&mut user_rewards);
+                ret.push(parent_reward.clone());
+                (
+                    ret,
+                    Some(reward_ptr),
+                    Some((miner_reward, user_rewards, parent_reward)),
+                )
+            } else {
+                (vec![], None, None)
+            };
+
+        let mut lockup_events = StacksChainState::finish_block(
+            clarity_tx,
+            miner_payouts_opt,
+            block.header.total_work.work as u32,
+            block.header.microblock_pubkey_hash,
+        )?;
+
+        // if any, append lockups events to the coinbase receipt
+        if lockup_events.len() > 0 {
+            // Receipts are appended in order, so the first receipt should be
+            // the one of the coinbase transaction
+            if let Some(receipt) = tx_receipts.get_mut(0) {
+                if receipt.is_coinbase_tx() {
+                    receipt.events.append(&mut lockup_events);
+                }
+            } else {
+                warn!("Unable to attach lockups events, block's first transaction is not a coinbase transaction")
+            }
+        }
+
+        tx_receipts.extend(microblock_txs_receipts.into_iter());
+
+        let state_root = clarity_tx.seal();
+
+        // Process withdrawal events (this assigns the withdrawal IDs in the receipts)
+        let withdrawal_tree =
+            create_withdrawal_merkle_tree(tx_receipts.iter_mut(), block.header.total_work.work);
+
+        Ok(ExecutedBlock {
+            tx_receipts,
+            block_fees,
+            block_burns,
+            block_cost,
+            matured_rewards,
+            matured_rewards_info,
+            state_root,
+            withdrawal_tree,
+        })
+    }
+
     /// Process the next pre-processed staging block.
     /// We've already processed `parent_chain_tip`, whereas `chain_tip` refers to a block we have _not_
     /// processed yet.
@@ -5410,7 +5691,7 @@ impl StacksChainState {
 
         let SetupBlockResult {
             mut clarity_tx,
-            mut tx_receipts,
+            tx_receipts,
             microblock_execution_cost,
             microblock_fees,
             microblock_burns,
@@ -5418,6 +5699,7 @@ impl StacksChainState {
             matured_miner_rewards_opt,
             evaluated_epoch,
             applied_epoch_transition,
+            failed_deposits,
         } = StacksChainState::setup_block(
             chainstate_tx,
             clarity_instance,
@@ -5448,31 +5730,16 @@ impl StacksChainState {
             parent_burn_block_timestamp,
             clarity_commit,
             withdrawal_tree,
+            tx_receipts,
         ) = {
             // get previous burn block stats
             let (parent_burn_block_hash, parent_burn_block_height, parent_burn_block_timestamp) =
-                if block.is_first_mined() {
-                    (BurnchainHeaderHash([0; 32]), 0, 0)
-                } else {
-                    match SortitionDB::get_block_snapshot_consensus(
-                        burn_dbconn,
-                        &parent_consensus_hash,
-                    )? {
-                        Some(sn) => (
-                            sn.burn_header_hash,
-                            sn.block_height as u32,
-                            sn.burn_header_timestamp,
-                        ),
-                        None => {
-                            // shouldn't happen
-                            warn!(
-                                "CORRUPTION: block {}/{} does not correspond to a burn block",
-                                &parent_consensus_hash, &parent_block_hash
-                            );
-                            (BurnchainHeaderHash([0; 32]), 0, 0)
-                        }
-                    }
-                };
+                StacksChainState::get_parent_burn_block_stats(
+                    burn_dbconn,
+                    block,
+                    &parent_consensus_hash,
+                    &parent_block_hash,
+                )?;
 
             // validation check -- is this microblock public key hash new to this fork?  It must
             // be, or this block is invalid.
@@ -5518,83 +5785,36 @@ impl StacksChainState {
                    "evaluated_epoch" => %evaluated_epoch);
 
             // process anchored block
-            let (block_fees, block_burns, txs_receipts) =
-                match StacksChainState::process_block_transactions(
-                    &mut clarity_tx,
-                    &block,
-                    microblock_txs_receipts.len() as u32,
-                ) {
-                    Err(e) => {
-                        let msg = format!("Invalid Stacks block {}: {:?}", block.block_hash(), &e);
-                        warn!("{}", &msg);
-
-                        clarity_tx.rollback_block();
-                        return Err(Error::InvalidStacksBlock(msg));
-                    }
-                    Ok((block_fees, block_burns, txs_receipts)) => {
-                        (block_fees, block_burns, txs_receipts)
-                    }
-                };
-
-            tx_receipts.extend(txs_receipts.into_iter());
-
-            let block_cost = clarity_tx.cost_so_far();
-
-            // obtain reward info for receipt
-            let (matured_rewards, matured_rewards_info, miner_payouts_opt) =
-                if let Some((miner_reward, mut user_rewards, parent_reward, reward_ptr)) =
-                    matured_miner_rewards_opt
-                {
-                    let mut ret = vec![];
-                    ret.push(miner_reward.clone());
-                    ret.append(&mut user_rewards);
-                    ret.push(parent_reward.clone());
-                    (
-                        ret,
-                        Some(reward_ptr),
-                        Some((miner_reward, user_rewards, parent_reward)),
-                    )
-                } else {
-                    (vec![], None, None)
-                };
-
-            // total burns
-            let total_burnt = block_burns
-                .checked_add(microblock_burns)
-                .expect("Overflow: Too many STX burnt");
-
-            let mut lockup_events = match StacksChainState::finish_block(
+            let ExecutedBlock {
+                tx_receipts,
+                block_fees,
+                block_burns,
+                block_cost,
+                matured_rewards,
+                matured_rewards_info,
+                state_root: root_hash,
+                withdrawal_tree,
+            } = match StacksChainState::execute_block(
                 &mut clarity_tx,
-                miner_payouts_opt,
-                block.header.total_work.work as u32,
-                block.header.microblock_pubkey_hash,
+                &block,
+                tx_receipts,
+                microblock_txs_receipts,
+                matured_miner_rewards_opt,
             ) {
+                Ok(executed) => executed,
                 Err(Error::InvalidStacksBlock(e)) => {
                     clarity_tx.rollback_block();
                     return Err(Error::InvalidStacksBlock(e));
                 }
                 Err(e) => return Err(e),
-                Ok(lockup_events) => lockup_events,
             };
 
-            // if any, append lockups events to the coinbase receipt
-            if lockup_events.len() > 0 {
-                // Receipts are appended in order, so the first receipt should be
-                // the one of the coinbase transaction
-                if let Some(receipt) = tx_receipts.get_mut(0) {
-                    if receipt.is_coinbase_tx() {
-                        receipt.events.append(&mut lockup_events);
-                    }
-                } else {
-                    warn!("Unable to attach lockups events, block's first transaction is not a coinbase transaction")
-                }
-            }
-
-            tx_receipts.extend(microblock_txs_receipts.into_iter());
+            // total burns
+            let total_burnt = block_burns
+                .checked_add(microblock_burns)
+                .expect("Overflow: Too many STX burnt");
 
             // check clarity state merkle root
-            let root_hash = clarity_tx.seal();
-
             if root_hash != block.header.state_index_root {
                 let msg = format!(
                     "Block {} state root mismatch: expected {}, got {}",
@@ -5613,9 +5833,6 @@ impl StacksChainState {
                    "block cost" => %block_cost);
 
             // Check withdrawal state merkle root
-            // Process withdrawal events
-            let withdrawal_tree =
-                create_withdrawal_merkle_tree(tx_receipts.iter_mut(), block.header.total_work.work);
             let withdrawal_root_hash = withdrawal_tree.root();
 
             if withdrawal_root_hash != block.header.withdrawal_merkle_root {
@@ -5678,6 +5895,7 @@ impl StacksChainState {
                 parent_burn_block_timestamp,
                 clarity_commit,
                 withdrawal_tree,
+                tx_receipts,
             )
         };
 
@@ -5708,6 +5926,8 @@ impl StacksChainState {
         chainstate_tx.log_transactions_processed(&new_tip.index_block_hash(), &tx_receipts);
 
         set_last_execution_cost_observed(&block_execution_cost, &block_limit);
+        observe_withdrawal_leaves(withdrawal_count(&new_tip.withdrawal_tree));
+        StacksChainState::count_processed_deposits(&tx_receipts, &failed_deposits);
 
         let epoch_receipt = StacksEpochReceipt {
             header: new_tip,
@@ -5725,6 +5945,228 @@ impl StacksChainState {
         Ok((epoch_receipt, clarity_commit))
     }
 
+    /// Re-execute an already-processed anchored block, along with the parent microblocks it
+    /// confirmed, on top of its parent's state, and reconstruct the `StacksEpochReceipt` that
+    /// `append_block` produced for it. Transaction receipts are not persisted, so this is how
+    /// they are recovered after the fact (e.g. to replay events to a new observer).
+    ///
+    /// Nothing is written: the Clarity block is rolled back and the chainstate transaction is
+    /// dropped once the receipts have been gathered.
+    ///
+    /// Returns None if the block is not a processed block in this chain state.
+    pub fn replay_block(
+        &mut self,
+        sortdb: &SortitionDB,
+        index_block_hash: &StacksBlockId,
+    ) -> Result<Option<(StacksBlock, StacksEpochReceipt)>, Error> {
+        let replayed = match self.replay_processed_block(sortdb, index_block_hash, false)? {
+            Some(replayed) => replayed,
+            None => return Ok(None),
+        };
+
+        // the replayed block must reach the same state as when it was first processed
+        if replayed.state_root != replayed.block.header.state_index_root {
+            return Err(Error::InvalidStacksBlock(format!(
+                "Replayed block {} state root mismatch: expected {}, got {}",
+                index_block_hash, replayed.block.header.state_index_root, replayed.state_root
+            )));
+        }
+        if replayed.withdrawal_root != replayed.block.header.withdrawal_merkle_root {
+            return Err(Error::InvalidStacksBlock(format!(
+                "Replayed block {} withdrawal root mismatch: expected {}, got {}",
+                index_block_hash,
+                replayed.block.header.withdrawal_merkle_root,
+                replayed.withdrawal_root
+            )));
+        }
+
+        Ok(Some((replayed.block, replayed.epoch_receipt)))
+    }
+
+    /// Re-execute an already-processed block like `replay_block`, but without checking the
+    /// outcome against its header: the caller gets the state and withdrawal roots the replay
+    /// reached. If `log_writes` is set, the MARF writes of each transaction are logged as well.
+    ///
+    /// Returns None if the block is not a processed block in this chain state.
+    pub fn replay_processed_block(
+        &mut self,
+        sortdb: &SortitionDB,
+        index_block_hash: &StacksBlockId,
+        log_writes: bool,
+    ) -> Result<Option<ReplayedBlock>, Error> {
+        self.clarity_state.set_log_writes(log_writes);
+        let result = self.inner_replay_processed_block(sortdb, index_block_hash);
+        self.clarity_state.set_log_writes(false);
+        result
+    }
+
+    fn inner_replay_processed_block(
+        &mut self,
+        sortdb: &SortitionDB,
+        index_block_hash: &StacksBlockId,
+    ) -> Result<Option<ReplayedBlock>, Error> {
+        let header_info = match StacksChainState::get_stacks_block_header_info_by_index_block_hash(
+            self.db(),
+            index_block_hash,
+        )? {
+            Some(header_info) => header_info,
+            None => return Ok(None),
+        };
+
+        let block = match StacksChainState::load_block(
+            &self.blocks_path,
+            &header_info.consensus_hash,
+            &header_info.anchored_header.block_hash(),
+        )? {
+            Some(block) => block,
+            None => return Ok(None),
+        };
+
+        let parent_header_info = if block.is_first_mined() {
+            StacksChainState::get_anchored_block_header_info(
+                self.db(),
+                &FIRST_BURNCHAIN_CONSENSUS_HASH,
+                &FIRST_STACKS_BLOCK_HASH,
+            )?
+        } else {
+            match StacksChainState::get_parent_block_id(self.db(), index_block_hash)? {
+                Some(parent_id) => {
+                    StacksChainState::get_stacks_block_header_info_by_index_block_hash(
+                        self.db(),
+                        &parent_id,
+                    )?
+                }
+                None => None,
+            }
+        }
+        .ok_or_else(|| {
+            Error::InvalidStacksBlock(format!(
+                "Failed to load parent header of processed block {}",
+                index_block_hash
+            ))
+        })?;
+
+        let (parent_consensus_hash, parent_block_hash) = if block.is_first_mined() {
+            (
+                FIRST_BURNCHAIN_CONSENSUS_HASH.clone(),
+                FIRST_STACKS_BLOCK_HASH.clone(),
+            )
+        } else {
+            (
+                parent_header_info.consensus_hash.clone(),
+                parent_header_info.anchored_header.block_hash(),
+            )
+        };
+
+        let microblocks = if block.has_microblock_parent() {
+            StacksChainState::load_processed_microblock_stream_fork(
+                self.db(),
+                &parent_consensus_hash,
+                &parent_block_hash,
+                &block.header.parent_microblock,
+            )?
+            .ok_or_else(|| {
+                Error::InvalidStacksBlock(format!(
+                    "Failed to load the processed microblocks confirmed by block {}",
+                    index_block_hash
+                ))
+            })?
+        } else {
+            vec![]
+        };
+
+        let parent_burn_hash =
+            SortitionDB::get_block_snapshot_consensus(sortdb.conn(), &header_info.consensus_hash)?
+                .ok_or_else(|| {
+                    Error::InvalidStacksBlock(format!(
+                        "Failed to load snapshot for processed block {}",
+                        index_block_hash
+                    ))
+                })?
+                .parent_burn_header_hash;
+
+        let (parent_burn_block_hash, parent_burn_block_height, parent_burn_block_timestamp) =
+            StacksChainState::get_parent_burn_block_stats(
+                sortdb.conn(),
+                &block,
+                &parent_consensus_hash,
+                &parent_block_hash,
+            )?;
+
+        let mainnet = self.mainnet;
+        let burn_dbconn = sortdb.index_conn();
+        let (mut chainstate_tx, clarity_instance) = self.chainstate_tx_begin()?;
+
+        let SetupBlockResult {
+            mut clarity_tx,
+            tx_receipts,
+            microblock_execution_cost,
+            microblock_txs_receipts,
+            matured_miner_rewards_opt,
+            evaluated_epoch,
+            ..
+        } = StacksChainState::setup_block(
+            &mut chainstate_tx,
+            clarity_instance,
+            &burn_dbconn,
+            sortdb.conn(),
+            &parent_header_info,
+            parent_burn_hash,
+            header_info.burn_header_height,
+            parent_consensus_hash,
+            parent_block_hash,
+            &microblocks,
+            mainnet,
+            None,
+        )?;
+
+        let ExecutedBlock {
+            tx_receipts,
+            block_cost,
+            matured_rewards,
+            matured_rewards_info,
+            state_root,
+            withdrawal_tree,
+            ..
+        } = match StacksChainState::execute_block(
+            &mut clarity_tx,
+            &block,
+            tx_receipts,
+            microblock_txs_receipts,
+            matured_miner_rewards_opt,
+        ) {
+            Ok(executed) => executed,
+            Err(e) => {
+                clarity_tx.rollback_block();
+                return Err(e);
+            }
+        };
+
+        let write_log = clarity_tx.take_write_log();
+        clarity_tx.rollback_block();
+
+        let epoch_receipt = StacksEpochReceipt {
+            header: header_info,
+            tx_receipts,
+            matured_rewards,
+            matured_rewards_info,
+            parent_microblocks_cost: microblock_execution_cost,
+            anchored_block_cost: block_cost,
+            parent_burn_block_hash,
+            parent_burn_block_height,
+            parent_burn_block_timestamp,
+            evaluated_epoch,
+        };
+
+        Ok(Some(ReplayedBlock {
+            block,
+            epoch_receipt,
+            state_root,
+            withdrawal_root: withdrawal_tree.root(),
+            write_log,
+        }))
+    }
+
     /// Verify that a Stacks anchored block attaches to its parent anchored block.
     /// * checks .header.total_work.work
     /// * checks .header.parent_block
@@ -6425,6 +6867,11 @@ impl StacksChainState {
             MemPoolRejection::BadAddressVersionByte |
             MemPoolRejection::NoCoinbaseViaMempool |
             MemPoolRejection::ConflictingNonceInMempool |
+            MemPoolRejection::ReplacementFeeTooLow(_, _) |
+            MemPoolRejection::NoTransactionToCancel |
+            MemPoolRejection::TransactionCancelled |
+            MemPoolRejection::TooManyPendingTransactions { .. } |
+            MemPoolRejection::TooManyCancellations { .. } |
             MemPoolRejection::BadTransactionVersion |
             MemPoolRejection::TransferAmountMustBePositive |
             MemPoolRejection::TransferRecipientIsSender(_) => Err(e)
@@ -6514,8 +6961,21 @@ impl StacksChainState {
                 )
             });
 
-        // 5: the paying account must have enough funds
-        if !payer.stx_balance.can_transfer_at_burn_block(
+        // 5: the paying account must have enough funds, in the fee asset if the subnet has one
+        let fee_asset =
+            StacksChainState::get_fee_asset(clarity_connection, chainstate_config.mainnet)
+                .map_err(|e| MemPoolRejection::Other(format!("Failed to load fee asset: {}", e)))?;
+        if let Some(ref fee_asset) = fee_asset {
+            let balance = StacksChainState::get_fee_asset_balance(
+                clarity_connection,
+                fee_asset,
+                &payer.principal,
+            )
+            .map_err(|e| MemPoolRejection::Other(format!("Failed to load fee balance: {}", e)))?;
+            if balance < fee as u128 {
+                return Err(MemPoolRejection::NotEnoughFunds(fee as u128, balance));
+            }
+        } else if !payer.stx_balance.can_transfer_at_burn_block(
             fee as u128,
             block_height,
             v1_unlock_height,
@@ -6546,7 +7006,8 @@ impl StacksChainState {
                 }
 
                 // does the owner have the funds for the token transfer?
-                let total_spent = (*amount as u128) + if origin == payer { fee as u128 } else { 0 };
+                let pays_fee_in_stx = origin == payer && fee_asset.is_none();
+                let total_spent = (*amount as u128) + if pays_fee_in_stx { fee as u128 } else { 0 };
                 if !origin.stx_balance.can_transfer_at_burn_block(
                     total_spent,
                     block_height,
@@ -6564,7 +7025,7 @@ impl StacksChainState {
                 }
 
                 // if the payer for the tx is different from owner, check if they can afford fee
-                if origin != payer {
+                if origin != payer && fee_asset.is_none() {
                     if !payer.stx_balance.can_transfer_at_burn_block(
                         fee as u128,
                         block_height,
@@ -11957,4 +12418,123 @@ pub mod test {
             1000000000 + (1000 + 2000 + 3000 + 4000 + 5000 + 6000 + 7000 + 8000 + 9000)
         );
     }
+
+    #[test]
+    fn test_matured_miner_reward_in_fee_asset() {
+        let miner_addr = StacksAddress {
+            version: C32_ADDRESS_VERSION_TESTNET_SINGLESIG,
+            bytes: Hash160([0x11; 20]),
+        };
+        let miner_principal = miner_addr.to_account_principal();
+        let (mut chainstate, fee_asset) = instantiate_chainstate_with_fee_asset(
+            "matured-miner-reward-in-fee-asset",
+            vec![],
+            vec![],
+        );
+
+        let reward = MinerReward {
+            address: miner_addr.clone(),
+            coinbase: 500,
+            tx_fees_anchored: 7,
+            tx_fees_streamed_produced: 2,
+            tx_fees_streamed_confirmed: 3,
+            vtxindex: 0,
+        };
+
+        let mut conn = chainstate.block_begin(
+            &TEST_BURN_STATE_DB,
+            &FIRST_BURNCHAIN_CONSENSUS_HASH,
+            &FIRST_STACKS_BLOCK_HASH,
+            &ConsensusHash([1u8; 20]),
+            &BlockHeaderHash([1u8; 32]),
+        );
+        StacksChainState::process_matured_miner_reward(&mut conn, &reward).unwrap();
+
+        // the coinbase is paid in STX, and the tx fees in the fee asset they were paid in
+        let account = StacksChainState::get_account(&mut conn, &miner_principal);
+        assert_eq!(account.stx_balance.amount_unlocked(), 500);
+        assert_eq!(
+            StacksChainState::get_fee_asset_balance(&mut conn, &fee_asset, &miner_principal)
+                .unwrap(),
+            7 + 2 + 3
+        );
+
+        conn.commit_block();
+    }
+
+    #[test]
+    fn test_mempool_admission_in_fee_asset() {
+        let privk = StacksPrivateKey::from_hex(
+            "6d430bb91222408e7706c9001cfaeb91b08c2be6d5ac95779ab52c6b431950e001",
+        )
+        .unwrap();
+        let privk_broke = StacksPrivateKey::from_hex(
+            "7e3af4db6af6b3c67e2c6c6d7d5983b519f4d9b3a6e00580ae96dcace3bde8bc01",
+        )
+        .unwrap();
+        let addr = TransactionAuth::from_p2pkh(&privk)
+            .unwrap()
+            .origin()
+            .address_testnet();
+        let addr_broke = TransactionAuth::from_p2pkh(&privk_broke)
+            .unwrap()
+            .origin()
+            .address_testnet();
+        let recv_addr = StacksAddress {
+            version: C32_ADDRESS_VERSION_TESTNET_SINGLESIG,
+            bytes: Hash160([0xff; 20]),
+        };
+
+        // `addr` has only a little STX but enough of the fee asset; `addr_broke` has plenty of
+        // STX but none of the fee asset
+        let (mut chainstate, _) = instantiate_chainstate_with_fee_asset(
+            "mempool-admission-in-fee-asset",
+            vec![(addr.clone(), 100), (addr_broke.clone(), 1_000_000)],
+            vec![(addr.clone(), 1_000_000)],
+        );
+        let conf = chainstate.config();
+
+        let make_transfer = |privk: &StacksPrivateKey| {
+            let mut tx_stx_transfer = StacksTransaction::new(
+                TransactionVersion::Testnet,
+                TransactionAuth::from_p2pkh(privk).unwrap(),
+                TransactionPayload::TokenTransfer(
+                    recv_addr.clone().into(),
+                    1,
+                    TokenTransferMemo([0u8; 34]),
+                ),
+            );
+            tx_stx_transfer.chain_id = 0x80000000;
+            tx_stx_transfer.post_condition_mode = TransactionPostConditionMode::Allow;
+            tx_stx_transfer.set_tx_fee(1000);
+
+            let mut signer = StacksTransactionSigner::new(&tx_stx_transfer);
+            signer.sign_origin(privk).unwrap();
+            signer.get_tx().unwrap()
+        };
+
+        let first_index_hash = StacksBlockHeader::make_index_block_hash(
+            &FIRST_BURNCHAIN_CONSENSUS_HASH,
+            &FIRST_STACKS_BLOCK_HASH,
+        );
+        let (admitted, rejected) = chainstate
+            .with_read_only_clarity_tx(&TEST_BURN_STATE_DB, &first_index_hash, |conn| {
+                let tx = make_transfer(&privk);
+                let tx_size = tx.serialize_to_vec().len() as u64;
+                let admitted = StacksChainState::can_include_tx(conn, &conf, true, &tx, tx_size);
+
+                let tx = make_transfer(&privk_broke);
+                let tx_size = tx.serialize_to_vec().len() as u64;
+                let rejected = StacksChainState::can_include_tx(conn, &conf, true, &tx, tx_size);
+                (admitted, rejected)
+            })
+            .unwrap();
+
+        // the fee is checked against the fee asset balance, not the STX balance
+        admitted.unwrap();
+        match rejected {
+            Err(MemPoolRejection::NotEnoughFunds(1000, 0)) => {}
+            x => panic!("Expected NotEnoughFunds, got {:?}", x),
+        }
+    }
 }
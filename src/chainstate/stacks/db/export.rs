@@ -0,0 +1,1212 @@
+// Copyright (C) 2013-2020 Blockstack PBC, a public benefit corporation
+// Copyright (C) 2020 Stacks Open Internet Foundation
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Export of the canonical chain into a relational schema, for block explorers and indexers.
+//! The export is either a SQLite DB, or a SQL script that is appended to on each run and can
+//! be replayed into another database (it only uses `CREATE TABLE IF NOT EXISTS`, plain types
+//! and `INSERT ... ON CONFLICT DO NOTHING`, so SQLite and Postgres both accept it).
+//!
+//! Every row is keyed by the index block hash of the block it came from, so the rows of
+//! blocks in different forks never collide. `blocks.canonical` is 1 for the blocks of the
+//! canonical chain as of the latest export; when a later export finds that the canonical chain
+//! has reorganized, it clears the flag on the orphaned blocks and exports the new fork. Queries
+//! for the canonical chain join on `blocks` with `canonical = 1`.
+//!
+//! Each run resumes from the height recorded in `export_progress`. For a SQL script, the
+//! progress is kept in a SQLite DB next to it, at `<script>.progress`.
+//!
+//! Receipts, events, deposits and withdrawals come from the node's transaction index
+//! (`txindex = true` in the node config). Blocks processed while it was off are replayed to
+//! rebuild their receipts, and are exported with `indexed = 0`. They are replayed in a copy of
+//! the chainstate's DBs at `<chainstate>.export`, made the first time a block needs replaying
+//! and removed once the export is done, so that replaying never writes to the chainstate.
+//! Blocks whose data was pruned cannot be replayed, and have no transaction rows.
+
+use std::cmp;
+use std::fs;
+use std::fs::{File, OpenOptions};
+use std::io::{BufWriter, Write};
+use std::path::PathBuf;
+
+use clarity::vm::events::{SmartContractEventData, StacksTransactionEvent};
+use clarity::vm::types::{CharType, ResponseData, SequenceData, Value};
+use rusqlite::types::ToSql;
+use rusqlite::{Connection, OpenFlags, OptionalExtension, NO_PARAMS};
+
+use crate::burnchains::events::NewBlockTxEvent;
+use crate::burnchains::Txid;
+use crate::chainstate::burn::db::sortdb::SortitionDB;
+use crate::chainstate::stacks::db::replay::open_scratch_chainstate;
+use crate::chainstate::stacks::db::snapshot::{make_staging_dir, stage_files};
+use crate::chainstate::stacks::db::{IndexedTransaction, StacksChainState, StacksHeaderInfo};
+use crate::chainstate::stacks::{Error, StacksBlockHeader, StacksTransaction};
+use crate::clarity_vm::verify::withdrawal_leaf_hash;
+use crate::clarity_vm::withdrawal::{convert_withdrawal_key_to_bytes, generate_key_from_event};
+use crate::codec::StacksMessageCodec;
+use crate::util_lib::db::{sqlite_open, u64_to_sql, Error as db_error};
+use stacks_common::types::chainstate::StacksBlockId;
+use stacks_common::util::hash::{hex_bytes, to_hex, MerkleTree, Sha512Trunc256Sum};
+
+/// The export schema. Amounts are `TEXT`, since they can exceed 64 bits.
+const EXPORT_SCHEMA: &[&str] = &[
+    r#"
+    CREATE TABLE IF NOT EXISTS blocks(
+        index_block_hash TEXT PRIMARY KEY,
+        block_hash TEXT NOT NULL,
+        consensus_hash TEXT NOT NULL,
+        parent_index_block_hash TEXT,
+        block_height BIGINT NOT NULL,
+        burn_header_hash TEXT NOT NULL,
+        burn_header_height BIGINT NOT NULL,
+        burn_header_timestamp BIGINT NOT NULL,
+        tx_merkle_root TEXT NOT NULL,
+        state_index_root TEXT NOT NULL,
+        withdrawal_merkle_root TEXT NOT NULL,
+        -- 1 if the block's receipts were in the transaction index, 0 if they were rebuilt by
+        -- replaying the block
+        indexed INTEGER NOT NULL,
+        -- 1 if the block was in the canonical chain as of the latest export
+        canonical INTEGER NOT NULL
+    )"#,
+    "CREATE INDEX IF NOT EXISTS blocks_height ON blocks(block_height, canonical)",
+    r#"
+    CREATE TABLE IF NOT EXISTS transactions(
+        index_block_hash TEXT NOT NULL,
+        txid TEXT NOT NULL,
+        -- order in which the block processed the transaction
+        position BIGINT NOT NULL,
+        tx_index BIGINT NOT NULL,
+        microblock_hash TEXT,
+        -- 'stacks' for a Stacks transaction, 'burn' for an L1 operation
+        origin TEXT NOT NULL,
+        sender TEXT,
+        nonce BIGINT,
+        fee BIGINT,
+        -- the payload type of a Stacks transaction, or the kind of L1 operation
+        payload_type TEXT NOT NULL,
+        raw_tx TEXT,
+        burnchain_op TEXT,
+        result_hex TEXT,
+        success INTEGER,
+        post_condition_aborted INTEGER,
+        stx_burned TEXT,
+        execution_cost TEXT,
+        PRIMARY KEY(index_block_hash, txid)
+    )"#,
+    "CREATE INDEX IF NOT EXISTS transactions_txid ON transactions(txid)",
+    r#"
+    CREATE TABLE IF NOT EXISTS events(
+        index_block_hash TEXT NOT NULL,
+        txid TEXT NOT NULL,
+        event_index BIGINT NOT NULL,
+        event_type TEXT NOT NULL,
+        committed INTEGER NOT NULL,
+        contract_identifier TEXT,
+        topic TEXT,
+        -- the event, as the event observer receives it
+        payload TEXT NOT NULL,
+        PRIMARY KEY(index_block_hash, txid, event_index)
+    )"#,
+    r#"
+    CREATE TABLE IF NOT EXISTS deposits(
+        index_block_hash TEXT NOT NULL,
+        txid TEXT NOT NULL,
+        -- 'stx', 'ft' or 'nft'
+        asset_type TEXT NOT NULL,
+        sender TEXT NOT NULL,
+        amount TEXT,
+        nft_id TEXT,
+        l1_contract_id TEXT,
+        subnet_contract_id TEXT,
+        asset_name TEXT,
+        burn_header_hash TEXT NOT NULL,
+        success INTEGER,
+        PRIMARY KEY(index_block_hash, txid)
+    )"#,
+    r#"
+    CREATE TABLE IF NOT EXISTS withdrawals(
+        index_block_hash TEXT NOT NULL,
+        withdrawal_id BIGINT NOT NULL,
+        txid TEXT NOT NULL,
+        -- 'stx', 'stx-to-subnet', 'ft' or 'nft'
+        withdrawal_type TEXT NOT NULL,
+        recipient TEXT NOT NULL,
+        amount TEXT,
+        nft_id TEXT,
+        asset_contract TEXT,
+        destination TEXT,
+        -- hex of the consensus-serialized withdrawal key, and its leaf hash in the block's
+        -- withdrawal tree
+        withdrawal_key TEXT NOT NULL,
+        leaf_hash TEXT NOT NULL,
+        PRIMARY KEY(index_block_hash, withdrawal_id)
+    )"#,
+    r#"
+    CREATE TABLE IF NOT EXISTS block_commits(
+        index_block_hash TEXT PRIMARY KEY,
+        txid TEXT NOT NULL,
+        burn_header_hash TEXT NOT NULL,
+        committed_block_hash TEXT NOT NULL,
+        withdrawal_merkle_root TEXT NOT NULL
+    )"#,
+    r#"
+    -- the highest exported block.  There is at most one row.
+    CREATE TABLE IF NOT EXISTS export_progress(
+        block_height BIGINT NOT NULL,
+        index_block_hash TEXT NOT NULL
+    )"#,
+];
+
+/// How an export is written
+#[derive(Debug, Clone, PartialEq)]
+pub enum ExportFormat {
+    /// A SQLite DB
+    Sqlite,
+    /// A SQL script, appended to on each run
+    SqlDump,
+}
+
+/// What an export run did
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ExportSummary {
+    pub tip_index_block_hash: StacksBlockId,
+    pub tip_height: u64,
+    /// Blocks that this run added to the canonical chain of the export
+    pub exported_blocks: u64,
+    /// Previously exported blocks that this run found to have left the canonical chain
+    pub orphaned_blocks: u64,
+    /// Exported blocks that were not in the transaction index, and were replayed instead
+    pub replayed_blocks: u64,
+}
+
+/// A literal in a generated SQL statement
+#[derive(Debug, Clone, PartialEq)]
+enum SqlValue {
+    Null,
+    Integer(u64),
+    Text(String),
+}
+
+impl SqlValue {
+    fn text<T: ToString>(value: T) -> SqlValue {
+        SqlValue::Text(value.to_string())
+    }
+
+    fn opt_text<T: ToString>(value: Option<T>) -> SqlValue {
+        value.map(SqlValue::text).unwrap_or(SqlValue::Null)
+    }
+
+    fn flag(value: bool) -> SqlValue {
+        SqlValue::Integer(if value { 1 } else { 0 })
+    }
+
+    fn opt_flag(value: Option<bool>) -> SqlValue {
+        value.map(SqlValue::flag).unwrap_or(SqlValue::Null)
+    }
+
+    fn literal(&self) -> String {
+        match self {
+            SqlValue::Null => "NULL".to_string(),
+            SqlValue::Integer(value) => value.to_string(),
+            SqlValue::Text(value) => format!("'{}'", value.replace('\'', "''")),
+        }
+    }
+}
+
+fn insert_sql(table: &str, columns: &[&str], values: &[SqlValue]) -> String {
+    let literals: Vec<String> = values.iter().map(|value| value.literal()).collect();
+    format!(
+        "INSERT INTO {} ({}) VALUES ({}) ON CONFLICT DO NOTHING",
+        table,
+        columns.join(", "),
+        literals.join(", ")
+    )
+}
+
+/// A generated SQL statement
+#[derive(Debug, Clone, PartialEq)]
+struct Statement {
+    sql: String,
+    /// Whether the statement touches the tables that track the export's progress (`blocks`
+    /// and `export_progress`), and so must also be applied to the progress DB of a SQL script
+    progress: bool,
+}
+
+impl Statement {
+    fn data(sql: String) -> Statement {
+        Statement {
+            sql,
+            progress: false,
+        }
+    }
+
+    fn progress(sql: String) -> Statement {
+        Statement {
+            sql,
+            progress: true,
+        }
+    }
+}
+
+/// Where an export's statements go
+struct ExportOutput {
+    /// The output DB for SQLite, or the progress DB for a SQL script
+    state: Connection,
+    dump: Option<BufWriter<File>>,
+}
+
+impl ExportOutput {
+    fn open(output_path: &str, format: &ExportFormat) -> Result<ExportOutput, Error> {
+        let (state_path, dump) = match format {
+            ExportFormat::Sqlite => (output_path.to_string(), None),
+            ExportFormat::SqlDump => {
+                let is_new = fs::metadata(output_path)
+                    .map(|md| md.len() == 0)
+                    .unwrap_or(true);
+                let file = OpenOptions::new()
+                    .create(true)
+                    .append(true)
+                    .open(output_path)
+                    .map_err(Error::WriteError)?;
+                let mut dump = BufWriter::new(file);
+                if is_new {
+                    for cmd in EXPORT_SCHEMA.iter() {
+                        writeln!(dump, "{};", cmd.trim()).map_err(Error::WriteError)?;
+                    }
+                    dump.flush().map_err(Error::WriteError)?;
+                }
+                (format!("{}.progress", output_path), Some(dump))
+            }
+        };
+
+        let state = sqlite_open(
+            PathBuf::from(state_path),
+            OpenFlags::SQLITE_OPEN_READ_WRITE | OpenFlags::SQLITE_OPEN_CREATE,
+            false,
+        )?;
+        for cmd in EXPORT_SCHEMA.iter() {
+            state.execute_batch(cmd)?;
+        }
+        Ok(ExportOutput { state, dump })
+    }
+
+    /// Apply `statements` atomically. A SQL script gets them as a single transaction, and is
+    /// written before the progress DB is updated, so a run that dies in between exports the
+    /// same rows again on the next run (which the script then ignores).
+    fn apply(&mut self, statements: &[Statement]) -> Result<(), Error> {
+        if let Some(dump) = self.dump.as_mut() {
+            writeln!(dump, "BEGIN;").map_err(Error::WriteError)?;
+            for statement in statements.iter() {
+                writeln!(dump, "{};", &statement.sql).map_err(Error::WriteError)?;
+            }
+            writeln!(dump, "COMMIT;").map_err(Error::WriteError)?;
+            dump.flush().map_err(Error::WriteError)?;
+        }
+
+        let progress_only = self.dump.is_some();
+        let tx = self.state.transaction()?;
+        for statement in statements.iter() {
+            if statement.progress || !progress_only {
+                tx.execute_batch(&statement.sql)?;
+            }
+        }
+        tx.commit()?;
+        Ok(())
+    }
+}
+
+/// The height and index block hash of the highest exported block, if any
+fn get_export_progress(conn: &Connection) -> Result<Option<(u64, StacksBlockId)>, Error> {
+    let row: Option<(i64, String)> = conn
+        .query_row(
+            "SELECT block_height, index_block_hash FROM export_progress LIMIT 1",
+            NO_PARAMS,
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )
+        .optional()?;
+    match row {
+        Some((height, hex)) => {
+            let block_id = StacksBlockId::from_hex(&hex).map_err(|_| db_error::ParseError)?;
+            Ok(Some((height as u64, block_id)))
+        }
+        None => Ok(None),
+    }
+}
+
+fn export_progress_statements(height: u64, block_id: &StacksBlockId) -> Vec<Statement> {
+    vec![
+        Statement::progress("DELETE FROM export_progress".to_string()),
+        Statement::progress(insert_sql(
+            "export_progress",
+            &["block_height", "index_block_hash"],
+            &[SqlValue::Integer(height), SqlValue::text(block_id)],
+        )),
+    ]
+}
+
+/// The exported block at `height` in the canonical chain of the export, if any
+fn get_exported_canonical_block(
+    conn: &Connection,
+    height: u64,
+) -> Result<Option<StacksBlockId>, Error> {
+    let args: &[&dyn ToSql] = &[&u64_to_sql(height)?];
+    let hex: Option<String> = conn
+        .query_row(
+            "SELECT index_block_hash FROM blocks WHERE block_height = ?1 AND canonical = 1 LIMIT 1",
+            args,
+            |row| row.get(0),
+        )
+        .optional()?;
+    match hex {
+        Some(hex) => Ok(Some(
+            StacksBlockId::from_hex(&hex).map_err(|_| db_error::ParseError)?,
+        )),
+        None => Ok(None),
+    }
+}
+
+fn is_block_exported(conn: &Connection, block_id: &StacksBlockId) -> Result<bool, Error> {
+    let args: &[&dyn ToSql] = &[&block_id.to_string()];
+    Ok(conn
+        .query_row(
+            "SELECT 1 FROM blocks WHERE index_block_hash = ?1 LIMIT 1",
+            args,
+            |_row| Ok(()),
+        )
+        .optional()?
+        .is_some())
+}
+
+/// Find the highest height, at or below `start`, at which the canonical chain of the export
+/// agrees with the chain that `ancestor_at` describes. `None` if they agree nowhere.
+fn find_fork_height<F>(conn: &Connection, start: u64, ancestor_at: F) -> Result<Option<u64>, Error>
+where
+    F: Fn(u64) -> Result<Option<StacksBlockId>, Error>,
+{
+    let mut height = start;
+    loop {
+        if let Some(exported) = get_exported_canonical_block(conn, height)? {
+            if Some(exported) == ancestor_at(height)? {
+                return Ok(Some(height));
+            }
+        }
+        if height == 0 {
+            return Ok(None);
+        }
+        height -= 1;
+    }
+}
+
+/// Whether a serialized transaction result is an `(ok ...)`
+fn result_success(result_hex: &str) -> Option<bool> {
+    match Value::try_deserialize_hex_untyped(result_hex).ok()? {
+        Value::Response(ResponseData { committed, .. }) => Some(committed),
+        _ => None,
+    }
+}
+
+const TRANSACTION_COLUMNS: &[&str] = &[
+    "index_block_hash",
+    "txid",
+    "position",
+    "tx_index",
+    "microblock_hash",
+    "origin",
+    "sender",
+    "nonce",
+    "fee",
+    "payload_type",
+    "raw_tx",
+    "burnchain_op",
+    "result_hex",
+    "success",
+    "post_condition_aborted",
+    "stx_burned",
+    "execution_cost",
+];
+
+fn indexed_transaction_sql(position: usize, itx: &IndexedTransaction) -> Result<String, Error> {
+    let (origin, sender, nonce, fee, payload_type) = match itx.burnchain_op.as_ref() {
+        Some(op) => {
+            let kind = op
+                .as_object()
+                .and_then(|op| op.keys().next().cloned())
+                .unwrap_or_else(|| "unknown".to_string());
+            ("burn", SqlValue::Null, SqlValue::Null, SqlValue::Null, kind)
+        }
+        None => {
+            let tx_bytes = hex_bytes(&itx.tx_hex).map_err(|_| db_error::ParseError)?;
+            let tx = StacksTransaction::consensus_deserialize(&mut &tx_bytes[..])?;
+            (
+                "stacks",
+                SqlValue::text(tx.origin_address()),
+                SqlValue::Integer(tx.get_origin_nonce()),
+                SqlValue::Integer(tx.get_tx_fee()),
+                tx.payload.name().to_string(),
+            )
+        }
+    };
+    let execution_cost = serde_json::to_string(&itx.execution_cost).map_err(Error::JSONError)?;
+    Ok(insert_sql(
+        "transactions",
+        TRANSACTION_COLUMNS,
+        &[
+            SqlValue::text(&itx.index_block_hash),
+            SqlValue::text(&itx.txid),
+            SqlValue::Integer(position as u64),
+            SqlValue::Integer(itx.tx_index as u64),
+            SqlValue::opt_text(itx.microblock_hash.as_ref()),
+            SqlValue::text(origin),
+            sender,
+            nonce,
+            fee,
+            SqlValue::Text(payload_type),
+            SqlValue::opt_text(itx.burnchain_op.is_none().then(|| &itx.tx_hex)),
+            SqlValue::opt_text(itx.burnchain_op.as_ref()),
+            SqlValue::text(&itx.result_hex),
+            SqlValue::opt_flag(result_success(&itx.result_hex)),
+            SqlValue::flag(itx.post_condition_aborted),
+            SqlValue::text(itx.stx_burned),
+            SqlValue::Text(execution_cost),
+        ],
+    ))
+}
+
+fn event_sql(itx: &IndexedTransaction, event_index: usize, event: &serde_json::Value) -> String {
+    let contract_event = event.get("contract_event");
+    insert_sql(
+        "events",
+        &[
+            "index_block_hash",
+            "txid",
+            "event_index",
+            "event_type",
+            "committed",
+            "contract_identifier",
+            "topic",
+            "payload",
+        ],
+        &[
+            SqlValue::text(&itx.index_block_hash),
+            SqlValue::text(&itx.txid),
+            SqlValue::Integer(event_index as u64),
+            SqlValue::text(
+                event
+                    .get("type")
+                    .and_then(|event_type| event_type.as_str())
+                    .unwrap_or("unknown"),
+            ),
+            SqlValue::flag(
+                event
+                    .get("committed")
+                    .and_then(|committed| committed.as_bool())
+                    .unwrap_or(false),
+            ),
+            SqlValue::opt_text(
+                contract_event
+                    .and_then(|ev| ev.get("contract_identifier"))
+                    .and_then(|id| id.as_str()),
+            ),
+            SqlValue::opt_text(
+                contract_event
+                    .and_then(|ev| ev.get("topic"))
+                    .and_then(|topic| topic.as_str()),
+            ),
+            SqlValue::text(event),
+        ],
+    )
+}
+
+/// A field of a burnchain operation's JSON, as text
+fn op_field(op: &serde_json::Value, field: &str) -> SqlValue {
+    match op.get(field) {
+        None | Some(serde_json::Value::Null) => SqlValue::Null,
+        Some(serde_json::Value::String(value)) => SqlValue::text(value),
+        Some(value) => SqlValue::text(value),
+    }
+}
+
+/// The deposit row for an indexed deposit operation
+fn deposit_sql(itx: &IndexedTransaction) -> Option<String> {
+    let (kind, op) = itx.burnchain_op.as_ref()?.as_object()?.iter().next()?;
+    let asset_type = match kind.as_str() {
+        "deposit_stx" => "stx",
+        "deposit_ft" => "ft",
+        "deposit_nft" => "nft",
+        _ => return None,
+    };
+    Some(insert_sql(
+        "deposits",
+        &[
+            "index_block_hash",
+            "txid",
+            "asset_type",
+            "sender",
+            "amount",
+            "nft_id",
+            "l1_contract_id",
+            "subnet_contract_id",
+            "asset_name",
+            "burn_header_hash",
+            "success",
+        ],
+        &[
+            SqlValue::text(&itx.index_block_hash),
+            SqlValue::text(&itx.txid),
+            SqlValue::text(asset_type),
+            op_field(op, "sender"),
+            op_field(op, "amount"),
+            op_field(op, "id"),
+            op_field(op, "l1_contract_id"),
+            op_field(op, "subnet_contract_id"),
+            op_field(op, "name"),
+            op_field(op, "burn_header_hash"),
+            SqlValue::opt_flag(result_success(&itx.result_hex)),
+        ],
+    ))
+}
+
+/// Rebuild a block's withdrawal keys from the contract events in its indexed receipts, the
+/// same way `generate_withdrawal_key_tuples` does from the receipts themselves
+pub(super) fn indexed_withdrawal_keys(
+    txs: &[IndexedTransaction],
+    block_height: u64,
+) -> Result<Vec<(Txid, Value)>, Error> {
+    let mut keys = vec![];
+    let mut withdrawal_id = 0;
+    for itx in txs.iter() {
+        for event in itx.events.iter() {
+            let parsed: NewBlockTxEvent =
+                serde_json::from_value(event.clone()).map_err(Error::JSONError)?;
+            let contract_event = match parsed.contract_event {
+                Some(contract_event) => contract_event,
+                None => continue,
+            };
+            let mut event = StacksTransactionEvent::SmartContractEvent(SmartContractEventData {
+                key: (contract_event.contract_identifier, contract_event.topic),
+                value: contract_event.value,
+            });
+            if let Some(key) = generate_key_from_event(&mut event, withdrawal_id, block_height) {
+                withdrawal_id += 1;
+                keys.push((itx.txid.clone(), key));
+            }
+        }
+    }
+    Ok(keys)
+}
+
+/// A field of a withdrawal key tuple, as text
+fn withdrawal_field(key: &Value, field: &str) -> SqlValue {
+    let value = match key {
+        Value::Tuple(tuple) => tuple.get(field),
+        _ => return SqlValue::Null,
+    };
+    match value {
+        Ok(Value::UInt(value)) => SqlValue::text(value),
+        Ok(Value::Principal(principal)) => SqlValue::text(principal),
+        Ok(Value::Sequence(SequenceData::String(CharType::ASCII(data)))) => {
+            SqlValue::text(String::from_utf8_lossy(&data.data))
+        }
+        Ok(value) => SqlValue::text(value),
+        Err(_) => SqlValue::Null,
+    }
+}
+
+fn withdrawal_sql(
+    block_id: &StacksBlockId,
+    withdrawal_id: usize,
+    txid: &Txid,
+    key: &Value,
+) -> String {
+    insert_sql(
+        "withdrawals",
+        &[
+            "index_block_hash",
+            "withdrawal_id",
+            "txid",
+            "withdrawal_type",
+            "recipient",
+            "amount",
+            "nft_id",
+            "asset_contract",
+            "destination",
+            "withdrawal_key",
+            "leaf_hash",
+        ],
+        &[
+            SqlValue::text(block_id),
+            SqlValue::Integer(withdrawal_id as u64),
+            SqlValue::text(txid),
+            withdrawal_field(key, "type"),
+            withdrawal_field(key, "recipient"),
+            withdrawal_field(key, "amount"),
+            withdrawal_field(key, "nft-id"),
+            withdrawal_field(key, "asset-contract"),
+            withdrawal_field(key, "destination"),
+            SqlValue::text(to_hex(&convert_withdrawal_key_to_bytes(key))),
+            SqlValue::text(withdrawal_leaf_hash(key)),
+        ],
+    )
+}
+
+/// Replays the blocks that are not in the transaction index, in a scratch copy of the
+/// chainstate that is made the first time one is needed, and removed when this is dropped
+struct BlockReplayer<'a> {
+    chainstate_path: &'a str,
+    scratch_root: PathBuf,
+    scratch_chainstate: Option<StacksChainState>,
+}
+
+impl<'a> BlockReplayer<'a> {
+    fn new(chainstate_path: &'a str) -> BlockReplayer<'a> {
+        BlockReplayer {
+            chainstate_path,
+            scratch_root: PathBuf::from(format!(
+                "{}.export",
+                chainstate_path.trim_end_matches('/')
+            )),
+            scratch_chainstate: None,
+        }
+    }
+
+    /// The receipts of the processed block `block_id`, as the transaction index would have
+    /// recorded them, or None if its data was pruned
+    fn replay(
+        &mut self,
+        sortdb: &SortitionDB,
+        block_id: &StacksBlockId,
+    ) -> Result<Option<Vec<IndexedTransaction>>, Error> {
+        if self.scratch_chainstate.is_none() {
+            info!("Replaying blocks that are not in the transaction index";
+                  "scratch_path" => %self.scratch_root.display());
+            make_staging_dir(&self.scratch_root)?;
+            stage_files(&PathBuf::from(self.chainstate_path), &self.scratch_root)?;
+            self.scratch_chainstate = Some(open_scratch_chainstate(
+                self.chainstate_path,
+                &self.scratch_root,
+            )?);
+        }
+        let scratch_chainstate = self
+            .scratch_chainstate
+            .as_mut()
+            .expect("FATAL: scratch chainstate not opened");
+        Ok(scratch_chainstate
+            .replay_processed_block(sortdb, block_id, false)?
+            .map(|replayed| {
+                replayed
+                    .epoch_receipt
+                    .tx_receipts
+                    .iter()
+                    .map(|receipt| IndexedTransaction::from_receipt(block_id, receipt))
+                    .collect()
+            }))
+    }
+}
+
+impl<'a> Drop for BlockReplayer<'a> {
+    fn drop(&mut self) {
+        if self.scratch_chainstate.take().is_some() {
+            let _ = fs::remove_dir_all(&self.scratch_root);
+        }
+    }
+}
+
+/// Generate the rows of a block that has not been exported before. Returns whether the block
+/// had to be replayed, along with the rows.
+fn block_statements(
+    chainstate: &StacksChainState,
+    replayer: &mut BlockReplayer,
+    sortdb: &SortitionDB,
+    header: &StacksHeaderInfo,
+) -> Result<(bool, Vec<Statement>), Error> {
+    let block_id = header.index_block_hash();
+    let block_hash = header.anchored_header.block_hash();
+    let mut indexed_txs =
+        StacksChainState::get_indexed_transactions_in_block(chainstate.db(), &block_id)?;
+    let indexed = !indexed_txs.is_empty();
+    // the boot block has no transactions, and no block to replay
+    let replayed = !indexed && header.stacks_block_height > 0;
+    if replayed {
+        indexed_txs = replayer.replay(sortdb, &block_id)?.unwrap_or_default();
+    }
+    let parent_block_id = StacksChainState::get_parent_block_id(chainstate.db(), &block_id)?;
+
+    let mut statements = vec![Statement::progress(insert_sql(
+        "blocks",
+        &[
+            "index_block_hash",
+            "block_hash",
+            "consensus_hash",
+            "parent_index_block_hash",
+            "block_height",
+            "burn_header_hash",
+            "burn_header_height",
+            "burn_header_timestamp",
+            "tx_merkle_root",
+            "state_index_root",
+            "withdrawal_merkle_root",
+            "indexed",
+            "canonical",
+        ],
+        &[
+            SqlValue::text(&block_id),
+            SqlValue::text(&block_hash),
+            SqlValue::text(&header.consensus_hash),
+            SqlValue::opt_text(parent_block_id.as_ref()),
+            SqlValue::Integer(header.stacks_block_height),
+            SqlValue::text(&header.burn_header_hash),
+            SqlValue::Integer(header.burn_header_height as u64),
+            SqlValue::Integer(header.burn_header_timestamp),
+            SqlValue::text(&header.anchored_header.tx_merkle_root),
+            SqlValue::text(&header.anchored_header.state_index_root),
+            SqlValue::text(&header.anchored_header.withdrawal_merkle_root),
+            SqlValue::flag(indexed),
+            SqlValue::flag(true),
+        ],
+    ))];
+
+    if !indexed_txs.is_empty() {
+        for (position, itx) in indexed_txs.iter().enumerate() {
+            statements.push(Statement::data(indexed_transaction_sql(position, itx)?));
+            for (event_index, event) in itx.events.iter().enumerate() {
+                statements.push(Statement::data(event_sql(itx, event_index, event)));
+            }
+            if let Some(sql) = deposit_sql(itx) {
+                statements.push(Statement::data(sql));
+            }
+        }
+
+        let keys = indexed_withdrawal_keys(&indexed_txs, header.anchored_header.total_work.work)?;
+        let leaves: Vec<Vec<u8>> = keys
+            .iter()
+            .map(|(_, key)| convert_withdrawal_key_to_bytes(key))
+            .collect();
+        let root = MerkleTree::<Sha512Trunc256Sum>::new(&leaves).root();
+        if root == header.anchored_header.withdrawal_merkle_root {
+            for (withdrawal_id, (txid, key)) in keys.iter().enumerate() {
+                statements.push(Statement::data(withdrawal_sql(
+                    &block_id,
+                    withdrawal_id,
+                    txid,
+                    key,
+                )));
+            }
+        } else {
+            warn!("Receipts do not reproduce the block's withdrawal root; not exporting its withdrawals";
+                  "index_block_hash" => %block_id,
+                  "expected" => %header.anchored_header.withdrawal_merkle_root,
+                  "computed" => %root);
+        }
+    }
+
+    if let Some(commit) = SortitionDB::get_block_commit_for_stacks_block(
+        sortdb.conn(),
+        &header.consensus_hash,
+        &block_hash,
+    )? {
+        statements.push(Statement::data(insert_sql(
+            "block_commits",
+            &[
+                "index_block_hash",
+                "txid",
+                "burn_header_hash",
+                "committed_block_hash",
+                "withdrawal_merkle_root",
+            ],
+            &[
+                SqlValue::text(&block_id),
+                SqlValue::text(&commit.txid),
+                SqlValue::text(&commit.burn_header_hash),
+                SqlValue::text(&commit.block_header_hash),
+                SqlValue::text(&commit.withdrawal_merkle_root),
+            ],
+        )));
+    }
+
+    Ok((replayed, statements))
+}
+
+pub(super) fn open_chainstate(chainstate_path: &str) -> Result<StacksChainState, Error> {
+    let db_config = {
+        let conn = sqlite_open(
+            StacksChainState::header_index_root_path(PathBuf::from(chainstate_path)),
+            OpenFlags::SQLITE_OPEN_READ_ONLY,
+            false,
+        )?;
+        StacksChainState::load_db_config(&conn)?
+    };
+    let (chainstate, _) =
+        StacksChainState::open(db_config.mainnet, db_config.chain_id, chainstate_path, None)?;
+    Ok(chainstate)
+}
+
+/// Export the canonical chain of the chainstate at `chainstate_path`, as of the canonical
+/// Stacks tip of the sortition DB at `sortdb_path`, to `output_path`. Resumes from the last
+/// export to `output_path`, if there is one: blocks that have since left the canonical chain
+/// are marked as such, and the export continues from where the chains agree.
+pub fn export_sql(
+    chainstate_path: &str,
+    sortdb_path: &str,
+    output_path: &str,
+    format: ExportFormat,
+) -> Result<ExportSummary, Error> {
+    let chainstate = open_chainstate(chainstate_path)?;
+    let sortdb = SortitionDB::open(sortdb_path, false)?;
+
+    let (consensus_hash, block_hash) =
+        SortitionDB::get_canonical_stacks_chain_tip_hash(sortdb.conn())?;
+    let tip = StacksBlockHeader::make_index_block_hash(&consensus_hash, &block_hash);
+    let tip_height =
+        StacksChainState::get_stacks_block_header_info_by_index_block_hash(chainstate.db(), &tip)?
+            .ok_or(Error::NoSuchBlockError)?
+            .stacks_block_height;
+
+    let ancestor_at = |height: u64| -> Result<Option<StacksBlockId>, Error> {
+        if height >= u32::MAX as u64 {
+            return Ok(None);
+        }
+        Ok(chainstate
+            .index_conn()?
+            .get_ancestor_block_hash(height, &tip)?)
+    };
+
+    let mut output = ExportOutput::open(output_path, &format)?;
+    let mut replayer = BlockReplayer::new(chainstate_path);
+    let mut summary = ExportSummary {
+        tip_index_block_hash: tip.clone(),
+        tip_height,
+        exported_blocks: 0,
+        orphaned_blocks: 0,
+        replayed_blocks: 0,
+    };
+
+    let start_height = match get_export_progress(&output.state)? {
+        Some((last_height, _)) => {
+            let fork_height = find_fork_height(
+                &output.state,
+                cmp::min(last_height, tip_height),
+                &ancestor_at,
+            )?;
+            if fork_height != Some(last_height) {
+                // the canonical chain has reorganized since the last export
+                let above: i64 = match fork_height {
+                    Some(height) => u64_to_sql(height)?,
+                    None => -1,
+                };
+                let args: &[&dyn ToSql] = &[&above];
+                let orphaned: i64 = output.state.query_row(
+                    "SELECT COUNT(*) FROM blocks WHERE canonical = 1 AND block_height > ?1",
+                    args,
+                    |row| row.get(0),
+                )?;
+                summary.orphaned_blocks = orphaned as u64;
+
+                let mut statements = vec![Statement::progress(format!(
+                    "UPDATE blocks SET canonical = 0 WHERE canonical = 1 AND block_height > {}",
+                    above
+                ))];
+                match fork_height {
+                    Some(height) => {
+                        let block_id = ancestor_at(height)?.ok_or(Error::NoSuchBlockError)?;
+                        statements.extend(export_progress_statements(height, &block_id));
+                    }
+                    None => statements.push(Statement::progress(
+                        "DELETE FROM export_progress".to_string(),
+                    )),
+                }
+                info!("Canonical chain reorganized since the last export";
+                      "fork_height" => ?fork_height,
+                      "orphaned_blocks" => summary.orphaned_blocks);
+                output.apply(&statements)?;
+            }
+            fork_height.map(|height| height + 1).unwrap_or(0)
+        }
+        None => 0,
+    };
+
+    for height in start_height..=tip_height {
+        let block_id = ancestor_at(height)?.ok_or(Error::NoSuchBlockError)?;
+        let mut statements = if is_block_exported(&output.state, &block_id)? {
+            // exported in an earlier run, and canonical again
+            vec![Statement::progress(format!(
+                "UPDATE blocks SET canonical = 1 WHERE index_block_hash = {}",
+                SqlValue::text(&block_id).literal()
+            ))]
+        } else {
+            let header = StacksChainState::get_stacks_block_header_info_by_index_block_hash(
+                chainstate.db(),
+                &block_id,
+            )?
+            .ok_or(Error::NoSuchBlockError)?;
+            let (replayed, statements) =
+                block_statements(&chainstate, &mut replayer, &sortdb, &header)?;
+            if replayed {
+                summary.replayed_blocks += 1;
+            }
+            statements
+        };
+        statements.extend(export_progress_statements(height, &block_id));
+        output.apply(&statements)?;
+        summary.exported_blocks += 1;
+        debug!("Exported block"; "height" => height, "index_block_hash" => %block_id);
+    }
+
+    Ok(summary)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::chainstate::stacks::index::MarfTrieId;
+    use clarity::boot_util::boot_code_id;
+    use clarity::vm::costs::ExecutionCost;
+    use clarity::vm::types::{PrincipalData, TupleData};
+
+    fn block_id(height: u64, fork: u8) -> StacksBlockId {
+        let mut bytes = [fork; 32];
+        bytes[0..8].copy_from_slice(&height.to_be_bytes());
+        StacksBlockId::from_bytes(&bytes).unwrap()
+    }
+
+    fn exported_block_sql(height: u64, fork: u8) -> Statement {
+        Statement::progress(format!(
+            "INSERT INTO blocks (index_block_hash, block_hash, consensus_hash, block_height, burn_header_hash, burn_header_height, burn_header_timestamp, tx_merkle_root, state_index_root, withdrawal_merkle_root, indexed, canonical) VALUES ('{}', '', '', {}, '', 0, 0, '', '', '', 1, 1) ON CONFLICT DO NOTHING",
+            block_id(height, fork),
+            height
+        ))
+    }
+
+    #[test]
+    fn test_sql_literals() {
+        assert_eq!(SqlValue::Null.literal(), "NULL");
+        assert_eq!(SqlValue::Integer(42).literal(), "42");
+        assert_eq!(SqlValue::text("it's").literal(), "'it''s'");
+        assert_eq!(
+            insert_sql(
+                "t",
+                &["a", "b", "c"],
+                &[
+                    SqlValue::flag(true),
+                    SqlValue::opt_text(None::<u8>),
+                    SqlValue::text("x")
+                ]
+            ),
+            "INSERT INTO t (a, b, c) VALUES (1, NULL, 'x') ON CONFLICT DO NOTHING"
+        );
+
+        // quoted text round-trips through SQLite
+        let conn = Connection::open_in_memory().unwrap();
+        let text = "{\"a\": \"it's\"}";
+        let read: String = conn
+            .query_row(
+                &format!("SELECT {}", SqlValue::text(text).literal()),
+                NO_PARAMS,
+                |row| row.get(0),
+            )
+            .unwrap();
+        assert_eq!(read, text);
+    }
+
+    #[test]
+    fn test_export_output_formats() {
+        let dir = "/tmp/stacks-node-tests/test_export_output_formats";
+        let _ = fs::remove_dir_all(dir);
+        fs::create_dir_all(dir).unwrap();
+        let sqlite_path = format!("{}/export.sqlite", dir);
+        let dump_path = format!("{}/export.sql", dir);
+
+        let mut statements = vec![exported_block_sql(0, 0)];
+        statements.push(Statement::data(insert_sql(
+            "block_commits",
+            &[
+                "index_block_hash",
+                "txid",
+                "burn_header_hash",
+                "committed_block_hash",
+                "withdrawal_merkle_root",
+            ],
+            &[
+                SqlValue::text(block_id(0, 0)),
+                SqlValue::text("aa"),
+                SqlValue::text("bb"),
+                SqlValue::text("cc"),
+                SqlValue::text("dd"),
+            ],
+        )));
+        statements.extend(export_progress_statements(0, &block_id(0, 0)));
+
+        let mut sqlite = ExportOutput::open(&sqlite_path, &ExportFormat::Sqlite).unwrap();
+        sqlite.apply(&statements).unwrap();
+        let mut dump = ExportOutput::open(&dump_path, &ExportFormat::SqlDump).unwrap();
+        dump.apply(&statements).unwrap();
+
+        for state in [&sqlite.state, &dump.state] {
+            assert_eq!(
+                get_export_progress(state).unwrap(),
+                Some((0, block_id(0, 0)))
+            );
+            assert!(is_block_exported(state, &block_id(0, 0)).unwrap());
+        }
+
+        // the progress DB of a script only tracks progress
+        let count_commits = |conn: &Connection| -> i64 {
+            conn.query_row("SELECT COUNT(*) FROM block_commits", NO_PARAMS, |row| {
+                row.get(0)
+            })
+            .unwrap()
+        };
+        assert_eq!(count_commits(&sqlite.state), 1);
+        assert_eq!(count_commits(&dump.state), 0);
+
+        // the script rebuilds the same DB, and can be replayed
+        let script = fs::read_to_string(&dump_path).unwrap();
+        let replayed = Connection::open_in_memory().unwrap();
+        replayed.execute_batch(&script).unwrap();
+        replayed.execute_batch(&script).unwrap();
+        assert_eq!(count_commits(&replayed), 1);
+        assert_eq!(
+            get_export_progress(&replayed).unwrap(),
+            Some((0, block_id(0, 0)))
+        );
+
+        // reopening doesn't repeat the schema
+        drop(dump);
+        let dump = ExportOutput::open(&dump_path, &ExportFormat::SqlDump).unwrap();
+        assert_eq!(fs::read_to_string(&dump_path).unwrap(), script);
+        assert_eq!(
+            get_export_progress(&dump.state).unwrap(),
+            Some((0, block_id(0, 0)))
+        );
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_find_fork_height() {
+        let conn = Connection::open_in_memory().unwrap();
+        for cmd in EXPORT_SCHEMA.iter() {
+            conn.execute_batch(cmd).unwrap();
+        }
+        for height in 0..5 {
+            conn.execute_batch(&exported_block_sql(height, 0).sql)
+                .unwrap();
+        }
+
+        // same chain, longer
+        let same =
+            |height: u64| -> Result<Option<StacksBlockId>, Error> { Ok(Some(block_id(height, 0))) };
+        assert_eq!(find_fork_height(&conn, 4, &same).unwrap(), Some(4));
+
+        // forked off after height 2
+        let forked = |height: u64| -> Result<Option<StacksBlockId>, Error> {
+            Ok(Some(block_id(height, if height <= 2 { 0 } else { 1 })))
+        };
+        assert_eq!(find_fork_height(&conn, 4, &forked).unwrap(), Some(2));
+
+        // a different chain altogether
+        let other =
+            |height: u64| -> Result<Option<StacksBlockId>, Error> { Ok(Some(block_id(height, 2))) };
+        assert_eq!(find_fork_height(&conn, 4, &other).unwrap(), None);
+
+        // orphaned blocks are no longer part of the exported chain
+        conn.execute_batch("UPDATE blocks SET canonical = 0 WHERE block_height > 2")
+            .unwrap();
+        assert_eq!(find_fork_height(&conn, 4, &same).unwrap(), Some(2));
+        assert_eq!(get_exported_canonical_block(&conn, 3).unwrap(), None);
+        assert!(is_block_exported(&conn, &block_id(3, 0)).unwrap());
+    }
+
+    #[test]
+    fn test_indexed_withdrawal_keys() {
+        let sender = PrincipalData::parse("ST1PQHQKV0RJXZFY1DGX8MNSNYVE3VGZJSRTPGZGM").unwrap();
+        let contract =
+            PrincipalData::parse("ST1PQHQKV0RJXZFY1DGX8MNSNYVE3VGZJSRTPGZGM.simple-ft").unwrap();
+        let print_event = |fields: Vec<(&str, Value)>| {
+            StacksTransactionEvent::SmartContractEvent(SmartContractEventData {
+                key: (boot_code_id("subnet", false), "print".into()),
+                value: Value::Tuple(
+                    TupleData::from_data(
+                        fields
+                            .into_iter()
+                            .map(|(name, value)| (name.into(), value))
+                            .collect(),
+                    )
+                    .unwrap(),
+                ),
+            })
+        };
+        let events = vec![
+            print_event(vec![
+                (
+                    "type",
+                    Value::string_ascii_from_bytes(b"stx".to_vec()).unwrap(),
+                ),
+                ("sender", Value::Principal(sender.clone())),
+                ("amount", Value::UInt(10)),
+            ]),
+            // not a withdrawal
+            StacksTransactionEvent::SmartContractEvent(SmartContractEventData {
+                key: (boot_code_id("costs", false), "print".into()),
+                value: Value::UInt(1),
+            }),
+            print_event(vec![
+                (
+                    "type",
+                    Value::string_ascii_from_bytes(b"ft".to_vec()).unwrap(),
+                ),
+                ("asset-contract", Value::Principal(contract.clone())),
+                ("sender", Value::Principal(sender.clone())),
+                ("amount", Value::UInt(20)),
+            ]),
+        ];
+
+        let txid = Txid([0x11; 32]);
+        let itx = IndexedTransaction {
+            txid: txid.clone(),
+            index_block_hash: StacksBlockId::sentinel(),
+            tx_hex: "".to_string(),
+            burnchain_op: None,
+            tx_index: 0,
+            microblock_hash: None,
+            result_hex: "".to_string(),
+            post_condition_aborted: false,
+            stx_burned: 0,
+            events: events
+                .iter()
+                .enumerate()
+                .map(|(event_index, event)| event.json_serialize(event_index, &txid, true))
+                .collect(),
+            execution_cost: ExecutionCost::zero(),
+        };
+
+        let mut expected = vec![];
+        let mut withdrawal_id = 0;
+        for mut event in events.into_iter() {
+            if let Some(key) = generate_key_from_event(&mut event, withdrawal_id, 5) {
+                withdrawal_id += 1;
+                expected.push((txid.clone(), key));
+            }
+        }
+        assert_eq!(expected.len(), 2);
+        let keys = indexed_withdrawal_keys(&[itx], 5).unwrap();
+        assert_eq!(keys, expected);
+
+        let sql = withdrawal_sql(&StacksBlockId::sentinel(), 1, &txid, &keys[1].1);
+        assert!(sql.contains("'ft'"));
+        assert!(sql.contains(&format!("'{}'", &contract)));
+        assert!(sql.contains("'20'"));
+    }
+}
@@ -0,0 +1,585 @@
+// Copyright (C) 2013-2020 Blockstack PBC, a public benefit corporation
+// Copyright (C) 2020 Stacks Open Internet Foundation
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Consistency checks across a node's chainstate, MARFs, sortition DB and mempool, for use
+//! after a disk incident. The checks are:
+//!
+//! * every DB passes SQLite's `PRAGMA integrity_check`, and its schema version is one this
+//!   node supports;
+//! * every processed block's header has the roots of its tries in the headers MARF and the
+//!   Clarity MARF;
+//! * every processed block's tries rehash to the hashes stored in their nodes, and the value
+//!   of each of their leaves is in the MARF's side table and hashes to the leaf. The
+//!   `metadata_table` of the Clarity MARF is not covered;
+//! * every processed block's withdrawal root is that of its receipts' withdrawal tree. The
+//!   receipts come from the transaction index, or else from replaying the block, if asked to;
+//!   otherwise the block's withdrawal root goes unchecked. Blocks are replayed in a scratch
+//!   copy of the chainstate, as `replay-block` does, so replaying never writes to the
+//!   chainstate being checked;
+//! * every processed block was committed to by a block commit in the sortition DB;
+//! * no staging rows are left over for blocks or microblocks that are gone.
+//!
+//! The DBs are opened read-only, so that checking never runs a schema migration or otherwise
+//! writes to them. Repair mode only deletes leftover staging rows whose data is gone, which the
+//! node never reads again. Everything else is reported for an operator to deal with, typically
+//! by restoring from a snapshot.
+
+use std::fs;
+use std::path::PathBuf;
+
+use rusqlite::types::ToSql;
+use rusqlite::{Connection, OpenFlags, NO_PARAMS};
+
+use crate::chainstate::burn::db::sortdb::SortitionDB;
+use crate::chainstate::stacks::db::export::indexed_withdrawal_keys;
+use crate::chainstate::stacks::db::replay::open_scratch_chainstate;
+use crate::chainstate::stacks::db::snapshot::{
+    make_staging_dir, open_marf_readonly, stage_files, verify_trie_contents, CLARITY_VALUE_QUERY,
+    HEADERS_VALUE_QUERY,
+};
+use crate::chainstate::stacks::db::{StacksChainState, StacksHeaderInfo};
+use crate::chainstate::stacks::index::marf::{MarfConnection, MARF};
+use crate::chainstate::stacks::Error;
+use crate::clarity_vm::withdrawal::convert_withdrawal_key_to_bytes;
+use crate::core::mempool::{MemPoolDB, MEMPOOL_SCHEMA_VERSION};
+use crate::core::StacksEpoch;
+use crate::util_lib::db::{query_row, query_rows, sqlite_open, tx_begin_immediate, u64_to_sql};
+use stacks_common::types::chainstate::StacksBlockId;
+use stacks_common::util::hash::{MerkleTree, Sha512Trunc256Sum};
+
+/// An inconsistency found by `fsck`
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct FsckIssue {
+    /// The check that found it
+    pub check: String,
+    /// What it concerns, e.g. an index block hash or a DB
+    pub subject: String,
+    pub detail: String,
+    /// Whether repair mode fixed it
+    pub repaired: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Default, Serialize)]
+pub struct FsckReport {
+    pub checked_blocks: u64,
+    /// Blocks whose withdrawal root went unchecked, since they were processed without the
+    /// transaction index and were not replayed (or their data was pruned)
+    pub unchecked_withdrawal_roots: u64,
+    pub issues: Vec<FsckIssue>,
+}
+
+impl FsckReport {
+    fn add_issue(&mut self, check: &str, subject: &str, detail: String) {
+        warn!("fsck: {} check failed for {}: {}", check, subject, &detail);
+        self.issues.push(FsckIssue {
+            check: check.to_string(),
+            subject: subject.to_string(),
+            detail,
+            repaired: false,
+        });
+    }
+
+    /// Are the DBs consistent, now that the repairable issues have been repaired?
+    pub fn is_consistent(&self) -> bool {
+        self.issues.iter().all(|issue| issue.repaired)
+    }
+}
+
+/// Staging rows that are left over once the data they refer to is gone
+struct StagingCheck {
+    description: &'static str,
+    /// Selects the leftover rows' keys
+    select: &'static str,
+    /// Deletes the leftover rows, if the node can do without them
+    delete: Option<&'static str>,
+}
+
+/// The staging checks. Microblock rows are checked before microblock data rows, so the data
+/// of the microblock rows repair deletes is deleted in the same pass.
+const STAGING_CHECKS: &[StagingCheck] = &[
+    StagingCheck {
+        description: "Microblock whose parent anchored block is not stored",
+        select: "SELECT index_microblock_hash FROM staging_microblocks \
+                 WHERE index_block_hash NOT IN (SELECT index_block_hash FROM staging_blocks)",
+        delete: Some(
+            "DELETE FROM staging_microblocks \
+             WHERE index_block_hash NOT IN (SELECT index_block_hash FROM staging_blocks)",
+        ),
+    },
+    StagingCheck {
+        description: "Microblock data with no staging microblock",
+        select: "SELECT block_hash FROM staging_microblocks_data \
+                 WHERE block_hash NOT IN (SELECT microblock_hash FROM staging_microblocks)",
+        delete: Some(
+            "DELETE FROM staging_microblocks_data \
+             WHERE block_hash NOT IN (SELECT microblock_hash FROM staging_microblocks)",
+        ),
+    },
+    StagingCheck {
+        description: "User burn support for a block that is not stored",
+        select: "SELECT anchored_block_hash FROM staging_user_burn_support AS s \
+                 WHERE NOT EXISTS (SELECT 1 FROM staging_blocks AS b \
+                 WHERE b.anchored_block_hash = s.anchored_block_hash \
+                 AND b.consensus_hash = s.consensus_hash)",
+        delete: Some(
+            "DELETE FROM staging_user_burn_support \
+             WHERE NOT EXISTS (SELECT 1 FROM staging_blocks AS b \
+             WHERE b.anchored_block_hash = staging_user_burn_support.anchored_block_hash \
+             AND b.consensus_hash = staging_user_burn_support.consensus_hash)",
+        ),
+    },
+    StagingCheck {
+        description: "Indexed transaction of a block with no header",
+        select: "SELECT txid FROM transactions \
+                 WHERE index_block_hash NOT IN (SELECT index_block_hash FROM block_headers)",
+        delete: Some(
+            "DELETE FROM transactions \
+             WHERE index_block_hash NOT IN (SELECT index_block_hash FROM block_headers)",
+        ),
+    },
+    StagingCheck {
+        description: "Processed block with no header",
+        select: "SELECT index_block_hash FROM staging_blocks \
+                 WHERE processed = 1 AND orphaned = 0 \
+                 AND index_block_hash NOT IN (SELECT index_block_hash FROM block_headers)",
+        delete: None,
+    },
+    StagingCheck {
+        description: "Block header with no staging block",
+        select: "SELECT index_block_hash FROM block_headers \
+                 WHERE block_height > 0 \
+                 AND index_block_hash NOT IN (SELECT index_block_hash FROM staging_blocks)",
+        delete: None,
+    },
+];
+
+/// The first column of each row of `sql`, as text
+fn query_strings(conn: &Connection, sql: &str) -> Result<Vec<String>, Error> {
+    let mut stmt = conn.prepare(sql)?;
+    let rows = stmt.query_map(NO_PARAMS, |row| row.get::<_, String>(0))?;
+    let mut strings = vec![];
+    for row in rows {
+        strings.push(row?);
+    }
+    Ok(strings)
+}
+
+fn check_integrity(report: &mut FsckReport, name: &str, conn: &Connection) -> Result<(), Error> {
+    let results = query_strings(conn, "PRAGMA integrity_check")?;
+    if results != vec!["ok".to_string()] {
+        report.add_issue("integrity", name, results.join("; "));
+    }
+    Ok(())
+}
+
+fn check_mempool_version(report: &mut FsckReport, conn: &Connection) -> Result<(), Error> {
+    let version = MemPoolDB::get_schema_version(conn)?.unwrap_or(1);
+    if version > MEMPOOL_SCHEMA_VERSION {
+        report.add_issue(
+            "schema_version",
+            "mempool",
+            format!(
+                "Mempool schema version {} is newer than this node's ({})",
+                version, MEMPOOL_SCHEMA_VERSION
+            ),
+        );
+    }
+    Ok(())
+}
+
+/// Check the schema versions of the sortition DB and the chainstate against the current epoch,
+/// as the node does on startup, without opening either DB for writing
+fn check_db_versions(
+    report: &mut FsckReport,
+    sortdb_conn: &Connection,
+    headers_conn: &Connection,
+    chainstate_path: &str,
+) -> Result<(), Error> {
+    let epochs = SortitionDB::get_stacks_epochs(sortdb_conn)?;
+    let burn_height: Option<u64> = query_row(
+        sortdb_conn,
+        "SELECT MAX(block_height) FROM snapshots",
+        NO_PARAMS,
+    )?;
+    let burn_height = burn_height.unwrap_or(0);
+    let epoch_id = match StacksEpoch::find_epoch(&epochs, burn_height) {
+        Some(ix) => epochs[ix].epoch_id,
+        None => {
+            report.add_issue(
+                "schema_version",
+                "sortition DB",
+                format!("No epoch for burn height {}", burn_height),
+            );
+            return Ok(());
+        }
+    };
+
+    let sortdb_version: Option<String> =
+        query_row(sortdb_conn, "SELECT MAX(version) FROM db_config", NO_PARAMS)?;
+    match sortdb_version {
+        Some(version) if SortitionDB::is_db_version_supported_in_epoch(epoch_id, &version) => {}
+        version => report.add_issue(
+            "schema_version",
+            "sortition DB",
+            format!(
+                "Sortition DB schema version {:?} does not support epoch {}",
+                version, epoch_id
+            ),
+        ),
+    }
+
+    let db_config = StacksChainState::load_db_config(headers_conn)?;
+    if !db_config.supports_epoch(epoch_id) {
+        report.add_issue(
+            "schema_version",
+            chainstate_path,
+            format!(
+                "Chainstate schema version {} does not support epoch {}",
+                &db_config.version, epoch_id
+            ),
+        );
+    }
+    Ok(())
+}
+
+/// Check for leftover staging rows in the chainstate DB `conn`, and delete the ones that can
+/// be deleted if `repair` is set
+fn check_staging(report: &mut FsckReport, conn: &Connection, repair: bool) -> Result<(), Error> {
+    for check in STAGING_CHECKS.iter() {
+        let leftovers = query_strings(conn, check.select)?;
+        if leftovers.is_empty() {
+            continue;
+        }
+        let repaired = match check.delete {
+            Some(delete) if repair => {
+                conn.execute(delete, NO_PARAMS)?;
+                true
+            }
+            _ => false,
+        };
+        for key in leftovers.iter() {
+            report.add_issue("staging", key, check.description.to_string());
+            if let Some(issue) = report.issues.last_mut() {
+                issue.repaired = repaired;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Is `header`'s block committed to by the winning block commit of its sortition?
+fn has_block_commit(sortdb_conn: &Connection, header: &StacksHeaderInfo) -> Result<bool, Error> {
+    let sql = "SELECT COUNT(*) FROM block_commits JOIN snapshots \
+               ON block_commits.sortition_id = snapshots.sortition_id \
+               AND block_commits.txid = snapshots.winning_block_txid \
+               WHERE snapshots.consensus_hash = ?1 AND block_commits.committed_block_hash = ?2";
+    let args: &[&dyn ToSql] = &[&header.consensus_hash, &header.anchored_header.block_hash()];
+    let count: Option<u64> = query_row(sortdb_conn, sql, args)?;
+    Ok(count.unwrap_or(0) > 0)
+}
+
+/// The withdrawal root of a block's receipts, or None if they are not available. Blocks
+/// missing from the transaction index are replayed in the scratch chainstate, if given.
+fn recompute_withdrawal_root(
+    headers_conn: &Connection,
+    replayer: Option<(&mut StacksChainState, &SortitionDB)>,
+    header: &StacksHeaderInfo,
+) -> Result<Option<Sha512Trunc256Sum>, Error> {
+    let block_id = header.index_block_hash();
+    let indexed_txs = StacksChainState::get_indexed_transactions_in_block(headers_conn, &block_id)?;
+    if !indexed_txs.is_empty() {
+        let keys = indexed_withdrawal_keys(&indexed_txs, header.anchored_header.total_work.work)?;
+        let leaves: Vec<Vec<u8>> = keys
+            .iter()
+            .map(|(_, key)| convert_withdrawal_key_to_bytes(key))
+            .collect();
+        return Ok(Some(MerkleTree::<Sha512Trunc256Sum>::new(&leaves).root()));
+    }
+    let (scratch_chainstate, sortdb) = match replayer {
+        Some(replayer) => replayer,
+        None => return Ok(None),
+    };
+    Ok(scratch_chainstate
+        .replay_processed_block(sortdb, &block_id, false)?
+        .map(|replayed| replayed.withdrawal_root))
+}
+
+/// Rehash the trie of `block_id` in `marf`, and check its leaves against the values in the
+/// side table that `value_query` looks them up in. A missing trie is left to the root checks.
+fn check_trie_contents(
+    report: &mut FsckReport,
+    check: &str,
+    marf: &mut MARF<StacksBlockId>,
+    value_query: &str,
+    block_id: &StacksBlockId,
+) {
+    let height = match marf.get_block_height(block_id, block_id) {
+        Ok(Some(height)) => height,
+        _ => return,
+    };
+    if let Err(e) = verify_trie_contents(marf, value_query, block_id, height) {
+        report.add_issue(check, &block_id.to_string(), e.to_string());
+    }
+}
+
+fn check_block(
+    report: &mut FsckReport,
+    headers_marf: &mut MARF<StacksBlockId>,
+    clarity_marf: &mut MARF<StacksBlockId>,
+    replayer: Option<(&mut StacksChainState, &SortitionDB)>,
+    sortdb_conn: &Connection,
+    header: &StacksHeaderInfo,
+) -> Result<(), Error> {
+    let block_id = header.index_block_hash();
+    let subject = block_id.to_string();
+    report.checked_blocks += 1;
+
+    match headers_marf.get_root_hash_at(&block_id) {
+        Ok(root) if root == header.index_root => {}
+        Ok(root) => report.add_issue(
+            "index_root",
+            &subject,
+            format!(
+                "Headers MARF root is {}, but the header has {}",
+                root, &header.index_root
+            ),
+        ),
+        Err(e) => report.add_issue("index_root", &subject, format!("No headers trie: {}", e)),
+    }
+    check_trie_contents(
+        report,
+        "index_trie",
+        headers_marf,
+        HEADERS_VALUE_QUERY,
+        &block_id,
+    );
+
+    // the boot block has no block commit, receipts or state root in its header
+    if header.stacks_block_height == 0 {
+        return Ok(());
+    }
+
+    match clarity_marf.get_root_hash_at(&block_id) {
+        Ok(root) if root == header.anchored_header.state_index_root => {}
+        Ok(root) => report.add_issue(
+            "state_root",
+            &subject,
+            format!(
+                "Clarity MARF root is {}, but the header has {}",
+                root, &header.anchored_header.state_index_root
+            ),
+        ),
+        Err(e) => report.add_issue("state_root", &subject, format!("No Clarity trie: {}", e)),
+    }
+    check_trie_contents(
+        report,
+        "state_trie",
+        clarity_marf,
+        CLARITY_VALUE_QUERY,
+        &block_id,
+    );
+
+    match recompute_withdrawal_root(headers_marf.sqlite_conn(), replayer, header) {
+        Ok(Some(root)) if root == header.anchored_header.withdrawal_merkle_root => {}
+        Ok(Some(root)) => report.add_issue(
+            "withdrawal_root",
+            &subject,
+            format!(
+                "Receipts have withdrawal root {}, but the header has {}",
+                root, &header.anchored_header.withdrawal_merkle_root
+            ),
+        ),
+        Ok(None) => report.unchecked_withdrawal_roots += 1,
+        Err(e) => report.add_issue(
+            "withdrawal_root",
+            &subject,
+            format!("Failed to recompute the withdrawal root: {}", e),
+        ),
+    }
+
+    if !has_block_commit(sortdb_conn, header)? {
+        report.add_issue(
+            "block_commit",
+            &subject,
+            format!(
+                "No block commit for block {} in sortition {}",
+                &header.anchored_header.block_hash(),
+                &header.consensus_hash
+            ),
+        );
+    }
+    Ok(())
+}
+
+/// Check the chainstate at `chainstate_path` (and its mempool) against itself and the
+/// sortition DB at `sortdb_path`. If `replay` is set, blocks that were processed without the
+/// transaction index are replayed to check their withdrawal roots, in a copy of the
+/// chainstate's DBs which is removed afterwards; replaying reads the sortition DB through a
+/// read-only `SortitionDB` handle. If `repair` is set, leftover staging rows are deleted.
+pub fn fsck(
+    chainstate_path: &str,
+    sortdb_path: &str,
+    replay: bool,
+    repair: bool,
+) -> Result<FsckReport, Error> {
+    if !replay {
+        return check_chainstate(chainstate_path, sortdb_path, None, repair);
+    }
+    let scratch_root = PathBuf::from(format!("{}.fsck", chainstate_path.trim_end_matches('/')));
+    make_staging_dir(&scratch_root)?;
+    let result = stage_files(&PathBuf::from(chainstate_path), &scratch_root)
+        .and_then(|_| open_scratch_chainstate(chainstate_path, &scratch_root))
+        .and_then(|mut scratch_chainstate| {
+            let sortdb = SortitionDB::open(sortdb_path, false)?;
+            check_chainstate(
+                chainstate_path,
+                sortdb_path,
+                Some((&mut scratch_chainstate, &sortdb)),
+                repair,
+            )
+        });
+    let _ = fs::remove_dir_all(&scratch_root);
+    result
+}
+
+fn check_chainstate(
+    chainstate_path: &str,
+    sortdb_path: &str,
+    mut replayer: Option<(&mut StacksChainState, &SortitionDB)>,
+    repair: bool,
+) -> Result<FsckReport, Error> {
+    let mut report = FsckReport::default();
+    // the sortition DB's index is at `<sortdb_path>/marf.sqlite`
+    let sortdb_conn = sqlite_open(
+        PathBuf::from(sortdb_path).join("marf.sqlite"),
+        OpenFlags::SQLITE_OPEN_READ_ONLY,
+        false,
+    )?;
+    let headers_marf_path = StacksChainState::header_index_root_path(chainstate_path.into());
+    let mut headers_marf = open_marf_readonly(&headers_marf_path)?;
+    let mut clarity_marf = open_marf_readonly(&StacksChainState::vm_state_index_marf_path(
+        chainstate_path.into(),
+    ))?;
+
+    check_db_versions(
+        &mut report,
+        &sortdb_conn,
+        headers_marf.sqlite_conn(),
+        chainstate_path,
+    )?;
+    check_integrity(&mut report, "sortition DB", &sortdb_conn)?;
+    check_integrity(&mut report, "headers DB", headers_marf.sqlite_conn())?;
+    check_integrity(&mut report, "Clarity MARF", clarity_marf.sqlite_conn())?;
+
+    let mempool_path = MemPoolDB::db_path(chainstate_path)?;
+    if fs::metadata(&mempool_path).is_ok() {
+        let mempool_conn = sqlite_open(&mempool_path, OpenFlags::SQLITE_OPEN_READ_ONLY, false)?;
+        check_integrity(&mut report, "mempool", &mempool_conn)?;
+        check_mempool_version(&mut report, &mempool_conn)?;
+    }
+
+    let max_height: Option<u64> = query_row(
+        headers_marf.sqlite_conn(),
+        "SELECT MAX(block_height) FROM block_headers",
+        NO_PARAMS,
+    )?;
+    for height in 0..=max_height.unwrap_or(0) {
+        let args: &[&dyn ToSql] = &[&u64_to_sql(height)?];
+        let headers: Vec<StacksHeaderInfo> = query_rows(
+            headers_marf.sqlite_conn(),
+            "SELECT * FROM block_headers WHERE block_height = ?1",
+            args,
+        )?;
+        for header in headers.iter() {
+            check_block(
+                &mut report,
+                &mut headers_marf,
+                &mut clarity_marf,
+                replayer
+                    .as_mut()
+                    .map(|(scratch_chainstate, sortdb)| (&mut **scratch_chainstate, *sortdb)),
+                &sortdb_conn,
+                header,
+            )?;
+        }
+    }
+
+    if repair {
+        let mut conn = sqlite_open(&headers_marf_path, OpenFlags::SQLITE_OPEN_READ_WRITE, false)?;
+        let tx = tx_begin_immediate(&mut conn)?;
+        check_staging(&mut report, &tx, true)?;
+        tx.commit()?;
+    } else {
+        check_staging(&mut report, headers_marf.sqlite_conn(), false)?;
+    }
+
+    Ok(report)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::chainstate::stacks::db::test::instantiate_chainstate;
+
+    #[test]
+    fn test_check_staging() {
+        let chainstate = instantiate_chainstate(false, 0x80000000, "fsck-check-staging");
+        let conn = chainstate.db();
+
+        let mut report = FsckReport::default();
+        check_staging(&mut report, conn, false).unwrap();
+        assert_eq!(report, FsckReport::default());
+
+        // a microblock with no parent block, and microblock data with no microblock
+        conn.execute(
+            "INSERT INTO staging_microblocks (anchored_block_hash, consensus_hash, \
+             index_block_hash, microblock_hash, parent_hash, index_microblock_hash, sequence, \
+             processed, orphaned) VALUES ('11', '22', '33', '44', '55', '66', 0, 0, 0)",
+            NO_PARAMS,
+        )
+        .unwrap();
+        conn.execute(
+            "INSERT INTO staging_microblocks_data (block_hash, block_data) \
+             VALUES ('44', X'01'), ('77', X'02')",
+            NO_PARAMS,
+        )
+        .unwrap();
+
+        check_staging(&mut report, conn, false).unwrap();
+        let subjects: Vec<_> = report
+            .issues
+            .iter()
+            .map(|issue| (issue.subject.as_str(), issue.repaired))
+            .collect();
+        assert_eq!(subjects, vec![("66", false), ("77", false)]);
+        assert!(!report.is_consistent());
+
+        // repair removes the microblock, and then both data rows
+        let mut report = FsckReport::default();
+        check_staging(&mut report, conn, true).unwrap();
+        let subjects: Vec<_> = report
+            .issues
+            .iter()
+            .map(|issue| (issue.subject.as_str(), issue.repaired))
+            .collect();
+        assert_eq!(subjects, vec![("66", true), ("44", true), ("77", true)]);
+        assert!(report.is_consistent());
+
+        let mut report = FsckReport::default();
+        check_staging(&mut report, conn, false).unwrap();
+        assert_eq!(report, FsckReport::default());
+    }
+}
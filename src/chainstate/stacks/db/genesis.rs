@@ -0,0 +1,478 @@
+// Copyright (C) 2013-2020 Blockstack PBC, a public benefit corporation
+// Copyright (C) 2020 Stacks Open Internet Foundation
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+//! A genesis manifest describes the initial state of a subnet: STX balances, asset mappings
+//! between the L1 and the subnet, and contracts deployed at boot. Every node of a subnet must
+//! boot from the same manifest, so its hash is stored in the headers index of the genesis block.
+
+use std::convert::TryFrom;
+
+use crate::burnchains::{AssetType, Txid};
+use crate::chainstate::burn::operations::{pd_deserialize, qc_deserialize, RegisterAssetOp};
+use crate::chainstate::stacks::db::accounts::FeeAsset;
+use crate::chainstate::stacks::db::*;
+use crate::chainstate::stacks::events::StacksTransactionReceipt;
+use crate::chainstate::stacks::Error;
+use crate::chainstate::stacks::*;
+use crate::core::{FIRST_BURNCHAIN_CONSENSUS_HASH, FIRST_STACKS_BLOCK_HASH};
+use crate::util_lib::boot::{boot_code_addr, boot_code_id};
+use crate::util_lib::strings::StacksString;
+use clarity::vm::clarity::TransactionConnection;
+use clarity::vm::representations::ContractName;
+use clarity::vm::types::{PrincipalData, QualifiedContractIdentifier};
+use clarity::vm::Value;
+use stacks_common::types::chainstate::BurnchainHeaderHash;
+use stacks_common::util::hash::Sha512Trunc256Sum;
+
+/// Key in the headers index of the genesis block under which the manifest hash is stored
+pub const GENESIS_MANIFEST_HASH_KEY: &str = "chainstate::genesis_manifest_hash";
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct GenesisBalance {
+    #[serde(deserialize_with = "pd_deserialize")]
+    pub address: PrincipalData,
+    pub amount: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct GenesisAsset {
+    pub asset_type: AssetType,
+    /// Contract ID of the asset on the L1 chain
+    #[serde(deserialize_with = "qc_deserialize")]
+    pub l1_contract_id: QualifiedContractIdentifier,
+    /// Contract ID of the asset on the subnet
+    #[serde(deserialize_with = "qc_deserialize")]
+    pub l2_contract_id: QualifiedContractIdentifier,
+}
+
+/// A contract deployed by the boot code address in the genesis block
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct GenesisContract {
+    pub name: String,
+    pub source: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Default, Deserialize)]
+pub struct GenesisManifest {
+    #[serde(default)]
+    pub balances: Vec<GenesisBalance>,
+    #[serde(default)]
+    pub assets: Vec<GenesisAsset>,
+    #[serde(default)]
+    pub contracts: Vec<GenesisContract>,
+    /// Fungible token, registered in `assets`, that transaction fees are paid in instead of STX
+    #[serde(default)]
+    pub fee_asset: Option<FeeAsset>,
+}
+
+impl GenesisManifest {
+    /// Parse a manifest from its JSON representation
+    pub fn from_json(content: &str) -> Result<GenesisManifest, Error> {
+        serde_json::from_str(content).map_err(Error::JSONError)
+    }
+
+    /// Hash of the manifest contents. Entries are hashed in manifest order, since that is the
+    /// order in which they are applied.
+    pub fn hash(&self) -> Sha512Trunc256Sum {
+        let mut bytes = vec![];
+
+        bytes.extend_from_slice(&(self.balances.len() as u32).to_be_bytes());
+        for balance in self.balances.iter() {
+            bytes.extend(Value::Principal(balance.address.clone()).serialize_to_vec());
+            bytes.extend_from_slice(&balance.amount.to_be_bytes());
+        }
+
+        bytes.extend_from_slice(&(self.assets.len() as u32).to_be_bytes());
+        for asset in self.assets.iter() {
+            let asset_type = asset.asset_type.to_string();
+            bytes.extend_from_slice(&(asset_type.len() as u32).to_be_bytes());
+            bytes.extend_from_slice(asset_type.as_bytes());
+            bytes.extend(Value::Principal(asset.l1_contract_id.clone().into()).serialize_to_vec());
+            bytes.extend(Value::Principal(asset.l2_contract_id.clone().into()).serialize_to_vec());
+        }
+
+        bytes.extend_from_slice(&(self.contracts.len() as u32).to_be_bytes());
+        for contract in self.contracts.iter() {
+            bytes.extend_from_slice(&(contract.name.len() as u32).to_be_bytes());
+            bytes.extend_from_slice(contract.name.as_bytes());
+            bytes.extend_from_slice(&(contract.source.len() as u32).to_be_bytes());
+            bytes.extend_from_slice(contract.source.as_bytes());
+        }
+
+        // only hashed when present, so manifests without a fee asset keep their hash
+        if let Some(fee_asset) = self.fee_asset.as_ref() {
+            bytes.extend(Value::Principal(fee_asset.contract_id.clone().into()).serialize_to_vec());
+            bytes.extend_from_slice(&(fee_asset.token_name.len() as u32).to_be_bytes());
+            bytes.extend_from_slice(fee_asset.token_name.as_bytes());
+        }
+
+        Sha512Trunc256Sum::from_data(&bytes)
+    }
+
+    /// The manifest's balances, in the form used for `ChainStateBootData::initial_balances`
+    pub fn initial_balances(&self) -> Vec<(PrincipalData, u64)> {
+        self.balances
+            .iter()
+            .map(|balance| (balance.address.clone(), balance.amount))
+            .collect()
+    }
+}
+
+impl StacksChainState {
+    /// Deploy the contracts listed in the genesis manifest from the boot code address.
+    /// Any contract that fails to deploy aborts the boot.
+    pub(crate) fn install_genesis_contracts(
+        clarity_tx: &mut ClarityTx,
+        manifest: &GenesisManifest,
+        tx_version: &TransactionVersion,
+        boot_code_auth: &TransactionAuth,
+        boot_code_account: &mut StacksAccount,
+    ) -> Result<Vec<StacksTransactionReceipt>, Error> {
+        let mut receipts = vec![];
+        for contract in manifest.contracts.iter() {
+            debug!(
+                "Instantiate genesis contract '{}' ({} bytes)...",
+                &contract.name,
+                contract.source.len()
+            );
+
+            let name = ContractName::try_from(contract.name.clone()).map_err(|_| {
+                Error::InvalidStacksTransaction(
+                    format!("Invalid genesis contract name '{}'", &contract.name),
+                    false,
+                )
+            })?;
+            let code_body = StacksString::from_str(&contract.source).ok_or_else(|| {
+                Error::InvalidStacksTransaction(
+                    format!("Invalid source for genesis contract '{}'", &contract.name),
+                    false,
+                )
+            })?;
+
+            let deploy_tx = StacksTransaction::new(
+                tx_version.clone(),
+                boot_code_auth.clone(),
+                TransactionPayload::SmartContract(
+                    TransactionSmartContract { name, code_body },
+                    None,
+                ),
+            );
+
+            let tx_receipt = clarity_tx.connection().as_transaction(|clarity| {
+                StacksChainState::process_transaction_payload(
+                    clarity,
+                    &deploy_tx,
+                    boot_code_account,
+                )
+            })?;
+            if let Value::Response(ref data) = tx_receipt.result {
+                if !data.committed {
+                    return Err(Error::InvalidStacksTransaction(
+                        format!(
+                            "Genesis contract '{}' failed to deploy: {}",
+                            &contract.name, &tx_receipt.result
+                        ),
+                        false,
+                    ));
+                }
+            }
+            receipts.push(tx_receipt);
+
+            boot_code_account.nonce += 1;
+        }
+        Ok(receipts)
+    }
+
+    /// Register the asset mappings listed in the genesis manifest with the subnet boot contract,
+    /// as if each had been registered by a `RegisterAssetOp` in the first burnchain block.
+    pub(crate) fn register_genesis_assets(
+        clarity_tx: &mut ClarityTx,
+        manifest: &GenesisManifest,
+        first_burnchain_block_hash: &BurnchainHeaderHash,
+    ) -> Result<Vec<StacksTransactionReceipt>, Error> {
+        let operations: Vec<_> = manifest
+            .assets
+            .iter()
+            .map(|asset| RegisterAssetOp {
+                txid: Txid([0u8; 32]),
+                burn_header_hash: first_burnchain_block_hash.clone(),
+                asset_type: asset.asset_type.clone(),
+                l1_contract_id: asset.l1_contract_id.clone(),
+                l2_contract_id: asset.l2_contract_id.clone(),
+            })
+            .collect();
+
+        let receipts = StacksChainState::process_register_asset_ops(clarity_tx, operations);
+        if receipts.len() != manifest.assets.len() {
+            return Err(Error::InvalidStacksTransaction(
+                "Failed to register genesis assets".into(),
+                false,
+            ));
+        }
+        for (receipt, asset) in receipts.iter().zip(manifest.assets.iter()) {
+            if receipt.result != Value::okay_true() {
+                return Err(Error::InvalidStacksTransaction(
+                    format!(
+                        "Failed to register genesis asset {}: {}",
+                        &asset.l2_contract_id, &receipt.result
+                    ),
+                    false,
+                ));
+            }
+        }
+        Ok(receipts)
+    }
+
+    /// Set the manifest's fee asset, if any, in the subnet boot contract. The asset must be a
+    /// fungible token registered by the manifest.
+    pub(crate) fn set_genesis_fee_asset(
+        clarity_tx: &mut ClarityTx,
+        manifest: &GenesisManifest,
+    ) -> Result<(), Error> {
+        let fee_asset = match manifest.fee_asset.as_ref() {
+            Some(fee_asset) => fee_asset,
+            None => return Ok(()),
+        };
+
+        let registered = manifest.assets.iter().any(|asset| {
+            asset.asset_type == AssetType::FungibleToken
+                && asset.l2_contract_id == fee_asset.contract_id
+        });
+        if !registered {
+            return Err(Error::InvalidStacksTransaction(
+                format!(
+                    "Genesis fee asset {} is not a registered fungible token",
+                    fee_asset
+                ),
+                false,
+            ));
+        }
+
+        // make sure the token actually exists
+        let boot_principal = PrincipalData::from(boot_code_addr(clarity_tx.config.mainnet));
+        StacksChainState::get_fee_asset_balance(
+            clarity_tx.connection(),
+            fee_asset,
+            &boot_principal,
+        )
+        .map_err(|e| {
+            Error::InvalidStacksTransaction(
+                format!("Invalid genesis fee asset {}: {:?}", fee_asset, &e),
+                false,
+            )
+        })?;
+
+        let mainnet = clarity_tx.config.mainnet;
+        let token_name = Value::string_ascii_from_bytes(fee_asset.token_name.clone().into_bytes())
+            .map_err(|_| {
+                Error::InvalidStacksTransaction(
+                    format!(
+                        "Invalid genesis fee asset token name '{}'",
+                        &fee_asset.token_name
+                    ),
+                    false,
+                )
+            })?;
+        let (result, _, _) = clarity_tx.connection().as_transaction(|tx| {
+            tx.run_contract_call(
+                &boot_principal,
+                None,
+                &boot_code_id("subnet", mainnet),
+                "set-fee-asset",
+                &[
+                    Value::Principal(fee_asset.contract_id.clone().into()),
+                    token_name,
+                ],
+                |_, _| false,
+            )
+        })?;
+        if result != Value::okay_true() {
+            return Err(Error::InvalidStacksTransaction(
+                format!("Failed to set genesis fee asset {}: {}", fee_asset, &result),
+                false,
+            ));
+        }
+        Ok(())
+    }
+
+    /// Get the hash of the genesis manifest this chainstate was booted from, if any.
+    pub fn get_genesis_manifest_hash(&self) -> Result<Option<Sha512Trunc256Sum>, Error> {
+        let first_index_hash = StacksBlockHeader::make_index_block_hash(
+            &FIRST_BURNCHAIN_CONSENSUS_HASH,
+            &FIRST_STACKS_BLOCK_HASH,
+        );
+        let hash_hex = self
+            .index_conn()?
+            .get_indexed(&first_index_hash, GENESIS_MANIFEST_HASH_KEY)?;
+        match hash_hex {
+            Some(hex) => Sha512Trunc256Sum::from_hex(&hex)
+                .map(Some)
+                .map_err(|_| Error::InvalidChainstateDB),
+            None => Ok(None),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::fs;
+
+    use super::*;
+    use crate::burnchains::PoxConstants;
+    use crate::chainstate::stacks::boot::STACKS_BOOT_CODE_TESTNET;
+    use crate::chainstate::stacks::db::test::chainstate_path;
+    use crate::util_lib::boot::boot_code_test_addr;
+    use clarity::vm::database::NULL_BURN_STATE_DB;
+
+    #[test]
+    fn test_genesis_manifest_parse_and_hash() {
+        let json = r#"{
+            "balances": [
+                { "address": "ST2REHHS5J3CERCRBEPMGH7921Q6PYKAADT7JP2VB", "amount": 1000 }
+            ],
+            "assets": [
+                {
+                    "asset_type": "ft",
+                    "l1_contract_id": "ST2REHHS5J3CERCRBEPMGH7921Q6PYKAADT7JP2VB.simple-ft",
+                    "l2_contract_id": "ST000000000000000000002AMW42H.simple-ft"
+                }
+            ],
+            "contracts": [
+                { "name": "simple-ft", "source": "(define-fungible-token ft)" }
+            ]
+        }"#;
+        let manifest = GenesisManifest::from_json(json).unwrap();
+        assert_eq!(manifest.balances.len(), 1);
+        assert_eq!(manifest.balances[0].amount, 1000);
+        assert_eq!(manifest.assets[0].asset_type, AssetType::FungibleToken);
+        assert_eq!(
+            manifest.initial_balances(),
+            vec![(manifest.balances[0].address.clone(), 1000)]
+        );
+
+        // the hash covers every entry
+        let mut changed = manifest.clone();
+        changed.balances[0].amount = 1001;
+        assert_ne!(manifest.hash(), changed.hash());
+
+        let mut changed = manifest.clone();
+        changed.contracts[0].source = "(define-fungible-token ft2)".into();
+        assert_ne!(manifest.hash(), changed.hash());
+
+        let mut changed = manifest.clone();
+        changed.assets[0].asset_type = AssetType::NonFungibleToken;
+        assert_ne!(manifest.hash(), changed.hash());
+
+        // moving bytes between the name and source of a contract changes the hash
+        let mut moved = manifest.clone();
+        moved.contracts[0].name = "simple-f".into();
+        moved.contracts[0].source = "t(define-fungible-token ft)".into();
+        assert_ne!(manifest.hash(), moved.hash());
+
+        let mut changed = manifest.clone();
+        changed.fee_asset = Some(FeeAsset {
+            contract_id: manifest.assets[0].l2_contract_id.clone(),
+            token_name: "ft".into(),
+        });
+        assert_ne!(manifest.hash(), changed.hash());
+
+        assert_eq!(manifest.hash(), manifest.clone().hash());
+        assert_eq!(
+            GenesisManifest::from_json("{}").unwrap(),
+            GenesisManifest::default()
+        );
+    }
+
+    #[test]
+    fn test_boot_with_genesis_manifest() {
+        let path = chainstate_path("boot-with-genesis-manifest");
+        let _ = fs::remove_dir_all(&path);
+
+        let recipient = PrincipalData::parse("ST2REHHS5J3CERCRBEPMGH7921Q6PYKAADT7JP2VB").unwrap();
+        let l2_contract_id = QualifiedContractIdentifier::new(
+            boot_code_test_addr().into(),
+            ContractName::try_from("simple-ft".to_string()).unwrap(),
+        );
+        let manifest = GenesisManifest {
+            balances: vec![GenesisBalance {
+                address: recipient.clone(),
+                amount: 1000,
+            }],
+            assets: vec![GenesisAsset {
+                asset_type: AssetType::FungibleToken,
+                l1_contract_id: QualifiedContractIdentifier::parse(
+                    "ST2REHHS5J3CERCRBEPMGH7921Q6PYKAADT7JP2VB.simple-ft",
+                )
+                .unwrap(),
+                l2_contract_id: l2_contract_id.clone(),
+            }],
+            contracts: vec![GenesisContract {
+                name: "simple-ft".into(),
+                source: "(define-fungible-token ft)".into(),
+            }],
+            fee_asset: Some(FeeAsset {
+                contract_id: l2_contract_id.clone(),
+                token_name: "ft".into(),
+            }),
+        };
+
+        let mut boot_data = ChainStateBootData {
+            initial_balances: vec![],
+            post_flight_callback: None,
+            first_burnchain_block_hash: BurnchainHeaderHash::zero(),
+            first_burnchain_block_height: 0,
+            first_burnchain_block_timestamp: 0,
+            pox_constants: PoxConstants::testnet_default(),
+            get_bulk_initial_lockups: None,
+            get_bulk_initial_balances: None,
+            get_bulk_initial_names: None,
+            get_bulk_initial_namespaces: None,
+            genesis_manifest: Some(manifest.clone()),
+        };
+
+        let (mut chainstate, receipts) =
+            StacksChainState::open_and_exec(false, 0x80000000, &path, Some(&mut boot_data), None)
+                .unwrap();
+
+        // the contract deploy and the asset registration each produce a receipt
+        let boot_contracts = STACKS_BOOT_CODE_TESTNET.len();
+        assert_eq!(receipts.len(), boot_contracts + 3);
+
+        assert_eq!(
+            chainstate.get_genesis_manifest_hash().unwrap(),
+            Some(manifest.hash())
+        );
+
+        let first_index_hash = StacksBlockHeader::make_index_block_hash(
+            &FIRST_BURNCHAIN_CONSENSUS_HASH,
+            &FIRST_STACKS_BLOCK_HASH,
+        );
+        let (balance, contract) = chainstate
+            .with_read_only_clarity_tx(&NULL_BURN_STATE_DB, &first_index_hash, |conn| {
+                let account = StacksChainState::get_account(conn, &recipient);
+                let contract = StacksChainState::get_contract(conn, &l2_contract_id).unwrap();
+                (account.stx_balance.amount_unlocked(), contract)
+            })
+            .unwrap();
+        assert_eq!(balance, 1000);
+        assert!(contract.is_some());
+
+        assert_eq!(
+            chainstate.get_subnet_fee_asset().unwrap(),
+            manifest.fee_asset
+        );
+    }
+}
@@ -34,10 +34,12 @@ use crate::burnchains::{Address, Burnchain, BurnchainParameters, PoxConstants};
 use crate::chainstate::burn::db::sortdb::BlockHeaderCache;
 use crate::chainstate::burn::db::sortdb::*;
 use crate::chainstate::burn::db::sortdb::{SortitionDB, SortitionDBConn};
+use crate::chainstate::burn::operations::BlockstackOperationType;
 use crate::chainstate::burn::ConsensusHash;
 use crate::chainstate::stacks::boot::*;
 use crate::chainstate::stacks::db::accounts::*;
 use crate::chainstate::stacks::db::blocks::*;
+use crate::chainstate::stacks::db::genesis::{GenesisManifest, GENESIS_MANIFEST_HASH_KEY};
 use crate::chainstate::stacks::db::unconfirmed::UnconfirmedState;
 use crate::chainstate::stacks::events::*;
 use crate::chainstate::stacks::index::marf::{
@@ -59,12 +61,13 @@ use crate::clarity_vm::clarity::{
 use crate::core::*;
 use crate::monitoring;
 use crate::net::atlas::BNS_CHARS_REGEX;
+use crate::net::event_stream::EventStreamData;
 use crate::net::Error as net_error;
 use crate::net::MemPoolSyncData;
 use crate::util_lib::db::Error as db_error;
 use crate::util_lib::db::{
-    query_count, query_row, tx_begin_immediate, tx_busy_handler, DBConn, DBTx, FromColumn, FromRow,
-    IndexDBConn, IndexDBTx,
+    query_count, query_row, query_rows, tx_begin_immediate, tx_busy_handler, DBConn, DBTx,
+    FromColumn, FromRow, IndexDBConn, IndexDBTx,
 };
 use clarity::vm::analysis::analysis_db::AnalysisDatabase;
 use clarity::vm::analysis::run_analysis;
@@ -89,7 +92,7 @@ use crate::chainstate::stacks::address::StacksAddressExtensions;
 use crate::chainstate::stacks::index::{ClarityMarfTrieId, MARFValue};
 use crate::chainstate::stacks::StacksBlockHeader;
 use crate::chainstate::stacks::StacksMicroblockHeader;
-use crate::clarity_vm::database::marf::MarfedKV;
+use crate::clarity_vm::database::marf::{MarfWriteLog, MarfedKV};
 use crate::clarity_vm::database::HeadersDBConn;
 use crate::util_lib::boot::{boot_code_acc, boot_code_addr, boot_code_id, boot_code_tx_auth};
 use clarity::vm::Value;
@@ -97,7 +100,13 @@ use stacks_common::types::chainstate::{StacksAddress, StacksBlockId, TrieHash};
 pub mod accounts;
 pub mod blocks;
 pub mod contracts;
+pub mod export;
+pub mod fsck;
+pub mod genesis;
 pub mod headers;
+pub mod prune;
+pub mod replay;
+pub mod snapshot;
 pub mod transactions;
 pub mod unconfirmed;
 
@@ -116,6 +125,10 @@ pub struct StacksChainState {
     pub clarity_state_index_root: String, // path to dir containing clarity MARF and side-store
     pub root_path: String,
     pub unconfirmed_state: Option<UnconfirmedState>,
+    /// Whether to record the receipts of processed transactions in the transaction index
+    pub txindex: bool,
+    /// If set, discard the data of blocks this many blocks below the chain tip
+    pub prune_depth: Option<u64>,
     marf_opts: Option<MARFOpenOpts>,
 }
 
@@ -158,6 +171,68 @@ pub struct StacksHeaderInfo {
     pub withdrawal_tree: MerkleTree<Sha512Trunc256Sum>,
 }
 
+/// A confirmed transaction's receipt, as recorded in the transaction index
+#[derive(Debug, Clone, PartialEq)]
+pub struct IndexedTransaction {
+    pub txid: Txid,
+    /// The anchored block that confirmed the transaction
+    pub index_block_hash: StacksBlockId,
+    /// Hex of the consensus-serialized transaction, or empty for a burnchain operation
+    pub tx_hex: String,
+    /// The burnchain operation, if the transaction was one
+    pub burnchain_op: Option<serde_json::Value>,
+    pub tx_index: u32,
+    /// The microblock the transaction was mined in, if any
+    pub microblock_hash: Option<BlockHeaderHash>,
+    /// Hex of the consensus-serialized result value
+    pub result_hex: String,
+    pub post_condition_aborted: bool,
+    pub stx_burned: u128,
+    pub events: Vec<serde_json::Value>,
+    pub execution_cost: ExecutionCost,
+}
+
+impl IndexedTransaction {
+    /// The receipt `receipt` of block `index_block_hash`, as the transaction index records it
+    pub fn from_receipt(
+        index_block_hash: &StacksBlockId,
+        receipt: &StacksTransactionReceipt,
+    ) -> IndexedTransaction {
+        let txid = receipt.transaction.txid();
+        let (tx_hex, burnchain_op) = match receipt.transaction {
+            TransactionOrigin::Stacks(ref tx) => (to_hex(&tx.serialize_to_vec()), None),
+            TransactionOrigin::Burn(ref op) => (
+                "".to_string(),
+                Some(BlockstackOperationType::blockstack_op_to_json(op)),
+            ),
+        };
+        let events = receipt
+            .events
+            .iter()
+            .enumerate()
+            .map(|(event_index, event)| {
+                event.json_serialize(event_index, &txid, !receipt.post_condition_aborted)
+            })
+            .collect();
+        IndexedTransaction {
+            txid,
+            index_block_hash: index_block_hash.clone(),
+            tx_hex,
+            burnchain_op,
+            tx_index: receipt.tx_index,
+            microblock_hash: receipt
+                .microblock_header
+                .as_ref()
+                .map(|header| header.block_hash()),
+            result_hex: receipt.result.serialize_to_hex(),
+            post_condition_aborted: receipt.post_condition_aborted,
+            stx_burned: receipt.stx_burned,
+            events,
+            execution_cost: receipt.execution_cost.clone(),
+        }
+    }
+}
+
 #[derive(Debug, Clone, PartialEq)]
 pub struct MinerRewardInfo {
     pub from_block_consensus_hash: ConsensusHash,
@@ -193,12 +268,12 @@ impl DBConfig {
     pub fn supports_epoch(&self, epoch_id: StacksEpochId) -> bool {
         match epoch_id {
             StacksEpochId::Epoch10 => true,
-            StacksEpochId::Epoch20 => self.version == "1" || self.version == "2",
-            StacksEpochId::Epoch2_05 => self.version == "2",
-            StacksEpochId::Epoch21 => self.version == "2",
-            StacksEpochId::Epoch22 => self.version == "2",
-            StacksEpochId::Epoch23 => self.version == "2",
-            StacksEpochId::Epoch24 => self.version == "2",
+            StacksEpochId::Epoch20 => matches!(self.version.as_str(), "1" | "2" | "3" | "4"),
+            StacksEpochId::Epoch2_05 => matches!(self.version.as_str(), "2" | "3" | "4"),
+            StacksEpochId::Epoch21 => matches!(self.version.as_str(), "2" | "3" | "4"),
+            StacksEpochId::Epoch22 => matches!(self.version.as_str(), "2" | "3" | "4"),
+            StacksEpochId::Epoch23 => matches!(self.version.as_str(), "2" | "3" | "4"),
+            StacksEpochId::Epoch24 => matches!(self.version.as_str(), "2" | "3" | "4"),
         }
     }
 }
@@ -266,6 +341,46 @@ impl FromRow<DBConfig> for DBConfig {
     }
 }
 
+impl FromRow<IndexedTransaction> for IndexedTransaction {
+    fn from_row<'a>(row: &'a Row) -> Result<IndexedTransaction, db_error> {
+        let txid = Txid::from_column(row, "txid")?;
+        let index_block_hash = StacksBlockId::from_column(row, "index_block_hash")?;
+        let tx_hex: String = row.get_unwrap("tx_hex");
+        let burnchain_op_str: Option<String> = row.get_unwrap("burnchain_op");
+        let burnchain_op = match burnchain_op_str {
+            Some(op) => Some(serde_json::from_str(&op).map_err(|_| db_error::ParseError)?),
+            None => None,
+        };
+        let tx_index_i64: i64 = row.get_unwrap("tx_index");
+        let microblock_hash: Option<BlockHeaderHash> = row.get_unwrap("microblock_hash");
+        let result_hex: String = row.get_unwrap("result_hex");
+        let post_condition_aborted: bool = row.get_unwrap("post_condition_aborted");
+        let stx_burned_str: String = row.get_unwrap("stx_burned");
+        let stx_burned = stx_burned_str
+            .parse::<u128>()
+            .map_err(|_| db_error::ParseError)?;
+        let events_str: String = row.get_unwrap("events");
+        let events = serde_json::from_str(&events_str).map_err(|_| db_error::ParseError)?;
+        let execution_cost_str: String = row.get_unwrap("execution_cost");
+        let execution_cost =
+            serde_json::from_str(&execution_cost_str).map_err(|_| db_error::ParseError)?;
+
+        Ok(IndexedTransaction {
+            txid,
+            index_block_hash,
+            tx_hex,
+            burnchain_op,
+            tx_index: tx_index_i64 as u32,
+            microblock_hash,
+            result_hex,
+            post_condition_aborted,
+            stx_burned,
+            events,
+            execution_cost,
+        })
+    }
+}
+
 impl FromRow<StacksHeaderInfo> for StacksHeaderInfo {
     fn from_row<'a>(row: &'a Row) -> Result<StacksHeaderInfo, db_error> {
         let block_height: u64 = u64::from_column(row, "block_height")?;
@@ -371,6 +486,16 @@ impl<'a, 'b> ClarityTx<'a, 'b> {
         self.block.seal()
     }
 
+    /// Attribute the writes logged since the last call to the transaction `txid`, or to no
+    /// transaction if `None`. Does nothing unless the block logs its writes.
+    pub fn close_write_log_entry(&mut self, txid: Option<Txid>) {
+        self.block.close_write_log_entry(txid)
+    }
+
+    pub fn take_write_log(&mut self) -> Option<MarfWriteLog> {
+        self.block.take_write_log()
+    }
+
     #[cfg(test)]
     pub fn commit_block(self) -> () {
         self.block.commit_block();
@@ -435,6 +560,7 @@ pub struct ChainstateTx<'a> {
     pub blocks_path: String,
     pub tx: StacksDBTx<'a>,
     pub root_path: String,
+    pub txindex: bool,
 }
 
 impl<'a> ChainstateTx<'a> {
@@ -443,12 +569,14 @@ impl<'a> ChainstateTx<'a> {
         blocks_path: String,
         root_path: String,
         config: DBConfig,
+        txindex: bool,
     ) -> ChainstateTx<'a> {
         ChainstateTx {
             config,
             blocks_path,
             tx,
             root_path,
+            txindex,
         }
     }
 
@@ -469,14 +597,28 @@ impl<'a> ChainstateTx<'a> {
         block_id: &StacksBlockId,
         events: &[StacksTransactionReceipt],
     ) {
-        if *TRANSACTION_LOG {
-            let insert =
-                "INSERT INTO transactions (txid, index_block_hash, tx_hex, result) VALUES (?, ?, ?, ?)";
+        if self.txindex {
+            let insert = "INSERT OR REPLACE INTO transactions
+                (txid, index_block_hash, tx_hex, result, tx_index, microblock_hash, result_hex,
+                 post_condition_aborted, stx_burned, events, execution_cost, burnchain_op)
+                VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12)";
             for tx_event in events.iter() {
-                let txid = tx_event.transaction.txid();
-                let tx_hex = to_hex(&tx_event.transaction.serialize_to_vec());
-                let result = tx_event.result.to_string();
-                let params: &[&dyn ToSql] = &[&txid, block_id, &tx_hex, &result];
+                let itx = IndexedTransaction::from_receipt(block_id, tx_event);
+                let params: &[&dyn ToSql] = &[
+                    &itx.txid,
+                    block_id,
+                    &itx.tx_hex,
+                    &tx_event.result.to_string(),
+                    &itx.tx_index,
+                    &itx.microblock_hash,
+                    &itx.result_hex,
+                    &itx.post_condition_aborted,
+                    &itx.stx_burned.to_string(),
+                    &serde_json::to_string(&itx.events).expect("FATAL: failed to serialize events"),
+                    &serde_json::to_string(&itx.execution_cost)
+                        .expect("FATAL: failed to serialize execution cost"),
+                    &itx.burnchain_op.as_ref().map(|op| op.to_string()),
+                ];
                 if let Err(e) = self.tx.tx().execute(insert, params) {
                     warn!("Failed to log TX: {}", e);
                 }
@@ -517,6 +659,7 @@ pub enum StreamCursor {
     Microblocks(MicroblockStreamData),
     Headers(HeaderStreamData),
     MempoolTxs(TxStreamData),
+    Events(EventStreamData),
 }
 
 #[derive(Debug, PartialEq, Clone)]
@@ -590,7 +733,7 @@ pub struct TxStreamData {
     pub corked: bool,
 }
 
-pub const CHAINSTATE_VERSION: &'static str = "2";
+pub const CHAINSTATE_VERSION: &'static str = "4";
 
 const CHAINSTATE_INITIAL_SCHEMA: &'static [&'static str] = &[
     "PRAGMA foreign_keys = ON;",
@@ -747,6 +890,34 @@ const CHAINSTATE_SCHEMA_2: &'static [&'static str] = &[
     "#,
 ];
 
+const CHAINSTATE_SCHEMA_3: &'static [&'static str] = &[
+    // the transaction log becomes the transaction index, with each transaction's receipt.
+    // rows logged before this have an empty `result_hex`.
+    r#"ALTER TABLE transactions ADD COLUMN tx_index INTEGER NOT NULL DEFAULT 0;"#,
+    r#"ALTER TABLE transactions ADD COLUMN microblock_hash TEXT;"#,
+    r#"ALTER TABLE transactions ADD COLUMN result_hex TEXT NOT NULL DEFAULT '';"#,
+    r#"ALTER TABLE transactions ADD COLUMN post_condition_aborted INTEGER NOT NULL DEFAULT 0;"#,
+    r#"ALTER TABLE transactions ADD COLUMN stx_burned TEXT NOT NULL DEFAULT '0';"#,
+    r#"ALTER TABLE transactions ADD COLUMN events TEXT NOT NULL DEFAULT '[]';"#,
+    r#"ALTER TABLE transactions ADD COLUMN execution_cost TEXT NOT NULL DEFAULT '{}';"#,
+    r#"ALTER TABLE transactions ADD COLUMN burnchain_op TEXT;"#,
+    r#"
+    UPDATE db_config SET version = "3";
+    "#,
+];
+
+const CHAINSTATE_SCHEMA_4: &'static [&'static str] = &[
+    r#"
+    -- the data of anchored blocks below this height (and of the microblock streams they
+    -- confirmed) has been pruned.  There is only ever one row.
+    CREATE TABLE pruned_height(height INTEGER NOT NULL);
+    "#,
+    r#"INSERT INTO pruned_height (height) VALUES (0);"#,
+    r#"
+    UPDATE db_config SET version = "4";
+    "#,
+];
+
 const CHAINSTATE_INDEXES: &'static [&'static str] = &[
     "CREATE INDEX IF NOT EXISTS index_block_hash_to_primary_key ON block_headers(index_block_hash,consensus_hash,block_hash);",
     "CREATE INDEX IF NOT EXISTS block_headers_hash_index ON block_headers(block_hash,block_height);",
@@ -841,6 +1012,8 @@ pub struct ChainStateBootData {
         Option<Box<dyn FnOnce() -> Box<dyn Iterator<Item = ChainstateBNSNamespace>>>>,
     pub get_bulk_initial_names:
         Option<Box<dyn FnOnce() -> Box<dyn Iterator<Item = ChainstateBNSName>>>>,
+    /// Balances, asset mappings and contracts to install in the genesis block
+    pub genesis_manifest: Option<GenesisManifest>,
 }
 
 impl ChainStateBootData {
@@ -860,6 +1033,7 @@ impl ChainStateBootData {
             get_bulk_initial_balances: None,
             get_bulk_initial_namespaces: None,
             get_bulk_initial_names: None,
+            genesis_manifest: None,
         }
     }
 }
@@ -958,6 +1132,20 @@ impl StacksChainState {
                         }
                     }
                     "2" => {
+                        // migrate to 3
+                        info!("Migrating chainstate schema from version 2 to 3");
+                        for cmd in CHAINSTATE_SCHEMA_3.iter() {
+                            tx.execute_batch(cmd)?;
+                        }
+                    }
+                    "3" => {
+                        // migrate to 4
+                        info!("Migrating chainstate schema from version 3 to 4");
+                        for cmd in CHAINSTATE_SCHEMA_4.iter() {
+                            tx.execute_batch(cmd)?;
+                        }
+                    }
+                    "4" => {
                         // done
                         break;
                     }
@@ -1146,6 +1334,25 @@ impl StacksChainState {
                 boot_code_account.nonce += 1;
             }
 
+            let mut manifest_balances = vec![];
+            if let Some(manifest) = boot_data.genesis_manifest.as_ref() {
+                info!(
+                    "Applying genesis manifest";
+                    "hash" => %manifest.hash(),
+                    "balances" => manifest.balances.len(),
+                    "assets" => manifest.assets.len(),
+                    "contracts" => manifest.contracts.len()
+                );
+                receipts.extend(StacksChainState::install_genesis_contracts(
+                    &mut clarity_tx,
+                    manifest,
+                    &tx_version,
+                    &boot_code_auth,
+                    &mut boot_code_account,
+                )?);
+                manifest_balances = manifest.initial_balances();
+            }
+
             let mut allocation_events: Vec<StacksTransactionEvent> = vec![];
             if boot_data.initial_balances.len() > 0 {
                 warn!(
@@ -1153,7 +1360,11 @@ impl StacksChainState {
                     boot_data.initial_balances.len()
                 );
             }
-            for (address, amount) in boot_data.initial_balances.iter() {
+            for (address, amount) in boot_data
+                .initial_balances
+                .iter()
+                .chain(manifest_balances.iter())
+            {
                 clarity_tx.connection().as_transaction(|clarity| {
                     StacksChainState::account_genesis_credit(clarity, address, (*amount).into())
                 });
@@ -1440,6 +1651,15 @@ impl StacksChainState {
             );
             receipts.push(allocations_receipt);
 
+            if let Some(manifest) = boot_data.genesis_manifest.as_ref() {
+                receipts.extend(StacksChainState::register_genesis_assets(
+                    &mut clarity_tx,
+                    manifest,
+                    &boot_data.first_burnchain_block_hash,
+                )?);
+                StacksChainState::set_genesis_fee_asset(&mut clarity_tx, manifest)?;
+            }
+
             if let Some(callback) = boot_data.post_flight_callback.take() {
                 callback(&mut clarity_tx);
             }
@@ -1469,7 +1689,8 @@ impl StacksChainState {
 
             info!("Computed Clarity state genesis"; "root_hash" => %genesis_root_hash);
 
-            if mainnet {
+            // a genesis manifest changes the genesis state, so there is no fixed root hash to check
+            if mainnet && boot_data.genesis_manifest.is_none() {
                 assert_eq!(
                     &genesis_root_hash.to_string(),
                     SUBNET_GENESIS_ROOT_HASH,
@@ -1495,8 +1716,16 @@ impl StacksChainState {
                 &first_index_hash
             );
 
+            // fold the genesis manifest hash into the genesis block's index root
+            let (keys, values) = match boot_data.genesis_manifest.as_ref() {
+                Some(manifest) => (
+                    vec![GENESIS_MANIFEST_HASH_KEY.to_string()],
+                    vec![manifest.hash().to_hex()],
+                ),
+                None => (vec![], vec![]),
+            };
             let first_root_hash =
-                tx.put_indexed_all(&parent_hash, &first_index_hash, &vec![], &vec![])?;
+                tx.put_indexed_all(&parent_hash, &first_index_hash, &keys, &values)?;
 
             test_debug!(
                 "Boot code headers index_commit {}-{}",
@@ -1536,12 +1765,15 @@ impl StacksChainState {
     /// Re-open the chainstate -- i.e. to get a new handle to it using an existing chain state's
     /// parameters
     pub fn reopen(&self) -> Result<(StacksChainState, Vec<StacksTransactionReceipt>), Error> {
-        StacksChainState::open(
+        let (mut chainstate, receipts) = StacksChainState::open(
             self.mainnet,
             self.chain_id,
             &self.root_path,
             self.marf_opts.clone(),
-        )
+        )?;
+        chainstate.txindex = self.txindex;
+        chainstate.prune_depth = self.prune_depth;
+        Ok((chainstate, receipts))
     }
 
     pub fn blocks_path(mut path: PathBuf) -> PathBuf {
@@ -1648,6 +1880,8 @@ impl StacksChainState {
             clarity_state_index_root: clarity_state_index_root,
             root_path: path_str.to_string(),
             unconfirmed_state: None,
+            txindex: *TRANSACTION_LOG,
+            prune_depth: None,
             marf_opts: marf_opts,
         };
 
@@ -1677,6 +1911,46 @@ impl StacksChainState {
         }
     }
 
+    /// Look up the transaction `txid` in the transaction index, as confirmed in the fork of
+    /// `tip`. Returns `None` if it is not indexed, or was only confirmed in other forks.
+    pub fn get_indexed_transaction(
+        &self,
+        txid: &Txid,
+        tip: &StacksBlockId,
+    ) -> Result<Option<(IndexedTransaction, StacksHeaderInfo)>, Error> {
+        // rows logged before the index stored receipts have no result
+        let sql = "SELECT * FROM transactions WHERE txid = ?1 AND result_hex != ''";
+        let args: &[&dyn ToSql] = &[txid];
+        let entries: Vec<IndexedTransaction> = query_rows(self.db(), sql, args)?;
+        let index_conn = self.index_conn()?;
+        for entry in entries.into_iter() {
+            let header = match StacksChainState::get_stacks_block_header_info_by_index_block_hash(
+                self.db(),
+                &entry.index_block_hash,
+            )? {
+                Some(header) => header,
+                None => continue,
+            };
+            let ancestor = index_conn.get_ancestor_block_hash(header.stacks_block_height, tip)?;
+            if ancestor.as_ref() == Some(&entry.index_block_hash) {
+                return Ok(Some((entry, header)));
+            }
+        }
+        Ok(None)
+    }
+
+    /// Get the receipts the transaction index holds for the block `block_id`, in the order
+    /// the block processed them. Empty if the index was off when the block was processed.
+    pub fn get_indexed_transactions_in_block(
+        conn: &Connection,
+        block_id: &StacksBlockId,
+    ) -> Result<Vec<IndexedTransaction>, Error> {
+        // rows logged before the index stored receipts have no result
+        let sql = "SELECT * FROM transactions WHERE index_block_hash = ?1 AND result_hex != '' ORDER BY id ASC";
+        let args: &[&dyn ToSql] = &[block_id];
+        Ok(query_rows(conn, sql, args)?)
+    }
+
     /// Begin a transaction against the (indexed) stacks chainstate DB.
     /// Does not create a Clarity instance.
     pub fn index_tx_begin<'a>(&'a mut self) -> Result<StacksDBTx<'a>, Error> {
@@ -1703,8 +1977,13 @@ impl StacksChainState {
         let clarity_instance = &mut self.clarity_state;
         let inner_tx = StacksDBTx::new(&mut self.state_index, ());
 
-        let chainstate_tx =
-            ChainstateTx::new(inner_tx, blocks_path, self.root_path.clone(), config);
+        let chainstate_tx = ChainstateTx::new(
+            inner_tx,
+            blocks_path,
+            self.root_path.clone(),
+            config,
+            self.txindex,
+        );
 
         Ok((chainstate_tx, clarity_instance))
     }
@@ -2215,6 +2494,9 @@ pub mod test {
     use crate::chainstate::stacks::*;
     use clarity::vm::test_util::TEST_BURN_STATE_DB;
 
+    use crate::burnchains::AssetType;
+    use crate::chainstate::stacks::db::accounts::FeeAsset;
+    use crate::chainstate::stacks::db::genesis::{GenesisAsset, GenesisContract, GenesisManifest};
     use crate::util_lib::boot::boot_code_test_addr;
 
     use super::*;
@@ -2232,6 +2514,65 @@ pub mod test {
         chain_id: u32,
         test_name: &str,
         balances: Vec<(StacksAddress, u64)>,
+    ) -> StacksChainState {
+        instantiate_chainstate_with_manifest(mainnet, chain_id, test_name, balances, None)
+    }
+
+    /// Boot a testnet chainstate whose transaction fees are paid in the fungible token `ft` of
+    /// the genesis contract `fee-token`. Each of `ft_balances` is minted at genesis.
+    pub fn instantiate_chainstate_with_fee_asset(
+        test_name: &str,
+        balances: Vec<(StacksAddress, u64)>,
+        ft_balances: Vec<(StacksAddress, u128)>,
+    ) -> (StacksChainState, FeeAsset) {
+        let l2_contract_id = QualifiedContractIdentifier::new(
+            boot_code_test_addr().into(),
+            ContractName::try_from("fee-token".to_string()).unwrap(),
+        );
+        let fee_asset = FeeAsset {
+            contract_id: l2_contract_id.clone(),
+            token_name: "ft".into(),
+        };
+        let mut source = "(define-fungible-token ft)".to_string();
+        for (addr, amount) in ft_balances.iter() {
+            source.push_str(&format!(
+                "\n(unwrap-panic (ft-mint? ft u{} '{}))",
+                amount, addr
+            ));
+        }
+        let manifest = GenesisManifest {
+            balances: vec![],
+            assets: vec![GenesisAsset {
+                asset_type: AssetType::FungibleToken,
+                l1_contract_id: QualifiedContractIdentifier::parse(
+                    "ST2REHHS5J3CERCRBEPMGH7921Q6PYKAADT7JP2VB.fee-token",
+                )
+                .unwrap(),
+                l2_contract_id,
+            }],
+            contracts: vec![GenesisContract {
+                name: "fee-token".into(),
+                source,
+            }],
+            fee_asset: Some(fee_asset.clone()),
+        };
+
+        let chainstate = instantiate_chainstate_with_manifest(
+            false,
+            0x80000000,
+            test_name,
+            balances,
+            Some(manifest),
+        );
+        (chainstate, fee_asset)
+    }
+
+    fn instantiate_chainstate_with_manifest(
+        mainnet: bool,
+        chain_id: u32,
+        test_name: &str,
+        balances: Vec<(StacksAddress, u64)>,
+        genesis_manifest: Option<GenesisManifest>,
     ) -> StacksChainState {
         let path = chainstate_path(test_name);
         match fs::metadata(&path) {
@@ -2257,6 +2598,7 @@ pub mod test {
             get_bulk_initial_balances: None,
             get_bulk_initial_names: None,
             get_bulk_initial_namespaces: None,
+            genesis_manifest,
         };
 
         StacksChainState::open_and_exec(mainnet, chain_id, &path, Some(&mut boot_data), None)
@@ -0,0 +1,387 @@
+// Copyright (C) 2013-2020 Blockstack PBC, a public benefit corporation
+// Copyright (C) 2020 Stacks Open Internet Foundation
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Block data pruning. The node can discard the data of accepted anchored blocks (and of the
+//! microblock streams they confirmed) once they are more than `prune_depth` blocks below the
+//! chain tip. Block headers are kept, and with them the withdrawal trees that withdrawal proofs
+//! are built from.
+//!
+//! This only reclaims the space taken by block and microblock data. The MARF, which holds the
+//! bulk of a chainstate, is not pruned: each block's trie refers back to the nodes of its
+//! ancestors' tries, and there is no trie garbage collection to drop older tries without
+//! rewriting the current state. A node pruning block data still grows with the chain.
+//!
+//! A pruned block's file is truncated rather than removed, so the block downloader still treats
+//! it as stored and does not fetch it again; with no data, it is left out of the node's block
+//! inventory, and RPC requests for it get a 410 instead of the block. The chainstate also
+//! truncates the files of invalid blocks, so an empty file alone does not mean a block was
+//! pruned: only accepted blocks are pruned, and only below the pruned height (see
+//! `is_block_pruned`). A pruned microblock's data is cleared the same way the chainstate clears
+//! staging data.
+
+use std::cmp;
+
+use rusqlite::types::ToSql;
+use rusqlite::NO_PARAMS;
+
+use crate::chainstate::stacks::db::blocks::StagingBlock;
+use crate::chainstate::stacks::db::StacksChainState;
+use crate::chainstate::stacks::Error;
+use crate::chainstate::stacks::StacksBlockHeader;
+use crate::util_lib::db::Error as db_error;
+use crate::util_lib::db::{query_row, query_rows, u64_to_sql, DBConn};
+use stacks_common::types::chainstate::StacksBlockId;
+
+/// The most block heights `prune_block_data` prunes in one transaction, so that catching up on a
+/// long unpruned chain, such as on an archival node's first prune, neither holds the chainstate
+/// DB's write lock nor grows its journal for the whole chain at once
+pub const PRUNE_BATCH_HEIGHTS: u64 = 100;
+
+impl StacksChainState {
+    /// Get the height below which block data has been pruned (0 if nothing has been pruned)
+    pub fn get_pruned_height(conn: &DBConn) -> Result<u64, Error> {
+        let height: Option<u64> = query_row(conn, "SELECT height FROM pruned_height", NO_PARAMS)?;
+        Ok(height.unwrap_or(0))
+    }
+
+    /// Was this block's data pruned?  That is, was the block accepted, and is it below the
+    /// pruned height?  Unlike an invalid block, whose file is also empty, a pruned block is
+    /// part of the chain.
+    pub fn is_block_pruned(conn: &DBConn, index_block_hash: &StacksBlockId) -> Result<bool, Error> {
+        let sql = "SELECT 1 FROM staging_blocks WHERE index_block_hash = ?1 AND processed = 1 \
+             AND orphaned = 0 AND height < (SELECT height FROM pruned_height)";
+        let row: Option<i64> = query_row(conn, sql, &[index_block_hash])?;
+        Ok(row.is_some())
+    }
+
+    /// Do we have the data of a stored block?  Pruned and invalid blocks leave an empty file
+    /// behind; use `is_block_pruned` to tell them apart.
+    pub fn has_block_data_indexed(
+        blocks_dir: &str,
+        index_block_hash: &StacksBlockId,
+    ) -> Result<bool, Error> {
+        let block_path = StacksChainState::get_index_block_path(blocks_dir, index_block_hash)?;
+        match StacksChainState::get_file_size(&block_path) {
+            Ok(sz) => Ok(sz > 0),
+            Err(Error::DBError(db_error::NotFoundError)) => Ok(false),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// If this chainstate is pruned, discard the data of the processed blocks more than
+    /// `prune_depth` blocks below `tip_height`, along with the microblock streams they confirmed.
+    /// Returns the new pruned height, if anything was pruned.
+    pub fn prune_block_data(&mut self, tip_height: u64) -> Result<Option<u64>, Error> {
+        self.prune_block_data_in_batches(tip_height, PRUNE_BATCH_HEIGHTS)
+    }
+
+    /// Prune block data up to `prune_depth` blocks below `tip_height`, committing after every
+    /// `batch_heights` heights.  The pruned height is advanced with each batch, so an
+    /// interrupted prune resumes where it stopped.
+    fn prune_block_data_in_batches(
+        &mut self,
+        tip_height: u64,
+        batch_heights: u64,
+    ) -> Result<Option<u64>, Error> {
+        let prune_depth = match self.prune_depth {
+            Some(prune_depth) => prune_depth,
+            None => return Ok(None),
+        };
+        let mut pruned_height = StacksChainState::get_pruned_height(self.db())?;
+        let target_height = tip_height.saturating_sub(prune_depth);
+        if target_height <= pruned_height {
+            return Ok(None);
+        }
+
+        if target_height - pruned_height > batch_heights {
+            info!(
+                "Pruning the data of blocks from height {} to {}, {} heights at a time",
+                pruned_height, target_height, batch_heights
+            );
+        }
+        while pruned_height < target_height {
+            let batch_height = cmp::min(pruned_height + batch_heights, target_height);
+            self.prune_block_data_batch(pruned_height, batch_height)?;
+            pruned_height = batch_height;
+        }
+        Ok(Some(target_height))
+    }
+
+    /// Discard the data of the processed blocks from `from_height` up to (but excluding)
+    /// `to_height`, and set the pruned height to `to_height`, in one transaction.
+    fn prune_block_data_batch(&mut self, from_height: u64, to_height: u64) -> Result<(), Error> {
+        let blocks_path = self.blocks_path.clone();
+        let tx = self.db_tx_begin()?;
+
+        // invalid blocks are left alone, so that an empty file below the pruned height is only
+        // taken for a pruned block if the block was accepted
+        let sql = "SELECT * FROM staging_blocks WHERE processed = 1 AND orphaned = 0 \
+             AND height >= ?1 AND height < ?2";
+        let args: &[&dyn ToSql] = &[&u64_to_sql(from_height)?, &u64_to_sql(to_height)?];
+        let blocks: Vec<StagingBlock> = query_rows(&tx, sql, args)?;
+
+        let clear_microblocks_sql = "UPDATE staging_microblocks_data SET block_data = ?1 \
+             WHERE block_hash IN (SELECT microblock_hash FROM staging_microblocks WHERE index_block_hash = ?2 AND processed = 1)";
+        let mut num_pruned = 0;
+        for block in blocks.iter() {
+            let index_block_hash = StacksBlockHeader::make_index_block_hash(
+                &block.consensus_hash,
+                &block.anchored_block_hash,
+            );
+            if StacksChainState::has_block_data_indexed(&blocks_path, &index_block_hash)? {
+                let block_path =
+                    StacksChainState::get_index_block_path(&blocks_path, &index_block_hash)?;
+                StacksChainState::atomic_file_write(&block_path, &vec![])?;
+                num_pruned += 1;
+            }
+
+            let parent_index_block_hash = StacksBlockHeader::make_index_block_hash(
+                &block.parent_consensus_hash,
+                &block.parent_anchored_block_hash,
+            );
+            let args: &[&dyn ToSql] = &[&Vec::<u8>::new(), &parent_index_block_hash];
+            tx.execute(clear_microblocks_sql, args)
+                .map_err(|e| Error::DBError(db_error::SqliteError(e)))?;
+        }
+
+        tx.execute(
+            "UPDATE pruned_height SET height = ?1",
+            &[&u64_to_sql(to_height)?],
+        )
+        .map_err(|e| Error::DBError(db_error::SqliteError(e)))?;
+        tx.commit()
+            .map_err(|e| Error::DBError(db_error::SqliteError(e)))?;
+
+        debug!(
+            "Pruned the data of {} blocks from height {} to {}",
+            num_pruned, from_height, to_height
+        );
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::chainstate::burn::ConsensusHash;
+    use crate::chainstate::stacks::db::blocks::test::*;
+    use crate::chainstate::stacks::db::test::instantiate_chainstate;
+    use crate::chainstate::stacks::StacksBlock;
+    use stacks_common::types::chainstate::{BlockHeaderHash, StacksPrivateKey};
+
+    #[test]
+    fn test_prune_block_data() {
+        let mut chainstate = instantiate_chainstate(false, 0x80000000, "test_prune_block_data");
+
+        let mut blocks: Vec<StacksBlock> = vec![];
+        let mut privks = vec![];
+        let mut consensus_hashes = vec![];
+        for i in 0..16 {
+            let privk = StacksPrivateKey::new();
+            let mut block = make_empty_coinbase_block(&privk);
+            block.header.total_work.work = (i + 1) as u64;
+            blocks.push(block);
+            privks.push(privk);
+            consensus_hashes.push(ConsensusHash([(i + 1) as u8; 20]));
+        }
+
+        // each block confirms its parent's microblock stream
+        let mut microblocks = vec![];
+        for i in 0..blocks.len() {
+            let mut mblocks = make_sample_microblock_stream(&privks[i], &blocks[i].block_hash());
+            mblocks.truncate(3);
+            if i + 1 < blocks.len() {
+                blocks[i + 1].header.parent_block = blocks[i].block_hash();
+                blocks[i + 1].header.parent_microblock = mblocks[2].block_hash();
+                blocks[i + 1].header.parent_microblock_sequence = mblocks[2].header.sequence;
+            }
+            microblocks.push(mblocks);
+        }
+        let block_hashes: Vec<BlockHeaderHash> = blocks.iter().map(|b| b.block_hash()).collect();
+
+        for i in 0..blocks.len() {
+            let parent_consensus_hash = ConsensusHash([i as u8; 20]);
+            store_staging_block(
+                &mut chainstate,
+                &consensus_hashes[i],
+                &blocks[i],
+                &parent_consensus_hash,
+                1,
+                2,
+            );
+            for mblock in microblocks[i].iter() {
+                store_staging_microblock(
+                    &mut chainstate,
+                    &consensus_hashes[i],
+                    &block_hashes[i],
+                    mblock,
+                );
+            }
+        }
+        for i in 0..blocks.len() {
+            set_block_processed(
+                &mut chainstate,
+                &consensus_hashes[i],
+                &block_hashes[i],
+                true,
+            );
+            if i + 1 < blocks.len() {
+                set_microblocks_processed(
+                    &mut chainstate,
+                    &consensus_hashes[i + 1],
+                    &block_hashes[i + 1],
+                    &microblocks[i][2].block_hash(),
+                );
+            }
+        }
+
+        // archival by default
+        assert_eq!(chainstate.prune_block_data(16).unwrap(), None);
+        assert_eq!(
+            StacksChainState::get_pruned_height(chainstate.db()).unwrap(),
+            0
+        );
+
+        // pruning in batches gets to the same height, committing each batch
+        chainstate.prune_depth = Some(12);
+        assert_eq!(
+            chainstate.prune_block_data_in_batches(16, 3).unwrap(),
+            Some(4)
+        );
+        assert_eq!(
+            StacksChainState::get_pruned_height(chainstate.db()).unwrap(),
+            4
+        );
+
+        chainstate.prune_depth = Some(6);
+        assert_eq!(
+            chainstate.prune_block_data_in_batches(16, 4).unwrap(),
+            Some(10)
+        );
+        assert_eq!(
+            StacksChainState::get_pruned_height(chainstate.db()).unwrap(),
+            10
+        );
+
+        // nothing more to do at the same tip
+        assert_eq!(chainstate.prune_block_data(16).unwrap(), None);
+
+        let header_hashes: Vec<_> = consensus_hashes
+            .iter()
+            .zip(block_hashes.iter())
+            .map(|(ch, bhh)| (ch.clone(), Some(bhh.clone())))
+            .collect();
+        let inv = chainstate.get_blocks_inventory(&header_hashes).unwrap();
+        for i in 0..blocks.len() {
+            let height = (i + 1) as u64;
+            let index_block_hash =
+                StacksBlockHeader::make_index_block_hash(&consensus_hashes[i], &block_hashes[i]);
+
+            // still counts as stored, so it won't be downloaded again
+            assert!(StacksChainState::has_block_indexed(
+                &chainstate.blocks_path,
+                &index_block_hash
+            )
+            .unwrap());
+
+            let has_data = height >= 10;
+            assert_eq!(
+                StacksChainState::has_block_data_indexed(
+                    &chainstate.blocks_path,
+                    &index_block_hash
+                )
+                .unwrap(),
+                has_data
+            );
+            assert_eq!(
+                StacksChainState::load_block(
+                    &chainstate.blocks_path,
+                    &consensus_hashes[i],
+                    &block_hashes[i]
+                )
+                .unwrap()
+                .is_some(),
+                has_data
+            );
+            assert_eq!(inv.has_ith_block(i as u16), has_data);
+
+            // the stream confirmed by block i is pruned with it
+            if i > 0 {
+                let stream = StacksChainState::load_processed_microblock_stream_fork(
+                    chainstate.db(),
+                    &consensus_hashes[i - 1],
+                    &block_hashes[i - 1],
+                    &microblocks[i - 1][2].block_hash(),
+                )
+                .unwrap();
+                assert_eq!(stream.is_some(), has_data);
+                assert_eq!(inv.has_ith_microblock_stream(i as u16), has_data);
+            }
+
+            assert_eq!(
+                StacksChainState::is_block_pruned(chainstate.db(), &index_block_hash).unwrap(),
+                !has_data
+            );
+        }
+    }
+
+    #[test]
+    fn test_prune_block_data_invalid_blocks() {
+        let mut chainstate =
+            instantiate_chainstate(false, 0x80000000, "test_prune_block_data_invalid_blocks");
+        chainstate.prune_depth = Some(6);
+
+        // an accepted and an invalid block at height 1, and an accepted block at height 16
+        let mut blocks = vec![];
+        for (i, height) in [1, 1, 16].iter().enumerate() {
+            let mut block = make_empty_coinbase_block(&StacksPrivateKey::new());
+            block.header.total_work.work = *height;
+            let consensus_hash = ConsensusHash([(i + 1) as u8; 20]);
+            store_staging_block(
+                &mut chainstate,
+                &consensus_hash,
+                &block,
+                &ConsensusHash([0; 20]),
+                1,
+                2,
+            );
+            set_block_processed(
+                &mut chainstate,
+                &consensus_hash,
+                &block.block_hash(),
+                i != 1,
+            );
+            blocks.push(StacksBlockHeader::make_index_block_hash(
+                &consensus_hash,
+                &block.block_hash(),
+            ));
+        }
+
+        assert_eq!(chainstate.prune_block_data(16).unwrap(), Some(10));
+
+        // both blocks at height 1 have empty files, but only the accepted one was pruned
+        for index_block_hash in blocks[0..2].iter() {
+            assert!(!StacksChainState::has_block_data_indexed(
+                &chainstate.blocks_path,
+                index_block_hash
+            )
+            .unwrap());
+        }
+        assert!(StacksChainState::is_block_pruned(chainstate.db(), &blocks[0]).unwrap());
+        assert!(!StacksChainState::is_block_pruned(chainstate.db(), &blocks[1]).unwrap());
+        assert!(!StacksChainState::is_block_pruned(chainstate.db(), &blocks[2]).unwrap());
+    }
+}
@@ -0,0 +1,457 @@
+// Copyright (C) 2013-2020 Blockstack PBC, a public benefit corporation
+// Copyright (C) 2020 Stacks Open Internet Foundation
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Deterministic replay of a processed block, for debugging consensus divergence between
+//! nodes. The block is re-executed on top of its parent's state in a scratch copy of the
+//! chainstate, with the same deposit operations it was first processed with, and the MARF
+//! writes, events and costs of each of its transactions are traced. The outcome is then
+//! compared with the block header's state and withdrawal roots and with the receipts the node
+//! stored in its transaction index.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use clarity::vm::costs::ExecutionCost;
+use rusqlite::OpenFlags;
+
+use crate::chainstate::burn::db::sortdb::SortitionDB;
+use crate::chainstate::stacks::db::snapshot::{make_staging_dir, path_str, stage_files};
+use crate::chainstate::stacks::db::{IndexedTransaction, StacksChainState};
+use crate::chainstate::stacks::events::{StacksTransactionReceipt, TransactionOrigin};
+use crate::chainstate::stacks::Error;
+use crate::clarity_vm::database::marf::MarfWriteLogEntry;
+use crate::util_lib::db::sqlite_open;
+use stacks_common::types::chainstate::StacksBlockId;
+
+/// A key-value write to the Clarity MARF
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct MarfWrite {
+    pub key: String,
+    pub value: String,
+}
+
+/// What one transaction of the replayed block did
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct TransactionTrace {
+    pub txid: String,
+    /// `stacks` for a transaction, `burn` for a burnchain operation
+    pub origin: String,
+    pub tx_index: u32,
+    pub microblock_hash: Option<String>,
+    pub result_hex: String,
+    pub post_condition_aborted: bool,
+    pub stx_burned: String,
+    pub events: Vec<serde_json::Value>,
+    pub execution_cost: ExecutionCost,
+    pub writes: Vec<MarfWrite>,
+}
+
+/// A difference between a replayed receipt and the one in the transaction index. `field` is
+/// the name of the differing receipt field, or `missing` for a stored receipt the replay did
+/// not produce, `extra` for a replayed receipt that was not stored, and `position` for a
+/// receipt at a different position in the block.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ReceiptDiff {
+    pub txid: String,
+    pub field: String,
+    pub stored: serde_json::Value,
+    pub replayed: serde_json::Value,
+}
+
+/// The outcome of replaying a block
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ReplayReport {
+    pub index_block_hash: String,
+    pub block_height: u64,
+    pub expected_state_root: String,
+    pub replayed_state_root: String,
+    pub state_root_matches: bool,
+    pub expected_withdrawal_root: String,
+    pub replayed_withdrawal_root: String,
+    pub withdrawal_root_matches: bool,
+    /// In the order of the block's receipts
+    pub transactions: Vec<TransactionTrace>,
+    /// Writes made outside of any transaction, e.g. by an epoch transition or by paying the
+    /// miners
+    pub unattributed_writes: Vec<MarfWrite>,
+    /// None if the transaction index has no receipts for the block
+    pub receipt_diffs: Option<Vec<ReceiptDiff>>,
+}
+
+fn trace_receipt(receipt: &StacksTransactionReceipt) -> TransactionTrace {
+    let txid = receipt.transaction.txid();
+    TransactionTrace {
+        txid: txid.to_string(),
+        origin: match receipt.transaction {
+            TransactionOrigin::Stacks(_) => "stacks".to_string(),
+            TransactionOrigin::Burn(_) => "burn".to_string(),
+        },
+        tx_index: receipt.tx_index,
+        microblock_hash: receipt
+            .microblock_header
+            .as_ref()
+            .map(|header| header.block_hash().to_string()),
+        result_hex: receipt.result.serialize_to_hex(),
+        post_condition_aborted: receipt.post_condition_aborted,
+        stx_burned: receipt.stx_burned.to_string(),
+        // serialized as the transaction index stores them, so the two compare
+        events: receipt
+            .events
+            .iter()
+            .enumerate()
+            .map(|(event_index, event)| {
+                event.json_serialize(event_index, &txid, !receipt.post_condition_aborted)
+            })
+            .collect(),
+        execution_cost: receipt.execution_cost.clone(),
+        writes: vec![],
+    }
+}
+
+fn marf_writes(writes: Vec<(String, String)>) -> Vec<MarfWrite> {
+    writes
+        .into_iter()
+        .map(|(key, value)| MarfWrite { key, value })
+        .collect()
+}
+
+/// Hand each logged write to the trace of the transaction that made it. Returns the writes
+/// that no traced transaction made.
+fn attribute_writes(
+    traces: &mut [TransactionTrace],
+    entries: Vec<MarfWriteLogEntry>,
+) -> Vec<MarfWrite> {
+    let mut unattributed = vec![];
+    for entry in entries.into_iter() {
+        let position = entry.txid.and_then(|txid| {
+            let txid = txid.to_string();
+            traces.iter().position(|trace| trace.txid == txid)
+        });
+        match position {
+            Some(i) => traces[i].writes.extend(marf_writes(entry.writes)),
+            None => unattributed.extend(marf_writes(entry.writes)),
+        }
+    }
+    unattributed
+}
+
+fn field_diff<T: PartialEq + serde::Serialize>(
+    diffs: &mut Vec<ReceiptDiff>,
+    txid: &str,
+    field: &str,
+    stored: &T,
+    replayed: &T,
+) {
+    if stored != replayed {
+        diffs.push(ReceiptDiff {
+            txid: txid.to_string(),
+            field: field.to_string(),
+            stored: serde_json::to_value(stored).expect("FATAL: failed to serialize receipt"),
+            replayed: serde_json::to_value(replayed).expect("FATAL: failed to serialize receipt"),
+        });
+    }
+}
+
+/// Compare the replayed receipts with the stored ones, which are in receipt order
+fn diff_receipts(stored: &[IndexedTransaction], replayed: &[TransactionTrace]) -> Vec<ReceiptDiff> {
+    let mut diffs = vec![];
+    for (stored_pos, stored_tx) in stored.iter().enumerate() {
+        let txid = stored_tx.txid.to_string();
+        let (replayed_pos, trace) = match replayed
+            .iter()
+            .enumerate()
+            .find(|(_, trace)| trace.txid == txid)
+        {
+            Some(found) => found,
+            None => {
+                diffs.push(ReceiptDiff {
+                    txid,
+                    field: "missing".to_string(),
+                    stored: serde_json::json!(stored_pos),
+                    replayed: serde_json::Value::Null,
+                });
+                continue;
+            }
+        };
+        field_diff(&mut diffs, &txid, "position", &stored_pos, &replayed_pos);
+        field_diff(
+            &mut diffs,
+            &txid,
+            "tx_index",
+            &stored_tx.tx_index,
+            &trace.tx_index,
+        );
+        field_diff(
+            &mut diffs,
+            &txid,
+            "microblock_hash",
+            &stored_tx
+                .microblock_hash
+                .as_ref()
+                .map(|hash| hash.to_string()),
+            &trace.microblock_hash,
+        );
+        field_diff(
+            &mut diffs,
+            &txid,
+            "result_hex",
+            &stored_tx.result_hex,
+            &trace.result_hex,
+        );
+        field_diff(
+            &mut diffs,
+            &txid,
+            "post_condition_aborted",
+            &stored_tx.post_condition_aborted,
+            &trace.post_condition_aborted,
+        );
+        field_diff(
+            &mut diffs,
+            &txid,
+            "stx_burned",
+            &stored_tx.stx_burned.to_string(),
+            &trace.stx_burned,
+        );
+        field_diff(
+            &mut diffs,
+            &txid,
+            "events",
+            &stored_tx.events,
+            &trace.events,
+        );
+        field_diff(
+            &mut diffs,
+            &txid,
+            "execution_cost",
+            &stored_tx.execution_cost,
+            &trace.execution_cost,
+        );
+    }
+    for (replayed_pos, trace) in replayed.iter().enumerate() {
+        if !stored
+            .iter()
+            .any(|stored_tx| stored_tx.txid.to_string() == trace.txid)
+        {
+            diffs.push(ReceiptDiff {
+                txid: trace.txid.clone(),
+                field: "extra".to_string(),
+                stored: serde_json::Value::Null,
+                replayed: serde_json::json!(replayed_pos),
+            });
+        }
+    }
+    diffs
+}
+
+/// Open the chainstate copied to `scratch_root`, reading block data from the original
+/// chainstate at `chainstate_path`
+pub(super) fn open_scratch_chainstate(
+    chainstate_path: &str,
+    scratch_root: &Path,
+) -> Result<StacksChainState, Error> {
+    let db_config = {
+        let conn = sqlite_open(
+            StacksChainState::header_index_root_path(scratch_root.to_path_buf()),
+            OpenFlags::SQLITE_OPEN_READ_ONLY,
+            false,
+        )?;
+        StacksChainState::load_db_config(&conn)?
+    };
+    let (mut chainstate, _) = StacksChainState::open(
+        db_config.mainnet,
+        db_config.chain_id,
+        &path_str(scratch_root)?,
+        None,
+    )?;
+    // block files are only read, so there is no need to copy them
+    chainstate.blocks_path = path_str(&StacksChainState::blocks_path(PathBuf::from(
+        chainstate_path,
+    )))?;
+    Ok(chainstate)
+}
+
+fn replay_in_scratch(
+    chainstate_path: &str,
+    scratch_root: &Path,
+    sortdb_path: &str,
+    index_block_hash: &StacksBlockId,
+) -> Result<ReplayReport, Error> {
+    stage_files(&PathBuf::from(chainstate_path), scratch_root)?;
+    let mut chainstate = open_scratch_chainstate(chainstate_path, scratch_root)?;
+    let sortdb = SortitionDB::open(sortdb_path, false)?;
+
+    let stored =
+        StacksChainState::get_indexed_transactions_in_block(chainstate.db(), index_block_hash)?;
+    let replayed = chainstate
+        .replay_processed_block(&sortdb, index_block_hash, true)?
+        .ok_or(Error::NoSuchBlockError)?;
+
+    let mut transactions: Vec<_> = replayed
+        .epoch_receipt
+        .tx_receipts
+        .iter()
+        .map(trace_receipt)
+        .collect();
+    let unattributed_writes = match replayed.write_log {
+        Some(write_log) => attribute_writes(&mut transactions, write_log.into_entries()),
+        None => vec![],
+    };
+    let receipt_diffs = if stored.is_empty() {
+        None
+    } else {
+        Some(diff_receipts(&stored, &transactions))
+    };
+
+    let header = &replayed.block.header;
+    Ok(ReplayReport {
+        index_block_hash: index_block_hash.to_string(),
+        block_height: replayed.epoch_receipt.header.stacks_block_height,
+        expected_state_root: header.state_index_root.to_string(),
+        replayed_state_root: replayed.state_root.to_string(),
+        state_root_matches: header.state_index_root == replayed.state_root,
+        expected_withdrawal_root: header.withdrawal_merkle_root.to_string(),
+        replayed_withdrawal_root: replayed.withdrawal_root.to_string(),
+        withdrawal_root_matches: header.withdrawal_merkle_root == replayed.withdrawal_root,
+        transactions,
+        unattributed_writes,
+        receipt_diffs,
+    })
+}
+
+/// Replay the processed block `index_block_hash` of the chainstate at `chainstate_path`, with
+/// the deposit operations in the sortition DB at `sortdb_path`. The chainstate is not
+/// modified: the replay runs in a copy of its DBs, which is removed afterwards.
+pub fn replay_block_report(
+    chainstate_path: &str,
+    sortdb_path: &str,
+    index_block_hash: &StacksBlockId,
+) -> Result<ReplayReport, Error> {
+    let scratch_root = PathBuf::from(format!("{}.replay", chainstate_path));
+    make_staging_dir(&scratch_root)?;
+    let result = replay_in_scratch(
+        chainstate_path,
+        &scratch_root,
+        sortdb_path,
+        index_block_hash,
+    );
+    let _ = fs::remove_dir_all(&scratch_root);
+    result
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::burnchains::Txid;
+    use crate::clarity_vm::database::marf::MarfWriteLog;
+
+    fn trace(txid: &Txid, tx_index: u32, result_hex: &str) -> TransactionTrace {
+        TransactionTrace {
+            txid: txid.to_string(),
+            origin: "stacks".to_string(),
+            tx_index,
+            microblock_hash: None,
+            result_hex: result_hex.to_string(),
+            post_condition_aborted: false,
+            stx_burned: "0".to_string(),
+            events: vec![],
+            execution_cost: ExecutionCost::zero(),
+            writes: vec![],
+        }
+    }
+
+    fn stored(txid: &Txid, tx_index: u32, result_hex: &str) -> IndexedTransaction {
+        IndexedTransaction {
+            txid: txid.clone(),
+            index_block_hash: StacksBlockId([0x01; 32]),
+            tx_hex: "".to_string(),
+            burnchain_op: None,
+            tx_index,
+            microblock_hash: None,
+            result_hex: result_hex.to_string(),
+            post_condition_aborted: false,
+            stx_burned: 0,
+            events: vec![],
+            execution_cost: ExecutionCost::zero(),
+        }
+    }
+
+    #[test]
+    fn test_attribute_writes() {
+        let txid_a = Txid([0xaa; 32]);
+        let txid_b = Txid([0xbb; 32]);
+        let write = |key: &str| (key.to_string(), "00".to_string());
+
+        let mut write_log = MarfWriteLog::default();
+        // an empty entry for a transaction that wrote nothing is not an unattributed one
+        write_log.close_entry(None);
+        write_log.add_writes(&[write("reward")]);
+        write_log.close_entry(None);
+        write_log.add_writes(&[write("a1")]);
+        write_log.add_writes(&[write("a2")]);
+        write_log.close_entry(Some(txid_a.clone()));
+        write_log.close_entry(Some(txid_b.clone()));
+        write_log.add_writes(&[write("finish")]);
+
+        let mut traces = vec![trace(&txid_a, 0, "03"), trace(&txid_b, 1, "03")];
+        let unattributed = attribute_writes(&mut traces, write_log.into_entries());
+        let keys = |writes: &[MarfWrite]| -> Vec<String> {
+            writes.iter().map(|write| write.key.clone()).collect()
+        };
+        assert_eq!(keys(&traces[0].writes), vec!["a1", "a2"]);
+        assert!(traces[1].writes.is_empty());
+        assert_eq!(keys(&unattributed), vec!["reward", "finish"]);
+    }
+
+    #[test]
+    fn test_diff_receipts() {
+        let txid_a = Txid([0xaa; 32]);
+        let txid_b = Txid([0xbb; 32]);
+        let txid_c = Txid([0xcc; 32]);
+
+        let stored_txs = vec![stored(&txid_a, 0, "03"), stored(&txid_b, 1, "03")];
+        let traces = vec![trace(&txid_a, 0, "03"), trace(&txid_b, 1, "03")];
+        assert!(diff_receipts(&stored_txs, &traces).is_empty());
+
+        // b's result differs, a is reordered after c, and c was never stored
+        let traces = vec![
+            trace(&txid_c, 0, "03"),
+            trace(&txid_a, 0, "03"),
+            trace(&txid_b, 1, "04"),
+        ];
+        let diffs = diff_receipts(&stored_txs, &traces);
+        let fields: Vec<_> = diffs
+            .iter()
+            .map(|diff| (diff.txid.clone(), diff.field.as_str()))
+            .collect();
+        assert_eq!(
+            fields,
+            vec![
+                (txid_a.to_string(), "position"),
+                (txid_b.to_string(), "position"),
+                (txid_b.to_string(), "result_hex"),
+                (txid_c.to_string(), "extra"),
+            ]
+        );
+        assert_eq!(diffs[2].stored, serde_json::json!("03"));
+        assert_eq!(diffs[2].replayed, serde_json::json!("04"));
+
+        // b was not replayed
+        let diffs = diff_receipts(&stored_txs, &traces[1..2]);
+        assert_eq!(diffs.len(), 1);
+        assert_eq!(diffs[0].txid, txid_b.to_string());
+        assert_eq!(diffs[0].field, "missing");
+    }
+}
@@ -0,0 +1,746 @@
+// Copyright (C) 2013-2020 Blockstack PBC, a public benefit corporation
+// Copyright (C) 2020 Stacks Open Internet Foundation
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Chainstate snapshots let a new node start from a recent block instead of replaying the
+//! chain from genesis. A snapshot is a single archive with the headers DB and the Clarity MARF
+//! (its tries and side tables) as of one Stacks block, the snapshot's anchor:
+//!
+//! ```text
+//! SNAPSHOT_MAGIC | manifest length (u32, big-endian) | manifest (JSON) | files, in manifest order
+//! ```
+//!
+//! An imported snapshot is checked against the importing node's own sortition DB: the anchor's
+//! block hash and withdrawal root must match the block commit that elected it, and the roots of
+//! the anchor's tries must match the ones in its header. Every trie in the anchor's fork, in
+//! both MARFs, is then rehashed from its leaves up, and each value a leaf commits to is checked
+//! against the MARF's side table, so altered tries or values fail the import even if the
+//! stored roots were left alone. This does not cover the Clarity `metadata_table` (contract
+//! source and analysis), which the MARF does not hash: a tampered metadata table would go
+//! unnoticed until a contract call misbehaves. Block files are not part of a snapshot, so the
+//! node cannot serve blocks from before the anchor to its peers.
+//!
+//! A snapshot can only be anchored at the highest processed block, since a MARF cannot be
+//! rolled back past the tries that were built on top of the anchor.
+
+use std::cmp;
+use std::fs;
+use std::fs::File;
+use std::io;
+use std::io::{BufReader, BufWriter, Read, Write};
+use std::path::{Path, PathBuf};
+
+use rusqlite::types::ToSql;
+use rusqlite::{OpenFlags, NO_PARAMS};
+use sha2::{Digest, Sha512_256};
+
+use crate::chainstate::burn::db::sortdb::SortitionDB;
+use crate::chainstate::burn::ConsensusHash;
+use crate::chainstate::stacks::db::{StacksChainState, StacksHeaderInfo};
+use crate::chainstate::stacks::index::marf::{
+    MARFOpenOpts, MarfConnection, BLOCK_HEIGHT_TO_HASH_MAPPING_KEY, MARF,
+};
+use crate::chainstate::stacks::index::node::TriePath;
+use crate::chainstate::stacks::index::storage::TrieFileStorage;
+use crate::chainstate::stacks::index::{Error as MarfError, MARFValue};
+use crate::chainstate::stacks::Error;
+use crate::util_lib::db::{query_row, query_rows, sqlite_open, u64_to_sql};
+use stacks_common::types::chainstate::{BlockHeaderHash, StacksBlockId, TrieHash};
+use stacks_common::util::hash::Sha512Trunc256Sum;
+
+/// Leading bytes of a snapshot archive
+pub const SNAPSHOT_MAGIC: &[u8; 8] = b"SUBNSNAP";
+/// Version of the archive layout and manifest
+pub const SNAPSHOT_VERSION: u32 = 1;
+/// Largest manifest accepted when reading an archive
+const MAX_MANIFEST_LEN: u32 = 1024 * 1024;
+/// Looks up the value a leaf of the headers MARF commits to
+pub(super) const HEADERS_VALUE_QUERY: &str =
+    "SELECT value FROM __fork_storage WHERE value_hash = ?1";
+/// Looks up the value a leaf of the Clarity MARF commits to
+pub(super) const CLARITY_VALUE_QUERY: &str = "SELECT value FROM data_table WHERE key = ?1";
+
+/// A file in a snapshot
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SnapshotFile {
+    /// Path relative to the chainstate directory
+    pub path: String,
+    pub size: u64,
+    pub sha512_256: Sha512Trunc256Sum,
+}
+
+/// Describes a snapshot's anchor block and the files that hold its state
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SnapshotManifest {
+    pub version: u32,
+    pub mainnet: bool,
+    pub chain_id: u32,
+    pub index_block_hash: StacksBlockId,
+    pub consensus_hash: ConsensusHash,
+    pub block_hash: BlockHeaderHash,
+    pub block_height: u64,
+    pub state_index_root: TrieHash,
+    pub files: Vec<SnapshotFile>,
+}
+
+/// The SQLite DBs in a snapshot, relative to the chainstate directory
+fn snapshot_db_paths() -> Vec<PathBuf> {
+    vec![
+        StacksChainState::header_index_root_path(PathBuf::new()),
+        StacksChainState::vm_state_index_marf_path(PathBuf::new()),
+    ]
+}
+
+/// Where the MARF at `db_path` keeps its trie blobs
+fn blobs_path(db_path: &Path) -> PathBuf {
+    let mut path = db_path.as_os_str().to_owned();
+    path.push(".blobs");
+    PathBuf::from(path)
+}
+
+/// Every file a snapshot may contain, relative to the chainstate directory
+fn snapshot_file_paths() -> Vec<PathBuf> {
+    snapshot_db_paths()
+        .into_iter()
+        .flat_map(|db_path| {
+            let blobs = blobs_path(&db_path);
+            vec![db_path, blobs]
+        })
+        .collect()
+}
+
+pub(super) fn path_str(path: &Path) -> Result<String, Error> {
+    path.to_str()
+        .map(|s| s.to_string())
+        .ok_or_else(|| Error::InvalidSnapshot(format!("Path {:?} is not valid UTF-8", path)))
+}
+
+/// Remove anything left over at `dir` and create it afresh
+pub(super) fn make_staging_dir(dir: &Path) -> Result<(), Error> {
+    if dir.exists() {
+        fs::remove_dir_all(dir).map_err(Error::WriteError)?;
+    }
+    fs::create_dir_all(dir).map_err(Error::WriteError)
+}
+
+/// Copy `size` bytes from `input` to `output`, and return their SHA512/256 hash
+fn copy_hashed<R: Read, W: Write>(
+    input: &mut R,
+    output: &mut W,
+    size: u64,
+) -> io::Result<Sha512Trunc256Sum> {
+    let mut sha2 = Sha512_256::new();
+    let mut buf = vec![0u8; 65536];
+    let mut remaining = size;
+    while remaining > 0 {
+        let len = cmp::min(remaining, buf.len() as u64) as usize;
+        input.read_exact(&mut buf[..len])?;
+        sha2.update(&buf[..len]);
+        output.write_all(&buf[..len])?;
+        remaining -= len as u64;
+    }
+    let mut digest_bits = [0u8; 32];
+    digest_bits.copy_from_slice(sha2.finalize().as_slice());
+    Ok(Sha512Trunc256Sum(digest_bits))
+}
+
+/// Copy the snapshot's files from `chainstate_root` to `staging_root`. Each DB is copied with
+/// `VACUUM INTO`, which gives a consistent copy even if the node has it open. The blobs are
+/// copied after their DB: they are only ever appended to, so the copy has every trie the DB
+/// refers to.
+pub(super) fn stage_files(
+    chainstate_root: &Path,
+    staging_root: &Path,
+) -> Result<Vec<PathBuf>, Error> {
+    let mut staged = vec![];
+    for db_path in snapshot_db_paths() {
+        let src = chainstate_root.join(&db_path);
+        let dest = staging_root.join(&db_path);
+        if let Some(parent) = dest.parent() {
+            fs::create_dir_all(parent).map_err(Error::WriteError)?;
+        }
+        let conn = sqlite_open(&src, OpenFlags::SQLITE_OPEN_READ_ONLY, false)?;
+        let args: &[&dyn ToSql] = &[&path_str(&dest)?];
+        conn.execute("VACUUM INTO ?1", args)?;
+        staged.push(db_path.clone());
+
+        let blobs = blobs_path(&db_path);
+        if chainstate_root.join(&blobs).exists() {
+            fs::copy(chainstate_root.join(&blobs), staging_root.join(&blobs))
+                .map_err(Error::WriteError)?;
+            staged.push(blobs);
+        }
+    }
+    Ok(staged)
+}
+
+/// Size and hash the staged files
+fn describe_files(staging_root: &Path, paths: &[PathBuf]) -> Result<Vec<SnapshotFile>, Error> {
+    let mut files = vec![];
+    for path in paths.iter() {
+        let mut fd = File::open(staging_root.join(path)).map_err(Error::ReadError)?;
+        let size = fd.metadata().map_err(Error::ReadError)?.len();
+        let sha512_256 = copy_hashed(&mut BufReader::new(&mut fd), &mut io::sink(), size)
+            .map_err(Error::ReadError)?;
+        files.push(SnapshotFile {
+            path: path_str(path)?,
+            size,
+            sha512_256,
+        });
+    }
+    Ok(files)
+}
+
+/// Open the MARF at `path` read-only
+pub(super) fn open_marf_readonly(path: &Path) -> Result<MARF<StacksBlockId>, Error> {
+    let mut open_opts = MARFOpenOpts::default();
+    open_opts.external_blobs = true;
+    let storage = TrieFileStorage::open_readonly(&path_str(path)?, open_opts)?;
+    Ok(MARF::from_storage(storage))
+}
+
+/// Is the leaf at `path` one of the keys a MARF writes into the trie at `height` for its own
+/// block height bookkeeping? Those leaves hold a block height or a block ID, rather than the hash of a
+/// value in the side table.
+fn is_block_height_leaf(height: u32, path: &TriePath, value: &MARFValue) -> bool {
+    let mut heights = vec![height];
+    if height > 0 {
+        heights.push(height - 1);
+    }
+    heights.iter().any(|h| {
+        *value == MARFValue::from(*h)
+            || *path == TriePath::from_key(&format!("{}::{}", BLOCK_HEIGHT_TO_HASH_MAPPING_KEY, h))
+    })
+}
+
+/// Rehash the trie of `block_id`, at `height` in `marf`, and check that each value written in
+/// it is in the MARF's side table (looked up with `value_query`) and hashes to its leaf.
+/// The first inconsistency found is returned as a MARF `CorruptionError`, to tell it apart from
+/// a failure to read the MARF.
+pub(super) fn verify_trie_contents(
+    marf: &mut MARF<StacksBlockId>,
+    value_query: &str,
+    block_id: &StacksBlockId,
+    height: u32,
+) -> Result<(), Error> {
+    let leaves = marf.verify_trie_at(block_id)?;
+    for (path, value) in leaves.iter() {
+        let args: &[&dyn ToSql] = &[&value.to_hex()];
+        let stored: Option<String> = query_row(marf.sqlite_conn(), value_query, args)?;
+        match stored {
+            Some(stored) if MARFValue::from_value(&stored) == *value => {}
+            Some(_) => {
+                return Err(MarfError::CorruptionError(format!(
+                    "Value of leaf {} in trie {} does not hash to {}",
+                    path.to_hex(),
+                    block_id,
+                    value.to_hex()
+                ))
+                .into());
+            }
+            None if is_block_height_leaf(height, path, value) => {}
+            None => {
+                return Err(MarfError::CorruptionError(format!(
+                    "Value {} of leaf {} in trie {} is missing",
+                    value.to_hex(),
+                    path.to_hex(),
+                    block_id
+                ))
+                .into());
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Check every trie in the fork of `tip` in `marf`, from the first one up to `tip`'s
+fn verify_fork_contents(
+    marf: &mut MARF<StacksBlockId>,
+    value_query: &str,
+    tip: &StacksBlockId,
+) -> Result<(), Error> {
+    let tip_height = marf
+        .get_block_height(tip, tip)?
+        .ok_or_else(|| Error::InvalidSnapshot(format!("No trie for {}", tip)))?;
+    for height in 0..=tip_height {
+        let block_id = marf.get_block_at_height(height, tip)?.ok_or_else(|| {
+            Error::InvalidSnapshot(format!("No trie at height {} below {}", height, tip))
+        })?;
+        verify_trie_contents(marf, value_query, &block_id, height).map_err(|e| match e {
+            Error::MARFError(MarfError::CorruptionError(msg)) => Error::InvalidSnapshot(msg),
+            e => e,
+        })?;
+    }
+    Ok(())
+}
+
+/// Check that the anchor's tries in the headers MARF and the Clarity MARF under `root` have
+/// the roots recorded in its header, and that every trie in its fork checks out against the
+/// data it commits to
+fn verify_anchor_state(root: &Path, header: &StacksHeaderInfo) -> Result<(), Error> {
+    let block_id = header.index_block_hash();
+
+    let mut headers_marf = open_marf_readonly(&StacksChainState::header_index_root_path(
+        root.to_path_buf(),
+    ))?;
+    let index_root = headers_marf.get_root_hash_at(&block_id)?;
+    if index_root != header.index_root {
+        return Err(Error::InvalidSnapshot(format!(
+            "Headers index root of {} is {}, but its header has {}",
+            &block_id, &index_root, &header.index_root
+        )));
+    }
+
+    let mut clarity_marf = open_marf_readonly(&StacksChainState::vm_state_index_marf_path(
+        root.to_path_buf(),
+    ))?;
+    let state_root = clarity_marf.get_root_hash_at(&block_id)?;
+    if state_root != header.anchored_header.state_index_root {
+        return Err(Error::InvalidSnapshot(format!(
+            "State root of {} is {}, but its header has {}",
+            &block_id, &state_root, &header.anchored_header.state_index_root
+        )));
+    }
+
+    verify_fork_contents(&mut headers_marf, HEADERS_VALUE_QUERY, &block_id)?;
+    verify_fork_contents(&mut clarity_marf, CLARITY_VALUE_QUERY, &block_id)
+}
+
+/// Find the anchor in the staged headers DB. It defaults to the highest processed block, and
+/// must be the highest processed block: a MARF cannot be rolled back, so a node restored from
+/// a snapshot with later tries would fail to process the blocks after the anchor.
+fn find_anchor(
+    staging_root: &Path,
+    anchor: Option<&StacksBlockId>,
+) -> Result<(StacksHeaderInfo, bool, u32), Error> {
+    let conn = sqlite_open(
+        StacksChainState::header_index_root_path(staging_root.to_path_buf()),
+        OpenFlags::SQLITE_OPEN_READ_WRITE,
+        false,
+    )?;
+    let db_config = StacksChainState::load_db_config(&conn)?;
+
+    let highest: i64 = conn.query_row(
+        "SELECT IFNULL(MAX(block_height), 0) FROM block_headers",
+        NO_PARAMS,
+        |row| row.get(0),
+    )?;
+    let highest = highest as u64;
+    if highest == 0 {
+        return Err(Error::InvalidSnapshot(
+            "No blocks have been processed yet".to_string(),
+        ));
+    }
+
+    let header = match anchor {
+        Some(anchor) => {
+            StacksChainState::get_stacks_block_header_info_by_index_block_hash(&conn, anchor)?
+                .ok_or_else(|| {
+                    Error::InvalidSnapshot(format!("Block {} has not been processed", anchor))
+                })?
+        }
+        None => {
+            let args: &[&dyn ToSql] = &[&u64_to_sql(highest)?];
+            let mut tips: Vec<StacksHeaderInfo> = query_rows(
+                &conn,
+                "SELECT * FROM block_headers WHERE block_height = ?1",
+                args,
+            )?;
+            if tips.len() != 1 {
+                return Err(Error::InvalidSnapshot(format!(
+                    "{} blocks were processed at height {}; pick one to export",
+                    tips.len(),
+                    highest
+                )));
+            }
+            tips.pop().expect("BUG: checked that there is one tip")
+        }
+    };
+
+    if header.stacks_block_height < highest {
+        return Err(Error::InvalidSnapshot(format!(
+            "Block {} is at height {}, but blocks up to height {} have been processed",
+            &header.index_block_hash(),
+            header.stacks_block_height,
+            highest
+        )));
+    }
+    Ok((header, db_config.mainnet, db_config.chain_id))
+}
+
+fn write_archive(
+    staging_root: &Path,
+    manifest: &SnapshotManifest,
+    output_path: &str,
+) -> Result<(), Error> {
+    let manifest_bytes = serde_json::to_vec(manifest).map_err(Error::JSONError)?;
+    let tmp_path = format!("{}.tmp", output_path);
+    {
+        let mut out = BufWriter::new(File::create(&tmp_path).map_err(Error::WriteError)?);
+        out.write_all(SNAPSHOT_MAGIC).map_err(Error::WriteError)?;
+        out.write_all(&(manifest_bytes.len() as u32).to_be_bytes())
+            .map_err(Error::WriteError)?;
+        out.write_all(&manifest_bytes).map_err(Error::WriteError)?;
+        for file in manifest.files.iter() {
+            let mut fd = File::open(staging_root.join(&file.path)).map_err(Error::ReadError)?;
+            io::copy(&mut fd, &mut out).map_err(Error::WriteError)?;
+        }
+        out.flush().map_err(Error::WriteError)?;
+    }
+    fs::rename(&tmp_path, output_path).map_err(Error::WriteError)
+}
+
+/// Unpack the archive at `archive_path` into `staging_root`, checking each file against the
+/// manifest
+fn read_archive(archive_path: &str, staging_root: &Path) -> Result<SnapshotManifest, Error> {
+    let mut input = BufReader::new(File::open(archive_path).map_err(Error::ReadError)?);
+
+    let mut magic = [0u8; 8];
+    input.read_exact(&mut magic).map_err(Error::ReadError)?;
+    if &magic != SNAPSHOT_MAGIC {
+        return Err(Error::InvalidSnapshot(format!(
+            "{} is not a snapshot archive",
+            archive_path
+        )));
+    }
+
+    let mut len_bytes = [0u8; 4];
+    input.read_exact(&mut len_bytes).map_err(Error::ReadError)?;
+    let manifest_len = u32::from_be_bytes(len_bytes);
+    if manifest_len > MAX_MANIFEST_LEN {
+        return Err(Error::InvalidSnapshot(format!(
+            "Manifest is too long ({} bytes)",
+            manifest_len
+        )));
+    }
+    let mut manifest_bytes = vec![0u8; manifest_len as usize];
+    input
+        .read_exact(&mut manifest_bytes)
+        .map_err(Error::ReadError)?;
+    let manifest: SnapshotManifest =
+        serde_json::from_slice(&manifest_bytes).map_err(Error::JSONError)?;
+    if manifest.version != SNAPSHOT_VERSION {
+        return Err(Error::InvalidSnapshot(format!(
+            "Unsupported snapshot version {}",
+            manifest.version
+        )));
+    }
+
+    let allowed = snapshot_file_paths();
+    for db_path in snapshot_db_paths() {
+        if !manifest
+            .files
+            .iter()
+            .any(|file| Path::new(&file.path) == db_path)
+        {
+            return Err(Error::InvalidSnapshot(format!(
+                "Snapshot does not contain {:?}",
+                &db_path
+            )));
+        }
+    }
+    for file in manifest.files.iter() {
+        // only ever write to the known chainstate files
+        if !allowed.iter().any(|path| Path::new(&file.path) == path) {
+            return Err(Error::InvalidSnapshot(format!(
+                "Unexpected file {} in snapshot",
+                &file.path
+            )));
+        }
+        let dest = staging_root.join(&file.path);
+        if let Some(parent) = dest.parent() {
+            fs::create_dir_all(parent).map_err(Error::WriteError)?;
+        }
+        let mut out = BufWriter::new(File::create(&dest).map_err(Error::WriteError)?);
+        let sha512_256 = copy_hashed(&mut input, &mut out, file.size).map_err(Error::ReadError)?;
+        out.flush().map_err(Error::WriteError)?;
+        if sha512_256 != file.sha512_256 {
+            return Err(Error::InvalidSnapshot(format!(
+                "{} has hash {}, but the manifest has {}",
+                &file.path,
+                sha512_256.to_hex(),
+                file.sha512_256.to_hex()
+            )));
+        }
+    }
+    Ok(manifest)
+}
+
+/// Check the staged snapshot's anchor against its manifest and the block commit for it in
+/// `sortdb`
+fn verify_staged_snapshot(
+    staging_root: &Path,
+    manifest: &SnapshotManifest,
+    sortdb: &SortitionDB,
+) -> Result<(), Error> {
+    let conn = sqlite_open(
+        StacksChainState::header_index_root_path(staging_root.to_path_buf()),
+        OpenFlags::SQLITE_OPEN_READ_WRITE,
+        false,
+    )?;
+    let db_config = StacksChainState::load_db_config(&conn)?;
+    if db_config.mainnet != manifest.mainnet || db_config.chain_id != manifest.chain_id {
+        return Err(Error::InvalidSnapshot(format!(
+            "Headers DB is for mainnet = {}, chain ID {}, but the manifest has mainnet = {}, chain ID {}",
+            db_config.mainnet, db_config.chain_id, manifest.mainnet, manifest.chain_id
+        )));
+    }
+
+    let header = StacksChainState::get_stacks_block_header_info_by_index_block_hash(
+        &conn,
+        &manifest.index_block_hash,
+    )?
+    .ok_or_else(|| {
+        Error::InvalidSnapshot(format!(
+            "Headers DB has no block {}",
+            &manifest.index_block_hash
+        ))
+    })?;
+    let block_hash = header.anchored_header.block_hash();
+    if header.consensus_hash != manifest.consensus_hash
+        || block_hash != manifest.block_hash
+        || StacksBlockId::new(&manifest.consensus_hash, &manifest.block_hash)
+            != manifest.index_block_hash
+        || header.stacks_block_height != manifest.block_height
+        || header.anchored_header.state_index_root != manifest.state_index_root
+    {
+        return Err(Error::InvalidSnapshot(format!(
+            "Header of {} does not match the manifest",
+            &manifest.index_block_hash
+        )));
+    }
+
+    let commit = SortitionDB::get_block_commit_for_stacks_block(
+        sortdb.conn(),
+        &header.consensus_hash,
+        &block_hash,
+    )?
+    .ok_or_else(|| {
+        Error::InvalidSnapshot(format!(
+            "No block commit for {}/{} in the sortition DB; sync the L1 past it first",
+            &header.consensus_hash, &block_hash
+        ))
+    })?;
+    if commit.block_header_hash != block_hash
+        || commit.withdrawal_merkle_root != header.anchored_header.withdrawal_merkle_root
+    {
+        return Err(Error::InvalidSnapshot(format!(
+            "Block {} does not match its block commit {}",
+            &manifest.index_block_hash, &commit.txid
+        )));
+    }
+
+    verify_anchor_state(staging_root, &header)
+}
+
+/// Move the staged files into the chainstate at `chainstate_path`, replacing whatever is there
+fn install_staged_files(
+    staging_root: &Path,
+    manifest: &SnapshotManifest,
+    chainstate_path: &str,
+) -> Result<(), Error> {
+    StacksChainState::make_chainstate_dirs(chainstate_path)?;
+    let chainstate_root = PathBuf::from(chainstate_path);
+    for file in manifest.files.iter() {
+        let dest = chainstate_root.join(&file.path);
+        if let Some(parent) = dest.parent() {
+            fs::create_dir_all(parent).map_err(Error::WriteError)?;
+        }
+        // a write-ahead log left over from the old DB would be applied to the new one
+        for suffix in ["", "-wal", "-shm"].iter() {
+            let mut old = dest.as_os_str().to_owned();
+            old.push(suffix);
+            let old = PathBuf::from(old);
+            if old.exists() {
+                fs::remove_file(&old).map_err(Error::WriteError)?;
+            }
+        }
+        fs::rename(staging_root.join(&file.path), &dest).map_err(Error::WriteError)?;
+    }
+    Ok(())
+}
+
+/// Write a snapshot of the chainstate at `chainstate_path` to `output_path`. The anchor is
+/// `anchor`, if given, or else the highest processed block; either way it must be the highest
+/// processed block, so `anchor` only serves to pick between sibling blocks at that height (or
+/// to make sure the snapshot is of the expected block). Snapshots of earlier blocks are not
+/// supported. The node should be stopped first: each DB is copied consistently, but not at the
+/// same instant as the other.
+pub fn export_snapshot(
+    chainstate_path: &str,
+    anchor: Option<&StacksBlockId>,
+    output_path: &str,
+) -> Result<SnapshotManifest, Error> {
+    let chainstate_root = PathBuf::from(chainstate_path);
+    let staging_root = PathBuf::from(format!("{}.staging", output_path));
+    make_staging_dir(&staging_root)?;
+
+    let result = stage_files(&chainstate_root, &staging_root).and_then(|paths| {
+        let (header, mainnet, chain_id) = find_anchor(&staging_root, anchor)?;
+        verify_anchor_state(&staging_root, &header)?;
+        let manifest = SnapshotManifest {
+            version: SNAPSHOT_VERSION,
+            mainnet,
+            chain_id,
+            index_block_hash: header.index_block_hash(),
+            consensus_hash: header.consensus_hash.clone(),
+            block_hash: header.anchored_header.block_hash(),
+            block_height: header.stacks_block_height,
+            state_index_root: header.anchored_header.state_index_root.clone(),
+            files: describe_files(&staging_root, &paths)?,
+        };
+        write_archive(&staging_root, &manifest, output_path)?;
+        Ok(manifest)
+    });
+
+    let _ = fs::remove_dir_all(&staging_root);
+    result
+}
+
+/// Restore the chainstate at `chainstate_path` from the snapshot at `archive_path`, once it
+/// checks out against `sortdb`. The node's sortition DB must already have processed the L1
+/// block with the commit for the snapshot's anchor. Once restored, the node processes blocks
+/// from the anchor on as usual.
+///
+/// Refuses to replace an existing chainstate unless `overwrite` is set.
+pub fn import_snapshot(
+    archive_path: &str,
+    chainstate_path: &str,
+    sortdb: &SortitionDB,
+    mainnet: bool,
+    chain_id: u32,
+    overwrite: bool,
+) -> Result<SnapshotManifest, Error> {
+    let chainstate_root = PathBuf::from(chainstate_path);
+    if !overwrite
+        && snapshot_db_paths()
+            .iter()
+            .any(|db_path| chainstate_root.join(db_path).exists())
+    {
+        return Err(Error::InvalidSnapshot(format!(
+            "A chainstate already exists at {}",
+            chainstate_path
+        )));
+    }
+
+    let staging_root = PathBuf::from(format!("{}.snapshot-import", chainstate_path));
+    make_staging_dir(&staging_root)?;
+
+    let result = read_archive(archive_path, &staging_root).and_then(|manifest| {
+        if manifest.mainnet != mainnet || manifest.chain_id != chain_id {
+            return Err(Error::InvalidSnapshot(format!(
+                "Snapshot is for mainnet = {}, chain ID {}, but this node has mainnet = {}, chain ID {}",
+                manifest.mainnet, manifest.chain_id, mainnet, chain_id
+            )));
+        }
+        verify_staged_snapshot(&staging_root, &manifest, sortdb)?;
+        install_staged_files(&staging_root, &manifest, chainstate_path)?;
+        Ok(manifest)
+    });
+
+    let _ = fs::remove_dir_all(&staging_root);
+    result
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::chainstate::stacks::db::test::instantiate_chainstate;
+
+    #[test]
+    fn test_snapshot_archive_roundtrip() {
+        let chainstate = instantiate_chainstate(false, 0x80000000, "snapshot-archive-roundtrip");
+        let chainstate_root = PathBuf::from(&chainstate.root_path);
+        let staging_root = PathBuf::from(format!("{}.staging", &chainstate.root_path));
+        let unpack_root = PathBuf::from(format!("{}.unpack", &chainstate.root_path));
+        let archive_path = format!("{}.snapshot", &chainstate.root_path);
+        make_staging_dir(&staging_root).unwrap();
+        make_staging_dir(&unpack_root).unwrap();
+
+        // nothing has been processed past the boot block
+        match export_snapshot(&chainstate.root_path, None, &archive_path) {
+            Err(Error::InvalidSnapshot(_)) => {}
+            x => panic!("Expected InvalidSnapshot, got {:?}", x),
+        }
+
+        let paths = stage_files(&chainstate_root, &staging_root).unwrap();
+        assert_eq!(paths, snapshot_file_paths());
+
+        let boot_header = StacksChainState::get_genesis_header_info(chainstate.db()).unwrap();
+        let manifest = SnapshotManifest {
+            version: SNAPSHOT_VERSION,
+            mainnet: false,
+            chain_id: 0x80000000,
+            index_block_hash: boot_header.index_block_hash(),
+            consensus_hash: boot_header.consensus_hash.clone(),
+            block_hash: boot_header.anchored_header.block_hash(),
+            block_height: 0,
+            state_index_root: boot_header.anchored_header.state_index_root.clone(),
+            files: describe_files(&staging_root, &paths).unwrap(),
+        };
+        write_archive(&staging_root, &manifest, &archive_path).unwrap();
+
+        assert_eq!(read_archive(&archive_path, &unpack_root).unwrap(), manifest);
+        for path in paths.iter() {
+            assert_eq!(
+                fs::read(staging_root.join(path)).unwrap(),
+                fs::read(unpack_root.join(path)).unwrap()
+            );
+        }
+
+        // the boot block's index root is the root of its trie in the headers MARF
+        let mut headers_marf = open_marf_readonly(&StacksChainState::header_index_root_path(
+            unpack_root.clone(),
+        ))
+        .unwrap();
+        assert_eq!(
+            headers_marf
+                .get_root_hash_at(&boot_header.index_block_hash())
+                .unwrap(),
+            boot_header.index_root
+        );
+
+        // files outside of the chainstate are never written
+        let archive = fs::read(&archive_path).unwrap();
+        let manifest_len = serde_json::to_vec(&manifest).unwrap().len();
+        let mut escaping = manifest.clone();
+        escaping.files[1].path = "../escaped.sqlite.blobs".to_string();
+        let escaping_bytes = serde_json::to_vec(&escaping).unwrap();
+        let mut bytes = SNAPSHOT_MAGIC.to_vec();
+        bytes.extend_from_slice(&(escaping_bytes.len() as u32).to_be_bytes());
+        bytes.extend_from_slice(&escaping_bytes);
+        bytes.extend_from_slice(&archive[12 + manifest_len..]);
+        fs::write(&archive_path, &bytes).unwrap();
+        match read_archive(&archive_path, &unpack_root) {
+            Err(Error::InvalidSnapshot(msg)) => assert!(msg.contains("Unexpected file")),
+            x => panic!("Expected InvalidSnapshot, got {:?}", x),
+        }
+        assert!(!unpack_root.join("../escaped.sqlite.blobs").exists());
+
+        // corrupt the last byte of the last file
+        let mut bytes = archive.clone();
+        let last = bytes.len() - 1;
+        bytes[last] ^= 0xff;
+        fs::write(&archive_path, &bytes).unwrap();
+        match read_archive(&archive_path, &unpack_root) {
+            Err(Error::InvalidSnapshot(msg)) => assert!(msg.contains("has hash")),
+            x => panic!("Expected InvalidSnapshot, got {:?}", x),
+        }
+
+        fs::remove_dir_all(&staging_root).unwrap();
+        fs::remove_dir_all(&unpack_root).unwrap();
+        fs::remove_file(&archive_path).unwrap();
+    }
+}
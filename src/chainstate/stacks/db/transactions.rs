@@ -370,6 +370,7 @@ impl StacksChainState {
     }
 
     /// Pay the transaction fee (but don't credit it to the miner yet).
+    /// The fee is paid in the subnet's fee asset if it has one, and in STX otherwise.
     /// Does not touch the account nonce.
     /// Consumes the account object, since it invalidates it.
     fn pay_transaction_fee(
@@ -377,6 +378,26 @@ impl StacksChainState {
         fee: u64,
         payer_account: StacksAccount,
     ) -> Result<u64, Error> {
+        let mainnet = clarity_tx.is_mainnet();
+        if let Some(fee_asset) = StacksChainState::get_fee_asset(clarity_tx, mainnet)? {
+            let balance = StacksChainState::get_fee_asset_balance(
+                clarity_tx,
+                &fee_asset,
+                &payer_account.principal,
+            )?;
+            if balance < fee as u128 {
+                return Err(Error::InvalidFee);
+            }
+
+            StacksChainState::account_debit_fee_asset(
+                clarity_tx,
+                &fee_asset,
+                &payer_account.principal,
+                fee as u128,
+            );
+            return Ok(fee);
+        }
+
         let (cur_burn_block_height, v1_unlock_ht, v2_unlock_ht) = clarity_tx
             .with_clarity_db_readonly(|ref mut db| {
                 (
@@ -995,6 +1016,9 @@ impl StacksChainState {
 
         StacksChainState::process_transaction_precheck(&clarity_block.config, tx)?;
 
+        // if the block logs its writes, the writes so far were not made by this transaction
+        clarity_block.close_write_log_entry(None);
+
         let mut transaction = clarity_block.connection().start_transaction_processing();
         let (origin_account, payer_account) =
             StacksChainState::check_transaction_nonces(&mut transaction, tx, quiet)?;
@@ -1021,6 +1045,7 @@ impl StacksChainState {
         }
 
         transaction.commit();
+        clarity_block.close_write_log_entry(Some(tx.txid()));
 
         Ok((fee, tx_receipt))
     }
@@ -1160,6 +1185,97 @@ pub mod test {
         conn.commit_block();
     }
 
+    #[test]
+    fn process_transaction_fee_in_fee_asset() {
+        let privk = StacksPrivateKey::from_hex(
+            "6d430bb91222408e7706c9001cfaeb91b08c2be6d5ac95779ab52c6b431950e001",
+        )
+        .unwrap();
+        let privk_broke = StacksPrivateKey::from_hex(
+            "7e3af4db6af6b3c67e2c6c6d7d5983b519f4d9b3a6e00580ae96dcace3bde8bc01",
+        )
+        .unwrap();
+        let addr = TransactionAuth::from_p2pkh(&privk)
+            .unwrap()
+            .origin()
+            .address_testnet();
+        let addr_broke = TransactionAuth::from_p2pkh(&privk_broke)
+            .unwrap()
+            .origin()
+            .address_testnet();
+        let recv_addr = StacksAddress {
+            version: 1,
+            bytes: Hash160([0xff; 20]),
+        };
+
+        // both accounts have STX, but only one has any of the fee asset
+        let (mut chainstate, fee_asset) = instantiate_chainstate_with_fee_asset(
+            "process-transaction-fee-in-fee-asset",
+            vec![(addr.clone(), 1000), (addr_broke.clone(), 1000)],
+            vec![(addr.clone(), 500)],
+        );
+
+        let make_transfer = |privk: &StacksPrivateKey| {
+            let mut tx_stx_transfer = StacksTransaction::new(
+                TransactionVersion::Testnet,
+                TransactionAuth::from_p2pkh(privk).unwrap(),
+                TransactionPayload::TokenTransfer(
+                    recv_addr.clone().into(),
+                    123,
+                    TokenTransferMemo([0u8; 34]),
+                ),
+            );
+            tx_stx_transfer.chain_id = 0x80000000;
+            tx_stx_transfer.post_condition_mode = TransactionPostConditionMode::Allow;
+            tx_stx_transfer.set_tx_fee(10);
+
+            let mut signer = StacksTransactionSigner::new(&tx_stx_transfer);
+            signer.sign_origin(privk).unwrap();
+            signer.get_tx().unwrap()
+        };
+
+        let mut conn = chainstate.block_begin(
+            &TEST_BURN_STATE_DB,
+            &FIRST_BURNCHAIN_CONSENSUS_HASH,
+            &FIRST_STACKS_BLOCK_HASH,
+            &ConsensusHash([1u8; 20]),
+            &BlockHeaderHash([1u8; 32]),
+        );
+
+        // the fee is debited from the fee asset, and the transfer from STX
+        let (fee, _) =
+            StacksChainState::process_transaction(&mut conn, &make_transfer(&privk), false)
+                .unwrap();
+        assert_eq!(fee, 10);
+
+        let account_after = StacksChainState::get_account(&mut conn, &addr.to_account_principal());
+        assert_eq!(account_after.nonce, 1);
+        assert_eq!(account_after.stx_balance.amount_unlocked(), 1000 - 123);
+        assert_eq!(
+            StacksChainState::get_fee_asset_balance(
+                &mut conn,
+                &fee_asset,
+                &addr.to_account_principal()
+            )
+            .unwrap(),
+            500 - 10
+        );
+
+        // STX do not pay for the fee
+        match StacksChainState::process_transaction(&mut conn, &make_transfer(&privk_broke), false)
+        {
+            Err(Error::InvalidFee) => {}
+            x => panic!("Expected InvalidFee, got {:?}", x),
+        }
+
+        let account_after =
+            StacksChainState::get_account(&mut conn, &addr_broke.to_account_principal());
+        assert_eq!(account_after.nonce, 0);
+        assert_eq!(account_after.stx_balance.amount_unlocked(), 1000);
+
+        conn.commit_block();
+    }
+
     #[test]
     fn process_token_transfer_stx_transaction_invalid() {
         let mut chainstate = instantiate_chainstate(
@@ -43,6 +43,7 @@ use stacks_common::types::chainstate::BurnchainHeaderHash;
 
 pub type UnconfirmedTxMap = HashMap<Txid, (StacksTransaction, BlockHeaderHash, u16)>;
 
+#[derive(Clone)]
 pub struct ProcessedUnconfirmedState {
     pub total_burns: u128,
     pub total_fees: u128,
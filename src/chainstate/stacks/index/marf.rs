@@ -163,6 +163,20 @@ pub trait MarfConnection<T: MarfTrieId> {
         self.with_conn(|c| c.get_root_hash_at(block_hash))
     }
 
+    /// Rehash the trie of a particular block, checking each of its nodes against its stored
+    /// hash, and get the leaves written in that trie
+    fn verify_trie_at(&mut self, block_hash: &T) -> Result<Vec<(TriePath, MARFValue)>, Error> {
+        self.with_conn(|c| {
+            let cur_block_hash = c.get_cur_block();
+            c.open_block(block_hash)?;
+            let result = Trie::verify_trie(c);
+
+            // restore
+            c.open_block(&cur_block_hash)?;
+            result
+        })
+    }
+
     /// Check if a block can open successfully, i.e.,
     ///   it's a known block, the storage system isn't issueing IOErrors, _and_ it's in the same fork
     ///   as the current block
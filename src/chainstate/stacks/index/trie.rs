@@ -29,7 +29,7 @@ use crate::chainstate::stacks::index::bits::{
 use crate::chainstate::stacks::index::marf::MARF;
 use crate::chainstate::stacks::index::node::{
     clear_backptr, is_backptr, set_backptr, CursorError, TrieCursor, TrieNode, TrieNode16,
-    TrieNode256, TrieNode4, TrieNode48, TrieNodeID, TrieNodeType, TriePtr,
+    TrieNode256, TrieNode4, TrieNode48, TrieNodeID, TrieNodeType, TriePath, TriePtr,
 };
 use crate::chainstate::stacks::index::storage::{
     TrieFileStorage, TrieHashCalculationMode, TrieStorageConnection,
@@ -42,6 +42,7 @@ use stacks_common::util::macros::is_trace;
 
 use crate::chainstate::stacks::index::TrieHashExtension;
 use crate::chainstate::stacks::index::TrieLeaf;
+use crate::chainstate::stacks::index::{ClarityMarfTrieId, MARFValue};
 use stacks_common::types::chainstate::BlockHeaderHash;
 use stacks_common::types::chainstate::TrieHash;
 use stacks_common::types::chainstate::BLOCK_HEADER_HASH_ENCODED_SIZE;
@@ -1016,6 +1017,88 @@ impl Trie {
         Ok(())
     }
 
+    /// Recompute the hash of `node` from its children, checking each child in the same trie
+    /// against the hash stored with it along the way. Children in ancestor tries are not
+    /// visited, since a node commits to those by their block hash. `path` is the path walked to
+    /// reach `node`; each leaf found is added to `leaves` with its full path.
+    fn verify_node_hashes<T: MarfTrieId>(
+        storage: &mut TrieStorageConnection<T>,
+        node: &TrieNodeType,
+        path: &mut Vec<u8>,
+        leaves: &mut Vec<(TriePath, MARFValue)>,
+    ) -> Result<TrieHash, Error> {
+        let prefix_len = path.len();
+        path.extend_from_slice(node.path_bytes());
+
+        if let TrieNodeType::Leaf(ref leaf) = node {
+            let leaf_path = TriePath::from_bytes(&path[..]).ok_or_else(|| {
+                Error::CorruptionError(format!(
+                    "Leaf in trie {} has a {}-byte path",
+                    &storage.get_cur_block(),
+                    path.len()
+                ))
+            })?;
+            leaves.push((leaf_path, leaf.data.clone()));
+            path.truncate(prefix_len);
+            return Ok(get_leaf_hash(leaf));
+        }
+
+        let mut child_hashes = Vec::with_capacity(node.ptrs().len());
+        for ptr in node.ptrs().iter() {
+            let child_hash = if ptr.id() == TrieNodeID::Empty as u8 {
+                TrieHash::from_data(&[])
+            } else if is_backptr(ptr.id()) {
+                let block_hash = storage.get_block_hash_caching(ptr.back_block())?.clone();
+                TrieHash(block_hash.to_bytes())
+            } else {
+                let (child, stored_hash) = storage.read_nodetype(ptr)?;
+                path.push(ptr.chr());
+                let child_hash = Trie::verify_node_hashes(storage, &child, path, leaves)?;
+                path.pop();
+                if child_hash != stored_hash {
+                    return Err(Error::CorruptionError(format!(
+                        "Node {:?} in trie {} hashes to {}, but has hash {}",
+                        ptr,
+                        &storage.get_cur_block(),
+                        &child_hash,
+                        &stored_hash
+                    )));
+                }
+                child_hash
+            };
+            child_hashes.push(child_hash);
+        }
+
+        path.truncate(prefix_len);
+        Ok(get_nodetype_hash_bytes::<T, _>(
+            node,
+            &child_hashes,
+            storage,
+        ))
+    }
+
+    /// Rehash the trie `storage` points to, from its leaves up to its root (mixing in the
+    /// ancestor tries' root hashes, as `get_trie_root_hash` does), and check every node against
+    /// the hash stored with it. Unlike reading the stored root hash, this catches nodes that
+    /// changed on disk. Returns the leaves in this trie, with their full paths.
+    pub fn verify_trie<T: MarfTrieId>(
+        storage: &mut TrieStorageConnection<T>,
+    ) -> Result<Vec<(TriePath, MARFValue)>, Error> {
+        let (root, stored_hash) = Trie::read_root(storage)?;
+        let mut leaves = vec![];
+        let content_hash = Trie::verify_node_hashes(storage, &root, &mut vec![], &mut leaves)?;
+        let root_hash = Trie::get_trie_root_hash(storage, &content_hash)?;
+        if root_hash != stored_hash {
+            return Err(Error::CorruptionError(format!(
+                "Root of trie {} hashes to {}, but has hash {}",
+                &storage.get_cur_block(),
+                &root_hash,
+                &stored_hash
+            )));
+        }
+        Ok(leaves)
+    }
+
     pub fn update_root_hash<T: MarfTrieId>(
         storage: &mut TrieStorageConnection<T>,
         cursor: &TrieCursor<T>,
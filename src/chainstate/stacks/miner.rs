@@ -2680,9 +2680,14 @@ impl Proposal {
 }
 
 impl SignedProposal {
+    /// The hash of `message`, which is what the proposer signed
+    pub fn message_hash(&self) -> Sha256Sum {
+        Sha256Sum::from_data(self.message.as_bytes())
+    }
+
     /// Perform the secp256k1 signature validation, recovering the public key used to sign
     pub fn recover_signer_pk(&self) -> Result<Secp256k1PublicKey, Error> {
-        let hash = Sha256Sum::from_data(&self.message.as_bytes());
+        let hash = self.message_hash();
         Secp256k1PublicKey::recover_to_pubkey(hash.as_bytes(), &self.signature)
             .map_err(|e| Error::Secp256k1Error(e.to_string()))
     }
@@ -2690,7 +2695,7 @@ impl SignedProposal {
     /// Check that `message` matches `signature`
     pub fn verify(&self) -> Result<bool, Error> {
         // Compute hash of message
-        let hash = Sha256Sum::from_data(self.message.as_bytes());
+        let hash = self.message_hash();
 
         // Recover pubkey using message hash
         let pubkey = self
@@ -2718,6 +2723,45 @@ impl SignedProposal {
 
         Ok(proposal)
     }
+
+    /// Check that this proposal comes from one of `allowed_proposers` (compressed public keys)
+    /// and that its block is valid, and if so, sign it with `validator_key` for
+    /// `signing_contract`.
+    /// Returns the signature, or a description of why the proposal was not signed.
+    pub fn validate_and_sign(
+        &self,
+        chainstate: &StacksChainState,
+        burn_dbconn: &SortitionDBConn,
+        allowed_proposers: &HashSet<Vec<u8>>,
+        validator_key: Option<&Secp256k1PrivateKey>,
+        signing_contract: Option<&QualifiedContractIdentifier>,
+    ) -> Result<[u8; 65], String> {
+        let validator_key = validator_key.ok_or_else(|| {
+            "Cannot validate block proposal: Not configured with validation key".to_string()
+        })?;
+        let signing_contract = signing_contract.ok_or_else(|| {
+            "Cannot validate block proposal: Not configured with a multiparty contract".to_string()
+        })?;
+
+        let pubk_recovered = self
+            .recover_signer_pk()
+            .map_err(|e| format!("Cannot validate block proposal: {e}"))?;
+
+        if !allowed_proposers.contains(&pubk_recovered.to_bytes_compressed()) {
+            return Err(
+                "Cannot validate block proposal: Not signed by approved block proposer".into(),
+            );
+        }
+
+        let proposal = self
+            .decode()
+            .map_err(|e| format!("Cannot validate block proposal: {e}"))?;
+
+        proposal
+            .validate(chainstate, burn_dbconn)
+            .map_err(|e| e.to_string())?;
+        Ok(proposal.sign(validator_key, signing_contract.clone()))
+    }
 }
 
 #[cfg(test)]
@@ -6948,6 +6992,7 @@ pub mod test {
             get_bulk_initial_balances: None,
             get_bulk_initial_names: None,
             get_bulk_initial_namespaces: None,
+            genesis_manifest: None,
         };
 
         StacksChainState::open_and_exec(mainnet, chain_id, &path, Some(&mut boot_data), None)
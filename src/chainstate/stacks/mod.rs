@@ -127,6 +127,7 @@ pub enum Error {
     PoxNoRewardCycle,
     JSONError(serde_json::Error),
     Secp256k1Error(String),
+    InvalidSnapshot(String),
 }
 
 impl From<marf_error> for Error {
@@ -206,6 +207,7 @@ impl fmt::Display for Error {
             }
             Error::JSONError(ref e) => fmt::Display::fmt(e, f),
             Error::Secp256k1Error(ref s) => fmt::Display::fmt(s, f),
+            Error::InvalidSnapshot(ref s) => fmt::Display::fmt(s, f),
         }
     }
 }
@@ -242,6 +244,7 @@ impl error::Error for Error {
             Error::StacksTransactionSkipped(ref _r) => None,
             Error::JSONError(ref e) => Some(e),
             Error::Secp256k1Error(ref _s) => None,
+            Error::InvalidSnapshot(ref _s) => None,
         }
     }
 }
@@ -278,6 +281,7 @@ impl Error {
             Error::StacksTransactionSkipped(ref _r) => "StacksTransactionSkipped",
             Error::JSONError(ref _e) => "JSONError",
             Error::Secp256k1Error(ref _s) => "Secp256k1Error",
+            Error::InvalidSnapshot(ref _s) => "InvalidSnapshot",
         }
     }
 
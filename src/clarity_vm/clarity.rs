@@ -20,6 +20,7 @@ use std::fmt;
 use std::thread;
 
 use crate::burnchains::PoxConstants;
+use crate::burnchains::Txid;
 use crate::chainstate::stacks::boot::BOOT_CODE_COSTS_2_TESTNET;
 use crate::chainstate::stacks::boot::BOOT_CODE_COSTS_3;
 use crate::chainstate::stacks::boot::COSTS_3_NAME;
@@ -54,7 +55,7 @@ use crate::util_lib::boot::{boot_code_acc, boot_code_addr, boot_code_id, boot_co
 use crate::util_lib::strings::StacksString;
 use crate::{
     burnchains::Burnchain,
-    clarity_vm::database::marf::{MarfedKV, WritableMarfStore},
+    clarity_vm::database::marf::{MarfWriteLog, MarfedKV, WritableMarfStore},
 };
 use crate::{clarity_vm::database::marf::ReadOnlyMarfStore, core::StacksEpochId};
 use clarity::vm::analysis;
@@ -108,6 +109,8 @@ pub struct ClarityInstance {
     datastore: MarfedKV,
     mainnet: bool,
     chain_id: u32,
+    /// Whether blocks begun with `begin_block` log their writes
+    log_writes: bool,
 }
 
 ///
@@ -229,9 +232,15 @@ impl ClarityInstance {
             datastore,
             mainnet,
             chain_id,
+            log_writes: false,
         }
     }
 
+    /// Set whether blocks begun from now on log the writes of each of their transactions
+    pub fn set_log_writes(&mut self, log_writes: bool) {
+        self.log_writes = log_writes;
+    }
+
     pub fn with_marf<F, R>(&mut self, f: F) -> R
     where
         F: FnOnce(&mut MARF<StacksBlockId>) -> R,
@@ -277,6 +286,9 @@ impl ClarityInstance {
         burn_state_db: &'b dyn BurnStateDB,
     ) -> ClarityBlockConnection<'a, 'b> {
         let mut datastore = self.datastore.begin(current, next);
+        if self.log_writes {
+            datastore.start_write_log();
+        }
 
         let epoch = Self::get_epoch_of(current, header_db, burn_state_db);
         let cost_track = {
@@ -976,6 +988,18 @@ impl<'a, 'b> ClarityBlockConnection<'a, 'b> {
         self.datastore.seal()
     }
 
+    /// Attribute the writes logged since the last call to the transaction `txid`, or to no
+    /// transaction if `None`. Does nothing unless the block logs its writes.
+    pub fn close_write_log_entry(&mut self, txid: Option<Txid>) {
+        if let Some(write_log) = self.datastore.write_log_mut() {
+            write_log.close_entry(txid);
+        }
+    }
+
+    pub fn take_write_log(&mut self) -> Option<MarfWriteLog> {
+        self.datastore.take_write_log()
+    }
+
     pub fn destruct(self) -> WritableMarfStore<'a> {
         self.datastore
     }
@@ -1110,6 +1134,10 @@ impl<'a, 'b> TransactionConnection for ClarityTransactionConnection<'a, 'b> {
 }
 
 impl<'a, 'b> ClarityTransactionConnection<'a, 'b> {
+    pub fn is_mainnet(&self) -> bool {
+        self.mainnet
+    }
+
     /// Do something to the underlying DB that involves writing.
     pub fn with_clarity_db<F, R>(&mut self, to_do: F) -> Result<R, Error>
     where
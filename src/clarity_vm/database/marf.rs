@@ -2,6 +2,7 @@ use std::path::PathBuf;
 
 use rusqlite::Connection;
 
+use crate::burnchains::Txid;
 use crate::chainstate::stacks::index::marf::{MARFOpenOpts, MarfConnection, MarfTransaction, MARF};
 use crate::chainstate::stacks::index::{Error, MarfTrieId};
 use crate::core::{FIRST_BURNCHAIN_CONSENSUS_HASH, FIRST_STACKS_BLOCK_HASH};
@@ -206,6 +207,7 @@ impl MarfedKV {
         WritableMarfStore {
             chain_tip,
             marf: tx,
+            write_log: None,
         }
     }
 
@@ -227,6 +229,7 @@ impl MarfedKV {
         WritableMarfStore {
             chain_tip,
             marf: tx,
+            write_log: None,
         }
     }
 
@@ -258,6 +261,47 @@ impl MarfedKV {
 pub struct WritableMarfStore<'a> {
     chain_tip: StacksBlockId,
     marf: MarfTransaction<'a, StacksBlockId>,
+    write_log: Option<MarfWriteLog>,
+}
+
+/// The key-value writes made to a `WritableMarfStore`, grouped by the transaction that made
+/// them. Only kept when tracing a block replay.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct MarfWriteLog {
+    /// Writes not yet attributed to a transaction
+    pending: Vec<(String, String)>,
+    entries: Vec<MarfWriteLogEntry>,
+}
+
+/// The writes of one transaction, or of block processing outside of any transaction (e.g. an
+/// epoch transition, or the miner payouts), in the order they were made
+#[derive(Debug, Clone, PartialEq)]
+pub struct MarfWriteLogEntry {
+    pub txid: Option<Txid>,
+    pub writes: Vec<(String, String)>,
+}
+
+impl MarfWriteLog {
+    /// Log writes made since the last entry was closed
+    pub fn add_writes(&mut self, items: &[(String, String)]) {
+        self.pending.extend(items.iter().cloned());
+    }
+
+    /// Attribute the pending writes to the transaction `txid`, or to no transaction if `None`
+    pub fn close_entry(&mut self, txid: Option<Txid>) {
+        if txid.is_none() && self.pending.is_empty() {
+            return;
+        }
+        self.entries.push(MarfWriteLogEntry {
+            txid,
+            writes: std::mem::replace(&mut self.pending, vec![]),
+        });
+    }
+
+    pub fn into_entries(mut self) -> Vec<MarfWriteLogEntry> {
+        self.close_entry(None);
+        self.entries
+    }
 }
 
 pub struct ReadOnlyMarfStore<'a> {
@@ -450,6 +494,19 @@ impl<'a> WritableMarfStore<'a> {
         self.marf.drop_current();
     }
 
+    /// Start logging the writes made to this store
+    pub fn start_write_log(&mut self) {
+        self.write_log = Some(MarfWriteLog::default());
+    }
+
+    pub fn write_log_mut(&mut self) -> Option<&mut MarfWriteLog> {
+        self.write_log.as_mut()
+    }
+
+    pub fn take_write_log(&mut self) -> Option<MarfWriteLog> {
+        self.write_log.take()
+    }
+
     pub fn rollback_unconfirmed(self) {
         debug!("Drop unconfirmed MARF trie {}", &self.chain_tip);
         SqliteConnection::drop_metadata(self.marf.sqlite_tx(), &self.chain_tip);
@@ -647,6 +704,9 @@ impl<'a> ClarityBackingStore for WritableMarfStore<'a> {
     }
 
     fn put_all(&mut self, items: Vec<(String, String)>) {
+        if let Some(write_log) = self.write_log.as_mut() {
+            write_log.add_writes(&items);
+        }
         let mut keys = Vec::new();
         let mut values = Vec::new();
         for (key, value) in items.into_iter() {
@@ -1,5 +1,6 @@
 /// High level interfaces for interacting with the Clarity vm
 pub mod clarity;
+pub mod verify;
 pub mod withdrawal;
 
 pub mod special;
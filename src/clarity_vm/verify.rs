@@ -0,0 +1,309 @@
+// Copyright (C) 2013-2020 Blockstack PBC, a public benefit corporation
+// Copyright (C) 2020 Stacks Open Internet Foundation
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Stand-alone verification of the proofs that a node serves over RPC: MARF proofs of Clarity
+//! state (returned with `?proof=1`), and withdrawal Merkle paths (returned by
+//! `/v2/withdrawal/...`). Nothing here needs a chainstate; the verifier supplies the roots it
+//! trusts, e.g. from block headers it has checked against the L1.
+
+use std::collections::HashMap;
+
+use clarity::types::chainstate::{StacksBlockId, TrieHash};
+use clarity::util::hash::{
+    MerklePath, MerklePathOrder, MerklePathPoint, MerkleTree, Sha512Trunc256Sum,
+};
+use clarity::vm::database::{ClarityDatabase, StoreType};
+use clarity::vm::types::{QualifiedContractIdentifier, SequenceData, Value};
+
+use crate::chainstate::stacks::index::node::TriePath;
+use crate::chainstate::stacks::index::{MARFValue, TrieMerkleProof};
+use crate::clarity_vm::withdrawal::convert_withdrawal_key_to_bytes;
+use crate::codec::{Error as codec_error, StacksMessageCodec};
+
+/// Check a serialized MARF proof that `key` maps to `value` in the state trie with root
+/// `state_root` (a block header's `state_index_root`).  `key` and `value` are the strings the
+/// Clarity database stores: e.g., for a data var, `value` is the hex serialization of the
+/// Clarity value, without a `0x` prefix.
+///
+/// Most proofs pass through the tries of ancestor blocks, so the verifier must also supply
+/// `root_to_block`, which maps the `state_index_root` of each such ancestor to its index block
+/// hash.
+pub fn verify_state_proof(
+    proof_bytes: &[u8],
+    key: &str,
+    value: &str,
+    state_root: &TrieHash,
+    root_to_block: &HashMap<TrieHash, StacksBlockId>,
+) -> Result<bool, codec_error> {
+    let proof = TrieMerkleProof::<StacksBlockId>::consensus_deserialize(&mut &proof_bytes[..])?;
+    let path = TriePath::from_key(key);
+    let marf_value = MARFValue::from_value(value);
+    Ok(proof.verify(&path, &marf_value, state_root, root_to_block))
+}
+
+/// The Clarity database key of a contract's data var, i.e. `vm::<contract-id>::1::<var-name>`
+pub fn data_var_key(contract: &QualifiedContractIdentifier, var_name: &str) -> String {
+    ClarityDatabase::make_key_for_trip(contract, StoreType::Variable, var_name)
+}
+
+/// The Clarity database key of a data map entry, i.e. `vm::<contract-id>::0::<map-name>::<key>`,
+/// where `<key>` is the hex serialization of the map key, without a `0x` prefix
+pub fn map_entry_key(
+    contract: &QualifiedContractIdentifier,
+    map_name: &str,
+    key: &Value,
+) -> String {
+    ClarityDatabase::make_key_for_data_map_entry(contract, map_name, key)
+}
+
+/// Parse a JSON object that maps the hex state root of each ancestor block to its hex index
+/// block hash, as `verify_state_proof` takes `root_to_block`
+pub fn parse_root_to_block(json: &str) -> Result<HashMap<TrieHash, StacksBlockId>, String> {
+    let entries: HashMap<String, String> = serde_json::from_str(json)
+        .map_err(|e| format!("Failed to parse root-to-block map: {}", e))?;
+    let mut root_to_block = HashMap::new();
+    for (root, block) in entries.iter() {
+        let root = TrieHash::from_hex(root.trim_start_matches("0x"))
+            .map_err(|e| format!("Invalid state root {}: {:?}", root, e))?;
+        let block = StacksBlockId::from_hex(block.trim_start_matches("0x"))
+            .map_err(|e| format!("Invalid index block hash {}: {:?}", block, e))?;
+        root_to_block.insert(root, block);
+    }
+    Ok(root_to_block)
+}
+
+/// Get the 32-byte hash held by a Clarity `(buff 32)`, like those a node returns for
+/// withdrawal roots and leaf hashes.
+pub fn hash_from_clarity_buffer(value: &Value) -> Option<Sha512Trunc256Sum> {
+    match value {
+        Value::Sequence(SequenceData::Buffer(buff)) => Sha512Trunc256Sum::from_bytes(&buff.data),
+        _ => None,
+    }
+}
+
+/// The inverse of `merkle_path_to_clarity_list`: convert a list of
+/// `{ hash: (buff 32), is-left-side: bool }` tuples back into a Merkle path.
+pub fn clarity_list_to_merkle_path(value: &Value) -> Option<MerklePath<Sha512Trunc256Sum>> {
+    let items = match value {
+        Value::Sequence(SequenceData::List(list)) => &list.data,
+        _ => return None,
+    };
+
+    let mut path = vec![];
+    for item in items.iter() {
+        let tuple = match item {
+            Value::Tuple(tuple) => tuple,
+            _ => return None,
+        };
+        let hash = hash_from_clarity_buffer(tuple.get("hash").ok()?)?;
+        let is_sibling_left_side = match tuple.get("is-left-side").ok()? {
+            Value::Bool(b) => *b,
+            _ => return None,
+        };
+        // the order is in reference to the leaf, so a left sibling means the leaf is on the right
+        let order = if is_sibling_left_side {
+            MerklePathOrder::Right
+        } else {
+            MerklePathOrder::Left
+        };
+        path.push(MerklePathPoint { order, hash });
+    }
+    Some(path)
+}
+
+/// The leaf hash of a withdrawal key tuple in the withdrawal Merkle tree
+pub fn withdrawal_leaf_hash(withdrawal_key: &Value) -> Sha512Trunc256Sum {
+    MerkleTree::<Sha512Trunc256Sum>::get_leaf_hash(&convert_withdrawal_key_to_bytes(withdrawal_key))
+}
+
+/// Compute the withdrawal root implied by a leaf hash and its Merkle path, the same way
+/// `check-withdrawal-hashes` does on the L1.
+pub fn compute_withdrawal_root(
+    leaf_hash: &Sha512Trunc256Sum,
+    path: &MerklePath<Sha512Trunc256Sum>,
+) -> Sha512Trunc256Sum {
+    let mut hash = leaf_hash.clone();
+    for point in path.iter() {
+        hash = match point.order {
+            MerklePathOrder::Right => {
+                MerkleTree::<Sha512Trunc256Sum>::get_node_hash(&point.hash, &hash)
+            }
+            MerklePathOrder::Left => {
+                MerkleTree::<Sha512Trunc256Sum>::get_node_hash(&hash, &point.hash)
+            }
+        };
+    }
+    hash
+}
+
+/// Check that a leaf hash and its Merkle path lead to the given withdrawal root (a block
+/// header's `withdrawal_merkle_root`)
+pub fn verify_withdrawal_path(
+    leaf_hash: &Sha512Trunc256Sum,
+    path: &MerklePath<Sha512Trunc256Sum>,
+    withdrawal_root: &Sha512Trunc256Sum,
+) -> bool {
+    compute_withdrawal_root(leaf_hash, path) == *withdrawal_root
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::chainstate::stacks::index::marf::{MARFOpenOpts, MARF};
+    use crate::chainstate::stacks::index::MarfTrieId;
+    use crate::clarity_vm::withdrawal::{buffer_from_hash, merkle_path_to_clarity_list};
+
+    #[test]
+    fn test_verify_state_proof() {
+        let mut m = MARF::from_path(":memory:", MARFOpenOpts::default()).unwrap();
+        let block_0 = StacksBlockId([0u8; 32]);
+        let block_1 = StacksBlockId([1u8; 32]);
+        let block_2 = StacksBlockId([2u8; 32]);
+
+        m.begin(&StacksBlockId::sentinel(), &block_0).unwrap();
+        m.commit().unwrap();
+
+        m.begin(&block_0, &block_1).unwrap();
+        m.insert("key-1", MARFValue::from_value("value-1")).unwrap();
+        m.commit().unwrap();
+
+        // key-1 is only reachable from block 2 through block 1's trie
+        m.begin(&block_1, &block_2).unwrap();
+        m.insert("key-2", MARFValue::from_value("value-2")).unwrap();
+        m.commit().unwrap();
+
+        let root_2 = m.get_root_hash_at(&block_2).unwrap();
+        let root_to_block = m
+            .borrow_storage_backend()
+            .read_root_to_block_table()
+            .unwrap();
+
+        for (key, value) in [("key-1", "value-1"), ("key-2", "value-2")] {
+            let (marf_value, proof) = m.get_with_proof(&block_2, key).unwrap().unwrap();
+            assert_eq!(marf_value, MARFValue::from_value(value));
+            let proof_bytes = proof.serialize_to_vec();
+
+            assert!(verify_state_proof(&proof_bytes, key, value, &root_2, &root_to_block).unwrap());
+
+            // wrong value, wrong key, wrong root
+            assert!(
+                !verify_state_proof(&proof_bytes, key, "other", &root_2, &root_to_block).unwrap()
+            );
+            assert!(
+                !verify_state_proof(&proof_bytes, "other", value, &root_2, &root_to_block).unwrap()
+            );
+            assert!(!verify_state_proof(
+                &proof_bytes,
+                key,
+                value,
+                &TrieHash([0x33; 32]),
+                &root_to_block
+            )
+            .unwrap());
+        }
+
+        // without the ancestor roots, the proof through block 1 can't be checked
+        let (_, proof) = m.get_with_proof(&block_2, "key-1").unwrap().unwrap();
+        assert!(!verify_state_proof(
+            &proof.serialize_to_vec(),
+            "key-1",
+            "value-1",
+            &root_2,
+            &HashMap::new()
+        )
+        .unwrap());
+
+        // garbage doesn't decode
+        assert!(
+            verify_state_proof(&[0xff; 8], "key-1", "value-1", &root_2, &root_to_block).is_err()
+        );
+    }
+
+    #[test]
+    fn test_clarity_keys() {
+        let contract = QualifiedContractIdentifier::parse(
+            "ST2DS4MSWSGJ3W9FBC6BVT0Y92S345HY8N3T6AV7R.hello-world",
+        )
+        .unwrap();
+        assert_eq!(
+            data_var_key(&contract, "bar"),
+            format!("vm::{}::1::bar", &contract)
+        );
+        let map_key = Value::UInt(1);
+        assert_eq!(
+            map_entry_key(&contract, "unit-map", &map_key),
+            format!(
+                "vm::{}::0::unit-map::{}",
+                &contract,
+                map_key.serialize_to_hex()
+            )
+        );
+    }
+
+    #[test]
+    fn test_parse_root_to_block() {
+        let root_to_block = parse_root_to_block(&format!(
+            "{{\"0x{}\": \"{}\"}}",
+            TrieHash([0x01; 32]).to_hex(),
+            StacksBlockId([0x02; 32]).to_hex()
+        ))
+        .unwrap();
+        assert_eq!(
+            root_to_block.get(&TrieHash([0x01; 32])),
+            Some(&StacksBlockId([0x02; 32]))
+        );
+
+        assert!(parse_root_to_block("[]").is_err());
+        assert!(parse_root_to_block("{\"00\": \"00\"}").is_err());
+    }
+
+    #[test]
+    fn test_verify_withdrawal_path() {
+        let keys: Vec<Value> = (0..5).map(|i| Value::UInt(i)).collect();
+        let leaves: Vec<Vec<u8>> = keys.iter().map(convert_withdrawal_key_to_bytes).collect();
+        let tree = MerkleTree::<Sha512Trunc256Sum>::new(&leaves);
+        let root = tree.root();
+
+        for (key, leaf) in keys.iter().zip(leaves.iter()) {
+            let path = tree.path(leaf).unwrap();
+
+            // round-trip through the representation the node serves
+            let sibling_hashes = merkle_path_to_clarity_list(path.clone()).unwrap();
+            let parsed_path = clarity_list_to_merkle_path(&sibling_hashes).unwrap();
+            let leaf_hash =
+                hash_from_clarity_buffer(&buffer_from_hash(withdrawal_leaf_hash(key))).unwrap();
+
+            assert!(verify_withdrawal_path(&leaf_hash, &parsed_path, &root));
+            assert_eq!(compute_withdrawal_root(&leaf_hash, &path), root);
+
+            // wrong leaf, wrong root
+            let other_leaf_hash = withdrawal_leaf_hash(&Value::UInt(100));
+            assert!(!verify_withdrawal_path(
+                &other_leaf_hash,
+                &parsed_path,
+                &root
+            ));
+            assert!(!verify_withdrawal_path(
+                &leaf_hash,
+                &parsed_path,
+                &Sha512Trunc256Sum([0u8; 32])
+            ));
+        }
+
+        assert!(clarity_list_to_merkle_path(&Value::UInt(1)).is_none());
+        assert!(hash_from_clarity_buffer(&Value::UInt(1)).is_none());
+    }
+}
@@ -1,15 +1,19 @@
+use crate::burnchains::Txid;
 use crate::chainstate::stacks::events::StacksTransactionReceipt;
 use clarity::boot_util::boot_code_id;
 use clarity::codec::StacksMessageCodec;
 use clarity::types::chainstate::{BlockHeaderHash, ConsensusHash, StacksBlockId, TrieHash};
-use clarity::util::hash::{MerkleTree, Sha512Trunc256Sum};
+use clarity::types::StacksEpochId;
+use clarity::util::hash::{
+    MerklePath, MerklePathOrder, MerklePathPoint, MerkleTree, Sha512Trunc256Sum,
+};
 use clarity::vm::database::ClarityBackingStore;
 use clarity::vm::events::StacksTransactionEvent;
 use clarity::vm::representations::ClarityName;
 use clarity::vm::types::{PrincipalData, QualifiedContractIdentifier, SequenceData, TupleData};
 use clarity::vm::Value;
 use regex::internal::Input;
-use std::collections::BTreeMap;
+use std::collections::{BTreeMap, VecDeque};
 
 fn is_subnet_contract_event(contract: &QualifiedContractIdentifier, function: &String) -> bool {
     if function != "print" {
@@ -44,6 +48,15 @@ pub fn buffer_from_hash(hash: Sha512Trunc256Sum) -> Value {
 /// ```
 ///
 /// ```javascript
+///   { type: "stx-to-subnet",
+///     height: u128,
+///     withdrawal-id: u128,
+///     recipient: principal,
+///     destination: principal,
+///     amount: u128 }
+/// ```
+///
+/// ```javascript
 ///   { type: "nft",
 ///     asset-contract: principal,
 ///     height: u128,
@@ -85,6 +98,11 @@ pub fn generate_key_from_event(
                     withdrawal_id,
                     block_height,
                 )),
+                "stx-to-subnet" => make_key_for_stx_to_subnet_withdrawal_event(
+                    data_map,
+                    withdrawal_id,
+                    block_height,
+                ),
                 "ft" => Some(make_key_for_ft_withdrawal_event(
                     data_map,
                     withdrawal_id,
@@ -189,6 +207,46 @@ pub fn make_key_for_stx_withdrawal_event(
     make_key_for_stx_withdrawal(&sender, withdrawal_id, amount, block_height)
 }
 
+/// Unlike the other withdrawal events, the destination of an `stx-to-subnet` withdrawal is not
+/// obtained through `contract-of`, so it is checked here too. Returns None (the event is not a
+/// withdrawal) if the destination is not a contract principal.
+pub fn make_key_for_stx_to_subnet_withdrawal_event(
+    data: &mut BTreeMap<ClarityName, Value>,
+    withdrawal_id: u32,
+    block_height: u64,
+) -> Option<Value> {
+    let sender = data.get("sender").unwrap().clone().expect_principal();
+    let recipient = data.get("recipient").unwrap().clone().expect_principal();
+    let amount = data.get("amount").unwrap().clone().expect_u128();
+    let destination = match data.get("destination").unwrap().clone().expect_principal() {
+        PrincipalData::Standard(destination) => {
+            warn!("Ignoring L2 withdrawal event with a non-contract destination";
+                  "type" => "stx-to-subnet",
+                  "block_height" => block_height,
+                  "sender" => %sender.to_string(),
+                  "destination" => %destination.to_string());
+            return None;
+        }
+        PrincipalData::Contract(contract_principal) => contract_principal,
+    };
+
+    info!("Parsed L2 withdrawal event";
+          "type" => "stx-to-subnet",
+          "block_height" => block_height,
+          "sender" => %sender.to_string(),
+          "recipient" => %recipient.to_string(),
+          "withdrawal_id" => withdrawal_id,
+          "amount" => amount,
+          "destination" => %destination.to_string());
+    Some(make_key_for_stx_to_subnet_withdrawal(
+        &recipient,
+        withdrawal_id,
+        &destination,
+        amount,
+        block_height,
+    ))
+}
+
 pub fn make_key_for_stx_withdrawal(
     recipient: &PrincipalData,
     withdrawal_id: u32,
@@ -209,6 +267,31 @@ pub fn make_key_for_stx_withdrawal(
     .into()
 }
 
+/// The key for STX withdrawn directly into another subnet: `destination` is the destination
+/// subnet's L1 contract, and `recipient` is credited on the destination subnet.
+pub fn make_key_for_stx_to_subnet_withdrawal(
+    recipient: &PrincipalData,
+    withdrawal_id: u32,
+    destination: &QualifiedContractIdentifier,
+    amount: u128,
+    block_height: u64,
+) -> Value {
+    let destination = Value::Principal(PrincipalData::from(destination.clone()));
+    TupleData::from_data(vec![
+        ("type".into(), clarity_ascii_str("stx-to-subnet")),
+        ("height".into(), Value::UInt(u128::from(block_height))),
+        (
+            "withdrawal-id".into(),
+            Value::UInt(u128::from(withdrawal_id)),
+        ),
+        ("recipient".into(), Value::Principal(recipient.clone())),
+        ("destination".into(), destination),
+        ("amount".into(), Value::UInt(amount)),
+    ])
+    .expect("Withdrawal key tuple is too large for Clarity")
+    .into()
+}
+
 pub fn make_key_for_nft_withdrawal(
     sender: &PrincipalData,
     withdrawal_id: u32,
@@ -266,13 +349,27 @@ pub fn generate_withdrawal_keys<'a>(
     tx_receipts: impl Iterator<Item = &'a mut StacksTransactionReceipt>,
     block_height: u64,
 ) -> Vec<Vec<u8>> {
+    generate_withdrawal_key_tuples(tx_receipts, block_height)
+        .iter()
+        .map(|(_, key)| convert_withdrawal_key_to_bytes(key))
+        .collect()
+}
+
+/// Like `generate_withdrawal_keys`, but returns the withdrawal key tuples themselves, each
+/// paired with the txid of the transaction that emitted the withdrawal. The keys are returned
+/// in withdrawal ID order.
+pub fn generate_withdrawal_key_tuples<'a>(
+    tx_receipts: impl Iterator<Item = &'a mut StacksTransactionReceipt>,
+    block_height: u64,
+) -> Vec<(Txid, Value)> {
     let mut items = Vec::new();
     let mut withdrawal_id = 0;
     for receipt in tx_receipts {
+        let txid = receipt.transaction.txid();
         for event in receipt.events.iter_mut() {
             if let Some(key) = generate_key_from_event(event, withdrawal_id, block_height) {
                 withdrawal_id += 1;
-                items.push(convert_withdrawal_key_to_bytes(&key));
+                items.push((txid.clone(), key));
             }
         }
     }
@@ -290,10 +387,220 @@ pub fn create_withdrawal_merkle_tree<'a>(
 ) -> MerkleTree<Sha512Trunc256Sum> {
     // The specific keys generated is dependent on the order of the provided transaction receipts
     let items = generate_withdrawal_keys(tx_receipts, block_height);
-
     MerkleTree::<Sha512Trunc256Sum>::new(&items)
 }
 
+/// The number of withdrawals in a withdrawal tree. `MerkleTree` pads an odd number of leaves
+/// with a copy of the last one; since every withdrawal key includes its withdrawal ID, no two
+/// withdrawals have the same leaf, so a repeated last leaf is always that padding.
+pub fn withdrawal_count(tree: &MerkleTree<Sha512Trunc256Sum>) -> usize {
+    let leaves = match tree.nodes.first() {
+        Some(leaves) => leaves,
+        None => return 0,
+    };
+    let len = leaves.len();
+    if len >= 2 && leaves[len - 1] == leaves[len - 2] {
+        len - 1
+    } else {
+        len
+    }
+}
+
+/// The Clarity representation of a withdrawal's Merkle path, as consumed by the withdraw
+/// functions in the L1 subnet contract: a list of `{ hash: (buff 32), is-left-side: bool }`
+/// tuples, ordered from the leaf up.
+pub fn merkle_path_to_clarity_list(path: MerklePath<Sha512Trunc256Sum>) -> Option<Value> {
+    let tuple_vec: Vec<_> = path
+        .into_iter()
+        .map(|merkle_point| {
+            let MerklePathPoint {
+                order,
+                hash: sibling_hash,
+            } = merkle_point;
+            // the sibling hash is the left sibling if the merkle path point order is right
+            //  because the merkle path point order is in reference to the leaf
+            let is_sibling_left_side = order == MerklePathOrder::Right;
+            // make the clarity tuple
+            Value::Tuple(
+                TupleData::from_data(vec![
+                    ("hash".into(), buffer_from_hash(sibling_hash)),
+                    ("is-left-side".into(), Value::Bool(is_sibling_left_side)),
+                ])
+                .expect("Failed to construct Clarity repr of merkle tree entry"),
+            )
+        })
+        .collect();
+
+    Value::cons_list(tuple_vec, &StacksEpochId::latest()).ok()
+}
+
+/// The most withdrawals `withdraw-stx-batch` in the L1 subnet contract settles in one call (the
+/// size of its `withdrawals` list)
+pub const MAX_BATCH_WITHDRAWALS: usize = 100;
+/// The most proof hashes, and the most proof steps, `withdraw-stx-batch` accepts (the sizes of
+/// its `proof-hashes` and `proof-steps` lists)
+pub const MAX_BATCH_PROOF_LEN: usize = 250;
+
+/// One step of a withdrawal multi-proof. Verification keeps a FIFO queue of hashes,
+/// initially holding the leaf hashes in tree order. Each step pops the next hash off the
+/// queue, hashes it together with its sibling, and pushes the parent hash onto the back
+/// of the queue. The sibling is either the next hash in the queue (if `use_proof` is
+/// false) or the next hash in the multi-proof's `proof_hashes`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct WithdrawalMultiProofStep {
+    pub use_proof: bool,
+    /// Only meaningful if `use_proof` is true: whether the proof hash is the left sibling.
+    pub is_sibling_left_side: bool,
+}
+
+/// A Merkle multi-proof that a set of withdrawal leaves is included in a withdrawal tree.
+/// Siblings shared between the paths of several leaves (or computable from other supplied
+/// leaves) are only included once.
+#[derive(Debug, Clone, PartialEq)]
+pub struct WithdrawalMultiProof {
+    /// Leaf hashes, ordered by their position in the withdrawal tree
+    pub leaf_hashes: Vec<Sha512Trunc256Sum>,
+    pub proof_hashes: Vec<Sha512Trunc256Sum>,
+    pub steps: Vec<WithdrawalMultiProofStep>,
+}
+
+impl WithdrawalMultiProof {
+    /// Construct a multi-proof for the leaves at the given positions of the withdrawal tree.
+    /// Because withdrawal IDs are assigned in the same order as the leaves of the tree, the
+    /// leaf positions are the withdrawal IDs.
+    /// Returns None if the tree is empty, no positions are given, or a position is not a
+    /// withdrawal's (including the position of the leaf that pads an odd row of leaves).
+    pub fn new(
+        tree: &MerkleTree<Sha512Trunc256Sum>,
+        leaf_positions: &[usize],
+    ) -> Option<WithdrawalMultiProof> {
+        if tree.nodes.is_empty() || leaf_positions.is_empty() {
+            return None;
+        }
+
+        let mut known: Vec<usize> = leaf_positions.to_vec();
+        known.sort();
+        known.dedup();
+
+        if known.iter().any(|pos| *pos >= withdrawal_count(tree)) {
+            return None;
+        }
+
+        let leaves = &tree.nodes[0];
+
+        let leaf_hashes = known.iter().map(|pos| leaves[*pos].clone()).collect();
+        let mut proof_hashes = vec![];
+        let mut steps = vec![];
+
+        // every row below the root has an even number of nodes, so every node has a sibling
+        for row in tree.nodes[..tree.nodes.len() - 1].iter() {
+            let mut parents = Vec::with_capacity(known.len());
+            let mut i = 0;
+            while i < known.len() {
+                let pos = known[i];
+                if pos % 2 == 0 && known.get(i + 1) == Some(&(pos + 1)) {
+                    // sibling is the next hash in the queue
+                    steps.push(WithdrawalMultiProofStep {
+                        use_proof: false,
+                        is_sibling_left_side: false,
+                    });
+                    i += 2;
+                } else {
+                    let sibling_pos = pos ^ 1;
+                    proof_hashes.push(row[sibling_pos].clone());
+                    steps.push(WithdrawalMultiProofStep {
+                        use_proof: true,
+                        is_sibling_left_side: sibling_pos < pos,
+                    });
+                    i += 1;
+                }
+                parents.push(pos / 2);
+            }
+            known = parents;
+        }
+
+        Some(WithdrawalMultiProof {
+            leaf_hashes,
+            proof_hashes,
+            steps,
+        })
+    }
+
+    /// Compute the withdrawal root implied by this multi-proof.
+    /// Returns None if the proof is malformed: i.e., it runs out of hashes, or it does not
+    /// consume every leaf and proof hash.
+    pub fn compute_root(&self) -> Option<Sha512Trunc256Sum> {
+        if self.steps.is_empty() {
+            return None;
+        }
+
+        let mut queue: VecDeque<_> = self.leaf_hashes.iter().cloned().collect();
+        let mut proof_hashes = self.proof_hashes.iter();
+        for step in self.steps.iter() {
+            let curr_hash = queue.pop_front()?;
+            let parent_hash = if step.use_proof {
+                let sibling_hash = proof_hashes.next()?;
+                if step.is_sibling_left_side {
+                    MerkleTree::<Sha512Trunc256Sum>::get_node_hash(sibling_hash, &curr_hash)
+                } else {
+                    MerkleTree::<Sha512Trunc256Sum>::get_node_hash(&curr_hash, sibling_hash)
+                }
+            } else {
+                let sibling_hash = queue.pop_front()?;
+                MerkleTree::<Sha512Trunc256Sum>::get_node_hash(&curr_hash, &sibling_hash)
+            };
+            queue.push_back(parent_hash);
+        }
+
+        if queue.len() != 1 || proof_hashes.next().is_some() {
+            return None;
+        }
+        queue.pop_front()
+    }
+
+    /// Check that this multi-proof proves inclusion of its leaves in the tree with the given root
+    pub fn verify(&self, withdrawal_root: &Sha512Trunc256Sum) -> bool {
+        self.compute_root().as_ref() == Some(withdrawal_root)
+    }
+
+    /// The Clarity representation of the proof steps, as consumed by `withdraw-stx-batch` in the
+    /// L1 subnet contract: a list of `{ use-proof: bool, is-left-side: bool }` tuples.
+    pub fn steps_to_clarity_list(&self) -> Option<Value> {
+        let steps = self
+            .steps
+            .iter()
+            .map(|step| {
+                Value::Tuple(
+                    TupleData::from_data(vec![
+                        ("use-proof".into(), Value::Bool(step.use_proof)),
+                        (
+                            "is-left-side".into(),
+                            Value::Bool(step.is_sibling_left_side),
+                        ),
+                    ])
+                    .expect("Failed to construct Clarity repr of multi-proof step"),
+                )
+            })
+            .collect();
+        Value::cons_list(steps, &StacksEpochId::latest()).ok()
+    }
+
+    /// The Clarity representation of the shared proof hashes: a list of `(buff 32)`
+    pub fn proof_hashes_to_clarity_list(&self) -> Option<Value> {
+        Self::hashes_to_clarity_list(&self.proof_hashes)
+    }
+
+    /// The Clarity representation of the leaf hashes: a list of `(buff 32)`
+    pub fn leaf_hashes_to_clarity_list(&self) -> Option<Value> {
+        Self::hashes_to_clarity_list(&self.leaf_hashes)
+    }
+
+    fn hashes_to_clarity_list(hashes: &[Sha512Trunc256Sum]) -> Option<Value> {
+        let buffers = hashes.iter().cloned().map(buffer_from_hash).collect();
+        Value::cons_list(buffers, &StacksEpochId::latest()).ok()
+    }
+}
+
 #[cfg(test)]
 mod test {
     use clarity::types::chainstate::StacksAddress;
@@ -309,6 +616,7 @@ mod test {
     };
     use crate::clarity_vm::withdrawal::{
         convert_withdrawal_key_to_bytes, create_withdrawal_merkle_tree, generate_key_from_event,
+        make_key_for_stx_to_subnet_withdrawal, make_key_for_stx_withdrawal, WithdrawalMultiProof,
     };
     use crate::net::test::to_addr;
     use crate::vm::ClarityName;
@@ -323,6 +631,13 @@ mod test {
     use clarity::vm::types::QualifiedContractIdentifier;
     use clarity::vm::Value;
 
+    use crate::chainstate::stacks::index::ClarityMarfTrieId;
+    use crate::clarity_vm::clarity::ClarityInstance;
+    use crate::clarity_vm::database::marf::MarfedKV;
+    use clarity::consts::CHAIN_ID_TESTNET;
+    use clarity::vm::clarity::TransactionConnection;
+    use clarity::vm::test_util::{TEST_BURN_STATE_DB, TEST_HEADER_DB};
+
     #[test]
     fn test_verify_withdrawal_merkle_tree() {
         let pk: StacksPrivateKey = StacksPrivateKey::from_hex(
@@ -491,4 +806,231 @@ mod test {
         );
         assert_eq!(root_hash, calculated_root_hash);
     }
+
+    #[test]
+    fn test_withdrawal_multi_proof() {
+        for num_leaves in 1..12 {
+            let items: Vec<Vec<u8>> = (0..num_leaves).map(|i: u8| vec![i; 4]).collect();
+            let tree = MerkleTree::<Sha512Trunc256Sum>::new(&items);
+            let root = tree.root();
+
+            // the leaf that pads an odd number of withdrawals is not a withdrawal
+            assert_eq!(withdrawal_count(&tree), num_leaves as usize);
+            assert!(WithdrawalMultiProof::new(&tree, &[num_leaves as usize]).is_none());
+            assert!(WithdrawalMultiProof::new(&tree, &[0, num_leaves as usize]).is_none());
+
+            // every non-empty subset of the leaves is provable with a single multi-proof
+            for subset in 1u32..(1 << num_leaves) {
+                let positions: Vec<usize> = (0..num_leaves as usize)
+                    .filter(|pos| subset & (1 << pos) != 0)
+                    .collect();
+                let proof = WithdrawalMultiProof::new(&tree, &positions).unwrap();
+                assert_eq!(proof.leaf_hashes.len(), positions.len());
+                for (leaf_hash, pos) in proof.leaf_hashes.iter().zip(positions.iter()) {
+                    assert_eq!(
+                        leaf_hash,
+                        &MerkleTree::<Sha512Trunc256Sum>::get_leaf_hash(&items[*pos])
+                    );
+                }
+                assert!(proof.verify(&root));
+
+                // shared siblings are not repeated, so the multi-proof is never larger than
+                //  the sum of the individual paths
+                let path_len_total: usize = positions
+                    .iter()
+                    .map(|pos| tree.path(&items[*pos]).unwrap().len())
+                    .sum();
+                assert!(proof.proof_hashes.len() <= path_len_total);
+
+                // tampering with a leaf invalidates the proof
+                let mut bad_proof = proof.clone();
+                bad_proof.leaf_hashes[0] =
+                    MerkleTree::<Sha512Trunc256Sum>::get_leaf_hash(&[0xff; 4]);
+                assert!(!bad_proof.verify(&root));
+
+                // so does dropping a proof step
+                let mut bad_proof = proof.clone();
+                bad_proof.steps.pop();
+                assert!(!bad_proof.verify(&root));
+            }
+        }
+
+        let items = vec![vec![0u8; 4], vec![1u8; 4]];
+        let tree = MerkleTree::<Sha512Trunc256Sum>::new(&items);
+        assert!(WithdrawalMultiProof::new(&tree, &[]).is_none());
+        assert!(WithdrawalMultiProof::new(&tree, &[2]).is_none());
+        assert!(WithdrawalMultiProof::new(&MerkleTree::empty(), &[0]).is_none());
+        assert_eq!(withdrawal_count(&MerkleTree::empty()), 0);
+
+        // duplicate positions are only proven once
+        let proof = WithdrawalMultiProof::new(&tree, &[1, 1, 0]).unwrap();
+        assert_eq!(proof.leaf_hashes.len(), 2);
+        assert!(proof.proof_hashes.is_empty());
+        assert!(proof.verify(&tree.root()));
+    }
+
+    #[test]
+    fn test_stx_to_subnet_withdrawal_key() {
+        let sender = PrincipalData::from(
+            StacksAddress::from_string("ST18F1AHKW194BWQ3CEFDPWVRARA79RBGFEWSDQR8").unwrap(),
+        );
+        let recipient = PrincipalData::from(
+            StacksAddress::from_string("ST2REHHS5J3CERCRBEPMGH7921Q6PYKAADT7JP2VB").unwrap(),
+        );
+        let destination = QualifiedContractIdentifier::new(
+            StacksAddress::from_string("ST1PQHQKV0RJXZFY1DGX8MNSNYVE3VGZJSRTPGZGM")
+                .unwrap()
+                .into(),
+            ContractName::from("subnet-v3-0-1"),
+        );
+
+        let mut withdraw_event =
+            StacksTransactionEvent::SmartContractEvent(SmartContractEventData {
+                key: (boot_code_id("subnet", false), "print".into()),
+                value: Value::Tuple(
+                    TupleData::from_data(vec![
+                        (
+                            "type".into(),
+                            Value::string_ascii_from_bytes(
+                                "stx-to-subnet".to_string().into_bytes(),
+                            )
+                            .unwrap(),
+                        ),
+                        ("sender".into(), Value::Principal(sender.clone())),
+                        ("recipient".into(), Value::Principal(recipient.clone())),
+                        (
+                            "destination".into(),
+                            Value::Principal(PrincipalData::Contract(destination.clone())),
+                        ),
+                        ("amount".into(), Value::UInt(10)),
+                    ])
+                    .expect("failed to create event tuple"),
+                ),
+            });
+
+        let key = generate_key_from_event(&mut withdraw_event, 3, 7).unwrap();
+        // the recipient on the destination subnet is committed to, not the sender
+        assert_eq!(
+            key,
+            make_key_for_stx_to_subnet_withdrawal(&recipient, 3, &destination, 10, 7)
+        );
+        assert_ne!(key, make_key_for_stx_withdrawal(&recipient, 3, 10, 7));
+    }
+
+    #[test]
+    fn test_stx_to_subnet_withdrawal_standard_destination() {
+        let sender = PrincipalData::from(
+            StacksAddress::from_string("ST18F1AHKW194BWQ3CEFDPWVRARA79RBGFEWSDQR8").unwrap(),
+        );
+        let recipient = PrincipalData::from(
+            StacksAddress::from_string("ST2REHHS5J3CERCRBEPMGH7921Q6PYKAADT7JP2VB").unwrap(),
+        );
+        let destination = PrincipalData::from(
+            StacksAddress::from_string("ST1PQHQKV0RJXZFY1DGX8MNSNYVE3VGZJSRTPGZGM").unwrap(),
+        );
+
+        // the subnet contract refuses a standard principal as the destination...
+        let mut clarity_instance =
+            ClarityInstance::new(false, CHAIN_ID_TESTNET, MarfedKV::temporary());
+        clarity_instance
+            .begin_test_genesis_block(
+                &StacksBlockId::sentinel(),
+                &StacksBlockId([0; 32]),
+                &TEST_HEADER_DB,
+                &TEST_BURN_STATE_DB,
+            )
+            .commit_block();
+        let mut conn = clarity_instance.begin_block(
+            &StacksBlockId([0; 32]),
+            &StacksBlockId([1; 32]),
+            &TEST_HEADER_DB,
+            &TEST_BURN_STATE_DB,
+        );
+        let (result, _, events) = conn
+            .as_transaction(|tx| {
+                tx.run_contract_call(
+                    &sender,
+                    None,
+                    &boot_code_id("subnet", false),
+                    "stx-withdraw-to-subnet?",
+                    &[
+                        Value::UInt(10),
+                        Value::Principal(sender.clone()),
+                        Value::Principal(recipient.clone()),
+                        Value::Principal(destination.clone()),
+                    ],
+                    |_, _| false,
+                )
+            })
+            .unwrap();
+        assert_eq!(result, Value::err_uint(8));
+        assert!(events.is_empty());
+        conn.commit_block();
+
+        // ...and if such an event is emitted anyway, it is not treated as a withdrawal
+        let make_event = |event_type: &str, destination: Option<&PrincipalData>| {
+            let mut data = vec![
+                (
+                    "type".into(),
+                    Value::string_ascii_from_bytes(event_type.to_string().into_bytes()).unwrap(),
+                ),
+                ("sender".into(), Value::Principal(sender.clone())),
+                ("recipient".into(), Value::Principal(recipient.clone())),
+                ("amount".into(), Value::UInt(10)),
+            ];
+            if let Some(destination) = destination {
+                data.push(("destination".into(), Value::Principal(destination.clone())));
+            }
+            StacksTransactionEvent::SmartContractEvent(SmartContractEventData {
+                key: (boot_code_id("subnet", false), "print".into()),
+                value: Value::Tuple(TupleData::from_data(data).unwrap()),
+            })
+        };
+        let mut bad_event = make_event("stx-to-subnet", Some(&destination));
+        assert!(generate_key_from_event(&mut bad_event, 0, 7).is_none());
+
+        let mut receipt = StacksTransactionReceipt {
+            transaction: TransactionOrigin::Stacks(StacksTransaction::new(
+                TransactionVersion::Testnet,
+                TransactionAuth::from_p2pkh(&StacksPrivateKey::new()).unwrap(),
+                TransactionPayload::Coinbase(CoinbasePayload([0u8; 32])),
+            )),
+            events: vec![bad_event, make_event("stx", None)],
+            post_condition_aborted: false,
+            result: Value::okay_true(),
+            stx_burned: 0,
+            contract_analysis: None,
+            execution_cost: ExecutionCost::zero(),
+            microblock_header: None,
+            tx_index: 0,
+        };
+        let tree = create_withdrawal_merkle_tree(vec![&mut receipt].into_iter(), 7);
+
+        // only the STX withdrawal is in the tree, and it is withdrawal 0: the ignored event does
+        // not use up a withdrawal ID
+        let stx_key = make_key_for_stx_withdrawal(&sender, 0, 10, 7);
+        assert_eq!(
+            tree.root(),
+            MerkleTree::<Sha512Trunc256Sum>::new(&vec![convert_withdrawal_key_to_bytes(&stx_key)])
+                .root()
+        );
+        let withdrawal_ids: Vec<_> = receipt
+            .events
+            .iter()
+            .map(|event| match event {
+                StacksTransactionEvent::SmartContractEvent(data) => data
+                    .value
+                    .clone()
+                    .expect_tuple()
+                    .get("withdrawal-id")
+                    .ok()
+                    .cloned(),
+                _ => panic!("Expected a contract event"),
+            })
+            .collect();
+        assert_eq!(
+            withdrawal_ids,
+            vec![Some(Value::UInt(0)), Some(Value::UInt(0))]
+        );
+    }
 }
@@ -49,6 +49,7 @@ use crate::core::StacksEpochId;
 use crate::core::FIRST_BURNCHAIN_CONSENSUS_HASH;
 use crate::core::FIRST_STACKS_BLOCK_HASH;
 use crate::monitoring::increment_stx_mempool_gc;
+use crate::util_lib::db::query_count;
 use crate::util_lib::db::query_int;
 use crate::util_lib::db::query_row_columns;
 use crate::util_lib::db::query_rows;
@@ -92,6 +93,12 @@ use crate::util_lib::db::table_exists;
 pub const MEMPOOL_MAX_TRANSACTION_AGE: u64 = 256;
 pub const MAXIMUM_MEMPOOL_TX_CHAINING: u64 = 25;
 
+// how long a cancelled transaction is refused re-entry into the mempool, in seconds
+pub const MEMPOOL_CANCELLED_TX_BLACKLIST_SECS: u64 = 86400;
+
+// the latest mempool schema version
+pub const MEMPOOL_SCHEMA_VERSION: i64 = 8;
+
 // name of table for storing the counting bloom filter
 pub const BLOOM_COUNTER_TABLE: &'static str = "txid_bloom_counter";
 
@@ -146,6 +153,46 @@ impl StacksMessageCodec for TxTag {
 pub struct MemPoolAdmitter {
     cur_block: BlockHeaderHash,
     cur_consensus_hash: ConsensusHash,
+    replace_policy: MemPoolReplacePolicy,
+}
+
+/// Rules for replacing a pending transaction with another one at the same nonce, and for how
+/// many transactions a principal can have pending at once.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MemPoolReplacePolicy {
+    /// A replacement in the same fork must raise the fee by at least this percentage of the
+    /// replaced transaction's fee (and always by at least 1).
+    pub min_fee_bump_pct: u64,
+    /// If true, a sender can cancel a pending transaction by sending a STX transfer to itself at
+    /// the same nonce, with a large enough fee bump.  The self-transfer is never stored or mined
+    /// (it would be invalid), so the fee bump is never paid; the cancelled transaction is dropped
+    /// and refused re-entry for `MEMPOOL_CANCELLED_TX_BLACKLIST_SECS`.
+    /// Cancellation is local to this node's mempool: peers that already have the cancelled
+    /// transaction keep it, and may still mine it.
+    pub allow_cancel_by_self_transfer: bool,
+    /// Minimum number of seconds between two cancellations by the same origin.  Since
+    /// cancelling costs nothing, this bounds how often a principal can cancel and resubmit.
+    pub min_cancel_interval_secs: u64,
+    /// Maximum number of pending transactions a principal can originate or sponsor.  0 means no
+    /// limit.
+    pub max_pending_txs_per_principal: u64,
+}
+
+impl MemPoolReplacePolicy {
+    pub fn default() -> MemPoolReplacePolicy {
+        MemPoolReplacePolicy {
+            min_fee_bump_pct: 0,
+            allow_cancel_by_self_transfer: false,
+            min_cancel_interval_secs: 600,
+            max_pending_txs_per_principal: 0,
+        }
+    }
+
+    /// The smallest fee that can replace a pending transaction paying `prior_fee`
+    pub fn min_replacement_fee(&self, prior_fee: u64) -> u64 {
+        let bump = prior_fee.saturating_mul(self.min_fee_bump_pct) / 100;
+        prior_fee.saturating_add(cmp::max(bump, 1))
+    }
 }
 
 enum MemPoolWalkResult {
@@ -159,9 +206,14 @@ impl MemPoolAdmitter {
         MemPoolAdmitter {
             cur_block,
             cur_consensus_hash,
+            replace_policy: MemPoolReplacePolicy::default(),
         }
     }
 
+    pub fn set_replace_policy(&mut self, replace_policy: MemPoolReplacePolicy) {
+        self.replace_policy = replace_policy;
+    }
+
     pub fn set_block(&mut self, cur_block: &BlockHeaderHash, cur_consensus_hash: ConsensusHash) {
         self.cur_consensus_hash = cur_consensus_hash.clone();
         self.cur_block = cur_block.clone();
@@ -176,9 +228,12 @@ impl MemPoolAdmitter {
     }
 }
 
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub enum MemPoolDropReason {
     REPLACE_ACROSS_FORK,
     REPLACE_BY_FEE,
+    /// Cancelled by its sender with a same-nonce transfer to themselves
+    CANCELLED,
     STALE_COLLECT,
     TOO_EXPENSIVE,
 }
@@ -207,6 +262,7 @@ impl std::fmt::Display for MemPoolDropReason {
             MemPoolDropReason::TOO_EXPENSIVE => write!(f, "TooExpensive"),
             MemPoolDropReason::REPLACE_ACROSS_FORK => write!(f, "ReplaceAcrossFork"),
             MemPoolDropReason::REPLACE_BY_FEE => write!(f, "ReplaceByFee"),
+            MemPoolDropReason::CANCELLED => write!(f, "Cancelled"),
         }
     }
 }
@@ -237,6 +293,17 @@ pub struct MemPoolTxInfo {
     pub metadata: MemPoolTxMetadata,
 }
 
+/// A pending transaction, as reported to clients that ask for an account's pending transactions.
+#[derive(Debug, PartialEq, Clone)]
+pub struct MemPoolPendingTx {
+    pub tx: MemPoolTxInfo,
+    pub fee_rate: Option<f64>,
+    /// Why the miner skipped this transaction the last time it considered it, if it did
+    pub skip_reason: Option<String>,
+    /// When the miner last skipped this transaction
+    pub skip_time: Option<u64>,
+}
+
 /// This class is a minimal version of `MemPoolTxInfo`. It contains
 /// just enough information to 1) filter by nonce readiness, 2) sort by fee rate.
 #[derive(Debug, Clone)]
@@ -376,6 +443,22 @@ impl FromRow<MemPoolTxInfo> for MemPoolTxInfo {
     }
 }
 
+impl FromRow<MemPoolPendingTx> for MemPoolPendingTx {
+    fn from_row<'a>(row: &'a Row) -> Result<MemPoolPendingTx, db_error> {
+        let tx = MemPoolTxInfo::from_row(row)?;
+        let fee_rate: Option<f64> = row.get("fee_rate")?;
+        let skip_reason: Option<String> = row.get("skip_reason")?;
+        let skip_time: Option<u64> = u64::from_column(row, "skip_time")?;
+
+        Ok(MemPoolPendingTx {
+            tx,
+            fee_rate,
+            skip_reason,
+            skip_time,
+        })
+    }
+}
+
 impl FromRow<MemPoolTxInfoPartial> for MemPoolTxInfoPartial {
     fn from_row<'a>(row: &'a Row) -> Result<MemPoolTxInfoPartial, db_error> {
         let txid = Txid::from_column(row, "txid")?;
@@ -543,6 +626,35 @@ const MEMPOOL_SCHEMA_6_NONCES: &'static [&'static str] = &[
     "#,
 ];
 
+const MEMPOOL_SCHEMA_7_SKIP_REASONS: &'static [&'static str] = &[
+    r#"
+    -- Why the miner most recently skipped a transaction, so clients can tell why it is still pending.
+    -- Rows go away with the transaction.
+    CREATE TABLE tx_skip_reasons(
+        txid TEXT PRIMARY KEY NOT NULL,
+        reason TEXT NOT NULL,
+        skip_time INTEGER NOT NULL,
+        FOREIGN KEY(txid) REFERENCES mempool(txid) ON DELETE CASCADE
+    );
+    "#,
+    r#"
+    INSERT INTO schema_version (version) VALUES (7)
+    "#,
+];
+
+const MEMPOOL_SCHEMA_8_CANCELLATIONS: &'static [&'static str] = &[
+    r#"
+    -- When each origin address last cancelled a pending transaction, to rate-limit cancellations.
+    CREATE TABLE tx_cancellations(
+        address TEXT PRIMARY KEY NOT NULL,
+        cancel_time INTEGER NOT NULL
+    );
+    "#,
+    r#"
+    INSERT INTO schema_version (version) VALUES (8)
+    "#,
+];
+
 const MEMPOOL_INDEXES: &'static [&'static str] = &[
     "CREATE INDEX IF NOT EXISTS by_txid ON mempool(txid);",
     "CREATE INDEX IF NOT EXISTS by_height ON mempool(height);",
@@ -616,6 +728,47 @@ impl<'a> MemPoolTx<'a> {
         self.tx.commit().map_err(db_error::SqliteError)
     }
 
+    /// Is this transaction refused re-entry into the mempool?
+    fn is_tx_blacklisted(tx: &MemPoolTx<'a>, txid: &Txid) -> Result<bool, db_error> {
+        let sql = "SELECT 1 FROM tx_blacklist WHERE txid = ?1 AND arrival_time >= ?2";
+        let cutoff = get_epoch_time_secs().saturating_sub(MEMPOOL_CANCELLED_TX_BLACKLIST_SECS);
+        let args: &[&dyn ToSql] = &[txid, &u64_to_sql(cutoff)?];
+        let row: Option<i64> = query_row(tx, sql, args)?;
+        Ok(row.is_some())
+    }
+
+    /// Refuse re-entry to a transaction, and forget transactions whose refusal has expired.
+    fn blacklist_tx(tx: &mut MemPoolTx<'a>, txid: &Txid) -> Result<(), db_error> {
+        let now = get_epoch_time_secs();
+        let cutoff = now.saturating_sub(MEMPOOL_CANCELLED_TX_BLACKLIST_SECS);
+        tx.execute(
+            "DELETE FROM tx_blacklist WHERE arrival_time < ?1",
+            &[&u64_to_sql(cutoff)?],
+        )?;
+
+        let args: &[&dyn ToSql] = &[txid, &u64_to_sql(now)?];
+        tx.execute(
+            "INSERT OR REPLACE INTO tx_blacklist (txid, arrival_time) VALUES (?1, ?2)",
+            args,
+        )?;
+        Ok(())
+    }
+
+    /// Record why the miner skipped a transaction, replacing any earlier reason.
+    /// Does nothing if the transaction has since left the mempool.
+    pub fn set_skip_reason(
+        tx: &mut MemPoolTx<'a>,
+        txid: &Txid,
+        reason: &str,
+        skip_time: u64,
+    ) -> Result<(), db_error> {
+        let sql = "INSERT OR REPLACE INTO tx_skip_reasons (txid, reason, skip_time)
+                   SELECT txid, ?2, ?3 FROM mempool WHERE txid = ?1";
+        let args: &[&dyn ToSql] = &[txid, &reason, &u64_to_sql(skip_time)?];
+        tx.execute(sql, args)?;
+        Ok(())
+    }
+
     /// Remove all txids at the given height from the bloom counter.
     /// Used to clear out txids that are now outside the bloom counter's depth.
     fn prune_bloom_counter(&mut self, target_height: u64) -> Result<(), MemPoolRejection> {
@@ -1023,7 +1176,7 @@ impl MemPoolDB {
 
     /// Load the schema version from the database, if it's new enough to have such a version.
     /// Returns Some(version) if a version can be loaded; None if not.
-    fn get_schema_version(conn: &DBConn) -> Result<Option<i64>, db_error> {
+    pub(crate) fn get_schema_version(conn: &DBConn) -> Result<Option<i64>, db_error> {
         let is_versioned = table_exists(conn, "schema_version")?;
         if !is_versioned {
             return Ok(None);
@@ -1061,6 +1214,12 @@ impl MemPoolDB {
                     MemPoolDB::instantiate_nonces(tx)?;
                 }
                 6 => {
+                    MemPoolDB::instantiate_skip_reasons(tx)?;
+                }
+                7 => {
+                    MemPoolDB::instantiate_cancellations(tx)?;
+                }
+                MEMPOOL_SCHEMA_VERSION => {
                     break;
                 }
                 _ => {
@@ -1132,6 +1291,24 @@ impl MemPoolDB {
         Ok(())
     }
 
+    /// Add the skipped-transaction table
+    fn instantiate_skip_reasons(tx: &DBTx) -> Result<(), db_error> {
+        for sql_exec in MEMPOOL_SCHEMA_7_SKIP_REASONS {
+            tx.execute_batch(sql_exec)?;
+        }
+
+        Ok(())
+    }
+
+    /// Add the cancellation table
+    fn instantiate_cancellations(tx: &DBTx) -> Result<(), db_error> {
+        for sql_exec in MEMPOOL_SCHEMA_8_CANCELLATIONS {
+            tx.execute_batch(sql_exec)?;
+        }
+
+        Ok(())
+    }
+
     pub fn db_path(chainstate_root_path: &str) -> Result<String, db_error> {
         let mut path = PathBuf::from(chainstate_root_path);
 
@@ -1366,6 +1543,9 @@ impl MemPoolDB {
         // single transaction.  This cannot grow to more than `settings.nonce_cache_size` entries.
         let mut retry_store = HashMap::new();
 
+        // reasons why transactions were skipped, to store after the inner loop completes.
+        let mut skip_reasons = HashMap::new();
+
         let sql = "
              SELECT txid, origin_nonce, origin_address, sponsor_nonce, sponsor_address, fee_rate
              FROM mempool
@@ -1567,8 +1747,12 @@ impl MemPoolDB {
                             }
                             output_events.push(tx_event);
                         }
-                        TransactionEvent::Skipped(_) => {
-                            // don't push `Skipped` events to the observer
+                        TransactionEvent::Skipped(skipped) => {
+                            // don't push `Skipped` events to the observer, but remember why
+                            // the transaction is still pending
+                            if (skip_reasons.len() as u64) < settings.candidate_retry_cache_size {
+                                skip_reasons.insert(skipped.txid, skipped.error);
+                            }
                         }
                         _ => {
                             output_events.push(tx_event);
@@ -1605,6 +1789,15 @@ impl MemPoolDB {
             tx.commit()?;
         }
 
+        if skip_reasons.len() > 0 {
+            let mut tx = self.tx_begin()?;
+            let skip_time = get_epoch_time_secs();
+            for (txid, reason) in skip_reasons.into_iter() {
+                MemPoolTx::set_skip_reason(&mut tx, &txid, &reason, skip_time)?;
+            }
+            tx.commit()?;
+        }
+
         debug!(
             "Mempool iteration finished";
             "considered_txs" => total_considered,
@@ -1617,6 +1810,10 @@ impl MemPoolDB {
         &self.db
     }
 
+    pub fn set_replace_policy(&mut self, replace_policy: MemPoolReplacePolicy) {
+        self.admitter.set_replace_policy(replace_policy);
+    }
+
     pub fn tx_begin<'a>(&'a mut self) -> Result<MemPoolTx<'a>, db_error> {
         let tx = tx_begin_immediate(&mut self.db)?;
         Ok(MemPoolTx::new(
@@ -1704,6 +1901,38 @@ impl MemPoolDB {
         Ok(rows)
     }
 
+    /// Get a page of the pending transactions that `addr` originated or sponsored, in nonce
+    /// order, along with the reason the miner last skipped each of them (if it did).
+    pub fn get_pending_txs_by_address(
+        conn: &DBConn,
+        addr: &StacksAddress,
+        offset: u64,
+        limit: u64,
+    ) -> Result<Vec<MemPoolPendingTx>, db_error> {
+        let sql = "SELECT mempool.*, tx_skip_reasons.reason AS skip_reason, tx_skip_reasons.skip_time AS skip_time
+                   FROM mempool LEFT JOIN tx_skip_reasons ON mempool.txid = tx_skip_reasons.txid
+                   WHERE mempool.origin_address = ?1 OR mempool.sponsor_address = ?1
+                   ORDER BY CASE WHEN mempool.origin_address = ?1 THEN mempool.origin_nonce ELSE mempool.sponsor_nonce END ASC, mempool.txid ASC
+                   LIMIT ?2 OFFSET ?3";
+        let args: &[&dyn ToSql] = &[&addr.to_string(), &u64_to_sql(limit)?, &u64_to_sql(offset)?];
+        query_rows(conn, sql, args)
+    }
+
+    /// Count the pending transactions that `addr` originated or sponsored.
+    pub fn count_pending_txs_by_address(
+        conn: &DBConn,
+        addr: &StacksAddress,
+    ) -> Result<u64, db_error> {
+        let sql = "SELECT COUNT(*) FROM mempool WHERE origin_address = ?1 OR sponsor_address = ?1";
+        let args: &[&dyn ToSql] = &[&addr.to_string()];
+        query_count(conn, sql, args).map(|count| count as u64)
+    }
+
+    /// Count all pending transactions.
+    pub fn count_txs(conn: &DBConn) -> Result<u64, db_error> {
+        query_count(conn, "SELECT COUNT(*) FROM mempool", NO_PARAMS).map(|count| count as u64)
+    }
+
     /// Get a transaction's metadata, given address and nonce, and whether the address is used as a sponsor or an origin.
     /// Faster than getting the MemPoolTxInfo, since no deserialization will be needed.
     /// Used to see if there exists a transaction with this info, so as to implement replace-by-fee
@@ -1788,6 +2017,10 @@ impl MemPoolDB {
     ) -> Result<(), MemPoolRejection> {
         let length = tx_bytes.len() as u64;
 
+        if MemPoolTx::is_tx_blacklisted(tx, &txid)? {
+            return Err(MemPoolRejection::TransactionCancelled);
+        }
+
         // do we already have txs with either the same origin nonce or sponsor nonce ?
         let prior_tx = {
             match MemPoolDB::get_tx_metadata_by_address(tx, true, origin_address, origin_nonce)? {
@@ -1805,7 +2038,11 @@ impl MemPoolDB {
 
         // if so, is this a replace-by-fee? or a replace-in-chain-tip?
         let add_tx = if let Some(ref prior_tx) = prior_tx {
-            if tx_fee > prior_tx.tx_fee {
+            let min_replacement_fee = tx
+                .admitter
+                .replace_policy
+                .min_replacement_fee(prior_tx.tx_fee);
+            if tx_fee >= min_replacement_fee {
                 // is this a replace-by-fee ?
                 debug!(
                     "Can replace {} with {} for {},{} by fee ({} < {})",
@@ -1827,6 +2064,18 @@ impl MemPoolDB {
                 );
                 replace_reason = MemPoolDropReason::REPLACE_ACROSS_FORK;
                 true
+            } else if tx_fee > prior_tx.tx_fee {
+                // the fee went up, but not by enough
+                info!("TX replacement in same fork does not bump the fee enough";
+                      "new_txid" => %txid,
+                      "old_txid" => %prior_tx.txid,
+                      "new_fee" => tx_fee,
+                      "old_fee" => prior_tx.tx_fee,
+                      "min_fee" => min_replacement_fee);
+                return Err(MemPoolRejection::ReplacementFeeTooLow(
+                    tx_fee,
+                    min_replacement_fee,
+                ));
             } else {
                 // there's a >= fee tx in this fork, cannot add
                 info!("TX conflicts with sponsor/origin nonce in same fork with >= fee";
@@ -1849,6 +2098,24 @@ impl MemPoolDB {
             return Err(MemPoolRejection::ConflictingNonceInMempool);
         }
 
+        // a new pending transaction (as opposed to a replacement) counts against its
+        // principals' caps
+        let max_pending = tx.admitter.replace_policy.max_pending_txs_per_principal;
+        if prior_tx.is_none() && max_pending > 0 {
+            let mut principals = vec![origin_address];
+            if sponsor_address != origin_address {
+                principals.push(sponsor_address);
+            }
+            for principal in principals.into_iter() {
+                if MemPoolDB::count_pending_txs_by_address(tx, principal)? >= max_pending {
+                    return Err(MemPoolRejection::TooManyPendingTransactions {
+                        principal: principal.clone().into(),
+                        max_pending,
+                    });
+                }
+            }
+        }
+
         tx.update_bloom_counter(height, &txid, prior_tx.as_ref().map(|tx| tx.txid.clone()))?;
 
         let sql = "INSERT OR REPLACE INTO mempool (
@@ -1894,6 +2161,91 @@ impl MemPoolDB {
         Ok(())
     }
 
+    /// Is this transaction a request to cancel a pending transaction, per the mempool's replace
+    /// policy?  That is, a STX transfer from the origin to itself.
+    pub fn is_cancellation(mempool_tx: &MemPoolTx, tx: &StacksTransaction) -> bool {
+        if !mempool_tx
+            .admitter
+            .replace_policy
+            .allow_cancel_by_self_transfer
+        {
+            return false;
+        }
+        match tx.payload {
+            TransactionPayload::TokenTransfer(ref recipient, ..) => {
+                *recipient == PrincipalData::from(tx.origin_address())
+            }
+            _ => false,
+        }
+    }
+
+    /// Cancel the pending transaction that `origin_address` sent with `origin_nonce`, on the
+    /// strength of a signed self-transfer `cancel_txid` at the same nonce.  The cancelled
+    /// transaction is refused re-entry, so mempool sync doesn't bring it back.  This only affects
+    /// this node's mempool.  An origin can cancel at most once per the replace policy's
+    /// `min_cancel_interval_secs`.
+    pub fn try_cancel_tx(
+        tx: &mut MemPoolTx,
+        cancel_txid: &Txid,
+        cancel_fee: u64,
+        origin_address: &StacksAddress,
+        origin_nonce: u64,
+        event_observer: Option<&dyn MemPoolEventDispatcher>,
+    ) -> Result<(), MemPoolRejection> {
+        let prior_tx =
+            MemPoolDB::get_tx_metadata_by_address(tx, true, origin_address, origin_nonce)?
+                .ok_or(MemPoolRejection::NoTransactionToCancel)?;
+
+        let min_replacement_fee = tx
+            .admitter
+            .replace_policy
+            .min_replacement_fee(prior_tx.tx_fee);
+        if cancel_fee < min_replacement_fee {
+            return Err(MemPoolRejection::ReplacementFeeTooLow(
+                cancel_fee,
+                min_replacement_fee,
+            ));
+        }
+
+        let now = get_epoch_time_secs();
+        let min_interval = tx.admitter.replace_policy.min_cancel_interval_secs;
+        let args: &[&dyn ToSql] = &[&origin_address.to_string()];
+        let last_cancel_time: Option<i64> = query_row(
+            tx,
+            "SELECT cancel_time FROM tx_cancellations WHERE address = ?1",
+            args,
+        )?;
+        if let Some(last_cancel_time) = last_cancel_time {
+            if now < (last_cancel_time as u64).saturating_add(min_interval) {
+                return Err(MemPoolRejection::TooManyCancellations {
+                    principal: origin_address.clone().into(),
+                    min_interval_secs: min_interval,
+                });
+            }
+        }
+
+        info!("Cancel mempool transaction";
+              "txid" => %prior_tx.txid,
+              "cancel_txid" => %cancel_txid,
+              "origin_addr" => %origin_address,
+              "origin_nonce" => origin_nonce);
+
+        tx.execute("DELETE FROM mempool WHERE txid = ?1", &[&prior_tx.txid])
+            .map_err(|e| MemPoolRejection::DBError(db_error::SqliteError(e)))?;
+        MemPoolTx::blacklist_tx(tx, &prior_tx.txid)?;
+        let args: &[&dyn ToSql] = &[&origin_address.to_string(), &u64_to_sql(now)?];
+        tx.execute(
+            "INSERT OR REPLACE INTO tx_cancellations (address, cancel_time) VALUES (?1, ?2)",
+            args,
+        )
+        .map_err(|e| MemPoolRejection::DBError(db_error::SqliteError(e)))?;
+
+        if let Some(event_observer) = event_observer {
+            event_observer.mempool_txs_dropped(vec![prior_tx.txid], MemPoolDropReason::CANCELLED);
+        }
+        Ok(())
+    }
+
     /// Garbage-collect the mempool.  Remove transactions that have a given number of
     /// confirmations.
     pub fn garbage_collect(
@@ -2006,6 +2358,20 @@ impl MemPoolDB {
                 (origin_address.clone(), origin_nonce)
             };
 
+        if MemPoolDB::is_cancellation(mempool_tx, tx) {
+            // never stored (it can't be mined), so it only needs to be signed by the sender
+            tx.verify()
+                .map_err(|e| MemPoolRejection::FailedToValidate(ChainstateError::NetError(e)))?;
+            return MemPoolDB::try_cancel_tx(
+                mempool_tx,
+                &txid,
+                tx_fee,
+                &origin_address,
+                origin_nonce,
+                event_observer,
+            );
+        }
+
         if do_admission_checks {
             mempool_tx
                 .admitter
@@ -14,6 +14,7 @@
 // You should have received a copy of the GNU General Public License
 // along with this program.  If not, see <http://www.gnu.org/licenses/>.
 
+use std::cell::RefCell;
 use std::cmp;
 use std::collections::HashSet;
 use std::io;
@@ -26,6 +27,7 @@ use crate::chainstate::stacks::db::test::instantiate_chainstate;
 use crate::chainstate::stacks::db::test::instantiate_chainstate_with_balances;
 use crate::chainstate::stacks::db::StreamCursor;
 use crate::chainstate::stacks::events::StacksTransactionReceipt;
+use crate::chainstate::stacks::miner::TransactionEvent;
 use crate::chainstate::stacks::miner::TransactionResult;
 use crate::chainstate::stacks::test::codec_all_transactions;
 use crate::chainstate::stacks::{
@@ -36,9 +38,14 @@ use crate::chainstate::stacks::{
     TransactionPostConditionMode, TransactionPublicKeyEncoding, TransactionSmartContract,
     TransactionSpendingCondition, TransactionVersion,
 };
+use crate::chainstate::stacks::{StacksBlock, StacksMicroblock};
 use crate::chainstate::stacks::{
     C32_ADDRESS_VERSION_MAINNET_SINGLESIG, C32_ADDRESS_VERSION_TESTNET_SINGLESIG,
 };
+use crate::core::mempool::MemPoolDropReason;
+use crate::core::mempool::MemPoolEventDispatcher;
+use crate::core::mempool::MemPoolReplacePolicy;
+use crate::core::mempool::MemPoolTx;
 use crate::core::mempool::MemPoolWalkSettings;
 use crate::core::mempool::TxTag;
 use crate::core::mempool::{BLOOM_COUNTER_DEPTH, BLOOM_COUNTER_ERROR_RATE, MAX_BLOOM_COUNTER_TXS};
@@ -865,6 +872,7 @@ fn test_iterate_candidates_skipped_transaction() {
         mempool_tx.commit().unwrap();
     }
 
+    let mut skipped_txid = None;
     chainstate.with_read_only_clarity_tx(
         &TEST_BURN_STATE_DB,
         &StacksBlockHeader::make_index_block_hash(&b_2.0, &b_2.1),
@@ -880,6 +888,7 @@ fn test_iterate_candidates_skipped_transaction() {
                         count_txs += 1;
                         // For the second transaction, return a `Skipped` result
                         let result = if count_txs == 2 {
+                            skipped_txid = Some(available_tx.tx.tx.txid());
                             TransactionResult::skipped(
                                 &available_tx.tx.tx,
                                 "event not relevant to test".to_string(),
@@ -909,6 +918,38 @@ fn test_iterate_candidates_skipped_transaction() {
             );
         },
     );
+
+    // the skip reason is reported alongside the sender's pending transactions
+    let skipped_txid = skipped_txid.unwrap();
+    let skipped_tx = MemPoolDB::get_tx(mempool.conn(), &skipped_txid)
+        .unwrap()
+        .unwrap();
+    let addr = skipped_tx.metadata.origin_address;
+    let total = MemPoolDB::count_pending_txs_by_address(mempool.conn(), &addr).unwrap();
+    let pending = MemPoolDB::get_pending_txs_by_address(mempool.conn(), &addr, 0, 100).unwrap();
+    assert_eq!(pending.len() as u64, total);
+    assert!(pending.iter().any(|p| p.tx.metadata.txid == skipped_txid));
+    for (i, p) in pending.iter().enumerate() {
+        if i > 0 {
+            assert!(pending[i - 1].tx.metadata.origin_nonce <= p.tx.metadata.origin_nonce);
+        }
+        if p.tx.metadata.txid == skipped_txid {
+            assert!(p
+                .skip_reason
+                .as_ref()
+                .unwrap()
+                .contains("event not relevant to test"));
+            assert!(p.skip_time.is_some());
+        } else {
+            assert!(p.skip_reason.is_none());
+            assert!(p.skip_time.is_none());
+        }
+    }
+
+    // pages are taken in nonce order
+    let page =
+        MemPoolDB::get_pending_txs_by_address(mempool.conn(), &addr, total - 1, 100).unwrap();
+    assert_eq!(page, pending[(total - 1) as usize..].to_vec());
 }
 
 #[test]
@@ -1537,6 +1578,294 @@ fn mempool_db_test_rbf() {
     assert_eq!(tx_info.metadata.tx_fee, 124);
 }
 
+#[test]
+fn mempool_db_test_replace_policy() {
+    let mut chainstate =
+        instantiate_chainstate(false, 0x80000000, "mempool_db_test_replace_policy");
+    let chainstate_path = chainstate_path("mempool_db_test_replace_policy");
+    let mut mempool = MemPoolDB::open_test(false, 0x80000000, &chainstate_path).unwrap();
+    // cancellation is opt-in
+    assert!(!MemPoolReplacePolicy::default().allow_cancel_by_self_transfer);
+    mempool.set_replace_policy(MemPoolReplacePolicy {
+        min_fee_bump_pct: 10,
+        allow_cancel_by_self_transfer: true,
+        min_cancel_interval_secs: 3600,
+        max_pending_txs_per_principal: 2,
+    });
+
+    let pk = StacksPrivateKey::new();
+    let recipient = StacksAddress {
+        version: C32_ADDRESS_VERSION_TESTNET_SINGLESIG,
+        bytes: Hash160([0xff; 20]),
+    };
+
+    let make_tx = |nonce: u64, fee: u64, to: &StacksAddress| {
+        let mut tx = StacksTransaction {
+            version: TransactionVersion::Testnet,
+            chain_id: 0x80000000,
+            auth: TransactionAuth::from_p2pkh(&pk).unwrap(),
+            anchor_mode: TransactionAnchorMode::Any,
+            post_condition_mode: TransactionPostConditionMode::Allow,
+            post_conditions: Vec::new(),
+            payload: TransactionPayload::TokenTransfer(
+                to.clone().into(),
+                123,
+                TokenTransferMemo([0u8; 34]),
+            ),
+        };
+        tx.set_origin_nonce(nonce);
+        tx.set_tx_fee(fee);
+        tx
+    };
+    let origin_address = make_tx(0, 0, &recipient).origin_address();
+
+    let mut mempool_tx = mempool.tx_begin().unwrap();
+    let mut add_tx = |mempool_tx: &mut MemPoolTx, tx: &StacksTransaction| {
+        MemPoolDB::try_add_tx(
+            mempool_tx,
+            &mut chainstate,
+            &ConsensusHash([0x1; 20]),
+            &BlockHeaderHash([0x2; 32]),
+            tx.txid(),
+            tx.serialize_to_vec(),
+            tx.get_tx_fee(),
+            100,
+            &origin_address,
+            tx.get_origin_nonce(),
+            &origin_address,
+            tx.get_origin_nonce(),
+            None,
+        )
+    };
+
+    let tx_0 = make_tx(0, 1000, &recipient);
+    add_tx(&mut mempool_tx, &tx_0).unwrap();
+
+    // a fee bump of less than 10% is not enough to replace it
+    let tx_0_low = make_tx(0, 1099, &recipient);
+    match add_tx(&mut mempool_tx, &tx_0_low).unwrap_err() {
+        MemPoolRejection::ReplacementFeeTooLow(actual, expected) => {
+            assert_eq!(actual, 1099);
+            assert_eq!(expected, 1100);
+        }
+        e => panic!("Unexpected rejection: {:?}", e),
+    }
+
+    // ...but a bump of 10% is
+    let tx_0_bumped = make_tx(0, 1100, &recipient);
+    add_tx(&mut mempool_tx, &tx_0_bumped).unwrap();
+    assert!(!MemPoolDB::db_has_tx(&mempool_tx, &tx_0.txid()).unwrap());
+    assert!(MemPoolDB::db_has_tx(&mempool_tx, &tx_0_bumped.txid()).unwrap());
+
+    // the principal can have at most two pending transactions
+    let tx_1 = make_tx(1, 1000, &recipient);
+    add_tx(&mut mempool_tx, &tx_1).unwrap();
+    let tx_2 = make_tx(2, 1000, &recipient);
+    match add_tx(&mut mempool_tx, &tx_2).unwrap_err() {
+        MemPoolRejection::TooManyPendingTransactions {
+            principal,
+            max_pending,
+        } => {
+            assert_eq!(principal, PrincipalData::from(origin_address.clone()));
+            assert_eq!(max_pending, 2);
+        }
+        e => panic!("Unexpected rejection: {:?}", e),
+    }
+
+    // replacements don't count against the cap
+    let tx_1_bumped = make_tx(1, 2000, &recipient);
+    add_tx(&mut mempool_tx, &tx_1_bumped).unwrap();
+
+    // cancel nonce 1 with a self-transfer
+    let cancel_tx = make_tx(1, 2200, &origin_address);
+    assert!(MemPoolDB::is_cancellation(&mempool_tx, &cancel_tx));
+    assert!(!MemPoolDB::is_cancellation(&mempool_tx, &tx_1));
+    MemPoolDB::try_cancel_tx(
+        &mut mempool_tx,
+        &cancel_tx.txid(),
+        cancel_tx.get_tx_fee(),
+        &origin_address,
+        1,
+        None,
+    )
+    .unwrap();
+    assert!(!MemPoolDB::db_has_tx(&mempool_tx, &tx_1_bumped.txid()).unwrap());
+    assert!(!MemPoolDB::db_has_tx(&mempool_tx, &cancel_tx.txid()).unwrap());
+
+    // the cancelled transaction can't come back
+    match add_tx(&mut mempool_tx, &tx_1_bumped).unwrap_err() {
+        MemPoolRejection::TransactionCancelled => {}
+        e => panic!("Unexpected rejection: {:?}", e),
+    }
+
+    // there's nothing left to cancel at nonce 1
+    match MemPoolDB::try_cancel_tx(
+        &mut mempool_tx,
+        &cancel_tx.txid(),
+        cancel_tx.get_tx_fee(),
+        &origin_address,
+        1,
+        None,
+    )
+    .unwrap_err()
+    {
+        MemPoolRejection::NoTransactionToCancel => {}
+        e => panic!("Unexpected rejection: {:?}", e),
+    }
+
+    // the cancellation freed up a slot under the cap
+    add_tx(&mut mempool_tx, &tx_2).unwrap();
+
+    // but the origin can't cancel again for a while
+    let cancel_tx_2 = make_tx(2, 2000, &origin_address);
+    match MemPoolDB::try_cancel_tx(
+        &mut mempool_tx,
+        &cancel_tx_2.txid(),
+        cancel_tx_2.get_tx_fee(),
+        &origin_address,
+        2,
+        None,
+    )
+    .unwrap_err()
+    {
+        MemPoolRejection::TooManyCancellations {
+            principal,
+            min_interval_secs,
+        } => {
+            assert_eq!(principal, PrincipalData::from(origin_address.clone()));
+            assert_eq!(min_interval_secs, 3600);
+        }
+        e => panic!("Unexpected rejection: {:?}", e),
+    }
+    assert!(MemPoolDB::db_has_tx(&mempool_tx, &tx_2.txid()).unwrap());
+    mempool_tx.commit().unwrap();
+}
+
+/// Records the transactions dropped from the mempool
+struct DroppedTxsRecorder {
+    dropped: RefCell<Vec<(Vec<Txid>, MemPoolDropReason)>>,
+}
+
+impl MemPoolEventDispatcher for DroppedTxsRecorder {
+    fn mempool_txs_dropped(&self, txids: Vec<Txid>, reason: MemPoolDropReason) {
+        self.dropped.borrow_mut().push((txids, reason));
+    }
+
+    fn mined_block_event(
+        &self,
+        _target_burn_height: u64,
+        _block: &StacksBlock,
+        _block_size_bytes: u64,
+        _consumed: &ExecutionCost,
+        _confirmed_microblock_cost: &ExecutionCost,
+        _tx_results: Vec<TransactionEvent>,
+    ) {
+    }
+
+    fn mined_microblock_event(
+        &self,
+        _microblock: &StacksMicroblock,
+        _tx_results: Vec<TransactionEvent>,
+        _anchor_block_consensus_hash: ConsensusHash,
+        _anchor_block: BlockHeaderHash,
+    ) {
+    }
+}
+
+#[test]
+fn mempool_db_test_replace_drop_events() {
+    let mut chainstate =
+        instantiate_chainstate(false, 0x80000000, "mempool_db_test_replace_drop_events");
+    let chainstate_path = chainstate_path("mempool_db_test_replace_drop_events");
+    let mut mempool = MemPoolDB::open_test(false, 0x80000000, &chainstate_path).unwrap();
+    mempool.set_replace_policy(MemPoolReplacePolicy {
+        min_fee_bump_pct: 10,
+        allow_cancel_by_self_transfer: true,
+        min_cancel_interval_secs: 3600,
+        max_pending_txs_per_principal: 0,
+    });
+    let recorder = DroppedTxsRecorder {
+        dropped: RefCell::new(vec![]),
+    };
+
+    let pk = StacksPrivateKey::new();
+    let recipient = StacksAddress {
+        version: C32_ADDRESS_VERSION_TESTNET_SINGLESIG,
+        bytes: Hash160([0xff; 20]),
+    };
+    let make_tx = |nonce: u64, fee: u64, to: &StacksAddress| {
+        let mut tx = StacksTransaction {
+            version: TransactionVersion::Testnet,
+            chain_id: 0x80000000,
+            auth: TransactionAuth::from_p2pkh(&pk).unwrap(),
+            anchor_mode: TransactionAnchorMode::Any,
+            post_condition_mode: TransactionPostConditionMode::Allow,
+            post_conditions: Vec::new(),
+            payload: TransactionPayload::TokenTransfer(
+                to.clone().into(),
+                123,
+                TokenTransferMemo([0u8; 34]),
+            ),
+        };
+        tx.set_origin_nonce(nonce);
+        tx.set_tx_fee(fee);
+        tx
+    };
+    let origin_address = make_tx(0, 0, &recipient).origin_address();
+
+    let mut mempool_tx = mempool.tx_begin().unwrap();
+    let mut add_tx = |mempool_tx: &mut MemPoolTx, tx: &StacksTransaction| {
+        MemPoolDB::try_add_tx(
+            mempool_tx,
+            &mut chainstate,
+            &ConsensusHash([0x1; 20]),
+            &BlockHeaderHash([0x2; 32]),
+            tx.txid(),
+            tx.serialize_to_vec(),
+            tx.get_tx_fee(),
+            100,
+            &origin_address,
+            tx.get_origin_nonce(),
+            &origin_address,
+            tx.get_origin_nonce(),
+            Some(&recorder),
+        )
+    };
+
+    // adding a transaction drops nothing
+    let tx_0 = make_tx(0, 1000, &recipient);
+    add_tx(&mut mempool_tx, &tx_0).unwrap();
+    assert!(recorder.dropped.borrow().is_empty());
+
+    // a plain replace-by-fee drops the replaced transaction
+    let tx_0_bumped = make_tx(0, 1100, &recipient);
+    add_tx(&mut mempool_tx, &tx_0_bumped).unwrap();
+    assert_eq!(
+        recorder.dropped.borrow().as_slice(),
+        &[(vec![tx_0.txid()], MemPoolDropReason::REPLACE_BY_FEE)]
+    );
+
+    // a cancellation drops it for a different reason
+    let cancel_tx = make_tx(0, 1210, &origin_address);
+    MemPoolDB::try_cancel_tx(
+        &mut mempool_tx,
+        &cancel_tx.txid(),
+        cancel_tx.get_tx_fee(),
+        &origin_address,
+        0,
+        Some(&recorder),
+    )
+    .unwrap();
+    assert_eq!(
+        recorder.dropped.borrow().as_slice(),
+        &[
+            (vec![tx_0.txid()], MemPoolDropReason::REPLACE_BY_FEE),
+            (vec![tx_0_bumped.txid()], MemPoolDropReason::CANCELLED)
+        ]
+    );
+    mempool_tx.commit().unwrap();
+}
+
 #[test]
 fn test_add_txs_bloom_filter() {
     let mut chainstate = instantiate_chainstate(false, 0x80000000, "mempool_add_txs_bloom_filter");
@@ -87,6 +87,10 @@ use subnet_lib::{
     burnchains::{db::BurnchainBlockData, PoxConstants},
     chainstate::{
         burn::db::sortdb::SortitionDB,
+        stacks::db::export::{export_sql, ExportFormat},
+        stacks::db::fsck::fsck,
+        stacks::db::replay::replay_block_report,
+        stacks::db::snapshot::{export_snapshot, import_snapshot},
         stacks::db::{StacksChainState, StacksHeaderInfo},
     },
     core::MemPoolDB,
@@ -922,6 +926,127 @@ simulating a miner.
         return;
     }
 
+    if argv[1] == "export-snapshot" {
+        if argv.len() < 4 {
+            eprintln!(
+                "Usage: {} export-snapshot CHAINSTATE_DIR OUTPUT_FILE [INDEX_BLOCK_HASH]",
+                argv[0]
+            );
+            eprintln!("       INDEX_BLOCK_HASH defaults to the highest processed block");
+            process::exit(1);
+        }
+        let anchor = argv
+            .get(4)
+            .map(|hex| StacksBlockId::from_hex(hex).expect("Bad index block hash"));
+        match export_snapshot(&argv[2], anchor.as_ref(), &argv[3]) {
+            Ok(manifest) => println!("{}", serde_json::to_string_pretty(&manifest).unwrap()),
+            Err(e) => {
+                eprintln!("Failed to export snapshot: {}", &e);
+                process::exit(1);
+            }
+        }
+        process::exit(0);
+    }
+
+    if argv[1] == "import-snapshot" {
+        if argv.len() < 6 {
+            eprintln!(
+                "Usage: {} import-snapshot SNAPSHOT_FILE CHAINSTATE_DIR SORTITION_DB_DIR CHAIN_ID [mainnet] [overwrite]",
+                argv[0]
+            );
+            process::exit(1);
+        }
+        let chain_id = u32::from_str_radix(argv[5].trim_start_matches("0x"), 16)
+            .expect("CHAIN_ID must be hex, e.g. 0x80000000");
+        let mainnet = argv[6..].iter().any(|arg| arg == "mainnet");
+        let overwrite = argv[6..].iter().any(|arg| arg == "overwrite");
+        let sortdb = SortitionDB::open(&argv[4], false).expect("Failed to open sortition DB");
+        match import_snapshot(&argv[2], &argv[3], &sortdb, mainnet, chain_id, overwrite) {
+            Ok(manifest) => println!("{}", serde_json::to_string_pretty(&manifest).unwrap()),
+            Err(e) => {
+                eprintln!("Failed to import snapshot: {}", &e);
+                process::exit(1);
+            }
+        }
+        process::exit(0);
+    }
+
+    if argv[1] == "export-sql" {
+        if argv.len() < 5 {
+            eprintln!(
+                "Usage: {} export-sql CHAINSTATE_DIR SORTITION_DB_DIR OUTPUT_FILE [sqlite|sql]",
+                argv[0]
+            );
+            eprintln!("       sqlite (the default) writes a SQLite DB; sql appends SQL statements to OUTPUT_FILE");
+            eprintln!("       Re-running with the same OUTPUT_FILE resumes the export");
+            eprintln!("       Blocks missing from the transaction index are replayed, in a scratch copy of the chainstate");
+            process::exit(1);
+        }
+        let format = match argv.get(5).map(|format| format.as_str()) {
+            None | Some("sqlite") => ExportFormat::Sqlite,
+            Some("sql") => ExportFormat::SqlDump,
+            Some(format) => {
+                eprintln!("Unknown export format '{}'", format);
+                process::exit(1);
+            }
+        };
+        match export_sql(&argv[2], &argv[3], &argv[4], format) {
+            Ok(summary) => println!("{}", serde_json::to_string_pretty(&summary).unwrap()),
+            Err(e) => {
+                eprintln!("Failed to export chain: {}", &e);
+                process::exit(1);
+            }
+        }
+        process::exit(0);
+    }
+
+    if argv[1] == "fsck" {
+        if argv.len() < 4 {
+            eprintln!(
+                "Usage: {} fsck CHAINSTATE_DIR SORTITION_DB_DIR [--replay] [--repair]",
+                argv[0]
+            );
+            eprintln!("       --replay replays blocks processed without the transaction index, in a scratch copy of the chainstate, to check their withdrawal roots");
+            eprintln!("       --repair deletes leftover staging rows; stop the node first");
+            process::exit(1);
+        }
+        let replay = argv[4..].iter().any(|arg| arg == "--replay");
+        let repair = argv[4..].iter().any(|arg| arg == "--repair");
+        match fsck(&argv[2], &argv[3], replay, repair) {
+            Ok(report) => {
+                println!("{}", serde_json::to_string_pretty(&report).unwrap());
+                if !report.is_consistent() {
+                    process::exit(1);
+                }
+            }
+            Err(e) => {
+                eprintln!("Failed to check chainstate: {}", &e);
+                process::exit(1);
+            }
+        }
+        process::exit(0);
+    }
+
+    if argv[1] == "replay-block" {
+        if argv.len() < 5 {
+            eprintln!(
+                "Usage: {} replay-block CHAINSTATE_DIR SORTITION_DB_DIR INDEX_BLOCK_HASH",
+                argv[0]
+            );
+            eprintln!("       Re-executes a processed block in a scratch copy of the chainstate, and reports each transaction's MARF writes, events and costs and any divergence from the stored block");
+            process::exit(1);
+        }
+        let index_block_hash = StacksBlockId::from_hex(&argv[4]).expect("Bad index block hash");
+        match replay_block_report(&argv[2], &argv[3], &index_block_hash) {
+            Ok(report) => println!("{}", serde_json::to_string_pretty(&report).unwrap()),
+            Err(e) => {
+                eprintln!("Failed to replay block {}: {}", &index_block_hash, &e);
+                process::exit(1);
+            }
+        }
+        process::exit(0);
+    }
+
     if argv[1] == "process-block" {
         let path = &argv[2];
         let sort_path = &argv[3];
@@ -304,6 +304,110 @@ pub fn increment_msg_counter(name: String) {
         .inc();
 }
 
+#[allow(unused_variables)]
+pub fn set_event_observer_queue_depth(endpoint: &str, depth: u64) {
+    #[cfg(feature = "monitoring_prom")]
+    prometheus::EVENT_OBSERVER_QUEUE_DEPTH
+        .with_label_values(&[endpoint])
+        .set(depth as i64);
+}
+
+#[allow(unused_variables)]
+pub fn set_event_observer_delivery_lag(endpoint: &str, lag_secs: u64) {
+    #[cfg(feature = "monitoring_prom")]
+    prometheus::EVENT_OBSERVER_DELIVERY_LAG
+        .with_label_values(&[endpoint])
+        .set(lag_secs as i64);
+}
+
+#[allow(unused_variables)]
+pub fn increment_event_observer_dropped_counter(endpoint: &str) {
+    #[cfg(feature = "monitoring_prom")]
+    prometheus::EVENT_OBSERVER_DROPPED_PAYLOADS
+        .with_label_values(&[endpoint])
+        .inc();
+}
+
+#[allow(unused_variables)]
+pub fn increment_l1_observer_blocks_received(block_height: u64, num_events: usize) {
+    #[cfg(feature = "monitoring_prom")]
+    {
+        prometheus::L1_OBSERVER_BLOCKS_RECEIVED_COUNTER.inc();
+        prometheus::L1_OBSERVER_EVENTS_RECEIVED_COUNTER.inc_by(num_events as u64);
+        prometheus::L1_OBSERVER_BLOCK_HEIGHT_GAUGE.set(block_height as i64);
+    }
+}
+
+/// Count an attempt to commit a block to the L1. `outcome` is one of `submitted`,
+/// `build_failed` or `submit_failed`.
+#[allow(unused_variables)]
+pub fn increment_l1_commits_counter(outcome: &str) {
+    #[cfg(feature = "monitoring_prom")]
+    prometheus::L1_COMMITS_COUNTER
+        .with_label_values(&[outcome])
+        .inc();
+}
+
+/// Record how long `participant` took to answer a block proposal. `outcome` is one of
+/// `accepted`, `rejected` or `failed`.
+#[allow(unused_variables)]
+pub fn observe_block_proposal_latency(participant: &str, outcome: &str, latency_secs: f64) {
+    #[cfg(feature = "monitoring_prom")]
+    prometheus::BLOCK_PROPOSAL_LATENCIES_HISTOGRAM
+        .with_label_values(&[participant, outcome])
+        .observe(latency_secs);
+}
+
+#[cfg(test)]
+thread_local! {
+    /// The deposits counted on this thread, as `(asset, outcome)`, so that tests can check
+    /// which blocks are counted
+    pub static OBSERVED_DEPOSITS: std::cell::RefCell<Vec<(String, String)>> =
+        std::cell::RefCell::new(vec![]);
+}
+
+/// Count a deposit processed by a block this node appended to its chain state. `asset` is one
+/// of `stx`, `ft` or `nft`, and `outcome` is one of `deposited`, `refunded` or `failed`.
+/// Blocks built by the miner or re-executed by a replay are not counted.
+#[allow(unused_variables)]
+pub fn increment_deposits_processed_counter(asset: &str, outcome: &str) {
+    #[cfg(test)]
+    OBSERVED_DEPOSITS.with(|observed| {
+        observed
+            .borrow_mut()
+            .push((asset.to_string(), outcome.to_string()))
+    });
+    #[cfg(feature = "monitoring_prom")]
+    prometheus::DEPOSITS_PROCESSED_COUNTER
+        .with_label_values(&[asset, outcome])
+        .inc();
+}
+
+/// Record how long the chains coordinator took to evaluate a burn block's sortition.
+#[allow(unused_variables)]
+pub fn observe_burn_block_processing_time(secs: f64) {
+    #[cfg(feature = "monitoring_prom")]
+    prometheus::BURN_BLOCK_PROCESSING_TIME_HISTOGRAM.observe(secs);
+}
+
+#[cfg(test)]
+thread_local! {
+    /// The withdrawal leaf counts recorded on this thread, so that tests can check which
+    /// blocks are counted
+    pub static OBSERVED_WITHDRAWAL_LEAVES: std::cell::RefCell<Vec<usize>> =
+        std::cell::RefCell::new(vec![]);
+}
+
+/// Record the number of withdrawals in a block this node appended to its chain state. Blocks
+/// built by the miner or re-executed by a replay are not counted.
+#[allow(unused_variables)]
+pub fn observe_withdrawal_leaves(num_leaves: usize) {
+    #[cfg(test)]
+    OBSERVED_WITHDRAWAL_LEAVES.with(|observed| observed.borrow_mut().push(num_leaves));
+    #[cfg(feature = "monitoring_prom")]
+    prometheus::WITHDRAWAL_LEAVES_HISTOGRAM.observe(num_leaves as f64);
+}
+
 pub fn increment_stx_mempool_gc() {
     #[cfg(feature = "monitoring_prom")]
     prometheus::STX_MEMPOOL_GC.inc();
@@ -16,6 +16,7 @@
 
 use prometheus::{
     Gauge, Histogram, HistogramTimer, HistogramVec, IntCounter, IntCounterVec, IntGauge,
+    IntGaugeVec,
 };
 
 lazy_static! {
@@ -215,6 +216,69 @@ lazy_static! {
         "stacks_node_miner_current_median_commitment_low",
         "Low 64 bits of a miner's median commitment over the mining commitment window."
     )).unwrap();
+
+    pub static ref EVENT_OBSERVER_QUEUE_DEPTH: IntGaugeVec = register_int_gauge_vec!(
+        "stacks_node_event_observer_queue_depth",
+        "Number of payloads waiting to be delivered to an event observer",
+        &["endpoint"]
+    ).unwrap();
+
+    pub static ref EVENT_OBSERVER_DELIVERY_LAG: IntGaugeVec = register_int_gauge_vec!(
+        "stacks_node_event_observer_delivery_lag",
+        "Time (seconds) between when the last payload delivered to an event observer was queued and when it was delivered",
+        &["endpoint"]
+    ).unwrap();
+
+    pub static ref EVENT_OBSERVER_DROPPED_PAYLOADS: IntCounterVec = register_int_counter_vec!(
+        "stacks_node_event_observer_dropped_payloads_total",
+        "Total number of payloads dropped because an event observer's outbox was full",
+        &["endpoint"]
+    ).unwrap();
+
+    pub static ref L1_OBSERVER_BLOCKS_RECEIVED_COUNTER: IntCounter = register_int_counter!(opts!(
+        "stacks_node_l1_observer_blocks_received_total",
+        "Total number of L1 blocks delivered to this node's L1 observer"
+    )).unwrap();
+
+    pub static ref L1_OBSERVER_EVENTS_RECEIVED_COUNTER: IntCounter = register_int_counter!(opts!(
+        "stacks_node_l1_observer_events_received_total",
+        "Total number of subnet contract events delivered to this node's L1 observer"
+    )).unwrap();
+
+    pub static ref L1_OBSERVER_BLOCK_HEIGHT_GAUGE: IntGauge = register_int_gauge!(opts!(
+        "stacks_node_l1_observer_block_height",
+        "Height of the last L1 block delivered to this node's L1 observer"
+    )).unwrap();
+
+    pub static ref L1_COMMITS_COUNTER: IntCounterVec = register_int_counter_vec!(
+        "stacks_node_l1_commits_total",
+        "Total number of block commitments this node tried to submit to the L1, by outcome",
+        &["outcome"]
+    ).unwrap();
+
+    pub static ref BLOCK_PROPOSAL_LATENCIES_HISTOGRAM: HistogramVec = register_histogram_vec!(histogram_opts!(
+        "stacks_node_block_proposal_latencies_histogram",
+        "Time (seconds) between sending a block proposal to a federation participant and receiving its answer",
+        vec![0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0, 30.0]
+    ), &["participant", "outcome"]).unwrap();
+
+    pub static ref DEPOSITS_PROCESSED_COUNTER: IntCounterVec = register_int_counter_vec!(
+        "stacks_node_deposits_processed_total",
+        "Total number of L1 deposit operations processed by the subnet blocks this node appended, by asset type and outcome",
+        &["asset", "outcome"]
+    ).unwrap();
+
+    pub static ref WITHDRAWAL_LEAVES_HISTOGRAM: Histogram = register_histogram!(histogram_opts!(
+        "stacks_node_withdrawal_leaves_per_block",
+        "Number of withdrawal leaves in each subnet block's withdrawal Merkle tree",
+        vec![0.0, 1.0, 2.0, 5.0, 10.0, 25.0, 50.0, 100.0, 250.0, 500.0, 1000.0]
+    )).unwrap();
+
+    pub static ref BURN_BLOCK_PROCESSING_TIME_HISTOGRAM: Histogram = register_histogram!(histogram_opts!(
+        "stacks_node_burn_block_processing_seconds",
+        "Time (seconds) the chains coordinator spent evaluating each burn block's sortition",
+        vec![0.001, 0.0025, 0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0]
+    )).unwrap();
 }
 
 pub fn new_rpc_call_timer(path: &str) -> HistogramTimer {
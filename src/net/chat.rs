@@ -1109,7 +1109,9 @@ impl ConversationP2P {
             return Ok((None, true));
         }
 
-        let res = self.validate_handshake(local_peer, chain_view, message);
+        let res = self
+            .validate_handshake(local_peer, chain_view, message)
+            .and_then(|_| self.check_handshake_allowlisted(peerdb.conn(), message));
         match res {
             Ok(_) => {}
             Err(net_error::InvalidHandshake) => {
@@ -1196,14 +1198,68 @@ impl ConversationP2P {
         Ok((Some(accept), false))
     }
 
+    /// Is this public key one we can talk to?  Always true unless we're in permissioned mode,
+    /// in which case the key must be on the peer DB's allowlist.
+    pub fn is_allowlisted(
+        &self,
+        peerdb_conn: &DBConn,
+        public_key: &Secp256k1PublicKey,
+    ) -> Result<bool, net_error> {
+        if !self.connection.options.permissioned {
+            return Ok(true);
+        }
+        PeerDB::is_peer_allowlisted(peerdb_conn, public_key).map_err(net_error::DBError)
+    }
+
+    /// In permissioned mode, reject a handshake from a peer that isn't on the allowlist.
+    fn check_handshake_allowlisted(
+        &self,
+        peerdb_conn: &DBConn,
+        message: &StacksMessage,
+    ) -> Result<(), net_error> {
+        let handshake_data = match message.payload {
+            StacksMessageType::Handshake(ref data) => data,
+            _ => panic!("Message is not a handshake"),
+        };
+        let public_key = handshake_data
+            .node_public_key
+            .to_public_key()
+            .map_err(|_e| net_error::InvalidHandshake)?;
+        if !self.is_allowlisted(peerdb_conn, &public_key)? {
+            debug!(
+                "{:?}: handshake from peer {} that is not on the allowlist",
+                &self,
+                &to_hex(&public_key.to_bytes_compressed())
+            );
+            return Err(net_error::InvalidHandshake);
+        }
+        Ok(())
+    }
+
     /// Handle an inbound handshake-accept
     /// Update conversation state based on a HandshakeAccept
     /// Called from the p2p network thread.
     fn handle_handshake_accept(
         &mut self,
+        peerdb: &PeerDB,
         preamble: &Preamble,
         handshake_accept: &HandshakeAcceptData,
     ) -> Result<(), net_error> {
+        let public_key = handshake_accept
+            .handshake
+            .node_public_key
+            .to_public_key()
+            .map_err(|_e| net_error::InvalidHandshake)?;
+        if !self.is_allowlisted(peerdb.conn(), &public_key)? {
+            // we reached out to a peer we shouldn't talk to.  Hang up.
+            debug!(
+                "{:?}: HandshakeAccept from peer {} that is not on the allowlist",
+                &self,
+                &to_hex(&public_key.to_bytes_compressed())
+            );
+            return Err(net_error::InvalidHandshake);
+        }
+
         self.update_from_handshake_data(preamble, &handshake_accept.handshake)?;
         self.peer_heartbeat =
             if handshake_accept.heartbeat_interval > (MAX_PEER_HEARTBEAT_INTERVAL as u32) {
@@ -1271,14 +1327,25 @@ impl ConversationP2P {
 
         // get neighbors at random as long as they're fresh, and as long as they're compatible with
         // the current system epoch
-        let mut neighbors = PeerDB::get_random_neighbors(
-            peer_dbconn,
-            self.network_id,
-            epoch.network_epoch,
-            MAX_NEIGHBORS_DATA_LEN,
-            chain_view.burn_block_height,
-            false,
-        )
+        let mut neighbors = if self.connection.options.permissioned {
+            // only ever point other peers at peers we'd talk to ourselves
+            PeerDB::get_random_allowlisted_neighbors(
+                peer_dbconn,
+                self.network_id,
+                epoch.network_epoch,
+                MAX_NEIGHBORS_DATA_LEN,
+                chain_view.burn_block_height,
+            )
+        } else {
+            PeerDB::get_random_neighbors(
+                peer_dbconn,
+                self.network_id,
+                epoch.network_epoch,
+                MAX_NEIGHBORS_DATA_LEN,
+                chain_view.burn_block_height,
+                false,
+            )
+        }
         .map_err(net_error::DBError)?;
 
         if cfg!(test) && self.connection.options.disable_chat_neighbors {
@@ -1674,6 +1741,14 @@ impl ConversationP2P {
         Ok(None)
     }
 
+    /// Is the remote peer an authenticated member of the block-signing federation?
+    pub fn is_federation_peer(&self) -> bool {
+        match self.connection.get_public_key() {
+            Some(pubkey) => self.connection.options.federation_peers.contains(&pubkey),
+            None => false,
+        }
+    }
+
     /// Handle an inbound authenticated p2p data-plane message.
     /// Return the message if not handled
     fn handle_data_message(
@@ -1753,6 +1828,37 @@ impl ConversationP2P {
                     }
                 }
             }
+            StacksMessageType::BlockProposal(_) => {
+                if self.is_federation_peer() {
+                    // validating the proposal means executing its block, which is too slow to
+                    // do here. Forward it upstream, to the relayer, which will answer it.
+                    return Ok(Some(msg));
+                } else {
+                    debug!(
+                        "{:?}: Got block proposal from a non-federation peer; will NACK",
+                        &self
+                    );
+                    self.stats.msgs_err += 1;
+                    self.reply_nack(
+                        local_peer,
+                        chain_view,
+                        &msg.preamble,
+                        NackErrorCodes::InvalidMessage,
+                    )
+                }
+            }
+            StacksMessageType::BlockProposalSignature(_) => {
+                if self.is_federation_peer() {
+                    // will forward upstream, to the proposer
+                    return Ok(Some(msg));
+                }
+                debug!(
+                    "{:?}: Drop block proposal signature from a non-federation peer",
+                    &self
+                );
+                self.stats.msgs_err += 1;
+                return Ok(None);
+            }
             _ => {
                 // all else will forward upstream
                 return Ok(Some(msg));
@@ -1925,7 +2031,7 @@ impl ConversationP2P {
             }
             StacksMessageType::HandshakeAccept(ref data) => {
                 test_debug!("{:?}: Got HandshakeAccept", &self);
-                self.handle_handshake_accept(&msg.preamble, data)
+                self.handle_handshake_accept(peerdb, &msg.preamble, data)
                     .and_then(|_| Ok(None))
             }
             StacksMessageType::Ping(_) => {
@@ -1997,7 +2103,7 @@ impl ConversationP2P {
             StacksMessageType::HandshakeAccept(ref data) => {
                 if solicited {
                     test_debug!("{:?}: Got unauthenticated HandshakeAccept", &self);
-                    self.handle_handshake_accept(&msg.preamble, data)
+                    self.handle_handshake_accept(peerdb, &msg.preamble, data)
                         .and_then(|_| Ok(None))
                 } else {
                     test_debug!("{:?}: Unsolicited unauthenticated HandshakeAccept", &self);
@@ -2279,6 +2385,7 @@ mod test {
     use crate::chainstate::burn::db::sortdb::*;
     use crate::chainstate::burn::*;
     use crate::chainstate::stacks::db::ChainStateBootData;
+    use crate::chainstate::stacks::miner::SignedProposal;
     use crate::chainstate::*;
     use crate::core::*;
     use crate::net::connection::*;
@@ -2787,6 +2894,188 @@ mod test {
         assert!(convo_2.connection.get_public_key().is_none());
     }
 
+    #[test]
+    fn convo_handshake_not_allowlisted() {
+        let conn_opts = ConnectionOptions::default();
+        let mut conn_opts_permissioned = ConnectionOptions::default();
+        conn_opts_permissioned.permissioned = true;
+
+        let socketaddr_1 = SocketAddr::new(IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1)), 8080);
+        let socketaddr_2 = SocketAddr::new(IpAddr::V4(Ipv4Addr::new(1, 2, 3, 4)), 8081);
+
+        let burnchain = testing_burnchain_config();
+
+        let mut chain_view = BurnchainView {
+            burn_block_height: 12348,
+            burn_block_hash: BurnchainHeaderHash([0x11; 32]),
+            burn_stable_block_height: 12341,
+            burn_stable_block_hash: BurnchainHeaderHash([0x22; 32]),
+            last_burn_block_hashes: HashMap::new(),
+        };
+        chain_view.make_test_data();
+
+        let (mut peerdb_1, mut sortdb_1, mut chainstate_1) = make_test_chain_dbs(
+            "convo_handshake_not_allowlisted_1",
+            &burnchain,
+            0x9abcdef0,
+            12350,
+            "http://peer1.com".into(),
+            &vec![],
+            &vec![],
+        );
+        let (mut peerdb_2, mut sortdb_2, mut chainstate_2) = make_test_chain_dbs(
+            "convo_handshake_not_allowlisted_2",
+            &burnchain,
+            0x9abcdef0,
+            12351,
+            "http://peer2.com".into(),
+            &vec![],
+            &vec![],
+        );
+
+        db_setup(&mut peerdb_1, &mut sortdb_1, &socketaddr_1, &chain_view);
+        db_setup(&mut peerdb_2, &mut sortdb_2, &socketaddr_2, &chain_view);
+
+        let local_peer_1 = PeerDB::get_local_peer(&peerdb_1.conn()).unwrap();
+        let local_peer_2 = PeerDB::get_local_peer(&peerdb_2.conn()).unwrap();
+
+        let mut convo_1 = ConversationP2P::new(
+            123,
+            456,
+            &burnchain,
+            &socketaddr_2,
+            &conn_opts,
+            true,
+            0,
+            StacksEpoch::unit_test_pre_2_05(0),
+        );
+        let mut convo_2 = ConversationP2P::new(
+            123,
+            456,
+            &burnchain,
+            &socketaddr_1,
+            &conn_opts_permissioned,
+            true,
+            0,
+            StacksEpoch::unit_test_pre_2_05(0),
+        );
+
+        let public_key_1 = Secp256k1PublicKey::from_private(&local_peer_1.private_key);
+        assert!(!convo_2
+            .is_allowlisted(peerdb_2.conn(), &public_key_1)
+            .unwrap());
+        assert!(convo_1
+            .is_allowlisted(peerdb_1.conn(), &public_key_1)
+            .unwrap());
+
+        // convo_1 sends a valid handshake to convo_2, but isn't on convo_2's allowlist
+        let handshake_data_1 = HandshakeData::from_local_peer(&local_peer_1);
+        let handshake_1 = convo_1
+            .sign_message(
+                &chain_view,
+                &local_peer_1.private_key,
+                StacksMessageType::Handshake(handshake_data_1.clone()),
+            )
+            .unwrap();
+
+        let mut rh_1 = convo_1.send_signed_request(handshake_1, 1000000).unwrap();
+
+        // convo_2 receives it and automatically rejects it.
+        convo_send_recv(&mut convo_1, vec![&mut rh_1], &mut convo_2);
+        let unhandled_2 = convo_2
+            .chat(
+                &local_peer_2,
+                &mut peerdb_2,
+                &sortdb_2,
+                &mut chainstate_2,
+                &mut BlockHeaderCache::new(),
+                &chain_view,
+            )
+            .unwrap();
+
+        convo_send_recv(&mut convo_2, vec![&mut rh_1], &mut convo_1);
+        let unhandled_1 = convo_1
+            .chat(
+                &local_peer_1,
+                &mut peerdb_1,
+                &sortdb_1,
+                &mut chainstate_1,
+                &mut BlockHeaderCache::new(),
+                &chain_view,
+            )
+            .unwrap();
+
+        let reply_1 = rh_1.recv(0).unwrap();
+
+        assert_eq!(unhandled_1.len(), 0);
+        assert_eq!(unhandled_2.len(), 0);
+
+        match reply_1.payload {
+            StacksMessageType::HandshakeReject => {}
+            _ => {
+                assert!(false);
+            }
+        };
+        assert!(convo_2.connection.get_public_key().is_none());
+
+        // allowlist convo_1's key, and try again
+        {
+            let mut tx = peerdb_2.tx_begin().unwrap();
+            PeerDB::add_allowlisted_peer(&mut tx, &public_key_1).unwrap();
+            tx.commit().unwrap();
+        }
+        assert!(convo_2
+            .is_allowlisted(peerdb_2.conn(), &public_key_1)
+            .unwrap());
+
+        let handshake_1 = convo_1
+            .sign_message(
+                &chain_view,
+                &local_peer_1.private_key,
+                StacksMessageType::Handshake(handshake_data_1.clone()),
+            )
+            .unwrap();
+
+        let mut rh_1 = convo_1.send_signed_request(handshake_1, 1000000).unwrap();
+
+        convo_send_recv(&mut convo_1, vec![&mut rh_1], &mut convo_2);
+        let unhandled_2 = convo_2
+            .chat(
+                &local_peer_2,
+                &mut peerdb_2,
+                &sortdb_2,
+                &mut chainstate_2,
+                &mut BlockHeaderCache::new(),
+                &chain_view,
+            )
+            .unwrap();
+
+        convo_send_recv(&mut convo_2, vec![&mut rh_1], &mut convo_1);
+        let unhandled_1 = convo_1
+            .chat(
+                &local_peer_1,
+                &mut peerdb_1,
+                &sortdb_1,
+                &mut chainstate_1,
+                &mut BlockHeaderCache::new(),
+                &chain_view,
+            )
+            .unwrap();
+
+        let reply_1 = rh_1.recv(0).unwrap();
+
+        assert_eq!(unhandled_1.len(), 0);
+        assert_eq!(unhandled_2.len(), 1);
+
+        match reply_1.payload {
+            StacksMessageType::HandshakeAccept(..) => {}
+            _ => {
+                assert!(false);
+            }
+        };
+        assert_eq!(convo_2.connection.get_public_key().unwrap(), public_key_1);
+    }
+
     #[test]
     fn convo_handshake_badsignature() {
         let conn_opts = ConnectionOptions::default();
@@ -3217,6 +3506,171 @@ mod test {
         }
     }
 
+    #[test]
+    fn convo_block_proposal() {
+        let socketaddr_1 = SocketAddr::new(IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1)), 8080);
+        let socketaddr_2 = SocketAddr::new(IpAddr::V4(Ipv4Addr::new(1, 2, 3, 4)), 8081);
+
+        let burnchain = testing_burnchain_config();
+
+        let mut chain_view = BurnchainView {
+            burn_block_height: 12348,
+            burn_block_hash: BurnchainHeaderHash([0x11; 32]),
+            burn_stable_block_height: 12341,
+            burn_stable_block_hash: BurnchainHeaderHash([0x22; 32]),
+            last_burn_block_hashes: HashMap::new(),
+        };
+        chain_view.make_test_data();
+
+        let (mut peerdb_1, mut sortdb_1, mut chainstate_1) = make_test_chain_dbs(
+            "convo_block_proposal_1",
+            &burnchain,
+            0x9abcdef0,
+            12350,
+            "http://peer1.com".into(),
+            &vec![],
+            &vec![],
+        );
+        let (mut peerdb_2, mut sortdb_2, mut chainstate_2) = make_test_chain_dbs(
+            "convo_block_proposal_2",
+            &burnchain,
+            0x9abcdef0,
+            12351,
+            "http://peer2.com".into(),
+            &vec![],
+            &vec![],
+        );
+
+        db_setup(&mut peerdb_1, &mut sortdb_1, &socketaddr_1, &chain_view);
+        db_setup(&mut peerdb_2, &mut sortdb_2, &socketaddr_2, &chain_view);
+
+        let local_peer_1 = PeerDB::get_local_peer(&peerdb_1.conn()).unwrap();
+        let local_peer_2 = PeerDB::get_local_peer(&peerdb_2.conn()).unwrap();
+
+        let proposal = SignedProposal {
+            message: "00".to_string(),
+            signature: MessageSignature::empty(),
+        };
+
+        for is_federation_peer in [false, true] {
+            let conn_opts_1 = ConnectionOptions::default();
+            let mut conn_opts_2 = ConnectionOptions::default();
+            if is_federation_peer {
+                conn_opts_2.federation_peers =
+                    vec![Secp256k1PublicKey::from_private(&local_peer_1.private_key)];
+            }
+
+            let mut convo_1 = ConversationP2P::new(
+                123,
+                456,
+                &burnchain,
+                &socketaddr_2,
+                &conn_opts_1,
+                true,
+                0,
+                StacksEpoch::unit_test_pre_2_05(0),
+            );
+            let mut convo_2 = ConversationP2P::new(
+                123,
+                456,
+                &burnchain,
+                &socketaddr_1,
+                &conn_opts_2,
+                true,
+                0,
+                StacksEpoch::unit_test_pre_2_05(0),
+            );
+
+            // convo_1 handshakes with convo_2, so convo_2 knows who it is
+            let handshake_1 = convo_1
+                .sign_message(
+                    &chain_view,
+                    &local_peer_1.private_key,
+                    StacksMessageType::Handshake(HandshakeData::from_local_peer(&local_peer_1)),
+                )
+                .unwrap();
+            let mut rh_handshake_1 = convo_1.send_signed_request(handshake_1, 1000000).unwrap();
+
+            convo_send_recv(&mut convo_1, vec![&mut rh_handshake_1], &mut convo_2);
+            convo_2
+                .chat(
+                    &local_peer_2,
+                    &mut peerdb_2,
+                    &sortdb_2,
+                    &mut chainstate_2,
+                    &mut BlockHeaderCache::new(),
+                    &chain_view,
+                )
+                .unwrap();
+            convo_send_recv(&mut convo_2, vec![&mut rh_handshake_1], &mut convo_1);
+            convo_1
+                .chat(
+                    &local_peer_1,
+                    &mut peerdb_1,
+                    &sortdb_1,
+                    &mut chainstate_1,
+                    &mut BlockHeaderCache::new(),
+                    &chain_view,
+                )
+                .unwrap();
+            rh_handshake_1.recv(0).unwrap();
+            assert_eq!(convo_2.is_federation_peer(), is_federation_peer);
+
+            // convo_1 proposes a block to convo_2
+            let proposal_1 = convo_1
+                .sign_message(
+                    &chain_view,
+                    &local_peer_1.private_key,
+                    StacksMessageType::BlockProposal(proposal.clone()),
+                )
+                .unwrap();
+            let mut rh_proposal_1 = convo_1.send_signed_request(proposal_1, 1000000).unwrap();
+
+            convo_send_recv(&mut convo_1, vec![&mut rh_proposal_1], &mut convo_2);
+            let unhandled_2 = convo_2
+                .chat(
+                    &local_peer_2,
+                    &mut peerdb_2,
+                    &sortdb_2,
+                    &mut chainstate_2,
+                    &mut BlockHeaderCache::new(),
+                    &chain_view,
+                )
+                .unwrap();
+
+            if is_federation_peer {
+                // passed along to the relayer, which answers it
+                assert_eq!(unhandled_2.len(), 1);
+                match unhandled_2[0].payload {
+                    StacksMessageType::BlockProposal(ref data) => assert_eq!(data, &proposal),
+                    ref payload => panic!("Unexpected message {:?}", payload),
+                }
+                continue;
+            }
+
+            assert_eq!(unhandled_2.len(), 0);
+            convo_send_recv(&mut convo_2, vec![&mut rh_proposal_1], &mut convo_1);
+            convo_1
+                .chat(
+                    &local_peer_1,
+                    &mut peerdb_1,
+                    &sortdb_1,
+                    &mut chainstate_1,
+                    &mut BlockHeaderCache::new(),
+                    &chain_view,
+                )
+                .unwrap();
+
+            let reply_1 = rh_proposal_1.recv(0).unwrap();
+            match reply_1.payload {
+                StacksMessageType::Nack(ref data) => {
+                    assert_eq!(data.error_code, NackErrorCodes::InvalidMessage);
+                }
+                _ => panic!("Unexpected reply {:?}", &reply_1.payload),
+            }
+        }
+    }
+
     #[test]
     fn convo_handshake_ping_loop() {
         let conn_opts = ConnectionOptions::default();
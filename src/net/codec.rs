@@ -30,6 +30,7 @@ use crate::burnchains::BurnchainView;
 use crate::burnchains::PrivateKey;
 use crate::burnchains::PublicKey;
 use crate::chainstate::burn::ConsensusHash;
+use crate::chainstate::stacks::miner::SignedProposal;
 use crate::chainstate::stacks::StacksBlock;
 use crate::chainstate::stacks::StacksMicroblock;
 use crate::chainstate::stacks::StacksPublicKey;
@@ -44,6 +45,7 @@ use stacks_common::util::hash::to_hex;
 use stacks_common::util::hash::DoubleSha256;
 use stacks_common::util::hash::Hash160;
 use stacks_common::util::hash::MerkleHashFunc;
+use stacks_common::util::hash::Sha256Sum;
 use stacks_common::util::log;
 use stacks_common::util::retry::BoundReader;
 use stacks_common::util::secp256k1::MessageSignature;
@@ -714,6 +716,71 @@ impl StacksMessageCodec for NatPunchData {
     }
 }
 
+impl StacksMessageCodec for SignedProposal {
+    fn consensus_serialize<W: Write>(&self, fd: &mut W) -> Result<(), codec_error> {
+        write_next(fd, &self.message.as_bytes().to_vec())?;
+        write_next(fd, &self.signature)?;
+        Ok(())
+    }
+
+    fn consensus_deserialize<R: Read>(fd: &mut R) -> Result<SignedProposal, codec_error> {
+        let message_bytes: Vec<u8> = read_next_at_most::<_, u8>(fd, MAX_MESSAGE_LEN)?;
+        let message = String::from_utf8(message_bytes).map_err(|_| {
+            codec_error::DeserializeError("Block proposal message is not UTF-8".to_string())
+        })?;
+        let signature: MessageSignature = read_next(fd)?;
+        Ok(SignedProposal { message, signature })
+    }
+}
+
+impl StacksMessageCodec for BlockProposalSignatureData {
+    fn consensus_serialize<W: Write>(&self, fd: &mut W) -> Result<(), codec_error> {
+        write_next(fd, &self.proposal_hash.0)?;
+        match self.response {
+            BlockProposalResponse::Accepted(ref signature) => {
+                write_next(fd, &BlockProposalResponseID::Accepted.to_u8())?;
+                fd.write_all(signature).map_err(codec_error::WriteError)?;
+            }
+            BlockProposalResponse::Rejected(ref reason) => {
+                write_next(fd, &BlockProposalResponseID::Rejected.to_u8())?;
+                write_next(fd, &reason.as_bytes().to_vec())?;
+            }
+        }
+        Ok(())
+    }
+
+    fn consensus_deserialize<R: Read>(
+        fd: &mut R,
+    ) -> Result<BlockProposalSignatureData, codec_error> {
+        let proposal_hash: [u8; 32] = read_next(fd)?;
+        let response_id: u8 = read_next(fd)?;
+        let response = match BlockProposalResponseID::from_u8(response_id).ok_or(
+            codec_error::DeserializeError(format!(
+                "Unrecognized BlockProposalResponseID {}",
+                &response_id
+            )),
+        )? {
+            BlockProposalResponseID::Accepted => {
+                let mut signature = [0u8; 65];
+                fd.read_exact(&mut signature)
+                    .map_err(codec_error::ReadError)?;
+                BlockProposalResponse::Accepted(signature)
+            }
+            BlockProposalResponseID::Rejected => {
+                let reason_bytes: Vec<u8> = read_next_at_most::<_, u8>(fd, MAX_MESSAGE_LEN)?;
+                let reason = String::from_utf8(reason_bytes).map_err(|_| {
+                    codec_error::DeserializeError("Rejection reason is not UTF-8".to_string())
+                })?;
+                BlockProposalResponse::Rejected(reason)
+            }
+        };
+        Ok(BlockProposalSignatureData {
+            proposal_hash: Sha256Sum(proposal_hash),
+            response,
+        })
+    }
+}
+
 impl StacksMessageCodec for MemPoolSyncData {
     fn consensus_serialize<W: Write>(&self, fd: &mut W) -> Result<(), codec_error> {
         match *self {
@@ -787,6 +854,10 @@ impl StacksMessageType {
             StacksMessageType::Pong(ref _m) => StacksMessageID::Pong,
             StacksMessageType::NatPunchRequest(ref _m) => StacksMessageID::NatPunchRequest,
             StacksMessageType::NatPunchReply(ref _m) => StacksMessageID::NatPunchReply,
+            StacksMessageType::BlockProposal(ref _m) => StacksMessageID::BlockProposal,
+            StacksMessageType::BlockProposalSignature(ref _m) => {
+                StacksMessageID::BlockProposalSignature
+            }
         }
     }
 
@@ -811,6 +882,8 @@ impl StacksMessageType {
             StacksMessageType::Pong(ref _m) => "Pong",
             StacksMessageType::NatPunchRequest(ref _m) => "NatPunchRequest",
             StacksMessageType::NatPunchReply(ref _m) => "NatPunchReply",
+            StacksMessageType::BlockProposal(ref _m) => "BlockProposal",
+            StacksMessageType::BlockProposalSignature(ref _m) => "BlockProposalSignature",
         }
     }
 
@@ -869,6 +942,18 @@ impl StacksMessageType {
             StacksMessageType::NatPunchReply(ref m) => {
                 format!("NatPunchReply({},{}:{})", m.nonce, &m.addrbytes, m.port)
             }
+            StacksMessageType::BlockProposal(ref m) => {
+                format!("BlockProposal({})", &m.message_hash())
+            }
+            StacksMessageType::BlockProposalSignature(ref m) => match m.response {
+                BlockProposalResponse::Accepted(_) => {
+                    format!("BlockProposalSignature({},accepted)", &m.proposal_hash)
+                }
+                BlockProposalResponse::Rejected(ref reason) => format!(
+                    "BlockProposalSignature({},rejected: {})",
+                    &m.proposal_hash, reason
+                ),
+            },
         }
     }
 }
@@ -902,6 +987,10 @@ impl StacksMessageCodec for StacksMessageID {
             x if x == StacksMessageID::Pong as u8 => StacksMessageID::Pong,
             x if x == StacksMessageID::NatPunchRequest as u8 => StacksMessageID::NatPunchRequest,
             x if x == StacksMessageID::NatPunchReply as u8 => StacksMessageID::NatPunchReply,
+            x if x == StacksMessageID::BlockProposal as u8 => StacksMessageID::BlockProposal,
+            x if x == StacksMessageID::BlockProposalSignature as u8 => {
+                StacksMessageID::BlockProposalSignature
+            }
             _ => {
                 return Err(codec_error::DeserializeError(
                     "Unknown message ID".to_string(),
@@ -935,6 +1024,8 @@ impl StacksMessageCodec for StacksMessageType {
             StacksMessageType::Pong(ref m) => write_next(fd, m)?,
             StacksMessageType::NatPunchRequest(ref nonce) => write_next(fd, nonce)?,
             StacksMessageType::NatPunchReply(ref m) => write_next(fd, m)?,
+            StacksMessageType::BlockProposal(ref m) => write_next(fd, m)?,
+            StacksMessageType::BlockProposalSignature(ref m) => write_next(fd, m)?,
         }
         Ok(())
     }
@@ -1012,6 +1103,14 @@ impl StacksMessageCodec for StacksMessageType {
                 let m: NatPunchData = read_next(fd)?;
                 StacksMessageType::NatPunchReply(m)
             }
+            StacksMessageID::BlockProposal => {
+                let m: SignedProposal = read_next(fd)?;
+                StacksMessageType::BlockProposal(m)
+            }
+            StacksMessageID::BlockProposalSignature => {
+                let m: BlockProposalSignatureData = read_next(fd)?;
+                StacksMessageType::BlockProposalSignature(m)
+            }
             StacksMessageID::Reserved => {
                 return Err(codec_error::DeserializeError(
                     "Unsupported message ID 'reserved'".to_string(),
@@ -1933,6 +2032,59 @@ pub mod test {
         check_codec_and_corruption::<NatPunchData>(&data, &bytes);
     }
 
+    #[test]
+    fn codec_BlockProposal() {
+        let data = SignedProposal {
+            message: "0a0b".to_string(),
+            signature: MessageSignature::from_raw(&vec![0x44; 65]),
+        };
+        let mut bytes = vec![
+            // message length
+            0x00, 0x00, 0x00, 0x04, // message
+            0x30, 0x61, 0x30, 0x62,
+        ];
+        // signature
+        bytes.extend_from_slice(&[0x44; 65]);
+
+        check_codec_and_corruption::<SignedProposal>(&data, &bytes);
+    }
+
+    #[test]
+    fn codec_BlockProposalSignature() {
+        let accepted = BlockProposalSignatureData {
+            proposal_hash: Sha256Sum([0x11; 32]),
+            response: BlockProposalResponse::Accepted([0x22; 65]),
+        };
+        let mut bytes = vec![0x11; 32];
+        // response ID
+        bytes.push(0x01);
+        // signature
+        bytes.extend_from_slice(&[0x22; 65]);
+
+        check_codec_and_corruption::<BlockProposalSignatureData>(&accepted, &bytes);
+
+        let rejected = BlockProposalSignatureData {
+            proposal_hash: Sha256Sum([0x11; 32]),
+            response: BlockProposalResponse::Rejected("bad".to_string()),
+        };
+        let mut bytes = vec![0x11; 32];
+        bytes.extend_from_slice(&[
+            // response ID
+            0x02, // reason length
+            0x00, 0x00, 0x00, 0x03, // reason
+            0x62, 0x61, 0x64,
+        ]);
+
+        check_codec_and_corruption::<BlockProposalSignatureData>(&rejected, &bytes);
+
+        // unknown response ID
+        let mut bad_bytes = vec![0x11; 32];
+        bad_bytes.push(0x03);
+        assert!(check_deserialize(
+            BlockProposalSignatureData::consensus_deserialize(&mut &bad_bytes[..])
+        ));
+    }
+
     #[test]
     fn codec_StacksMessage() {
         let payloads: Vec<StacksMessageType> = vec![
@@ -2049,6 +2201,18 @@ pub mod test {
                 port: 12345,
                 nonce: 0x12345678,
             }),
+            StacksMessageType::BlockProposal(SignedProposal {
+                message: "0a0b0c0d".to_string(),
+                signature: MessageSignature::from_raw(&vec![0x44; 65]),
+            }),
+            StacksMessageType::BlockProposalSignature(BlockProposalSignatureData {
+                proposal_hash: Sha256Sum([0x11; 32]),
+                response: BlockProposalResponse::Accepted([0x22; 65]),
+            }),
+            StacksMessageType::BlockProposalSignature(BlockProposalSignatureData {
+                proposal_hash: Sha256Sum([0x11; 32]),
+                response: BlockProposalResponse::Rejected("invalid block".to_string()),
+            }),
         ];
 
         let mut maximal_relayers: Vec<RelayData> = vec![];
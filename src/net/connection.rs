@@ -14,6 +14,7 @@
 // You should have received a copy of the GNU General Public License
 // along with this program.  If not, see <http://www.gnu.org/licenses/>.
 
+use std::collections::HashSet;
 use std::collections::VecDeque;
 use std::convert::TryFrom;
 use std::io;
@@ -264,6 +265,16 @@ impl<P: ProtocolFamily> NetworkReplyHandle<P> {
         self.request_pipe_write = fd_opt;
         Ok(self.request_pipe_write.is_none())
     }
+
+    /// Try to move buffered data along without dropping the inner pipe writer, for replies that
+    /// are sent over time and aren't done yet.
+    /// Return true if all buffered data was flushed.
+    pub fn try_flush_buffered(&mut self) -> Result<bool, net_error> {
+        match self.request_pipe_write {
+            Some(ref mut fd) => fd.try_flush().map_err(net_error::WriteError),
+            None => Ok(true),
+        }
+    }
 }
 
 impl<P: ProtocolFamily> Write for NetworkReplyHandle<P> {
@@ -404,10 +415,21 @@ pub struct ConnectionOptions {
     pub subnet_validator: Option<Secp256k1PrivateKey>,
     /// the contract used to submit multiparty commits (if a validator)
     pub subnet_signing_contract: Option<QualifiedContractIdentifier>,
-    // Set of participants allowed to propose blocks when using `MultiMiner`
-    // TODO: This should be `HashSet` or `BTreeSet` for more efficient lookup
-    //       Using these types requires deriving `Hash` or `Ord` on `Secp256k1PublicKey`
-    pub allowed_block_proposers: Vec<Secp256k1PublicKey>,
+    /// Participants allowed to propose blocks when using `MultiMiner`, by their compressed
+    /// public keys (`Secp256k1PublicKey` is neither `Hash` nor `Ord`)
+    pub allowed_block_proposers: HashSet<Vec<u8>>,
+    /// P2P public keys of the other members of the block-signing federation. Block proposals
+    /// and their signatures are only exchanged with these peers, once authenticated.
+    pub federation_peers: Vec<Secp256k1PublicKey>,
+    /// If true, only talk to peers whose public keys are on the peer DB's allowlist: other
+    /// peers' handshakes are rejected, and they are neither walked to nor reported to neighbors.
+    pub permissioned: bool,
+    /// Public keys added to the peer DB's allowlist when the node starts.  The allowlist can
+    /// also be edited at runtime over RPC, if `peer_allowlist_admin_token` is set.
+    pub peer_allowlist: Vec<Secp256k1PublicKey>,
+    /// The token a `POST /v2/neighbors/allowlist` must send in its `Authorization` header.  If
+    /// unset, the allowlist cannot be edited over RPC.
+    pub peer_allowlist_admin_token: Option<String>,
 }
 
 impl std::default::Default for ConnectionOptions {
@@ -496,7 +518,11 @@ impl std::default::Default for ConnectionOptions {
             force_disconnect_interval: None,
             subnet_validator: None,
             subnet_signing_contract: None,
-            allowed_block_proposers: Vec::default(),
+            allowed_block_proposers: HashSet::default(),
+            federation_peers: Vec::default(),
+            permissioned: false,
+            peer_allowlist: Vec::default(),
+            peer_allowlist_admin_token: None,
         }
     }
 }
@@ -64,7 +64,7 @@ use crate::core::NETWORK_P2P_PORT;
 
 use crate::util_lib::strings::UrlString;
 
-pub const PEERDB_VERSION: &'static str = "1";
+pub const PEERDB_VERSION: &'static str = "2";
 
 const NUM_SLOTS: usize = 8;
 
@@ -360,6 +360,12 @@ const PEERDB_INITIAL_SCHEMA: &'static [&'static str] = &[
     );"#,
 ];
 
+// Public keys of the only peers a node in permissioned mode will talk to
+const PEERDB_SCHEMA_2: &'static [&'static str] = &[r#"
+    CREATE TABLE peer_allowlist(
+        public_key TEXT PRIMARY KEY NOT NULL
+    );"#];
+
 const PEERDB_INDEXES: &'static [&'static str] =
     &["CREATE INDEX IF NOT EXISTS peer_address_index ON frontier(network_id,addrbytes,port);"];
 
@@ -397,6 +403,9 @@ impl PeerDB {
         for row_text in PEERDB_INITIAL_SCHEMA {
             tx.execute_batch(row_text).map_err(db_error::SqliteError)?;
         }
+        for row_text in PEERDB_SCHEMA_2 {
+            tx.execute_batch(row_text).map_err(db_error::SqliteError)?;
+        }
 
         tx.execute(
             "INSERT INTO db_config (version) VALUES (?1)",
@@ -447,6 +456,29 @@ impl PeerDB {
         Ok(())
     }
 
+    /// Get the database schema version
+    fn get_schema_version(conn: &DBConn) -> Result<String, db_error> {
+        let version = conn
+            .query_row("SELECT MAX(version) FROM db_config", NO_PARAMS, |row| {
+                row.get(0)
+            })
+            .map_err(db_error::SqliteError)?;
+        Ok(version)
+    }
+
+    /// Bring an existing database up to the current schema version
+    fn apply_schema_migrations<'a>(tx: &mut Transaction<'a>) -> Result<(), db_error> {
+        let version = PeerDB::get_schema_version(tx)?;
+        if version == "1" {
+            for row_text in PEERDB_SCHEMA_2 {
+                tx.execute_batch(row_text).map_err(db_error::SqliteError)?;
+            }
+            tx.execute("UPDATE db_config SET version = ?1", &["2"])
+                .map_err(db_error::SqliteError)?;
+        }
+        Ok(())
+    }
+
     fn add_indexes(&mut self) -> Result<(), db_error> {
         let tx = self.tx_begin()?;
         for row_text in PEERDB_INDEXES {
@@ -584,6 +616,9 @@ impl PeerDB {
 
             {
                 let mut tx = db.tx_begin()?;
+                if readwrite {
+                    PeerDB::apply_schema_migrations(&mut tx)?;
+                }
                 PeerDB::refresh_allows(&mut tx)?;
                 PeerDB::refresh_denies(&mut tx)?;
                 PeerDB::clear_initial_peers(&mut tx)?;
@@ -1169,6 +1204,67 @@ impl PeerDB {
         Ok(ret)
     }
 
+    /// Get the public keys of all peers on the permissioned-mode allowlist
+    pub fn get_allowlisted_peers(conn: &DBConn) -> Result<Vec<Secp256k1PublicKey>, db_error> {
+        let mut stmt = conn.prepare("SELECT public_key FROM peer_allowlist ORDER BY public_key")?;
+        let rows_res_iter = stmt
+            .query_and_then(NO_PARAMS, |row| {
+                let mut public_key = Secp256k1PublicKey::from_column(row, "public_key")?;
+                public_key.set_compressed(true);
+                let res: Result<Secp256k1PublicKey, db_error> = Ok(public_key);
+                res
+            })
+            .map_err(db_error::SqliteError)?;
+
+        let mut ret = vec![];
+        for row_res in rows_res_iter {
+            ret.push(row_res?);
+        }
+
+        Ok(ret)
+    }
+
+    /// Is a peer on the permissioned-mode allowlist?
+    pub fn is_peer_allowlisted(
+        conn: &DBConn,
+        public_key: &Secp256k1PublicKey,
+    ) -> Result<bool, db_error> {
+        let args: &[&dyn ToSql] = &[&to_hex(&public_key.to_bytes_compressed())];
+        let count = query_count(
+            conn,
+            "SELECT COUNT(*) FROM peer_allowlist WHERE public_key = ?1",
+            args,
+        )?;
+        Ok(count > 0)
+    }
+
+    /// Add a peer to the permissioned-mode allowlist
+    pub fn add_allowlisted_peer<'a>(
+        tx: &mut Transaction<'a>,
+        public_key: &Secp256k1PublicKey,
+    ) -> Result<(), db_error> {
+        let args: &[&dyn ToSql] = &[&to_hex(&public_key.to_bytes_compressed())];
+        tx.execute(
+            "INSERT OR REPLACE INTO peer_allowlist (public_key) VALUES (?1)",
+            args,
+        )
+        .map_err(db_error::SqliteError)?;
+        Ok(())
+    }
+
+    /// Remove a peer from the permissioned-mode allowlist.
+    /// Returns whether or not it was on the list.
+    pub fn remove_allowlisted_peer<'a>(
+        tx: &mut Transaction<'a>,
+        public_key: &Secp256k1PublicKey,
+    ) -> Result<bool, db_error> {
+        let args: &[&dyn ToSql] = &[&to_hex(&public_key.to_bytes_compressed())];
+        let num_removed = tx
+            .execute("DELETE FROM peer_allowlist WHERE public_key = ?1", args)
+            .map_err(db_error::SqliteError)?;
+        Ok(num_removed > 0)
+    }
+
     /// Get all deny CIDR prefixes
     pub fn get_denied_cidrs(conn: &DBConn) -> Result<Vec<(PeerAddress, u32)>, db_error> {
         PeerDB::get_cidr_prefixes(conn, "denied_prefixes")
@@ -1345,6 +1441,27 @@ impl PeerDB {
         PeerDB::get_random_neighbors(conn, network_id, network_epoch, count, block_height, false)
     }
 
+    /// Get a randomized set of fresh peers on the permissioned-mode allowlist.
+    /// Used in place of get_random_walk_neighbors() in permissioned mode.
+    pub fn get_random_allowlisted_neighbors(
+        conn: &DBConn,
+        network_id: u32,
+        network_epoch: u8,
+        count: u32,
+        block_height: u64,
+    ) -> Result<Vec<Neighbor>, db_error> {
+        let qry = "SELECT * FROM frontier WHERE network_id = ?1 AND last_contact_time >= 0 AND ?2 < expire_block_height AND denied < ?3 AND \
+                 (peer_version & 0x000000ff) >= ?4 AND public_key IN (SELECT public_key FROM peer_allowlist) ORDER BY RANDOM() LIMIT ?5";
+        let args: &[&dyn ToSql] = &[
+            &network_id,
+            &u64_to_sql(block_height)?,
+            &u64_to_sql(util::get_epoch_time_secs())?,
+            &network_epoch,
+            &count,
+        ];
+        query_rows::<Neighbor, _>(conn, qry, args)
+    }
+
     /// Add an IPv4 <--> ASN mapping
     /// Used during db instantiation
     fn asn4_insert<'a>(tx: &mut Transaction<'a>, asn4: &ASEntry4) -> Result<(), db_error> {
@@ -1949,6 +2066,82 @@ mod test {
         assert_eq!(peer_allowed.allowed, 20000000);
     }
 
+    #[test]
+    fn test_peer_allowlist() {
+        let mut db = PeerDB::connect_memory(
+            0x9abcdef0,
+            12345,
+            0,
+            "http://foo.com".into(),
+            &vec![],
+            &vec![],
+        )
+        .unwrap();
+
+        let mut neighbors = vec![];
+        for i in 0..2u8 {
+            let privkey = Secp256k1PrivateKey::new();
+            let mut public_key = Secp256k1PublicKey::from_private(&privkey);
+            public_key.set_compressed(true);
+            neighbors.push(Neighbor {
+                addr: NeighborKey {
+                    peer_version: 0x12345678,
+                    network_id: 0x9abcdef0,
+                    addrbytes: PeerAddress([i + 1; 16]),
+                    port: 12345,
+                },
+                public_key,
+                expire_block: 23456,
+                last_contact_time: 1552509642,
+                allowed: 0,
+                denied: 0,
+                asn: 34567,
+                org: 45678,
+                in_degree: 1,
+                out_degree: 1,
+            });
+        }
+
+        {
+            let mut tx = db.tx_begin().unwrap();
+            for (i, neighbor) in neighbors.iter().enumerate() {
+                PeerDB::insert_or_replace_peer(&mut tx, neighbor, i as u32).unwrap();
+            }
+            PeerDB::add_allowlisted_peer(&mut tx, &neighbors[0].public_key).unwrap();
+            // idempotent
+            PeerDB::add_allowlisted_peer(&mut tx, &neighbors[0].public_key).unwrap();
+            tx.commit().unwrap();
+        }
+
+        assert_eq!(
+            PeerDB::get_allowlisted_peers(db.conn()).unwrap(),
+            vec![neighbors[0].public_key.clone()]
+        );
+        assert!(PeerDB::is_peer_allowlisted(db.conn(), &neighbors[0].public_key).unwrap());
+        assert!(!PeerDB::is_peer_allowlisted(db.conn(), &neighbors[1].public_key).unwrap());
+
+        // only allowlisted peers are sampled
+        let sampled =
+            PeerDB::get_random_allowlisted_neighbors(db.conn(), 0x9abcdef0, 0, 10, 0).unwrap();
+        assert_eq!(sampled, vec![neighbors[0].clone()]);
+        let sampled = PeerDB::get_random_walk_neighbors(db.conn(), 0x9abcdef0, 0, 10, 0).unwrap();
+        assert_eq!(sampled.len(), 2);
+
+        {
+            let mut tx = db.tx_begin().unwrap();
+            assert!(PeerDB::remove_allowlisted_peer(&mut tx, &neighbors[0].public_key).unwrap());
+            assert!(!PeerDB::remove_allowlisted_peer(&mut tx, &neighbors[1].public_key).unwrap());
+            tx.commit().unwrap();
+        }
+
+        assert!(PeerDB::get_allowlisted_peers(db.conn()).unwrap().is_empty());
+        assert!(
+            PeerDB::get_random_allowlisted_neighbors(db.conn(), 0x9abcdef0, 0, 10, 0)
+                .unwrap()
+                .is_empty()
+        );
+    }
+
     #[test]
     fn test_peer_cidr_lists() {
         let mut db = PeerDB::connect_memory(
@@ -0,0 +1,234 @@
+// Copyright (C) 2013-2020 Blockstack PBC, a public benefit corporation
+// Copyright (C) 2020-2022 Stacks Open Internet Foundation
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+use std::fmt;
+use std::io;
+use std::io::Write;
+use std::sync::mpsc::{Receiver, TryRecvError};
+use std::sync::{Arc, Mutex};
+
+use crate::chainstate::stacks::Error as ChainstateError;
+use stacks_common::util::get_epoch_time_secs;
+
+/// How long an event stream may go without sending anything before a keep-alive comment is
+/// sent. This must be shorter than the HTTP server's idle timeout.
+pub const EVENT_STREAM_HEARTBEAT_SECS: u64 = 5;
+
+/// A payload built by the event dispatcher, bound for an event stream subscriber
+#[derive(Debug, Clone, PartialEq)]
+pub struct EventStreamMessage {
+    /// The path the payload would be POSTed to on an event observer, e.g. `new_block`
+    pub path: String,
+    /// The JSON-serialized payload
+    pub payload: String,
+}
+
+impl EventStreamMessage {
+    /// Encode this message as a server-sent event, named after its path
+    pub fn to_sse_bytes(&self) -> Vec<u8> {
+        format!("event: {}\ndata: {}\n\n", self.path, self.payload).into_bytes()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum EventStreamError {
+    /// The given events key is not understood
+    InvalidEventKey(String),
+    /// The node already serves its maximum number of event streams
+    TooManySubscribers(usize),
+}
+
+impl fmt::Display for EventStreamError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            EventStreamError::InvalidEventKey(key) => write!(f, "Invalid events key: {}", key),
+            EventStreamError::TooManySubscribers(max) => {
+                write!(f, "Too many event stream subscribers (max {})", max)
+            }
+        }
+    }
+}
+
+/// Source of event streams for the RPC server.
+/// `events_keys` uses the same vocabulary as an event observer's `events_keys` setting.
+pub trait EventStreamProvider {
+    fn subscribe(
+        &self,
+        events_keys: &[String],
+    ) -> Result<Receiver<EventStreamMessage>, EventStreamError>;
+}
+
+/// State of an event stream being sent to an RPC client.
+/// The stream stays open until the provider drops its end of the subscription.
+#[derive(Debug, Clone)]
+pub struct EventStreamData {
+    receiver: Arc<Mutex<Receiver<EventStreamMessage>>>,
+    /// the message currently being written, and how much of it has been written
+    buf: Vec<u8>,
+    buf_ptr: usize,
+    /// when something was last written to the stream
+    last_write: u64,
+    /// whether or not the provider has closed the subscription
+    closed: bool,
+}
+
+impl PartialEq for EventStreamData {
+    fn eq(&self, other: &EventStreamData) -> bool {
+        Arc::ptr_eq(&self.receiver, &other.receiver)
+            && self.buf == other.buf
+            && self.buf_ptr == other.buf_ptr
+            && self.last_write == other.last_write
+            && self.closed == other.closed
+    }
+}
+
+impl EventStreamData {
+    pub fn new(receiver: Receiver<EventStreamMessage>) -> EventStreamData {
+        EventStreamData {
+            receiver: Arc::new(Mutex::new(receiver)),
+            buf: vec![],
+            buf_ptr: 0,
+            last_write: get_epoch_time_secs(),
+            closed: false,
+        }
+    }
+
+    /// Has the subscription been closed, and everything it carried been written?
+    pub fn is_closed(&self) -> bool {
+        self.closed && self.buf_ptr >= self.buf.len()
+    }
+
+    /// Write up to `count` bytes of pending events to `fd`.
+    /// Returns the number of bytes written, which is 0 if there is nothing to send right now.
+    /// Only once `is_closed()` is true does a 0-byte write mean the stream is over.
+    pub fn stream_to<W: Write>(&mut self, fd: &mut W, count: u64) -> Result<u64, ChainstateError> {
+        let mut num_written = 0;
+        while num_written < count {
+            if self.buf_ptr >= self.buf.len() {
+                let next = self
+                    .receiver
+                    .lock()
+                    .expect("FATAL: event stream receiver lock poisoned")
+                    .try_recv();
+                match next {
+                    Ok(message) => {
+                        self.buf = message.to_sse_bytes();
+                        self.buf_ptr = 0;
+                    }
+                    Err(TryRecvError::Empty) => break,
+                    Err(TryRecvError::Disconnected) => {
+                        self.closed = true;
+                        break;
+                    }
+                }
+            }
+
+            let end = self
+                .buf
+                .len()
+                .min(self.buf_ptr + (count - num_written) as usize);
+            let nw = match fd.write(&self.buf[self.buf_ptr..end]) {
+                Ok(nw) => nw,
+                Err(e) => {
+                    if e.kind() == io::ErrorKind::Interrupted {
+                        continue;
+                    } else if e.kind() == io::ErrorKind::WouldBlock {
+                        break;
+                    } else {
+                        return Err(ChainstateError::WriteError(e));
+                    }
+                }
+            };
+            if nw == 0 {
+                break;
+            }
+            self.buf_ptr += nw;
+            num_written += nw as u64;
+        }
+
+        let now = get_epoch_time_secs();
+        if num_written == 0
+            && !self.closed
+            && self.buf_ptr >= self.buf.len()
+            && self.last_write + EVENT_STREAM_HEARTBEAT_SECS <= now
+        {
+            // SSE comment line, ignored by clients
+            self.buf = b": keep-alive\n\n".to_vec();
+            self.buf_ptr = 0;
+            self.last_write = now;
+            return self.stream_to(fd, count);
+        }
+        if num_written > 0 {
+            self.last_write = now;
+        }
+        Ok(num_written)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::sync::mpsc::sync_channel;
+
+    #[test]
+    fn test_event_stream_to() {
+        let (sender, receiver) = sync_channel(4);
+        let mut stream = EventStreamData::new(receiver);
+
+        let mut out = vec![];
+        assert_eq!(stream.stream_to(&mut out, 4096).unwrap(), 0);
+        assert!(!stream.is_closed());
+
+        let message = EventStreamMessage {
+            path: "new_block".to_string(),
+            payload: "{\"block_height\":1}".to_string(),
+        };
+        sender.send(message.clone()).unwrap();
+        sender.send(message.clone()).unwrap();
+
+        // partial writes pick up where they left off
+        let mut expected = message.to_sse_bytes();
+        expected.extend(message.to_sse_bytes());
+        assert_eq!(stream.stream_to(&mut out, 10).unwrap(), 10);
+        let nw = stream.stream_to(&mut out, 4096).unwrap();
+        assert_eq!(nw as usize, expected.len() - 10);
+        assert_eq!(out, expected);
+        assert_eq!(
+            std::str::from_utf8(&out[0..message.to_sse_bytes().len()]).unwrap(),
+            "event: new_block\ndata: {\"block_height\":1}\n\n"
+        );
+
+        // closing the subscription ends the stream
+        drop(sender);
+        assert_eq!(stream.stream_to(&mut out, 4096).unwrap(), 0);
+        assert!(stream.is_closed());
+    }
+
+    #[test]
+    fn test_event_stream_heartbeat() {
+        let (_sender, receiver) = sync_channel::<EventStreamMessage>(1);
+        let mut stream = EventStreamData::new(receiver);
+        stream.last_write = 0;
+
+        let mut out = vec![];
+        let nw = stream.stream_to(&mut out, 4096).unwrap();
+        assert_eq!(&out[..], b": keep-alive\n\n");
+        assert_eq!(nw as usize, out.len());
+
+        // nothing more until the next heartbeat is due
+        assert_eq!(stream.stream_to(&mut out, 4096).unwrap(), 0);
+    }
+}
@@ -0,0 +1,67 @@
+// Copyright (C) 2013-2020 Blockstack PBC, a public benefit corporation
+// Copyright (C) 2020-2022 Stacks Open Internet Foundation
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+use crate::net::{RPCHealthComponent, RPCHealthResponse};
+
+/// Status reported by `/health/live` and `/health/ready` when every component is healthy
+pub const HEALTH_STATUS_OK: &'static str = "ok";
+/// Status reported by `/health/live` and `/health/ready` when some component is unhealthy
+pub const HEALTH_STATUS_UNAVAILABLE: &'static str = "unavailable";
+
+/// Source of health checks for the parts of the node the RPC server can't see for itself,
+/// such as the L1 observer and the miner.
+pub trait HealthCheckProvider {
+    /// Check whether the node is ready to serve. `sortition_tip_height` is the height of the
+    /// node's canonical sortition tip.
+    fn check_readiness(&self, sortition_tip_height: u64) -> Vec<RPCHealthComponent>;
+}
+
+impl RPCHealthComponent {
+    pub fn healthy(name: &str, message: String) -> RPCHealthComponent {
+        RPCHealthComponent {
+            name: name.to_string(),
+            healthy: true,
+            message,
+        }
+    }
+
+    pub fn unhealthy(name: &str, message: String) -> RPCHealthComponent {
+        RPCHealthComponent {
+            name: name.to_string(),
+            healthy: false,
+            message,
+        }
+    }
+}
+
+impl RPCHealthResponse {
+    /// The node is healthy if all of its components are
+    pub fn from_components(components: Vec<RPCHealthComponent>) -> RPCHealthResponse {
+        let status = if components.iter().all(|component| component.healthy) {
+            HEALTH_STATUS_OK
+        } else {
+            HEALTH_STATUS_UNAVAILABLE
+        };
+        RPCHealthResponse {
+            status: status.to_string(),
+            components,
+        }
+    }
+
+    pub fn is_healthy(&self) -> bool {
+        self.status == HEALTH_STATUS_OK
+    }
+}
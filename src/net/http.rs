@@ -17,6 +17,7 @@
  along with Blockstack. If not, see <http://www.gnu.org/licenses/>.
 */
 
+use std::cmp;
 use std::collections::{HashMap, HashSet};
 use std::convert::TryFrom;
 use std::fmt;
@@ -42,6 +43,7 @@ use crate::chainstate::stacks::miner::SignedProposal;
 use crate::chainstate::stacks::{
     StacksBlock, StacksMicroblock, StacksPublicKey, StacksTransaction,
 };
+use crate::clarity_vm::withdrawal::MAX_BATCH_WITHDRAWALS;
 use crate::deps::httparse;
 use crate::net::atlas::Attachment;
 use crate::net::ClientError;
@@ -60,17 +62,21 @@ use crate::net::MemPoolSyncData;
 use crate::net::MessageSequence;
 use crate::net::NeighborAddress;
 use crate::net::PeerAddress;
+use crate::net::PeerAllowlistRequestBody;
 use crate::net::PeerHost;
 use crate::net::ProtocolFamily;
+use crate::net::RPCConfirmedTransaction;
 use crate::net::StacksHttpMessage;
 use crate::net::StacksHttpPreamble;
 use crate::net::UnconfirmedTransactionResponse;
 use crate::net::UnconfirmedTransactionStatus;
+use crate::net::WithdrawalMultiProofRequestBody;
 use crate::net::HTTP_PREAMBLE_MAX_ENCODED_SIZE;
 use crate::net::HTTP_PREAMBLE_MAX_NUM_HEADERS;
 use crate::net::HTTP_REQUEST_ID_RESERVED;
 use crate::net::MAX_HEADERS;
 use crate::net::MAX_MICROBLOCKS_UNCONFIRMED;
+use crate::net::{BatchStxWithdrawal, BatchStxWithdrawalRequest};
 use crate::net::{CallReadOnlyRequestBody, TipRequest};
 use crate::net::{GetAttachmentResponse, GetAttachmentsInvResponse, PostTransactionRequestBody};
 use clarity::vm::ast::parser::v1::CLARITY_NAME_REGEX;
@@ -90,6 +96,7 @@ use stacks_common::util::hash::Hash160;
 use stacks_common::util::log;
 use stacks_common::util::retry::BoundReader;
 use stacks_common::util::retry::RetryReader;
+use stacks_common::util::secp256k1::Secp256k1PublicKey;
 
 use crate::chainstate::stacks::StacksBlockHeader;
 use crate::chainstate::stacks::TransactionPayload;
@@ -105,12 +112,28 @@ const MAX_BLOCK_PROPOSAL_LENGTH: u32 = 1024 * 1024 * 15;
 
 pub const PATH_STR_POST_BLOCK_PROPOSAL: &'static str = "/v2/block_proposal";
 
+/// Page size of `/v2/mempool/principal/:principal` when the client doesn't ask for one
+pub const DEFAULT_MEMPOOL_PRINCIPAL_PAGE_SIZE: u64 = 50;
+/// Largest page of `/v2/mempool/principal/:principal` a client can ask for
+pub const MAX_MEMPOOL_PRINCIPAL_PAGE_SIZE: u64 = 200;
+
+/// Page size of `/v2/blocks/list` when the client doesn't ask for one
+pub const DEFAULT_BLOCK_LIST_PAGE_SIZE: u64 = 20;
+/// Largest page of `/v2/blocks/list` a client can ask for
+pub const MAX_BLOCK_LIST_PAGE_SIZE: u64 = 50;
+
 lazy_static! {
     static ref PATH_GETINFO: Regex = Regex::new(r#"^/v2/info$"#).unwrap();
     static ref PATH_GETPOXINFO: Regex = Regex::new(r#"^/v2/pox$"#).unwrap();
     static ref PATH_GETNEIGHBORS: Regex = Regex::new(r#"^/v2/neighbors$"#).unwrap();
+    static ref PATH_PEER_ALLOWLIST: Regex = Regex::new(r#"^/v2/neighbors/allowlist$"#).unwrap();
+    static ref PATH_HEALTH_LIVE: Regex = Regex::new(r#"^/health/live$"#).unwrap();
+    static ref PATH_HEALTH_READY: Regex = Regex::new(r#"^/health/ready$"#).unwrap();
     static ref PATH_GETHEADERS: Regex = Regex::new(r#"^/v2/headers/([0-9]+)$"#).unwrap();
     static ref PATH_GETBLOCK: Regex = Regex::new(r#"^/v2/blocks/([0-9a-f]{64})$"#).unwrap();
+    static ref PATH_GETBLOCK_BY_HEIGHT: Regex =
+        Regex::new(r#"^/v2/blocks/by-height/([0-9]{1,20})$"#).unwrap();
+    static ref PATH_GETBLOCK_LIST: Regex = Regex::new(r#"^/v2/blocks/list$"#).unwrap();
     static ref PATH_GETMICROBLOCKS_INDEXED: Regex =
         Regex::new(r#"^/v2/microblocks/([0-9a-f]{64})$"#).unwrap();
     static ref PATH_GETMICROBLOCKS_CONFIRMED: Regex =
@@ -119,6 +142,8 @@ lazy_static! {
         Regex::new(r#"^/v2/microblocks/unconfirmed/([0-9a-f]{64})/([0-9]{1,5})$"#).unwrap();
     static ref PATH_GETTRANSACTION_UNCONFIRMED: Regex =
         Regex::new(r#"^/v2/transactions/unconfirmed/([0-9a-f]{64})$"#).unwrap();
+    static ref PATH_GETTRANSACTION: Regex =
+        Regex::new(r#"^/v2/transactions/([0-9a-f]{64})$"#).unwrap();
     static ref PATH_POSTTRANSACTION: Regex = Regex::new(r#"^/v2/transactions$"#).unwrap();
     static ref PATH_POST_FEE_RATE_ESIMATE: Regex = Regex::new(r#"^/v2/fees/transaction$"#).unwrap();
     static ref PATH_POSTBLOCK: Regex = Regex::new(r#"^/v2/blocks/upload/([0-9a-f]{40})$"#).unwrap();
@@ -140,6 +165,13 @@ lazy_static! {
          *PRINCIPAL_DATA_REGEX_STRING,  *STANDARD_PRINCIPAL_REGEX_STRING, *CONTRACT_NAME_REGEX_STRING
      ))
      .unwrap();
+    static ref PATH_GET_STX_TO_SUBNET_WITHDRAWAL: Regex = Regex::new(&format!(
+         "^/v2/withdrawal/stx-to-subnet/(?P<block_height>[0-9]+)/(?P<recipient>{})/(?P<withdrawal_id>[0-9]+)/(?P<contract_address>{})/(?P<contract_name>{})/(?P<amount>[0-9]+)$",
+         *PRINCIPAL_DATA_REGEX_STRING,  *STANDARD_PRINCIPAL_REGEX_STRING, *CONTRACT_NAME_REGEX_STRING
+     ))
+     .unwrap();
+    static ref PATH_POST_WITHDRAWAL_MULTIPROOF: Regex =
+        Regex::new(r#"^/v2/withdrawal/multiproof/(?P<block_height>[0-9]+)$"#).unwrap();
     static ref PATH_GET_ACCOUNT: Regex = Regex::new(&format!(
         "^/v2/accounts/(?P<principal>{})$",
         *PRINCIPAL_DATA_REGEX_STRING
@@ -181,6 +213,12 @@ lazy_static! {
         Regex::new(r#"^/v2/attachments/([0-9a-f]{40})$"#).unwrap();
     static ref PATH_POST_MEMPOOL_QUERY: Regex =
         Regex::new(r#"^/v2/mempool/query$"#).unwrap();
+    static ref PATH_GET_MEMPOOL_PRINCIPAL: Regex = Regex::new(&format!(
+        "^/v2/mempool/principal/(?P<principal>{})$",
+        *STANDARD_PRINCIPAL_REGEX_STRING
+    ))
+    .unwrap();
+    static ref PATH_GET_EVENT_STREAM: Regex = Regex::new("^/v2/events/stream$").unwrap();
     static ref PATH_OPTIONS_WILDCARD: Regex = Regex::new("^/v2/.{0,4096}$").unwrap();
 }
 
@@ -664,6 +702,15 @@ impl<'a, 'state, W: Write> HttpChunkedTransferWriter<'a, 'state, W> {
         to_copy
     }
 
+    /// Send whatever is buffered as a chunk of its own, without ending the stream.
+    /// Used by streams that produce data over time, so it doesn't sit in the buffer.
+    pub fn send_buffered_chunk(&mut self) -> io::Result<usize> {
+        if self.state.corked || self.state.chunk_buf.len() == 0 {
+            return Ok(0);
+        }
+        self.flush_chunk()
+    }
+
     pub fn cork(&mut self) -> () {
         // block future flushes from sending trailing empty chunks -- we're done sending
         self.state.corked = true;
@@ -1528,6 +1575,16 @@ impl HttpRequestType {
             ),
             ("GET", &PATH_GETHEADERS, &HttpRequestType::parse_getheaders),
             ("GET", &PATH_GETBLOCK, &HttpRequestType::parse_getblock),
+            (
+                "GET",
+                &PATH_GETBLOCK_BY_HEIGHT,
+                &HttpRequestType::parse_getblock_by_height,
+            ),
+            (
+                "GET",
+                &PATH_GETBLOCK_LIST,
+                &HttpRequestType::parse_getblock_list,
+            ),
             (
                 "GET",
                 &PATH_GETMICROBLOCKS_INDEXED,
@@ -1548,6 +1605,11 @@ impl HttpRequestType {
                 &PATH_GETTRANSACTION_UNCONFIRMED,
                 &HttpRequestType::parse_gettransaction_unconfirmed,
             ),
+            (
+                "GET",
+                &PATH_GETTRANSACTION,
+                &HttpRequestType::parse_gettransaction,
+            ),
             (
                 "POST",
                 &PATH_POST_FEE_RATE_ESIMATE,
@@ -1624,6 +1686,11 @@ impl HttpRequestType {
                 &PATH_POST_MEMPOOL_QUERY,
                 &HttpRequestType::parse_post_mempool_query,
             ),
+            (
+                "GET",
+                &PATH_GET_EVENT_STREAM,
+                &HttpRequestType::parse_get_event_stream,
+            ),
             (
                 "GET",
                 &PATH_GET_STX_WITHDRAWAL,
@@ -1644,6 +1711,41 @@ impl HttpRequestType {
                 &PATH_GET_NFT_WITHDRAWAL,
                 &HttpRequestType::parse_get_nft_withdrawal,
             ),
+            (
+                "GET",
+                &PATH_GET_STX_TO_SUBNET_WITHDRAWAL,
+                &HttpRequestType::parse_get_stx_to_subnet_withdrawal,
+            ),
+            (
+                "POST",
+                &PATH_POST_WITHDRAWAL_MULTIPROOF,
+                &HttpRequestType::parse_post_withdrawal_multiproof,
+            ),
+            (
+                "GET",
+                &PATH_PEER_ALLOWLIST,
+                &HttpRequestType::parse_get_peer_allowlist,
+            ),
+            (
+                "GET",
+                &PATH_HEALTH_LIVE,
+                &HttpRequestType::parse_get_health_live,
+            ),
+            (
+                "GET",
+                &PATH_HEALTH_READY,
+                &HttpRequestType::parse_get_health_ready,
+            ),
+            (
+                "POST",
+                &PATH_PEER_ALLOWLIST,
+                &HttpRequestType::parse_post_peer_allowlist,
+            ),
+            (
+                "GET",
+                &PATH_GET_MEMPOOL_PRINCIPAL,
+                &HttpRequestType::parse_get_mempool_principal,
+            ),
         ];
 
         // use url::Url to parse path and query string
@@ -1787,6 +1889,38 @@ impl HttpRequestType {
         }
     }
 
+    /// get the chain tip optional query argument (`tip`), or the `height` of an anchored block in
+    /// the canonical fork to use as the tip instead.  Giving both is an error.
+    fn get_chain_tip_or_height_query(query: Option<&str>) -> Result<TipRequest, net_error> {
+        let tip = HttpRequestType::get_chain_tip_query(query);
+        let query_string = match query {
+            Some(query_string) => query_string,
+            None => return Ok(tip),
+        };
+
+        let mut height = None;
+        let mut has_tip = false;
+        for (key, value) in form_urlencoded::parse(query_string.as_bytes()) {
+            match key.as_ref() {
+                "height" => {
+                    height = Some(value.parse::<u64>().map_err(|_e| {
+                        net_error::DeserializeError(format!("Invalid height: {}", value))
+                    })?);
+                }
+                "tip" => has_tip = true,
+                _ => continue,
+            }
+        }
+
+        match height {
+            Some(_) if has_tip => Err(net_error::DeserializeError(
+                "Cannot query by both tip and height".to_string(),
+            )),
+            Some(height) => Ok(TipRequest::AtHeight(height)),
+            None => Ok(tip),
+        }
+    }
+
     /// get the mempool page ID optional query argument (`page_id`)
     /// Take the first value we can parse.
     fn get_mempool_page_id_query(query: Option<&str>) -> Option<Txid> {
@@ -1826,7 +1960,7 @@ impl HttpRequestType {
         })?;
 
         let with_proof = HttpRequestType::get_proof_query(query);
-        let tip = HttpRequestType::get_chain_tip_query(query);
+        let tip = HttpRequestType::get_chain_tip_or_height_query(query)?;
 
         Ok(HttpRequestType::GetAccount(
             HttpRequestMetadata::from_preamble(preamble),
@@ -1959,6 +2093,254 @@ impl HttpRequestType {
         })
     }
 
+    fn parse_get_stx_to_subnet_withdrawal<R: Read>(
+        _protocol: &mut StacksHttp,
+        preamble: &HttpRequestPreamble,
+        captures: &Captures,
+        _query: Option<&str>,
+        _fd: &mut R,
+    ) -> Result<HttpRequestType, net_error> {
+        if preamble.get_content_length() != 0 {
+            return Err(net_error::DeserializeError(
+                "Invalid Http request: expected 0-length body for GetStxToSubnetWithdrawal"
+                    .to_string(),
+            ));
+        }
+
+        let recipient = PrincipalData::parse(&captures["recipient"]).map_err(|_e| {
+            net_error::DeserializeError("Failed to parse recipient principal".into())
+        })?;
+
+        let withdraw_block_height = u64::from_str(&captures["block_height"])
+            .map_err(|_e| net_error::DeserializeError("Failed to parse block height".into()))?;
+
+        let withdrawal_id = u32::from_str(&captures["withdrawal_id"])
+            .map_err(|_e| net_error::DeserializeError("Failed to parse withdrawal id".into()))?;
+        let contract_addr =
+            StacksAddress::from_string(&captures["contract_address"]).ok_or_else(|| {
+                net_error::DeserializeError("Failed to parse contract address".into())
+            })?;
+        let contract_name = ContractName::try_from(captures["contract_name"].to_string())
+            .map_err(|_e| net_error::DeserializeError("Failed to parse contract name".into()))?;
+        let amount = u128::from_str(&captures["amount"])
+            .map_err(|_e| net_error::DeserializeError("Failed to parse amount".into()))?;
+
+        Ok(HttpRequestType::GetWithdrawalStxToSubnet {
+            metadata: HttpRequestMetadata::from_preamble(preamble),
+            withdraw_block_height,
+            recipient,
+            withdrawal_id,
+            destination: QualifiedContractIdentifier::new(contract_addr.into(), contract_name),
+            amount,
+        })
+    }
+
+    fn parse_post_withdrawal_multiproof<R: Read>(
+        _protocol: &mut StacksHttp,
+        preamble: &HttpRequestPreamble,
+        captures: &Captures,
+        _query: Option<&str>,
+        fd: &mut R,
+    ) -> Result<HttpRequestType, net_error> {
+        let content_len = preamble.get_content_length();
+        if !(content_len > 0 && content_len < MAX_PAYLOAD_LEN) {
+            return Err(net_error::DeserializeError(format!(
+                "Invalid Http request: invalid body length for WithdrawalMultiProof ({})",
+                content_len
+            )));
+        }
+
+        if preamble.content_type != Some(HttpContentType::JSON) {
+            return Err(net_error::DeserializeError(
+                "Invalid content-type: expected application/json".to_string(),
+            ));
+        }
+
+        let withdraw_block_height = u64::from_str(&captures["block_height"])
+            .map_err(|_e| net_error::DeserializeError("Failed to parse block height".into()))?;
+
+        let bound_fd = BoundReader::from_reader(fd, content_len as u64);
+        let body: WithdrawalMultiProofRequestBody =
+            serde_json::from_reader(bound_fd).map_err(|e| {
+                net_error::DeserializeError(format!("Failed to parse JSON body: {}", e))
+            })?;
+
+        if body.withdrawals.is_empty() {
+            return Err(net_error::DeserializeError(
+                "Invalid Http request: no withdrawals supplied".to_string(),
+            ));
+        }
+        if body.withdrawals.len() > MAX_BATCH_WITHDRAWALS {
+            return Err(net_error::DeserializeError(format!(
+                "Invalid Http request: {} withdrawals supplied, but at most {} can be settled in a batch",
+                body.withdrawals.len(),
+                MAX_BATCH_WITHDRAWALS
+            )));
+        }
+
+        let mut withdrawals = Vec::with_capacity(body.withdrawals.len());
+        for withdrawal in body.withdrawals.into_iter() {
+            let recipient = PrincipalData::parse(&withdrawal.recipient).map_err(|_e| {
+                net_error::DeserializeError(format!(
+                    "Failed to parse recipient {}",
+                    &withdrawal.recipient
+                ))
+            })?;
+            withdrawals.push(BatchStxWithdrawal {
+                withdrawal_id: withdrawal.withdrawal_id,
+                recipient,
+                amount: withdrawal.amount,
+            });
+        }
+
+        Ok(HttpRequestType::PostWithdrawalMultiProof {
+            metadata: HttpRequestMetadata::from_preamble(preamble),
+            withdraw_block_height,
+            withdrawals,
+        })
+    }
+
+    fn parse_get_peer_allowlist<R: Read>(
+        _protocol: &mut StacksHttp,
+        preamble: &HttpRequestPreamble,
+        _captures: &Captures,
+        _query: Option<&str>,
+        _fd: &mut R,
+    ) -> Result<HttpRequestType, net_error> {
+        if preamble.get_content_length() != 0 {
+            return Err(net_error::DeserializeError(
+                "Invalid Http request: expected 0-length body".to_string(),
+            ));
+        }
+
+        Ok(HttpRequestType::GetPeerAllowlist(
+            HttpRequestMetadata::from_preamble(preamble),
+        ))
+    }
+
+    fn parse_get_health_live<R: Read>(
+        _protocol: &mut StacksHttp,
+        preamble: &HttpRequestPreamble,
+        _captures: &Captures,
+        _query: Option<&str>,
+        _fd: &mut R,
+    ) -> Result<HttpRequestType, net_error> {
+        if preamble.get_content_length() != 0 {
+            return Err(net_error::DeserializeError(
+                "Invalid Http request: expected 0-length body".to_string(),
+            ));
+        }
+
+        Ok(HttpRequestType::GetHealthLive(
+            HttpRequestMetadata::from_preamble(preamble),
+        ))
+    }
+
+    fn parse_get_health_ready<R: Read>(
+        _protocol: &mut StacksHttp,
+        preamble: &HttpRequestPreamble,
+        _captures: &Captures,
+        _query: Option<&str>,
+        _fd: &mut R,
+    ) -> Result<HttpRequestType, net_error> {
+        if preamble.get_content_length() != 0 {
+            return Err(net_error::DeserializeError(
+                "Invalid Http request: expected 0-length body".to_string(),
+            ));
+        }
+
+        Ok(HttpRequestType::GetHealthReady(
+            HttpRequestMetadata::from_preamble(preamble),
+        ))
+    }
+
+    fn parse_post_peer_allowlist<R: Read>(
+        _protocol: &mut StacksHttp,
+        preamble: &HttpRequestPreamble,
+        _captures: &Captures,
+        _query: Option<&str>,
+        fd: &mut R,
+    ) -> Result<HttpRequestType, net_error> {
+        let content_len = preamble.get_content_length();
+        if !(content_len > 0 && content_len < MAX_PAYLOAD_LEN) {
+            return Err(net_error::DeserializeError(format!(
+                "Invalid Http request: invalid body length for PeerAllowlist ({})",
+                content_len
+            )));
+        }
+
+        if preamble.content_type != Some(HttpContentType::JSON) {
+            return Err(net_error::DeserializeError(
+                "Invalid content-type: expected application/json".to_string(),
+            ));
+        }
+
+        let bound_fd = BoundReader::from_reader(fd, content_len as u64);
+        let body: PeerAllowlistRequestBody = serde_json::from_reader(bound_fd).map_err(|e| {
+            net_error::DeserializeError(format!("Failed to parse JSON body: {}", e))
+        })?;
+
+        let parse_public_keys = |public_keys: &[String]| {
+            public_keys
+                .iter()
+                .map(|public_key| {
+                    Secp256k1PublicKey::from_hex(public_key).map_err(|_e| {
+                        net_error::DeserializeError(format!("Invalid public key: {}", public_key))
+                    })
+                })
+                .collect::<Result<Vec<_>, net_error>>()
+        };
+        let add = parse_public_keys(&body.add)?;
+        let remove = parse_public_keys(&body.remove)?;
+
+        Ok(HttpRequestType::PostPeerAllowlist(
+            HttpRequestMetadata::from_preamble(preamble),
+            add,
+            remove,
+            preamble.headers.get("authorization").cloned(),
+        ))
+    }
+
+    fn parse_get_mempool_principal<R: Read>(
+        _protocol: &mut StacksHttp,
+        preamble: &HttpRequestPreamble,
+        captures: &Captures,
+        query: Option<&str>,
+        _fd: &mut R,
+    ) -> Result<HttpRequestType, net_error> {
+        if preamble.get_content_length() != 0 {
+            return Err(net_error::DeserializeError(
+                "Invalid Http request: expected 0-length body for GetMempoolPrincipal".to_string(),
+            ));
+        }
+
+        let principal = StacksAddress::from_string(&captures["principal"]).ok_or_else(|| {
+            net_error::DeserializeError("Failed to parse account principal".into())
+        })?;
+
+        let mut offset = 0;
+        let mut limit = DEFAULT_MEMPOOL_PRINCIPAL_PAGE_SIZE;
+        if let Some(query) = query {
+            for (key, value) in form_urlencoded::parse(query.as_bytes()) {
+                let target = match key.as_ref() {
+                    "offset" => &mut offset,
+                    "limit" => &mut limit,
+                    _ => continue,
+                };
+                *target = value.parse::<u64>().map_err(|_e| {
+                    net_error::DeserializeError(format!("Invalid {}: {}", key, value))
+                })?;
+            }
+        }
+
+        Ok(HttpRequestType::GetMempoolPrincipal(
+            HttpRequestMetadata::from_preamble(preamble),
+            principal,
+            offset,
+            cmp::min(limit, MAX_MEMPOOL_PRINCIPAL_PAGE_SIZE),
+        ))
+    }
+
     fn parse_get_data_var<R: Read>(
         _protocol: &mut StacksHttp,
         preamble: &HttpRequestPreamble,
@@ -1983,7 +2365,7 @@ impl HttpRequestType {
             .map_err(|_e| net_error::DeserializeError("Failed to parse data var name".into()))?;
 
         let with_proof = HttpRequestType::get_proof_query(query);
-        let tip = HttpRequestType::get_chain_tip_query(query);
+        let tip = HttpRequestType::get_chain_tip_or_height_query(query)?;
 
         Ok(HttpRequestType::GetDataVar(
             HttpRequestMetadata::from_preamble(preamble),
@@ -2031,7 +2413,7 @@ impl HttpRequestType {
             .map_err(|_e| net_error::DeserializeError("Failed to deserialize key value".into()))?;
 
         let with_proof = HttpRequestType::get_proof_query(query);
-        let tip = HttpRequestType::get_chain_tip_query(query);
+        let tip = HttpRequestType::get_chain_tip_or_height_query(query)?;
 
         Ok(HttpRequestType::GetMapEntry(
             HttpRequestMetadata::from_preamble(preamble),
@@ -2088,7 +2470,7 @@ impl HttpRequestType {
                 net_error::DeserializeError("Failed to deserialize argument value".into())
             })?;
 
-        let tip = HttpRequestType::get_chain_tip_query(query);
+        let tip = HttpRequestType::get_chain_tip_or_height_query(query)?;
 
         Ok(HttpRequestType::CallReadOnlyFunction(
             HttpRequestMetadata::from_preamble(preamble),
@@ -2285,6 +2667,90 @@ impl HttpRequestType {
         ))
     }
 
+    fn parse_getblock_by_height<R: Read>(
+        _protocol: &mut StacksHttp,
+        preamble: &HttpRequestPreamble,
+        captures: &Captures,
+        query: Option<&str>,
+        _fd: &mut R,
+    ) -> Result<HttpRequestType, net_error> {
+        if preamble.get_content_length() != 0 {
+            return Err(net_error::DeserializeError(
+                "Invalid Http request: expected 0-length body for GetBlockByHeight".to_string(),
+            ));
+        }
+
+        let height = captures
+            .get(1)
+            .ok_or(net_error::DeserializeError(
+                "Failed to match path to block height group".to_string(),
+            ))?
+            .as_str()
+            .parse::<u64>()
+            .map_err(|_| net_error::DeserializeError("Failed to parse block height".to_string()))?;
+
+        Ok(HttpRequestType::GetBlockByHeight(
+            HttpRequestMetadata::from_preamble(preamble),
+            height,
+            HttpRequestType::get_chain_tip_query(query),
+        ))
+    }
+
+    fn parse_getblock_list<R: Read>(
+        _protocol: &mut StacksHttp,
+        preamble: &HttpRequestPreamble,
+        _captures: &Captures,
+        query: Option<&str>,
+        _fd: &mut R,
+    ) -> Result<HttpRequestType, net_error> {
+        if preamble.get_content_length() != 0 {
+            return Err(net_error::DeserializeError(
+                "Invalid Http request: expected 0-length body for GetBlockList".to_string(),
+            ));
+        }
+
+        let mut start_height = None;
+        let mut limit = DEFAULT_BLOCK_LIST_PAGE_SIZE;
+        let mut json = false;
+        if let Some(query) = query {
+            for (key, value) in form_urlencoded::parse(query.as_bytes()) {
+                match key.as_ref() {
+                    "start_height" | "limit" => {
+                        let parsed = value.parse::<u64>().map_err(|_e| {
+                            net_error::DeserializeError(format!("Invalid {}: {}", key, value))
+                        })?;
+                        if key == "limit" {
+                            limit = parsed;
+                        } else {
+                            start_height = Some(parsed);
+                        }
+                    }
+                    "format" => {
+                        json = match value.as_ref() {
+                            "json" => true,
+                            "hex" => false,
+                            _ => {
+                                return Err(net_error::DeserializeError(format!(
+                                    "Invalid format: {}",
+                                    value
+                                )))
+                            }
+                        };
+                    }
+                    _ => continue,
+                }
+            }
+        }
+
+        Ok(HttpRequestType::GetBlockList(
+            HttpRequestMetadata::from_preamble(preamble),
+            start_height,
+            cmp::min(limit, MAX_BLOCK_LIST_PAGE_SIZE),
+            json,
+            HttpRequestType::get_chain_tip_query(query),
+        ))
+    }
+
     fn parse_getmicroblocks_indexed<R: Read>(
         _protocol: &mut StacksHttp,
         preamble: &HttpRequestPreamble,
@@ -2425,6 +2891,36 @@ impl HttpRequestType {
         ))
     }
 
+    fn parse_gettransaction<R: Read>(
+        _protocol: &mut StacksHttp,
+        preamble: &HttpRequestPreamble,
+        regex: &Captures,
+        query: Option<&str>,
+        _fd: &mut R,
+    ) -> Result<HttpRequestType, net_error> {
+        if preamble.get_content_length() != 0 {
+            return Err(net_error::DeserializeError(
+                "Invalid Http request: expected 0-length body for GetTransaction".to_string(),
+            ));
+        }
+
+        let txid_hex = regex
+            .get(1)
+            .ok_or(net_error::DeserializeError(
+                "Failed to match path to txid group".to_string(),
+            ))?
+            .as_str();
+
+        let txid = Txid::from_hex(&txid_hex)
+            .map_err(|_e| net_error::DeserializeError("Failed to decode txid hex".to_string()))?;
+
+        Ok(HttpRequestType::GetTransaction(
+            HttpRequestMetadata::from_preamble(preamble),
+            txid,
+            HttpRequestType::get_chain_tip_query(query),
+        ))
+    }
+
     fn parse_post_fee_rate_estimate<R: Read>(
         _protocol: &mut StacksHttp,
         preamble: &HttpRequestPreamble,
@@ -2785,6 +3281,46 @@ impl HttpRequestType {
         ))
     }
 
+    fn parse_get_event_stream<R: Read>(
+        _protocol: &mut StacksHttp,
+        preamble: &HttpRequestPreamble,
+        _captures: &Captures,
+        query: Option<&str>,
+        _fd: &mut R,
+    ) -> Result<HttpRequestType, net_error> {
+        if preamble.get_content_length() != 0 {
+            return Err(net_error::DeserializeError(
+                "Invalid Http request: expected 0-length body".to_string(),
+            ));
+        }
+
+        let mut events_keys = vec![];
+        if let Some(query) = query {
+            for (key, value) in form_urlencoded::parse(query.as_bytes()) {
+                if key == "events_keys" {
+                    events_keys.extend(
+                        value
+                            .split(",")
+                            .map(|events_key| events_key.trim())
+                            .filter(|events_key| !events_key.is_empty())
+                            .map(|events_key| events_key.to_string()),
+                    );
+                }
+            }
+        }
+
+        if events_keys.is_empty() {
+            return Err(net_error::DeserializeError(
+                "Invalid Http request: expecting events_keys".to_string(),
+            ));
+        }
+
+        Ok(HttpRequestType::GetEventStream(
+            HttpRequestMetadata::from_preamble(preamble),
+            events_keys,
+        ))
+    }
+
     fn parse_post_mempool_query<R: Read>(
         _protocol: &mut StacksHttp,
         preamble: &HttpRequestPreamble,
@@ -2855,6 +3391,7 @@ impl HttpRequestType {
             HttpRequestType::GetMicroblocksConfirmed(md, _) => md,
             HttpRequestType::GetMicroblocksUnconfirmed(md, _, _) => md,
             HttpRequestType::GetTransactionUnconfirmed(md, _) => md,
+            HttpRequestType::GetTransaction(md, ..) => md,
             HttpRequestType::PostTransaction(md, _, _) => md,
             HttpRequestType::PostBlock(md, ..) => md,
             HttpRequestType::PostMicroblock(md, ..) => md,
@@ -2870,12 +3407,22 @@ impl HttpRequestType {
             HttpRequestType::GetAttachmentsInv(md, ..) => md,
             HttpRequestType::GetAttachment(md, ..) => md,
             HttpRequestType::MemPoolQuery(md, ..) => md,
+            HttpRequestType::GetEventStream(md, ..) => md,
             HttpRequestType::FeeRateEstimate(md, _, _) => md,
             HttpRequestType::ClientError(md, ..) => md,
             HttpRequestType::GetWithdrawalStx { metadata, .. } => metadata,
             HttpRequestType::BlockProposal(metadata, ..) => metadata,
+            HttpRequestType::GetHealthLive(md) => md,
+            HttpRequestType::GetHealthReady(md) => md,
+            HttpRequestType::GetPeerAllowlist(md) => md,
+            HttpRequestType::PostPeerAllowlist(md, ..) => md,
+            HttpRequestType::GetMempoolPrincipal(md, ..) => md,
+            HttpRequestType::GetBlockByHeight(md, ..) => md,
+            HttpRequestType::GetBlockList(md, ..) => md,
             HttpRequestType::GetWithdrawalFt { metadata, .. } => metadata,
             HttpRequestType::GetWithdrawalNft { metadata, .. } => metadata,
+            HttpRequestType::GetWithdrawalStxToSubnet { metadata, .. } => metadata,
+            HttpRequestType::PostWithdrawalMultiProof { metadata, .. } => metadata,
         }
     }
 
@@ -2889,6 +3436,7 @@ impl HttpRequestType {
             HttpRequestType::GetMicroblocksConfirmed(ref mut md, _) => md,
             HttpRequestType::GetMicroblocksUnconfirmed(ref mut md, _, _) => md,
             HttpRequestType::GetTransactionUnconfirmed(ref mut md, _) => md,
+            HttpRequestType::GetTransaction(ref mut md, ..) => md,
             HttpRequestType::PostTransaction(ref mut md, _, _) => md,
             HttpRequestType::PostBlock(ref mut md, ..) => md,
             HttpRequestType::PostMicroblock(ref mut md, ..) => md,
@@ -2904,9 +3452,17 @@ impl HttpRequestType {
             HttpRequestType::GetAttachmentsInv(ref mut md, ..) => md,
             HttpRequestType::GetAttachment(ref mut md, ..) => md,
             HttpRequestType::MemPoolQuery(ref mut md, ..) => md,
+            HttpRequestType::GetEventStream(ref mut md, ..) => md,
             HttpRequestType::FeeRateEstimate(ref mut md, _, _) => md,
             HttpRequestType::ClientError(ref mut md, ..) => md,
             HttpRequestType::BlockProposal(ref mut metadata, ..) => metadata,
+            HttpRequestType::GetHealthLive(ref mut md) => md,
+            HttpRequestType::GetHealthReady(ref mut md) => md,
+            HttpRequestType::GetPeerAllowlist(ref mut md) => md,
+            HttpRequestType::PostPeerAllowlist(ref mut md, ..) => md,
+            HttpRequestType::GetMempoolPrincipal(ref mut md, ..) => md,
+            HttpRequestType::GetBlockByHeight(ref mut md, ..) => md,
+            HttpRequestType::GetBlockList(ref mut md, ..) => md,
             HttpRequestType::GetWithdrawalStx {
                 ref mut metadata, ..
             } => metadata,
@@ -2916,6 +3472,12 @@ impl HttpRequestType {
             HttpRequestType::GetWithdrawalNft {
                 ref mut metadata, ..
             } => metadata,
+            HttpRequestType::GetWithdrawalStxToSubnet {
+                ref mut metadata, ..
+            } => metadata,
+            HttpRequestType::PostWithdrawalMultiProof {
+                ref mut metadata, ..
+            } => metadata,
         }
     }
 
@@ -2934,6 +3496,13 @@ impl HttpRequestType {
                     "".to_string()
                 }
             }
+            TipRequest::AtHeight(height) => {
+                format!(
+                    "?height={}{}",
+                    height,
+                    if with_proof { "" } else { "&proof=0" }
+                )
+            }
         }
     }
 
@@ -2963,6 +3532,11 @@ impl HttpRequestType {
             HttpRequestType::GetTransactionUnconfirmed(_md, txid) => {
                 format!("/v2/transactions/unconfirmed/{}", txid)
             }
+            HttpRequestType::GetTransaction(_md, txid, tip_req) => format!(
+                "/v2/transactions/{}{}",
+                txid,
+                HttpRequestType::make_tip_query_string(tip_req, true)
+            ),
             HttpRequestType::PostTransaction(_md, ..) => "/v2/transactions".to_string(),
             HttpRequestType::PostBlock(_md, ch, ..) => format!("/v2/blocks/upload/{}", &ch),
             HttpRequestType::PostMicroblock(_md, _, tip_req) => format!(
@@ -3079,6 +3653,11 @@ impl HttpRequestType {
                 }
                 None => "/v2/mempool/query".to_string(),
             },
+            HttpRequestType::GetEventStream(_, events_keys) => format!(
+                "/v2/events/stream?events_keys={}",
+                form_urlencoded::byte_serialize(events_keys.join(",").as_bytes())
+                    .collect::<String>()
+            ),
             HttpRequestType::FeeRateEstimate(_, _, _) => self.get_path().to_string(),
             HttpRequestType::ClientError(_md, e) => match e {
                 ClientError::NotFound(path) => path.to_string(),
@@ -3095,6 +3674,34 @@ impl HttpRequestType {
                 withdraw_block_height, sender, withdrawal_id, amount
             ),
             HttpRequestType::BlockProposal(..) => self.get_path().to_string(),
+            HttpRequestType::GetPeerAllowlist(..) | HttpRequestType::PostPeerAllowlist(..) => {
+                self.get_path().to_string()
+            }
+            HttpRequestType::GetHealthLive(..) | HttpRequestType::GetHealthReady(..) => {
+                self.get_path().to_string()
+            }
+            HttpRequestType::GetMempoolPrincipal(_, principal, offset, limit) => format!(
+                "/v2/mempool/principal/{}?offset={}&limit={}",
+                principal, offset, limit
+            ),
+            HttpRequestType::GetBlockByHeight(_md, height, tip_req) => format!(
+                "/v2/blocks/by-height/{}{}",
+                height,
+                HttpRequestType::make_tip_query_string(tip_req, true)
+            ),
+            HttpRequestType::GetBlockList(_md, start_height, limit, json, tip_req) => {
+                let tip_query = HttpRequestType::make_tip_query_string(tip_req, true);
+                format!(
+                    "/v2/blocks/list{}{}limit={}{}{}",
+                    tip_query,
+                    if tip_query.is_empty() { "?" } else { "&" },
+                    limit,
+                    start_height
+                        .map(|height| format!("&start_height={}", height))
+                        .unwrap_or_default(),
+                    if *json { "&format=json" } else { "" }
+                )
+            }
             HttpRequestType::GetWithdrawalFt {
                 metadata: _,
                 withdraw_block_height,
@@ -3127,6 +3734,26 @@ impl HttpRequestType {
                 contract_identifier.name.as_str(),
                 id
             ),
+            HttpRequestType::GetWithdrawalStxToSubnet {
+                metadata: _,
+                withdraw_block_height,
+                recipient,
+                withdrawal_id,
+                destination,
+                amount,
+            } => format!(
+                "/v2/withdrawal/stx-to-subnet/{}/{}/{}/{}/{}/{}",
+                withdraw_block_height,
+                recipient,
+                withdrawal_id,
+                StacksAddress::from(destination.issuer.clone()),
+                destination.name.as_str(),
+                amount
+            ),
+            HttpRequestType::PostWithdrawalMultiProof {
+                withdraw_block_height,
+                ..
+            } => format!("/v2/withdrawal/multiproof/{}", withdraw_block_height),
         }
     }
 
@@ -3142,6 +3769,7 @@ impl HttpRequestType {
                 "/v2/microblocks/unconfirmed/:hash/:seq"
             }
             HttpRequestType::GetTransactionUnconfirmed(..) => "/v2/transactions/unconfirmed/:txid",
+            HttpRequestType::GetTransaction(..) => "/v2/transactions/:txid",
             HttpRequestType::PostTransaction(..) => "/v2/transactions",
             HttpRequestType::PostBlock(..) => "/v2/blocks/upload/:block",
             HttpRequestType::PostMicroblock(..) => "/v2/microblocks",
@@ -3160,18 +3788,33 @@ impl HttpRequestType {
             HttpRequestType::GetAttachment(..) => "/v2/attachments/:hash",
             HttpRequestType::GetIsTraitImplemented(..) => "/v2/traits/:principal/:contract_name",
             HttpRequestType::MemPoolQuery(..) => "/v2/mempool/query",
+            HttpRequestType::GetEventStream(..) => "/v2/events/stream",
             HttpRequestType::FeeRateEstimate(_, _, _) => "/v2/fees/transaction",
             HttpRequestType::OptionsPreflight(..) | HttpRequestType::ClientError(..) => "/",
             HttpRequestType::GetWithdrawalStx { .. } => {
                 "/v2/withdrawal/stx/:block-height/:sender/:withdrawal_id/:amount"
             }
             HttpRequestType::BlockProposal(..) => PATH_STR_POST_BLOCK_PROPOSAL,
+            HttpRequestType::GetPeerAllowlist(..) | HttpRequestType::PostPeerAllowlist(..) => {
+                "/v2/neighbors/allowlist"
+            }
+            HttpRequestType::GetHealthLive(..) => "/health/live",
+            HttpRequestType::GetHealthReady(..) => "/health/ready",
+            HttpRequestType::GetMempoolPrincipal(..) => "/v2/mempool/principal/:principal",
+            HttpRequestType::GetBlockByHeight(..) => "/v2/blocks/by-height/:height",
+            HttpRequestType::GetBlockList(..) => "/v2/blocks/list",
             HttpRequestType::GetWithdrawalFt { .. } => {
                 "/v2/withdrawal/ft/:block-height/:sender/:withdrawal_id/:contract_address/:contract_name/:amount"
             }
             HttpRequestType::GetWithdrawalNft { .. } => {
                 "/v2/withdrawal/nft/:block-height/:sender/:withdrawal_id/:contract_address/:contract_name/:id"
             }
+            HttpRequestType::GetWithdrawalStxToSubnet { .. } => {
+                "/v2/withdrawal/stx-to-subnet/:block-height/:recipient/:withdrawal_id/:contract_address/:contract_name/:amount"
+            }
+            HttpRequestType::PostWithdrawalMultiProof { .. } => {
+                "/v2/withdrawal/multiproof/:block-height"
+            }
         }
     }
 
@@ -3306,7 +3949,76 @@ impl HttpRequestType {
                 let mut request_body_bytes = vec![];
                 serde_json::to_writer(&mut request_body_bytes, &request_body).map_err(|e| {
                     net_error::SerializeError(format!(
-                        "Failed to serialize read-only call to JSON: {e:?}"
+                        "Failed to serialize read-only call to JSON: {e:?}"
+                    ))
+                })?;
+
+                HttpRequestPreamble::new_serialized(
+                    fd,
+                    &md.version,
+                    "POST",
+                    &self.request_path(),
+                    &md.peer,
+                    md.keep_alive,
+                    Some(request_body_bytes.len() as u32),
+                    Some(&HttpContentType::JSON),
+                    |fd| stacks_height_headers(fd, md),
+                )?;
+                fd.write_all(&request_body_bytes)
+                    .map_err(net_error::WriteError)?;
+            }
+            HttpRequestType::PostWithdrawalMultiProof {
+                metadata: md,
+                withdrawals,
+                ..
+            } => {
+                let withdrawals = withdrawals
+                    .iter()
+                    .map(|withdrawal| BatchStxWithdrawalRequest {
+                        withdrawal_id: withdrawal.withdrawal_id,
+                        recipient: withdrawal.recipient.to_string(),
+                        amount: withdrawal.amount,
+                    })
+                    .collect();
+                let request_body = WithdrawalMultiProofRequestBody { withdrawals };
+
+                let mut request_body_bytes = vec![];
+                serde_json::to_writer(&mut request_body_bytes, &request_body).map_err(|e| {
+                    net_error::SerializeError(format!(
+                        "Failed to serialize withdrawal multi-proof request to JSON: {e:?}"
+                    ))
+                })?;
+
+                HttpRequestPreamble::new_serialized(
+                    fd,
+                    &md.version,
+                    "POST",
+                    &self.request_path(),
+                    &md.peer,
+                    md.keep_alive,
+                    Some(request_body_bytes.len() as u32),
+                    Some(&HttpContentType::JSON),
+                    |fd| stacks_height_headers(fd, md),
+                )?;
+                fd.write_all(&request_body_bytes)
+                    .map_err(net_error::WriteError)?;
+            }
+            HttpRequestType::PostPeerAllowlist(md, add, remove, authorization) => {
+                let request_body = PeerAllowlistRequestBody {
+                    add: add
+                        .iter()
+                        .map(|public_key| to_hex(&public_key.to_bytes_compressed()))
+                        .collect(),
+                    remove: remove
+                        .iter()
+                        .map(|public_key| to_hex(&public_key.to_bytes_compressed()))
+                        .collect(),
+                };
+
+                let mut request_body_bytes = vec![];
+                serde_json::to_writer(&mut request_body_bytes, &request_body).map_err(|e| {
+                    net_error::SerializeError(format!(
+                        "Failed to serialize peer allowlist request to JSON: {e:?}"
                     ))
                 })?;
 
@@ -3319,7 +4031,12 @@ impl HttpRequestType {
                     md.keep_alive,
                     Some(request_body_bytes.len() as u32),
                     Some(&HttpContentType::JSON),
-                    |fd| stacks_height_headers(fd, md),
+                    |fd| match authorization {
+                        Some(authorization) => fd
+                            .write_all(format!("Authorization: {}\r\n", authorization).as_bytes())
+                            .map_err(codec_error::WriteError),
+                        None => Ok(()),
+                    },
                 )?;
                 fd.write_all(&request_body_bytes)
                     .map_err(net_error::WriteError)?;
@@ -3588,8 +4305,16 @@ impl HttpResponseType {
             (&PATH_GETINFO, &HttpResponseType::parse_peerinfo),
             (&PATH_GETPOXINFO, &HttpResponseType::parse_poxinfo),
             (&PATH_GETNEIGHBORS, &HttpResponseType::parse_neighbors),
+            (
+                &PATH_PEER_ALLOWLIST,
+                &HttpResponseType::parse_peer_allowlist,
+            ),
+            (&PATH_HEALTH_LIVE, &HttpResponseType::parse_health_status),
+            (&PATH_HEALTH_READY, &HttpResponseType::parse_health_status),
             (&PATH_GETHEADERS, &HttpResponseType::parse_headers),
             (&PATH_GETBLOCK, &HttpResponseType::parse_block),
+            (&PATH_GETBLOCK_BY_HEIGHT, &HttpResponseType::parse_block),
+            (&PATH_GETBLOCK_LIST, &HttpResponseType::parse_block_list),
             (&PATH_GET_DATA_VAR, &HttpResponseType::parse_get_data_var),
             (&PATH_GET_MAP_ENTRY, &HttpResponseType::parse_get_map_entry),
             (
@@ -3608,6 +4333,10 @@ impl HttpResponseType {
                 &PATH_GETTRANSACTION_UNCONFIRMED,
                 &HttpResponseType::parse_transaction_unconfirmed,
             ),
+            (
+                &PATH_GETTRANSACTION,
+                &HttpResponseType::parse_transaction_confirmed,
+            ),
             (&PATH_POSTTRANSACTION, &HttpResponseType::parse_txid),
             (
                 &PATH_POSTBLOCK,
@@ -3646,6 +4375,10 @@ impl HttpResponseType {
                 &PATH_POST_MEMPOOL_QUERY,
                 &HttpResponseType::parse_post_mempool_query,
             ),
+            (
+                &PATH_GET_MEMPOOL_PRINCIPAL,
+                &HttpResponseType::parse_mempool_principal_txs,
+            ),
         ];
 
         // use url::Url to parse path and query string
@@ -3739,6 +4472,70 @@ impl HttpResponseType {
         ))
     }
 
+    fn parse_peer_allowlist<R: Read>(
+        _protocol: &mut StacksHttp,
+        request_version: HttpVersion,
+        preamble: &HttpResponsePreamble,
+        fd: &mut R,
+        len_hint: Option<usize>,
+    ) -> Result<HttpResponseType, net_error> {
+        let allowlist_data =
+            HttpResponseType::parse_json(preamble, fd, len_hint, MAX_MESSAGE_LEN as u64)?;
+        Ok(HttpResponseType::PeerAllowlist(
+            HttpResponseMetadata::from_preamble(request_version, preamble),
+            allowlist_data,
+        ))
+    }
+
+    fn parse_health_status<R: Read>(
+        _protocol: &mut StacksHttp,
+        request_version: HttpVersion,
+        preamble: &HttpResponsePreamble,
+        fd: &mut R,
+        len_hint: Option<usize>,
+    ) -> Result<HttpResponseType, net_error> {
+        let health = HttpResponseType::parse_json(preamble, fd, len_hint, MAX_MESSAGE_LEN as u64)?;
+        Ok(HttpResponseType::HealthStatus(
+            HttpResponseMetadata::from_preamble(request_version, preamble),
+            health,
+        ))
+    }
+
+    fn parse_mempool_principal_txs<R: Read>(
+        _protocol: &mut StacksHttp,
+        request_version: HttpVersion,
+        preamble: &HttpResponsePreamble,
+        fd: &mut R,
+        len_hint: Option<usize>,
+    ) -> Result<HttpResponseType, net_error> {
+        let pending_txs =
+            HttpResponseType::parse_json(preamble, fd, len_hint, MAX_MESSAGE_LEN as u64)?;
+        Ok(HttpResponseType::MempoolPrincipalTxs(
+            HttpResponseMetadata::from_preamble(request_version, preamble),
+            pending_txs,
+        ))
+    }
+
+    fn parse_block_list<R: Read>(
+        _protocol: &mut StacksHttp,
+        request_version: HttpVersion,
+        preamble: &HttpResponsePreamble,
+        fd: &mut R,
+        len_hint: Option<usize>,
+    ) -> Result<HttpResponseType, net_error> {
+        // each entry can carry a whole block
+        let block_list = HttpResponseType::parse_json(
+            preamble,
+            fd,
+            len_hint,
+            MAX_MESSAGE_LEN as u64 * MAX_BLOCK_LIST_PAGE_SIZE,
+        )?;
+        Ok(HttpResponseType::BlockList(
+            HttpResponseMetadata::from_preamble(request_version, preamble),
+            block_list,
+        ))
+    }
+
     fn parse_headers<R: Read>(
         _protocol: &mut StacksHttp,
         request_version: HttpVersion,
@@ -3952,6 +4749,34 @@ impl HttpResponseType {
         ))
     }
 
+    fn parse_transaction_confirmed<R: Read>(
+        _protocol: &mut StacksHttp,
+        request_version: HttpVersion,
+        preamble: &HttpResponsePreamble,
+        fd: &mut R,
+        len_hint: Option<usize>,
+    ) -> Result<HttpResponseType, net_error> {
+        let confirmed_tx: RPCConfirmedTransaction =
+            HttpResponseType::parse_json(preamble, fd, len_hint, MAX_MESSAGE_LEN as u64)?;
+
+        // tx payload must decode to a transaction, unless it was a burnchain operation
+        if confirmed_tx.burnchain_op.is_none() {
+            let tx_bytes = hex_bytes(&confirmed_tx.tx).map_err(|_| {
+                net_error::DeserializeError("Confirmed transaction is not hex-encoded".to_string())
+            })?;
+            let _ = StacksTransaction::consensus_deserialize(&mut &tx_bytes[..]).map_err(|_| {
+                net_error::DeserializeError(
+                    "Confirmed transaction is not a well-formed Stacks transaction".to_string(),
+                )
+            })?;
+        }
+
+        Ok(HttpResponseType::ConfirmedTransaction(
+            HttpResponseMetadata::from_preamble(request_version, preamble),
+            confirmed_tx,
+        ))
+    }
+
     fn parse_txid<R: Read>(
         _protocol: &mut StacksHttp,
         request_version: HttpVersion,
@@ -4189,6 +5014,7 @@ impl HttpResponseType {
             403 => "Forbidden",
             404 => "Not Found",
             406 => "Not Acceptable",
+            410 => "Gone",
             500 => "Internal Server Error",
             503 => "Service Temporarily Unavailable",
             _ => "Error",
@@ -4221,6 +5047,10 @@ impl HttpResponseType {
             HttpResponseType::PeerInfo(md, _) => md,
             HttpResponseType::PoxInfo(md, _) => md,
             HttpResponseType::Neighbors(md, _) => md,
+            HttpResponseType::PeerAllowlist(md, _) => md,
+            HttpResponseType::HealthStatus(md, _) => md,
+            HttpResponseType::MempoolPrincipalTxs(md, _) => md,
+            HttpResponseType::BlockList(md, _) => md,
             HttpResponseType::HeaderStream(md) => md,
             HttpResponseType::Headers(md, _) => md,
             HttpResponseType::Block(md, _) => md,
@@ -4239,13 +5069,16 @@ impl HttpResponseType {
             HttpResponseType::GetIsTraitImplemented(md, _) => md,
             HttpResponseType::CallReadOnlyFunction(md, _) => md,
             HttpResponseType::UnconfirmedTransaction(md, _) => md,
+            HttpResponseType::ConfirmedTransaction(md, _) => md,
             HttpResponseType::GetAttachment(md, _) => md,
             HttpResponseType::GetAttachmentsInv(md, _) => md,
             HttpResponseType::MemPoolTxStream(md) => md,
+            HttpResponseType::EventStream(md) => md,
             HttpResponseType::MemPoolTxs(md, ..) => md,
             HttpResponseType::OptionsPreflight(md) => md,
             HttpResponseType::TransactionFeeEstimation(md, _) => md,
             HttpResponseType::GetWithdrawal(md, _) => md,
+            HttpResponseType::GetWithdrawalMultiProof(md, _) => md,
             // errors
             HttpResponseType::BadRequestJSON(md, _) => md,
             HttpResponseType::BadRequest(md, _) => md,
@@ -4371,6 +5204,36 @@ impl HttpResponseType {
                 HttpResponsePreamble::ok_JSON_from_md(fd, md)?;
                 HttpResponseType::send_json(protocol, md, fd, neighbor_data)?;
             }
+            HttpResponseType::PeerAllowlist(ref md, ref allowlist_data) => {
+                HttpResponsePreamble::ok_JSON_from_md(fd, md)?;
+                HttpResponseType::send_json(protocol, md, fd, allowlist_data)?;
+            }
+            HttpResponseType::HealthStatus(ref md, ref health) => {
+                // orchestrators only look at the status code, so an unhealthy node answers 503
+                let (status_code, reason) = if health.is_healthy() {
+                    (200, "OK")
+                } else {
+                    (503, HttpResponseType::error_reason(503))
+                };
+                HttpResponsePreamble::new_serialized(
+                    fd,
+                    status_code,
+                    reason,
+                    md.content_length.clone(),
+                    &HttpContentType::JSON,
+                    md.request_id,
+                    |ref mut fd| keep_alive_headers(fd, md),
+                )?;
+                HttpResponseType::send_json(protocol, md, fd, health)?;
+            }
+            HttpResponseType::MempoolPrincipalTxs(ref md, ref pending_txs) => {
+                HttpResponsePreamble::ok_JSON_from_md(fd, md)?;
+                HttpResponseType::send_json(protocol, md, fd, pending_txs)?;
+            }
+            HttpResponseType::BlockList(ref md, ref block_list) => {
+                HttpResponsePreamble::ok_JSON_from_md(fd, md)?;
+                HttpResponseType::send_json(protocol, md, fd, block_list)?;
+            }
             HttpResponseType::GetAttachment(ref md, ref zonefile_data) => {
                 HttpResponsePreamble::ok_JSON_from_md(fd, md)?;
                 HttpResponseType::send_json(protocol, md, fd, zonefile_data)?;
@@ -4500,6 +5363,10 @@ impl HttpResponseType {
                 HttpResponsePreamble::ok_JSON_from_md(fd, md)?;
                 HttpResponseType::send_json(protocol, md, fd, unconfirmed_status)?;
             }
+            HttpResponseType::ConfirmedTransaction(ref md, ref confirmed_tx) => {
+                HttpResponsePreamble::ok_JSON_from_md(fd, md)?;
+                HttpResponseType::send_json(protocol, md, fd, confirmed_tx)?;
+            }
             HttpResponseType::MemPoolTxStream(ref md) => {
                 // only send the preamble.  The caller will need to figure out how to send along
                 // the tx data itself.
@@ -4513,6 +5380,19 @@ impl HttpResponseType {
                     |ref mut fd| keep_alive_headers(fd, md),
                 )?;
             }
+            HttpResponseType::EventStream(ref md) => {
+                // only send the preamble.  The caller will need to figure out how to send along
+                // the events themselves.
+                HttpResponsePreamble::new_serialized(
+                    fd,
+                    200,
+                    "OK",
+                    None,
+                    &HttpContentType::EventStream,
+                    md.request_id,
+                    |ref mut fd| keep_alive_headers(fd, md),
+                )?;
+            }
             HttpResponseType::MemPoolTxs(ref md, ref page_id, ref txs) => {
                 HttpResponsePreamble::new_serialized(
                     fd,
@@ -4585,6 +5465,10 @@ impl HttpResponseType {
                 HttpResponsePreamble::ok_JSON_from_md(fd, md)?;
                 HttpResponseType::send_json(protocol, md, fd, json)?;
             }
+            HttpResponseType::GetWithdrawalMultiProof(ref md, ref json) => {
+                HttpResponsePreamble::ok_JSON_from_md(fd, md)?;
+                HttpResponseType::send_json(protocol, md, fd, json)?;
+            }
             HttpResponseType::BlockProposalValid {
                 metadata: ref md,
                 ref signature,
@@ -4682,6 +5566,7 @@ impl MessageSequence for StacksHttpMessage {
                 HttpRequestType::GetTransactionUnconfirmed(_, _) => {
                     "HTTP(GetTransactionUnconfirmed)"
                 }
+                HttpRequestType::GetTransaction(..) => "HTTP(GetTransaction)",
                 HttpRequestType::PostTransaction(_, _, _) => "HTTP(PostTransaction)",
                 HttpRequestType::PostBlock(..) => "HTTP(PostBlock)",
                 HttpRequestType::PostMicroblock(..) => "HTTP(PostMicroblock)",
@@ -4696,13 +5581,27 @@ impl MessageSequence for StacksHttpMessage {
                 HttpRequestType::GetAttachment(..) => "HTTP(GetAttachment)",
                 HttpRequestType::GetAttachmentsInv(..) => "HTTP(GetAttachmentsInv)",
                 HttpRequestType::MemPoolQuery(..) => "HTTP(MemPoolQuery)",
+                HttpRequestType::GetEventStream(..) => "HTTP(GetEventStream)",
                 HttpRequestType::OptionsPreflight(..) => "HTTP(OptionsPreflight)",
                 HttpRequestType::ClientError(..) => "HTTP(ClientError)",
                 HttpRequestType::FeeRateEstimate(_, _, _) => "HTTP(FeeRateEstimate)",
                 HttpRequestType::GetWithdrawalStx { .. } => "HTTP(GetWithdrawalStx)",
                 HttpRequestType::BlockProposal(_, _) => "HTTP(BlockProposal)",
+                HttpRequestType::GetHealthLive(_) => "HTTP(GetHealthLive)",
+                HttpRequestType::GetHealthReady(_) => "HTTP(GetHealthReady)",
+                HttpRequestType::GetPeerAllowlist(_) => "HTTP(GetPeerAllowlist)",
+                HttpRequestType::PostPeerAllowlist(..) => "HTTP(PostPeerAllowlist)",
+                HttpRequestType::GetMempoolPrincipal(..) => "HTTP(GetMempoolPrincipal)",
+                HttpRequestType::GetBlockByHeight(..) => "HTTP(GetBlockByHeight)",
+                HttpRequestType::GetBlockList(..) => "HTTP(GetBlockList)",
                 HttpRequestType::GetWithdrawalFt { .. } => "HTTP(GetWithdrawalFt)",
                 HttpRequestType::GetWithdrawalNft { .. } => "HTTP(GetWithdrawalNft)",
+                HttpRequestType::GetWithdrawalStxToSubnet { .. } => {
+                    "HTTP(GetWithdrawalStxToSubnet)"
+                }
+                HttpRequestType::PostWithdrawalMultiProof { .. } => {
+                    "HTTP(PostWithdrawalMultiProof)"
+                }
             },
             StacksHttpMessage::Response(ref res) => match res {
                 HttpResponseType::TokenTransferCost(_, _) => "HTTP(TokenTransferCost)",
@@ -4718,6 +5617,10 @@ impl MessageSequence for StacksHttpMessage {
                 HttpResponseType::PeerInfo(_, _) => "HTTP(PeerInfo)",
                 HttpResponseType::PoxInfo(_, _) => "HTTP(PeerInfo)",
                 HttpResponseType::Neighbors(_, _) => "HTTP(Neighbors)",
+                HttpResponseType::PeerAllowlist(_, _) => "HTTP(PeerAllowlist)",
+                HttpResponseType::HealthStatus(_, _) => "HTTP(HealthStatus)",
+                HttpResponseType::MempoolPrincipalTxs(_, _) => "HTTP(MempoolPrincipalTxs)",
+                HttpResponseType::BlockList(_, _) => "HTTP(BlockList)",
                 HttpResponseType::Headers(..) => "HTTP(Headers)",
                 HttpResponseType::HeaderStream(..) => "HTTP(HeaderStream)",
                 HttpResponseType::Block(_, _) => "HTTP(Block)",
@@ -4728,7 +5631,9 @@ impl MessageSequence for StacksHttpMessage {
                 HttpResponseType::StacksBlockAccepted(..) => "HTTP(StacksBlockAccepted)",
                 HttpResponseType::MicroblockHash(_, _) => "HTTP(MicroblockHash)",
                 HttpResponseType::UnconfirmedTransaction(_, _) => "HTTP(UnconfirmedTransaction)",
+                HttpResponseType::ConfirmedTransaction(_, _) => "HTTP(ConfirmedTransaction)",
                 HttpResponseType::MemPoolTxStream(..) => "HTTP(MemPoolTxStream)",
+                HttpResponseType::EventStream(..) => "HTTP(EventStream)",
                 HttpResponseType::MemPoolTxs(..) => "HTTP(MemPoolTxs)",
                 HttpResponseType::OptionsPreflight(_) => "HTTP(OptionsPreflight)",
                 HttpResponseType::BadRequestJSON(..) | HttpResponseType::BadRequest(..) => {
@@ -4745,6 +5650,7 @@ impl MessageSequence for StacksHttpMessage {
                     "HTTP(TransactionFeeEstimation)"
                 }
                 HttpResponseType::GetWithdrawal(_, _) => "HTTP(GetWithdrawal)",
+                HttpResponseType::GetWithdrawalMultiProof(_, _) => "HTTP(GetWithdrawalMultiProof)",
                 HttpResponseType::BlockProposalValid { .. }
                 | HttpResponseType::BlockProposalInvalid { .. } => "HTTP(BlockProposal)",
             },
@@ -5280,6 +6186,7 @@ mod test {
     use crate::net::test::*;
     use crate::net::RPCNeighbor;
     use crate::net::RPCNeighborsInfo;
+    use crate::net::{RPCHealthComponent, RPCHealthResponse};
     use stacks_common::util::hash::to_hex;
     use stacks_common::util::hash::Hash160;
     use stacks_common::util::hash::MerkleTree;
@@ -7252,6 +8159,424 @@ mod test {
         );
     }
 
+    #[test]
+    fn test_http_parse_height_query() {
+        assert_eq!(
+            HttpRequestType::get_chain_tip_or_height_query(Some("height=123")).unwrap(),
+            TipRequest::AtHeight(123)
+        );
+        assert_eq!(
+            HttpRequestType::get_chain_tip_or_height_query(Some("proof=0&height=123")).unwrap(),
+            TipRequest::AtHeight(123)
+        );
+
+        // falls back to the tip query
+        assert_eq!(
+            HttpRequestType::get_chain_tip_or_height_query(None).unwrap(),
+            TipRequest::UseLatestUnconfirmedTip
+        );
+        assert_eq!(
+            HttpRequestType::get_chain_tip_or_height_query(Some("tip=anchored")).unwrap(),
+            TipRequest::UseLatestAnchoredTip
+        );
+
+        // bad heights, and heights with tips, are rejected
+        assert!(HttpRequestType::get_chain_tip_or_height_query(Some("height=abc")).is_err());
+        assert!(HttpRequestType::get_chain_tip_or_height_query(Some("height=-1")).is_err());
+        assert!(
+            HttpRequestType::get_chain_tip_or_height_query(Some("tip=latest&height=123")).is_err()
+        );
+
+        // height queries round-trip through the request path
+        let principal = PrincipalData::parse("ST2DS4MSWSGJ3W9FBC6BVT0Y92S345HY8N3T6AV7R").unwrap();
+        for with_proof in [true, false] {
+            let req = HttpRequestType::GetAccount(
+                HttpRequestMetadata::from_host(
+                    PeerHost::from_host_port("127.0.0.1".to_string(), 20443),
+                    None,
+                ),
+                principal.clone(),
+                TipRequest::AtHeight(123),
+                with_proof,
+            );
+            let request = format!(
+                "GET {} HTTP/1.1\r\nUser-Agent: stacks/2.0\r\nHost: localhost:20443\r\n\r\n",
+                req.request_path()
+            );
+            let mut http = StacksHttp::new("127.0.0.1:20443".parse().unwrap());
+            let (preamble, offset) = http.read_preamble(request.as_bytes()).unwrap();
+            let (message, _) = http
+                .read_payload(&preamble, &request.as_bytes()[offset..])
+                .unwrap();
+            match message {
+                StacksHttpMessage::Request(HttpRequestType::GetAccount(
+                    _,
+                    parsed_principal,
+                    tip_req,
+                    parsed_with_proof,
+                )) => {
+                    assert_eq!(parsed_principal, principal);
+                    assert_eq!(tip_req, TipRequest::AtHeight(123));
+                    assert_eq!(parsed_with_proof, with_proof);
+                }
+                _ => panic!("Expected an account request, got {:?}", &message),
+            }
+        }
+    }
+
+    #[test]
+    fn test_http_parse_event_stream() {
+        let mut http = StacksHttp::new("127.0.0.1:20443".parse().unwrap());
+        let request = "GET /v2/events/stream?events_keys=block,%20microblocks,,memtx HTTP/1.1\r\nUser-Agent: stacks/2.0\r\nHost: localhost:20443\r\n\r\n";
+        let (preamble, offset) = http.read_preamble(request.as_bytes()).unwrap();
+        let (message, _) = http
+            .read_payload(&preamble, &request.as_bytes()[offset..])
+            .unwrap();
+        match message {
+            StacksHttpMessage::Request(HttpRequestType::GetEventStream(_, events_keys)) => {
+                assert_eq!(events_keys, vec!["block", "microblocks", "memtx"]);
+            }
+            _ => panic!("Expected an event stream request, got {:?}", &message),
+        }
+
+        // events_keys is required
+        for bad_request in &[
+            "GET /v2/events/stream HTTP/1.1\r\nUser-Agent: stacks/2.0\r\nHost: localhost:20443\r\n\r\n",
+            "GET /v2/events/stream?events_keys= HTTP/1.1\r\nUser-Agent: stacks/2.0\r\nHost: localhost:20443\r\n\r\n",
+        ] {
+            let mut http = StacksHttp::new("127.0.0.1:20443".parse().unwrap());
+            let (preamble, offset) = http.read_preamble(bad_request.as_bytes()).unwrap();
+            assert!(http
+                .read_payload(&preamble, &bad_request.as_bytes()[offset..])
+                .is_err());
+        }
+    }
+
+    #[test]
+    fn test_http_parse_peer_allowlist() {
+        let pubk = Secp256k1PublicKey::from_hex(
+            "02fa66b66f8971a8cd4d20ffded09674e030f0f33883f337f34b95ad4935bac0e3",
+        )
+        .unwrap();
+        let body = format!("{{\"add\":[\"{}\"]}}", to_hex(&pubk.to_bytes_compressed()));
+        let request = format!("POST /v2/neighbors/allowlist HTTP/1.1\r\nUser-Agent: stacks/2.0\r\nHost: localhost:20443\r\nAuthorization: admin-token\r\nContent-Type: application/json\r\nContent-Length: {}\r\n\r\n{}", body.len(), body);
+
+        let mut http = StacksHttp::new("127.0.0.1:20443".parse().unwrap());
+        let (preamble, offset) = http.read_preamble(request.as_bytes()).unwrap();
+        let (message, _) = http
+            .read_payload(&preamble, &request.as_bytes()[offset..])
+            .unwrap();
+        match message {
+            StacksHttpMessage::Request(HttpRequestType::PostPeerAllowlist(
+                _,
+                add,
+                remove,
+                authorization,
+            )) => {
+                assert_eq!(add, vec![pubk]);
+                assert!(remove.is_empty());
+                assert_eq!(authorization, Some("admin-token".to_string()));
+            }
+            _ => panic!("Expected a peer allowlist request, got {:?}", &message),
+        }
+
+        // public keys must be well-formed
+        let body = "{\"remove\":[\"00\"]}";
+        let request = format!("POST /v2/neighbors/allowlist HTTP/1.1\r\nUser-Agent: stacks/2.0\r\nHost: localhost:20443\r\nContent-Type: application/json\r\nContent-Length: {}\r\n\r\n{}", body.len(), body);
+        let mut http = StacksHttp::new("127.0.0.1:20443".parse().unwrap());
+        let (preamble, offset) = http.read_preamble(request.as_bytes()).unwrap();
+        let e = http
+            .read_payload(&preamble, &request.as_bytes()[offset..])
+            .unwrap_err();
+        assert!(
+            e.to_string().find("Invalid public key").is_some(),
+            "{:?}",
+            &e
+        );
+    }
+
+    #[test]
+    fn test_http_parse_withdrawal_multiproof() {
+        let make_request = |num_withdrawals: u32| {
+            let withdrawals: Vec<_> = (0..num_withdrawals)
+                .map(|withdrawal_id| {
+                    format!(
+                        "{{\"withdrawal_id\":{},\"recipient\":\"ST18F1AHKW194BWQ3CEFDPWVRARA79RBGFEWSDQR8\",\"amount\":1}}",
+                        withdrawal_id
+                    )
+                })
+                .collect();
+            let body = format!("{{\"withdrawals\":[{}]}}", withdrawals.join(","));
+            format!("POST /v2/withdrawal/multiproof/7 HTTP/1.1\r\nUser-Agent: stacks/2.0\r\nHost: localhost:20443\r\nContent-Type: application/json\r\nContent-Length: {}\r\n\r\n{}", body.len(), body)
+        };
+
+        let request = make_request(MAX_BATCH_WITHDRAWALS as u32);
+        let mut http = StacksHttp::new("127.0.0.1:20443".parse().unwrap());
+        let (preamble, offset) = http.read_preamble(request.as_bytes()).unwrap();
+        let (message, _) = http
+            .read_payload(&preamble, &request.as_bytes()[offset..])
+            .unwrap();
+        match message {
+            StacksHttpMessage::Request(HttpRequestType::PostWithdrawalMultiProof {
+                withdraw_block_height,
+                withdrawals,
+                ..
+            }) => {
+                assert_eq!(withdraw_block_height, 7);
+                assert_eq!(withdrawals.len(), MAX_BATCH_WITHDRAWALS);
+                assert_eq!(
+                    withdrawals[1],
+                    BatchStxWithdrawal {
+                        withdrawal_id: 1,
+                        recipient: PrincipalData::parse(
+                            "ST18F1AHKW194BWQ3CEFDPWVRARA79RBGFEWSDQR8"
+                        )
+                        .unwrap(),
+                        amount: 1,
+                    }
+                );
+            }
+            _ => panic!("Expected a multi-proof request, got {:?}", &message),
+        }
+
+        // more withdrawals than `withdraw-stx-batch` settles, or none at all
+        for num_withdrawals in [MAX_BATCH_WITHDRAWALS as u32 + 1, 0] {
+            let request = make_request(num_withdrawals);
+            let mut http = StacksHttp::new("127.0.0.1:20443".parse().unwrap());
+            let (preamble, offset) = http.read_preamble(request.as_bytes()).unwrap();
+            assert!(http
+                .read_payload(&preamble, &request.as_bytes()[offset..])
+                .is_err());
+        }
+    }
+
+    #[test]
+    fn test_http_health_status() {
+        for path in &["/health/live", "/health/ready"] {
+            let request = format!(
+                "GET {} HTTP/1.1\r\nUser-Agent: stacks/2.0\r\nHost: localhost:20443\r\n\r\n",
+                path
+            );
+            let mut http = StacksHttp::new("127.0.0.1:20443".parse().unwrap());
+            let (preamble, offset) = http.read_preamble(request.as_bytes()).unwrap();
+            let (message, _) = http
+                .read_payload(&preamble, &request.as_bytes()[offset..])
+                .unwrap();
+            match message {
+                StacksHttpMessage::Request(req) => assert_eq!(req.get_path(), *path),
+                _ => panic!("Expected a health request, got {:?}", &message),
+            }
+        }
+
+        // an unhealthy component makes the node unavailable
+        for (components, status_line) in vec![
+            (
+                vec![RPCHealthComponent::healthy("mempool", "ok".to_string())],
+                "HTTP/1.1 200 OK\r\n",
+            ),
+            (
+                vec![
+                    RPCHealthComponent::healthy("mempool", "ok".to_string()),
+                    RPCHealthComponent::unhealthy("sortition", "behind".to_string()),
+                ],
+                "HTTP/1.1 503 Service Temporarily Unavailable\r\n",
+            ),
+        ] {
+            let health = RPCHealthResponse::from_components(components);
+            let response = HttpResponseType::HealthStatus(
+                HttpResponseMetadata::new(HttpVersion::Http11, 123, None, true, None),
+                health,
+            );
+            let mut http = StacksHttp::new("127.0.0.1:20443".parse().unwrap());
+            let mut bytes = vec![];
+            response.send(&mut http, &mut bytes).unwrap();
+            let response_str = String::from_utf8(bytes).unwrap();
+            assert!(response_str.starts_with(status_line), "{}", &response_str);
+        }
+    }
+
+    #[test]
+    fn test_http_parse_mempool_principal() {
+        let addr = StacksAddress::from_string("ST2DS4MSWSGJ3W9FBC6BVT0Y92S345HY8N3T6AV7R").unwrap();
+        for (query, expected_offset, expected_limit) in &[
+            ("", 0, DEFAULT_MEMPOOL_PRINCIPAL_PAGE_SIZE),
+            ("?offset=10&limit=5", 10, 5),
+            ("?limit=1000000", 0, MAX_MEMPOOL_PRINCIPAL_PAGE_SIZE),
+        ] {
+            let request = format!("GET /v2/mempool/principal/{}{} HTTP/1.1\r\nUser-Agent: stacks/2.0\r\nHost: localhost:20443\r\n\r\n", &addr, query);
+            let mut http = StacksHttp::new("127.0.0.1:20443".parse().unwrap());
+            let (preamble, offset) = http.read_preamble(request.as_bytes()).unwrap();
+            let (message, _) = http
+                .read_payload(&preamble, &request.as_bytes()[offset..])
+                .unwrap();
+            match message {
+                StacksHttpMessage::Request(HttpRequestType::GetMempoolPrincipal(
+                    _,
+                    principal,
+                    offset,
+                    limit,
+                )) => {
+                    assert_eq!(principal, addr);
+                    assert_eq!(offset, *expected_offset);
+                    assert_eq!(limit, *expected_limit);
+                }
+                _ => panic!("Expected a mempool principal request, got {:?}", &message),
+            }
+        }
+
+        // offset and limit must be numbers
+        let request = format!("GET /v2/mempool/principal/{}?offset=-1 HTTP/1.1\r\nUser-Agent: stacks/2.0\r\nHost: localhost:20443\r\n\r\n", &addr);
+        let mut http = StacksHttp::new("127.0.0.1:20443".parse().unwrap());
+        let (preamble, offset) = http.read_preamble(request.as_bytes()).unwrap();
+        assert!(http
+            .read_payload(&preamble, &request.as_bytes()[offset..])
+            .is_err());
+    }
+
+    #[test]
+    fn test_http_parse_get_transaction() {
+        let txid = Txid([0x11; 32]);
+        let tip = StacksBlockId([0x22; 32]);
+        // a request without a tip is re-encoded with `?tip=latest`
+        let tip_latest = format!("/v2/transactions/{}?tip=latest", &txid);
+        for (path, expected_tip, expected_path) in &[
+            (
+                format!("/v2/transactions/{}", &txid),
+                TipRequest::UseLatestUnconfirmedTip,
+                tip_latest.clone(),
+            ),
+            (
+                tip_latest.clone(),
+                TipRequest::UseLatestUnconfirmedTip,
+                tip_latest.clone(),
+            ),
+            (
+                format!("/v2/transactions/{}?tip={}", &txid, &tip),
+                TipRequest::SpecificTip(tip.clone()),
+                format!("/v2/transactions/{}?tip={}", &txid, &tip),
+            ),
+        ] {
+            let request = format!(
+                "GET {} HTTP/1.1\r\nUser-Agent: stacks/2.0\r\nHost: localhost:20443\r\n\r\n",
+                path
+            );
+            let mut http = StacksHttp::new("127.0.0.1:20443".parse().unwrap());
+            let (preamble, offset) = http.read_preamble(request.as_bytes()).unwrap();
+            let (message, _) = http
+                .read_payload(&preamble, &request.as_bytes()[offset..])
+                .unwrap();
+            match message {
+                StacksHttpMessage::Request(req) => {
+                    assert_eq!(req.request_path(), *expected_path);
+                    match req {
+                        HttpRequestType::GetTransaction(_, parsed_txid, tip_req) => {
+                            assert_eq!(parsed_txid, txid);
+                            assert_eq!(tip_req, *expected_tip);
+                        }
+                        _ => panic!("Expected a transaction request, got {:?}", &req),
+                    }
+                }
+                _ => panic!("Expected a transaction request, got {:?}", &message),
+            }
+        }
+
+        // the unconfirmed route is still distinct
+        let request = format!(
+            "GET /v2/transactions/unconfirmed/{} HTTP/1.1\r\nUser-Agent: stacks/2.0\r\nHost: localhost:20443\r\n\r\n",
+            &txid
+        );
+        let mut http = StacksHttp::new("127.0.0.1:20443".parse().unwrap());
+        let (preamble, offset) = http.read_preamble(request.as_bytes()).unwrap();
+        let (message, _) = http
+            .read_payload(&preamble, &request.as_bytes()[offset..])
+            .unwrap();
+        match message {
+            StacksHttpMessage::Request(HttpRequestType::GetTransactionUnconfirmed(_, parsed)) => {
+                assert_eq!(parsed, txid)
+            }
+            _ => panic!(
+                "Expected an unconfirmed transaction request, got {:?}",
+                &message
+            ),
+        }
+    }
+
+    #[test]
+    fn test_http_parse_get_block_by_height_and_list() {
+        let tip = StacksBlockId([0x22; 32]);
+        let request = format!("GET /v2/blocks/by-height/123?tip={} HTTP/1.1\r\nUser-Agent: stacks/2.0\r\nHost: localhost:20443\r\n\r\n", &tip);
+        let mut http = StacksHttp::new("127.0.0.1:20443".parse().unwrap());
+        let (preamble, offset) = http.read_preamble(request.as_bytes()).unwrap();
+        let (message, _) = http
+            .read_payload(&preamble, &request.as_bytes()[offset..])
+            .unwrap();
+        match message {
+            StacksHttpMessage::Request(HttpRequestType::GetBlockByHeight(_, height, tip_req)) => {
+                assert_eq!(height, 123);
+                assert_eq!(tip_req, TipRequest::SpecificTip(tip.clone()));
+            }
+            _ => panic!("Expected a block by height request, got {:?}", &message),
+        }
+
+        for (query, expected_start, expected_limit, expected_json) in &[
+            ("".to_string(), None, DEFAULT_BLOCK_LIST_PAGE_SIZE, false),
+            (
+                "?start_height=10&limit=5&format=json".to_string(),
+                Some(10),
+                5,
+                true,
+            ),
+            (
+                format!("?tip={}&limit=1000&format=hex", &tip),
+                None,
+                MAX_BLOCK_LIST_PAGE_SIZE,
+                false,
+            ),
+        ] {
+            let request = format!("GET /v2/blocks/list{} HTTP/1.1\r\nUser-Agent: stacks/2.0\r\nHost: localhost:20443\r\n\r\n", query);
+            let mut http = StacksHttp::new("127.0.0.1:20443".parse().unwrap());
+            let (preamble, offset) = http.read_preamble(request.as_bytes()).unwrap();
+            let (message, _) = http
+                .read_payload(&preamble, &request.as_bytes()[offset..])
+                .unwrap();
+            match message {
+                StacksHttpMessage::Request(req) => {
+                    match req {
+                        HttpRequestType::GetBlockList(_, start_height, limit, json, _) => {
+                            assert_eq!(start_height, *expected_start);
+                            assert_eq!(limit, *expected_limit);
+                            assert_eq!(json, *expected_json);
+                        }
+                        _ => panic!("Expected a block list request, got {:?}", &req),
+                    }
+
+                    // the request path round-trips
+                    let request = format!("GET {} HTTP/1.1\r\nUser-Agent: stacks/2.0\r\nHost: localhost:20443\r\n\r\n", req.request_path());
+                    let mut http = StacksHttp::new("127.0.0.1:20443".parse().unwrap());
+                    let (preamble, offset) = http.read_preamble(request.as_bytes()).unwrap();
+                    let (reparsed, _) = http
+                        .read_payload(&preamble, &request.as_bytes()[offset..])
+                        .unwrap();
+                    match reparsed {
+                        StacksHttpMessage::Request(reparsed) => {
+                            assert_eq!(reparsed.request_path(), req.request_path())
+                        }
+                        _ => panic!("Expected a block list request, got {:?}", &reparsed),
+                    }
+                }
+                _ => panic!("Expected a block list request, got {:?}", &message),
+            }
+        }
+
+        // unknown formats are rejected
+        let request = "GET /v2/blocks/list?format=xml HTTP/1.1\r\nUser-Agent: stacks/2.0\r\nHost: localhost:20443\r\n\r\n";
+        let mut http = StacksHttp::new("127.0.0.1:20443".parse().unwrap());
+        let (preamble, offset) = http.read_preamble(request.as_bytes()).unwrap();
+        assert!(http
+            .read_payload(&preamble, &request.as_bytes()[offset..])
+            .is_err());
+    }
+
     #[test]
     fn test_http_live_headers() {
         // headers pulled from prod
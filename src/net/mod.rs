@@ -118,6 +118,9 @@ pub mod db;
 /// which serves as an API for `DNSResolver`.  
 pub mod dns;
 pub mod download;
+/// Implements the RPC server's server-sent event streams of event dispatcher payloads.
+pub mod event_stream;
+pub mod health;
 pub mod http;
 pub mod inv;
 pub mod neighbors;
@@ -635,6 +638,7 @@ pub enum HttpContentType {
     Bytes,
     Text,
     JSON,
+    EventStream,
 }
 
 impl fmt::Display for HttpContentType {
@@ -649,6 +653,7 @@ impl HttpContentType {
             HttpContentType::Bytes => "application/octet-stream",
             HttpContentType::Text => "text/plain",
             HttpContentType::JSON => "application/json",
+            HttpContentType::EventStream => "text/event-stream",
         }
     }
 }
@@ -664,6 +669,8 @@ impl FromStr for HttpContentType {
             Ok(HttpContentType::Text)
         } else if s == "application/json" {
             Ok(HttpContentType::JSON)
+        } else if s == "text/event-stream" {
+            Ok(HttpContentType::EventStream)
         } else {
             Err(codec_error::DeserializeError(
                 "Unsupported HTTP content type".to_string(),
@@ -896,6 +903,29 @@ pub struct RelayData {
     pub seq: u32,
 }
 
+define_u8_enum!(BlockProposalResponseID {
+    Accepted = 0x01,
+    Rejected = 0x02
+});
+
+/// A federation member's answer to a block proposal
+#[derive(Debug, Clone, PartialEq)]
+pub enum BlockProposalResponse {
+    /// The proposed block is valid. Carries the member's signature over it, in the form the
+    /// multi-miner contract expects.
+    Accepted([u8; 65]),
+    /// The proposed block was not signed, for the given reason
+    Rejected(String),
+}
+
+/// Reply to a `BlockProposal`
+#[derive(Debug, Clone, PartialEq)]
+pub struct BlockProposalSignatureData {
+    /// `SignedProposal::message_hash()` of the proposal being answered
+    pub proposal_hash: Sha256Sum,
+    pub response: BlockProposalResponse,
+}
+
 /// All P2P message types
 #[derive(Debug, Clone, PartialEq)]
 pub enum StacksMessageType {
@@ -918,6 +948,10 @@ pub enum StacksMessageType {
     Pong(PongData),
     NatPunchRequest(u32),
     NatPunchReply(NatPunchData),
+    // federation-only messages: these are only exchanged with peers in `federation_peers`, and
+    // are never relayed
+    BlockProposal(SignedProposal),
+    BlockProposalSignature(BlockProposalSignatureData),
 }
 
 /// Peer address variants
@@ -1051,6 +1085,10 @@ pub struct RPCPeerInfoData {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub node_public_key_hash: Option<Hash160>,
     pub l1_subnet_governing_contract: String,
+    /// If this node is pruned, the height below which it no longer has (or serves) block data
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub pruned_height: Option<u64>,
 }
 
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
@@ -1180,6 +1218,9 @@ pub struct RPCFeeEstimateResponse {
     pub estimated_cost_scalar: u64,
     pub estimations: Vec<RPCFeeEstimate>,
     pub cost_scalar_change_by_byte: f64,
+    /// Fungible token that transaction fees are paid in, if not STX
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub fee_asset: Option<String>,
 }
 
 #[derive(Debug, Clone, PartialEq, Copy, Hash)]
@@ -1204,6 +1245,10 @@ pub struct DataVarResponse {
     #[serde(default)]
     #[serde(skip_serializing_if = "Option::is_none")]
     pub marf_proof: Option<String>,
+    /// The block that the proof was generated against
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub index_block_hash: Option<StacksBlockId>,
 }
 
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
@@ -1213,6 +1258,10 @@ pub struct MapEntryResponse {
     #[serde(default)]
     #[serde(skip_serializing_if = "Option::is_none")]
     pub marf_proof: Option<String>,
+    /// The block that the proof was generated against
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub index_block_hash: Option<StacksBlockId>,
 }
 
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
@@ -1253,6 +1302,10 @@ pub struct AccountEntryResponse {
     #[serde(skip_serializing_if = "Option::is_none")]
     #[serde(default)]
     pub nonce_proof: Option<String>,
+    /// The block that the proof was generated against
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub index_block_hash: Option<StacksBlockId>,
 }
 
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
@@ -1262,6 +1315,37 @@ pub struct WithdrawalResponse {
     pub sibling_hashes: String,
 }
 
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct WithdrawalMultiProofRequestBody {
+    pub withdrawals: Vec<BatchStxWithdrawalRequest>,
+}
+
+/// An STX withdrawal to prove in a batch, as it is passed to `withdraw-stx-batch`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BatchStxWithdrawalRequest {
+    pub withdrawal_id: u32,
+    pub recipient: String,
+    pub amount: u64,
+}
+
+/// An STX withdrawal to prove in a batch
+#[derive(Debug, Clone, PartialEq)]
+pub struct BatchStxWithdrawal {
+    pub withdrawal_id: u32,
+    pub recipient: PrincipalData,
+    pub amount: u64,
+}
+
+/// A multi-proof for a batch of withdrawals from the same block. Each field is the hex of a
+/// consensus-serialized Clarity value, ready to be passed to `withdraw-stx-batch`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct WithdrawalMultiProofResponse {
+    pub withdrawal_root: String,
+    pub withdrawal_leaf_hashes: String,
+    pub proof_hashes: String,
+    pub proof_steps: String,
+}
+
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub enum UnconfirmedTransactionStatus {
     Microblock {
@@ -1277,6 +1361,90 @@ pub struct UnconfirmedTransactionResponse {
     pub status: UnconfirmedTransactionStatus,
 }
 
+/// A confirmed transaction and its receipt, as returned by `/v2/transactions/:txid`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RPCConfirmedTransaction {
+    pub txid: String,
+    /// Hex of the consensus-serialized transaction, or empty for a burnchain operation
+    pub tx: String,
+    /// The burnchain operation, if the transaction was one
+    pub burnchain_op: Option<serde_json::Value>,
+    pub index_block_hash: String,
+    pub block_hash: String,
+    pub block_height: u64,
+    pub tx_index: u32,
+    /// The microblock the transaction was mined in, if any
+    pub microblock_hash: Option<String>,
+    /// Hex of the consensus-serialized result value
+    pub result: String,
+    pub post_condition_aborted: bool,
+    pub stx_burned: u128,
+    pub events: Vec<serde_json::Value>,
+    pub execution_cost: ExecutionCost,
+}
+
+/// A pending transaction, as returned by `/v2/mempool/principal/:principal`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RPCPendingTransaction {
+    pub txid: String,
+    /// Hex of the consensus-serialized transaction
+    pub tx: String,
+    pub payload_type: String,
+    pub origin_address: String,
+    pub origin_nonce: u64,
+    pub sponsor_address: String,
+    pub sponsor_nonce: u64,
+    pub fee: u64,
+    pub fee_rate: Option<f64>,
+    pub accept_time: u64,
+    /// Why the miner skipped this transaction the last time it considered it, if it did
+    pub skip_reason: Option<String>,
+    pub skip_time: Option<u64>,
+}
+
+/// A page of an account's pending transactions, in nonce order.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RPCMempoolPrincipalResponse {
+    pub principal: String,
+    /// Total number of pending transactions the principal originated or sponsored
+    pub total: u64,
+    pub offset: u64,
+    pub limit: u64,
+    pub transactions: Vec<RPCPendingTransaction>,
+}
+
+/// An anchored block and its header metadata, as listed by `/v2/blocks/list`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RPCBlockListEntry {
+    pub index_block_hash: String,
+    pub block_hash: String,
+    pub consensus_hash: String,
+    pub parent_block_hash: String,
+    pub height: u64,
+    pub burn_block_hash: String,
+    pub burn_block_height: u32,
+    pub burn_block_time: u64,
+    pub withdrawal_root: String,
+    pub block_size: u64,
+    /// Number of transactions in the block, if this node stores it
+    pub tx_count: Option<u64>,
+    pub execution_cost: ExecutionCost,
+    /// The block, if this node stores it: hex of its consensus serialization, or its JSON
+    /// decoding if the client asked for `format=json`
+    pub block: Option<serde_json::Value>,
+}
+
+/// A page of the canonical fork's blocks, newest first.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RPCBlockListResponse {
+    pub tip_height: u64,
+    pub start_height: u64,
+    pub limit: u64,
+    /// The `start_height` of the next page, if there are older blocks
+    pub next_height: Option<u64>,
+    pub blocks: Vec<RPCBlockListEntry>,
+}
+
 #[derive(Serialize, Deserialize)]
 pub struct PostTransactionRequestBody {
     pub tx: String,
@@ -1414,11 +1582,49 @@ pub struct RPCNeighborsInfo {
     pub outbound: Vec<RPCNeighbor>,
 }
 
+/// Body of a `POST /v2/neighbors/allowlist` request: hex-encoded public keys of peers to add to
+/// and remove from the permissioned-mode allowlist
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PeerAllowlistRequestBody {
+    #[serde(default)]
+    pub add: Vec<String>,
+    #[serde(default)]
+    pub remove: Vec<String>,
+}
+
+/// Struct given back from a call to `/v2/neighbors/allowlist`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RPCPeerAllowlistInfo {
+    /// Whether or not this node only talks to allowlisted peers
+    pub permissioned: bool,
+    /// Hex-encoded public keys of the allowlisted peers
+    pub public_keys: Vec<String>,
+}
+
+/// The status of one component of the node, as reported by `/health/live` and `/health/ready`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RPCHealthComponent {
+    pub name: String,
+    pub healthy: bool,
+    /// Human-readable detail, e.g. how long ago something last happened
+    pub message: String,
+}
+
+/// Struct given back from a call to `/health/live` or `/health/ready`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RPCHealthResponse {
+    /// `ok` if every component is healthy, and `unavailable` otherwise
+    pub status: String,
+    pub components: Vec<RPCHealthComponent>,
+}
+
 #[derive(Debug, Clone, PartialEq)]
 pub enum TipRequest {
     UseLatestAnchoredTip,
     UseLatestUnconfirmedTip,
     SpecificTip(StacksBlockId),
+    /// The anchored block at this height in the canonical fork
+    AtHeight(u64),
 }
 
 /// All HTTP request paths we support, and the arguments they carry in their paths
@@ -1428,10 +1634,19 @@ pub enum HttpRequestType {
     GetNeighbors(HttpRequestMetadata),
     GetHeaders(HttpRequestMetadata, u64, TipRequest),
     GetBlock(HttpRequestMetadata, StacksBlockId),
+    /// Get the block at the given height in the fork of the given tip
+    GetBlockByHeight(HttpRequestMetadata, u64, TipRequest),
+    /// List the blocks in the fork of the given tip, walking back from the given height (or the
+    /// tip), up to the given limit.  The flag is whether to decode the blocks to JSON.
+    GetBlockList(HttpRequestMetadata, Option<u64>, u64, bool, TipRequest),
     GetMicroblocksIndexed(HttpRequestMetadata, StacksBlockId),
     GetMicroblocksConfirmed(HttpRequestMetadata, StacksBlockId),
     GetMicroblocksUnconfirmed(HttpRequestMetadata, StacksBlockId, u16),
     GetTransactionUnconfirmed(HttpRequestMetadata, Txid),
+    GetTransaction(HttpRequestMetadata, Txid, TipRequest),
+    /// List an account's pending transactions, starting at the given offset, up to the given
+    /// limit
+    GetMempoolPrincipal(HttpRequestMetadata, StacksAddress, u64, u64),
     PostTransaction(HttpRequestMetadata, StacksTransaction, Option<Attachment>),
     PostBlock(HttpRequestMetadata, ConsensusHash, StacksBlock),
     PostMicroblock(HttpRequestMetadata, StacksMicroblock, TipRequest),
@@ -1458,6 +1673,19 @@ pub enum HttpRequestType {
         contract_identifier: QualifiedContractIdentifier,
         id: u128,
     },
+    GetWithdrawalStxToSubnet {
+        metadata: HttpRequestMetadata,
+        withdraw_block_height: u64,
+        recipient: PrincipalData,
+        withdrawal_id: u32,
+        destination: QualifiedContractIdentifier,
+        amount: u128,
+    },
+    PostWithdrawalMultiProof {
+        metadata: HttpRequestMetadata,
+        withdraw_block_height: u64,
+        withdrawals: Vec<BatchStxWithdrawal>,
+    },
     GetAccount(HttpRequestMetadata, PrincipalData, TipRequest, bool),
     GetDataVar(
         HttpRequestMetadata,
@@ -1506,7 +1734,20 @@ pub enum HttpRequestType {
         TipRequest,
     ),
     MemPoolQuery(HttpRequestMetadata, MemPoolSyncData, Option<Txid>),
+    /// Subscribe to a server-sent event stream of the given events keys
+    GetEventStream(HttpRequestMetadata, Vec<String>),
     BlockProposal(HttpRequestMetadata, SignedProposal),
+    GetHealthLive(HttpRequestMetadata),
+    GetHealthReady(HttpRequestMetadata),
+    GetPeerAllowlist(HttpRequestMetadata),
+    /// Add the first list of peers to, and remove the second list of peers from, the
+    /// permissioned-mode allowlist.  The last field is the request's `Authorization` header.
+    PostPeerAllowlist(
+        HttpRequestMetadata,
+        Vec<Secp256k1PublicKey>,
+        Vec<Secp256k1PublicKey>,
+        Option<String>,
+    ),
     /// catch-all for any errors we should surface from parsing
     ClientError(HttpRequestMetadata, ClientError),
 }
@@ -1600,6 +1841,8 @@ pub enum HttpResponseType {
     PeerInfo(HttpResponseMetadata, RPCPeerInfoData),
     PoxInfo(HttpResponseMetadata, RPCPoxInfoData),
     Neighbors(HttpResponseMetadata, RPCNeighborsInfo),
+    PeerAllowlist(HttpResponseMetadata, RPCPeerAllowlistInfo),
+    HealthStatus(HttpResponseMetadata, RPCHealthResponse),
     Headers(HttpResponseMetadata, Vec<ExtendedStacksHeader>),
     HeaderStream(HttpResponseMetadata),
     Block(HttpResponseMetadata, StacksBlock),
@@ -1615,13 +1858,18 @@ pub enum HttpResponseType {
     CallReadOnlyFunction(HttpResponseMetadata, CallReadOnlyResponse),
     GetAccount(HttpResponseMetadata, AccountEntryResponse),
     GetWithdrawal(HttpResponseMetadata, WithdrawalResponse),
+    GetWithdrawalMultiProof(HttpResponseMetadata, WithdrawalMultiProofResponse),
     GetContractABI(HttpResponseMetadata, ContractInterface),
     GetContractSrc(HttpResponseMetadata, ContractSrcResponse),
     GetIsTraitImplemented(HttpResponseMetadata, GetIsTraitImplementedResponse),
     UnconfirmedTransaction(HttpResponseMetadata, UnconfirmedTransactionResponse),
+    ConfirmedTransaction(HttpResponseMetadata, RPCConfirmedTransaction),
+    MempoolPrincipalTxs(HttpResponseMetadata, RPCMempoolPrincipalResponse),
+    BlockList(HttpResponseMetadata, RPCBlockListResponse),
     GetAttachment(HttpResponseMetadata, GetAttachmentResponse),
     GetAttachmentsInv(HttpResponseMetadata, GetAttachmentsInvResponse),
     MemPoolTxStream(HttpResponseMetadata),
+    EventStream(HttpResponseMetadata),
     MemPoolTxs(HttpResponseMetadata, Option<Txid>, Vec<StacksTransaction>),
     OptionsPreflight(HttpResponseMetadata),
     TransactionFeeEstimation(HttpResponseMetadata, RPCFeeEstimateResponse),
@@ -1673,6 +1921,8 @@ pub enum StacksMessageID {
     Pong = 16,
     NatPunchRequest = 17,
     NatPunchReply = 18,
+    BlockProposal = 19,
+    BlockProposalSignature = 20,
     // reserved
     Reserved = 255,
 }
@@ -1937,6 +2187,8 @@ pub struct NetworkResult {
     pub uploaded_microblocks: Vec<MicroblocksData>,    // microblocks sent to us by the http server
     pub attachments: Vec<(AttachmentInstance, Attachment)>,
     pub synced_transactions: Vec<StacksTransaction>, // transactions we downloaded via a mempool sync
+    pub block_proposal_signatures: Vec<(Secp256k1PublicKey, BlockProposalSignatureData)>, // replies to our block proposals, and which federation peer sent them
+    pub block_proposals: Vec<(NeighborKey, SignedProposal)>, // block proposals from federation peers, for the relayer to answer
     pub num_state_machine_passes: u64,
     pub num_inv_sync_passes: u64,
     pub num_download_passes: u64,
@@ -1960,6 +2212,8 @@ impl NetworkResult {
             uploaded_microblocks: vec![],
             attachments: vec![],
             synced_transactions: vec![],
+            block_proposal_signatures: vec![],
+            block_proposals: vec![],
             num_state_machine_passes: num_state_machine_passes,
             num_inv_sync_passes: num_inv_sync_passes,
             num_download_passes: num_download_passes,
@@ -1986,6 +2240,10 @@ impl NetworkResult {
         self.attachments.len() > 0
     }
 
+    pub fn has_block_proposals(&self) -> bool {
+        self.block_proposals.len() > 0
+    }
+
     pub fn transactions(&self) -> Vec<StacksTransaction> {
         self.pushed_transactions
             .values()
@@ -2000,6 +2258,7 @@ impl NetworkResult {
             || self.has_microblocks()
             || self.has_transactions()
             || self.has_attachments()
+            || self.has_block_proposals()
     }
 
     pub fn consume_unsolicited(
@@ -2035,6 +2294,9 @@ impl NetworkResult {
                                 .insert(neighbor_key.clone(), vec![(message.relayers, tx_data)]);
                         }
                     }
+                    StacksMessageType::BlockProposal(proposal) => {
+                        self.block_proposals.push((neighbor_key.clone(), proposal));
+                    }
                     _ => {
                         // forward along
                         if let Some(messages) = self.unhandled_messages.get_mut(&neighbor_key) {
@@ -2663,7 +2925,7 @@ pub mod test {
         pub relayer: Relayer,
         pub mempool: Option<MemPoolDB>,
         pub chainstate_path: String,
-        pub coord: ChainsCoordinator<'a, TestEventObserver, (), OnChainRewardSetProvider, (), ()>,
+        pub coord: ChainsCoordinator<'a, TestEventObserver, (), (), ()>,
     }
 
     impl<'a> TestPeer<'a> {
@@ -2835,7 +3097,6 @@ pub mod test {
                 &config.burnchain,
                 config.network_id,
                 &test_path,
-                OnChainRewardSetProvider(),
                 tx,
                 observer,
             );
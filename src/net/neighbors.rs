@@ -795,6 +795,7 @@ impl NeighborWalk {
                         );
                         let neighbors =
                             NeighborWalk::filter_sensible_neighbors(data.neighbors.clone());
+                        let neighbors = network.filter_allowlisted_neighbors(neighbors)?;
                         let (mut found, to_resolve) = NeighborWalk::lookup_stale_neighbors(
                             network.peerdb.conn(),
                             message.preamble.network_id,
@@ -1717,13 +1718,23 @@ impl PeerNetwork {
         block_height: u64,
     ) -> Result<Vec<Neighbor>, net_error> {
         let cur_epoch = self.get_current_epoch();
-        let neighbors = PeerDB::get_random_walk_neighbors(
-            &self.peerdb.conn(),
-            self.local_peer.network_id,
-            cur_epoch.network_epoch,
-            num_neighbors as u32,
-            block_height,
-        )
+        let neighbors = if self.connection_opts.permissioned {
+            PeerDB::get_random_allowlisted_neighbors(
+                &self.peerdb.conn(),
+                self.local_peer.network_id,
+                cur_epoch.network_epoch,
+                num_neighbors as u32,
+                block_height,
+            )
+        } else {
+            PeerDB::get_random_walk_neighbors(
+                &self.peerdb.conn(),
+                self.local_peer.network_id,
+                cur_epoch.network_epoch,
+                num_neighbors as u32,
+                block_height,
+            )
+        }
         .map_err(net_error::DBError)?;
 
         if neighbors.len() == 0 {
@@ -1736,6 +1747,25 @@ impl PeerNetwork {
         Ok(neighbors)
     }
 
+    /// In permissioned mode, drop reported neighbors whose public keys are not on the allowlist,
+    /// so we don't bother connecting to them.  Otherwise, keep them all.
+    pub fn filter_allowlisted_neighbors(
+        &self,
+        neighbors: Vec<NeighborAddress>,
+    ) -> Result<Vec<NeighborAddress>, net_error> {
+        if !self.connection_opts.permissioned {
+            return Ok(neighbors);
+        }
+        let allowlist: HashSet<Hash160> = PeerDB::get_allowlisted_peers(self.peerdb.conn())?
+            .iter()
+            .map(|public_key| Hash160::from_node_public_key(public_key))
+            .collect();
+        Ok(neighbors
+            .into_iter()
+            .filter(|neighbor| allowlist.contains(&neighbor.public_key_hash))
+            .collect())
+    }
+
     /// Send off a handshake to a remote peer
     fn walk_handshake(
         &mut self,
@@ -46,6 +46,7 @@ use crate::burnchains::PublicKey;
 use crate::chainstate::burn::db::sortdb::{BlockHeaderCache, SortitionDB};
 use crate::chainstate::burn::BlockSnapshot;
 use crate::chainstate::stacks::db::StacksChainState;
+use crate::chainstate::stacks::miner::SignedProposal;
 use crate::chainstate::stacks::{MAX_BLOCK_LEN, MAX_TRANSACTION_LEN};
 use crate::monitoring::{update_inbound_neighbors, update_outbound_neighbors};
 use crate::net::asn::ASEntry4;
@@ -97,6 +98,8 @@ pub enum NetworkRequest {
     ), // announce to all wanting neighbors that we have these confirmed microblock streams
     Relay(NeighborKey, StacksMessage),
     Broadcast(Vec<RelayData>, StacksMessageType),
+    ProposeBlock(SignedProposal), // send a block proposal to each connected federation peer
+    AnswerBlockProposal(NeighborKey, BlockProposalSignatureData), // send our answer to a federation peer's block proposal
 }
 
 /// Handle for other threads to use to issue p2p network requests.
@@ -182,6 +185,24 @@ impl NetworkHandle {
         let req = NetworkRequest::Broadcast(relay_hints, msg);
         self.send_request(req)
     }
+
+    /// Send a block proposal to each connected federation peer via the p2p network thread.
+    /// Their replies show up in `NetworkResult::block_proposal_signatures`.
+    pub fn propose_block(&mut self, proposal: SignedProposal) -> Result<(), net_error> {
+        let req = NetworkRequest::ProposeBlock(proposal);
+        self.send_request(req)
+    }
+
+    /// Send our answer to a block proposal back to the federation peer that proposed it, via
+    /// the p2p network thread
+    pub fn answer_block_proposal(
+        &mut self,
+        neighbor_key: NeighborKey,
+        answer: BlockProposalSignatureData,
+    ) -> Result<(), net_error> {
+        let req = NetworkRequest::AnswerBlockProposal(neighbor_key, answer);
+        self.send_request(req)
+    }
 }
 
 impl NetworkHandleServer {
@@ -346,6 +367,10 @@ pub struct PeerNetwork {
     // can't process yet, but might be able to process on the next chain view update
     pub pending_messages: HashMap<usize, Vec<StacksMessage>>,
 
+    // replies to our block proposals received from federation peers since the last call to
+    // run(), and who sent them
+    block_proposal_signatures: Vec<(Secp256k1PublicKey, BlockProposalSignatureData)>,
+
     // fault injection -- force disconnects
     fault_last_disconnect: u64,
 }
@@ -457,6 +482,8 @@ impl PeerNetwork {
 
             pending_messages: HashMap::new(),
 
+            block_proposal_signatures: vec![],
+
             fault_last_disconnect: 0,
         };
 
@@ -856,6 +883,25 @@ impl PeerNetwork {
                 );
                 return Err(net_error::Denied);
             }
+
+            // in permissioned mode, don't talk to peers we know aren't allowlisted.  Peers we
+            // don't know yet are checked when they handshake.
+            if self.connection_opts.permissioned {
+                if let Some(peer) = PeerDB::get_peer(
+                    self.peerdb.conn(),
+                    neighbor.network_id,
+                    &neighbor.addrbytes,
+                    neighbor.port,
+                )? {
+                    if !PeerDB::is_peer_allowlisted(self.peerdb.conn(), &peer.public_key)? {
+                        debug!(
+                            "{:?}: Neighbor {:?} is not allowlisted; will not connect",
+                            &self.local_peer, neighbor
+                        );
+                        return Err(net_error::Denied);
+                    }
+                }
+            }
         }
 
         // already connected?
@@ -1076,7 +1122,157 @@ impl PeerNetwork {
                 self.broadcast_message(neighbor_keys, relay_hints, msg);
                 Ok(())
             }
+            NetworkRequest::ProposeBlock(proposal) => {
+                self.propose_block(proposal);
+                Ok(())
+            }
+            NetworkRequest::AnswerBlockProposal(neighbor_key, answer) => {
+                self.answer_block_proposal(&neighbor_key, answer)
+            }
+        }
+    }
+
+    /// Send a block proposal to each authenticated federation peer we're connected to.
+    /// Proposals are never relayed any further than that.
+    /// Returns the number of peers the proposal was sent to.
+    pub fn propose_block(&mut self, proposal: SignedProposal) -> usize {
+        let event_ids: Vec<usize> = self
+            .peers
+            .iter()
+            .filter(|(_, convo)| convo.is_authenticated() && convo.is_federation_peer())
+            .map(|(event_id, _)| *event_id)
+            .collect();
+
+        let proposal_hash = proposal.message_hash();
+        let payload = StacksMessageType::BlockProposal(proposal);
+        let mut num_sent = 0;
+        for event_id in event_ids.into_iter() {
+            let convo = match self.peers.get_mut(&event_id) {
+                Some(convo) => convo,
+                None => continue,
+            };
+            let nk = convo.to_neighbor_key();
+            let res = convo
+                .sign_message(
+                    &self.chain_view,
+                    &self.local_peer.private_key,
+                    payload.clone(),
+                )
+                .and_then(|msg| convo.relay_signed_message(msg));
+            match res {
+                Ok(rh) => {
+                    debug!(
+                        "{:?}: Sent block proposal {} to {:?}",
+                        &self.local_peer, &proposal_hash, &nk
+                    );
+                    self.add_relay_handle(event_id, rh);
+                    num_sent += 1;
+                }
+                Err(e) => {
+                    warn!(
+                        "{:?}: Failed to send block proposal {} to {:?}: {:?}",
+                        &self.local_peer, &proposal_hash, &nk, &e
+                    );
+                }
+            }
+        }
+        if num_sent == 0 {
+            warn!(
+                "{:?}: Not connected to any federation peers; block proposal {} was not sent",
+                &self.local_peer, &proposal_hash
+            );
+        }
+        num_sent
+    }
+
+    /// Send our answer to a block proposal to the federation peer `neighbor_key` that
+    /// proposed it
+    pub fn answer_block_proposal(
+        &mut self,
+        neighbor_key: &NeighborKey,
+        answer: BlockProposalSignatureData,
+    ) -> Result<(), net_error> {
+        // the relayer got the proposal keyed by the neighbor key of the conversation it came
+        // in on, which may be an inbound one
+        let event_id = match self
+            .peers
+            .iter()
+            .find(|(_, convo)| convo.to_neighbor_key() == *neighbor_key)
+        {
+            Some((event_id, _)) => *event_id,
+            None => {
+                info!(
+                    "{:?}: No ongoing conversation with {:?}; dropping answer to block proposal {}",
+                    &self.local_peer, &neighbor_key, &answer.proposal_hash
+                );
+                return Err(net_error::PeerNotConnected);
+            }
+        };
+        let convo = self
+            .peers
+            .get_mut(&event_id)
+            .expect("BUG: conversation disappeared");
+        if !convo.is_federation_peer() {
+            warn!(
+                "{:?}: Will not answer block proposal {} from non-federation peer {:?}",
+                &self.local_peer, &answer.proposal_hash, &neighbor_key
+            );
+            return Err(net_error::InvalidMessage);
+        }
+        let proposal_hash = answer.proposal_hash.clone();
+        let rh = convo
+            .sign_message(
+                &self.chain_view,
+                &self.local_peer.private_key,
+                StacksMessageType::BlockProposalSignature(answer),
+            )
+            .and_then(|msg| convo.relay_signed_message(msg))?;
+        debug!(
+            "{:?}: Sent answer to block proposal {} to {:?}",
+            &self.local_peer, &proposal_hash, &neighbor_key
+        );
+        self.add_relay_handle(event_id, rh);
+        Ok(())
+    }
+
+    /// Add peers to and remove peers from the permissioned-mode allowlist.  If we're in
+    /// permissioned mode, removed peers are disconnected right away.
+    /// Returns the updated allowlist.
+    pub fn update_peer_allowlist(
+        &mut self,
+        add: &[Secp256k1PublicKey],
+        remove: &[Secp256k1PublicKey],
+    ) -> Result<Vec<Secp256k1PublicKey>, net_error> {
+        let mut tx = self.peerdb.tx_begin()?;
+        for public_key in add.iter() {
+            PeerDB::add_allowlisted_peer(&mut tx, public_key)?;
+        }
+        for public_key in remove.iter() {
+            PeerDB::remove_allowlisted_peer(&mut tx, public_key)?;
+        }
+        tx.commit()
+            .map_err(|e| net_error::DBError(db_error::SqliteError(e)))?;
+
+        if self.connection_opts.permissioned {
+            let to_remove: Vec<usize> = self
+                .peers
+                .iter()
+                .filter(|(_, convo)| match convo.get_public_key() {
+                    Some(public_key) => remove.contains(&public_key),
+                    None => false,
+                })
+                .map(|(event_id, _)| *event_id)
+                .collect();
+            for event_id in to_remove.into_iter() {
+                info!(
+                    "{:?}: Disconnect event {}: peer was removed from the allowlist",
+                    &self.local_peer, event_id
+                );
+                self.deregister_peer(event_id);
+            }
         }
+
+        Ok(PeerDB::get_allowlisted_peers(self.peerdb.conn())?)
     }
 
     /// Process any handle requests from other threads.
@@ -4652,6 +4848,22 @@ impl PeerNetwork {
                 // only forward to the relayer if we don't need to buffer it.
                 (to_buffer, true)
             }
+            StacksMessageType::BlockProposalSignature(ref data) => {
+                // ConversationP2P only passes these along from federation peers
+                let pubkey_opt = self
+                    .peers
+                    .get(&event_id)
+                    .and_then(|convo| convo.get_public_key());
+                if let Some(pubkey) = pubkey_opt {
+                    self.block_proposal_signatures.push((pubkey, data.clone()));
+                }
+                (false, false)
+            }
+            StacksMessageType::BlockProposal(_) => {
+                // ConversationP2P only passes these along from federation peers. Forward to
+                // the relayer, which validates the proposal and answers it.
+                (false, true)
+            }
             _ => (false, true),
         }
     }
@@ -5308,6 +5520,10 @@ impl PeerNetwork {
             p2p_poll_state,
         )?;
 
+        network_result
+            .block_proposal_signatures
+            .append(&mut self.block_proposal_signatures);
+
         debug!("<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<< End Network Dispatch <<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<");
         Ok(network_result)
     }
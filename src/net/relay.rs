@@ -34,6 +34,7 @@ use crate::chainstate::burn::ConsensusHash;
 use crate::chainstate::coordinator::comm::CoordinatorChannels;
 use crate::chainstate::stacks::db::{StacksChainState, StacksEpochReceipt, StacksHeaderInfo};
 use crate::chainstate::stacks::events::StacksTransactionReceipt;
+use crate::chainstate::stacks::miner::SignedProposal;
 use crate::chainstate::stacks::StacksBlockHeader;
 use crate::core::mempool::MemPoolDB;
 use crate::core::mempool::*;
@@ -68,6 +69,9 @@ pub const RELAY_DUPLICATE_INFERENCE_WARMUP: usize = 128;
 pub struct Relayer {
     /// Connection to the p2p thread
     p2p: NetworkHandle,
+    /// The p2p network's connection options, which say whether and how to answer block
+    /// proposals from the federation
+    connection_opts: ConnectionOptions,
 }
 
 #[derive(Debug)]
@@ -438,13 +442,16 @@ impl RelayerStats {
 }
 
 impl Relayer {
-    pub fn new(handle: NetworkHandle) -> Relayer {
-        Relayer { p2p: handle }
+    pub fn new(handle: NetworkHandle, connection_opts: ConnectionOptions) -> Relayer {
+        Relayer {
+            p2p: handle,
+            connection_opts,
+        }
     }
 
     pub fn from_p2p(network: &mut PeerNetwork) -> Relayer {
         let handle = network.new_handle(1024);
-        Relayer::new(handle)
+        Relayer::new(handle, network.connection_opts.clone())
     }
 
     /// Given blocks pushed to us, verify that they correspond to expected block data.
@@ -1118,6 +1125,48 @@ impl Relayer {
             .broadcast_message(vec![], StacksMessageType::Blocks(blocks_data))
     }
 
+    /// Send a signed block proposal to the federation peers this node is connected to
+    pub fn propose_block(&mut self, proposal: SignedProposal) -> Result<(), net_error> {
+        self.p2p.propose_block(proposal)
+    }
+
+    /// Validate the block proposals that federation peers sent us, and have the p2p thread
+    /// send each proposer our signature, or the reason we won't sign.  This runs here rather
+    /// than in the p2p thread, since validating a proposal means executing its block.
+    pub fn process_block_proposals(
+        &mut self,
+        network_result: &mut NetworkResult,
+        sortdb: &SortitionDB,
+        chainstate: &StacksChainState,
+    ) {
+        for (neighbor_key, proposal) in network_result.block_proposals.drain(..) {
+            let proposal_hash = proposal.message_hash();
+            let response = match proposal.validate_and_sign(
+                chainstate,
+                &sortdb.index_conn(),
+                &self.connection_opts.allowed_block_proposers,
+                self.connection_opts.subnet_validator.as_ref(),
+                self.connection_opts.subnet_signing_contract.as_ref(),
+            ) {
+                Ok(signature) => BlockProposalResponse::Accepted(signature),
+                Err(reason) => {
+                    info!(
+                        "Will not sign block proposal {} from {:?}: {}",
+                        &proposal_hash, &neighbor_key, &reason
+                    );
+                    BlockProposalResponse::Rejected(reason)
+                }
+            };
+            let answer = BlockProposalSignatureData {
+                proposal_hash,
+                response,
+            };
+            if let Err(e) = self.p2p.answer_block_proposal(neighbor_key, answer) {
+                warn!("Failed to answer block proposal: {:?}", &e);
+            }
+        }
+    }
+
     pub fn broadcast_microblock(
         &mut self,
         block_consensus_hash: &ConsensusHash,
@@ -1332,6 +1381,10 @@ impl Relayer {
             }
         }
 
+        if network_result.has_block_proposals() {
+            self.process_block_proposals(network_result, sortdb, chainstate);
+        }
+
         let mut processed_unconfirmed_state = Default::default();
 
         // finally, refresh the unconfirmed chainstate, if need be.
@@ -2153,6 +2206,49 @@ mod test {
         assert_eq!(*ranking.get(&nk_3).unwrap(), 4 - 2 + 1);
     }
 
+    #[test]
+    fn test_consume_unsolicited_block_proposals() {
+        let nk = NeighborKey {
+            peer_version: 12345,
+            network_id: 0x80000000,
+            addrbytes: PeerAddress([0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0xff, 0xff, 127, 0, 0, 1]),
+            port: 54321,
+        };
+        let proposal = SignedProposal {
+            message: "00".to_string(),
+            signature: stacks_common::util::secp256k1::MessageSignature::empty(),
+        };
+        let message = |payload| {
+            StacksMessage::new(
+                12345,
+                0x80000000,
+                12345,
+                &BurnchainHeaderHash([0x11; 32]),
+                12339,
+                &BurnchainHeaderHash([0x22; 32]),
+                payload,
+            )
+        };
+
+        let mut unhandled = HashMap::new();
+        unhandled.insert(
+            nk.clone(),
+            vec![
+                message(StacksMessageType::BlockProposal(proposal.clone())),
+                message(StacksMessageType::Ping(PingData { nonce: 1 })),
+            ],
+        );
+
+        // proposals are set aside for the relayer to answer; everything else is passed along
+        let mut network_result = NetworkResult::new(0, 0, 0);
+        assert!(!network_result.has_data_to_store());
+        network_result.consume_unsolicited(unhandled);
+        assert_eq!(network_result.block_proposals, vec![(nk.clone(), proposal)]);
+        assert!(network_result.has_block_proposals());
+        assert!(network_result.has_data_to_store());
+        assert_eq!(network_result.unhandled_messages[&nk].len(), 1);
+    }
+
     #[test]
     #[ignore]
     fn test_get_blocks_and_microblocks_3_peers_push_available() {
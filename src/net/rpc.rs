@@ -17,6 +17,7 @@
  along with Blockstack. If not, see <http://www.gnu.org/licenses/>.
 */
 
+use std::cmp;
 use std::collections::HashMap;
 use std::collections::HashSet;
 use std::collections::VecDeque;
@@ -27,16 +28,14 @@ use std::net::SocketAddr;
 use std::time::Instant;
 use std::{convert::TryFrom, fmt};
 
-use clarity::util::hash::MerklePathOrder;
-use clarity::util::hash::MerklePathPoint;
 use clarity::util::hash::MerkleTree;
 use clarity::util::hash::Sha512Trunc256Sum;
 use clarity::vm::types::AssetIdentifier;
-use clarity::vm::types::TupleData;
 use rand::prelude::*;
 use rand::thread_rng;
 use rusqlite::{DatabaseName, NO_PARAMS};
 use stacks_common::util::secp256k1::Secp256k1PrivateKey;
+use stacks_common::util::secp256k1::Secp256k1PublicKey;
 
 use crate::burnchains::Burnchain;
 use crate::burnchains::BurnchainView;
@@ -45,12 +44,14 @@ use crate::chainstate::burn::db::sortdb::SortitionDB;
 use crate::chainstate::burn::ConsensusHash;
 use crate::chainstate::stacks::db::blocks::CheckError;
 use crate::chainstate::stacks::db::{
-    blocks::MINIMUM_TX_FEE_RATE_PER_BYTE, StacksChainState, StreamCursor,
+    blocks::MINIMUM_TX_FEE_RATE_PER_BYTE, StacksChainState, StacksHeaderInfo, StreamCursor,
 };
 use crate::chainstate::stacks::Error as chain_error;
 use crate::chainstate::stacks::*;
 use crate::clarity_vm::clarity::ClarityConnection;
+use crate::clarity_vm::verify::withdrawal_leaf_hash;
 use crate::clarity_vm::withdrawal;
+use crate::clarity_vm::withdrawal::WithdrawalMultiProof;
 use crate::codec::StacksMessageCodec;
 use crate::core::mempool::*;
 use crate::cost_estimates::metrics::CostMetric;
@@ -62,10 +63,13 @@ use crate::net::connection::ConnectionHttp;
 use crate::net::connection::ConnectionOptions;
 use crate::net::connection::ReplyHandleHttp;
 use crate::net::db::PeerDB;
+use crate::net::event_stream::{EventStreamError, EventStreamProvider};
+use crate::net::health::HealthCheckProvider;
 use crate::net::http::*;
 use crate::net::p2p::PeerMap;
 use crate::net::p2p::PeerNetwork;
 use crate::net::relay::Relayer;
+use crate::net::BatchStxWithdrawal;
 use crate::net::BlocksDatum;
 use crate::net::Error as net_error;
 use crate::net::HttpRequestMetadata;
@@ -79,6 +83,9 @@ use crate::net::NeighborsData;
 use crate::net::PeerAddress;
 use crate::net::PeerHost;
 use crate::net::ProtocolFamily;
+use crate::net::RPCBlockListEntry;
+use crate::net::RPCBlockListResponse;
+use crate::net::RPCConfirmedTransaction;
 use crate::net::RPCFeeEstimate;
 use crate::net::RPCFeeEstimateResponse;
 use crate::net::StacksHttp;
@@ -87,6 +94,7 @@ use crate::net::StacksMessageType;
 use crate::net::UnconfirmedTransactionResponse;
 use crate::net::UnconfirmedTransactionStatus;
 use crate::net::UrlString;
+use crate::net::WithdrawalMultiProofResponse;
 use crate::net::WithdrawalResponse;
 use crate::net::HTTP_REQUEST_ID_RESERVED;
 use crate::net::MAX_HEADERS;
@@ -97,7 +105,9 @@ use crate::net::{
 };
 use crate::net::{BlocksData, GetIsTraitImplementedResponse};
 use crate::net::{ClientError, TipRequest};
-use crate::net::{RPCNeighbor, RPCNeighborsInfo};
+use crate::net::{RPCHealthComponent, RPCHealthResponse};
+use crate::net::{RPCMempoolPrincipalResponse, RPCPendingTransaction};
+use crate::net::{RPCNeighbor, RPCNeighborsInfo, RPCPeerAllowlistInfo};
 use crate::net::{RPCPeerInfoData, RPCPoxInfoData};
 use crate::util_lib::db::DBConn;
 use crate::util_lib::db::Error as db_error;
@@ -145,6 +155,8 @@ pub struct RPCHandlerArgs<'a> {
     pub cost_estimator: Option<&'a dyn CostEstimator>,
     pub fee_estimator: Option<&'a dyn FeeEstimator>,
     pub cost_metric: Option<&'a dyn CostMetric>,
+    pub event_stream: Option<&'a dyn EventStreamProvider>,
+    pub health: Option<&'a dyn HealthCheckProvider>,
 }
 
 pub struct ConversationHttp {
@@ -238,6 +250,15 @@ impl RPCPeerInfoData {
             None => (None, None),
         };
 
+        let pruned_height = match StacksChainState::get_pruned_height(chainstate.db()) {
+            Ok(0) => None,
+            Ok(height) => Some(height),
+            Err(e) => {
+                warn!("Failed to load pruned height: {:?}", &e);
+                None
+            }
+        };
+
         let public_key = StacksPublicKey::from_private(&network.local_peer.private_key);
         let public_key_buf = StacksPublicKeyBuffer::from_public_key(&public_key);
         let public_key_hash = Hash160::from_node_public_key(&public_key);
@@ -264,6 +285,7 @@ impl RPCPeerInfoData {
             node_public_key: Some(public_key_buf),
             node_public_key_hash: Some(public_key_hash),
             l1_subnet_governing_contract: network.burnchain.subnet_governing_contract.to_string(),
+            pruned_height,
         }
     }
 }
@@ -594,6 +616,190 @@ impl ConversationHttp {
         response.send(http, fd)
     }
 
+    /// Handle a GET of `/v2/neighbors/allowlist`.  Only clients on localhost may read the
+    /// allowlist.
+    /// The response will be synchronously written to the given fd (so use a fd that can buffer!)
+    fn handle_get_peer_allowlist<W: Write>(
+        http: &mut StacksHttp,
+        fd: &mut W,
+        req: &HttpRequestType,
+        client_addr: &SocketAddr,
+        network: &PeerNetwork,
+        canonical_stacks_tip_height: u64,
+    ) -> Result<(), net_error> {
+        let response_metadata =
+            HttpResponseMetadata::from_http_request_type(req, Some(canonical_stacks_tip_height));
+        if !client_addr.ip().is_loopback() {
+            let response = HttpResponseType::Forbidden(
+                response_metadata,
+                "The peer allowlist is only available from localhost".to_string(),
+            );
+            return response.send(http, fd);
+        }
+
+        let response = match PeerDB::get_allowlisted_peers(network.peerdb.conn()) {
+            Ok(public_keys) => ConversationHttp::make_peer_allowlist_response(
+                response_metadata,
+                network,
+                &public_keys,
+            ),
+            Err(e) => {
+                warn!("Failed to read peer allowlist: {:?}", &e);
+                HttpResponseType::ServerError(
+                    response_metadata,
+                    "Failed to read peer allowlist".to_string(),
+                )
+            }
+        };
+        response.send(http, fd)
+    }
+
+    /// Handle a POST to `/v2/neighbors/allowlist`.  The allowlist can only be edited if the
+    /// node has a `peer_allowlist_admin_token`, by requests that send it as their
+    /// `Authorization` header.
+    /// The response will be synchronously written to the given fd (so use a fd that can buffer!)
+    fn handle_post_peer_allowlist<W: Write>(
+        http: &mut StacksHttp,
+        fd: &mut W,
+        req: &HttpRequestType,
+        network: &mut PeerNetwork,
+        add: &[Secp256k1PublicKey],
+        remove: &[Secp256k1PublicKey],
+        authorization: Option<&str>,
+        canonical_stacks_tip_height: u64,
+    ) -> Result<(), net_error> {
+        let response_metadata =
+            HttpResponseMetadata::from_http_request_type(req, Some(canonical_stacks_tip_height));
+        let admin_token = match network.connection_opts.peer_allowlist_admin_token.as_ref() {
+            Some(admin_token) => admin_token,
+            None => {
+                let response = HttpResponseType::Forbidden(
+                    response_metadata,
+                    "Editing the peer allowlist over RPC is disabled".to_string(),
+                );
+                return response.send(http, fd);
+            }
+        };
+        if !ConversationHttp::is_admin_token(admin_token, authorization) {
+            let response = HttpResponseType::Unauthorized(
+                response_metadata,
+                "Missing or invalid Authorization header".to_string(),
+            );
+            return response.send(http, fd);
+        }
+
+        let response = match network.update_peer_allowlist(add, remove) {
+            Ok(public_keys) => {
+                info!(
+                    "Updated peer allowlist";
+                    "added" => add.len(),
+                    "removed" => remove.len(),
+                    "allowlisted" => public_keys.len()
+                );
+                ConversationHttp::make_peer_allowlist_response(
+                    response_metadata,
+                    network,
+                    &public_keys,
+                )
+            }
+            Err(e) => {
+                warn!("Failed to update peer allowlist: {:?}", &e);
+                HttpResponseType::ServerError(
+                    response_metadata,
+                    "Failed to update peer allowlist".to_string(),
+                )
+            }
+        };
+        response.send(http, fd)
+    }
+
+    fn make_peer_allowlist_response(
+        response_metadata: HttpResponseMetadata,
+        network: &PeerNetwork,
+        public_keys: &[Secp256k1PublicKey],
+    ) -> HttpResponseType {
+        HttpResponseType::PeerAllowlist(
+            response_metadata,
+            RPCPeerAllowlistInfo {
+                permissioned: network.connection_opts.permissioned,
+                public_keys: public_keys
+                    .iter()
+                    .map(|public_key| to_hex(&public_key.to_bytes_compressed()))
+                    .collect(),
+            },
+        )
+    }
+
+    /// Is `authorization` the admin token?  The comparison takes the same time wherever the two
+    /// first differ, so that the token cannot be guessed byte by byte.
+    fn is_admin_token(admin_token: &str, authorization: Option<&str>) -> bool {
+        let authorization = match authorization {
+            Some(authorization) => authorization.as_bytes(),
+            None => return false,
+        };
+        authorization.len() == admin_token.len()
+            && authorization
+                .iter()
+                .zip(admin_token.as_bytes().iter())
+                .fold(0u8, |diff, (a, b)| diff | (a ^ b))
+                == 0
+    }
+
+    /// Handle a GET of `/health/live`.  If the node can answer at all, it's alive.
+    /// The response will be synchronously written to the given fd (so use a fd that can buffer!)
+    fn handle_get_health_live<W: Write>(
+        http: &mut StacksHttp,
+        fd: &mut W,
+        req: &HttpRequestType,
+        canonical_stacks_tip_height: u64,
+    ) -> Result<(), net_error> {
+        let response_metadata =
+            HttpResponseMetadata::from_http_request_type(req, Some(canonical_stacks_tip_height));
+        let response = HttpResponseType::HealthStatus(
+            response_metadata,
+            RPCHealthResponse::from_components(vec![RPCHealthComponent::healthy(
+                "rpc",
+                "Serving requests".to_string(),
+            )]),
+        );
+        response.send(http, fd)
+    }
+
+    /// Handle a GET of `/health/ready`.  The node is ready if its mempool is readable, and if
+    /// `health` (if given) finds the rest of the node in order.
+    /// The response will be synchronously written to the given fd (so use a fd that can buffer!)
+    fn handle_get_health_ready<W: Write>(
+        http: &mut StacksHttp,
+        fd: &mut W,
+        req: &HttpRequestType,
+        mempool: &MemPoolDB,
+        health: Option<&dyn HealthCheckProvider>,
+        sortition_tip_height: u64,
+        canonical_stacks_tip_height: u64,
+    ) -> Result<(), net_error> {
+        let response_metadata =
+            HttpResponseMetadata::from_http_request_type(req, Some(canonical_stacks_tip_height));
+
+        let mut components = vec![match MemPoolDB::count_txs(mempool.conn()) {
+            Ok(count) => {
+                RPCHealthComponent::healthy("mempool", format!("{} pending transactions", count))
+            }
+            Err(e) => {
+                warn!("Health check failed to read the mempool: {:?}", &e);
+                RPCHealthComponent::unhealthy("mempool", format!("Failed to read mempool: {}", e))
+            }
+        }];
+        if let Some(health) = health {
+            components.extend(health.check_readiness(sortition_tip_height));
+        }
+
+        let response = HttpResponseType::HealthStatus(
+            response_metadata,
+            RPCHealthResponse::from_components(components),
+        );
+        response.send(http, fd)
+    }
+
     /// Handle a not-found
     fn handle_notfound<W: Write>(
         http: &mut StacksHttp,
@@ -687,16 +893,36 @@ impl ConversationHttp {
         let response_metadata =
             HttpResponseMetadata::from_http_request_type(req, Some(canonical_stacks_tip_height));
 
-        // do we have this block?
-        match StacksChainState::has_block_indexed(&chainstate.blocks_path, index_block_hash) {
-            Ok(false) => {
-                return ConversationHttp::handle_notfound(
+        // do we have this block?  (pruned blocks are stored, but have no data)
+        match StacksChainState::has_block_data_indexed(&chainstate.blocks_path, index_block_hash) {
+            Ok(false) => match StacksChainState::is_block_pruned(chainstate.db(), index_block_hash)
+            {
+                Ok(true) => {
+                    let response = HttpResponseType::Error(
+                        response_metadata,
+                        410,
+                        format!(
+                            "Block {} was pruned from this node",
+                            index_block_hash.to_hex()
+                        ),
+                    );
+                    response.send(http, fd).and_then(|_| Ok(None))
+                }
+                Ok(false) => ConversationHttp::handle_notfound(
                     http,
                     fd,
                     response_metadata,
                     format!("No such block {}", index_block_hash.to_hex()),
-                );
-            }
+                ),
+                Err(e) => {
+                    warn!("Failed to serve block {:?}: {:?}", req, &e);
+                    let response = HttpResponseType::ServerError(
+                        response_metadata,
+                        format!("Failed to query block {}", index_block_hash.to_hex()),
+                    );
+                    response.send(http, fd).and_then(|_| Ok(None))
+                }
+            },
             Err(e) => {
                 // nope -- error trying to check
                 warn!("Failed to serve block {:?}: {:?}", req, &e);
@@ -715,6 +941,207 @@ impl ConversationHttp {
         }
     }
 
+    /// Get the anchored block at the given height in the fork of `tip`, if there is one.
+    fn get_block_at_height(
+        chainstate: &StacksChainState,
+        height: u64,
+        tip: &StacksBlockId,
+    ) -> Result<Option<StacksBlockId>, chain_error> {
+        // the MARF indexes block heights as u32s
+        if height >= u32::MAX as u64 {
+            return Ok(None);
+        }
+        Ok(chainstate
+            .index_conn()?
+            .get_ancestor_block_hash(height, tip)?)
+    }
+
+    /// Handle a GET block by height, in the fork of the given tip.  Start streaming the reply,
+    /// like `handle_getblock`.
+    fn handle_getblock_by_height<W: Write>(
+        http: &mut StacksHttp,
+        fd: &mut W,
+        req: &HttpRequestType,
+        tip: &StacksBlockId,
+        height: u64,
+        chainstate: &StacksChainState,
+        canonical_stacks_tip_height: u64,
+    ) -> Result<Option<StreamCursor>, net_error> {
+        let tip = ConversationHttp::get_anchored_tip(chainstate, tip);
+        match ConversationHttp::get_block_at_height(chainstate, height, &tip) {
+            Ok(Some(index_block_hash)) => ConversationHttp::handle_getblock(
+                http,
+                fd,
+                req,
+                &index_block_hash,
+                chainstate,
+                canonical_stacks_tip_height,
+            ),
+            Ok(None) => {
+                let response_metadata = HttpResponseMetadata::from_http_request_type(
+                    req,
+                    Some(canonical_stacks_tip_height),
+                );
+                ConversationHttp::handle_notfound(
+                    http,
+                    fd,
+                    response_metadata,
+                    format!("No block at height {} in the fork of {}", height, &tip),
+                )
+            }
+            Err(e) => {
+                warn!("Failed to look up block at height {}: {:?}", height, &e);
+                let response_metadata = HttpResponseMetadata::from_http_request_type(
+                    req,
+                    Some(canonical_stacks_tip_height),
+                );
+                let response = HttpResponseType::ServerError(
+                    response_metadata,
+                    format!("Failed to query block at height {}", height),
+                );
+                response.send(http, fd).and_then(|_| Ok(None))
+            }
+        }
+    }
+
+    /// Load a page of the blocks in the fork of `tip`, walking back from `start_height`.
+    /// Returns Ok(None) if `tip` is not a processed block.
+    fn load_block_list(
+        chainstate: &StacksChainState,
+        tip: &StacksBlockId,
+        start_height: Option<u64>,
+        limit: u64,
+        json: bool,
+    ) -> Result<Option<RPCBlockListResponse>, chain_error> {
+        let tip_height = match StacksChainState::get_stacks_block_header_info_by_index_block_hash(
+            chainstate.db(),
+            tip,
+        )? {
+            Some(header) => header.stacks_block_height,
+            None => return Ok(None),
+        };
+        let start_height = cmp::min(start_height.unwrap_or(tip_height), tip_height);
+
+        let index_conn = chainstate.index_conn()?;
+        let mut blocks = vec![];
+        let mut next_height = Some(start_height);
+        while (blocks.len() as u64) < limit {
+            let height = match next_height {
+                Some(height) => height,
+                None => break,
+            };
+            let index_block_hash = index_conn
+                .get_ancestor_block_hash(height, tip)?
+                .ok_or(chain_error::NoSuchBlockError)?;
+            let header = StacksChainState::get_stacks_block_header_info_by_index_block_hash(
+                chainstate.db(),
+                &index_block_hash,
+            )?
+            .ok_or(chain_error::NoSuchBlockError)?;
+            blocks.push(ConversationHttp::make_block_list_entry(
+                chainstate, header, json,
+            )?);
+            next_height = height.checked_sub(1);
+        }
+
+        Ok(Some(RPCBlockListResponse {
+            tip_height,
+            start_height,
+            limit,
+            next_height,
+            blocks,
+        }))
+    }
+
+    fn make_block_list_entry(
+        chainstate: &StacksChainState,
+        header: StacksHeaderInfo,
+        json: bool,
+    ) -> Result<RPCBlockListEntry, chain_error> {
+        let index_block_hash = header.index_block_hash();
+        let block_hash = header.anchored_header.block_hash();
+        let execution_cost =
+            StacksChainState::get_stacks_block_anchored_cost(chainstate.db(), &index_block_hash)?
+                .unwrap_or(ExecutionCost::zero());
+
+        // the boot block has no block data
+        let block_bytes = match StacksChainState::load_block_bytes(
+            &chainstate.blocks_path,
+            &header.consensus_hash,
+            &block_hash,
+        ) {
+            Ok(bytes_opt) => bytes_opt,
+            Err(chain_error::DBError(db_error::NotFoundError)) => None,
+            Err(e) => return Err(e),
+        };
+        let (tx_count, block) = match block_bytes {
+            Some(bytes) => {
+                let block = StacksBlock::consensus_deserialize(&mut &bytes[..])?;
+                let block_value = if json {
+                    serde_json::to_value(&block).map_err(|e| {
+                        chain_error::InvalidStacksBlock(format!(
+                            "Failed to encode block as JSON: {:?}",
+                            &e
+                        ))
+                    })?
+                } else {
+                    serde_json::Value::String(to_hex(&bytes))
+                };
+                (Some(block.txs.len() as u64), Some(block_value))
+            }
+            None => (None, None),
+        };
+
+        Ok(RPCBlockListEntry {
+            index_block_hash: index_block_hash.to_hex(),
+            block_hash: block_hash.to_hex(),
+            consensus_hash: header.consensus_hash.to_hex(),
+            parent_block_hash: header.anchored_header.parent_block.to_hex(),
+            height: header.stacks_block_height,
+            burn_block_hash: header.burn_header_hash.to_hex(),
+            burn_block_height: header.burn_header_height,
+            burn_block_time: header.burn_header_timestamp,
+            withdrawal_root: header.anchored_header.withdrawal_merkle_root.to_hex(),
+            block_size: header.anchored_block_size,
+            tx_count,
+            execution_cost,
+            block,
+        })
+    }
+
+    /// Handle a GET block listing, in the fork of the given tip.
+    /// The response will be synchronously written to the fd.
+    fn handle_getblock_list<W: Write>(
+        http: &mut StacksHttp,
+        fd: &mut W,
+        req: &HttpRequestType,
+        tip: &StacksBlockId,
+        start_height: Option<u64>,
+        limit: u64,
+        json: bool,
+        chainstate: &StacksChainState,
+        canonical_stacks_tip_height: u64,
+    ) -> Result<(), net_error> {
+        let response_metadata =
+            HttpResponseMetadata::from_http_request_type(req, Some(canonical_stacks_tip_height));
+        let tip = ConversationHttp::get_anchored_tip(chainstate, tip);
+        let response =
+            match ConversationHttp::load_block_list(chainstate, &tip, start_height, limit, json) {
+                Ok(Some(block_list)) => HttpResponseType::BlockList(response_metadata, block_list),
+                Ok(None) => {
+                    HttpResponseType::NotFound(response_metadata, format!("No such block {}", &tip))
+                }
+                Err(e) => {
+                    warn!("Failed to list blocks in the fork of {}: {:?}", &tip, &e);
+                    HttpResponseType::ServerError(
+                        response_metadata,
+                        format!("Failed to list blocks in the fork of {}", &tip),
+                    )
+                }
+            };
+        response.send(http, fd).map(|_| ())
+    }
+
     /// Handle a GET confirmed microblock stream, by _anchor block hash_.  Start streaming the reply.
     /// The response's preamble (but not the block data) will be synchronously written to the fd
     /// (so use a fd that can buffer!)
@@ -943,74 +1370,20 @@ impl ConversationHttp {
     ) -> Result<(), net_error> {
         let response_metadata =
             HttpResponseMetadata::from_http_request_type(req, Some(canonical_stacks_tip_height));
-        let validator_key = match validator_key {
-            Some(key) => key,
-            None => {
-                let response = HttpResponseType::BlockProposalInvalid {
-                    metadata: response_metadata,
-                    error_message:
-                        "Cannot validate block proposal: Not configured with validation key".into(),
-                };
-                return response.send(http, fd);
-            }
-        };
-
-        let signing_contract = match signing_contract {
-            Some(key) => key,
-            None => {
-                let response = HttpResponseType::BlockProposalInvalid {
-                    metadata: response_metadata,
-                    error_message:
-                        "Cannot validate block proposal: Not configured with a multiparty contract"
-                            .into(),
-                };
-                return response.send(http, fd);
-            }
-        };
-
-        let pubk_recovered = match signed_proposal.recover_signer_pk() {
-            Ok(key) => key,
-            Err(e) => {
-                let response = HttpResponseType::BlockProposalInvalid {
-                    metadata: response_metadata,
-                    error_message: format!("Cannot validate block proposal: {e}"),
-                };
-                return response.send(http, fd);
-            }
-        };
-
-        // TODO: Replace with lookup in `HashSet`
-        if !options.allowed_block_proposers.contains(&pubk_recovered) {
-            let response = HttpResponseType::BlockProposalInvalid {
+        let response = match signed_proposal.validate_and_sign(
+            chainstate,
+            &sortdb.index_conn(),
+            &options.allowed_block_proposers,
+            validator_key,
+            signing_contract,
+        ) {
+            Ok(signature) => HttpResponseType::BlockProposalValid {
                 metadata: response_metadata,
-                error_message:
-                    "Cannot validate block proposal: Not signed by approved block proposer".into(),
-            };
-            return response.send(http, fd);
-        }
-
-        let proposal = match signed_proposal.decode() {
-            Ok(p) => p,
-            Err(e) => {
-                let response = HttpResponseType::BlockProposalInvalid {
-                    metadata: response_metadata,
-                    error_message: format!("Cannot validate block proposal: {e}"),
-                };
-                return response.send(http, fd);
-            }
-        };
-
-        let response = match proposal.validate(chainstate, &sortdb.index_conn()) {
-            Ok(_) => {
-                let signature = proposal.sign(validator_key, signing_contract.clone());
-                HttpResponseType::BlockProposalValid {
-                    metadata: response_metadata,
-                    signature,
-                }
-            }
-            Err(e) => HttpResponseType::BlockProposalInvalid {
+                signature,
+            },
+            Err(error_message) => HttpResponseType::BlockProposalInvalid {
                 metadata: response_metadata,
-                error_message: e.to_string(),
+                error_message,
             },
         };
         response.send(http, fd)
@@ -1110,21 +1483,114 @@ impl ConversationHttp {
         )
     }
 
-    fn handle_get_generic_withdrawal_entry<W: Write>(
+    fn handle_get_withdrawal_stx_to_subnet_entry<W: Write>(
         http: &mut StacksHttp,
         fd: &mut W,
         req: &HttpRequestType,
         chainstate: &mut StacksChainState,
         canonical_tip: &StacksBlockId,
         requested_block_height: u64,
-        withdrawal_key: Value,
+        recipient: &PrincipalData,
+        withdrawal_id: u32,
+        destination: &QualifiedContractIdentifier,
+        amount: u128,
         canonical_stacks_tip_height: u64,
     ) -> Result<(), net_error> {
-        let response_metadata =
-            HttpResponseMetadata::from_http_request_type(req, Some(canonical_stacks_tip_height));
-        let withdrawal_key_bytes = withdrawal_key.serialize_to_vec();
+        let withdrawal_key = withdrawal::make_key_for_stx_to_subnet_withdrawal(
+            recipient,
+            withdrawal_id,
+            destination,
+            amount,
+            requested_block_height,
+        );
+        Self::handle_get_generic_withdrawal_entry(
+            http,
+            fd,
+            req,
+            chainstate,
+            canonical_tip,
+            requested_block_height,
+            withdrawal_key,
+            canonical_stacks_tip_height,
+        )
+    }
 
-        let requested_block = match chainstate
+    fn handle_get_generic_withdrawal_entry<W: Write>(
+        http: &mut StacksHttp,
+        fd: &mut W,
+        req: &HttpRequestType,
+        chainstate: &mut StacksChainState,
+        canonical_tip: &StacksBlockId,
+        requested_block_height: u64,
+        withdrawal_key: Value,
+        canonical_stacks_tip_height: u64,
+    ) -> Result<(), net_error> {
+        let response_metadata =
+            HttpResponseMetadata::from_http_request_type(req, Some(canonical_stacks_tip_height));
+        let withdrawal_key_bytes = withdrawal_key.serialize_to_vec();
+
+        let (requested_block, withdrawal_tree) = match Self::load_withdrawal_tree(
+            chainstate,
+            canonical_tip,
+            requested_block_height,
+            &response_metadata,
+        )? {
+            Ok(x) => x,
+            Err(response) => return response.send(http, fd).map(|_| ()),
+        };
+
+        let merkle_path = match withdrawal_tree.path(&withdrawal_key_bytes) {
+            Some(path) => path,
+            None => {
+                return HttpResponseType::NotFound(
+                    response_metadata,
+                    "Supplied withdrawal key not found".into(),
+                )
+                .send(http, fd)
+                .map(|_| ())
+            }
+        };
+
+        let sibling_hashes = match withdrawal::merkle_path_to_clarity_list(merkle_path) {
+            Some(list) => list,
+            None => {
+                error!("Failed to construct a valid Clarity list type out of withdrawal merkle path";
+                       "l2_block_id" => %requested_block);
+                return HttpResponseType::NotFound(
+                    response_metadata,
+                    "Withdrawal merkle tree at this block height is invalid".into(),
+                )
+                .send(http, fd)
+                .map(|_| ());
+            }
+        };
+
+        let withdrawal_root = withdrawal::buffer_from_hash(withdrawal_tree.root());
+        let withdrawal_leaf_hash = withdrawal::buffer_from_hash(
+            MerkleTree::<Sha512Trunc256Sum>::get_leaf_hash(&withdrawal_key_bytes),
+        );
+
+        let response = WithdrawalResponse {
+            withdrawal_root: format!("0x{}", withdrawal_root.serialize_to_hex()),
+            withdrawal_leaf_hash: format!("0x{}", withdrawal_leaf_hash.serialize_to_hex()),
+            sibling_hashes: format!("0x{}", sibling_hashes.serialize_to_hex()),
+        };
+
+        HttpResponseType::GetWithdrawal(response_metadata, response)
+            .send(http, fd)
+            .map(|_| ())
+    }
+
+    /// Load the withdrawal tree of the block at `requested_block_height` in the fork of
+    /// `canonical_tip`. If there is no such block, returns the NotFound response to send.
+    fn load_withdrawal_tree(
+        chainstate: &mut StacksChainState,
+        canonical_tip: &StacksBlockId,
+        requested_block_height: u64,
+        response_metadata: &HttpResponseMetadata,
+    ) -> Result<Result<(StacksBlockId, MerkleTree<Sha512Trunc256Sum>), HttpResponseType>, net_error>
+    {
+        let requested_block = match chainstate
             .index_conn()
             .map_err(|_| {
                 warn!("Failed to start MARF connection");
@@ -1134,12 +1600,10 @@ impl ConversationHttp {
         {
             Ok(Some(x)) => x,
             Err(_) | Ok(None) => {
-                return HttpResponseType::NotFound(
-                    response_metadata,
+                return Ok(Err(HttpResponseType::NotFound(
+                    response_metadata.clone(),
                     "Supplied block height not found".into(),
-                )
-                .send(http, fd)
-                .map(|_| ())
+                )))
             }
         };
 
@@ -1147,55 +1611,115 @@ impl ConversationHttp {
             chainstate.db(),
             &requested_block,
         );
-        let withdrawal_tree = match block_info_result {
-            Ok(Some(block_info)) => block_info.withdrawal_tree,
-            Err(_) | Ok(None) => {
+        match block_info_result {
+            Ok(Some(block_info)) => Ok(Ok((requested_block, block_info.withdrawal_tree))),
+            Err(_) | Ok(None) => Ok(Err(HttpResponseType::NotFound(
+                response_metadata.clone(),
+                "Supplied block not found".into(),
+            ))),
+        }
+    }
+
+    /// Handle a POST for a multi-proof of a batch of STX withdrawals from the block at
+    /// `requested_block_height`. The withdrawal IDs of the withdrawals are also their positions
+    /// in the block's withdrawal tree. Since `withdraw-stx-batch` only settles STX withdrawals,
+    /// each withdrawal must be the STX withdrawal the request describes, and the proof must fit
+    /// in its arguments.
+    fn handle_post_withdrawal_multiproof<W: Write>(
+        http: &mut StacksHttp,
+        fd: &mut W,
+        req: &HttpRequestType,
+        chainstate: &mut StacksChainState,
+        canonical_tip: &StacksBlockId,
+        requested_block_height: u64,
+        withdrawals: &[BatchStxWithdrawal],
+        canonical_stacks_tip_height: u64,
+    ) -> Result<(), net_error> {
+        let response_metadata =
+            HttpResponseMetadata::from_http_request_type(req, Some(canonical_stacks_tip_height));
+
+        let (requested_block, withdrawal_tree) = match Self::load_withdrawal_tree(
+            chainstate,
+            canonical_tip,
+            requested_block_height,
+            &response_metadata,
+        )? {
+            Ok(x) => x,
+            Err(response) => return response.send(http, fd).map(|_| ()),
+        };
+
+        let withdrawal_count = withdrawal::withdrawal_count(&withdrawal_tree);
+        for entry in withdrawals.iter() {
+            let position = entry.withdrawal_id as usize;
+            if position >= withdrawal_count {
                 return HttpResponseType::NotFound(
                     response_metadata,
-                    "Supplied block not found".into(),
+                    "Supplied withdrawal IDs not found".into(),
                 )
                 .send(http, fd)
-                .map(|_| ())
+                .map(|_| ());
             }
-        };
+            let withdrawal_key = withdrawal::make_key_for_stx_withdrawal(
+                &entry.recipient,
+                entry.withdrawal_id,
+                u128::from(entry.amount),
+                requested_block_height,
+            );
+            if withdrawal_tree.nodes[0][position] != withdrawal_leaf_hash(&withdrawal_key) {
+                return HttpResponseType::BadRequest(
+                    response_metadata,
+                    format!(
+                        "Withdrawal {} is not an STX withdrawal of {} to {}. Only STX withdrawals can be settled in a batch.",
+                        entry.withdrawal_id, entry.amount, &entry.recipient
+                    ),
+                )
+                .send(http, fd)
+                .map(|_| ());
+            }
+        }
 
-        let merkle_path = match withdrawal_tree.path(&withdrawal_key_bytes) {
-            Some(path) => path,
+        let leaf_positions: Vec<usize> = withdrawals
+            .iter()
+            .map(|entry| entry.withdrawal_id as usize)
+            .collect();
+        let multi_proof = match WithdrawalMultiProof::new(&withdrawal_tree, &leaf_positions) {
+            Some(proof) => proof,
             None => {
                 return HttpResponseType::NotFound(
                     response_metadata,
-                    "Supplied withdrawal key not found".into(),
+                    "Supplied withdrawal IDs not found".into(),
                 )
                 .send(http, fd)
                 .map(|_| ())
             }
         };
+        if multi_proof.proof_hashes.len() > withdrawal::MAX_BATCH_PROOF_LEN
+            || multi_proof.steps.len() > withdrawal::MAX_BATCH_PROOF_LEN
+        {
+            return HttpResponseType::BadRequest(
+                response_metadata,
+                format!(
+                    "The multi-proof of these withdrawals needs {} proof hashes and {} proof steps, but a batch takes at most {} of each. Split the batch.",
+                    multi_proof.proof_hashes.len(),
+                    multi_proof.steps.len(),
+                    withdrawal::MAX_BATCH_PROOF_LEN
+                ),
+            )
+            .send(http, fd)
+            .map(|_| ());
+        }
 
-        let tuple_vec: Vec<_> = merkle_path
-            .into_iter()
-            .map(|merkle_point| {
-                let MerklePathPoint {
-                    order,
-                    hash: sibling_hash,
-                } = merkle_point;
-                // the sibling hash is the left sibling if the merkle path point order is right
-                //  because the merkle path point order is in reference to the leaf
-                let is_sibling_left_side = order == MerklePathOrder::Right;
-                // make the clarity tuple
-                Value::Tuple(
-                    TupleData::from_data(vec![
-                        ("hash".into(), withdrawal::buffer_from_hash(sibling_hash)),
-                        ("is-left-side".into(), Value::Bool(is_sibling_left_side)),
-                    ])
-                    .expect("Failed to construct Clarity repr of merkle tree entry"),
-                )
-            })
-            .collect();
-
-        let sibling_hashes = match Value::cons_list(tuple_vec, &StacksEpochId::latest()) {
-            Ok(list) => list,
-            Err(_) => {
-                error!("Failed to construct a valid Clarity list type out of withdrawal merkle path";
+        let clarity_lists = (
+            multi_proof.leaf_hashes_to_clarity_list(),
+            multi_proof.proof_hashes_to_clarity_list(),
+            multi_proof.steps_to_clarity_list(),
+        );
+        let (leaf_hashes, proof_hashes, proof_steps) = match clarity_lists {
+            (Some(leaf_hashes), Some(proof_hashes), Some(proof_steps)) => {
+                (leaf_hashes, proof_hashes, proof_steps)
+            }
+            _ => {
+                error!("Failed to construct valid Clarity list types out of withdrawal multi-proof";
                        "l2_block_id" => %requested_block);
                 return HttpResponseType::NotFound(
                     response_metadata,
@@ -1207,17 +1731,14 @@ impl ConversationHttp {
         };
 
         let withdrawal_root = withdrawal::buffer_from_hash(withdrawal_tree.root());
-        let withdrawal_leaf_hash = withdrawal::buffer_from_hash(
-            MerkleTree::<Sha512Trunc256Sum>::get_leaf_hash(&withdrawal_key_bytes),
-        );
-
-        let response = WithdrawalResponse {
+        let response = WithdrawalMultiProofResponse {
             withdrawal_root: format!("0x{}", withdrawal_root.serialize_to_hex()),
-            withdrawal_leaf_hash: format!("0x{}", withdrawal_leaf_hash.serialize_to_hex()),
-            sibling_hashes: format!("0x{}", sibling_hashes.serialize_to_hex()),
+            withdrawal_leaf_hashes: format!("0x{}", leaf_hashes.serialize_to_hex()),
+            proof_hashes: format!("0x{}", proof_hashes.serialize_to_hex()),
+            proof_steps: format!("0x{}", proof_steps.serialize_to_hex()),
         };
 
-        HttpResponseType::GetWithdrawal(response_metadata, response)
+        HttpResponseType::GetWithdrawalMultiProof(response_metadata, response)
             .send(http, fd)
             .map(|_| ())
     }
@@ -1237,6 +1758,8 @@ impl ConversationHttp {
     ) -> Result<(), net_error> {
         let response_metadata =
             HttpResponseMetadata::from_http_request_type(req, Some(canonical_stacks_tip_height));
+        // proofs are generated against the resolved block, so report it alongside them
+        let proof_tip = if with_proof { Some(tip.clone()) } else { None };
         let response =
             match chainstate.maybe_read_only_clarity_tx(&sortdb.index_conn(), tip, |clarity_tx| {
                 clarity_tx.with_clarity_db_readonly(|clarity_db| {
@@ -1290,6 +1813,7 @@ impl ConversationHttp {
                         nonce,
                         balance_proof,
                         nonce_proof,
+                        index_block_hash: proof_tip,
                     }
                 })
             }) {
@@ -1322,6 +1846,7 @@ impl ConversationHttp {
         let contract_identifier =
             QualifiedContractIdentifier::new(contract_addr.clone().into(), contract_name.clone());
 
+        let proof_tip = if with_proof { Some(tip.clone()) } else { None };
         let response =
             match chainstate.maybe_read_only_clarity_tx(&sortdb.index_conn(), tip, |clarity_tx| {
                 clarity_tx.with_clarity_db_readonly(|clarity_db| {
@@ -1340,7 +1865,11 @@ impl ConversationHttp {
                     };
 
                     let data = format!("0x{}", value_hex);
-                    Some(DataVarResponse { data, marf_proof })
+                    Some(DataVarResponse {
+                        data,
+                        marf_proof,
+                        index_block_hash: proof_tip,
+                    })
                 })
             }) {
                 Ok(Some(Some(data))) => HttpResponseType::GetDataVar(response_metadata, data),
@@ -1376,6 +1905,7 @@ impl ConversationHttp {
         let contract_identifier =
             QualifiedContractIdentifier::new(contract_addr.clone().into(), contract_name.clone());
 
+        let proof_tip = if with_proof { Some(tip.clone()) } else { None };
         let response =
             match chainstate.maybe_read_only_clarity_tx(&sortdb.index_conn(), tip, |clarity_tx| {
                 clarity_tx.with_clarity_db_readonly(|clarity_db| {
@@ -1400,7 +1930,11 @@ impl ConversationHttp {
                     };
 
                     let data = format!("0x{}", value_hex);
-                    MapEntryResponse { data, marf_proof }
+                    MapEntryResponse {
+                        data,
+                        marf_proof,
+                        index_block_hash: proof_tip,
+                    }
                 })
             }) {
                 Ok(Some(data)) => HttpResponseType::GetMapEntry(response_metadata, data),
@@ -1822,6 +2356,136 @@ impl ConversationHttp {
         return response.send(http, fd).map(|_| ());
     }
 
+    /// The anchored block that `tip` is, or builds on if it is the unconfirmed state's tip.
+    /// Chain history is only recorded for anchored blocks.
+    fn get_anchored_tip(chainstate: &StacksChainState, tip: &StacksBlockId) -> StacksBlockId {
+        match chainstate.unconfirmed_state {
+            Some(ref unconfirmed) if unconfirmed.unconfirmed_chain_tip == *tip => {
+                unconfirmed.confirmed_chain_tip.clone()
+            }
+            _ => tip.clone(),
+        }
+    }
+
+    /// Handle a GET confirmed transaction, looked up in the transaction index.
+    /// The response will be synchronously written to the fd.
+    fn handle_gettransaction<W: Write>(
+        http: &mut StacksHttp,
+        fd: &mut W,
+        req: &HttpRequestType,
+        chainstate: &StacksChainState,
+        tip: &StacksBlockId,
+        txid: &Txid,
+        canonical_stacks_tip_height: u64,
+    ) -> Result<(), net_error> {
+        let response_metadata =
+            HttpResponseMetadata::from_http_request_type(req, Some(canonical_stacks_tip_height));
+
+        if !chainstate.txindex {
+            let response = HttpResponseType::NotFound(
+                response_metadata,
+                "Transaction index is not enabled on this node".to_string(),
+            );
+            return response.send(http, fd).map(|_| ());
+        }
+
+        let tip = ConversationHttp::get_anchored_tip(chainstate, tip);
+        let response = match chainstate.get_indexed_transaction(txid, &tip) {
+            Ok(Some((entry, header))) => HttpResponseType::ConfirmedTransaction(
+                response_metadata,
+                RPCConfirmedTransaction {
+                    txid: entry.txid.to_hex(),
+                    tx: entry.tx_hex,
+                    burnchain_op: entry.burnchain_op,
+                    index_block_hash: entry.index_block_hash.to_hex(),
+                    block_hash: header.anchored_header.block_hash().to_hex(),
+                    block_height: header.stacks_block_height,
+                    tx_index: entry.tx_index,
+                    microblock_hash: entry.microblock_hash.map(|hash| hash.to_hex()),
+                    result: format!("0x{}", &entry.result_hex),
+                    post_condition_aborted: entry.post_condition_aborted,
+                    stx_burned: entry.stx_burned,
+                    events: entry.events,
+                    execution_cost: entry.execution_cost,
+                },
+            ),
+            Ok(None) => HttpResponseType::NotFound(
+                response_metadata,
+                format!("No such confirmed transaction {}", txid),
+            ),
+            Err(e) => {
+                warn!("Failed to query transaction index for {}: {:?}", txid, &e);
+                HttpResponseType::ServerError(
+                    response_metadata,
+                    format!("Failed to query transaction {}", txid),
+                )
+            }
+        };
+        response.send(http, fd).map(|_| ())
+    }
+
+    /// Handle a GET for a page of an account's pending transactions.
+    /// The response will be synchronously written to the fd.
+    fn handle_get_mempool_principal<W: Write>(
+        http: &mut StacksHttp,
+        fd: &mut W,
+        req: &HttpRequestType,
+        mempool: &MemPoolDB,
+        principal: &StacksAddress,
+        offset: u64,
+        limit: u64,
+        canonical_stacks_tip_height: u64,
+    ) -> Result<(), net_error> {
+        let response_metadata =
+            HttpResponseMetadata::from_http_request_type(req, Some(canonical_stacks_tip_height));
+
+        let pending_txs = MemPoolDB::count_pending_txs_by_address(mempool.conn(), principal)
+            .and_then(|total| {
+                MemPoolDB::get_pending_txs_by_address(mempool.conn(), principal, offset, limit)
+                    .map(|txs| (total, txs))
+            });
+
+        let response = match pending_txs {
+            Ok((total, txs)) => HttpResponseType::MempoolPrincipalTxs(
+                response_metadata,
+                RPCMempoolPrincipalResponse {
+                    principal: principal.to_string(),
+                    total,
+                    offset,
+                    limit,
+                    transactions: txs
+                        .into_iter()
+                        .map(|pending| RPCPendingTransaction {
+                            txid: pending.tx.metadata.txid.to_hex(),
+                            tx: to_hex(&pending.tx.tx.serialize_to_vec()),
+                            payload_type: pending.tx.tx.payload.name().to_string(),
+                            origin_address: pending.tx.metadata.origin_address.to_string(),
+                            origin_nonce: pending.tx.metadata.origin_nonce,
+                            sponsor_address: pending.tx.metadata.sponsor_address.to_string(),
+                            sponsor_nonce: pending.tx.metadata.sponsor_nonce,
+                            fee: pending.tx.metadata.tx_fee,
+                            fee_rate: pending.fee_rate,
+                            accept_time: pending.tx.metadata.accept_time,
+                            skip_reason: pending.skip_reason,
+                            skip_time: pending.skip_time,
+                        })
+                        .collect(),
+                },
+            ),
+            Err(e) => {
+                warn!(
+                    "Failed to load pending transactions for {}: {:?}",
+                    principal, &e
+                );
+                HttpResponseType::ServerError(
+                    response_metadata,
+                    format!("Failed to load pending transactions for {}", principal),
+                )
+            }
+        };
+        response.send(http, fd).map(|_| ())
+    }
+
     /// Load up the canonical Stacks chain tip.  Note that this is subject to both burn chain block
     /// Stacks block availability -- different nodes with different partial replicas of the Stacks chain state
     /// will return different values here.
@@ -1836,7 +2500,8 @@ impl ConversationHttp {
     /// # Inputs
     /// - `tip_req` is given by the HTTP request as the optional query parameter for the chain tip
     /// hash.  It will be UseLatestAnchoredTip if there was no parameter given. If it is set to
-    /// `latest`, the parameter will be set to UseLatestUnconfirmedTip.
+    /// `latest`, the parameter will be set to UseLatestUnconfirmedTip.  AtHeight is resolved to
+    /// the ancestor of the canonical anchored tip at that height.
     fn handle_load_stacks_chain_tip<W: Write>(
         http: &mut StacksHttp,
         fd: &mut W,
@@ -1890,6 +2555,40 @@ impl ConversationHttp {
                 }
             }
             TipRequest::SpecificTip(tip) => Ok(Some(*tip).clone()),
+            TipRequest::AtHeight(height) => {
+                let tip = match chainstate.get_stacks_chain_tip(sortdb)? {
+                    Some(tip) => StacksBlockHeader::make_index_block_hash(
+                        &tip.consensus_hash,
+                        &tip.anchored_block_hash,
+                    ),
+                    None => {
+                        let response_metadata = HttpResponseMetadata::from_http_request_type(
+                            req,
+                            Some(canonical_stacks_tip_height),
+                        );
+                        warn!("Failed to load Stacks chain tip");
+                        let response = HttpResponseType::ServerError(
+                            response_metadata,
+                            format!("Failed to load Stacks chain tip"),
+                        );
+                        return response.send(http, fd).and_then(|_| Ok(None));
+                    }
+                };
+                match ConversationHttp::get_block_at_height(chainstate, *height, &tip)? {
+                    Some(block_id) => Ok(Some(block_id)),
+                    None => {
+                        let response_metadata = HttpResponseMetadata::from_http_request_type(
+                            req,
+                            Some(canonical_stacks_tip_height),
+                        );
+                        let response = HttpResponseType::NotFound(
+                            response_metadata,
+                            format!("No block at height {} in the canonical fork", height),
+                        );
+                        response.send(http, fd).and_then(|_| Ok(None))
+                    }
+                }
+            }
             TipRequest::UseLatestAnchoredTip => match chainstate.get_stacks_chain_tip(sortdb)? {
                 Some(tip) => Ok(Some(StacksBlockHeader::make_index_block_hash(
                     &tip.consensus_hash,
@@ -1941,6 +2640,7 @@ impl ConversationHttp {
         req: &HttpRequestType,
         handler_args: &RPCHandlerArgs,
         sortdb: &SortitionDB,
+        chainstate: &mut StacksChainState,
         tx: &TransactionPayload,
         estimated_len: u64,
         canonical_stacks_tip_height: u64,
@@ -1993,6 +2693,10 @@ impl ConversationHttp {
                 }
             }
 
+            let fee_asset = chainstate
+                .get_subnet_fee_asset()?
+                .map(|asset| asset.to_string());
+
             let response = HttpResponseType::TransactionFeeEstimation(
                 response_metadata,
                 RPCFeeEstimateResponse {
@@ -2000,6 +2704,7 @@ impl ConversationHttp {
                     estimations,
                     estimated_cost_scalar: scalar_cost,
                     cost_scalar_change_by_byte: metric.change_per_byte(),
+                    fee_asset,
                 },
             );
             response.send(http, fd)
@@ -2301,6 +3006,49 @@ impl ConversationHttp {
         response.send(http, fd).and_then(|_| Ok(stream))
     }
 
+    /// Handle a request to subscribe to an event stream.
+    /// Returns the stream to feed to the client if the subscription was accepted.
+    fn handle_get_event_stream<W: Write>(
+        http: &mut StacksHttp,
+        fd: &mut W,
+        req: &HttpRequestType,
+        event_stream: Option<&dyn EventStreamProvider>,
+        events_keys: &[String],
+        canonical_stacks_tip_height: u64,
+    ) -> Result<Option<StreamCursor>, net_error> {
+        let response_metadata =
+            HttpResponseMetadata::from_http_request_type(req, Some(canonical_stacks_tip_height));
+        let event_stream = match event_stream {
+            Some(event_stream) => event_stream,
+            None => {
+                let response = HttpResponseType::NotFound(
+                    response_metadata,
+                    "Event streams are not enabled on this node".to_string(),
+                );
+                return response.send(http, fd).and_then(|_| Ok(None));
+            }
+        };
+
+        let (response, stream_opt) = match event_stream.subscribe(events_keys) {
+            Ok(receiver) => {
+                debug!("Begin event stream"; "events_keys" => ?events_keys);
+                (
+                    HttpResponseType::EventStream(response_metadata),
+                    Some(StreamCursor::new_event_stream(receiver)),
+                )
+            }
+            Err(e @ EventStreamError::InvalidEventKey(..)) => (
+                HttpResponseType::BadRequest(response_metadata, e.to_string()),
+                None,
+            ),
+            Err(e @ EventStreamError::TooManySubscribers(..)) => (
+                HttpResponseType::ServiceUnavailable(response_metadata, e.to_string()),
+                None,
+            ),
+        };
+        response.send(http, fd).and_then(|_| Ok(stream_opt))
+    }
+
     /// Handle an external HTTP request.
     /// Some requests, such as those for blocks, will create new reply streams.  This method adds
     /// those new streams into the `reply_streams` set.
@@ -2375,19 +3123,72 @@ impl ConversationHttp {
                     network.burnchain_tip.canonical_stacks_tip_height,
                 )?
             }
-            HttpRequestType::GetMicroblocksIndexed(ref _md, ref index_head_hash) => {
-                ConversationHttp::handle_getmicroblocks_indexed(
+            HttpRequestType::GetBlockByHeight(ref _md, ref height, ref tip_req) => {
+                if let Some(tip) = ConversationHttp::handle_load_stacks_chain_tip(
                     &mut self.connection.protocol,
                     &mut reply,
                     &req,
-                    index_head_hash,
+                    tip_req,
+                    sortdb,
                     chainstate,
                     network.burnchain_tip.canonical_stacks_tip_height,
-                )?
+                )? {
+                    ConversationHttp::handle_getblock_by_height(
+                        &mut self.connection.protocol,
+                        &mut reply,
+                        &req,
+                        &tip,
+                        *height,
+                        chainstate,
+                        network.burnchain_tip.canonical_stacks_tip_height,
+                    )?
+                } else {
+                    None
+                }
             }
-            HttpRequestType::GetMicroblocksConfirmed(ref _md, ref anchor_index_block_hash) => {
-                ConversationHttp::handle_getmicroblocks_confirmed(
-                    &mut self.connection.protocol,
+            HttpRequestType::GetBlockList(
+                ref _md,
+                ref start_height,
+                ref limit,
+                ref json,
+                ref tip_req,
+            ) => {
+                if let Some(tip) = ConversationHttp::handle_load_stacks_chain_tip(
+                    &mut self.connection.protocol,
+                    &mut reply,
+                    &req,
+                    tip_req,
+                    sortdb,
+                    chainstate,
+                    network.burnchain_tip.canonical_stacks_tip_height,
+                )? {
+                    ConversationHttp::handle_getblock_list(
+                        &mut self.connection.protocol,
+                        &mut reply,
+                        &req,
+                        &tip,
+                        *start_height,
+                        *limit,
+                        *json,
+                        chainstate,
+                        network.burnchain_tip.canonical_stacks_tip_height,
+                    )?;
+                }
+                None
+            }
+            HttpRequestType::GetMicroblocksIndexed(ref _md, ref index_head_hash) => {
+                ConversationHttp::handle_getmicroblocks_indexed(
+                    &mut self.connection.protocol,
+                    &mut reply,
+                    &req,
+                    index_head_hash,
+                    chainstate,
+                    network.burnchain_tip.canonical_stacks_tip_height,
+                )?
+            }
+            HttpRequestType::GetMicroblocksConfirmed(ref _md, ref anchor_index_block_hash) => {
+                ConversationHttp::handle_getmicroblocks_confirmed(
+                    &mut self.connection.protocol,
                     &mut reply,
                     &req,
                     anchor_index_block_hash,
@@ -2420,6 +3221,41 @@ impl ConversationHttp {
                 )?;
                 None
             }
+            HttpRequestType::GetTransaction(ref _md, ref txid, ref tip_req) => {
+                if let Some(tip) = ConversationHttp::handle_load_stacks_chain_tip(
+                    &mut self.connection.protocol,
+                    &mut reply,
+                    &req,
+                    tip_req,
+                    sortdb,
+                    chainstate,
+                    network.burnchain_tip.canonical_stacks_tip_height,
+                )? {
+                    ConversationHttp::handle_gettransaction(
+                        &mut self.connection.protocol,
+                        &mut reply,
+                        &req,
+                        chainstate,
+                        &tip,
+                        txid,
+                        network.burnchain_tip.canonical_stacks_tip_height,
+                    )?;
+                }
+                None
+            }
+            HttpRequestType::GetMempoolPrincipal(ref _md, ref principal, ref offset, ref limit) => {
+                ConversationHttp::handle_get_mempool_principal(
+                    &mut self.connection.protocol,
+                    &mut reply,
+                    &req,
+                    mempool,
+                    principal,
+                    *offset,
+                    *limit,
+                    network.burnchain_tip.canonical_stacks_tip_height,
+                )?;
+                None
+            }
             HttpRequestType::GetAccount(ref _md, ref principal, ref tip_req, ref with_proof) => {
                 if let Some(tip) = ConversationHttp::handle_load_stacks_chain_tip(
                     &mut self.connection.protocol,
@@ -2557,6 +3393,7 @@ impl ConversationHttp {
                     &req,
                     handler_opts,
                     sortdb,
+                    chainstate,
                     tx,
                     estimated_len,
                     network.burnchain_tip.canonical_stacks_tip_height,
@@ -2774,6 +3611,63 @@ impl ConversationHttp {
                     page_id_opt.clone(),
                 )?)
             }
+            HttpRequestType::GetEventStream(ref _md, ref events_keys) => {
+                ConversationHttp::handle_get_event_stream(
+                    &mut self.connection.protocol,
+                    &mut reply,
+                    &req,
+                    handler_opts.event_stream,
+                    events_keys,
+                    network.burnchain_tip.canonical_stacks_tip_height,
+                )?
+            }
+            HttpRequestType::GetHealthLive(ref _md) => {
+                ConversationHttp::handle_get_health_live(
+                    &mut self.connection.protocol,
+                    &mut reply,
+                    &req,
+                    network.burnchain_tip.canonical_stacks_tip_height,
+                )?;
+                None
+            }
+            HttpRequestType::GetHealthReady(ref _md) => {
+                ConversationHttp::handle_get_health_ready(
+                    &mut self.connection.protocol,
+                    &mut reply,
+                    &req,
+                    mempool,
+                    handler_opts.health,
+                    network.burnchain_tip.block_height,
+                    network.burnchain_tip.canonical_stacks_tip_height,
+                )?;
+                None
+            }
+            HttpRequestType::GetPeerAllowlist(ref _md) => {
+                let canonical_stacks_tip_height = network.burnchain_tip.canonical_stacks_tip_height;
+                ConversationHttp::handle_get_peer_allowlist(
+                    &mut self.connection.protocol,
+                    &mut reply,
+                    &req,
+                    &self.peer_addr,
+                    network,
+                    canonical_stacks_tip_height,
+                )?;
+                None
+            }
+            HttpRequestType::PostPeerAllowlist(ref _md, ref add, ref remove, ref authorization) => {
+                let canonical_stacks_tip_height = network.burnchain_tip.canonical_stacks_tip_height;
+                ConversationHttp::handle_post_peer_allowlist(
+                    &mut self.connection.protocol,
+                    &mut reply,
+                    &req,
+                    network,
+                    add,
+                    remove,
+                    authorization.as_deref(),
+                    canonical_stacks_tip_height,
+                )?;
+                None
+            }
             HttpRequestType::OptionsPreflight(ref _md, ref _path) => {
                 let response_metadata = HttpResponseMetadata::from_http_request_type(
                     &req,
@@ -2956,6 +3850,68 @@ impl ConversationHttp {
                 }
                 None
             }
+
+            HttpRequestType::GetWithdrawalStxToSubnet {
+                withdraw_block_height,
+                ref recipient,
+                withdrawal_id,
+                ref destination,
+                amount,
+                ..
+            } => {
+                if let Some(tip) = ConversationHttp::handle_load_stacks_chain_tip(
+                    &mut self.connection.protocol,
+                    &mut reply,
+                    &req,
+                    &TipRequest::UseLatestAnchoredTip,
+                    sortdb,
+                    chainstate,
+                    network.burnchain_tip.canonical_stacks_tip_height,
+                )? {
+                    ConversationHttp::handle_get_withdrawal_stx_to_subnet_entry(
+                        &mut self.connection.protocol,
+                        &mut reply,
+                        &req,
+                        chainstate,
+                        &tip,
+                        withdraw_block_height,
+                        recipient,
+                        withdrawal_id,
+                        destination,
+                        amount,
+                        network.burnchain_tip.canonical_stacks_tip_height,
+                    )?;
+                }
+                None
+            }
+
+            HttpRequestType::PostWithdrawalMultiProof {
+                withdraw_block_height,
+                ref withdrawals,
+                ..
+            } => {
+                if let Some(tip) = ConversationHttp::handle_load_stacks_chain_tip(
+                    &mut self.connection.protocol,
+                    &mut reply,
+                    &req,
+                    &TipRequest::UseLatestAnchoredTip,
+                    sortdb,
+                    chainstate,
+                    network.burnchain_tip.canonical_stacks_tip_height,
+                )? {
+                    ConversationHttp::handle_post_withdrawal_multiproof(
+                        &mut self.connection.protocol,
+                        &mut reply,
+                        &req,
+                        chainstate,
+                        &tip,
+                        withdraw_block_height,
+                        withdrawals,
+                        network.burnchain_tip.canonical_stacks_tip_height,
+                    )?;
+                }
+                None
+            }
         };
 
         match stream_opt {
@@ -3008,7 +3964,28 @@ impl ConversationHttp {
                         {
                             Ok(nw) => {
                                 test_debug!("{}: Streamed {} bytes", &_self_str, nw);
-                                if nw == 0 {
+                                if stream.is_open_ended() {
+                                    // live stream -- send what we have now, without ending the
+                                    // chunked encoding
+                                    if nw > 0 {
+                                        encoder.send_buffered_chunk().map_err(|e| {
+                                            test_debug!(
+                                                "{}: Write error on encoder chunk: {:?}",
+                                                &_self_str,
+                                                &e
+                                            );
+                                            net_error::WriteError(e)
+                                        })?;
+
+                                        if let Err(e) = reply.try_flush_buffered() {
+                                            warn!(
+                                                "{}: Broken HTTP connection: {:?}",
+                                                &_self_str, &e
+                                            );
+                                            broken = true;
+                                        }
+                                    }
+                                } else if nw == 0 {
                                     // EOF -- finish chunk and stop sending.
                                     if !encoder.corked() {
                                         encoder.flush().map_err(|e| {
@@ -3185,6 +4162,14 @@ impl ConversationHttp {
             && self.reply_streams.len() == 0
     }
 
+    /// Is the conversation currently sending a live event stream?
+    pub fn has_open_ended_stream(&self) -> bool {
+        match self.reply_streams.front() {
+            Some((_, Some((_, ref stream)), _)) => stream.is_open_ended(),
+            _ => false,
+        }
+    }
+
     /// Is the conversation out of pending data?
     /// Don't consider it drained if we haven't received anything yet
     pub fn is_drained(&self) -> bool {
@@ -3385,6 +4370,15 @@ impl ConversationHttp {
         )
     }
 
+    /// Make a new request for the block at a height in the fork of a tip
+    pub fn new_getblock_by_height(&self, height: u64, tip_req: TipRequest) -> HttpRequestType {
+        HttpRequestType::GetBlockByHeight(
+            HttpRequestMetadata::from_host(self.peer_host.clone(), None),
+            height,
+            tip_req,
+        )
+    }
+
     /// Make a new get-microblocks request to this endpoint
     pub fn new_getmicroblocks_indexed(
         &self,
@@ -3611,6 +4605,7 @@ mod test {
     use crate::chainstate::stacks::test::*;
     use crate::chainstate::stacks::Error as chain_error;
     use crate::chainstate::stacks::*;
+    use crate::clarity_vm::verify;
     use crate::net::codec::*;
     use crate::net::http::*;
     use crate::net::test::*;
@@ -4975,29 +5970,23 @@ mod test {
         );
     }
 
+    /// In this test, a block is requested at a height the MARF cannot index.  We expect a 404
+    /// rather than a failure in the lookup.
     #[test]
-    #[ignore]
-    fn test_rpc_missing_index_getmicroblocks() {
+    fn test_rpc_getblock_by_height_out_of_range() {
         test_rpc(
-            "test_rpc_missing_index_getmicroblocks",
-            40070,
-            40071,
-            50070,
-            50071,
+            "test_rpc_getblock_by_height_out_of_range",
+            40819,
+            40820,
+            50819,
+            50820,
             true,
             |ref mut peer_client,
              ref mut convo_client,
              ref mut peer_server,
              ref mut convo_server| {
-                let peer_server_block_hash = BlockHeaderHash([0x04; 32]);
-                let peer_server_consensus_hash = ConsensusHash([0x02; 20]);
-                let index_block_hash = StacksBlockHeader::make_index_block_hash(
-                    &peer_server_consensus_hash,
-                    &peer_server_block_hash,
-                );
-
-                // now ask for it
-                convo_client.new_getmicroblocks_indexed(index_block_hash)
+                convo_client
+                    .new_getblock_by_height(u32::MAX as u64, TipRequest::UseLatestAnchoredTip)
             },
             |ref http_request,
              ref http_response,
@@ -5005,7 +5994,6 @@ mod test {
              ref mut peer_server,
              ref convo_client,
              ref convo_server| {
-                let req_md = http_request.metadata().clone();
                 match http_response {
                     HttpResponseType::NotFound(response_md, msg) => true,
                     _ => {
@@ -5017,29 +6005,52 @@ mod test {
         );
     }
 
+    /// In this test, a block whose data was pruned is requested.  We expect a 410 rather than a
+    /// 404, since the block is part of the chain.
     #[test]
-    #[ignore]
-    fn test_rpc_missing_confirmed_getmicroblocks() {
+    fn test_rpc_getblock_pruned() {
         test_rpc(
-            "test_rpc_missing_confirmed_getmicroblocks",
-            40072,
-            40073,
-            50072,
-            50073,
+            "test_rpc_getblock_pruned",
+            40827,
+            40828,
+            50827,
+            50828,
             true,
             |ref mut peer_client,
              ref mut convo_client,
              ref mut peer_server,
              ref mut convo_server| {
-                let peer_server_block_hash = BlockHeaderHash([0x04; 32]);
+                let mut peer_server_block = make_codec_test_block(25);
+                peer_server_block.header.total_work.work = 1;
                 let peer_server_consensus_hash = ConsensusHash([0x02; 20]);
                 let index_block_hash = StacksBlockHeader::make_index_block_hash(
                     &peer_server_consensus_hash,
-                    &peer_server_block_hash,
+                    &peer_server_block.block_hash(),
                 );
 
-                // now ask for it
-                convo_client.new_getmicroblocks_confirmed(index_block_hash)
+                store_staging_block(
+                    peer_server.chainstate(),
+                    &peer_server_consensus_hash,
+                    &peer_server_block,
+                    &ConsensusHash([0x03; 20]),
+                    456,
+                    123,
+                );
+                set_block_processed(
+                    peer_server.chainstate(),
+                    &peer_server_consensus_hash,
+                    &peer_server_block.block_hash(),
+                    true,
+                );
+
+                let chainstate = peer_server.chainstate();
+                chainstate.prune_depth = Some(1);
+                assert_eq!(chainstate.prune_block_data(10).unwrap(), Some(9));
+                assert!(
+                    StacksChainState::is_block_pruned(chainstate.db(), &index_block_hash).unwrap()
+                );
+
+                convo_client.new_getblock(index_block_hash)
             },
             |ref http_request,
              ref http_response,
@@ -5047,9 +6058,8 @@ mod test {
              ref mut peer_server,
              ref convo_client,
              ref convo_server| {
-                let req_md = http_request.metadata().clone();
                 match http_response {
-                    HttpResponseType::NotFound(response_md, msg) => true,
+                    HttpResponseType::Error(_, 410, msg) => msg.find("was pruned").is_some(),
                     _ => {
                         error!("Invalid response: {:?}", &http_response);
                         false
@@ -5059,37 +6069,237 @@ mod test {
         );
     }
 
+    /// In this test, the peer allowlist is edited over RPC.  We expect a 403 if the server has
+    /// no admin token, a 401 if the request's token is wrong, and the updated allowlist
+    /// otherwise.
     #[test]
-    #[ignore]
-    fn test_rpc_missing_unconfirmed_microblocks() {
-        let server_microblocks_cell = RefCell::new(vec![]);
+    fn test_rpc_post_peer_allowlist() {
+        let public_key = Secp256k1PublicKey::from_hex(
+            "02fa66b66f8971a8cd4d20ffded09674e030f0f33883f337f34b95ad4935bac0e3",
+        )
+        .unwrap();
+        let make_request = |admin_token: Option<&str>, authorization: Option<&str>| {
+            let public_key = public_key.clone();
+            let admin_token = admin_token.map(|token| token.to_string());
+            let authorization = authorization.map(|token| token.to_string());
+            move |_peer_client: &mut TestPeer,
+                  convo_client: &mut ConversationHttp,
+                  peer_server: &mut TestPeer,
+                  _convo_server: &mut ConversationHttp| {
+                peer_server
+                    .network
+                    .connection_opts
+                    .peer_allowlist_admin_token = admin_token;
+                HttpRequestType::PostPeerAllowlist(
+                    HttpRequestMetadata::from_host(convo_client.peer_host.clone(), None),
+                    vec![public_key],
+                    vec![],
+                    authorization,
+                )
+            }
+        };
 
         test_rpc(
-            "test_rpc_missing_unconfirmed_microblocks",
-            40080,
-            40081,
-            50080,
-            50081,
+            "test_rpc_post_peer_allowlist_disabled",
+            40821,
+            40822,
+            50821,
+            50822,
             true,
-            |ref mut peer_client,
-             ref mut convo_client,
+            make_request(None, Some("admin-token")),
+            |ref http_request,
+             ref http_response,
+             ref mut peer_client,
              ref mut peer_server,
-             ref mut convo_server| {
-                let privk = StacksPrivateKey::from_hex(
-                    "eb05c83546fdd2c79f10f5ad5434a90dd28f7e3acb7c092157aa1bc3656b012c01",
-                )
-                .unwrap();
-
-                let consensus_hash = ConsensusHash([0x02; 20]);
-                let anchored_block_hash = BlockHeaderHash([0x03; 32]);
-                let index_block_hash =
-                    StacksBlockHeader::make_index_block_hash(&consensus_hash, &anchored_block_hash);
-
-                let mut mblocks = make_sample_microblock_stream(&privk, &anchored_block_hash);
-                mblocks.truncate(15);
-
-                for mblock in mblocks.iter() {
-                    store_staging_microblock(
+             ref convo_client,
+             ref convo_server| {
+                match http_response {
+                    HttpResponseType::Forbidden(..) => {
+                        !PeerDB::is_peer_allowlisted(peer_server.network.peerdb.conn(), &public_key)
+                            .unwrap()
+                    }
+                    _ => {
+                        error!("Invalid response: {:?}", &http_response);
+                        false
+                    }
+                }
+            },
+        );
+
+        test_rpc(
+            "test_rpc_post_peer_allowlist_unauthorized",
+            40823,
+            40824,
+            50823,
+            50824,
+            true,
+            make_request(Some("admin-token"), Some("admin-tokem")),
+            |ref http_request,
+             ref http_response,
+             ref mut peer_client,
+             ref mut peer_server,
+             ref convo_client,
+             ref convo_server| {
+                match http_response {
+                    HttpResponseType::Unauthorized(..) => {
+                        !PeerDB::is_peer_allowlisted(peer_server.network.peerdb.conn(), &public_key)
+                            .unwrap()
+                    }
+                    _ => {
+                        error!("Invalid response: {:?}", &http_response);
+                        false
+                    }
+                }
+            },
+        );
+
+        test_rpc(
+            "test_rpc_post_peer_allowlist_authorized",
+            40825,
+            40826,
+            50825,
+            50826,
+            true,
+            make_request(Some("admin-token"), Some("admin-token")),
+            |ref http_request,
+             ref http_response,
+             ref mut peer_client,
+             ref mut peer_server,
+             ref convo_client,
+             ref convo_server| {
+                match http_response {
+                    HttpResponseType::PeerAllowlist(_, allowlist) => {
+                        assert_eq!(
+                            allowlist.public_keys,
+                            vec![to_hex(&public_key.to_bytes_compressed())]
+                        );
+                        PeerDB::is_peer_allowlisted(peer_server.network.peerdb.conn(), &public_key)
+                            .unwrap()
+                    }
+                    _ => {
+                        error!("Invalid response: {:?}", &http_response);
+                        false
+                    }
+                }
+            },
+        );
+    }
+
+    #[test]
+    #[ignore]
+    fn test_rpc_missing_index_getmicroblocks() {
+        test_rpc(
+            "test_rpc_missing_index_getmicroblocks",
+            40070,
+            40071,
+            50070,
+            50071,
+            true,
+            |ref mut peer_client,
+             ref mut convo_client,
+             ref mut peer_server,
+             ref mut convo_server| {
+                let peer_server_block_hash = BlockHeaderHash([0x04; 32]);
+                let peer_server_consensus_hash = ConsensusHash([0x02; 20]);
+                let index_block_hash = StacksBlockHeader::make_index_block_hash(
+                    &peer_server_consensus_hash,
+                    &peer_server_block_hash,
+                );
+
+                // now ask for it
+                convo_client.new_getmicroblocks_indexed(index_block_hash)
+            },
+            |ref http_request,
+             ref http_response,
+             ref mut peer_client,
+             ref mut peer_server,
+             ref convo_client,
+             ref convo_server| {
+                let req_md = http_request.metadata().clone();
+                match http_response {
+                    HttpResponseType::NotFound(response_md, msg) => true,
+                    _ => {
+                        error!("Invalid response: {:?}", &http_response);
+                        false
+                    }
+                }
+            },
+        );
+    }
+
+    #[test]
+    #[ignore]
+    fn test_rpc_missing_confirmed_getmicroblocks() {
+        test_rpc(
+            "test_rpc_missing_confirmed_getmicroblocks",
+            40072,
+            40073,
+            50072,
+            50073,
+            true,
+            |ref mut peer_client,
+             ref mut convo_client,
+             ref mut peer_server,
+             ref mut convo_server| {
+                let peer_server_block_hash = BlockHeaderHash([0x04; 32]);
+                let peer_server_consensus_hash = ConsensusHash([0x02; 20]);
+                let index_block_hash = StacksBlockHeader::make_index_block_hash(
+                    &peer_server_consensus_hash,
+                    &peer_server_block_hash,
+                );
+
+                // now ask for it
+                convo_client.new_getmicroblocks_confirmed(index_block_hash)
+            },
+            |ref http_request,
+             ref http_response,
+             ref mut peer_client,
+             ref mut peer_server,
+             ref convo_client,
+             ref convo_server| {
+                let req_md = http_request.metadata().clone();
+                match http_response {
+                    HttpResponseType::NotFound(response_md, msg) => true,
+                    _ => {
+                        error!("Invalid response: {:?}", &http_response);
+                        false
+                    }
+                }
+            },
+        );
+    }
+
+    #[test]
+    #[ignore]
+    fn test_rpc_missing_unconfirmed_microblocks() {
+        let server_microblocks_cell = RefCell::new(vec![]);
+
+        test_rpc(
+            "test_rpc_missing_unconfirmed_microblocks",
+            40080,
+            40081,
+            50080,
+            50081,
+            true,
+            |ref mut peer_client,
+             ref mut convo_client,
+             ref mut peer_server,
+             ref mut convo_server| {
+                let privk = StacksPrivateKey::from_hex(
+                    "eb05c83546fdd2c79f10f5ad5434a90dd28f7e3acb7c092157aa1bc3656b012c01",
+                )
+                .unwrap();
+
+                let consensus_hash = ConsensusHash([0x02; 20]);
+                let anchored_block_hash = BlockHeaderHash([0x03; 32]);
+                let index_block_hash =
+                    StacksBlockHeader::make_index_block_hash(&consensus_hash, &anchored_block_hash);
+
+                let mut mblocks = make_sample_microblock_stream(&privk, &anchored_block_hash);
+                mblocks.truncate(15);
+
+                for mblock in mblocks.iter() {
+                    store_staging_microblock(
                         peer_server.chainstate(),
                         &consensus_hash,
                         &anchored_block_hash,
@@ -5362,6 +6572,441 @@ mod test {
         );
     }
 
+    /// Mine a second anchored block on `peer`, on top of the block `test_rpc()` mined.  Besides
+    /// a coinbase from STVN97YYA10MY5F6KQJHKNYJNM24C4A1AT39WRW, it calls `hello-world.add-unit`
+    /// from ST2DS4MSWSGJ3W9FBC6BVT0Y92S345HY8N3T6AV7R (nonce 2, fee 123), which changes that
+    /// account's nonce and balance, `bar`, and its `unit-map` entry.  Returns the index block
+    /// hash of the first block, which is at height 1.
+    fn mine_add_unit_block(peer: &mut TestPeer) -> StacksBlockId {
+        // ST2DS4MSWSGJ3W9FBC6BVT0Y92S345HY8N3T6AV7R
+        let privk1 = StacksPrivateKey::from_hex(
+            "9f1f85a512a96a244e4c0d762788500687feb97481639572e3bffbd6860e6ab001",
+        )
+        .unwrap();
+        // STVN97YYA10MY5F6KQJHKNYJNM24C4A1AT39WRW
+        let privk2 = StacksPrivateKey::from_hex(
+            "94c319327cc5cd04da7147d32d836eb2e4c44f4db39aa5ede7314a761183d0c701",
+        )
+        .unwrap();
+        let addr1 =
+            StacksAddress::from_string("ST2DS4MSWSGJ3W9FBC6BVT0Y92S345HY8N3T6AV7R").unwrap();
+
+        let mut tx_coinbase = StacksTransaction::new(
+            TransactionVersion::Testnet,
+            TransactionAuth::from_p2pkh(&privk2).unwrap(),
+            TransactionPayload::Coinbase(CoinbasePayload([0x01; 32])),
+        );
+        tx_coinbase.chain_id = 0x80000000;
+        tx_coinbase.anchor_mode = TransactionAnchorMode::OnChainOnly;
+        tx_coinbase.auth.set_origin_nonce(0);
+
+        let mut tx_signer = StacksTransactionSigner::new(&tx_coinbase);
+        tx_signer.sign_origin(&privk2).unwrap();
+        let tx_coinbase_signed = tx_signer.get_tx().unwrap();
+
+        let mut tx_cc = StacksTransaction::new(
+            TransactionVersion::Testnet,
+            TransactionAuth::from_p2pkh(&privk1).unwrap(),
+            TransactionPayload::new_contract_call(addr1, "hello-world", "add-unit", vec![])
+                .unwrap(),
+        );
+        tx_cc.chain_id = 0x80000000;
+        tx_cc.anchor_mode = TransactionAnchorMode::OnChainOnly;
+        tx_cc.auth.set_origin_nonce(2);
+        tx_cc.set_tx_fee(123);
+
+        let mut tx_signer = StacksTransactionSigner::new(&tx_cc);
+        tx_signer.sign_origin(&privk1).unwrap();
+        let tx_cc_signed = tx_signer.get_tx().unwrap();
+
+        let sortdb = peer.sortdb.take().unwrap();
+        let parent_tip = peer
+            .chainstate()
+            .get_stacks_chain_tip(&sortdb)
+            .unwrap()
+            .unwrap();
+        peer.sortdb = Some(sortdb);
+
+        // this peer didn't mine the parent block, so build on its chain tip directly
+        let (burn_ops, stacks_block, _) =
+            peer.make_tenure(|_miner, sortdb, chainstate, vrf_proof, _parent_opt, _| {
+                let parent_header = StacksChainState::get_anchored_block_header_info(
+                    chainstate.db(),
+                    &parent_tip.consensus_hash,
+                    &parent_tip.anchored_block_hash,
+                )
+                .unwrap()
+                .unwrap();
+                let tip = SortitionDB::get_canonical_burn_chain_tip(sortdb.conn()).unwrap();
+                let block_builder = StacksBlockBuilder::make_regtest_block_builder(
+                    &parent_header,
+                    vrf_proof,
+                    tip.total_burn,
+                    Hash160([0x02; 20]),
+                )
+                .unwrap();
+                let (anchored_block, _, _) = StacksBlockBuilder::make_anchored_block_from_txs(
+                    block_builder,
+                    chainstate,
+                    &sortdb.index_conn(),
+                    vec![tx_coinbase_signed.clone(), tx_cc_signed.clone()],
+                )
+                .unwrap();
+                (anchored_block, vec![])
+            });
+        peer.next_burnchain_block(burn_ops);
+        peer.process_stacks_epoch_at_tip(&stacks_block, &vec![]);
+
+        let sortdb = peer.sortdb.take().unwrap();
+        let tip = peer
+            .chainstate()
+            .get_stacks_chain_tip(&sortdb)
+            .unwrap()
+            .unwrap();
+        peer.sortdb = Some(sortdb);
+        assert_eq!(tip.anchored_block_hash, stacks_block.block_hash());
+        assert_eq!(tip.height, 2);
+
+        StacksBlockHeader::make_index_block_hash(
+            &parent_tip.consensus_hash,
+            &parent_tip.anchored_block_hash,
+        )
+    }
+
+    /// In this test, the account is queried at height 1, below a tip that changed its nonce and
+    /// balance, with a proof.  We check that the account state is the one at height 1, and that
+    /// the proof is reported against that block.
+    #[test]
+    fn test_rpc_get_account_at_height() {
+        let ancestor = RefCell::new(None);
+        test_rpc(
+            "test_rpc_get_account_at_height",
+            40817,
+            40818,
+            50817,
+            50818,
+            false,
+            |ref mut peer_client,
+             ref mut convo_client,
+             ref mut peer_server,
+             ref mut convo_server| {
+                ancestor.replace(Some(mine_add_unit_block(peer_server)));
+                convo_client.new_getaccount(
+                    StacksAddress::from_string("ST2DS4MSWSGJ3W9FBC6BVT0Y92S345HY8N3T6AV7R")
+                        .unwrap()
+                        .to_account_principal(),
+                    TipRequest::AtHeight(1),
+                    true,
+                )
+            },
+            |ref http_request,
+             ref http_response,
+             ref mut peer_client,
+             ref mut peer_server,
+             ref convo_client,
+             ref convo_server| {
+                match http_response {
+                    HttpResponseType::GetAccount(response_md, data) => {
+                        // at the tip, the nonce is 3 and the balance is 1000000000 - 123
+                        assert_eq!(data.nonce, 2);
+                        let balance = u128::from_str_radix(&data.balance[2..], 16).unwrap();
+                        assert_eq!(balance, 1000000000);
+                        assert!(data.balance_proof.is_some());
+                        assert_eq!(data.index_block_hash, ancestor.borrow().clone());
+                        true
+                    }
+                    _ => {
+                        error!("Invalid response; {:?}", &http_response);
+                        false
+                    }
+                }
+            },
+        );
+    }
+
+    /// In this test, a data var is queried at height 1, below a tip that set it, with a proof.
+    /// We check that the value is the one at height 1, and that the proof is reported against
+    /// that block.
+    #[test]
+    fn test_rpc_get_data_var_at_height() {
+        let ancestor = RefCell::new(None);
+        test_rpc(
+            "test_rpc_get_data_var_at_height",
+            40829,
+            40830,
+            50829,
+            50830,
+            false,
+            |ref mut peer_client,
+             ref mut convo_client,
+             ref mut peer_server,
+             ref mut convo_server| {
+                ancestor.replace(Some(mine_add_unit_block(peer_server)));
+                convo_client.new_getdatavar(
+                    StacksAddress::from_string("ST2DS4MSWSGJ3W9FBC6BVT0Y92S345HY8N3T6AV7R")
+                        .unwrap(),
+                    "hello-world".try_into().unwrap(),
+                    "bar".try_into().unwrap(),
+                    TipRequest::AtHeight(1),
+                    true,
+                )
+            },
+            |ref http_request,
+             ref http_response,
+             ref mut peer_client,
+             ref mut peer_server,
+             ref convo_client,
+             ref convo_server| {
+                match http_response {
+                    HttpResponseType::GetDataVar(response_md, data) => {
+                        // `add-unit` set it to 1 at the tip
+                        assert_eq!(
+                            Value::try_deserialize_hex_untyped(&data.data).unwrap(),
+                            Value::Int(0)
+                        );
+                        assert!(data.marf_proof.is_some());
+                        assert_eq!(data.index_block_hash, ancestor.borrow().clone());
+                        true
+                    }
+                    _ => {
+                        error!("Invalid response; {:?}", &http_response);
+                        false
+                    }
+                }
+            },
+        );
+    }
+
+    /// In this test, a map entry is queried at height 1, below a tip that overwrote it, with a
+    /// proof.  We check that the entry is the one at height 1, and that the proof is reported
+    /// against that block.
+    #[test]
+    fn test_rpc_get_map_entry_at_height() {
+        let ancestor = RefCell::new(None);
+        test_rpc(
+            "test_rpc_get_map_entry_at_height",
+            40831,
+            40832,
+            50831,
+            50832,
+            false,
+            |ref mut peer_client,
+             ref mut convo_client,
+             ref mut peer_server,
+             ref mut convo_server| {
+                ancestor.replace(Some(mine_add_unit_block(peer_server)));
+                let principal =
+                    StacksAddress::from_string("ST2DS4MSWSGJ3W9FBC6BVT0Y92S345HY8N3T6AV7R")
+                        .unwrap()
+                        .to_account_principal();
+                convo_client.new_getmapentry(
+                    StacksAddress::from_string("ST2DS4MSWSGJ3W9FBC6BVT0Y92S345HY8N3T6AV7R")
+                        .unwrap(),
+                    "hello-world".try_into().unwrap(),
+                    "unit-map".try_into().unwrap(),
+                    Value::Tuple(
+                        TupleData::from_data(vec![("account".into(), Value::Principal(principal))])
+                            .unwrap(),
+                    ),
+                    TipRequest::AtHeight(1),
+                    true,
+                )
+            },
+            |ref http_request,
+             ref http_response,
+             ref mut peer_client,
+             ref mut peer_server,
+             ref convo_client,
+             ref convo_server| {
+                match http_response {
+                    HttpResponseType::GetMapEntry(response_md, data) => {
+                        // `add-unit` set it to { units: 1 } at the tip
+                        assert_eq!(
+                            Value::try_deserialize_hex_untyped(&data.data).unwrap(),
+                            Value::some(Value::Tuple(
+                                TupleData::from_data(vec![("units".into(), Value::Int(123))])
+                                    .unwrap()
+                            ))
+                            .unwrap()
+                        );
+                        assert!(data.marf_proof.is_some());
+                        assert_eq!(data.index_block_hash, ancestor.borrow().clone());
+                        true
+                    }
+                    _ => {
+                        error!("Invalid response; {:?}", &http_response);
+                        false
+                    }
+                }
+            },
+        );
+    }
+
+    /// In this test, a function that reads a data var is called at height 1, below a tip that
+    /// set the var.  We check that the result is computed from the state at height 1.
+    #[test]
+    fn test_rpc_call_read_only_at_height() {
+        test_rpc(
+            "test_rpc_call_read_only_at_height",
+            40833,
+            40834,
+            50833,
+            50834,
+            false,
+            |ref mut peer_client,
+             ref mut convo_client,
+             ref mut peer_server,
+             ref mut convo_server| {
+                mine_add_unit_block(peer_server);
+                convo_client.new_callreadonlyfunction(
+                    StacksAddress::from_string("ST2DS4MSWSGJ3W9FBC6BVT0Y92S345HY8N3T6AV7R")
+                        .unwrap(),
+                    "hello-world".try_into().unwrap(),
+                    StacksAddress::from_string("ST2DS4MSWSGJ3W9FBC6BVT0Y92S345HY8N3T6AV7R")
+                        .unwrap()
+                        .to_account_principal(),
+                    "get-bar".try_into().unwrap(),
+                    vec![],
+                    TipRequest::AtHeight(1),
+                )
+            },
+            |ref http_request,
+             ref http_response,
+             ref mut peer_client,
+             ref mut peer_server,
+             ref convo_client,
+             ref convo_server| {
+                match http_response {
+                    HttpResponseType::CallReadOnlyFunction(response_md, data) => {
+                        assert!(data.okay);
+                        // `add-unit` set `bar` to 1 at the tip
+                        assert_eq!(
+                            Value::try_deserialize_hex_untyped(&data.result.clone().unwrap())
+                                .unwrap(),
+                            Value::okay(Value::Int(0)).unwrap()
+                        );
+                        true
+                    }
+                    _ => {
+                        error!("Invalid response; {:?}", &http_response);
+                        false
+                    }
+                }
+            },
+        );
+    }
+
+    /// In this test, a data var is fetched with a proof from a node whose chain tip set it, and
+    /// the proof is checked the way `blockstack-cli verify-proof` checks it: against the tip's
+    /// `state_index_root`, with a root-to-block map built from the node's block headers.
+    #[test]
+    fn test_rpc_get_data_var_proof_verifies() {
+        test_rpc(
+            "test_rpc_get_data_var_proof_verifies",
+            40835,
+            40836,
+            50835,
+            50836,
+            false,
+            |ref mut peer_client,
+             ref mut convo_client,
+             ref mut peer_server,
+             ref mut convo_server| {
+                mine_add_unit_block(peer_server);
+                convo_client.new_getdatavar(
+                    StacksAddress::from_string("ST2DS4MSWSGJ3W9FBC6BVT0Y92S345HY8N3T6AV7R")
+                        .unwrap(),
+                    "hello-world".try_into().unwrap(),
+                    "bar".try_into().unwrap(),
+                    TipRequest::UseLatestAnchoredTip,
+                    true,
+                )
+            },
+            |ref http_request,
+             ref http_response,
+             ref mut peer_client,
+             ref mut peer_server,
+             ref convo_client,
+             ref convo_server| {
+                let data = match http_response {
+                    HttpResponseType::GetDataVar(_, data) => data.clone(),
+                    _ => {
+                        error!("Invalid response; {:?}", &http_response);
+                        return false;
+                    }
+                };
+                let contract = QualifiedContractIdentifier::parse(
+                    "ST2DS4MSWSGJ3W9FBC6BVT0Y92S345HY8N3T6AV7R.hello-world",
+                )
+                .unwrap();
+                let key = verify::data_var_key(&contract, "bar");
+                assert_eq!(key, format!("vm::{}::1::bar", &contract));
+                // `add-unit` set it to 1 in the tip
+                let value = data.data.trim_start_matches("0x");
+                assert_eq!(value, Value::Int(1).serialize_to_hex());
+                let proof = hex_bytes(data.marf_proof.unwrap().trim_start_matches("0x")).unwrap();
+
+                let chainstate = peer_server.chainstate();
+                let tip = data.index_block_hash.unwrap();
+                let state_root =
+                    StacksChainState::get_stacks_block_header_info_by_index_block_hash(
+                        chainstate.db(),
+                        &tip,
+                    )
+                    .unwrap()
+                    .unwrap()
+                    .anchored_header
+                    .state_index_root;
+
+                // every header but the genesis block's commits to its trie's root
+                let mut stmt = chainstate
+                    .db()
+                    .prepare(
+                        "SELECT index_root, state_index_root, index_block_hash, block_height \
+                         FROM block_headers",
+                    )
+                    .unwrap();
+                let headers: Vec<(String, String, String, i64)> = stmt
+                    .query_map(NO_PARAMS, |row| {
+                        Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?))
+                    })
+                    .unwrap()
+                    .map(|row| row.unwrap())
+                    .collect();
+                assert_eq!(headers.len(), 3);
+                let mut root_to_block = serde_json::Map::new();
+                for (index_root, state_index_root, index_block_hash, block_height) in headers {
+                    if block_height > 0 {
+                        assert_eq!(index_root, state_index_root);
+                    }
+                    root_to_block.insert(index_root, serde_json::Value::String(index_block_hash));
+                }
+                let root_to_block =
+                    verify::parse_root_to_block(&serde_json::to_string(&root_to_block).unwrap())
+                        .unwrap();
+
+                assert!(verify::verify_state_proof(
+                    &proof,
+                    &key,
+                    value,
+                    &state_root,
+                    &root_to_block
+                )
+                .unwrap());
+                // the value `bar` had before the tip doesn't check out
+                assert!(!verify::verify_state_proof(
+                    &proof,
+                    &key,
+                    &Value::Int(0).serialize_to_hex(),
+                    &state_root,
+                    &root_to_block
+                )
+                .unwrap());
+                true
+            },
+        );
+    }
+
     /// In this test, the query parameter `tip_req` is set to UseLatestUnconfirmedTip, and so we expect the
     /// tip used for the query to be the latest microblock.
     /// We check that the account state matches the state in the most recent microblock.
@@ -665,6 +665,34 @@ impl HttpPeer {
         close
     }
 
+    /// Push out data on live event streams.  They get new data without any activity on their
+    /// sockets, so the poller won't report them as ready.
+    /// Return the list of conversation event IDs whose sockets are broken.
+    fn flush_event_streams(
+        &mut self,
+        mempool: &MemPoolDB,
+        chainstate: &mut StacksChainState,
+    ) -> Vec<usize> {
+        let mut broken = vec![];
+        for (event_id, convo) in self.peers.iter_mut() {
+            if !convo.has_open_ended_stream() {
+                continue;
+            }
+            if let Some(client_sock) = self.sockets.get_mut(event_id) {
+                if let Err(e) =
+                    HttpPeer::saturate_http_socket(client_sock, convo, mempool, chainstate)
+                {
+                    debug!(
+                        "Failed to send event stream data to event {} (socket {:?}): {:?}",
+                        event_id, &client_sock, &e
+                    );
+                    broken.push(*event_id);
+                }
+            }
+        }
+        broken
+    }
+
     /// Update HTTP server state
     /// -- accept new connections
     /// -- send data on ready sockets
@@ -708,6 +736,12 @@ impl HttpPeer {
             self.deregister_http(network_state, close_event);
         }
 
+        let broken_events = self.flush_event_streams(mempool, chainstate);
+        for broken_event in broken_events {
+            debug!("Broken event stream on event {}", broken_event);
+            self.deregister_http(network_state, broken_event);
+        }
+
         // remove timed-out requests
         for (_, convo) in self.peers.iter_mut() {
             convo.clear_timeouts();
@@ -1,3 +1,7 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Condvar, Mutex};
+use std::time::{Duration, Instant};
+
 use reqwest::StatusCode;
 use serde_json::json;
 use stacks::address::AddressHashMode;
@@ -8,8 +12,9 @@ use stacks::chainstate::stacks::{
     TransactionVersion,
 };
 use stacks::net::http::HttpBlockProposalRejected;
-use stacks::net::RPCFeeEstimateResponse;
-use stacks::util::hash::hex_bytes;
+use stacks::net::{BlockProposalResponse, BlockProposalSignatureData, RPCFeeEstimateResponse};
+use stacks::util::hash::{hex_bytes, Sha256Sum};
+use stacks::util::secp256k1::Secp256k1PublicKey;
 use stacks::vm::types::{QualifiedContractIdentifier, TupleData};
 use stacks::vm::ClarityName;
 use stacks::vm::Value as ClarityValue;
@@ -18,6 +23,7 @@ use stacks_common::types::StacksEpochId;
 use stacks_common::util::hash::{to_hex, Sha512Trunc256Sum};
 
 use crate::config::BurnchainConfig;
+use crate::monitoring::observe_block_proposal_latency;
 use crate::operations::BurnchainOpSigner;
 use crate::stacks_common::codec::StacksMessageCodec;
 
@@ -54,10 +60,29 @@ pub struct DirectCommitter {
     pub config: BurnchainConfig,
 }
 
+/// How long to wait for a federation peer to answer a block proposal sent over P2P
+pub const P2P_BLOCK_PROPOSAL_TIMEOUT: Duration = Duration::from_secs(30);
+
 #[derive(Clone, Debug)]
 pub struct MultiMinerParticipant {
     pub public_key: [u8; 33],
     pub rpc_server: String,
+    /// The participant's P2P public key. If set, block proposals are sent to it over the P2P
+    /// network instead of to `rpc_server`.
+    pub p2p_public_key: Option<[u8; 33]>,
+}
+
+/// Replies to this node's block proposals that arrived over the P2P network, keyed by proposal
+/// hash. The p2p thread records them, and the miner waits on them.
+#[derive(Clone, Default)]
+pub struct ProposalSignatures {
+    replies: Arc<(Mutex<HashMap<Sha256Sum, ProposalReplies>>, Condvar)>,
+}
+
+/// The replies received so far to one block proposal
+struct ProposalReplies {
+    sent_at: Instant,
+    replies: Vec<(Secp256k1PublicKey, BlockProposalResponse)>,
 }
 
 pub struct MultiPartyCommitter {
@@ -192,6 +217,131 @@ fn calculate_l1_fee_for_transaction(
     compute_fee_from_response_and_transaction(transaction, &json_response)
 }
 
+impl MultiMinerParticipant {
+    pub fn get_p2p_public_key(&self) -> Option<Secp256k1PublicKey> {
+        self.p2p_public_key
+            .as_ref()
+            .map(|key| Secp256k1PublicKey::from_slice(key).expect("Failed to load P2P public key"))
+    }
+}
+
+impl ProposalSignatures {
+    /// Start collecting replies to the proposal with hash `proposal_hash`
+    pub fn expect(&self, proposal_hash: &Sha256Sum) {
+        let (ref replies, _) = *self.replies;
+        replies
+            .lock()
+            .expect("FATAL: proposal signatures lock poisoned")
+            .insert(
+                proposal_hash.clone(),
+                ProposalReplies {
+                    sent_at: Instant::now(),
+                    replies: vec![],
+                },
+            );
+    }
+
+    /// Stop collecting replies to the proposal with hash `proposal_hash`
+    pub fn forget(&self, proposal_hash: &Sha256Sum) {
+        let (ref replies, _) = *self.replies;
+        replies
+            .lock()
+            .expect("FATAL: proposal signatures lock poisoned")
+            .remove(proposal_hash);
+    }
+
+    /// Record replies from federation peers. Replies to proposals that aren't expected are
+    /// dropped.
+    pub fn record(&self, received: Vec<(Secp256k1PublicKey, BlockProposalSignatureData)>) {
+        let (ref replies, ref cvar) = *self.replies;
+        let mut replies = replies
+            .lock()
+            .expect("FATAL: proposal signatures lock poisoned");
+        for (signer, data) in received.into_iter() {
+            match replies.get_mut(&data.proposal_hash) {
+                Some(proposal_replies) => proposal_replies.replies.push((signer, data.response)),
+                None => {
+                    debug!("Dropping reply to unknown block proposal";
+                           "proposal_hash" => %data.proposal_hash);
+                }
+            }
+        }
+        cvar.notify_all();
+    }
+
+    /// Wait up to `timeout` for `participant` to answer, over the P2P network, the proposal with
+    /// hash `proposal_hash`
+    pub fn wait_for(
+        &self,
+        proposal_hash: &Sha256Sum,
+        participant: &MultiMinerParticipant,
+        timeout: Duration,
+    ) -> Result<ClaritySignature, Error> {
+        let p2p_public_key = participant
+            .get_p2p_public_key()
+            .ok_or(Error::NoSuchParticipant)?;
+        let deadline = Instant::now() + timeout;
+        let (ref replies, ref cvar) = *self.replies;
+        let mut replies = replies
+            .lock()
+            .expect("FATAL: proposal signatures lock poisoned");
+        loop {
+            let proposal_replies = replies.get(proposal_hash);
+            let sent_at = proposal_replies.map(|proposal_replies| proposal_replies.sent_at);
+            let response = proposal_replies
+                .and_then(|proposal_replies| {
+                    proposal_replies
+                        .replies
+                        .iter()
+                        .find(|(signer, _)| *signer == p2p_public_key)
+                })
+                .map(|(_, response)| response.clone());
+            let result = match response {
+                Some(BlockProposalResponse::Accepted(signature)) => {
+                    Some(Ok(ClaritySignature(signature)))
+                }
+                Some(BlockProposalResponse::Rejected(reason)) => {
+                    Some(Err(Error::BlockProposalRejected(reason)))
+                }
+                None if Instant::now() >= deadline => Some(Err(Error::BlockProposalRequest(
+                    "Timed out waiting for a P2P reply".into(),
+                ))),
+                None => None,
+            };
+            if let Some(result) = result {
+                if let Some(sent_at) = sent_at {
+                    observe_proposal_result(participant, &result, sent_at);
+                }
+                return result;
+            }
+
+            replies = cvar
+                .wait_timeout(replies, deadline.saturating_duration_since(Instant::now()))
+                .expect("FATAL: proposal signatures lock poisoned")
+                .0;
+        }
+    }
+}
+
+/// Record how long `participant` took to answer a block proposal sent at `sent_at`, and how
+/// it answered
+fn observe_proposal_result(
+    participant: &MultiMinerParticipant,
+    result: &Result<ClaritySignature, Error>,
+    sent_at: Instant,
+) {
+    let outcome = match result {
+        Ok(_) => "accepted",
+        Err(Error::BlockProposalRejected(_)) => "rejected",
+        Err(_) => "failed",
+    };
+    observe_block_proposal_latency(
+        &to_hex(&participant.public_key),
+        outcome,
+        sent_at.elapsed().as_secs_f64(),
+    );
+}
+
 impl std::fmt::Display for Error {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
@@ -306,6 +456,59 @@ impl MultiPartyCommitter {
             .expect("Failed to get signed transaction from signer"))
     }
 
+    /// Send a block proposal to `participant`'s RPC server, and parse its answer
+    fn send_proposal(
+        propose_to: &MultiMinerParticipant,
+        proposal: &SignedProposal,
+    ) -> Result<ClaritySignature, Error> {
+        let url = format!(
+            "{}{}",
+            &propose_to.rpc_server,
+            stacks::net::http::PATH_STR_POST_BLOCK_PROPOSAL
+        );
+        let response = reqwest::blocking::Client::new()
+            .post(url)
+            .json(proposal)
+            .send()
+            .map_err(|e| Error::BlockProposalRequest(e.to_string()))?;
+        match response.status() {
+            StatusCode::OK => {
+                let signature_hex: String = response
+                    .json()
+                    .map_err(|e| Error::BlockProposalRequest(e.to_string()))?;
+                // 132 = 65 * 2 + "0x" prefix
+                if signature_hex.len() != 132 {
+                    return Err(Error::BlockProposalRequest(
+                        "Bad signature hex length".into(),
+                    ));
+                }
+
+                let signature_bytes = hex_bytes(&signature_hex[2..])
+                    .map_err(|_| Error::BlockProposalRequest("Bad hex bytes".into()))?;
+                if signature_bytes.len() != 65 {
+                    return Err(Error::BlockProposalRequest(
+                        "Bad signature byte length".into(),
+                    ));
+                }
+                let mut signature_buff = [0u8; 65];
+                signature_buff.copy_from_slice(&signature_bytes);
+                Ok(ClaritySignature(signature_buff))
+            }
+            StatusCode::NOT_ACCEPTABLE => {
+                let error_struct: HttpBlockProposalRejected = response
+                    .json()
+                    .map_err(|e| Error::BlockProposalRequest(e.to_string()))?;
+                Err(Error::BlockProposalRejected(error_struct.error_message))
+            }
+            _ => {
+                let error_message = response
+                    .text()
+                    .map_err(|e| Error::BlockProposalRequest(e.to_string()))?;
+                Err(Error::BlockProposalRequest(error_message))
+            }
+        }
+    }
+
     pub fn make_commit_tx(
         &self,
         committed_block_hash: BlockHeaderHash,
@@ -385,52 +588,10 @@ impl Layer1Committer for MultiPartyCommitter {
             return Err(Error::NoSuchParticipant);
         }
         let propose_to = &self.other_participants[participant_index as usize];
-        let url = format!(
-            "{}{}",
-            &propose_to.rpc_server,
-            stacks::net::http::PATH_STR_POST_BLOCK_PROPOSAL
-        );
-        let response = reqwest::blocking::Client::new()
-            .post(url)
-            .json(proposal)
-            .send()
-            .map_err(|e| Error::BlockProposalRequest(e.to_string()))?;
-        match response.status() {
-            StatusCode::OK => {
-                let signature_hex: String = response
-                    .json()
-                    .map_err(|e| Error::BlockProposalRequest(e.to_string()))?;
-                // 132 = 65 * 2 + "0x" prefix
-                if signature_hex.len() != 132 {
-                    return Err(Error::BlockProposalRequest(
-                        "Bad signature hex length".into(),
-                    ));
-                }
-
-                let signature_bytes = hex_bytes(&signature_hex[2..])
-                    .map_err(|_| Error::BlockProposalRequest("Bad hex bytes".into()))?;
-                if signature_bytes.len() != 65 {
-                    return Err(Error::BlockProposalRequest(
-                        "Bad signature byte length".into(),
-                    ));
-                }
-                let mut signature_buff = [0u8; 65];
-                signature_buff.copy_from_slice(&signature_bytes);
-                Ok(ClaritySignature(signature_buff))
-            }
-            StatusCode::NOT_ACCEPTABLE => {
-                let error_struct: HttpBlockProposalRejected = response
-                    .json()
-                    .map_err(|e| Error::BlockProposalRequest(e.to_string()))?;
-                Err(Error::BlockProposalRejected(error_struct.error_message))
-            }
-            _ => {
-                let error_message = response
-                    .text()
-                    .map_err(|e| Error::BlockProposalRequest(e.to_string()))?;
-                Err(Error::BlockProposalRequest(error_message))
-            }
-        }
+        let sent_at = Instant::now();
+        let result = Self::send_proposal(propose_to, proposal);
+        observe_proposal_result(propose_to, &result, sent_at);
+        result
     }
 
     fn make_commit_tx(
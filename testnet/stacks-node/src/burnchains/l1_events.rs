@@ -31,6 +31,8 @@ use super::{burnchain_from_config, BurnchainChannel, ClaritySignature, Error};
 
 use crate::burnchains::commitment::DirectCommitter;
 use crate::config::CommitStrategy;
+use crate::health::record_l1_contract_check;
+use crate::monitoring::increment_l1_commits_counter;
 use crate::operations::BurnchainOpSigner;
 use crate::util::hash::Sha512Trunc256Sum;
 use crate::{BurnchainController, BurnchainTip, Config};
@@ -407,12 +409,19 @@ impl L1Controller {
                 // This error is fatal. We can't continue with wrong contract version
                 Err(e @ Error::BurnchainContractVersion(_)) => panic!("{e}"),
                 // Error checking version, not fatal
-                Err(e @ Error::BurnchainContractCheck(_)) => Err(e),
+                Err(e @ Error::BurnchainContractCheck(_)) => {
+                    record_l1_contract_check(Err(e.to_string()));
+                    Err(e)
+                }
                 // Error, transform into `Error::BurnchainContractCheck`
-                Err(e) => Err(Error::BurnchainContractCheck(e.to_string())),
+                Err(e) => {
+                    record_l1_contract_check(Err(e.to_string()));
+                    Err(Error::BurnchainContractCheck(e.to_string()))
+                }
                 Ok(version) => {
                     info!("Found supported L1 contract version: {version}");
                     self.l1_contract_check_passed = true;
+                    record_l1_contract_check(Ok(()));
                     Ok(())
                 }
             },
@@ -463,17 +472,29 @@ impl BurnchainController for L1Controller {
     ) -> Result<Txid, Error> {
         self.l1_contract_ok()?;
 
-        let tx = self.committer.make_commit_tx(
-            committed_block_hash,
-            committed_block_height,
-            target_tip,
-            target_height,
-            withdrawal_merkle_root,
-            signatures,
-            op_signer,
-        )?;
+        let tx = self
+            .committer
+            .make_commit_tx(
+                committed_block_hash,
+                committed_block_height,
+                target_tip,
+                target_height,
+                withdrawal_merkle_root,
+                signatures,
+                op_signer,
+            )
+            .map_err(|e| {
+                increment_l1_commits_counter("build_failed");
+                e
+            })?;
 
-        self.l1_submit_tx(tx)
+        let result = self.l1_submit_tx(tx);
+        increment_l1_commits_counter(if result.is_ok() {
+            "submitted"
+        } else {
+            "submit_failed"
+        });
+        result
     }
 
     fn sync(&mut self, target_block_height_opt: Option<u64>) -> Result<(BurnchainTip, u64), Error> {
@@ -122,6 +122,7 @@ fn make_dummy_response_with_num_estimations(num_estimations: u64) -> RPCFeeEstim
         estimated_cost_scalar: 6,
         estimations,
         cost_scalar_change_by_byte: 1.0,
+        fee_asset: None,
     }
 }
 #[test]
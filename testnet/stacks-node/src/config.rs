@@ -6,13 +6,14 @@ use std::path::PathBuf;
 use rand::RngCore;
 
 use stacks::chainstate::coordinator::comm::CoordinatorChannels;
+use stacks::chainstate::stacks::db::genesis::GenesisManifest;
 use stacks::chainstate::stacks::index::marf::MARFOpenOpts;
 use stacks::chainstate::stacks::index::storage::TrieHashCalculationMode;
 use stacks::chainstate::stacks::miner::BlockBuilderSettings;
 use stacks::chainstate::stacks::StacksPrivateKey;
 use stacks::chainstate::stacks::TransactionAnchorMode;
 use stacks::chainstate::stacks::MAX_BLOCK_LEN;
-use stacks::core::mempool::MemPoolWalkSettings;
+use stacks::core::mempool::{MemPoolReplacePolicy, MemPoolWalkSettings};
 use stacks::core::{StacksEpoch, NETWORK_ID_TESTNET, SUBNET_CHAIN_ID};
 use stacks::core::{
     LAYER_1_CHAIN_ID_MAINNET, LAYER_1_CHAIN_ID_TESTNET, PEER_VERSION_MAINNET, PEER_VERSION_TESTNET,
@@ -36,11 +37,16 @@ use stacks::vm::types::{AssetIdentifier, PrincipalData, QualifiedContractIdentif
 use crate::burnchains::commitment::MultiMinerParticipant;
 use crate::burnchains::l1_events::L1Controller;
 use crate::burnchains::mock_events::MockController;
+use crate::event_file_sink::{EventFileSinkConfig, DEFAULT_ROTATE_BYTES};
+use crate::event_outbox::{BacklogPolicy, DEFAULT_MAX_BACKLOG};
 use crate::BurnchainController;
 
 const DEFAULT_MAX_RBF_RATE: u64 = 150; // 1.5x
 const DEFAULT_RBF_FEE_RATE_INCREMENT: u64 = 5;
 const INV_REWARD_CYCLES_TESTNET: u64 = 6;
+/// Nodes pruning block data keep at least this many blocks of data below the tip, so they can
+/// still serve (and reprocess) recent forks.
+const MIN_PRUNE_DEPTH: u64 = 144;
 
 pub const BURNCHAIN_NAME_STACKS_TESTNET_L1: &str = "stacks_layer_1";
 pub const BURNCHAIN_NAME_STACKS_MAINNET_L1: &str = "stacks_layer_1::mainnet";
@@ -181,6 +187,60 @@ impl Config {
                     wait_before_first_anchored_block: node
                         .wait_before_first_anchored_block
                         .unwrap_or(default_node_config.wait_before_first_anchored_block),
+                    genesis_manifest: node
+                        .genesis_manifest
+                        .map(|path| NodeConfig::load_genesis_manifest(&path)),
+                    max_event_stream_subscribers: node
+                        .max_event_stream_subscribers
+                        .unwrap_or(default_node_config.max_event_stream_subscribers),
+                    event_stream_buffer_size: node
+                        .event_stream_buffer_size
+                        .unwrap_or(default_node_config.event_stream_buffer_size),
+                    health_l1_block_max_age_secs: node
+                        .health_l1_block_max_age_secs
+                        .unwrap_or(default_node_config.health_l1_block_max_age_secs),
+                    health_max_sortition_lag: node
+                        .health_max_sortition_lag
+                        .unwrap_or(default_node_config.health_max_sortition_lag),
+                    health_commit_max_age_secs: node
+                        .health_commit_max_age_secs
+                        .unwrap_or(default_node_config.health_commit_max_age_secs),
+                    txindex: node.txindex.unwrap_or(default_node_config.txindex),
+                    prune_depth: node.prune_depth.map(|depth| {
+                        if depth < MIN_PRUNE_DEPTH {
+                            warn!(
+                                "prune_depth {} is below the minimum, using {}",
+                                depth, MIN_PRUNE_DEPTH
+                            );
+                            MIN_PRUNE_DEPTH
+                        } else {
+                            depth
+                        }
+                    }),
+                    mempool_replace_policy: MemPoolReplacePolicy {
+                        min_fee_bump_pct: node
+                            .mempool_min_fee_bump_pct
+                            .unwrap_or(default_node_config.mempool_replace_policy.min_fee_bump_pct),
+                        allow_cancel_by_self_transfer: node
+                            .mempool_allow_cancel_by_self_transfer
+                            .unwrap_or(
+                                default_node_config
+                                    .mempool_replace_policy
+                                    .allow_cancel_by_self_transfer,
+                            ),
+                        min_cancel_interval_secs: node.mempool_min_cancel_interval_secs.unwrap_or(
+                            default_node_config
+                                .mempool_replace_policy
+                                .min_cancel_interval_secs,
+                        ),
+                        max_pending_txs_per_principal: node
+                            .mempool_max_pending_txs_per_principal
+                            .unwrap_or(
+                                default_node_config
+                                    .mempool_replace_policy
+                                    .max_pending_txs_per_principal,
+                            ),
+                    },
                     ..default_node_config
                 };
                 (node_config, node.bootstrap_node, node.deny_nodes)
@@ -299,11 +359,15 @@ impl Config {
         };
 
         if let Some(bootstrap_node) = bootstrap_node {
-            node.set_bootstrap_nodes(bootstrap_node, node.chain_id, burnchain.peer_version);
+            node.set_bootstrap_nodes(
+                bootstrap_node,
+                node.p2p_network_id(),
+                burnchain.peer_version,
+            );
         }
 
         if let Some(deny_nodes) = deny_nodes {
-            node.set_deny_nodes(deny_nodes, node.chain_id, burnchain.peer_version);
+            node.set_deny_nodes(deny_nodes, node.p2p_network_id(), burnchain.peer_version);
         }
 
         let initial_balances_len = config_file
@@ -325,11 +389,34 @@ impl Config {
                         .map(|e| EventKeyType::from_string(e).unwrap())
                         .collect();
 
-                    let endpoint = format!("{}", observer.endpoint);
+                    let file_sink = observer.file_path.map(|file_path| EventFileSinkConfig {
+                        rotate_bytes: observer.file_rotate_bytes.unwrap_or(DEFAULT_ROTATE_BYTES),
+                        rotate_secs: observer.file_rotate_secs.unwrap_or(0),
+                        compress: observer.file_compress.unwrap_or(false),
+                        ..EventFileSinkConfig::new(PathBuf::from(file_path))
+                    });
+                    let endpoint = match (observer.endpoint, file_sink.as_ref()) {
+                        (Some(endpoint), None) => endpoint,
+                        (None, Some(file_sink)) => file_sink.observer_name(),
+                        _ => panic!(
+                            "Config file error: each events_observer needs exactly one of endpoint and file_path"
+                        ),
+                    };
+
+                    let backlog_policy = match observer.backlog_policy {
+                        Some(policy) => BacklogPolicy::from_string(&policy).expect(&format!(
+                            "Invalid backlog_policy '{}' for event observer {}: expected 'block' or 'drop'",
+                            policy, endpoint
+                        )),
+                        None => BacklogPolicy::default(),
+                    };
 
                     observers.push(EventObserverConfig {
                         endpoint,
                         events_keys,
+                        max_backlog: observer.max_backlog.unwrap_or(DEFAULT_MAX_BACKLOG),
+                        backlog_policy,
+                        file_sink,
                     });
                 }
                 observers
@@ -342,6 +429,7 @@ impl Config {
             Ok(val) => events_observers.push(EventObserverConfig {
                 endpoint: val,
                 events_keys: vec![EventKeyType::AnyEvent],
+                ..EventObserverConfig::default()
             }),
             _ => (),
         };
@@ -487,6 +575,28 @@ impl Config {
                     max_sockets: opts.max_sockets.unwrap_or(800) as usize,
                     antientropy_public: opts.antientropy_public.unwrap_or(true),
                     subnet_validator: node.mining_key.clone(),
+                    federation_peers: opts
+                        .federation_peers
+                        .unwrap_or_default()
+                        .iter()
+                        .map(|pubkey_str| {
+                            Secp256k1PublicKey::from_hex(pubkey_str)
+                                .expect("Invalid federation peer public key")
+                        })
+                        .collect(),
+                    permissioned: opts.permissioned.unwrap_or(false),
+                    peer_allowlist: opts
+                        .peer_allowlist
+                        .unwrap_or_default()
+                        .iter()
+                        .map(|pubkey_str| {
+                            Secp256k1PublicKey::from_hex(pubkey_str)
+                                .expect("Invalid allowlisted peer public key")
+                        })
+                        .collect(),
+                    peer_allowlist_admin_token: opts
+                        .peer_allowlist_admin_token
+                        .filter(|token| !token.is_empty()),
                     ..ConnectionOptions::default()
                 };
                 if let CommitStrategy::MultiMiner {
@@ -501,8 +611,14 @@ impl Config {
                         .map(|p| {
                             Secp256k1PublicKey::from_slice(&p.public_key)
                                 .expect("Failed to load public key")
+                                .to_bytes_compressed()
                         })
                         .collect();
+                    result_opts.federation_peers.extend(
+                        other_participants
+                            .iter()
+                            .filter_map(|p| p.get_p2p_public_key()),
+                    );
                 }
 
                 result_opts
@@ -540,6 +656,14 @@ impl Config {
         path
     }
 
+    /// Returns the path `{get_chainstate_path()}/event_observers`, where the outboxes of
+    /// undelivered event observer payloads are kept.
+    pub fn get_event_outbox_path(&self) -> PathBuf {
+        let mut path = self.get_chainstate_path();
+        path.push("event_observers");
+        path
+    }
+
     /// Returns the path `{get_chainstate_path()}/estimates`, and ensures it exists.
     pub fn get_estimates_path(&self) -> PathBuf {
         let mut path = self.get_chainstate_path();
@@ -633,7 +757,7 @@ impl Config {
     pub fn add_bootstrap_node(&mut self, bootstrap_node: &str) {
         self.node.add_bootstrap_node(
             bootstrap_node,
-            self.node.chain_id,
+            self.node.p2p_network_id(),
             self.burnchain.peer_version,
         );
     }
@@ -874,6 +998,30 @@ pub struct NodeConfig {
     ///  as the validation key when running as a subnet 'validator' (i.e.,
     ///  the follower in the two-phase commit protocol)
     pub mining_key: Option<StacksPrivateKey>,
+    /// Balances, asset mappings and contracts installed in the genesis block.
+    /// Every node of the subnet must be configured with the same manifest.
+    pub genesis_manifest: Option<GenesisManifest>,
+    /// How many clients may subscribe to event streams on the RPC server at once.
+    /// If 0 (the default), event streams are disabled. Each subscriber's payloads are built on
+    /// the block-processing thread, while the subscriber list is locked, so every subscriber
+    /// adds to the time it takes to process a block.
+    pub max_event_stream_subscribers: u64,
+    /// How many payloads an event stream subscriber may fall behind before it is cut off.
+    pub event_stream_buffer_size: u64,
+    /// Rules for replacing, cancelling and capping pending mempool transactions.
+    pub mempool_replace_policy: MemPoolReplacePolicy,
+    /// `/health/ready` fails if the L1 observer hasn't received a block in this many seconds.
+    pub health_l1_block_max_age_secs: u64,
+    /// `/health/ready` fails if the sortition tip is more than this many blocks behind the L1
+    /// indexer.
+    pub health_max_sortition_lag: u64,
+    /// `/health/ready` fails if a miner hasn't submitted a commitment in this many seconds.
+    pub health_commit_max_age_secs: u64,
+    /// Index confirmed transactions by txid, for `/v2/transactions/:txid`.
+    pub txindex: bool,
+    /// If set, discard the data of blocks more than this many blocks below the chain tip.  The
+    /// MARF is not pruned.
+    pub prune_depth: Option<u64>,
 }
 
 #[derive(Clone, Debug)]
@@ -1173,6 +1321,42 @@ impl NodeConfig {
             pox_sync_sample_secs: 30,
             use_test_genesis_chainstate: None,
             mining_key: None,
+            genesis_manifest: None,
+            max_event_stream_subscribers: 0,
+            event_stream_buffer_size: 64,
+            mempool_replace_policy: MemPoolReplacePolicy::default(),
+            health_l1_block_max_age_secs: 1800,
+            health_max_sortition_lag: 6,
+            health_commit_max_age_secs: 1800,
+            txindex: false,
+            prune_depth: None,
+        }
+    }
+
+    /// Load a genesis manifest from a TOML file (if the path ends in `.toml`) or a JSON file.
+    fn load_genesis_manifest(path: &str) -> GenesisManifest {
+        let content = fs::read_to_string(path)
+            .unwrap_or_else(|e| panic!("Failed to read genesis manifest {}: {}", path, e));
+        if path.ends_with(".toml") {
+            toml::from_str(&content)
+                .unwrap_or_else(|e| panic!("Invalid genesis manifest {}: {}", path, e))
+        } else {
+            GenesisManifest::from_json(&content)
+                .unwrap_or_else(|e| panic!("Invalid genesis manifest {}: {:?}", path, e))
+        }
+    }
+
+    /// The `network_id` used in P2P messages. This is the `chain_id`, unless the node boots
+    /// from a genesis manifest, in which case the manifest hash is mixed in, so that nodes
+    /// booted from different genesis states refuse each other's messages.
+    pub fn p2p_network_id(&self) -> u32 {
+        match self.genesis_manifest.as_ref() {
+            Some(manifest) => {
+                let mut hash_prefix = [0u8; 4];
+                hash_prefix.copy_from_slice(&manifest.hash().as_bytes()[0..4]);
+                self.chain_id ^ u32::from_be_bytes(hash_prefix)
+            }
+            None => self.chain_id,
         }
     }
 
@@ -1330,6 +1514,15 @@ pub struct ConnectionOptionsFile {
     pub disable_block_download: Option<bool>,
     pub force_disconnect_interval: Option<u64>,
     pub antientropy_public: Option<bool>,
+    /// Hex-encoded P2P public keys of the other members of the block-signing federation
+    pub federation_peers: Option<Vec<String>>,
+    /// Only talk to peers whose public keys are on the allowlist
+    pub permissioned: Option<bool>,
+    /// Hex-encoded public keys to add to the peer allowlist at startup
+    pub peer_allowlist: Option<Vec<String>>,
+    /// Enables editing the peer allowlist over RPC, for requests that send this token in their
+    /// `Authorization` header
+    pub peer_allowlist_admin_token: Option<String>,
 }
 
 #[derive(Clone, Deserialize, Default)]
@@ -1358,6 +1551,24 @@ pub struct NodeConfigFile {
     pub pox_sync_sample_secs: Option<u64>,
     pub use_test_genesis_chainstate: Option<bool>,
     pub mining_key: Option<String>,
+    /// Path to a genesis manifest, in TOML or JSON
+    pub genesis_manifest: Option<String>,
+    pub max_event_stream_subscribers: Option<u64>,
+    pub event_stream_buffer_size: Option<u64>,
+    /// Minimum fee increase, in percent, for a same-nonce transaction to replace a pending one
+    pub mempool_min_fee_bump_pct: Option<u64>,
+    /// Whether a same-nonce STX transfer to oneself cancels a pending transaction in this node's
+    /// mempool (off by default; peers are not affected)
+    pub mempool_allow_cancel_by_self_transfer: Option<bool>,
+    /// Minimum seconds between two cancellations by the same origin
+    pub mempool_min_cancel_interval_secs: Option<u64>,
+    /// Maximum pending transactions per origin or sponsor (0 means unlimited)
+    pub mempool_max_pending_txs_per_principal: Option<u64>,
+    pub health_l1_block_max_age_secs: Option<u64>,
+    pub health_max_sortition_lag: Option<u64>,
+    pub health_commit_max_age_secs: Option<u64>,
+    pub txindex: Option<bool>,
+    pub prune_depth: Option<u64>,
 }
 
 #[derive(Clone, Deserialize)]
@@ -1398,14 +1609,45 @@ pub struct MinerConfigFile {
 
 #[derive(Clone, Deserialize, Default)]
 pub struct EventObserverConfigFile {
-    pub endpoint: String,
+    pub endpoint: Option<String>,
     pub events_keys: Vec<String>,
+    /// Maximum number of undelivered payloads to hold for this observer. 0 means unbounded.
+    pub max_backlog: Option<u64>,
+    /// What to do when the backlog is full: `"block"` (the default) or `"drop"`
+    pub backlog_policy: Option<String>,
+    /// Write payloads to this newline-delimited JSON file, instead of POSTing them to `endpoint`
+    pub file_path: Option<String>,
+    /// Rotate the file once it reaches this many bytes. 0 disables size-based rotation.
+    pub file_rotate_bytes: Option<u64>,
+    /// Rotate the file once it has been written to for this many seconds. 0 disables
+    /// time-based rotation.
+    pub file_rotate_secs: Option<u64>,
+    /// Gzip rotated files
+    pub file_compress: Option<bool>,
 }
 
-#[derive(Clone, Default)]
+#[derive(Clone)]
 pub struct EventObserverConfig {
+    /// The `host:port` payloads are POSTed to, or a name of the form `file:<path>` for file
+    /// observers
     pub endpoint: String,
     pub events_keys: Vec<EventKeyType>,
+    pub max_backlog: u64,
+    pub backlog_policy: BacklogPolicy,
+    /// If set, payloads are written to a file instead of POSTed to `endpoint`
+    pub file_sink: Option<EventFileSinkConfig>,
+}
+
+impl Default for EventObserverConfig {
+    fn default() -> EventObserverConfig {
+        EventObserverConfig {
+            endpoint: String::new(),
+            events_keys: vec![],
+            max_backlog: DEFAULT_MAX_BACKLOG,
+            backlog_policy: BacklogPolicy::default(),
+            file_sink: None,
+        }
+    }
 }
 
 #[derive(Clone)]
@@ -1423,7 +1665,7 @@ pub enum EventKeyType {
 }
 
 impl EventKeyType {
-    fn from_string(raw_key: &str) -> Option<EventKeyType> {
+    pub fn from_string(raw_key: &str) -> Option<EventKeyType> {
         if raw_key == "*" {
             return Some(EventKeyType::AnyEvent);
         }
@@ -1444,6 +1686,10 @@ impl EventKeyType {
             return Some(EventKeyType::Microblocks);
         }
 
+        if raw_key == "withdrawals" {
+            return Some(EventKeyType::WithdrawalEvent);
+        }
+
         let comps: Vec<_> = raw_key.split("::").collect();
         if comps.len() == 1 {
             let split: Vec<_> = comps[0].split(".").collect();
@@ -1,6 +1,7 @@
 use std::collections::hash_map::Entry;
-use std::thread::sleep;
-use std::time::Duration;
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{sync_channel, Receiver, SyncSender};
 use std::{
     collections::{HashMap, HashSet},
     sync::{Arc, Mutex},
@@ -21,19 +22,23 @@ use stacks::chainstate::stacks::{
     db::accounts::MinerReward, db::MinerRewardInfo, StacksTransaction,
 };
 use stacks::chainstate::stacks::{StacksBlock, StacksMicroblock};
+use stacks::clarity_vm::withdrawal;
 use stacks::codec::StacksMessageCodec;
 use stacks::core::mempool::{MemPoolDropReason, MemPoolEventDispatcher};
 use stacks::net::atlas::{Attachment, AttachmentInstance};
+use stacks::net::event_stream::{EventStreamError, EventStreamMessage, EventStreamProvider};
 use stacks::types::chainstate::{
     BlockHeaderHash, BurnchainHeaderHash, StacksAddress, StacksBlockId,
 };
-use stacks::util::hash::bytes_to_hex;
+use stacks::util::hash::{bytes_to_hex, MerklePathOrder, MerkleTree, Sha512Trunc256Sum};
 use stacks::vm::analysis::contract_interface_builder::build_contract_interface;
 use stacks::vm::costs::ExecutionCost;
 use stacks::vm::events::{FTEventType, NFTEventType, STXEventType};
 use stacks::vm::types::{AssetIdentifier, QualifiedContractIdentifier, Value};
 
 use super::config::{EventKeyType, EventObserverConfig};
+use super::event_file_sink::EventFileSink;
+use super::event_outbox::EventOutbox;
 use stacks::chainstate::burn::operations::BlockstackOperationType;
 use stacks::chainstate::burn::ConsensusHash;
 use stacks::chainstate::stacks::db::unconfirmed::ProcessedUnconfirmedState;
@@ -43,6 +48,20 @@ use stacks::chainstate::stacks::TransactionPayload;
 #[derive(Debug, Clone)]
 struct EventObserver {
     endpoint: String,
+    sink: ObserverSink,
+}
+
+/// Where an observer's payloads go
+#[derive(Debug, Clone)]
+enum ObserverSink {
+    /// POSTed to the observer's endpoint, from a durable outbox
+    Outbox(Arc<EventOutbox>),
+    /// Sent to an RPC event stream subscriber. `closed` is set once the subscriber has gone
+    /// away, or fallen too far behind.
+    Stream {
+        sender: SyncSender<EventStreamMessage>,
+        closed: Arc<AtomicBool>,
+    },
 }
 
 struct ReceiptPayloadInfo<'a> {
@@ -67,6 +86,7 @@ pub const PATH_MINED_MICROBLOCK: &str = "mined_microblock";
 pub const PATH_BURN_BLOCK_SUBMIT: &str = "new_burn_block";
 pub const PATH_BLOCK_PROCESSED: &str = "new_block";
 pub const PATH_ATTACHMENT_PROCESSED: &str = "attachments/new";
+pub const PATH_NEW_WITHDRAWALS: &str = "new_withdrawals";
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct MinedBlockEvent {
@@ -89,19 +109,50 @@ pub struct MinedMicroblockEvent {
 }
 
 impl EventObserver {
+    /// Queue a payload for delivery to `path` on this observer. Delivery happens asynchronously,
+    /// in order, on the observer's outbox worker, or on the RPC server for event streams.
     fn send_payload(&self, payload: &serde_json::Value, path: &str) {
-        let body = match serde_json::to_vec(&payload) {
-            Ok(body) => body,
-            Err(err) => {
-                error!("Event dispatcher: serialization failed  - {:?}", err);
-                return;
+        match self.sink {
+            ObserverSink::Outbox(ref outbox) => outbox.enqueue(path, payload),
+            ObserverSink::Stream {
+                ref sender,
+                ref closed,
+            } => {
+                let message = EventStreamMessage {
+                    path: path.to_string(),
+                    payload: payload.to_string(),
+                };
+                // a subscriber that can't keep up is cut off, rather than holding up the node
+                if sender.try_send(message).is_err() {
+                    closed.store(true, Ordering::SeqCst);
+                }
             }
-        };
+        }
+    }
+
+    /// Number of payloads still waiting to be delivered to this observer
+    fn pending_payloads(&self) -> u64 {
+        match self.sink {
+            ObserverSink::Outbox(ref outbox) => outbox.depth(),
+            ObserverSink::Stream { .. } => 0,
+        }
+    }
+
+    /// Has this observer's event stream subscriber gone away?
+    fn is_closed(&self) -> bool {
+        match self.sink {
+            ObserverSink::Outbox(..) => false,
+            ObserverSink::Stream { ref closed, .. } => closed.load(Ordering::SeqCst),
+        }
+    }
 
+    /// Make a single attempt at POSTing a serialized payload to `path` on `endpoint`.
+    /// Returns whether the observer accepted it.
+    fn post_payload(endpoint: &str, path: &str, body: &[u8]) -> bool {
         let url = {
             let joined_components = match path.starts_with("/") {
-                true => format!("{}{}", &self.endpoint, path),
-                false => format!("{}/{}", &self.endpoint, path),
+                true => format!("{}{}", endpoint, path),
+                false => format!("{}/{}", endpoint, path),
             };
             let url = format!("http://{}", joined_components);
             Url::parse(&url).expect(&format!(
@@ -110,46 +161,41 @@ impl EventObserver {
             ))
         };
 
-        let backoff = Duration::from_millis((1.0 * 1_000.0) as u64);
-
-        loop {
-            let body = body.clone();
-            let mut req = Request::new(Method::Post, url.clone());
-            req.append_header("Content-Type", "application/json");
-            req.set_body(body);
-
-            let response = async_std::task::block_on(async {
-                let stream = match TcpStream::connect(self.endpoint.clone()).await {
-                    Ok(stream) => stream,
-                    Err(err) => {
-                        warn!("Event dispatcher: connection failed  - {:?}", err);
-                        return None;
-                    }
-                };
+        let mut req = Request::new(Method::Post, url.clone());
+        req.append_header("Content-Type", "application/json");
+        req.set_body(body.to_vec());
 
-                match client::connect(stream, req).await {
-                    Ok(response) => Some(response),
-                    Err(err) => {
-                        warn!("Event dispatcher: rpc invocation failed  - {:?}", err);
-                        return None;
-                    }
+        let response = async_std::task::block_on(async {
+            let stream = match TcpStream::connect(endpoint).await {
+                Ok(stream) => stream,
+                Err(err) => {
+                    warn!("Event dispatcher: connection failed  - {:?}", err);
+                    return None;
                 }
-            });
-
-            if let Some(response) = response {
-                if response.status().is_success() {
-                    debug!(
-                        "Event dispatcher: Successful POST"; "url" => %url
-                    );
-                    break;
-                } else {
-                    error!(
-                        "Event dispatcher: Failed POST"; "url" => %url, "err" => ?response
-                    );
+            };
+
+            match client::connect(stream, req).await {
+                Ok(response) => Some(response),
+                Err(err) => {
+                    warn!("Event dispatcher: rpc invocation failed  - {:?}", err);
+                    return None;
                 }
             }
-            sleep(backoff);
+        });
+
+        if let Some(response) = response {
+            if response.status().is_success() {
+                debug!(
+                    "Event dispatcher: Successful POST"; "url" => %url
+                );
+                return true;
+            } else {
+                error!(
+                    "Event dispatcher: Failed POST"; "url" => %url, "err" => ?response
+                );
+            }
         }
+        false
     }
 
     fn make_new_mempool_txs_payload(transactions: Vec<StacksTransaction>) -> serde_json::Value {
@@ -263,6 +309,99 @@ impl EventObserver {
         })
     }
 
+    /// Returns the json payload describing a single withdrawal in a block's withdrawal tree,
+    /// including the Merkle proof needed to claim it on the L1.
+    fn make_withdrawal_payload(
+        txid: &Txid,
+        withdrawal_key: &Value,
+        withdrawal_tree: &MerkleTree<Sha512Trunc256Sum>,
+    ) -> serde_json::Value {
+        let key_bytes = withdrawal::convert_withdrawal_key_to_bytes(withdrawal_key);
+        let leaf_hash = MerkleTree::<Sha512Trunc256Sum>::get_leaf_hash(&key_bytes);
+        let merkle_path = withdrawal_tree
+            .path(&key_bytes)
+            .expect("BUG: withdrawal key not found in the withdrawal tree built from it");
+
+        let sibling_path: Vec<_> = merkle_path
+            .iter()
+            .map(|point| {
+                json!({
+                    "hash": format!("0x{}", point.hash),
+                    "is_left_side": point.order == MerklePathOrder::Right,
+                })
+            })
+            .collect();
+        let sibling_hashes = withdrawal::merkle_path_to_clarity_list(merkle_path)
+            .expect("BUG: failed to construct Clarity list out of withdrawal merkle path");
+
+        let key_data = withdrawal_key.clone().expect_tuple();
+        let field = |name: &str| key_data.get(name).ok().cloned();
+        let uint_field = |name: &str| field(name).map(|v| v.expect_u128().to_string());
+        let principal_field = |name: &str| field(name).map(|v| v.expect_principal().to_string());
+
+        json!({
+            "txid": format!("0x{}", txid),
+            "type": field("type").map(|v| v.expect_ascii()),
+            "withdrawal_id": field("withdrawal-id").map(|v| v.expect_u128() as u64),
+            "block_height": field("height").map(|v| v.expect_u128() as u64),
+            "recipient": principal_field("recipient"),
+            "amount": uint_field("amount"),
+            "nft_id": uint_field("nft-id"),
+            "asset_contract": principal_field("asset-contract"),
+            "destination": principal_field("destination"),
+            "withdrawal_key": format!("0x{}", withdrawal_key.serialize_to_hex()),
+            "withdrawal_leaf_hash": format!("0x{}", withdrawal::buffer_from_hash(leaf_hash).serialize_to_hex()),
+            "sibling_path": sibling_path,
+            "sibling_hashes": format!("0x{}", sibling_hashes.serialize_to_hex()),
+        })
+    }
+
+    /// Returns the json payload describing every withdrawal in the block's withdrawal tree, or
+    /// None if the block has no withdrawals.
+    fn make_new_withdrawals_payload(
+        block: &StacksBlock,
+        metadata: &StacksHeaderInfo,
+        receipts: &Vec<StacksTransactionReceipt>,
+    ) -> Option<serde_json::Value> {
+        // regenerate the block's withdrawal keys, which assigns the same withdrawal IDs as
+        //  block processing did, since both walk the same receipts in the same order
+        let block_height = block.header.total_work.work;
+        let mut receipts = receipts.clone();
+        let withdrawal_keys =
+            withdrawal::generate_withdrawal_key_tuples(receipts.iter_mut(), block_height);
+        if withdrawal_keys.is_empty() {
+            return None;
+        }
+
+        let items: Vec<_> = withdrawal_keys
+            .iter()
+            .map(|(_, key)| withdrawal::convert_withdrawal_key_to_bytes(key))
+            .collect();
+        let withdrawal_tree = MerkleTree::<Sha512Trunc256Sum>::new(&items);
+        if withdrawal_tree.root() != block.header.withdrawal_merkle_root {
+            error!(
+                "Event dispatcher: withdrawal root mismatch for block {}: expected {}, got {}",
+                block.block_hash(),
+                block.header.withdrawal_merkle_root,
+                withdrawal_tree.root()
+            );
+            return None;
+        }
+
+        let withdrawals: Vec<_> = withdrawal_keys
+            .iter()
+            .map(|(txid, key)| EventObserver::make_withdrawal_payload(txid, key, &withdrawal_tree))
+            .collect();
+
+        Some(json!({
+            "block_hash": format!("0x{}", block.block_hash()),
+            "block_height": metadata.stacks_block_height,
+            "index_block_hash": format!("0x{}", metadata.index_block_hash()),
+            "withdrawal_root": format!("0x{}", withdrawal::buffer_from_hash(withdrawal_tree.root()).serialize_to_hex()),
+            "withdrawals": withdrawals,
+        }))
+    }
+
     fn make_new_attachment_payload(
         attachment: &(AttachmentInstance, Attachment),
     ) -> serde_json::Value {
@@ -282,6 +421,10 @@ impl EventObserver {
         self.send_payload(payload, PATH_ATTACHMENT_PROCESSED);
     }
 
+    fn send_new_withdrawals(&self, payload: &serde_json::Value) {
+        self.send_payload(payload, PATH_NEW_WITHDRAWALS);
+    }
+
     fn send_new_mempool_txs(&self, payload: &serde_json::Value) {
         self.send_payload(payload, PATH_MEMPOOL_TX_SUBMIT);
     }
@@ -407,6 +550,85 @@ pub struct EventDispatcher {
     miner_observers_lookup: HashSet<u16>,
     mined_microblocks_observers_lookup: HashSet<u16>,
     boot_receipts: Arc<Mutex<Option<Vec<StacksTransactionReceipt>>>>,
+    event_streams: Option<Arc<EventStreamHub>>,
+}
+
+/// Serves the RPC server's event stream subscriptions.  Each subscriber gets a dispatcher of its
+/// own, with a single observer that feeds the subscriber's stream, so it receives exactly the
+/// payloads an event observer with the same `events_keys` would.
+pub struct EventStreamHub {
+    max_subscribers: usize,
+    buffer_size: usize,
+    subscribers: Mutex<Vec<EventDispatcher>>,
+}
+
+impl EventStreamHub {
+    pub fn new(max_subscribers: usize, buffer_size: usize) -> EventStreamHub {
+        EventStreamHub {
+            max_subscribers,
+            buffer_size,
+            subscribers: Mutex::new(vec![]),
+        }
+    }
+
+    /// Run `f` on each subscriber's dispatcher, and then drop the subscribers that have gone
+    /// away.  Dropping a subscriber ends its stream.  This builds every subscriber's payload on
+    /// the caller's thread (i.e. the one processing blocks) while the subscribers are locked.
+    fn publish<F: Fn(&EventDispatcher)>(&self, f: F) {
+        let mut subscribers = self
+            .subscribers
+            .lock()
+            .expect("FATAL: event stream subscribers lock poisoned");
+        for subscriber in subscribers.iter() {
+            f(subscriber);
+        }
+        subscribers.retain(|subscriber| !subscriber.is_stream_closed());
+    }
+}
+
+impl EventStreamProvider for EventStreamHub {
+    fn subscribe(
+        &self,
+        events_keys: &[String],
+    ) -> Result<Receiver<EventStreamMessage>, EventStreamError> {
+        let events_keys = events_keys
+            .iter()
+            .map(|events_key| {
+                EventKeyType::from_string(events_key)
+                    .ok_or_else(|| EventStreamError::InvalidEventKey(events_key.clone()))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let mut subscribers = self
+            .subscribers
+            .lock()
+            .expect("FATAL: event stream subscribers lock poisoned");
+        subscribers.retain(|subscriber| !subscriber.is_stream_closed());
+        if subscribers.len() >= self.max_subscribers {
+            return Err(EventStreamError::TooManySubscribers(self.max_subscribers));
+        }
+
+        let (sender, receiver) = sync_channel(self.buffer_size);
+        let mut dispatcher = EventDispatcher::new();
+        dispatcher.add_observer(
+            EventObserver {
+                endpoint: "event-stream".to_string(),
+                sink: ObserverSink::Stream {
+                    sender,
+                    closed: Arc::new(AtomicBool::new(false)),
+                },
+            },
+            &events_keys,
+        );
+        subscribers.push(dispatcher);
+
+        debug!(
+            "Event stream subscribed ({} of {})",
+            subscribers.len(),
+            self.max_subscribers
+        );
+        Ok(receiver)
+    }
 }
 
 impl MemPoolEventDispatcher for EventDispatcher {
@@ -520,9 +742,37 @@ impl EventDispatcher {
             boot_receipts: Arc::new(Mutex::new(None)),
             miner_observers_lookup: HashSet::new(),
             mined_microblocks_observers_lookup: HashSet::new(),
+            event_streams: None,
+        }
+    }
+
+    /// Serve up to `max_subscribers` event streams on the RPC server.  Each subscriber may fall
+    /// up to `buffer_size` payloads behind before it is cut off.
+    pub fn enable_event_streams(&mut self, max_subscribers: usize, buffer_size: usize) {
+        self.event_streams = Some(Arc::new(EventStreamHub::new(max_subscribers, buffer_size)));
+    }
+
+    /// Source of event streams for the RPC server, if they are enabled
+    pub fn event_stream_provider(&self) -> Option<&dyn EventStreamProvider> {
+        self.event_streams
+            .as_ref()
+            .map(|hub| hub.as_ref() as &dyn EventStreamProvider)
+    }
+
+    /// Hand an event to the event stream subscribers, if there are any
+    fn publish_to_event_streams<F: Fn(&EventDispatcher)>(&self, f: F) {
+        if let Some(ref event_streams) = self.event_streams {
+            event_streams.publish(f);
         }
     }
 
+    /// Is this the dispatcher of an event stream subscriber that has gone away?
+    fn is_stream_closed(&self) -> bool {
+        self.registered_observers
+            .iter()
+            .any(|observer| observer.is_closed())
+    }
+
     pub fn process_burn_block(
         &self,
         burn_block: &BurnchainHeaderHash,
@@ -531,6 +781,16 @@ impl EventDispatcher {
         burns: u64,
         recipient_info: Vec<StacksAddress>,
     ) {
+        self.publish_to_event_streams(|dispatcher| {
+            dispatcher.process_burn_block(
+                burn_block,
+                burn_block_height,
+                rewards.clone(),
+                burns,
+                recipient_info.clone(),
+            )
+        });
+
         // lazily assemble payload only if we have observers
         let interested_observers: Vec<_> = self
             .registered_observers
@@ -675,6 +935,23 @@ impl EventDispatcher {
         anchored_consumed: &ExecutionCost,
         mblock_confirmed_consumed: &ExecutionCost,
     ) {
+        self.publish_to_event_streams(|dispatcher| {
+            dispatcher.process_chain_tip(
+                block,
+                metadata,
+                receipts,
+                parent_index_hash,
+                winner_txid,
+                mature_rewards,
+                mature_rewards_info,
+                parent_burn_block_hash,
+                parent_burn_block_height,
+                parent_burn_block_timestamp,
+                anchored_consumed,
+                mblock_confirmed_consumed,
+            )
+        });
+
         let boot_receipts = if metadata.stacks_block_height == 1 {
             let mut boot_receipts_result = self
                 .boot_receipts
@@ -742,6 +1019,39 @@ impl EventDispatcher {
                 );
             }
         }
+
+        self.process_new_withdrawals(block, metadata, receipts);
+    }
+
+    /// Sends the withdrawals made in a newly processed block, along with their Merkle proofs,
+    /// to the observers subscribed to withdrawal events. Blocks without withdrawals are skipped.
+    pub fn process_new_withdrawals(
+        &self,
+        block: &StacksBlock,
+        metadata: &StacksHeaderInfo,
+        receipts: &Vec<StacksTransactionReceipt>,
+    ) {
+        // lazily assemble payload only if we have observers
+        let interested_observers: Vec<_> = self
+            .registered_observers
+            .iter()
+            .enumerate()
+            .filter(|(obs_id, _observer)| {
+                self.withdrawal_observers_lookup.contains(&(*obs_id as u16))
+            })
+            .collect();
+        if interested_observers.len() < 1 {
+            return;
+        }
+
+        let payload = match EventObserver::make_new_withdrawals_payload(block, metadata, receipts) {
+            Some(payload) => payload,
+            None => return,
+        };
+
+        for (_, observer) in interested_observers.iter() {
+            observer.send_new_withdrawals(&payload);
+        }
     }
 
     /// Creates a list of observers that are interested in the new microblocks event,
@@ -752,6 +1062,13 @@ impl EventDispatcher {
         parent_index_block_hash: StacksBlockId,
         processed_unconfirmed_state: ProcessedUnconfirmedState,
     ) {
+        self.publish_to_event_streams(|dispatcher| {
+            dispatcher.process_new_microblocks(
+                parent_index_block_hash,
+                processed_unconfirmed_state.clone(),
+            )
+        });
+
         // lazily assemble payload only if we have observers
         let interested_observers: Vec<_> = self
             .registered_observers
@@ -805,6 +1122,8 @@ impl EventDispatcher {
     }
 
     pub fn process_new_mempool_txs(&self, txs: Vec<StacksTransaction>) {
+        self.publish_to_event_streams(|dispatcher| dispatcher.process_new_mempool_txs(txs.clone()));
+
         // lazily assemble payload only if we have observers
         let interested_observers: Vec<_> = self
             .registered_observers
@@ -835,6 +1154,17 @@ impl EventDispatcher {
         confirmed_microblock_cost: &ExecutionCost,
         tx_events: Vec<TransactionEvent>,
     ) {
+        self.publish_to_event_streams(|dispatcher| {
+            dispatcher.process_mined_block_event(
+                target_burn_height,
+                block,
+                block_size_bytes,
+                consumed,
+                confirmed_microblock_cost,
+                tx_events.clone(),
+            )
+        });
+
         let interested_observers: Vec<_> = self
             .registered_observers
             .iter()
@@ -868,6 +1198,15 @@ impl EventDispatcher {
         anchor_block_consensus_hash: ConsensusHash,
         anchor_block: BlockHeaderHash,
     ) {
+        self.publish_to_event_streams(|dispatcher| {
+            dispatcher.process_mined_microblock_event(
+                microblock,
+                tx_events.clone(),
+                anchor_block_consensus_hash.clone(),
+                anchor_block.clone(),
+            )
+        });
+
         let interested_observers: Vec<_> = self
             .registered_observers
             .iter()
@@ -896,6 +1235,10 @@ impl EventDispatcher {
     }
 
     pub fn process_dropped_mempool_txs(&self, txs: Vec<Txid>, reason: MemPoolDropReason) {
+        self.publish_to_event_streams(|dispatcher| {
+            dispatcher.process_dropped_mempool_txs(txs.clone(), reason)
+        });
+
         // lazily assemble payload only if we have observers
         let interested_observers: Vec<_> = self
             .registered_observers
@@ -926,6 +1269,8 @@ impl EventDispatcher {
     }
 
     pub fn process_new_attachments(&self, attachments: &Vec<(AttachmentInstance, Attachment)>) {
+        self.publish_to_event_streams(|dispatcher| dispatcher.process_new_attachments(attachments));
+
         let interested_observers: Vec<_> = self.registered_observers.iter().enumerate().collect();
         if interested_observers.len() < 1 {
             return;
@@ -942,6 +1287,14 @@ impl EventDispatcher {
         }
     }
 
+    /// Number of payloads that are still waiting to be delivered, across all observers
+    pub fn pending_payloads(&self) -> u64 {
+        self.registered_observers
+            .iter()
+            .map(|observer| observer.pending_payloads())
+            .sum()
+    }
+
     pub fn process_boot_receipts(&mut self, receipts: Vec<StacksTransactionReceipt>) {
         self.boot_receipts = Arc::new(Mutex::new(Some(receipts)));
     }
@@ -959,15 +1312,64 @@ impl EventDispatcher {
         }
     }
 
-    pub fn register_observer(&mut self, conf: &EventObserverConfig) {
+    /// Register an event observer. Its undelivered payloads are kept in an outbox under
+    /// `outbox_dir`, and delivered by a dedicated worker thread, either to its endpoint or to its
+    /// file.
+    pub fn register_observer(&mut self, conf: &EventObserverConfig, outbox_dir: &Path) {
         info!("Registering event observer at: {}", conf.endpoint);
+
+        // observers that share an endpoint each get their own outbox
+        let mut outbox_name = EventOutbox::name_for_endpoint(&conf.endpoint);
+        let same_endpoint = self
+            .registered_observers
+            .iter()
+            .filter(|observer| observer.endpoint == conf.endpoint)
+            .count();
+        if same_endpoint > 0 {
+            outbox_name = format!("{}_{}", outbox_name, same_endpoint);
+        }
+
+        EventOutbox::create_outbox_dir(outbox_dir);
+        let outbox_path = EventOutbox::db_path(outbox_dir, &outbox_name);
+        let outbox = EventOutbox::open(
+            &outbox_path,
+            &conf.endpoint,
+            conf.max_backlog,
+            conf.backlog_policy,
+        )
+        .expect(&format!(
+            "FATAL: failed to open event observer outbox at {}",
+            outbox_path.to_string_lossy()
+        ));
+        let outbox = Arc::new(outbox);
+
+        match conf.file_sink {
+            Some(ref file_sink) => {
+                let sink = EventFileSink::new(file_sink.clone());
+                EventOutbox::spawn_worker(outbox.clone(), move |path, body| {
+                    sink.write_payload(path, body)
+                });
+            }
+            None => {
+                let endpoint = conf.endpoint.clone();
+                EventOutbox::spawn_worker(outbox.clone(), move |path, body| {
+                    EventObserver::post_payload(&endpoint, path, body)
+                });
+            }
+        }
+
         let event_observer = EventObserver {
             endpoint: conf.endpoint.clone(),
+            sink: ObserverSink::Outbox(outbox),
         };
+        self.add_observer(event_observer, &conf.events_keys);
+    }
 
+    /// Add an observer, subscribed to `events_keys`
+    fn add_observer(&mut self, event_observer: EventObserver, events_keys: &[EventKeyType]) {
         let observer_index = self.registered_observers.len() as u16;
 
-        for event_key_type in conf.events_keys.iter() {
+        for event_key_type in events_keys.iter() {
             match event_key_type {
                 EventKeyType::SmartContractEvent(event_key) => {
                     match self
@@ -0,0 +1,274 @@
+//! Newline-delimited JSON file sink for event observer payloads.
+//!
+//! An event observer configured with a `file_path` has its payloads appended to that file, one
+//! JSON object per line, instead of POSTed to an endpoint. Payloads reach the file through the
+//! observer's outbox like any other observer's, so a payload may occasionally be written twice
+//! if the node stops between writing it and removing it from the outbox.
+//!
+//! Once the file grows past `rotate_bytes`, or has been written to for `rotate_secs`, it is
+//! renamed with the time of rotation as a suffix (and gzipped, if `compress` is set), and a new
+//! file is started.
+
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, MutexGuard};
+
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use serde_json::json;
+
+use stacks::util::get_epoch_time_secs;
+
+/// Default size, in bytes, at which a sink's file is rotated
+pub const DEFAULT_ROTATE_BYTES: u64 = 100 * 1024 * 1024;
+
+/// Where and how an event observer's payloads are written to disk
+#[derive(Debug, Clone, PartialEq)]
+pub struct EventFileSinkConfig {
+    /// The file payloads are appended to
+    pub path: PathBuf,
+    /// Rotate the file once it is at least this many bytes. 0 disables size-based rotation.
+    pub rotate_bytes: u64,
+    /// Rotate the file once it has been written to for this many seconds. 0 disables time-based
+    /// rotation.
+    pub rotate_secs: u64,
+    /// Gzip rotated files
+    pub compress: bool,
+}
+
+impl EventFileSinkConfig {
+    pub fn new(path: PathBuf) -> EventFileSinkConfig {
+        EventFileSinkConfig {
+            path,
+            rotate_bytes: DEFAULT_ROTATE_BYTES,
+            rotate_secs: 0,
+            compress: false,
+        }
+    }
+
+    /// The name an observer writing to this sink goes by, in logs, metrics and its outbox
+    pub fn observer_name(&self) -> String {
+        format!("file:{}", self.path.display())
+    }
+}
+
+struct OpenSinkFile {
+    file: File,
+    size: u64,
+    opened_at: u64,
+}
+
+pub struct EventFileSink {
+    config: EventFileSinkConfig,
+    current: Mutex<Option<OpenSinkFile>>,
+}
+
+impl EventFileSink {
+    pub fn new(config: EventFileSinkConfig) -> EventFileSink {
+        EventFileSink {
+            config,
+            current: Mutex::new(None),
+        }
+    }
+
+    /// Append a payload, already serialized as JSON, to the sink as a line of the form
+    /// `{"path": ..., "timestamp": ..., "payload": ...}`.
+    /// Returns whether the payload was written.
+    pub fn write_payload(&self, path: &str, body: &[u8]) -> bool {
+        match self.try_write_payload(path, body) {
+            Ok(()) => true,
+            Err(e) => {
+                error!("Event dispatcher: failed to write to event file";
+                       "file" => %self.config.path.display(), "err" => ?e);
+                // reopen the file on the next attempt
+                *self.lock() = None;
+                false
+            }
+        }
+    }
+
+    fn lock(&self) -> MutexGuard<Option<OpenSinkFile>> {
+        self.current
+            .lock()
+            .expect("FATAL: event file sink lock poisoned")
+    }
+
+    fn try_write_payload(&self, path: &str, body: &[u8]) -> io::Result<()> {
+        let payload: serde_json::Value = serde_json::from_slice(body)?;
+        let mut line = serde_json::to_vec(&json!({
+            "path": path,
+            "timestamp": get_epoch_time_secs(),
+            "payload": payload,
+        }))?;
+        line.push(b'\n');
+
+        let mut current = self.lock();
+        let now = get_epoch_time_secs();
+        let rotate = match *current {
+            Some(ref open) => self.should_rotate(open, now),
+            None => false,
+        };
+        if rotate {
+            // close the file before moving it out of the way
+            *current = None;
+            self.rotate(now)?;
+        }
+        if current.is_none() {
+            *current = Some(self.open(now)?);
+        }
+
+        let open = current.as_mut().expect("BUG: event file not open");
+        open.file.write_all(&line)?;
+        open.file.flush()?;
+        open.size += line.len() as u64;
+        Ok(())
+    }
+
+    fn should_rotate(&self, open: &OpenSinkFile, now: u64) -> bool {
+        (self.config.rotate_bytes > 0 && open.size >= self.config.rotate_bytes)
+            || (self.config.rotate_secs > 0 && open.opened_at + self.config.rotate_secs <= now)
+    }
+
+    fn open(&self, now: u64) -> io::Result<OpenSinkFile> {
+        if let Some(dir) = self.config.path.parent() {
+            if !dir.as_os_str().is_empty() {
+                fs::create_dir_all(dir)?;
+            }
+        }
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.config.path)?;
+        let size = file.metadata()?.len();
+        Ok(OpenSinkFile {
+            file,
+            size,
+            opened_at: now,
+        })
+    }
+
+    /// Move the current file aside, and compress it if configured to.
+    fn rotate(&self, now: u64) -> io::Result<()> {
+        let rotated = Self::unused_path(&self.config.path, now, self.config.compress);
+        let moved = if self.config.compress {
+            rotated.with_extension("")
+        } else {
+            rotated.clone()
+        };
+        fs::rename(&self.config.path, &moved)?;
+        if self.config.compress {
+            Self::gzip(&moved, &rotated)?;
+            fs::remove_file(&moved)?;
+        }
+        info!("Event dispatcher: rotated event file";
+              "file" => %self.config.path.display(), "rotated" => %rotated.display());
+        Ok(())
+    }
+
+    /// Find a name for a file rotated at `now`, of the form `<path>.<now>[.<n>][.gz]`
+    fn unused_path(path: &Path, now: u64, compress: bool) -> PathBuf {
+        let mut n = 0;
+        loop {
+            let mut name = path.as_os_str().to_os_string();
+            name.push(format!(".{}", now));
+            if n > 0 {
+                name.push(format!(".{}", n));
+            }
+            if compress {
+                name.push(".gz");
+            }
+            let candidate = PathBuf::from(name);
+            if !candidate.exists() {
+                return candidate;
+            }
+            n += 1;
+        }
+    }
+
+    fn gzip(from: &Path, to: &Path) -> io::Result<()> {
+        let mut input = File::open(from)?;
+        let mut encoder = GzEncoder::new(BufWriter::new(File::create(to)?), Compression::default());
+        io::copy(&mut input, &mut encoder)?;
+        encoder.finish()?.flush()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use flate2::read::GzDecoder;
+    use std::io::Read;
+
+    fn temp_sink_dir(name: &str) -> PathBuf {
+        let mut path = std::env::temp_dir();
+        path.push(format!(
+            "event-file-sink-{}-{}",
+            name,
+            rand::random::<u64>()
+        ));
+        path
+    }
+
+    fn rotated_files(dir: &Path) -> Vec<PathBuf> {
+        let mut rotated: Vec<_> = fs::read_dir(dir)
+            .unwrap()
+            .map(|entry| entry.unwrap().path())
+            .filter(|path| path.file_name().unwrap() != "events.ndjson")
+            .collect();
+        rotated.sort();
+        rotated
+    }
+
+    #[test]
+    fn test_file_sink_writes_ndjson() {
+        let dir = temp_sink_dir("ndjson");
+        let sink = EventFileSink::new(EventFileSinkConfig::new(dir.join("events.ndjson")));
+        assert!(sink.write_payload("new_block", br#"{"block_height":1}"#));
+        assert!(sink.write_payload("new_burn_block", br#"{"burn_block_height":2}"#));
+        assert!(!sink.write_payload("new_block", b"not json"));
+
+        let contents = fs::read_to_string(dir.join("events.ndjson")).unwrap();
+        let lines: Vec<serde_json::Value> = contents
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        assert_eq!(lines.len(), 2);
+        assert_eq!(lines[0]["path"], "new_block");
+        assert_eq!(lines[0]["payload"], json!({"block_height": 1}));
+        assert_eq!(lines[1]["path"], "new_burn_block");
+        assert_eq!(lines[1]["payload"], json!({"burn_block_height": 2}));
+    }
+
+    #[test]
+    fn test_file_sink_rotates_by_size() {
+        for compress in [false, true] {
+            let dir = temp_sink_dir("rotate");
+            let sink = EventFileSink::new(EventFileSinkConfig {
+                rotate_bytes: 1,
+                compress,
+                ..EventFileSinkConfig::new(dir.join("events.ndjson"))
+            });
+            assert!(sink.write_payload("new_block", br#"{"block_height":1}"#));
+            assert!(sink.write_payload("new_block", br#"{"block_height":2}"#));
+
+            // the first payload was rotated out
+            let rotated = rotated_files(&dir);
+            assert_eq!(rotated.len(), 1);
+            let mut contents = String::new();
+            if compress {
+                assert_eq!(rotated[0].extension().unwrap(), "gz");
+                GzDecoder::new(File::open(&rotated[0]).unwrap())
+                    .read_to_string(&mut contents)
+                    .unwrap();
+            } else {
+                contents = fs::read_to_string(&rotated[0]).unwrap();
+            }
+            assert!(contents.contains(r#""block_height":1"#));
+
+            let current = fs::read_to_string(dir.join("events.ndjson")).unwrap();
+            assert!(current.contains(r#""block_height":2"#));
+            assert_eq!(current.lines().count(), 1);
+        }
+    }
+}
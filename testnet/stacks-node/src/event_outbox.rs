@@ -0,0 +1,403 @@
+//! Durable, per-observer outbox for event observer payloads.
+//!
+//! Payloads bound for an event observer are written to a SQLite queue and delivered, in order,
+//! by a worker thread dedicated to that observer. An unreachable observer therefore only delays
+//! its own payloads, instead of stalling block processing. Undelivered payloads survive a
+//! restart and are delivered once the node comes back up, so observers may see a payload more
+//! than once.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Condvar, Mutex, MutexGuard};
+use std::thread;
+use std::time::Duration;
+
+use rusqlite::{Connection, OpenFlags, OptionalExtension, NO_PARAMS};
+
+use stacks::util::get_epoch_time_secs;
+
+use crate::monitoring::{
+    increment_event_observer_dropped_counter, set_event_observer_delivery_lag,
+    set_event_observer_queue_depth,
+};
+
+/// Default maximum number of undelivered payloads an observer's outbox may hold. 0 means the
+/// outbox is unbounded, and grows on disk for as long as the observer is behind, so that no
+/// payload is lost unless an operator opts into dropping them.
+pub const DEFAULT_MAX_BACKLOG: u64 = 0;
+
+/// Delay before the first retry of a failed delivery. Each further failure doubles the delay,
+/// up to `MAX_RETRY_BACKOFF`.
+const INITIAL_RETRY_BACKOFF: Duration = Duration::from_millis(100);
+const MAX_RETRY_BACKOFF: Duration = Duration::from_secs(60);
+
+const OUTBOX_SCHEMA: &str = "
+    CREATE TABLE IF NOT EXISTS outbox (
+        seq INTEGER PRIMARY KEY AUTOINCREMENT,
+        path TEXT NOT NULL,
+        payload TEXT NOT NULL,
+        enqueued_at INTEGER NOT NULL
+    );";
+
+/// What to do with a new payload when an observer's outbox is full
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum BacklogPolicy {
+    /// Wait for the observer to catch up. This stalls the thread emitting the event, which is
+    /// usually block processing, but loses nothing, so it is the default.
+    Block,
+    /// Drop the new payload, logging it and counting it in the observer's dropped payloads
+    /// metric. Payloads are only ever dropped if this is asked for explicitly.
+    Drop,
+}
+
+impl Default for BacklogPolicy {
+    fn default() -> BacklogPolicy {
+        BacklogPolicy::Block
+    }
+}
+
+impl BacklogPolicy {
+    pub fn from_string(raw_policy: &str) -> Option<BacklogPolicy> {
+        match raw_policy {
+            "block" => Some(BacklogPolicy::Block),
+            "drop" => Some(BacklogPolicy::Drop),
+            _ => None,
+        }
+    }
+}
+
+/// A payload waiting to be delivered
+#[derive(Debug, Clone, PartialEq)]
+pub struct OutboxEntry {
+    pub seq: i64,
+    pub path: String,
+    pub payload: String,
+    pub enqueued_at: u64,
+}
+
+#[derive(Debug)]
+struct OutboxState {
+    conn: Connection,
+    depth: u64,
+}
+
+#[derive(Debug)]
+pub struct EventOutbox {
+    endpoint: String,
+    max_backlog: u64,
+    backlog_policy: BacklogPolicy,
+    state: Mutex<OutboxState>,
+    /// Signaled whenever a payload is enqueued or delivered
+    changed: Condvar,
+}
+
+impl EventOutbox {
+    /// Open (or create) the outbox for `endpoint` at `db_path`. Payloads left over from a
+    /// previous run are kept, and will be delivered first.
+    /// A `max_backlog` of 0 means the outbox is unbounded.
+    pub fn open(
+        db_path: &Path,
+        endpoint: &str,
+        max_backlog: u64,
+        backlog_policy: BacklogPolicy,
+    ) -> Result<EventOutbox, rusqlite::Error> {
+        let conn = Connection::open_with_flags(
+            db_path,
+            OpenFlags::SQLITE_OPEN_READ_WRITE | OpenFlags::SQLITE_OPEN_CREATE,
+        )?;
+        EventOutbox::from_connection(conn, endpoint, max_backlog, backlog_policy)
+    }
+
+    fn from_connection(
+        conn: Connection,
+        endpoint: &str,
+        max_backlog: u64,
+        backlog_policy: BacklogPolicy,
+    ) -> Result<EventOutbox, rusqlite::Error> {
+        conn.execute_batch(OUTBOX_SCHEMA)?;
+        let depth: i64 =
+            conn.query_row("SELECT COUNT(*) FROM outbox", NO_PARAMS, |row| row.get(0))?;
+        if depth > 0 {
+            info!(
+                "Event dispatcher: resuming delivery of {} queued payloads to {}",
+                depth, endpoint
+            );
+        }
+        set_event_observer_queue_depth(endpoint, depth as u64);
+
+        Ok(EventOutbox {
+            endpoint: endpoint.to_string(),
+            max_backlog,
+            backlog_policy,
+            state: Mutex::new(OutboxState {
+                conn,
+                depth: depth as u64,
+            }),
+            changed: Condvar::new(),
+        })
+    }
+
+    /// The path of the outbox database for an observer, under `outbox_dir`.
+    pub fn db_path(outbox_dir: &Path, name: &str) -> PathBuf {
+        let mut path = outbox_dir.to_path_buf();
+        path.push(format!("{}.sqlite", name));
+        path
+    }
+
+    /// A file-name-safe name for an observer's outbox, derived from its endpoint
+    pub fn name_for_endpoint(endpoint: &str) -> String {
+        endpoint
+            .chars()
+            .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+            .collect()
+    }
+
+    /// Ensure the directory holding the outboxes exists
+    pub fn create_outbox_dir(outbox_dir: &Path) {
+        fs::create_dir_all(outbox_dir).expect(&format!(
+            "Failed to create event observer outbox directory at {}",
+            outbox_dir.to_string_lossy()
+        ));
+    }
+
+    fn lock(&self) -> MutexGuard<OutboxState> {
+        self.state
+            .lock()
+            .expect("FATAL: event observer outbox lock poisoned")
+    }
+
+    /// Number of payloads waiting to be delivered
+    pub fn depth(&self) -> u64 {
+        self.lock().depth
+    }
+
+    /// Queue a payload for delivery to `path` on the observer.
+    /// If the outbox is full, this either blocks until there is room, or drops the payload,
+    /// depending on the outbox's backlog policy. Returns whether the payload was queued.
+    pub fn enqueue(&self, path: &str, payload: &serde_json::Value) -> bool {
+        let payload = match serde_json::to_string(payload) {
+            Ok(payload) => payload,
+            Err(err) => {
+                error!("Event dispatcher: serialization failed  - {:?}", err);
+                return false;
+            }
+        };
+
+        let mut state = self.lock();
+        if self.max_backlog > 0 && state.depth >= self.max_backlog {
+            match self.backlog_policy {
+                BacklogPolicy::Drop => {
+                    warn!("Event dispatcher: outbox full, dropping payload";
+                          "endpoint" => &self.endpoint,
+                          "path" => path,
+                          "depth" => state.depth);
+                    increment_event_observer_dropped_counter(&self.endpoint);
+                    return false;
+                }
+                BacklogPolicy::Block => {
+                    warn!("Event dispatcher: outbox full, waiting for observer to catch up";
+                          "endpoint" => &self.endpoint,
+                          "depth" => state.depth);
+                    while state.depth >= self.max_backlog {
+                        state = self
+                            .changed
+                            .wait(state)
+                            .expect("FATAL: event observer outbox lock poisoned");
+                    }
+                }
+            }
+        }
+
+        state
+            .conn
+            .execute(
+                "INSERT INTO outbox (path, payload, enqueued_at) VALUES (?1, ?2, ?3)",
+                rusqlite::params![path, payload, get_epoch_time_secs() as i64],
+            )
+            .expect("FATAL: failed to write to event observer outbox");
+        state.depth += 1;
+        set_event_observer_queue_depth(&self.endpoint, state.depth);
+        self.changed.notify_all();
+        true
+    }
+
+    /// The oldest undelivered payload, if any
+    pub fn peek(&self) -> Option<OutboxEntry> {
+        EventOutbox::head(&self.lock().conn)
+    }
+
+    fn head(conn: &Connection) -> Option<OutboxEntry> {
+        conn.query_row(
+            "SELECT seq, path, payload, enqueued_at FROM outbox ORDER BY seq ASC LIMIT 1",
+            NO_PARAMS,
+            |row| {
+                let enqueued_at: i64 = row.get(3)?;
+                Ok(OutboxEntry {
+                    seq: row.get(0)?,
+                    path: row.get(1)?,
+                    payload: row.get(2)?,
+                    enqueued_at: enqueued_at as u64,
+                })
+            },
+        )
+        .optional()
+        .expect("FATAL: failed to read from event observer outbox")
+    }
+
+    /// Block until there is a payload to deliver, and return it
+    fn wait_for_head(&self) -> OutboxEntry {
+        let mut state = self.lock();
+        loop {
+            if let Some(entry) = EventOutbox::head(&state.conn) {
+                return entry;
+            }
+            state = self
+                .changed
+                .wait(state)
+                .expect("FATAL: event observer outbox lock poisoned");
+        }
+    }
+
+    /// Remove a delivered payload from the outbox
+    pub fn remove(&self, entry: &OutboxEntry) {
+        let mut state = self.lock();
+        let removed = state
+            .conn
+            .execute("DELETE FROM outbox WHERE seq = ?1", &[entry.seq])
+            .expect("FATAL: failed to delete from event observer outbox");
+        state.depth = state.depth.saturating_sub(removed as u64);
+        set_event_observer_queue_depth(&self.endpoint, state.depth);
+        set_event_observer_delivery_lag(
+            &self.endpoint,
+            get_epoch_time_secs().saturating_sub(entry.enqueued_at),
+        );
+        self.changed.notify_all();
+    }
+
+    /// Spawn the worker thread that delivers this outbox's payloads, in order, using `deliver`.
+    /// `deliver` makes a single delivery attempt and returns whether it succeeded; failed
+    /// deliveries are retried with exponential backoff.
+    pub fn spawn_worker<F>(outbox: Arc<EventOutbox>, deliver: F) -> thread::JoinHandle<()>
+    where
+        F: Fn(&str, &[u8]) -> bool + Send + 'static,
+    {
+        thread::Builder::new()
+            .name(format!("event-observer:{}", &outbox.endpoint))
+            .spawn(move || loop {
+                let entry = outbox.wait_for_head();
+                let mut backoff = INITIAL_RETRY_BACKOFF;
+                while !deliver(&entry.path, entry.payload.as_bytes()) {
+                    thread::sleep(backoff);
+                    backoff = std::cmp::min(backoff * 2, MAX_RETRY_BACKOFF);
+                }
+                outbox.remove(&entry);
+            })
+            .expect("FATAL: failed to spawn event observer delivery thread")
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use serde_json::json;
+
+    fn temp_outbox_path(name: &str) -> PathBuf {
+        let mut path = std::env::temp_dir();
+        path.push(format!(
+            "event-outbox-{}-{}.sqlite",
+            name,
+            rand::random::<u64>()
+        ));
+        path
+    }
+
+    #[test]
+    fn test_outbox_persists_in_order() {
+        let path = temp_outbox_path("persist");
+        {
+            let outbox =
+                EventOutbox::open(&path, "localhost:3700", 0, BacklogPolicy::Block).unwrap();
+            assert!(outbox.enqueue("new_block", &json!({"height": 1})));
+            assert!(outbox.enqueue("new_burn_block", &json!({"height": 2})));
+            assert_eq!(outbox.depth(), 2);
+        }
+
+        // reopening resumes from the oldest payload
+        let outbox = EventOutbox::open(&path, "localhost:3700", 0, BacklogPolicy::Block).unwrap();
+        assert_eq!(outbox.depth(), 2);
+        let first = outbox.peek().unwrap();
+        assert_eq!(first.path, "new_block");
+        assert_eq!(first.payload, r#"{"height":1}"#);
+        outbox.remove(&first);
+
+        let second = outbox.peek().unwrap();
+        assert_eq!(second.path, "new_burn_block");
+        outbox.remove(&second);
+        assert_eq!(outbox.depth(), 0);
+        assert!(outbox.peek().is_none());
+
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_backlog_defaults_never_drop() {
+        assert_eq!(DEFAULT_MAX_BACKLOG, 0);
+        assert_eq!(BacklogPolicy::default(), BacklogPolicy::Block);
+        assert_eq!(
+            BacklogPolicy::from_string("block"),
+            Some(BacklogPolicy::Block)
+        );
+        assert_eq!(
+            BacklogPolicy::from_string("drop"),
+            Some(BacklogPolicy::Drop)
+        );
+        assert_eq!(BacklogPolicy::from_string("wait"), None);
+    }
+
+    #[test]
+    fn test_outbox_drop_policy() {
+        let conn = Connection::open_in_memory().unwrap();
+        let outbox =
+            EventOutbox::from_connection(conn, "localhost:3700", 2, BacklogPolicy::Drop).unwrap();
+        assert!(outbox.enqueue("new_block", &json!(1)));
+        assert!(outbox.enqueue("new_block", &json!(2)));
+        assert!(!outbox.enqueue("new_block", &json!(3)));
+        assert_eq!(outbox.depth(), 2);
+
+        // once a payload is delivered there is room again
+        outbox.remove(&outbox.peek().unwrap());
+        assert!(outbox.enqueue("new_block", &json!(4)));
+        assert_eq!(outbox.peek().unwrap().payload, "2");
+    }
+
+    #[test]
+    fn test_outbox_worker_retries_until_delivered() {
+        let conn = Connection::open_in_memory().unwrap();
+        let outbox = Arc::new(
+            EventOutbox::from_connection(conn, "localhost:3700", 1, BacklogPolicy::Block).unwrap(),
+        );
+        let attempts = Arc::new(Mutex::new(vec![]));
+
+        let worker_attempts = attempts.clone();
+        EventOutbox::spawn_worker(outbox.clone(), move |path, body| {
+            let mut attempts = worker_attempts.lock().unwrap();
+            attempts.push((path.to_string(), body.to_vec()));
+            // fail the first attempt at each payload
+            attempts.len() % 2 == 0
+        });
+
+        assert!(outbox.enqueue("new_block", &json!(1)));
+        // blocks until the first payload is delivered
+        assert!(outbox.enqueue("new_block", &json!(2)));
+        while outbox.depth() > 0 {
+            thread::sleep(Duration::from_millis(10));
+        }
+
+        let attempts = attempts.lock().unwrap();
+        let bodies: Vec<_> = attempts.iter().map(|(_, body)| body.clone()).collect();
+        assert_eq!(
+            bodies,
+            vec![b"1".to_vec(), b"1".to_vec(), b"2".to_vec(), b"2".to_vec()]
+        );
+    }
+}
@@ -0,0 +1,68 @@
+//! Replays the events of already-processed blocks to an event observer.
+//!
+//! Transaction receipts are not stored, so each Stacks block is re-executed on top of its
+//! parent's state to rebuild them, and then handed to an `EventDispatcher` exactly as the
+//! chains coordinator did when the block was first processed. Burnchain blocks are announced
+//! in the same order relative to the Stacks blocks they elected. Nothing is written to the
+//! chain state.
+
+use std::thread;
+use std::time::Duration;
+
+use stacks::chainstate::burn::db::sortdb::SortitionDB;
+use stacks::chainstate::coordinator::replay_chain_events;
+use stacks::chainstate::stacks::db::StacksChainState;
+use stacks::chainstate::stacks::Error as ChainstateError;
+
+use super::config::{Config, EventObserverConfig};
+use super::event_outbox::BacklogPolicy;
+use super::EventDispatcher;
+
+/// How often to check whether the replayed payloads have all been delivered
+const DELIVERY_POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Re-emit the `/new_burn_block`, `/new_block` and `/new_withdrawals` payloads for the
+/// canonical Stacks blocks at or above `from_height`, and for the burnchain blocks from the one
+/// that elected the first of them up to the burnchain tip, to the single observer `observer`.
+/// Its `events_keys` filter applies as it would on a running node.
+///
+/// Blocks until every payload has been delivered, and returns the number of Stacks blocks
+/// replayed.
+pub fn replay_events(
+    config: &Config,
+    observer: &EventObserverConfig,
+    from_height: u64,
+) -> Result<u64, ChainstateError> {
+    let sortdb = SortitionDB::open(&config.get_burn_db_file_path(), false)?;
+    let (mut chainstate, _) = StacksChainState::open(
+        config.is_mainnet(),
+        config.node.chain_id,
+        &config.get_chainstate_path_str(),
+        Some(config.node.get_marf_opts()),
+    )?;
+
+    // replayed payloads get an outbox of their own, and must never be dropped
+    let observer = EventObserverConfig {
+        backlog_policy: BacklogPolicy::Block,
+        ..observer.clone()
+    };
+    let mut dispatcher = EventDispatcher::new();
+    dispatcher.register_observer(&observer, &config.get_event_outbox_path().join("replay"));
+
+    info!(
+        "Replaying Stacks blocks from height {} to observer {}",
+        from_height, &observer.endpoint
+    );
+
+    let replayed = replay_chain_events(&mut chainstate, &sortdb, &dispatcher, from_height)?;
+
+    while dispatcher.pending_payloads() > 0 {
+        thread::sleep(DELIVERY_POLL_INTERVAL);
+    }
+
+    info!(
+        "Replayed {} Stacks blocks to observer {}",
+        replayed, &observer.endpoint
+    );
+    Ok(replayed)
+}
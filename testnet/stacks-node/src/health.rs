@@ -0,0 +1,184 @@
+//! Node-level checks for the RPC server's `/health/ready` endpoint.
+//!
+//! The L1 observer, the burnchain controllers and the miner each run on their own thread, so
+//! they record what they see in a process-wide `NodeHealthState`. The RPC server reads it back
+//! through `NodeHealth` when a client asks.
+
+use std::sync::Mutex;
+
+use stacks::burnchains::indexer::BurnchainIndexer;
+use stacks::net::health::HealthCheckProvider;
+use stacks::net::RPCHealthComponent;
+use stacks::util::get_epoch_time_secs;
+
+use crate::burnchains::db_indexer::DBBurnchainIndexer;
+use crate::config::{BurnchainConfig, Config};
+
+lazy_static! {
+    static ref NODE_HEALTH_STATE: Mutex<NodeHealthState> = Mutex::new(NodeHealthState::default());
+}
+
+#[derive(Default)]
+struct NodeHealthState {
+    /// When the L1 observer last received a block
+    last_l1_block_time: Option<u64>,
+    /// The outcome of the last check of the L1 contract's version, if it was checked
+    l1_contract_check: Option<Result<(), String>>,
+    /// When the miner last submitted a block commitment to the L1
+    last_commit_time: Option<u64>,
+}
+
+fn with_state<F, R>(f: F) -> R
+where
+    F: FnOnce(&mut NodeHealthState) -> R,
+{
+    let mut state = NODE_HEALTH_STATE
+        .lock()
+        .expect("FATAL: node health state lock poisoned");
+    f(&mut state)
+}
+
+/// Record that the L1 observer received a block
+pub fn record_l1_block_received() {
+    with_state(|state| state.last_l1_block_time = Some(get_epoch_time_secs()));
+}
+
+/// Record the outcome of checking the L1 contract's version
+pub fn record_l1_contract_check(result: Result<(), String>) {
+    with_state(|state| state.l1_contract_check = Some(result));
+}
+
+/// Record that the miner submitted a block commitment to the L1
+pub fn record_commit_submitted() {
+    with_state(|state| state.last_commit_time = Some(get_epoch_time_secs()));
+}
+
+/// Readiness checks for the parts of the node outside of the RPC server
+pub struct NodeHealth {
+    burnchain: BurnchainConfig,
+    burnchain_path: String,
+    is_miner: bool,
+    l1_block_max_age_secs: u64,
+    max_sortition_lag: u64,
+    commit_max_age_secs: u64,
+    started_at: u64,
+}
+
+impl NodeHealth {
+    pub fn new(config: &Config) -> NodeHealth {
+        NodeHealth {
+            burnchain: config.burnchain.clone(),
+            burnchain_path: config.get_burnchain_path_str(),
+            is_miner: config.node.miner,
+            l1_block_max_age_secs: config.node.health_l1_block_max_age_secs,
+            max_sortition_lag: config.node.health_max_sortition_lag,
+            commit_max_age_secs: config.node.health_commit_max_age_secs,
+            started_at: get_epoch_time_secs(),
+        }
+    }
+
+    /// Check that `last_time` (or, if it never happened, node startup) was at most
+    /// `max_age_secs` ago
+    fn check_age(
+        &self,
+        name: &str,
+        what: &str,
+        last_time: Option<u64>,
+        max_age_secs: u64,
+    ) -> RPCHealthComponent {
+        let now = get_epoch_time_secs();
+        let (age, message) = match last_time {
+            Some(time) => {
+                let age = now.saturating_sub(time);
+                (age, format!("Last {} {} seconds ago", what, age))
+            }
+            None => {
+                let age = now.saturating_sub(self.started_at);
+                (
+                    age,
+                    format!("No {} in the {} seconds since startup", what, age),
+                )
+            }
+        };
+        if age <= max_age_secs {
+            RPCHealthComponent::healthy(name, message)
+        } else {
+            RPCHealthComponent::unhealthy(name, format!("{} (max {})", message, max_age_secs))
+        }
+    }
+
+    fn check_sortition_lag(&self, sortition_tip_height: u64) -> RPCHealthComponent {
+        let l1_height =
+            DBBurnchainIndexer::new(&self.burnchain_path, self.burnchain.clone(), false)
+                .map_err(|e| e.to_string())
+                .and_then(|indexer| {
+                    indexer
+                        .get_highest_header_height()
+                        .map_err(|e| format!("{:?}", e))
+                });
+        match l1_height {
+            Ok(l1_height) => {
+                let lag = l1_height.saturating_sub(sortition_tip_height);
+                let message = format!(
+                    "Sortition tip at height {}, L1 indexer at height {}",
+                    sortition_tip_height, l1_height
+                );
+                if lag <= self.max_sortition_lag {
+                    RPCHealthComponent::healthy("sortition", message)
+                } else {
+                    RPCHealthComponent::unhealthy(
+                        "sortition",
+                        format!("{} (max lag {})", message, self.max_sortition_lag),
+                    )
+                }
+            }
+            Err(e) => RPCHealthComponent::unhealthy(
+                "sortition",
+                format!("Failed to read the L1 indexer: {}", e),
+            ),
+        }
+    }
+}
+
+impl HealthCheckProvider for NodeHealth {
+    fn check_readiness(&self, sortition_tip_height: u64) -> Vec<RPCHealthComponent> {
+        let (last_l1_block_time, l1_contract_check, last_commit_time) = with_state(|state| {
+            (
+                state.last_l1_block_time,
+                state.l1_contract_check.clone(),
+                state.last_commit_time,
+            )
+        });
+
+        let mut components = vec![];
+        if self.burnchain.spawn_l1_observer() {
+            components.push(self.check_age(
+                "l1_observer",
+                "L1 block received",
+                last_l1_block_time,
+                self.l1_block_max_age_secs,
+            ));
+            components.push(self.check_sortition_lag(sortition_tip_height));
+            components.push(match l1_contract_check {
+                Some(Ok(())) => {
+                    RPCHealthComponent::healthy("l1_contract", "Version check passed".to_string())
+                }
+                Some(Err(e)) => RPCHealthComponent::unhealthy(
+                    "l1_contract",
+                    format!("Version check failed: {}", e),
+                ),
+                // only checked before the first commitment
+                None => RPCHealthComponent::healthy("l1_contract", "Not checked yet".to_string()),
+            });
+        }
+        if self.is_miner {
+            components.push(self.check_age(
+                "miner_commits",
+                "commitment submitted",
+                last_commit_time,
+                self.commit_max_age_secs,
+            ));
+        }
+        components
+    }
+}
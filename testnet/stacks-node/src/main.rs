@@ -16,6 +16,9 @@ extern crate stacks;
 #[macro_use(o, slog_log, slog_trace, slog_debug, slog_info, slog_warn, slog_error)]
 extern crate slog;
 
+use stacks::chainstate::burn::db::sortdb::SortitionDB;
+use stacks::chainstate::stacks::db::snapshot;
+use stacks::types::chainstate::StacksBlockId;
 pub use stacks::util;
 use stacks::util::hash::hex_bytes;
 
@@ -24,7 +27,11 @@ pub mod monitoring;
 pub mod burnchains;
 pub mod config;
 pub mod event_dispatcher;
+pub mod event_file_sink;
+pub mod event_outbox;
+pub mod event_replay;
 pub mod genesis_data;
+pub mod health;
 pub mod keychain;
 pub mod neon_node;
 pub mod node;
@@ -34,7 +41,9 @@ pub mod syncctl;
 
 pub use self::burnchains::{BurnchainController, BurnchainTip};
 pub use self::config::{Config, ConfigFile};
+use self::config::{EventKeyType, EventObserverConfig};
 pub use self::event_dispatcher::EventDispatcher;
+use self::event_file_sink::EventFileSinkConfig;
 pub use self::keychain::Keychain;
 pub use self::run_loop::neon;
 pub use node::ChainTip;
@@ -44,6 +53,7 @@ use std::env;
 
 use std::convert::TryInto;
 use std::panic;
+use std::path::PathBuf;
 use std::process;
 
 use backtrace::Backtrace;
@@ -98,6 +108,120 @@ fn main() {
             println!("{}", &version());
             return;
         }
+        "replay-events" => {
+            let config_path: String = args.value_from_str("--config").unwrap();
+            let observer: String = args.value_from_str("--observer").unwrap();
+            let from_height: u64 = args
+                .opt_value_from_str("--from-height")
+                .unwrap()
+                .unwrap_or(0);
+            let events_keys: Option<String> = args.opt_value_from_str("--events-keys").unwrap();
+            args.finish().unwrap();
+
+            let conf = Config::from_config_file(ConfigFile::from_path(&config_path));
+            // use the observer's configured subscriptions, unless overridden
+            let mut observer_conf = conf
+                .events_observers
+                .iter()
+                .find(|configured| configured.endpoint == observer)
+                .cloned()
+                .unwrap_or_else(|| {
+                    // `file:<path>` replays into a newline-delimited JSON file
+                    let file_sink = observer
+                        .strip_prefix("file:")
+                        .map(|file_path| EventFileSinkConfig::new(PathBuf::from(file_path)));
+                    EventObserverConfig {
+                        endpoint: observer.clone(),
+                        events_keys: vec![EventKeyType::AnyEvent],
+                        file_sink,
+                        ..EventObserverConfig::default()
+                    }
+                });
+            if let Some(events_keys) = events_keys {
+                observer_conf.events_keys = events_keys
+                    .split(',')
+                    .map(|key| {
+                        EventKeyType::from_string(key.trim())
+                            .expect(&format!("Invalid event key '{}'", key))
+                    })
+                    .collect();
+            }
+
+            match event_replay::replay_events(&conf, &observer_conf, from_height) {
+                Ok(replayed) => {
+                    println!("Replayed {} blocks to {}", replayed, &observer);
+                }
+                Err(e) => {
+                    eprintln!("Failed to replay events to {}: {}", &observer, &e);
+                    process::exit(1);
+                }
+            }
+            return;
+        }
+        "export-snapshot" => {
+            let config_path: String = args.value_from_str("--config").unwrap();
+            let output: String = args.value_from_str("--output").unwrap();
+            let block: Option<String> = args.opt_value_from_str("--block").unwrap();
+            args.finish().unwrap();
+
+            let conf = Config::from_config_file(ConfigFile::from_path(&config_path));
+            let anchor = block.map(|block| {
+                StacksBlockId::from_hex(&block).expect("--block must be a hex index block hash")
+            });
+            match snapshot::export_snapshot(
+                &conf.get_chainstate_path_str(),
+                anchor.as_ref(),
+                &output,
+            ) {
+                Ok(manifest) => {
+                    println!(
+                        "Exported snapshot of block {} at height {} to {}",
+                        &manifest.index_block_hash, manifest.block_height, &output
+                    );
+                }
+                Err(e) => {
+                    eprintln!("Failed to export snapshot: {}", &e);
+                    process::exit(1);
+                }
+            }
+            return;
+        }
+        "import-snapshot" => {
+            let config_path: String = args.value_from_str("--config").unwrap();
+            let snapshot_path: String = args.value_from_str("--snapshot").unwrap();
+            let overwrite = args.contains("--overwrite");
+            args.finish().unwrap();
+
+            let conf = Config::from_config_file(ConfigFile::from_path(&config_path));
+            let sortdb = SortitionDB::open(&conf.get_burn_db_file_path(), false)
+                .unwrap_or_else(|e| {
+                    eprintln!(
+                        "Failed to open the sortition DB ({:?}); run the node until it has synced the L1 past the snapshot first",
+                        &e
+                    );
+                    process::exit(1);
+                });
+            match snapshot::import_snapshot(
+                &snapshot_path,
+                &conf.get_chainstate_path_str(),
+                &sortdb,
+                conf.is_mainnet(),
+                conf.node.chain_id,
+                overwrite,
+            ) {
+                Ok(manifest) => {
+                    println!(
+                        "Imported snapshot of block {} at height {}",
+                        &manifest.index_block_hash, manifest.block_height
+                    );
+                }
+                Err(e) => {
+                    eprintln!("Failed to import snapshot: {}", &e);
+                    process::exit(1);
+                }
+            }
+            return;
+        }
         "key-for-seed" => {
             let seed = {
                 let config_path: Option<String> = args.opt_value_from_str("--config").unwrap();
@@ -188,6 +312,36 @@ key-for-seed\tOutput the associated secret key for a burnchain signer created wi
 \t\tCan be passed a config file for the seed via the `--config=<file>` option *or* by supplying the hex seed on
 \t\tthe command line directly.
 
+replay-events\tRe-send the events of already-processed blocks to an event observer, e.g. a newly attached indexer.
+\t\tThe node should not be running while events are replayed.
+\t\tArguments:
+\t\t  --config: path of the node's config.
+\t\t  --observer: the observer's endpoint (host:port), or file:<path> to write the events to a file.
+\t\t    If it is one of the config's observers, its events_keys apply.
+\t\t  --from-height: optional, the Stacks block height to replay from (defaults to the first block).
+\t\t  --events-keys: optional, comma-separated events_keys to use instead (defaults to \"*\").
+\t\tExample:
+\t\t  stacks-node replay-events --config=/path/to/config.toml --observer=localhost:3700 --from-height=100
+
+export-snapshot\tWrite the chain state as of a block to a snapshot file, for another node to start from.
+\t\tThe node should not be running while the snapshot is exported.
+\t\tArguments:
+\t\t  --config: path of the node's config.
+\t\t  --output: path of the snapshot file to write.
+\t\t  --block: optional, the index block hash of the block to export (defaults to the highest processed block).
+\t\t    It must be the highest processed block.
+\t\tExample:
+\t\t  stacks-node export-snapshot --config=/path/to/config.toml --output=/path/to/snapshot.bin
+
+import-snapshot\tRestore the chain state from a snapshot file, after checking it against the block commit on the L1.
+\t\tThe node must have synced the L1 past that commit, and should not be running while the snapshot is imported.
+\t\tArguments:
+\t\t  --config: path of the node's config.
+\t\t  --snapshot: path of the snapshot file.
+\t\t  --overwrite: optional, replace the node's existing chain state.
+\t\tExample:
+\t\t  stacks-node import-snapshot --config=/path/to/config.toml --snapshot=/path/to/snapshot.bin --overwrite
+
 help\t\tDisplay this help.
 
 OPTIONAL ARGUMENTS:
@@ -1,6 +1,11 @@
 #![allow(unused_variables)]
 
-pub use stacks::monitoring::{increment_errors_emitted_counter, increment_warning_emitted_counter};
+pub use stacks::monitoring::{
+    increment_errors_emitted_counter, increment_event_observer_dropped_counter,
+    increment_l1_commits_counter, increment_l1_observer_blocks_received,
+    increment_warning_emitted_counter, observe_block_proposal_latency,
+    set_event_observer_delivery_lag, set_event_observer_queue_depth,
+};
 
 #[cfg(feature = "monitoring_prom")]
 mod prometheus;
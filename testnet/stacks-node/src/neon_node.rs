@@ -3,13 +3,18 @@ use std::collections::{BTreeMap, HashMap};
 use std::collections::{HashSet, VecDeque};
 use std::convert::TryFrom;
 use std::default::Default;
+use std::mem;
 use std::net::SocketAddr;
 use std::sync::mpsc::{sync_channel, Receiver, SyncSender, TrySendError};
 use std::sync::{atomic::Ordering, Arc, Mutex};
 use std::time::Duration;
 use std::{thread, thread::JoinHandle};
 
+use crate::burnchains::commitment::{ProposalSignatures, P2P_BLOCK_PROPOSAL_TIMEOUT};
 use crate::burnchains::BurnchainController;
+use crate::burnchains::Error as BurnchainControllerError;
+use crate::config::CommitStrategy;
+use crate::health::{record_commit_submitted, NodeHealth};
 use stacks::burnchains::BurnchainParameters;
 use stacks::chainstate::burn::db::sortdb::SortitionDB;
 use stacks::chainstate::burn::BlockSnapshot;
@@ -569,6 +574,7 @@ fn spawn_peer(
     relay_channel: SyncSender<RelayerDirective>,
     attachments_rx: Receiver<HashSet<AttachmentInstance>>,
     unconfirmed_txs: Arc<Mutex<UnconfirmedTxMap>>,
+    proposal_signatures: ProposalSignatures,
 ) -> Result<JoinHandle<()>, NetError> {
     let config = runloop.config().clone();
     let mut sync_comms = runloop.get_pox_sync_comms();
@@ -591,6 +597,7 @@ fn spawn_peer(
         Some(config.node.get_marf_opts()),
     )
     .map_err(|e| NetError::ChainstateError(e.to_string()))?;
+    chainstate.txindex |= config.node.txindex;
 
     // buffer up blocks to store without stalling the p2p thread
     let mut results_with_data = VecDeque::new();
@@ -615,6 +622,7 @@ fn spawn_peer(
                 metric,
             )
             .expect("Database failure opening mempool");
+            mem_pool.set_replace_policy(config.node.mempool_replace_policy.clone());
 
             let cost_estimator = config
                 .make_cost_estimator()
@@ -623,14 +631,18 @@ fn spawn_peer(
                 .make_cost_metric()
                 .unwrap_or_else(|| Box::new(UnitMetric));
 
+            let node_health = NodeHealth::new(&config);
+
             let handler_args = RPCHandlerArgs {
                 exit_at_block_height: exit_at_block_height.as_ref(),
                 genesis_chainstate_hash: Sha256Sum::from_hex(stx_genesis::GENESIS_CHAINSTATE_HASH)
                     .unwrap(),
                 event_observer: Some(&event_dispatcher),
+                event_stream: event_dispatcher.event_stream_provider(),
                 cost_estimator: Some(cost_estimator.as_ref()),
                 cost_metric: Some(metric.as_ref()),
                 fee_estimator: fee_estimator.as_ref().map(|x| x.as_ref()),
+                health: Some(&node_health),
                 ..RPCHandlerArgs::default()
             };
 
@@ -680,7 +692,14 @@ fn spawn_peer(
                     &handler_args,
                     &mut expected_attachments,
                 ) {
-                    Ok(network_result) => {
+                    Ok(mut network_result) => {
+                        if !network_result.block_proposal_signatures.is_empty() {
+                            // hand replies to our block proposals straight to the miner, which
+                            // is waiting on them in the relayer thread
+                            proposal_signatures
+                                .record(mem::take(&mut network_result.block_proposal_signatures));
+                        }
+
                         if num_p2p_state_machine_passes < network_result.num_state_machine_passes {
                             // p2p state-machine did a full pass. Notify anyone listening.
                             sync_comms.notify_p2p_state_pass();
@@ -808,6 +827,7 @@ fn spawn_miner_relayer(
     last_sortition: Arc<Mutex<Option<BlockSnapshot>>>,
     coord_comms: CoordinatorChannels,
     unconfirmed_txs: Arc<Mutex<UnconfirmedTxMap>>,
+    proposal_signatures: ProposalSignatures,
 ) -> Result<JoinHandle<()>, NetError> {
     let config = runloop.config().clone();
     let event_dispatcher = runloop.get_event_dispatcher();
@@ -855,6 +875,7 @@ fn spawn_miner_relayer(
 
         let mut mem_pool = MemPoolDB::open(is_mainnet, chain_id, &stacks_chainstate_path, cost_estimator, metric)
             .expect("Database failure opening mempool");
+        mem_pool.set_replace_policy(config.node.mempool_replace_policy.clone());
 
         while let Ok(mut directive) = relay_channel.recv() {
             match directive {
@@ -1131,6 +1152,8 @@ fn spawn_miner_relayer(
                         &mut keychain,
                         &mut *burnchain_controller,
                         &event_dispatcher,
+                        &mut relayer,
+                        &proposal_signatures,
                     );
                     if let Some((last_mined_block, microblock_privkey)) = last_mined_block_opt {
                         microblock_miner_state = None;
@@ -1304,7 +1327,7 @@ impl StacksNode {
         let mut peerdb = PeerDB::connect(
             &config.get_peer_db_file_path(),
             true,
-            config.node.chain_id,
+            config.node.p2p_network_id(),
             config.burnchain.network_id,
             Some(node_privkey),
             config.connection_options.private_key_lifetime.clone(),
@@ -1342,6 +1365,21 @@ impl StacksNode {
             tx.commit().unwrap();
         }
 
+        if config.connection_options.permissioned {
+            info!(
+                "Permissioned mode: will only talk to allowlisted peers";
+                "configured" => config.connection_options.peer_allowlist.len()
+            );
+        }
+
+        {
+            let mut tx = peerdb.tx_begin().unwrap();
+            for public_key in config.connection_options.peer_allowlist.iter() {
+                PeerDB::add_allowlisted_peer(&mut tx, public_key).unwrap();
+            }
+            tx.commit().unwrap();
+        }
+
         if !config.node.deny_nodes.is_empty() {
             warn!("Will ignore nodes {:?}", &config.node.deny_nodes);
         }
@@ -1425,6 +1463,7 @@ impl StacksNode {
 
         let relayer = Relayer::from_p2p(&mut p2p_net);
         let shared_unconfirmed_txs = Arc::new(Mutex::new(UnconfirmedTxMap::new()));
+        let proposal_signatures = ProposalSignatures::default();
 
         let relayer_thread_handle = spawn_miner_relayer(
             runloop,
@@ -1435,6 +1474,7 @@ impl StacksNode {
             last_sortition.clone(),
             coord_comms,
             shared_unconfirmed_txs.clone(),
+            proposal_signatures.clone(),
         )
         .expect("Failed to initialize mine/relay thread");
 
@@ -1447,6 +1487,7 @@ impl StacksNode {
             relay_send.clone(),
             attachments_rx,
             shared_unconfirmed_txs,
+            proposal_signatures,
         )
         .expect("Failed to initialize p2p thread");
 
@@ -1621,6 +1662,8 @@ impl StacksNode {
         keychain: &mut Keychain,
         burnchain_controller: &mut (dyn BurnchainController + Send),
         event_dispatcher: &EventDispatcher,
+        relayer: &mut Relayer,
+        proposal_signatures: &ProposalSignatures,
     ) -> Option<(AssembledAnchorBlock, Secp256k1PrivateKey)> {
         let MiningTenureInformation {
             mut stacks_parent_header,
@@ -1793,9 +1836,36 @@ impl StacksNode {
                 }
             };
 
-            (0..required_signatures)
+            // participants with a known P2P key are asked over the P2P network, the rest over RPC
+            let participants = match config.burnchain.commit_strategy {
+                CommitStrategy::MultiMiner {
+                    ref other_participants,
+                    ..
+                } => other_participants.clone(),
+                _ => vec![],
+            };
+            let proposal_hash = proposal.message_hash();
+            if participants
+                .iter()
+                .any(|participant| participant.p2p_public_key.is_some())
+            {
+                proposal_signatures.expect(&proposal_hash);
+                if let Err(e) = relayer.propose_block(proposal.clone()) {
+                    warn!("Failed to send block proposal to federation peers"; "error" => ?e);
+                }
+            }
+
+            let signatures = (0..required_signatures)
                 .filter_map(|participant_index| {
-                    match burnchain_controller.propose_block(participant_index, &proposal) {
+                    let result = match participants.get(participant_index as usize) {
+                        Some(participant) if participant.p2p_public_key.is_some() => {
+                            proposal_signatures
+                                .wait_for(&proposal_hash, participant, P2P_BLOCK_PROPOSAL_TIMEOUT)
+                                .map_err(BurnchainControllerError::BadCommitment)
+                        }
+                        _ => burnchain_controller.propose_block(participant_index, &proposal),
+                    };
+                    match result {
                         Ok(signature) => Some(signature),
                         Err(rejection) => {
                             warn!("Failed to obtain approval"; "error" => %rejection);
@@ -1803,7 +1873,9 @@ impl StacksNode {
                         }
                     }
                 })
-                .collect()
+                .collect();
+            proposal_signatures.forget(&proposal_hash);
+            signatures
         } else {
             vec![]
         };
@@ -1879,6 +1951,7 @@ impl StacksNode {
         match res {
             Ok(x) => {
                 info!("Submitted miner commitment L1 transaction"; "txid" => %x);
+                record_commit_submitted();
             }
             Err(e) => {
                 if !config.node.mock_mining {
@@ -11,6 +11,9 @@ use tokio::task::JoinError;
 use warp;
 use warp::Filter;
 
+use crate::health::record_l1_block_received;
+use crate::monitoring::increment_l1_observer_blocks_received;
+
 /// Adds in `channel` to downstream functions.
 fn with_db(
     channel: Arc<dyn BurnchainChannel>,
@@ -26,6 +29,8 @@ async fn handle_new_block(
     let parsed_block: NewBlock =
         serde_json::from_str(&block.to_string()).expect("Failed to parse events JSON");
     info!("handle_new_block receives new block {:?}", &parsed_block);
+    increment_l1_observer_blocks_received(parsed_block.block_height, parsed_block.events.len());
+    record_l1_block_received();
     match channel.push_block(parsed_block) {
         Ok(_) => {}
         // TODO: It might be possible to return an error from this method for more graceful
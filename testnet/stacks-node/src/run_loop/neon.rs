@@ -157,8 +157,15 @@ impl RunLoop {
         let should_keep_running = Arc::new(AtomicBool::new(true));
 
         let mut event_dispatcher = EventDispatcher::new();
+        let outbox_dir = config.get_event_outbox_path();
         for observer in config.events_observers.iter() {
-            event_dispatcher.register_observer(observer);
+            event_dispatcher.register_observer(observer, &outbox_dir);
+        }
+        if config.node.max_event_stream_subscribers > 0 {
+            event_dispatcher.enable_event_streams(
+                config.node.max_event_stream_subscribers as usize,
+                config.node.event_stream_buffer_size as usize,
+            );
         }
 
         Self {
@@ -391,9 +398,10 @@ impl RunLoop {
             get_bulk_initial_balances: None,
             get_bulk_initial_namespaces: None,
             get_bulk_initial_names: None,
+            genesis_manifest: self.config.node.genesis_manifest.clone(),
         };
 
-        let (chain_state_db, receipts) = StacksChainState::open_and_exec(
+        let (mut chain_state_db, receipts) = StacksChainState::open_and_exec(
             self.config.is_mainnet(),
             self.config.node.chain_id,
             &self.config.get_chainstate_path_str(),
@@ -401,6 +409,8 @@ impl RunLoop {
             Some(self.config.node.get_marf_opts()),
         )
         .unwrap();
+        chain_state_db.txindex |= self.config.node.txindex;
+        chain_state_db.prune_depth = self.config.node.prune_depth;
         self.event_dispatcher.dispatch_boot_receipts(receipts);
 
         // NOTE: re-instantiate AtlasConfig so we don't have to keep the genesis attachments around
@@ -218,13 +218,16 @@ fn l1_multiparty_2_of_2_integration_test() {
         other_participants: vec![MultiMinerParticipant {
             rpc_server: l2_rpc_origin.clone(),
             public_key: [0; 33],
+            p2p_public_key: None,
         }],
         leader: false,
     };
 
     follower_config.connection_options.subnet_signing_contract = Some(multi_party_contract.clone());
     follower_config.connection_options.allowed_block_proposers =
-        vec![Secp256k1PublicKey::from_private(&MOCKNET_PRIVATE_KEY_2)];
+        vec![Secp256k1PublicKey::from_private(&MOCKNET_PRIVATE_KEY_2).to_bytes_compressed()]
+            .into_iter()
+            .collect();
 
     follower_config.add_bootstrap_node(
         "024d4b6cd1361032ca9bd2aeb9d900aa4d45d9ead80ac9423374c451a7254d0766@127.0.0.1:30444",
@@ -238,6 +241,7 @@ fn l1_multiparty_2_of_2_integration_test() {
         other_participants: vec![MultiMinerParticipant {
             rpc_server: follower_rpc_origin.clone(),
             public_key: [0; 33],
+            p2p_public_key: None,
         }],
         leader: true,
     };
@@ -490,6 +490,7 @@ fn l1_deposit_and_withdraw_asset_integration_test() {
     config.events_observers.push(EventObserverConfig {
         endpoint: format!("localhost:{}", test_observer::EVENT_OBSERVER_PORT),
         events_keys: vec![EventKeyType::AnyEvent],
+        ..EventObserverConfig::default()
     });
 
     test_observer::spawn();
@@ -1379,7 +1380,8 @@ fn l1_deposit_and_withdraw_stx_integration_test() {
 
     config.events_observers.push(EventObserverConfig {
         endpoint: format!("localhost:{}", test_observer::EVENT_OBSERVER_PORT),
-        events_keys: vec![EventKeyType::AnyEvent],
+        events_keys: vec![EventKeyType::AnyEvent, EventKeyType::WithdrawalEvent],
+        ..EventObserverConfig::default()
     });
 
     test_observer::spawn();
@@ -1590,6 +1592,43 @@ fn l1_deposit_and_withdraw_stx_integration_test() {
         withdrawal_amount,
     );
 
+    // The withdrawal observer should have received the same proof as the RPC endpoint returns
+    let observed_withdrawals: Vec<_> = test_observer::get_withdrawals()
+        .into_iter()
+        .flat_map(|block| {
+            block
+                .get("withdrawals")
+                .unwrap()
+                .as_array()
+                .unwrap()
+                .clone()
+        })
+        .collect();
+    assert_eq!(observed_withdrawals.len(), 1);
+    let observed_withdrawal = &observed_withdrawals[0];
+    assert_eq!(observed_withdrawal.get("type").unwrap(), "stx");
+    assert_eq!(observed_withdrawal.get("withdrawal_id").unwrap(), 0u64);
+    assert_eq!(
+        observed_withdrawal.get("block_height").unwrap(),
+        withdrawal_height
+    );
+    assert_eq!(
+        observed_withdrawal.get("recipient").unwrap(),
+        &user_addr.to_string()
+    );
+    assert_eq!(observed_withdrawal.get("amount").unwrap(), "1");
+    let observed_hex = |field: &str| {
+        Value::try_deserialize_hex_untyped(
+            observed_withdrawal.get(field).unwrap().as_str().unwrap(),
+        )
+        .unwrap()
+    };
+    assert_eq!(
+        observed_hex("withdrawal_leaf_hash"),
+        withdrawal_entry.leaf_hash
+    );
+    assert_eq!(observed_hex("sibling_hashes"), withdrawal_entry.siblings);
+
     // Check that the user does not own any additional STX anymore on the subnet now
     let account = get_account(&l2_rpc_origin, &user_addr);
     assert_eq!(
@@ -1763,6 +1802,7 @@ fn l2_simple_contract_calls() {
     config.events_observers.push(EventObserverConfig {
         endpoint: format!("localhost:{}", test_observer::EVENT_OBSERVER_PORT),
         events_keys: vec![EventKeyType::AnyEvent],
+        ..EventObserverConfig::default()
     });
 
     test_observer::spawn();
@@ -1888,6 +1928,7 @@ fn nft_deposit_and_withdraw_integration_test() {
     config.events_observers.push(EventObserverConfig {
         endpoint: format!("localhost:{}", test_observer::EVENT_OBSERVER_PORT),
         events_keys: vec![EventKeyType::AnyEvent],
+        ..EventObserverConfig::default()
     });
 
     test_observer::spawn();
@@ -2688,6 +2729,7 @@ fn nft_deposit_failure_and_refund_integration_test() {
     config.events_observers.push(EventObserverConfig {
         endpoint: format!("localhost:{}", test_observer::EVENT_OBSERVER_PORT),
         events_keys: vec![EventKeyType::AnyEvent],
+        ..EventObserverConfig::default()
     });
 
     test_observer::spawn();
@@ -3189,6 +3231,7 @@ fn ft_deposit_and_withdraw_integration_test() {
     config.events_observers.push(EventObserverConfig {
         endpoint: format!("localhost:{}", test_observer::EVENT_OBSERVER_PORT),
         events_keys: vec![EventKeyType::AnyEvent],
+        ..EventObserverConfig::default()
     });
 
     test_observer::spawn();
@@ -3777,6 +3820,7 @@ fn ft_deposit_failure_and_refund_integration_test() {
     config.events_observers.push(EventObserverConfig {
         endpoint: format!("localhost:{}", test_observer::EVENT_OBSERVER_PORT),
         events_keys: vec![EventKeyType::AnyEvent],
+        ..EventObserverConfig::default()
     });
 
     test_observer::spawn();
@@ -66,6 +66,7 @@ fn withdraw_unregistered_asset() {
     config.events_observers.push(EventObserverConfig {
         endpoint: format!("localhost:{}", test_observer::EVENT_OBSERVER_PORT),
         events_keys: vec![EventKeyType::AnyEvent],
+        ..EventObserverConfig::default()
     });
 
     test_observer::spawn();
@@ -89,6 +89,7 @@ pub mod test_observer {
         pub static ref MEMTXS: Mutex<Vec<String>> = Mutex::new(Vec::new());
         pub static ref MEMTXS_DROPPED: Mutex<Vec<(String, String)>> = Mutex::new(Vec::new());
         pub static ref ATTACHMENTS: Mutex<Vec<serde_json::Value>> = Mutex::new(Vec::new());
+        pub static ref NEW_WITHDRAWALS: Mutex<Vec<serde_json::Value>> = Mutex::new(Vec::new());
     }
 
     async fn handle_burn_block(
@@ -201,6 +202,14 @@ pub mod test_observer {
         Ok(warp::http::StatusCode::OK)
     }
 
+    async fn handle_withdrawals(
+        withdrawals: serde_json::Value,
+    ) -> Result<impl warp::Reply, Infallible> {
+        let mut new_withdrawals = NEW_WITHDRAWALS.lock().unwrap();
+        new_withdrawals.push(withdrawals);
+        Ok(warp::http::StatusCode::OK)
+    }
+
     pub fn get_memtxs() -> Vec<String> {
         MEMTXS.lock().unwrap().clone()
     }
@@ -225,6 +234,10 @@ pub mod test_observer {
         ATTACHMENTS.lock().unwrap().clone()
     }
 
+    pub fn get_withdrawals() -> Vec<serde_json::Value> {
+        NEW_WITHDRAWALS.lock().unwrap().clone()
+    }
+
     pub fn get_mined_blocks() -> Vec<MinedBlockEvent> {
         MINED_BLOCKS.lock().unwrap().clone()
     }
@@ -267,6 +280,10 @@ pub mod test_observer {
             .and(warp::post())
             .and(warp::body::json())
             .and_then(handle_mined_microblock);
+        let new_withdrawals = warp::path!("new_withdrawals")
+            .and(warp::post())
+            .and(warp::body::json())
+            .and_then(handle_withdrawals);
 
         info!("Spawning warp server");
         warp::serve(
@@ -277,7 +294,8 @@ pub mod test_observer {
                 .or(new_attachments)
                 .or(new_microblocks)
                 .or(mined_blocks)
-                .or(mined_microblocks),
+                .or(mined_microblocks)
+                .or(new_withdrawals),
         )
         .run(([127, 0, 0, 1], EVENT_OBSERVER_PORT))
         .await
@@ -299,6 +317,7 @@ pub mod test_observer {
         MEMTXS_DROPPED.lock().unwrap().clear();
         MINED_BLOCKS.lock().unwrap().clear();
         NEW_MICROBLOCKS.lock().unwrap().clear();
+        NEW_WITHDRAWALS.lock().unwrap().clear();
     }
 }
 
@@ -1165,6 +1184,7 @@ fn transactions_in_block_and_microblock() {
     conf.events_observers.push(EventObserverConfig {
         endpoint: format!("localhost:{}", test_observer::EVENT_OBSERVER_PORT),
         events_keys: vec![EventKeyType::AnyEvent],
+        ..EventObserverConfig::default()
     });
 
     info!(
@@ -1439,6 +1459,7 @@ fn transactions_microblocks_then_block() {
     conf.events_observers.push(EventObserverConfig {
         endpoint: format!("localhost:{}", test_observer::EVENT_OBSERVER_PORT),
         events_keys: vec![EventKeyType::AnyEvent],
+        ..EventObserverConfig::default()
     });
 
     test_observer::spawn();